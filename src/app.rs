@@ -0,0 +1,2372 @@
+//! Top-level application state machine
+//!
+//! The desktop game is always in exactly one `AppState`. Each state handles its
+//! own input (`handle_event`), advances its own logic (`update`) and draws its
+//! own screen (`draw`); `App` owns everything that outlives a state (window,
+//! audio, controllers, menu, network connections, recordings) and switches
+//! between states with `enter`.
+//!
+//! ```text
+//! Menu -> Playing <-> Paused <-> Settings
+//!           |  ^         |
+//!           |  |         +-> ConfirmQuit -> Menu (or exit)
+//!           |  +-- Dying, LevelComplete, Cutscene (timed, then back to Playing;
+//!           |      a campaign stage with text shows its card after LevelComplete)
+//!           +-> GameOver -> Menu (or, after winning a campaign, Playing new game plus)
+//!           +-> Desynced -> Playing (online, once the host resyncs)
+//! ```
+//!
+//! Behind the main menu an AI-only game (`demo::AttractDemo`) plays, dimmed,
+//! on the maze last played; it is restarted each time the menu opens.
+//!
+//! Escape pauses a run (online, where pausing is impossible, it leaves straight
+//! away) and quits from the menu. Leaving a run, or closing the window during
+//! one, asks first and offers to autosave it.
+//! Keys that work everywhere (debug overlay, clips, fullscreen, controllers,
+//! window resizes) are handled by `App` before the state sees the event.
+
+use sdl2::controller::Button;
+use sdl2::event::{Event, WindowEvent};
+use sdl2::keyboard::{KeyboardState, Scancode};
+use sdl2::mouse::MouseButton;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::EventPump;
+use std::net::SocketAddrV4;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use crate::audio::{AudioEvent, AudioManager};
+use crate::campaign::{Campaign, load_campaigns};
+use crate::capture::ClipRecorder;
+use crate::cli::CliOptions;
+use crate::constants::{
+    VIEW_H, FPS, GRID_H, MAZE_1, MAZE_2, MAX_TIME_STEP, FAST_FORWARD_SPEED, SLOW_MOTION_SPEED, EMOTE_TICKS,
+    MENU_FPS, PAUSED_FPS, MINIMIZED_FPS,
+    DEATH_FREEZE_TICKS, LEVEL_COMPLETE_TICKS, STAGE_CARD_TICKS, DEMO_DIM_ALPHA, STAGE_CARD_DIM_ALPHA, STICK_DEADZONE
+};
+use crate::debug::{DebugStats, allocations, draw_debug_overlay, draw_ghost_markers};
+use crate::demo::AttractDemo;
+use crate::emote::Emote;
+use crate::events::EventBus;
+use crate::hooks::{GameHook, Hooks};
+use crate::font::{GLYPH_H, Align, draw_lines, wrap_text};
+use crate::game::{Game, GameEvent};
+use crate::game_config::{GameConfig, GameMode, GhostPreset, PlayerRole};
+use crate::input::{Gamepads, EMOTE_BUTTONS};
+use crate::touch::{TouchButton, TouchControls, TouchLayout};
+use crate::speech::{Speaker, SystemSpeech};
+use crate::one_button::OneButton;
+use crate::maze::{floor_rows, load_maze_file, maze_hash, set_current_maze};
+use crate::menu::{Menu, MenuAction, MenuState};
+use crate::pause_menu::{PauseMenu, PauseAction, QuitConfirm, QuitChoice};
+use crate::profile::Span;
+use crate::profile_file::{ProfileFile, ImportMode, find_import, mark_imported};
+use crate::practice::Practice;
+use crate::race::{Race, viewports};
+use crate::progress::Progress;
+use crate::rating::{Ratings, VersusMatch};
+use crate::stats::{History, RunStats};
+use crate::unlocks::Unlock;
+use crate::skins::{load_skins, SkinChoices};
+use crate::hot_reload::{AssetKind, AssetWatcher};
+use crate::level_table::table_hash;
+use crate::net::{
+    NetHost, NetLobby, NetSession, NetRole, Exchange, SessionSetup, DEFAULT_PORT,
+    encode_join_code, local_ipv4
+};
+use crate::render::{
+    RenderCache, SdlRenderer, dim_screen, draw_emote, draw_scaled, draw_exit_marker, draw_touch_controls, draw_net_quality, draw_practice_status, draw_game_speed, pause_menu_top
+};
+use crate::renderer::Renderer;
+use crate::rng::clock_seed;
+use crate::savegame::{
+    can_save, autosave_exists, save_autosave, load_autosave, delete_autosave,
+    write_game, read_game, save_desync_dump
+};
+use crate::settings::{Settings, EMOTE_KEYS};
+use crate::state_dump::{load_state_dump, save_state_dump};
+use crate::tas::{TasHeader, TasRecorder, TasPlayback};
+use crate::telemetry::SessionTelemetry;
+use crate::ui;
+use crate::video::{VideoSettings, FrameLimiter, build_canvas};
+
+/// A maze loaded with `--maze`
+pub type CustomMaze = Option<&'static [&'static str; GRID_H as usize]>;
+
+/// Widest a campaign stage card's text gets in pixels
+const STAGE_CARD_TEXT_WIDTH: i32 = 440;
+
+/// What the application is doing right now
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AppState {
+    /// Main menu, settings and online lobbies (no game running)
+    Menu,
+    /// A game is running
+    Playing,
+    /// The game is frozen under the pause menu until resumed (never online, the peer keeps running)
+    Paused,
+    /// The settings pages, opened from the pause menu (the game stays paused)
+    Settings,
+    /// Asking before leaving a run, with the game paused; `exit` closes the
+    /// game instead of going back to the menu
+    ConfirmQuit { exit: bool },
+    /// A life was just lost; `timer` counts down the frozen ticks before play resumes
+    Dying { timer: i32 },
+    /// A maze was just cleared; `timer` counts down the frozen ticks before the next level
+    LevelComplete { timer: i32 },
+    /// The run is over; the final board stays up until the player goes back to the menu
+    GameOver,
+    /// The online games differed after `tick`: both are stopped and dumped
+    /// to disk, and the host may resync both from its game
+    Desynced { tick: u32 },
+    /// A non-interactive scene between levels; `timer` counts down its ticks
+    ///
+    /// Shows a campaign stage's text card before the stage starts.
+    Cutscene { timer: i32 },
+    /// Maze editor; F5 playtests the current maze and comes back here
+    ///
+    /// Nothing enters this state yet: the maze editor will.
+    Editor,
+}
+
+impl AppState {
+    /// Whether a game exists in this state (everything but the menu and the editor)
+    fn has_game(self) -> bool {
+        !matches!(self, AppState::Menu | AppState::Editor)
+    }
+}
+
+/// The desktop application: window, audio, input and the current state
+pub struct App {
+    state: AppState,
+    /// Set once the application should exit
+    quit: bool,
+    /// `--seed` for new games
+    seed: Option<u16>,
+    custom_maze: CustomMaze,
+    /// Dev mode's eye on the assets and the maze file (None unless `--dev`)
+    asset_watcher: Option<AssetWatcher>,
+    /// Campaigns found in the asset directories, in the menu's order
+    campaigns: Vec<Arc<Campaign>>,
+
+    // Layout and maze layer of the game screen (declared, and so dropped,
+    // before the canvas that owns its texture)
+    render_cache: RenderCache,
+    /// Player 2's game and viewport in a split-screen race (None for other
+    /// games; dropped before the canvas too)
+    race: Option<Race>,
+    /// The window's renderer (None only while a vsync change rebuilds it)
+    canvas: Option<Canvas<Window>>,
+    canvas_vsync: bool,
+    frame_limiter: FrameLimiter,
+    /// The window is minimized: nothing is drawn
+    minimized: bool,
+    /// When the last frame started, for the idle frame rate
+    last_frame: Instant,
+
+    /// F3 debug overlay
+    show_debug: bool,
+    debug_stats: DebugStats,
+    /// F8 keeps the last few seconds of gameplay for F12 to save as a GIF
+    clip_recorder: Option<ClipRecorder>,
+
+    /// Owns the audio backend (None when muted or unavailable)
+    audio_manager: Option<AudioManager>,
+    /// Menu/game music switches
+    audio_events: Option<Sender<AudioEvent>>,
+    /// The jukebox page is open and has the music
+    jukebox_open: bool,
+    /// Systems that react to gameplay events subscribe here
+    event_bus: EventBus,
+    /// Custom rules that may tweak the game after each tick (see `hooks`)
+    hooks: Hooks,
+    /// Game controllers are opened as they are plugged in
+    gamepads: Gamepads,
+    /// Player whose controller was unplugged mid-run, until the run is resumed
+    /// or a controller is given to them
+    lost_controller: Option<usize>,
+    /// On-screen D-pad and pause button for touch screens
+    touch: TouchControls,
+    /// Player 1's single action and the way it picked at the next junction
+    one_button: OneButton,
+    /// Speaks game events and menu entries when announcements are on
+    speaker: Speaker,
+    /// Menu entry last announced, to say the next one only when it changes
+    announced: Option<String>,
+
+    menu: Menu,
+    /// AI-only game playing behind the main menu (None outside the menu)
+    demo: Option<AttractDemo>,
+    /// Menu page to go back to after visiting the settings from the pause menu
+    menu_before_settings: (MenuState, usize),
+    pause_menu: PauseMenu,
+    quit_confirm: QuitConfirm,
+    game: Option<Game>,
+    /// The game as it was when the current level started (for Restart Level)
+    level_start: Option<Game>,
+    /// The game is a playtest started from the editor, which it goes back to when it ends
+    playtest: bool,
+    /// Savestate and drill aids of a practice run (None for other games)
+    practice: Option<Practice>,
+    /// Levels unlocked for level select
+    progress: Progress,
+    /// A profile file read for import, waiting for the merge-or-replace answer
+    pending_import: Option<(std::path::PathBuf, ProfileFile)>,
+    /// The players of a rated versus game (None for other games)
+    versus: Option<VersusMatch>,
+    /// Emote shown above the human ghost, and the ticks it has left
+    emote: Option<(Emote, i32)>,
+    /// How the current game is going, for the run history (None for replays)
+    run_stats: Option<RunStats>,
+    /// What is being gathered about the current game when telemetry is on
+    telemetry: Option<SessionTelemetry>,
+    /// Accumulates real time for the fixed timestep
+    time_accumulator: f64,
+    /// How fast the game runs against real time: 1 but while fast-forwarding
+    /// or in slow motion
+    game_speed: f64,
+    previous_frame_time: Instant,
+
+    net_host: Option<NetHost>,
+    net_lobby: Option<NetLobby>,
+    net_session: Option<NetSession>,
+    /// A join was chosen; it connects once the "Connecting" status has been drawn
+    pending_join: bool,
+    tas_recorder: Option<TasRecorder>,
+    tas_playback: Option<TasPlayback>,
+    /// The host's game in the save format after an online desync, to resync from
+    resync_state: Option<String>,
+}
+
+impl App {
+    /// Opens the window and audio and shows the menu (or starts `--replay` straight away)
+    ///
+    /// # Arguments
+    /// * `sdl` - Initialized SDL context
+    /// * `options` - Command-line options
+    /// * `custom_maze` - Maze loaded with `--maze`, if any
+    pub fn new(sdl: &sdl2::Sdl, options: &CliOptions, custom_maze: CustomMaze) -> Result<Self, String> {
+        let video = sdl.video()?;
+
+        // Saved settings; --fullscreen wins over the saved window mode
+        let mut settings = Settings::load();
+        if options.fullscreen {
+            settings.video.fullscreen = true;
+        }
+        let display_count = video.num_video_displays().unwrap_or(1);
+        settings.video.display_index = settings.video.display_index.clamp(0, display_count.max(1) - 1);
+
+        // Window size to fit game content with minimal borders (moved to the saved monitor below)
+        let (window_w, window_h) = settings.video.window_size();
+        let mut window_builder = video.window("Pacman - Atari 2600 Style (Rust)", window_w, window_h);
+        window_builder.position_centered().opengl().resizable();
+        if settings.video.fullscreen {
+            window_builder.fullscreen_desktop();
+        }
+        let window = window_builder.build().map_err(|e| e.to_string())?;
+        let canvas = build_canvas(window, settings.video.vsync)?;
+
+        // Initialize audio (unless started with --mute)
+        let audio_manager = if options.mute { None } else { open_audio(sdl)? };
+        let audio_events = audio_manager.as_ref().map(AudioManager::sender);
+        let mut event_bus = EventBus::new();
+        if let Some(ref sounds) = audio_events {
+            event_bus.subscribe(Box::new(sounds.clone()));
+        }
+        let speaker = Speaker::start(Box::new(SystemSpeech::default()), settings.audio.announcements);
+        event_bus.subscribe(Box::new(speaker.clone()));
+
+        let mut debug_stats = DebugStats::new();
+        if let Some(ref path) = options.profile {
+            debug_stats.profile.open_csv(path)?;
+        }
+
+        let mut menu = Menu::new();
+        menu.settings = settings;
+        menu.ratings = Ratings::load();
+        menu.history = History::load();
+        menu.skins = load_skins();
+        menu.skin_choices = SkinChoices::load();
+        menu.display_count = display_count;
+        if autosave_exists() {
+            menu.state = MenuState::ContinueSave;
+        }
+        let progress = Progress::load();
+        menu.unlocked_level = progress.highest_level;
+        let campaigns = load_campaigns();
+        menu.campaign_names = campaigns.iter().map(|campaign| campaign.name.clone()).collect();
+
+        let mut app = Self {
+            state: AppState::Menu,
+            quit: false,
+            seed: options.seed,
+            custom_maze,
+            asset_watcher: options.dev.then(|| AssetWatcher::new(options.maze.clone())),
+            campaigns,
+            render_cache: RenderCache::new(),
+            race: None,
+            canvas: Some(canvas),
+            canvas_vsync: settings.video.vsync,
+            frame_limiter: FrameLimiter::new(),
+            minimized: false,
+            last_frame: Instant::now(),
+            show_debug: false,
+            debug_stats,
+            clip_recorder: None,
+            audio_manager,
+            audio_events,
+            jukebox_open: false,
+            event_bus,
+            hooks: Hooks::new(),
+            gamepads: Gamepads::new(sdl)?,
+            lost_controller: None,
+            touch: TouchControls::new(menu.settings.controls.touch),
+            one_button: OneButton::new(menu.settings.controls.one_button),
+            speaker,
+            announced: None,
+            menu,
+            demo: Some(AttractDemo::new(clock_seed())),
+            menu_before_settings: (MenuState::GameMode, 0),
+            pause_menu: PauseMenu::new(),
+            quit_confirm: QuitConfirm::new(false),
+            game: None,
+            level_start: None,
+            playtest: false,
+            practice: None,
+            progress,
+            pending_import: None,
+            versus: None,
+            emote: None,
+            run_stats: None,
+            telemetry: None,
+            time_accumulator: 0.0,
+            game_speed: 1.0,
+            previous_frame_time: Instant::now(),
+            net_host: None,
+            net_lobby: None,
+            net_session: None,
+            pending_join: false,
+            tas_recorder: None,
+            tas_playback: None,
+            resync_state: None,
+        };
+        if let Some(ref mut canvas) = app.canvas {
+            apply_video(&app.menu.settings.video, canvas, &mut app.render_cache);
+        }
+        app.apply_unlocks();
+        app.send_volume();
+
+        // --replay: skip the menu and play the recording straight away
+        if let Some(ref path) = options.replay {
+            let playback = TasPlayback::load(path)?;
+            let game = start_replay(&playback, custom_maze)?;
+            app.tas_playback = Some(playback);
+            app.start_game(game);
+        }
+        // --load-state: start from a dumped game (a bug report, not a run of the player's)
+        if let Some(ref path) = options.load_state {
+            let game = load_state_dump(path)?;
+            app.start_game(game);
+            app.run_stats = None;
+            app.telemetry = None;
+        }
+        Ok(app)
+    }
+
+    /// Adds a gameplay hook to every game played from now on, local and online
+    ///
+    /// Both sides of an online match need the same hooks, and a recording only
+    /// plays back with the hooks it was made with.
+    pub fn add_hook(&mut self, hook: Box<dyn GameHook + Send>) {
+        self.hooks.add(hook);
+    }
+
+    /// Runs until the window is closed or the player quits from the menu
+    pub fn run(&mut self, event_pump: &mut EventPump) -> Result<(), String> {
+        while !self.quit {
+            // When nothing needs a full frame rate, sleep until input arrives or the next frame is due
+            let idle_period = self.idle_period();
+            if let Some(period) = idle_period {
+                let remaining = period.saturating_sub(self.last_frame.elapsed());
+                if !remaining.is_zero() {
+                    if let Some(event) = event_pump.wait_event_timeout(remaining.as_millis().max(1) as u32) {
+                        self.handle_event(event);
+                    }
+                }
+            }
+            self.last_frame = Instant::now();
+
+            // Process ALL events immediately - instantaneous input response
+            for event in event_pump.poll_iter() {
+                self.handle_event(event);
+            }
+            if self.quit {
+                break;
+            }
+            self.update(&event_pump.keyboard_state())?;
+            self.sync_jukebox();
+            self.reload_assets();
+            if !self.minimized {
+                self.draw()?;
+            }
+            self.announce_selection();
+
+            // Without vsync, pace frames to the chosen cap (the game itself still ticks at a fixed rate)
+            if !self.canvas_vsync && idle_period.is_none() {
+                self.frame_limiter.wait(self.menu.settings.video.fps_cap());
+            }
+        }
+        self.save_run_records();
+        Ok(())
+    }
+
+    /// Time between frames when the main loop can wait for input instead of running flat out
+    ///
+    /// # Returns
+    /// None while a game runs in sight, or online (the connection is polled every frame)
+    fn idle_period(&self) -> Option<Duration> {
+        if self.net_session.is_some() {
+            return None;
+        }
+        let period = |fps: u32| Some(Duration::from_secs_f64(1.0 / fps as f64));
+        let fps = match self.state {
+            AppState::Menu | AppState::Settings => MENU_FPS,
+            AppState::Paused | AppState::ConfirmQuit { .. } | AppState::GameOver | AppState::Editor => PAUSED_FPS,
+            // A game running minimized keeps its pace, unseen
+            _ if self.minimized => return period(self.tick_rate()),
+            _ => return None,
+        };
+        period(if self.minimized { fps.min(MINIMIZED_FPS) } else { fps })
+    }
+
+    /// Simulation ticks per second of the game being played (`FPS` in the
+    /// menu, where the attract demo plays)
+    fn tick_rate(&self) -> u32 {
+        match self.game {
+            Some(ref game) if self.state != AppState::Menu => game.config.tick_rate,
+            _ => FPS,
+        }
+    }
+
+    /// A duration given in ticks at `FPS` in ticks of the game being played
+    /// (see `GameConfig::ticks`)
+    fn game_ticks(&self, ticks: i32) -> i32 {
+        self.game.as_ref().map(|game| game.config.ticks(ticks)).unwrap_or(ticks)
+    }
+
+    /// Switches to another state
+    ///
+    /// Keeps the music, the pause flag the renderer draws and the frame clock
+    /// in step with the new state.
+    fn enter(&mut self, state: AppState) {
+        let was_menu = self.state == AppState::Menu;
+        // Coming back from the settings or the quit question keeps the pause menu where it was
+        if state == AppState::Paused && !matches!(self.state, AppState::Settings | AppState::ConfirmQuit { .. }) {
+            self.pause_menu = PauseMenu::new();
+        }
+        self.state = state;
+        let paused = matches!(state, AppState::Paused | AppState::Settings | AppState::ConfirmQuit { .. });
+        if let Some(ref mut game) = self.game {
+            game.paused = paused;
+        }
+        if let Some(ref mut race) = self.race {
+            race.rival.paused = paused;
+        }
+        if !paused {
+            self.lost_controller = None;
+        }
+        if state != AppState::Playing {
+            // The proximity tone only follows the ghosts during play
+            self.send_audio(AudioEvent::proximity(None));
+        }
+        if was_menu != (state == AppState::Menu) {
+            if let Some(ref events) = self.audio_events {
+                let _ = events.send(if state == AppState::Menu { AudioEvent::MenuOpened } else { AudioEvent::GameStarted });
+            }
+            // Don't catch up on the time spent in the menu
+            self.previous_frame_time = Instant::now();
+            self.time_accumulator = 0.0;
+
+            // A fresh demo on the maze last played, seeded from where that game ended
+            self.demo = if state == AppState::Menu {
+                let seed = self.game.as_ref().map(|game| game.rng.state()).unwrap_or_else(clock_seed);
+                Some(AttractDemo::new(seed))
+            } else {
+                None
+            };
+        }
+    }
+
+    /// Leaves the game (or editor) for the menu, ending recordings and online sessions
+    ///
+    /// A playtest goes back to the editor instead.
+    fn back_to_menu(&mut self) {
+        self.save_run_records();
+        self.game = None;
+        self.level_start = None;
+        self.tas_recorder = None;
+        self.tas_playback = None;
+        self.resync_state = None;
+        self.versus = None;
+        self.emote = None;
+        self.practice = None;
+        self.race = None;
+        if self.playtest {
+            self.playtest = false;
+            self.enter(AppState::Editor);
+            return;
+        }
+        // Leaving ends any online session
+        if self.net_session.take().is_some() {
+            self.menu.reset();
+        }
+        self.enter(AppState::Menu);
+    }
+
+    /// Starts playing a new game (unrated; local and online versus games set up their match afterwards)
+    fn start_game(&mut self, game: Game) {
+        self.versus = None;
+        self.emote = None;
+        self.race = None;
+        self.menu.unlock_notice.clear();
+        // Replays are someone else's run played again
+        self.save_run_records();
+        if self.tas_playback.is_none() {
+            self.run_stats = Some(RunStats::new(&game, self.menu.ratings.name(0)));
+            if self.menu.settings.gameplay.telemetry {
+                self.telemetry = Some(SessionTelemetry::new(&game));
+            }
+        }
+        self.level_start = Some(game.clone());
+        self.game = Some(game);
+        self.enter(AppState::Playing);
+    }
+
+    /// Adds the current game to the run history, and to the telemetry file if
+    /// telemetry is on (practice runs and playtests aren't real runs and are dropped)
+    fn save_run_records(&mut self) {
+        let run_stats = self.run_stats.take();
+        let telemetry = self.telemetry.take();
+        if self.practice.is_some() || self.playtest {
+            return;
+        }
+        if let Some(run) = run_stats.filter(|run| run.ticks > 0) {
+            let unlocked_before = self.menu.unlocks().unlocked();
+            if let Err(e) = self.menu.history.add(run) {
+                eprintln!("{}", e);
+            }
+            let earned: Vec<String> = self.menu.unlocks().unlocked().into_iter()
+                .filter(|unlock| !unlocked_before.contains(unlock))
+                .map(Unlock::name)
+                .collect();
+            if !earned.is_empty() {
+                self.menu.unlock_notice = format!("Unlocked: {}", earned.join(", "));
+            }
+            self.apply_unlocks();
+        }
+        if let Some(telemetry) = telemetry {
+            if let Err(e) = telemetry.save() {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    /// Keeps the chosen maze colors on every level, once player 1's profile has
+    /// unlocked them, and dresses both players in their profiles' skins
+    fn apply_unlocks(&mut self) {
+        let unlocks = self.menu.unlocks();
+        self.render_cache.maze_colors = self.menu.settings.video.maze_colors.filter(|&palette| unlocks.has(Unlock::Palette(palette)));
+        for player in 0..2 {
+            if let Some(skin) = self.menu.skin_of(player) {
+                self.render_cache.skins[player] = Rc::new(skin.clone());
+            }
+        }
+    }
+
+    /// Takes an imported profile into the saves and applies its settings
+    ///
+    /// # Arguments
+    /// * `path` - The profile file, marked as imported afterwards
+    /// * `profile` - Its contents
+    /// * `mode` - Merge with or replace a profile of the same name
+    fn import_profile(&mut self, path: std::path::PathBuf, profile: ProfileFile, mode: ImportMode) -> Result<(), String> {
+        let name = profile.name.clone();
+        let menu = &mut self.menu;
+        let imported = profile.apply(mode, &mut menu.settings, &mut self.progress, &mut menu.ratings, &mut menu.history, &mut menu.skin_choices)
+            .and_then(|_| mark_imported(&path));
+        menu.unlocked_level = self.progress.highest_level;
+        menu.notice = match imported {
+            Ok(()) => format!("Imported {}", name),
+            Err(e) => e,
+        };
+        // The other monitor may not exist here
+        menu.settings.video.display_index = menu.settings.video.display_index.clamp(0, menu.display_count.max(1) - 1);
+        self.send_volume();
+        self.touch.enabled = self.menu.settings.controls.touch;
+        self.one_button.enabled = self.menu.settings.controls.one_button;
+        self.speaker.set_enabled(self.menu.settings.audio.announcements);
+        self.apply_unlocks();
+        self.menu_action(MenuAction::VideoChanged)
+    }
+
+    /// Says the selected menu entry whenever it changes, so the menus can be used by ear
+    ///
+    /// Runs after drawing, since the menu learns its entries' text as it draws them.
+    fn announce_selection(&mut self) {
+        let label = match self.state {
+            AppState::Menu | AppState::Settings => self.menu.selected_label(),
+            AppState::Paused => Some(self.pause_menu.selected_label()),
+            AppState::ConfirmQuit { .. } => Some(self.quit_confirm.selected_label()),
+            _ => None,
+        };
+        if label != self.announced.as_deref() {
+            if let Some(text) = label {
+                self.speaker.say(text);
+            }
+            self.announced = label.map(str::to_string);
+        }
+    }
+
+    /// Hands the music to the jukebox while its page is open, and back to the game when it closes
+    fn sync_jukebox(&mut self) {
+        let open = matches!(self.state, AppState::Menu | AppState::Settings) && self.menu.state == MenuState::Jukebox;
+        if open != self.jukebox_open {
+            self.jukebox_open = open;
+            self.send_audio(if open { AudioEvent::JukeboxOpened } else { AudioEvent::JukeboxClosed });
+        }
+    }
+
+    /// Dev mode: reloads whatever assets changed since the last look
+    fn reload_assets(&mut self) {
+        let Some(ref mut watcher) = self.asset_watcher else { return };
+        let changed = watcher.poll();
+        for kind in changed {
+            match kind {
+                AssetKind::Audio => self.send_audio(AudioEvent::ReloadAssets),
+                AssetKind::Skins => {
+                    self.menu.skins = load_skins();
+                    self.apply_unlocks();
+                }
+                AssetKind::Campaigns => {
+                    self.campaigns = load_campaigns();
+                    self.menu.campaign_names = self.campaigns.iter().map(|campaign| campaign.name.clone()).collect();
+                }
+                AssetKind::Maze => self.reload_maze(),
+            }
+        }
+    }
+
+    /// Dev mode: loads the `--maze` file again, and restarts the level of a
+    /// game being played on it if the layout changed
+    fn reload_maze(&mut self) {
+        let (Some(path), Some(old)) = (self.asset_watcher.as_ref().and_then(|watcher| watcher.maze()), self.custom_maze) else {
+            return;
+        };
+        let maze = match load_maze_file(path) {
+            Ok(maze) => maze,
+            Err(e) => {
+                eprintln!("Keeping the maze as it was: {}", e);
+                return;
+            }
+        };
+        if maze == old {
+            return;
+        }
+        self.custom_maze = Some(maze);
+
+        // Online games and campaigns play other mazes
+        if !std::ptr::eq(floor_rows(0).as_ptr(), old.as_ptr()) {
+            return;
+        }
+        set_current_maze(maze);
+        if let Some(ref mut game) = self.game {
+            game.restart_level();
+            self.level_start = Some(game.clone());
+        }
+        if let Some(ref mut race) = self.race {
+            race.rival.restart_level();
+            race.rival_start = race.rival.clone();
+        }
+    }
+
+    /// Sends an event to the mixer, if there is audio
+    fn send_audio(&self, event: AudioEvent) {
+        if let Some(ref events) = self.audio_events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Tells the mixer how near the nearest ghost is, if the proximity tone is on
+    fn send_proximity(&self) {
+        if self.menu.settings.audio.proximity_tone {
+            self.send_audio(AudioEvent::proximity(self.game.as_ref().and_then(Game::nearest_ghost)));
+        }
+    }
+
+    /// Passes the volume settings to the mixer
+    fn send_volume(&self) {
+        if let Some(ref events) = self.audio_events {
+            let audio = self.menu.settings.audio;
+            let _ = events.send(AudioEvent::SetVolume { music: audio.music_volume, effects: audio.effects_volume });
+        }
+    }
+
+    /// Writes the settings to the config file (a failure only costs persistence)
+    fn save_settings(&self) {
+        if let Err(e) = self.menu.settings.save() {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Online and recorded games only take input through the tick, so it can be exchanged or replayed
+    fn ticks_only(&self) -> bool {
+        self.net_session.is_some() || self.tas_recorder.is_some() || self.tas_playback.is_some()
+    }
+
+    // ========================================================================
+    // Input
+    // ========================================================================
+
+    /// Handles one SDL event: global keys first, then the current state's input
+    pub fn handle_event(&mut self, event: Event) {
+        // Keys and controllers put the on-screen controls away
+        if is_physical_input(&event) {
+            self.touch.physical_input();
+        }
+        if self.handle_global_event(&event) {
+            return;
+        }
+        match self.state {
+            AppState::Menu => self.menu_event(event),
+            AppState::Playing => self.playing_event(event),
+            AppState::Paused => self.paused_event(event),
+            AppState::Settings => self.settings_event(event),
+            AppState::ConfirmQuit { exit } => self.confirm_quit_event(event, exit),
+            AppState::Dying { .. } | AppState::LevelComplete { .. } => {
+                if is_escape(&event) {
+                    self.escape_from_run();
+                }
+            }
+            AppState::Editor => {
+                if is_escape(&event) {
+                    self.back_to_menu();
+                } else if let Event::KeyDown { scancode: Some(Scancode::F5), .. } = event {
+                    self.start_playtest();
+                }
+            }
+            AppState::GameOver => {
+                if is_escape(&event) || is_confirm(&event) {
+                    self.back_to_menu();
+                } else if let Event::KeyDown { scancode: Some(Scancode::N), .. } = event {
+                    self.start_ng_plus();
+                }
+            }
+            AppState::Desynced { .. } => {
+                let is_host = self.net_session.as_ref().map(|session| session.role == NetRole::Host).unwrap_or(false);
+                if is_escape(&event) {
+                    self.back_to_menu();
+                } else if is_confirm(&event) && is_host {
+                    self.resync();
+                }
+            }
+            AppState::Cutscene { .. } => {
+                if is_escape(&event) {
+                    self.escape_from_run();
+                } else if is_confirm(&event) {
+                    // Skip the scene
+                    self.enter(AppState::Playing);
+                }
+            }
+        }
+    }
+
+    /// Events that mean the same in every state
+    ///
+    /// # Returns
+    /// True if the event was used up
+    fn handle_global_event(&mut self, event: &Event) -> bool {
+        match *event {
+            // Window closed: ask first in the middle of a run
+            Event::Quit { .. } => {
+                let in_run = matches!(
+                    self.state,
+                    AppState::Playing | AppState::Paused | AppState::Settings |
+                    AppState::Dying { .. } | AppState::LevelComplete { .. } | AppState::Cutscene { .. }
+                );
+                if in_run && self.net_session.is_none() {
+                    self.ask_to_quit(true);
+                } else {
+                    self.quit = true;
+                }
+            }
+
+            // F3: toggle the debug overlay
+            Event::KeyDown { scancode: Some(Scancode::F3), .. } => {
+                self.show_debug = !self.show_debug;
+            }
+
+            // F4: dump the game state for a bug report
+            Event::KeyDown { scancode: Some(Scancode::F4), .. } if self.state != AppState::Menu => {
+                if let Some(ref game) = self.game {
+                    match save_state_dump(game) {
+                        Ok(path) => println!("Game state dumped to {}", path.display()),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            }
+
+            // F5-F8 in a practice run: savestates and drill aids (instead of clips for F8)
+            Event::KeyDown { scancode: Some(scancode @ (Scancode::F5 | Scancode::F6 | Scancode::F7 | Scancode::F8)), .. }
+                if self.practice.is_some() && matches!(
+                    self.state,
+                    AppState::Playing | AppState::Dying { .. } | AppState::LevelComplete { .. } | AppState::GameOver
+                ) => self.practice_key(scancode),
+
+            // F8: start or stop keeping frames for clips
+            Event::KeyDown { scancode: Some(Scancode::F8), .. } => {
+                self.clip_recorder = match self.clip_recorder {
+                    Some(_) => None,
+                    None => {
+                        let sound = self.audio_manager.as_ref().filter(|_| self.menu.settings.audio.clip_sound);
+                        Some(ClipRecorder::new(sound.map(AudioManager::tap)))
+                    }
+                };
+            }
+
+            // F12: save the last few seconds as a GIF
+            Event::KeyDown { scancode: Some(Scancode::F12), .. } => {
+                if let Some(ref clip) = self.clip_recorder {
+                    match clip.save() {
+                        Ok(path) => println!("Saving clip to {}", path.display()),
+                        Err(e) => eprintln!("{}", e),
+                    }
+                }
+            }
+
+            // F11: toggle desktop fullscreen
+            Event::KeyDown { scancode: Some(Scancode::F11), .. } => {
+                self.menu.settings.video.fullscreen = !self.menu.settings.video.fullscreen;
+                if let Some(ref mut canvas) = self.canvas {
+                    apply_video(&self.menu.settings.video, canvas, &mut self.render_cache);
+                }
+                self.save_settings();
+            }
+
+            // Controller plugged in or removed
+            Event::ControllerDeviceAdded { which, .. } => {
+                // Plugged back in: it goes to the first player without one
+                if self.gamepads.add(which).is_some_and(|player| self.lost_controller == Some(player)) {
+                    self.lost_controller = None;
+                }
+            }
+            Event::ControllerDeviceRemoved { which, .. } => {
+                if let Some(player) = self.gamepads.remove(which) {
+                    self.controller_lost(player);
+                }
+            }
+
+            // Touches on the on-screen controls (menus get them as mouse clicks from SDL)
+            Event::FingerDown { finger_id, x, y, .. } => {
+                let (layout, x, y) = self.touch_point(x, y);
+                let button = self.touch.press(finger_id, x, y, &layout);
+                if button == Some(TouchButton::Pause) && self.state == AppState::Playing {
+                    self.escape_from_run();
+                }
+            }
+            Event::FingerMotion { finger_id, x, y, .. } => {
+                let (layout, x, y) = self.touch_point(x, y);
+                self.touch.slide(finger_id, x, y, &layout);
+            }
+            Event::FingerUp { finger_id, .. } => self.touch.release(finger_id),
+
+            // Back button: move that controller to the other player
+            Event::ControllerButtonDown { which, button: Button::Back, .. } => {
+                self.gamepads.swap_player(which);
+            }
+
+            // Window resize: update render cache
+            Event::Window { win_event: WindowEvent::Resized(..) | WindowEvent::SizeChanged(..), .. } => {
+                self.render_cache.invalidate();
+            }
+            Event::Window { win_event: WindowEvent::Minimized, .. } => self.minimized = true,
+            Event::Window { win_event: WindowEvent::Restored | WindowEvent::Maximized | WindowEvent::Exposed, .. } => {
+                self.minimized = false;
+            }
+
+            _ => return false,
+        }
+        true
+    }
+
+    fn menu_event(&mut self, event: Event) {
+        match event {
+            // Escape key: exit game (or stop waiting for a key to bind)
+            Event::KeyDown { scancode: Some(Scancode::Escape), .. } if !self.menu.capturing => self.quit = true,
+
+            // F9: arm or disarm recording of the next games started from the menu
+            Event::KeyDown { scancode: Some(Scancode::F9), .. } if !self.menu.capturing => {
+                self.menu.recording = !self.menu.recording;
+            }
+
+            // F7: arm or disarm practice mode for the next games started from the menu
+            Event::KeyDown { scancode: Some(Scancode::F7), .. } if !self.menu.capturing => {
+                self.menu.practice = !self.menu.practice;
+            }
+
+            // F10: replay the most recent recording
+            Event::KeyDown { scancode: Some(Scancode::F10), .. } if !self.menu.capturing => {
+                let replay = TasPlayback::load_latest()
+                    .and_then(|playback| Ok((start_replay(&playback, self.custom_maze)?, playback)));
+                match replay {
+                    Ok((game, playback)) => {
+                        self.tas_playback = Some(playback);
+                        self.start_game(game);
+                    }
+                    Err(e) => self.menu.show_status(&e),
+                }
+            }
+
+            event => self.menu_navigation_event(event),
+        }
+    }
+
+    /// Keys shared by the main menu and the settings opened from the pause menu:
+    /// moving the selection, sliders, going back, choosing and rebinding controls
+    fn menu_navigation_event(&mut self, event: Event) {
+        // Rebinding a control: the next key is the new binding (Escape or B cancels)
+        if self.menu.capturing {
+            match event {
+                Event::KeyDown { scancode: Some(Scancode::Escape), .. } |
+                Event::ControllerButtonDown { button: Button::B, .. } |
+                Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. } => self.menu.back(),
+                Event::KeyDown { scancode: Some(scancode), .. } => {
+                    let action = self.menu.bind_key(scancode);
+                    if let Err(e) = self.menu_action(action) {
+                        eprintln!("{}", e);
+                    }
+                }
+                _ => {}
+            }
+            return;
+        }
+        
+        let action = match event {
+            // Typed text: address entry on the join screen, names on the players page
+            Event::TextInput { text, .. } => self.menu.text_input(&text),
+
+            // Arrow keys and d-pad: navigate menu
+            Event::KeyDown { scancode: Some(Scancode::Up), .. } |
+            Event::ControllerButtonDown { button: Button::DPadUp, .. } => self.menu.process_input(0, -1),
+            Event::KeyDown { scancode: Some(Scancode::Down), .. } |
+            Event::ControllerButtonDown { button: Button::DPadDown, .. } => self.menu.process_input(0, 1),
+
+            // Left and right: change the selected slider
+            Event::KeyDown { scancode: Some(Scancode::Left), .. } |
+            Event::ControllerButtonDown { button: Button::DPadLeft, .. } => self.menu.process_input(-1, 0),
+            Event::KeyDown { scancode: Some(Scancode::Right), .. } |
+            Event::ControllerButtonDown { button: Button::DPadRight, .. } => self.menu.process_input(1, 0),
+
+            // Mouse: hovering selects, left click chooses, the wheel changes sliders
+            Event::MouseMotion { x, y, .. } => {
+                self.menu.mouse_moved(x, y);
+                MenuAction::None
+            }
+            Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => self.menu.mouse_clicked(x, y),
+            Event::MouseWheel { y, .. } if y != 0 => self.menu.mouse_wheel(y),
+
+            // Backspace: delete a typed character or go back in menu
+            Event::KeyDown { scancode: Some(Scancode::Backspace), .. } => self.menu.backspace(),
+
+            // B button / right click: go back in menu
+            Event::ControllerButtonDown { button: Button::B, .. } |
+            Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. } => {
+                self.menu.back();
+                MenuAction::None
+            }
+
+            // Enter key / A button: select menu option
+            Event::KeyDown { scancode: Some(Scancode::Return), .. } |
+            Event::ControllerButtonDown { button: Button::A, .. } => self.menu.select(),
+
+            _ => MenuAction::None,
+        };
+        if let Err(e) = self.menu_action(action) {
+            eprintln!("{}", e);
+        }
+    }
+
+    /// Carries out what was chosen in the menu
+    fn menu_action(&mut self, action: MenuAction) -> Result<(), String> {
+        match action {
+            MenuAction::ContinueSave => match load_autosave() {
+                Ok(game) => {
+                    // A save is continued once; quitting again makes a new one
+                    delete_autosave();
+                    self.menu.state = MenuState::GameMode;
+                    self.menu.selected = 0;
+                    self.start_game(game);
+                }
+                Err(e) => self.menu.show_status(&e),
+            },
+            MenuAction::SelectMaze(maze_index) => self.start_local_game(maze_index, 1),
+            MenuAction::SelectLevel(maze_index, level) => self.start_local_game(maze_index, level),
+            MenuAction::SelectCampaign(campaign_index) => self.start_campaign(campaign_index),
+            MenuAction::HostOnline => {
+                // Seed from the clock so every online match plays differently
+                let seed = clock_seed();
+                let setup = SessionSetup {
+                    seed,
+                    maze_index: self.menu.lobby.maze_index,
+                    input_delay: self.menu.lobby.input_delay,
+                    host_role: self.menu.lobby.host_role,
+                    level_table: table_hash(),
+                };
+                match NetHost::bind(DEFAULT_PORT, setup) {
+                    Ok(host) => {
+                        self.menu.lobby.port = host.port();
+                        self.menu.lobby.join_code = encode_join_code(SocketAddrV4::new(local_ipv4(), host.port()));
+                        self.net_host = Some(host);
+                    }
+                    Err(e) => self.menu.show_status(&e),
+                }
+            }
+            MenuAction::LobbyChanged => {
+                // Host changed role, maze or input delay: tell the client (or remember it until one joins)
+                if let Some(ref mut lobby) = self.net_lobby {
+                    let setup = SessionSetup {
+                        host_role: self.menu.lobby.host_role,
+                        maze_index: self.menu.lobby.maze_index,
+                        input_delay: self.menu.lobby.input_delay,
+                        ..lobby.setup
+                    };
+                    if let Err(e) = lobby.update_setup(setup) {
+                        self.menu.show_status(&e);
+                    }
+                } else if let Some(ref mut host) = self.net_host {
+                    host.setup.host_role = self.menu.lobby.host_role;
+                    host.setup.maze_index = self.menu.lobby.maze_index;
+                    host.setup.input_delay = self.menu.lobby.input_delay;
+                }
+            }
+            MenuAction::VideoChanged => {
+                // Vsync belongs to the renderer: rebuild it on the same window
+                if self.menu.settings.video.vsync != self.canvas_vsync {
+                    if let Some(canvas) = self.canvas.take() {
+                        self.render_cache.drop_textures();
+                        if let Some(ref mut race) = self.race {
+                            race.rival_cache.drop_textures();
+                        }
+                        self.canvas = Some(build_canvas(canvas.into_window(), self.menu.settings.video.vsync)?);
+                        self.canvas_vsync = self.menu.settings.video.vsync;
+                    }
+                }
+                if let Some(ref mut canvas) = self.canvas {
+                    apply_video(&self.menu.settings.video, canvas, &mut self.render_cache);
+                }
+                self.save_settings();
+            }
+            MenuAction::SettingsChanged => {
+                // Volume applies straight away; bindings are read as the game is played
+                self.send_volume();
+                self.touch.enabled = self.menu.settings.controls.touch;
+                self.one_button.enabled = self.menu.settings.controls.one_button;
+                self.speaker.set_enabled(self.menu.settings.audio.announcements);
+                self.apply_unlocks();
+                self.save_settings();
+            }
+            MenuAction::SkinsChanged => {
+                self.apply_unlocks();
+                if let Err(e) = self.menu.skin_choices.save() {
+                    eprintln!("{}", e);
+                }
+            }
+            MenuAction::NamesChanged => {
+                // Unlocks belong to the profile
+                self.apply_unlocks();
+                if let Err(e) = self.menu.ratings.save() {
+                    eprintln!("{}", e);
+                }
+            }
+            MenuAction::ExportStats => {
+                self.menu.notice = match self.menu.history.export(&self.menu.ratings) {
+                    Ok(dir) => format!("Exported to {}", dir.display()),
+                    Err(e) => e,
+                };
+            }
+            MenuAction::ExportProfile => {
+                let menu = &self.menu;
+                let profile = ProfileFile::gather(
+                    menu.ratings.name(0), &menu.settings, &self.progress, &menu.ratings, &menu.history, &menu.skin_choices
+                );
+                self.menu.notice = match profile.export() {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => e,
+                };
+            }
+            MenuAction::ImportProfile => {
+                let Some(path) = find_import() else {
+                    self.menu.notice = "Put a .profile file in saves/imports to import it".to_string();
+                    return Ok(());
+                };
+                match ProfileFile::load(&path) {
+                    Ok(profile) if profile.conflicts(&self.menu.ratings, &self.menu.history) => {
+                        // Ask first; the answer comes back as ResolveImport
+                        self.menu.importing = profile.name.clone();
+                        self.menu.state = MenuState::ImportConflict;
+                        self.menu.selected = 0;
+                        self.pending_import = Some((path, profile));
+                    }
+                    Ok(profile) => self.import_profile(path, profile, ImportMode::Replace)?,
+                    Err(e) => self.menu.notice = e,
+                }
+            }
+            MenuAction::ResolveImport(mode) => {
+                self.menu.state = MenuState::PlayerSettings;
+                self.menu.selected = 4;
+                if let Some((path, profile)) = self.pending_import.take() {
+                    self.import_profile(path, profile, mode)?;
+                }
+            }
+            MenuAction::JoinOnline => {
+                // Connect after the status screen has been drawn once
+                self.menu.show_status(&format!("Connecting to {}", self.menu.join_address));
+                self.pending_join = true;
+            }
+            MenuAction::ToggleReady => {
+                if let Some(ref mut lobby) = self.net_lobby {
+                    let ready = !lobby.client_ready;
+                    match lobby.set_ready(ready) {
+                        Ok(()) => self.menu.lobby.client_ready = ready,
+                        Err(e) => self.menu.show_status(&e),
+                    }
+                }
+            }
+            MenuAction::PlaySound(sound) => self.send_audio(AudioEvent::Play(sound)),
+            MenuAction::StopSounds => self.send_audio(AudioEvent::Stop),
+            MenuAction::StartOnline => {
+                if let Some(mut lobby) = self.net_lobby.take() {
+                    match lobby.start() {
+                        Ok(()) => self.start_online_session(lobby.into_session()),
+                        Err(e) => self.menu.show_status(&e),
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Starts a local game on the chosen maze and level with the menu's mode and roles
+    fn start_local_game(&mut self, maze_index: usize, level: u32) {
+        // Switch to selected maze
+        select_maze(maze_index, self.custom_maze);
+
+        let mut new_game = self.new_local_game(
+            self.menu.game_mode.unwrap_or(GameMode::SinglePlayer),
+            self.menu.player1_role.unwrap_or(PlayerRole::PacMan),
+            self.menu.player2_role,
+        );
+        new_game.start_at_level(level);
+        // Time trial clears are kept as personal bests, so they can't be practised,
+        // and a race is between two players
+        let race = new_game.config.mode == GameMode::Race;
+        let practice = self.menu.practice && new_game.time_trial.is_none() && !race;
+
+        // Recording armed: write this game's inputs from its first tick
+        if self.menu.recording && practice {
+            eprintln!("Practice runs can't be recorded; playing unrecorded");
+        } else if self.menu.recording && race {
+            eprintln!("Races can't be recorded; playing unrecorded");
+        } else if self.menu.recording {
+            let header = TasHeader {
+                mode: new_game.config.mode,
+                player1_role: new_game.config.player1_role,
+                player2_role: new_game.config.player2_role,
+                cornering_window: new_game.config.cornering_window,
+                arcade_quirks: new_game.config.arcade_quirks,
+                ghost_preset: new_game.config.ghost_preset,
+                pacman_style: new_game.config.pacman_style,
+                maze_index,
+                maze_hash: Some(maze_hash()),
+                level: new_game.level,
+                seed: new_game.rng.seed(),
+                tick_rate: new_game.config.tick_rate,
+                ice: new_game.config.ice,
+            };
+            match TasRecorder::create(&header) {
+                Ok(recorder) => {
+                    println!("Recording to {}", recorder.path().display());
+                    self.tas_recorder = Some(recorder);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        self.start_game(new_game);
+        if race {
+            self.race = self.game.as_ref().map(Race::new);
+        }
+        self.practice = practice.then(Practice::new);
+        self.versus = self.local_versus_match();
+    }
+
+    /// The players of a local game if it is Pac-Man against a ghost, by their profile names
+    /// (practice runs aren't rated)
+    fn local_versus_match(&self) -> Option<VersusMatch> {
+        if self.practice.is_some() {
+            return None;
+        }
+        let config = self.game.as_ref()?.config;
+        let player2_role = config.player2_role?;
+        if config.mode != GameMode::Multiplayer || player2_role == config.player1_role {
+            return None;
+        }
+        let (player1, player2) = (self.menu.ratings.name(0), self.menu.ratings.name(1));
+        Some(match config.player1_role {
+            PlayerRole::PacMan => VersusMatch::new(player1, player2),
+            PlayerRole::Ghost => VersusMatch::new(player2, player1),
+        })
+    }
+
+    /// Creates a game on the current maze with the gameplay settings and `--seed`
+    fn new_local_game(&self, mode: GameMode, player1_role: PlayerRole, player2_role: Option<PlayerRole>) -> Game {
+        let mut config = GameConfig::new(mode, player1_role, player2_role);
+        config.cornering_window = self.menu.settings.gameplay.cornering_window;
+        config.arcade_quirks = self.menu.settings.gameplay.arcade_targeting;
+        config.ghost_preset = self.menu.settings.gameplay.ghost_preset;
+        config.pacman_style = self.menu.settings.gameplay.pacman_style;
+        // Time trial records are compared tick for tick, so they all run at the
+        // default rate and off the ice
+        if mode != GameMode::TimeTrial {
+            config.tick_rate = self.menu.settings.gameplay.tick_rate;
+            config.ice = self.menu.settings.gameplay.ice_maze;
+        }
+
+        match self.seed {
+            Some(seed) => Game::new_with_seed(config, seed),
+            None => Game::new(config),
+        }
+    }
+
+    /// Starts a local game playing a campaign, on its first stage's card if it has one
+    ///
+    /// Campaign runs are not recorded: a recording names a single maze.
+    fn start_campaign(&mut self, campaign_index: usize) {
+        let Some(campaign) = self.campaigns.get(campaign_index).cloned() else { return };
+        if self.menu.recording {
+            eprintln!("Campaigns can't be recorded; playing unrecorded");
+        }
+        let mut new_game = self.new_local_game(
+            self.menu.game_mode.unwrap_or(GameMode::SinglePlayer),
+            self.menu.player1_role.unwrap_or(PlayerRole::PacMan),
+            self.menu.player2_role,
+        );
+        new_game.start_campaign(campaign);
+        self.start_game(new_game);
+        self.practice = self.menu.practice.then(Practice::new);
+        self.versus = self.local_versus_match();
+        self.show_stage_card();
+    }
+
+    /// The campaign just won, if new game plus can follow it (not after a
+    /// practice run)
+    fn won_campaign(&self) -> Option<Arc<Campaign>> {
+        let run = self.game.as_ref()?.campaign.as_ref()?;
+        (run.finished() && self.practice.is_none() && !self.playtest).then(|| run.campaign.clone())
+    }
+
+    /// Starts new game plus on the campaign just won, with the same players:
+    /// the campaign loops, pellets come back and the ghosts play their
+    /// hardest, for a score kept apart from standard runs (see `stats.rs`)
+    fn start_ng_plus(&mut self) {
+        let (Some(campaign), Some(old)) = (self.won_campaign(), self.game.as_ref()) else { return };
+        let (mode, player1_role, player2_role) = (old.config.mode, old.config.player1_role, old.config.player2_role);
+        let mut new_game = self.new_local_game(mode, player1_role, player2_role);
+        new_game.config.ng_plus = true;
+        new_game.config.ghost_preset = GhostPreset::HARDEST;
+        new_game.start_campaign(campaign);
+        self.start_game(new_game);
+        self.versus = self.local_versus_match();
+        self.show_stage_card();
+    }
+
+    /// Playtests the maze being edited: a single-player game that goes back to
+    /// the editor when it ends or is left
+    fn start_playtest(&mut self) {
+        let game = self.new_local_game(GameMode::SinglePlayer, PlayerRole::PacMan, None);
+        self.playtest = true;
+        self.start_game(game);
+    }
+
+    /// Shows the current campaign stage's text card, if it has one
+    fn show_stage_card(&mut self) {
+        let has_text = self.game.as_ref()
+            .and_then(|game| game.campaign.as_ref())
+            .and_then(|run| run.current())
+            .map(|stage| stage.text.is_some())
+            .unwrap_or(false);
+        if has_text {
+            self.enter(AppState::Cutscene { timer: self.game_ticks(STAGE_CARD_TICKS) });
+        }
+    }
+
+    /// Starts the match of an established online session
+    ///
+    /// The match is rated once the other player's profile name has arrived.
+    fn start_online_session(&mut self, session: NetSession) {
+        let game = start_online_game(&session);
+        let local_name = self.menu.ratings.name(0).to_string();
+        let versus = self.menu.lobby.peer_name.clone().map(|peer_name| match session.local_role() {
+            PlayerRole::PacMan => VersusMatch::new(&local_name, &peer_name),
+            PlayerRole::Ghost => VersusMatch::new(&peer_name, &local_name),
+        });
+        self.net_session = Some(session);
+        self.net_host = None;
+        self.start_game(game);
+        self.versus = versus;
+    }
+
+    /// Moves rating points to the winner of a versus game that just ended
+    fn rate_versus_game(&mut self) {
+        let Some(ref mut versus) = self.versus else { return };
+        if versus.result.is_some() || !versus.is_rated() {
+            return;
+        }
+        let winner = versus.winner();
+        let (winner_name, loser_name) = match winner {
+            PlayerRole::PacMan => (&versus.pacman, &versus.ghost),
+            PlayerRole::Ghost => (&versus.ghost, &versus.pacman),
+        };
+        let points = self.menu.ratings.record_game(winner_name, loser_name);
+        versus.result = Some((winner, points));
+        if let Err(e) = self.menu.ratings.save() {
+            eprintln!("{}", e);
+        }
+    }
+
+    fn playing_event(&mut self, event: Event) {
+        let ticks_only = self.ticks_only();
+        // Pausing is not possible online, the peer keeps running
+        let can_pause = self.net_session.is_none();
+        let controls = self.menu.settings.controls;
+        match event {
+            // Escape key: pause (online: return to menu)
+            Event::KeyDown { scancode: Some(Scancode::Escape), .. } => self.escape_from_run(),
+
+            // Pause key / Start button: pause
+            Event::KeyDown { scancode: Some(scancode), .. } if scancode == controls.pause && can_pause => {
+                self.enter(AppState::Paused);
+            }
+            Event::ControllerButtonDown { button: Button::Start, .. } if can_pause => self.enter(AppState::Paused),
+
+            // Window lost focus: pause if the player wants that
+            Event::Window { win_event: WindowEvent::FocusLost, .. }
+                if can_pause && self.menu.settings.gameplay.pause_on_focus_loss => {
+                self.enter(AppState::Paused);
+            }
+
+            // Space, Enter or A: the one-button scheme's action
+            Event::KeyDown { scancode: Some(Scancode::Space | Scancode::Return), repeat: false, .. }
+                if self.one_button.enabled => self.one_button.cycle(),
+            Event::ControllerButtonDown { which, button: Button::A, .. }
+                if self.one_button.enabled && self.gamepads.player_for(which) == Some(0) => self.one_button.cycle(),
+
+            // Number keys / face buttons: the human ghost's emotes
+            Event::KeyDown { scancode: Some(scancode), .. } if EMOTE_KEYS.contains(&scancode) => {
+                if let Some(index) = EMOTE_KEYS.iter().position(|&key| key == scancode) {
+                    self.show_emote(Emote::ALL[index]);
+                }
+            }
+            Event::ControllerButtonDown { which, button, .. } if EMOTE_BUTTONS.contains(&button) => {
+                // Only the pad of the player steering the ghost
+                let index = EMOTE_BUTTONS.iter().position(|&emote_button| emote_button == button);
+                if let Some(index) = index.filter(|_| self.gamepads.player_for(which) == self.human_ghost_player()) {
+                    self.show_emote(Emote::ALL[index]);
+                }
+            }
+
+            // Player 1's keys: move player (online or recorded, input only enters the game through the tick)
+            Event::KeyDown { scancode: Some(scancode), .. } if !ticks_only => {
+                if let (Some((dx, dy)), Some(game)) = (key_direction(controls.player1, scancode), self.game.as_mut()) {
+                    game.process_input(dx, dy);
+                }
+                // In a race, player 2's keys steer player 2's own game
+                if let (Some((dx, dy)), Some(race)) = (key_direction(controls.player2, scancode), self.race.as_mut()) {
+                    race.rival.process_input(dx, dy);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    /// Which player (0 or 1) steers a ghost in a multiplayer game, if a
+    /// human does (online, only the local player counts)
+    fn human_ghost_player(&self) -> Option<usize> {
+        if let Some(ref session) = self.net_session {
+            return (session.local_role() == PlayerRole::Ghost).then_some(0);
+        }
+        let config = self.game.as_ref()?.config;
+        if config.mode != GameMode::Multiplayer {
+            None
+        } else if config.player1_role == PlayerRole::Ghost {
+            Some(0)
+        } else if config.player2_role == Some(PlayerRole::Ghost) {
+            Some(1)
+        } else {
+            None
+        }
+    }
+
+    /// Shows an emote above the human ghost (and sends it to the peer online)
+    fn show_emote(&mut self, emote: Emote) {
+        if self.human_ghost_player().is_none() {
+            return;
+        }
+        if let Some(ref mut session) = self.net_session {
+            session.queue_emote(emote);
+        }
+        self.emote = Some((emote, self.game_ticks(EMOTE_TICKS)));
+    }
+
+    fn paused_event(&mut self, event: Event) {
+        let action = match event {
+            // A button while a player is without a controller: that controller is theirs now
+            Event::ControllerButtonDown { which, button: Button::A, .. } if self.lost_controller.is_some() => {
+                if let Some(player) = self.lost_controller {
+                    self.gamepads.assign(which, player);
+                }
+                PauseAction::Resume
+            }
+
+            // Escape / pause key / Start / B button / right click: resume
+            Event::KeyDown { scancode: Some(Scancode::Escape), .. } |
+            Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. } => PauseAction::Resume,
+            Event::KeyDown { scancode: Some(scancode), .. } if scancode == self.menu.settings.controls.pause => {
+                PauseAction::Resume
+            }
+            Event::ControllerButtonDown { button: Button::Start | Button::B, .. } => PauseAction::Resume,
+
+            // Arrow keys and d-pad: move through the pause menu
+            Event::KeyDown { scancode: Some(Scancode::Up), .. } |
+            Event::ControllerButtonDown { button: Button::DPadUp, .. } => {
+                self.pause_menu.process_input(-1);
+                PauseAction::None
+            }
+            Event::KeyDown { scancode: Some(Scancode::Down), .. } |
+            Event::ControllerButtonDown { button: Button::DPadDown, .. } => {
+                self.pause_menu.process_input(1);
+                PauseAction::None
+            }
+
+            // Enter key / A button / left click: choose
+            Event::KeyDown { scancode: Some(Scancode::Return), .. } |
+            Event::ControllerButtonDown { button: Button::A, .. } => self.pause_menu.select(),
+            Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => self.pause_menu.mouse_clicked(x, y),
+            Event::MouseMotion { x, y, .. } => {
+                self.pause_menu.mouse_moved(x, y);
+                PauseAction::None
+            }
+
+            _ => PauseAction::None,
+        };
+
+        match action {
+            PauseAction::None => {}
+            PauseAction::Resume => self.enter(AppState::Playing),
+            PauseAction::RestartLevel => self.restart_level(),
+            PauseAction::Settings => {
+                self.menu_before_settings = (self.menu.state, self.menu.selected);
+                self.menu.state = MenuState::Settings;
+                self.menu.selected = 0;
+                self.enter(AppState::Settings);
+            }
+            PauseAction::QuitToMenu => self.ask_to_quit(false),
+        }
+    }
+
+    /// The on-screen controls' layout in the window, and a touch (0.0 to 1.0
+    /// across the window) in window pixels
+    fn touch_point(&self, x: f32, y: f32) -> (TouchLayout, i32, i32) {
+        let (w, h) = self.canvas.as_ref().map(|canvas| canvas.window().size()).unwrap_or((0, 0));
+        (TouchLayout::new(w as i32, h as i32), (x * w as f32) as i32, (y * h as f32) as i32)
+    }
+
+    /// Whether a player (0 or 1) steers something in the current game from this machine
+    fn plays_locally(&self, player: usize) -> bool {
+        let Some(ref game) = self.game else { return false };
+        if self.net_session.is_some() || self.tas_playback.is_some() {
+            return false;
+        }
+        match game.config.mode {
+            GameMode::Demo => false,
+            GameMode::SinglePlayer | GameMode::TimeTrial => player == 0,
+            _ => true,
+        }
+    }
+
+    /// A player's last controller was unplugged: pauses their run and asks for
+    /// another controller (nothing happens online, where the peer can't be paused)
+    fn controller_lost(&mut self, player: usize) {
+        if !self.plays_locally(player) {
+            return;
+        }
+        match self.state {
+            AppState::Playing | AppState::Dying { .. } | AppState::LevelComplete { .. } => self.enter(AppState::Paused),
+            AppState::Paused | AppState::Settings | AppState::ConfirmQuit { .. } => {}
+            _ => return,
+        }
+        self.lost_controller = Some(player);
+    }
+
+    /// Escape during a run: opens the pause menu, or leaves online (the peer can't be paused)
+    fn escape_from_run(&mut self) {
+        if self.net_session.is_some() {
+            self.back_to_menu();
+        } else {
+            self.enter(AppState::Paused);
+        }
+    }
+
+    /// Asks whether to leave the run, offering to save it first
+    ///
+    /// # Arguments
+    /// * `exit` - Close the game afterwards instead of going back to the menu
+    fn ask_to_quit(&mut self, exit: bool) {
+        if let AppState::ConfirmQuit { .. } = self.state {
+            // Asked again (e.g. the window was closed twice): just go
+            self.quit = true;
+            return;
+        }
+        if self.state == AppState::Settings {
+            (self.menu.state, self.menu.selected) = self.menu_before_settings;
+        }
+        // A playtest or a practice run is thrown away, never saved
+        let saveable = !self.playtest && self.practice.is_none() && self.game.as_ref().map(can_save).unwrap_or(false);
+        self.quit_confirm = QuitConfirm::new(saveable);
+        self.enter(AppState::ConfirmQuit { exit });
+    }
+
+    fn confirm_quit_event(&mut self, event: Event, exit: bool) {
+        let choice = match event {
+            Event::KeyDown { scancode: Some(Scancode::Escape), .. } |
+            Event::ControllerButtonDown { button: Button::B | Button::Start, .. } |
+            Event::MouseButtonDown { mouse_btn: MouseButton::Right, .. } => QuitChoice::Cancel,
+            Event::KeyDown { scancode: Some(Scancode::Up), .. } |
+            Event::ControllerButtonDown { button: Button::DPadUp, .. } => {
+                self.quit_confirm.process_input(-1);
+                QuitChoice::None
+            }
+            Event::KeyDown { scancode: Some(Scancode::Down), .. } |
+            Event::ControllerButtonDown { button: Button::DPadDown, .. } => {
+                self.quit_confirm.process_input(1);
+                QuitChoice::None
+            }
+            Event::KeyDown { scancode: Some(Scancode::Return), .. } |
+            Event::ControllerButtonDown { button: Button::A, .. } => self.quit_confirm.select(),
+            Event::MouseButtonDown { mouse_btn: MouseButton::Left, x, y, .. } => self.quit_confirm.mouse_clicked(x, y),
+            Event::MouseMotion { x, y, .. } => {
+                self.quit_confirm.mouse_moved(x, y);
+                QuitChoice::None
+            }
+            _ => QuitChoice::None,
+        };
+
+        match choice {
+            QuitChoice::None => return,
+            QuitChoice::Cancel => {
+                self.enter(AppState::Paused);
+                return;
+            }
+            QuitChoice::SaveAndQuit => {
+                let Some(ref game) = self.game else { return };
+                match save_autosave(game) {
+                    Ok(path) => println!("Game saved to {}", path.display()),
+                    Err(e) => {
+                        // Keep the run rather than losing it
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            }
+            QuitChoice::Quit => {}
+        }
+        if exit {
+            self.quit = true;
+        } else {
+            self.back_to_menu();
+        }
+    }
+
+    /// Puts the game back to how it was when the current level started
+    ///
+    /// A recording or replay no longer matches the game after this, so it stops.
+    fn restart_level(&mut self) {
+        let Some(ref level_start) = self.level_start else { return };
+        self.game = Some(level_start.clone());
+        if let Some(ref mut race) = self.race {
+            race.restart();
+        }
+        if self.tas_recorder.take().is_some() {
+            println!("Recording stopped: the level was restarted");
+        }
+        if self.tas_playback.take().is_some() {
+            println!("Replay stopped: the level was restarted");
+        }
+        self.enter(AppState::Playing);
+    }
+
+    /// Practice run keys: F5 saves the game, F8 goes back to the save, F6 marks
+    /// the ghosts' target tiles and F7 freezes or unfreezes the frightened timer
+    fn practice_key(&mut self, scancode: Scancode) {
+        let (Some(practice), Some(game)) = (&mut self.practice, &mut self.game) else { return };
+        match scancode {
+            Scancode::F5 => practice.save(game),
+            Scancode::F6 => practice.show_targets = !practice.show_targets,
+            Scancode::F7 => game.power_frozen = !game.power_frozen,
+            _ => {
+                let Some(saved) = practice.restore(game) else { return };
+                *game = saved;
+                self.emote = None;
+                self.enter(AppState::Playing);
+            }
+        }
+    }
+
+    /// The settings pages work as in the main menu; leaving them returns to the pause menu
+    fn settings_event(&mut self, event: Event) {
+        if is_escape(&event) && !self.menu.capturing {
+            self.menu.state = MenuState::GameMode;
+        } else {
+            self.menu_navigation_event(event);
+        }
+        if !self.menu.state.is_settings() {
+            (self.menu.state, self.menu.selected) = self.menu_before_settings;
+            self.enter(AppState::Paused);
+        }
+    }
+
+    // ========================================================================
+    // Update
+    // ========================================================================
+
+    /// Advances the current state by one frame
+    ///
+    /// # Arguments
+    /// * `keyboard` - Keys held right now (steering is read from the held state)
+    pub fn update(&mut self, keyboard: &KeyboardState) -> Result<(), String> {
+        if self.state.has_game() && self.game.is_none() {
+            self.enter(AppState::Menu);
+        }
+        self.game_speed = self.held_game_speed(keyboard);
+        match self.state {
+            AppState::Menu => self.update_menu(),
+            AppState::Playing => self.update_playing(keyboard),
+            AppState::Dying { .. } | AppState::LevelComplete { .. } | AppState::Cutscene { .. } => {
+                self.update_timed();
+            }
+            AppState::Desynced { .. } => {
+                self.fixed_ticks();
+                // The client waits for the host's call
+                let resynced = self.net_session.as_mut().map(NetSession::poll_resync).unwrap_or(Ok(false));
+                match resynced {
+                    Ok(true) => self.resync(),
+                    Ok(false) => {}
+                    Err(e) => {
+                        self.back_to_menu();
+                        self.menu.show_status(&e);
+                    }
+                }
+            }
+            AppState::Paused | AppState::Settings | AppState::ConfirmQuit { .. } | AppState::GameOver | AppState::Editor => {
+                // Nothing moves; keep the clock from building up a burst of ticks
+                self.fixed_ticks();
+            }
+        }
+        Ok(())
+    }
+
+    /// Speed asked for by the keys held: Tab fast-forwards and ` slows the game
+    /// down, in practice runs and playtests only (other games are scored or
+    /// played against someone)
+    fn held_game_speed(&self, keyboard: &KeyboardState) -> f64 {
+        if self.practice.is_none() && !self.playtest {
+            1.0
+        } else if keyboard.is_scancode_pressed(Scancode::Tab) {
+            FAST_FORWARD_SPEED
+        } else if keyboard.is_scancode_pressed(Scancode::Grave) {
+            SLOW_MOTION_SPEED
+        } else {
+            1.0
+        }
+    }
+
+    /// Number of fixed-timestep ticks due since the last frame
+    fn fixed_ticks(&mut self) -> u32 {
+        // Fixed timestep game loop
+        // This ensures the game runs at a consistent speed regardless of frame rate
+        let current_time = Instant::now();
+        let frame_duration = current_time.duration_since(self.previous_frame_time);
+        self.previous_frame_time = current_time;
+
+        // Add elapsed time to accumulator (clamped to prevent spiral of death),
+        // sped up or slowed down while fast-forwarding or in slow motion
+        self.time_accumulator += frame_duration.as_secs_f64().min(MAX_TIME_STEP) * self.game_speed;
+        let tick_period = 1.0 / self.tick_rate() as f64;
+        let mut ticks = 0;
+        while self.time_accumulator >= tick_period {
+            self.time_accumulator -= tick_period;
+            ticks += 1;
+        }
+        ticks
+    }
+
+    fn update_menu(&mut self) {
+        let ticks = self.fixed_ticks();
+        if let Some(ref mut demo) = self.demo {
+            demo.update(ticks);
+        }
+
+        // Joining blocks until connected, so it runs after the status was shown
+        if self.pending_join {
+            self.pending_join = false;
+            let joined = NetLobby::join(&self.menu.join_address).and_then(|mut lobby| {
+                lobby.send_name(self.menu.ratings.name(0))?;
+                Ok(lobby)
+            });
+            match joined {
+                Ok(lobby) => {
+                    self.menu.lobby.peer_name = None;
+                    self.menu.lobby.host_role = lobby.setup.host_role;
+                    self.menu.lobby.maze_index = lobby.setup.maze_index;
+                    self.menu.lobby.input_delay = lobby.setup.input_delay;
+                    self.menu.lobby.peer_connected = true;
+                    self.menu.lobby.client_ready = false;
+                    self.menu.state = MenuState::ClientLobby;
+                    self.menu.selected = 0;
+                    self.net_lobby = Some(lobby);
+                }
+                Err(e) => self.menu.show_status(&e),
+            }
+        }
+
+        // Leaving the lobby screens drops the listener and the connection
+        if self.menu.state != MenuState::HostLobby {
+            self.net_host = None;
+        }
+        if self.menu.state != MenuState::HostLobby && self.menu.state != MenuState::ClientLobby {
+            self.net_lobby = None;
+        }
+
+        // Hosting: wait for the client without blocking the menu
+        if self.net_lobby.is_none() {
+            if let Some(ref host) = self.net_host {
+                match host.poll_accept() {
+                    Ok(Some(mut lobby)) => match lobby.send_name(self.menu.ratings.name(0)) {
+                        Ok(()) => {
+                            self.menu.lobby.peer_name = None;
+                            self.menu.lobby.peer_connected = true;
+                            self.menu.lobby.client_ready = false;
+                            self.net_lobby = Some(lobby);
+                        }
+                        Err(e) => self.menu.show_status(&e),
+                    },
+                    Ok(None) => {}
+                    Err(e) => self.menu.show_status(&e),
+                }
+            }
+        }
+
+        // Lobby: pick up the other side's changes (ready flag, host settings, start)
+        let mut lobby_error = None;
+        if let Some(ref mut lobby) = self.net_lobby {
+            match lobby.poll() {
+                Ok(()) => {
+                    self.menu.lobby.client_ready = lobby.client_ready;
+                    self.menu.lobby.host_role = lobby.setup.host_role;
+                    self.menu.lobby.maze_index = lobby.setup.maze_index;
+                    self.menu.lobby.input_delay = lobby.setup.input_delay;
+                    self.menu.lobby.peer_name = lobby.peer_name.clone();
+                }
+                Err(e) => lobby_error = Some(e),
+            }
+        }
+        if let Some(e) = lobby_error {
+            self.net_lobby = None;
+            self.net_host = None;
+            self.menu.show_status(&e);
+        }
+        if self.net_lobby.as_ref().map(|lobby| lobby.started).unwrap_or(false) {
+            if let Some(lobby) = self.net_lobby.take() {
+                self.start_online_session(lobby.into_session());
+            }
+        }
+    }
+
+    /// Runs both games of a race until caught up with real time, ending the
+    /// race once a player clears their maze or loses their last life
+    fn update_race(&mut self, keyboard: &KeyboardState) {
+        let controls = self.menu.settings.controls;
+        let player1_input = held_direction(keyboard, controls.player1)
+            .or_else(|| self.gamepads.direction(0))
+            .or_else(|| self.touch.direction());
+        let player2_input = held_direction(keyboard, controls.player2).or_else(|| self.gamepads.direction(1));
+        let rumble = controls.rumble_strength();
+
+        let ticks = self.fixed_ticks();
+        let (Some(ref mut current_game), Some(ref mut race)) = (&mut self.game, &mut self.race) else { return };
+        let tick_start = Instant::now();
+        for _ in 0..ticks {
+            let player1_input = self.one_button.steer(current_game).or(player1_input);
+            let events = current_game.tick(player1_input, None);
+            self.event_bus.publish(current_game, &events);
+            self.hooks.run(current_game, &events);
+            self.gamepads.rumble(Some(0), &events, rumble);
+            if let Some(ref mut run) = self.run_stats {
+                run.record(current_game, &events);
+            }
+            race.check(0, &events);
+
+            let rival_events = race.rival.tick(player2_input, None);
+            self.event_bus.publish(&race.rival, &rival_events);
+            self.hooks.run(&mut race.rival, &rival_events);
+            self.gamepads.rumble(Some(1), &rival_events, rumble);
+            race.check(1, &rival_events);
+            if race.winner.is_some() {
+                break;
+            }
+        }
+        self.debug_stats.profile.record(Span::Tick, tick_start.elapsed(), ticks);
+
+        if race.winner.is_some() {
+            self.enter(AppState::GameOver);
+        }
+    }
+
+    /// Runs game ticks until caught up with real time, switching state on deaths,
+    /// cleared mazes and game over
+    fn update_playing(&mut self, keyboard: &KeyboardState) {
+        // Where the ghosts were as last drawn (a frame behind at most)
+        self.send_proximity();
+        if self.race.is_some() {
+            self.update_race(keyboard);
+            return;
+        }
+
+        // Held keys (fallback input), then player 1's controller
+        let controls = self.menu.settings.controls;
+        let player1_input = held_direction(keyboard, controls.player1)
+            .or_else(|| self.gamepads.direction(0))
+            .or_else(|| self.touch.direction());
+
+        // Check for player 2 input (WASD by default, for multiplayer and co-op)
+        let two_players = self.game.as_ref().map(|g| g.config.mode != GameMode::SinglePlayer).unwrap_or(false);
+        let player2_input = if two_players {
+            held_direction(keyboard, controls.player2).or_else(|| self.gamepads.direction(1))
+        } else {
+            None
+        };
+
+        let ticks = self.fixed_ticks();
+        self.emote = self.emote.map(|(emote, left)| (emote, left - ticks as i32)).filter(|&(_, left)| left > 0);
+        let Some(ref mut current_game) = self.game else { return };
+        let mut peer_emote = None;
+        let mut network_error = None;
+        let mut desync = None;
+        let mut recording_error = None;
+        let mut next_state = None;
+
+        // Run game updates until we've caught up with real time
+        let tick_start = Instant::now();
+        for _ in 0..ticks {
+            if !current_game.alive {
+                next_state = Some(AppState::GameOver);
+                break;
+            }
+            // One-button play looks ahead from where Pac-Man is on every tick
+            // (online, only when the local player is Pac-Man)
+            let local_pacman = self.net_session.as_ref().map(|session| session.local_role() == PlayerRole::PacMan);
+            let player1_input = match local_pacman {
+                Some(false) => player1_input,
+                _ => self.one_button.steer(current_game).or(player1_input),
+            };
+            let events = if let Some(ref mut session) = self.net_session {
+                // Online: both peers tick with the same exchanged inputs
+                let exchange = session.advance(player1_input, current_game.state_hash());
+                peer_emote = session.take_peer_emote().or(peer_emote);
+                match exchange {
+                    Ok(Exchange::Inputs(pacman_input, ghost_input)) => current_game.tick(pacman_input, ghost_input),
+                    Ok(Exchange::Desync(tick)) => {
+                        desync = Some(tick);
+                        break;
+                    }
+                    Err(e) => {
+                        network_error = Some(e);
+                        break;
+                    }
+                }
+            } else if let Some(ref mut playback) = self.tas_playback {
+                // Replay: recorded inputs only, checking the RNG and checksum after every tick
+                match playback.next_frame() {
+                    Some(frame) => {
+                        let events = current_game.tick(frame.player1_input, frame.player2_input);
+                        playback.verify(current_game.rng.state(), current_game.state_hash());
+                        events
+                    }
+                    None => Vec::new(),
+                }
+            } else {
+                let events = current_game.tick(player1_input, player2_input);
+                if let Some(ref mut recorder) = self.tas_recorder {
+                    if let Err(e) = recorder.record(player1_input, player2_input, current_game) {
+                        recording_error = Some(e);
+                    }
+                }
+                events
+            };
+
+            // Sound effects, music changes and the like for what just happened
+            self.event_bus.publish(current_game, &events);
+            self.hooks.run(current_game, &events);
+            self.gamepads.rumble(None, &events, controls.rumble_strength());
+            if let Some(ref mut run) = self.run_stats {
+                run.record(current_game, &events);
+            }
+            if let Some(ref mut telemetry) = self.telemetry {
+                telemetry.record(current_game, [player1_input, player2_input], &events);
+            }
+
+            // Deaths and cleared mazes freeze the board for a moment (the
+            // same tick on both peers and in replays, as it follows the events)
+            next_state = events.iter().find_map(|event| match *event {
+                GameEvent::GameOver => Some(AppState::GameOver),
+                GameEvent::PlayerDied { .. } => Some(AppState::Dying { timer: current_game.config.ticks(DEATH_FREEZE_TICKS) }),
+                // A time trial ends on its results card instead
+                GameEvent::LevelCleared { .. } if current_game.time_trial.is_none() => {
+                    Some(AppState::LevelComplete { timer: current_game.config.ticks(LEVEL_COMPLETE_TICKS) })
+                }
+                _ => None,
+            });
+            if let Some(AppState::LevelComplete { .. }) = next_state {
+                // The maze is already reset for the next level: Restart Level comes back here
+                self.level_start = Some(current_game.clone());
+                if let Some(ref mut versus) = self.versus {
+                    versus.mazes_cleared += 1;
+                }
+
+                // Reaching a level in a local game unlocks it for level select (campaigns
+                // pick their own levels and playtests aren't real runs, so they unlock nothing)
+                let unlocks = self.net_session.is_none() && self.tas_playback.is_none()
+                    && current_game.campaign.is_none() && !self.playtest && self.practice.is_none();
+                if unlocks && self.progress.unlock(current_game.level) {
+                    self.menu.unlocked_level = self.progress.highest_level;
+                    if let Err(e) = self.progress.save() {
+                        eprintln!("{}", e);
+                    }
+                }
+            }
+            if next_state.is_some() {
+                break;
+            }
+        }
+        self.debug_stats.profile.record(Span::Tick, tick_start.elapsed(), ticks);
+
+        if let Some(emote) = peer_emote {
+            self.emote = Some((emote, self.game_ticks(EMOTE_TICKS)));
+        }
+
+        // Recording failed (e.g. disk full): keep playing unrecorded
+        if let Some(e) = recording_error {
+            eprintln!("{}", e);
+            self.tas_recorder = None;
+        }
+
+        // Desync: stop both games and swap them for the dump
+        if let Some(tick) = desync {
+            network_error = self.stop_on_desync(tick).err();
+        }
+
+        // Lost connection: back to the menu with the error shown
+        if let Some(e) = network_error {
+            self.back_to_menu();
+            self.menu.show_status(&e);
+            return;
+        }
+
+        if next_state == Some(AppState::GameOver) {
+            self.rate_versus_game();
+        }
+        if let Some(state) = next_state {
+            self.enter(state);
+        }
+    }
+
+    /// Swaps games with the peer after a desync, dumps both and waits for the host to resync
+    fn stop_on_desync(&mut self, tick: u32) -> Result<(), String> {
+        let (Some(ref game), Some(ref mut session)) = (&self.game, &mut self.net_session) else { return Ok(()) };
+        let local_state = write_game(game);
+        let remote_state = session.exchange_snapshots(&local_state)?;
+        let (host_state, client_state) = if session.role == NetRole::Host {
+            (local_state, remote_state)
+        } else {
+            (remote_state, local_state)
+        };
+        match save_desync_dump(tick, &host_state, &client_state) {
+            Ok(path) => eprintln!("Desync after tick {}: both games saved next to {}", tick, path.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+        self.resync_state = Some(host_state);
+        self.enter(AppState::Desynced { tick });
+        Ok(())
+    }
+
+    /// Replaces the game with the host's after a desync and resumes the match
+    ///
+    /// The host tells the client first; both then load the same state, so the
+    /// games match again.
+    fn resync(&mut self) {
+        let result = (|| {
+            let session = self.net_session.as_mut().ok_or("Not in an online match")?;
+            if session.role == NetRole::Host {
+                session.send_resync()?;
+            }
+            let state = self.resync_state.take().ok_or("No game to resync from")?;
+            read_game(&state)
+        })();
+        match result {
+            Ok(game) => {
+                self.game = Some(game);
+                self.enter(AppState::Playing);
+            }
+            Err(e) => {
+                self.back_to_menu();
+                self.menu.show_status(&e);
+            }
+        }
+    }
+
+    /// Counts down a frozen or scripted state, then resumes play
+    fn update_timed(&mut self) {
+        let ticks = self.fixed_ticks() as i32;
+        let state = match self.state {
+            AppState::Dying { timer } => AppState::Dying { timer: timer - ticks },
+            AppState::LevelComplete { timer } => AppState::LevelComplete { timer: timer - ticks },
+            AppState::Cutscene { timer } => AppState::Cutscene { timer: timer - ticks },
+            state => state,
+        };
+        match state {
+            AppState::LevelComplete { timer } if timer <= 0 => {
+                // In a campaign the next stage may open with its card
+                self.enter(AppState::Playing);
+                self.show_stage_card();
+            }
+            AppState::Dying { timer } | AppState::Cutscene { timer } if timer <= 0 => {
+                self.enter(AppState::Playing);
+            }
+            state => self.state = state,
+        }
+    }
+
+    // ========================================================================
+    // Draw
+    // ========================================================================
+
+    /// Draws and presents the current state's screen
+    pub fn draw(&mut self) -> Result<(), String> {
+        let Some(ref mut canvas) = self.canvas else { return Ok(()) };
+        match self.state {
+            AppState::Menu | AppState::Settings => {
+                // The attract demo, dimmed, behind the main menu (there is none behind
+                // the settings opened from the pause menu)
+                if let Some(ref demo) = self.demo {
+                    draw_scaled(canvas, &mut self.render_cache, |canvas, cache| {
+                        cache.set_area(None);
+                        demo.game.draw(&mut SdlRenderer::new(canvas, cache))
+                    })?;
+                    dim_screen(canvas, DEMO_DIM_ALPHA);
+                } else {
+                    canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+                    canvas.clear();
+                }
+                self.menu.draw(canvas)?;
+                canvas.present();
+                Ok(())
+            }
+            AppState::Editor => {
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+                canvas.clear();
+                let (ww, wh) = canvas.window().size();
+                ui::heading(canvas, "EDITOR", ww as i32 / 2, wh as i32 / 2);
+                ui::hint(canvas, "F5: Playtest   Esc: Menu", ww as i32 / 2, wh as i32 / 2 + 4 * GLYPH_H);
+                canvas.present();
+                Ok(())
+            }
+            _ => self.draw_game(),
+        }
+    }
+
+    /// Draws the board with the current state's overlay
+    fn draw_game(&mut self) -> Result<(), String> {
+        let (Some(ref current_game), Some(ref mut canvas)) = (&self.game, &mut self.canvas) else { return Ok(()) };
+        let draw_start = Instant::now();
+        let (ww, _) = canvas.window().size();
+
+        // The boards, at the render scale; overlays are drawn over them at full resolution
+        draw_scaled(canvas, &mut self.render_cache, |canvas, cache| {
+            // A race splits the window: player 2's game on the right, player 1's on the left
+            if let Some(ref mut race) = self.race {
+                let [left, right] = viewports(ww, canvas.window().size().1);
+                canvas.set_draw_color(sdl2::pixels::Color::RGB(0, 0, 0));
+                canvas.clear();
+                cache.set_area(Some(left));
+                race.rival_cache.set_area(Some(right));
+                race.rival_cache.photosensitive = cache.photosensitive;
+                race.rival_cache.maze_colors = cache.maze_colors;
+                // Player 2's game, so player 2's skin comes first
+                race.rival_cache.skins = [cache.skins[1].clone(), cache.skins[0].clone()];
+                race.rival.draw(&mut SdlRenderer::new(canvas, &mut race.rival_cache))?;
+            } else {
+                cache.set_area(None);
+            }
+            let allocations_before = allocations();
+            current_game.draw(&mut SdlRenderer::new(canvas, cache))?;
+            self.debug_stats.draw_allocations = allocations() - allocations_before;
+
+            // The human ghost's emote
+            let emoting_ghost = current_game.player_ghost_index.and_then(|index| current_game.ghosts.get(index));
+            if let (Some((emote, _)), Some(ghost)) = (self.emote, emoting_ghost) {
+                draw_emote(canvas, cache, ghost.x, ghost.y, emote);
+            }
+
+            // The way one-button play will take at the next junction
+            if self.state == AppState::Playing && self.one_button.enabled {
+                if let Some(((x, y), way)) = self.one_button.target() {
+                    draw_exit_marker(canvas, cache, x, y, way);
+                }
+            }
+            Ok(())
+        })?;
+        let mut renderer = SdlRenderer::new(canvas, &mut self.render_cache);
+
+        // State overlays, centered on the maze (on the whole window in a race)
+        let center_x = if self.race.is_some() { ww as i32 / 2 } else { renderer.cache.ox + renderer.cache.sw / 2 };
+        let center_y = renderer.cache.game_start_y + (VIEW_H as f32 * renderer.cache.scale) as i32 / 2;
+        match self.state {
+            AppState::Paused => {
+                let top = pause_menu_top(renderer.cache);
+                self.pause_menu.draw(renderer.canvas, center_x, top);
+                if let Some(player) = self.lost_controller {
+                    let y = top + PauseMenu::height() + 10;
+                    ui::hint(renderer.canvas, &format!("Player {}'s controller was disconnected", player + 1), center_x, y);
+                    ui::hint(renderer.canvas, "Press A on a controller to take over, or resume on the keyboard", center_x, y + 18);
+                }
+            }
+            AppState::ConfirmQuit { .. } => {
+                self.quit_confirm.draw(renderer.canvas, center_x, pause_menu_top(renderer.cache));
+            }
+            AppState::LevelComplete { .. } => {
+                ui::heading(renderer.canvas, &format!("LEVEL {}", current_game.level), center_x, center_y);
+            }
+            AppState::GameOver => {
+                let won = current_game.campaign.as_ref().map(|run| run.finished()).unwrap_or(false);
+                let heading = match self.race.as_ref().and_then(|race| race.winner) {
+                    Some(winner) => format!("PLAYER {} WINS", winner + 1),
+                    None if won => "CAMPAIGN COMPLETE".to_string(),
+                    None => "GAME OVER".to_string(),
+                };
+                ui::heading(renderer.canvas, &heading, center_x, center_y);
+                let mut hint_y = center_y + 4 * GLYPH_H;
+                if let Some(ref versus) = self.versus {
+                    if let Some((winner, points)) = versus.result {
+                        // Versus results: who won and the ratings after the game
+                        let ratings = &self.menu.ratings;
+                        let line = |name: &str, change: i32| format!("{} {} ({:+})", name, ratings.get(name).rating, change);
+                        let (verdict, pacman_change) = match winner {
+                            PlayerRole::PacMan => ("Pac-Man wins", points),
+                            PlayerRole::Ghost => ("The ghost wins", -points),
+                        };
+                        let standings = format!("{}   {}", line(&versus.pacman, pacman_change), line(&versus.ghost, -pacman_change));
+                        ui::label(renderer.canvas, verdict, center_x, hint_y, ui::TEXT_COLOR, Align::Center);
+                        ui::hint(renderer.canvas, &standings, center_x, hint_y + 2 * GLYPH_H);
+                        hint_y += 5 * GLYPH_H;
+                    }
+                }
+                let back_to = if self.playtest {
+                    "Enter: Editor"
+                } else if won && self.practice.is_none() {
+                    "Enter: Menu   N: New Game+"
+                } else {
+                    "Enter: Menu"
+                };
+                ui::hint(renderer.canvas, back_to, center_x, hint_y);
+            }
+            AppState::Desynced { tick } => {
+                ui::heading(renderer.canvas, "DESYNC", center_x, center_y);
+                let detail = format!("The games differed after tick {}; both were saved for diffing", tick);
+                ui::hint(renderer.canvas, &detail, center_x, center_y + 3 * GLYPH_H);
+                let is_host = self.net_session.as_ref().map(|session| session.role == NetRole::Host).unwrap_or(false);
+                let choices = if is_host { "Enter: Resync from Host   Esc: Leave" } else { "Waiting for the host to resync   Esc: Leave" };
+                ui::hint(renderer.canvas, choices, center_x, center_y + 5 * GLYPH_H);
+            }
+            AppState::Cutscene { .. } => {
+                // Campaign stage card over the dimmed maze
+                let run = current_game.campaign.as_ref();
+                if let Some(stage) = run.and_then(|run| run.current()) {
+                    dim_screen(renderer.canvas, STAGE_CARD_DIM_ALPHA);
+                    let stage_number = run.map(|run| run.stage + 1).unwrap_or(1);
+                    let top = center_y - 6 * GLYPH_H;
+                    ui::heading(renderer.canvas, &format!("STAGE {}", stage_number), center_x, top);
+                    if let Some(ref text) = stage.text {
+                        let lines = wrap_text(text, STAGE_CARD_TEXT_WIDTH, 1);
+                        draw_lines(renderer.canvas, &lines, center_x, top + 4 * GLYPH_H, 1, ui::TEXT_COLOR, Align::Center);
+                    }
+                }
+                ui::hint(renderer.canvas, "Enter: Skip", center_x, center_y + 4 * GLYPH_H);
+            }
+            _ => {}
+        }
+
+        // Connection quality during an online match
+        if let Some(ref session) = self.net_session {
+            draw_net_quality(renderer.canvas, renderer.cache, session.ping_ms(), session.jitter_ms(), session.setup.input_delay);
+        }
+
+        // Practice keys, and the ghosts' targets once asked for (the debug overlay marks them anyway)
+        if let Some(ref practice) = self.practice {
+            draw_practice_status(renderer.canvas, renderer.cache, practice.has_savestate(), current_game.power_frozen);
+            if practice.show_targets && !self.show_debug {
+                draw_ghost_markers(renderer.canvas, renderer.cache, current_game);
+            }
+        }
+        if self.game_speed != 1.0 {
+            draw_game_speed(renderer.canvas, renderer.cache, self.game_speed);
+        }
+
+        if self.show_debug {
+            draw_debug_overlay(renderer.canvas, renderer.cache, current_game, &self.debug_stats);
+        }
+        if let Some(ref mut clip) = self.clip_recorder {
+            if let Err(e) = clip.capture(renderer.canvas) {
+                eprintln!("Clip capture stopped: {}", e);
+                self.clip_recorder = None;
+            }
+        }
+        // The on-screen controls (after the clip frame, so clips don't show them)
+        if self.state == AppState::Playing && self.touch.visible() {
+            let (ww, wh) = renderer.canvas.window().size();
+            draw_touch_controls(renderer.canvas, &TouchLayout::new(ww as i32, wh as i32), self.touch.held());
+        }
+        self.debug_stats.profile.record(Span::Draw, draw_start.elapsed(), 0);
+        renderer.present()?;
+        self.debug_stats.frame_presented();
+        Ok(())
+    }
+}
+
+/// Escape key
+fn is_escape(event: &Event) -> bool {
+    matches!(event, Event::KeyDown { scancode: Some(Scancode::Escape), .. })
+}
+
+/// A key, a controller button or a controller stick out of its dead zone
+fn is_physical_input(event: &Event) -> bool {
+    match *event {
+        Event::KeyDown { .. } | Event::ControllerButtonDown { .. } => true,
+        Event::ControllerAxisMotion { value, .. } => value.unsigned_abs() > STICK_DEADZONE as u16,
+        _ => false,
+    }
+}
+
+/// Enter key or A button
+fn is_confirm(event: &Event) -> bool {
+    matches!(
+        event,
+        Event::KeyDown { scancode: Some(Scancode::Return), .. } |
+        Event::ControllerButtonDown { button: Button::A, .. }
+    )
+}
+
+/// Switches the current maze to the one at the given menu index,
+/// or to the maze loaded with `--maze` if there is one
+pub fn select_maze(maze_index: usize, custom_maze: CustomMaze) {
+    if let Some(maze) = custom_maze {
+        set_current_maze(maze);
+        return;
+    }
+    set_current_maze(match maze_index {
+        0 => &MAZE_1,
+        1 => &MAZE_2,
+        _ => &MAZE_1,  // Default to maze 1
+    });
+}
+
+/// Creates the game for playing back a recording, set up exactly as it was recorded
+///
+/// Fails when the maze differs from the one the recording was made on.
+pub fn start_replay(playback: &TasPlayback, custom_maze: CustomMaze) -> Result<Game, String> {
+    select_maze(playback.header.maze_index, custom_maze);
+    playback.check_maze()?;
+    let mut game = Game::new_with_seed(playback.header.config(), playback.header.seed);
+    game.start_at_level(playback.header.level);
+    Ok(game)
+}
+
+/// Creates the game for an established online session
+///
+/// Both peers build the exact same game from the lobby settings:
+/// same maze, same roles (player 1 = Pac-Man, player 2 = ghost) and same RNG seed.
+/// Which peer steers which side is decided by `NetSession::advance`.
+fn start_online_game(session: &NetSession) -> Game {
+    // Always a built-in maze: the peer cannot see a local maze file
+    select_maze(session.setup.maze_index, None);
+    let config = GameConfig::new(GameMode::Multiplayer, PlayerRole::PacMan, Some(PlayerRole::Ghost));
+    Game::new_with_seed(config, session.setup.seed)
+}
+
+/// Directions of a player's up, down, left and right keys
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// Returns the direction of the first held key among up, down, left and right
+fn held_direction(keyboard_state: &KeyboardState, keys: [Scancode; 4]) -> Option<(i32, i32)> {
+    keys.iter()
+        .zip(DIRECTIONS)
+        .find(|(scancode, _)| keyboard_state.is_scancode_pressed(**scancode))
+        .map(|(_, direction)| direction)
+}
+
+/// Returns the direction a key steers, if it is one of the player's up, down, left and right keys
+fn key_direction(keys: [Scancode; 4], scancode: Scancode) -> Option<(i32, i32)> {
+    keys.iter().position(|&key| key == scancode).map(|i| DIRECTIONS[i])
+}
+
+/// Applies changed video settings to the window and makes the game re-layout
+fn apply_video(settings: &VideoSettings, canvas: &mut Canvas<Window>, render_cache: &mut RenderCache) {
+    if let Err(e) = settings.apply(canvas.window_mut()) {
+        eprintln!("Could not change video mode: {}", e);
+    }
+    render_cache.photosensitive = settings.photosensitive;
+    render_cache.render_scale = settings.render_scale();
+    render_cache.invalidate();
+}
+
+/// Opens the default audio backend (SDL2)
+#[cfg(feature = "sdl-audio")]
+fn open_audio(sdl: &sdl2::Sdl) -> Result<Option<AudioManager>, String> {
+    let backend = crate::sdl_audio::SdlAudio::new(sdl)?;
+    Ok(Some(AudioManager::new(Box::new(backend))?))
+}
+
+/// Built with cpal but not SDL audio: sound goes through cpal
+#[cfg(all(feature = "cpal-audio", not(feature = "sdl-audio")))]
+fn open_audio(_sdl: &sdl2::Sdl) -> Result<Option<AudioManager>, String> {
+    let backend = crate::cpal_audio::CpalAudio::new();
+    Ok(Some(AudioManager::new(Box::new(backend))?))
+}
+
+/// Built without an audio backend: the game runs silently
+#[cfg(not(any(feature = "sdl-audio", feature = "cpal-audio")))]
+fn open_audio(_sdl: &sdl2::Sdl) -> Result<Option<AudioManager>, String> {
+    Ok(None)
+}
@@ -0,0 +1,72 @@
+//! Locating optional asset files (sounds, music, data tables and campaigns)
+//!
+//! Assets are looked up by logical name in a list of search paths, first match
+//! wins:
+//! 1. The directory in the `PACLIKE_ASSET_DIR` environment variable
+//! 2. `assets/` in the directory the game is started from
+//! 3. `assets/` next to the executable
+//!
+//! Every asset is optional: the game falls back to its built-in content when
+//! nothing is found.
+
+use std::path::{Path, PathBuf};
+
+/// Environment variable that adds an asset directory in front of the defaults
+const ASSET_DIR_ENV: &str = "PACLIKE_ASSET_DIR";
+
+/// Returns the asset directories to search, in order
+pub fn search_paths() -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    if let Some(dir) = std::env::var_os(ASSET_DIR_ENV) {
+        paths.push(PathBuf::from(dir));
+    }
+    paths.push(PathBuf::from("assets"));
+    if let Some(exe_dir) = std::env::current_exe().ok().as_deref().and_then(Path::parent) {
+        let beside_exe = exe_dir.join("assets");
+        if !paths.contains(&beside_exe) {
+            paths.push(beside_exe);
+        }
+    }
+    paths
+}
+
+/// Finds an asset by logical name
+///
+/// # Arguments
+/// * `subdir` - Subdirectory of the asset directory (e.g. "audio")
+/// * `name` - File name without extension (e.g. "death")
+/// * `extensions` - Accepted extensions, in order of preference
+///
+/// # Returns
+/// The first existing file, or None
+pub fn find_asset(subdir: &str, name: &str, extensions: &[&str]) -> Option<PathBuf> {
+    search_paths().into_iter()
+        .flat_map(|dir| {
+            let dir = dir.join(subdir);
+            extensions.iter().map(move |ext| dir.join(format!("{}.{}", name, ext)))
+        })
+        .find(|path| path.is_file())
+}
+
+/// Lists every asset of one kind
+///
+/// # Arguments
+/// * `subdir` - Subdirectory of the asset directory (e.g. "campaigns")
+/// * `extension` - Extension of the files to list (e.g. "txt")
+///
+/// # Returns
+/// One path per file name (the first directory's copy wins), sorted by name
+pub fn list_assets(subdir: &str, extension: &str) -> Vec<PathBuf> {
+    let mut found: Vec<PathBuf> = Vec::new();
+    for dir in search_paths() {
+        let Ok(entries) = std::fs::read_dir(dir.join(subdir)) else { continue };
+        for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+            let wanted = path.is_file() && path.extension().is_some_and(|ext| ext == extension);
+            if wanted && !found.iter().any(|other| other.file_name() == path.file_name()) {
+                found.push(path);
+            }
+        }
+    }
+    found.sort_by(|a, b| a.file_name().cmp(&b.file_name()));
+    found
+}
@@ -1,102 +1,815 @@
-//! Audio module for background music
+//! Audio module for background music and sound effects
 //! 
-//! Generates simple chiptune music combining Tetris-style melodies with Zelda-style harmonies
+//! Generates simple chiptune music combining Tetris-style melodies with Zelda-style harmonies,
+//! mixed with one-shot synthesized effects. The events returned by `Game::tick` are turned
+//! into `AudioEvent`s and sent over a channel; the mixer picks them up on the audio thread
+//! and starts the matching effect.
+//! 
+//! The music follows the game (`MusicContext`): menu, gameplay, power pellet and game
+//! over each have their own song, crossfaded when the context changes. Songs are written
+//! in the tracker notation of `tracker.rs`; the built-in ones can be replaced by `.song`
+//! files in `assets/music/` (`menu`, `gameplay`, `power`, `game_over`).
+//! 
+//! Music and effects can be replaced by WAV or OGG Vorbis files in `assets/audio/` (see
+//! `assets.rs`), named after the sound: `music_menu`, `music`, `music_power`, `music_game_over`,
+//! `pellet`, `power_pellet`, `ghost_eaten`, `fruit`, `extra_life` and `death`. Files are decoded to the device's
+//! sample rate once at startup; sounds without a file keep their synthesized version.
+//! 
+//! For low-vision players, an optional proximity tone (`AudioEvent::Proximity`) hums under
+//! the music, rising in pitch and volume as the nearest ghost closes in, with a brighter,
+//! reedier sound while the ghosts are frightened.
+//! 
+//! The jukebox (see `jukebox.rs`) takes the music over while it is open: it plays any track
+//! or effect on its own (`AudioEvent::Play`), and the game's music comes back when it closes.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::{channel, Receiver, Sender};
+use crate::assets::find_asset;
+use crate::audio_tap::AudioTap;
+use crate::game::GameEvent;
+use crate::tracker::{Sequencer, Song};
+use crate::wav::decode_wav;
+#[cfg(feature = "ogg")]
+use crate::ogg::decode_ogg;
+
+/// Asset subdirectory holding sound files
+const AUDIO_DIR: &str = "audio";
+
+/// Sound file extensions looked for, in order of preference
+#[cfg(feature = "ogg")]
+const AUDIO_EXTENSIONS: &[&str] = &["wav", "ogg"];
+#[cfg(not(feature = "ogg"))]
+const AUDIO_EXTENSIONS: &[&str] = &["wav"];
+
+/// Logical name of the music file
+const MUSIC_NAME: &str = "music";
+
+/// Asset subdirectory holding song files
+const MUSIC_DIR: &str = "music";
+
+/// Extension of song files
+const SONG_EXTENSION: &str = "song";
+
+/// Something happened in the game that makes a sound
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum AudioEvent {
+    /// A pellet was eaten (alternates between the two halves of the waka-waka)
+    PelletEaten,
+    /// A power pellet was eaten
+    PowerPelletEaten,
+    /// A frightened ghost was eaten
+    GhostEaten,
+    /// A bonus fruit was collected
+    FruitEaten,
+    /// An extra life was earned
+    ExtraLife,
+    /// Pac-Man lost a life
+    Death,
+    /// The power pellet effect ended (music only)
+    PowerPelletEnded,
+    /// The last life was lost (music only)
+    GameOver,
+    /// The menu was opened (music only)
+    MenuOpened,
+    /// A game started or was returned to (music only)
+    GameStarted,
+    /// The volume settings changed (0 = silent, 10 = full)
+    SetVolume { music: u8, effects: u8 },
+    /// How near the nearest ghost is, from 0 (out of range: silent) to 255
+    /// (touching), and whether it is frightened (sets the proximity tone)
+    Proximity { closeness: u8, frightened: bool },
+    /// The jukebox opened: the music goes quiet and follows `Play` and `Stop` until it closes
+    JukeboxOpened,
+    /// The jukebox closed: the game's music comes back
+    JukeboxClosed,
+    /// Play a track or an effect on its own (a track only plays while the jukebox is open)
+    Play(Sound),
+    /// Silence the music and every effect playing
+    Stop,
+    /// Sound and music files changed (dev mode): load them again and start the music over
+    ReloadAssets,
+}
+
+impl AudioEvent {
+    /// Sound for something that happened in the game, if it makes one
+    pub fn for_game_event(event: &GameEvent) -> Option<AudioEvent> {
+        match event {
+            GameEvent::PelletEaten { .. } => Some(AudioEvent::PelletEaten),
+            GameEvent::PowerPelletEaten { .. } => Some(AudioEvent::PowerPelletEaten),
+            GameEvent::GhostEaten { .. } => Some(AudioEvent::GhostEaten),
+            GameEvent::FruitEaten { .. } | GameEvent::DoublePointsTaken { .. } | GameEvent::BombTaken { .. } => {
+                Some(AudioEvent::FruitEaten)
+            }
+            GameEvent::WallBlasted { .. } => Some(AudioEvent::GhostEaten),
+            GameEvent::ExtraLife { .. } => Some(AudioEvent::ExtraLife),
+            GameEvent::PlayerDied { .. } => Some(AudioEvent::Death),
+            GameEvent::PowerPelletEnded => Some(AudioEvent::PowerPelletEnded),
+            GameEvent::GameOver => Some(AudioEvent::GameOver),
+            GameEvent::FruitSpawned | GameEvent::DoublePointsSpawned | GameEvent::BombSpawned |
+            GameEvent::WallHealed { .. } | GameEvent::LevelCleared { .. } | GameEvent::PelletsRespawned { .. } |
+            GameEvent::ScoreChanged { .. } => None,
+        }
+    }
+
+    /// The proximity tone for `Game::nearest_ghost` (None silences it)
+    pub fn proximity(nearest: Option<(i32, bool)>) -> AudioEvent {
+        match nearest {
+            Some((distance, frightened)) if distance < PROXIMITY_RANGE => {
+                let closeness = (PROXIMITY_RANGE - distance) * 255 / PROXIMITY_RANGE;
+                AudioEvent::Proximity { closeness: closeness as u8, frightened }
+            }
+            _ => AudioEvent::Proximity { closeness: 0, frightened: false },
+        }
+    }
+
+    /// The event's sound effect, if it has one
+    fn effect(self) -> Option<Effect> {
+        match self {
+            AudioEvent::PelletEaten => Some(Effect::Pellet),
+            AudioEvent::PowerPelletEaten => Some(Effect::PowerPellet),
+            AudioEvent::GhostEaten => Some(Effect::GhostEaten),
+            AudioEvent::FruitEaten => Some(Effect::Fruit),
+            AudioEvent::ExtraLife => Some(Effect::ExtraLife),
+            AudioEvent::Death => Some(Effect::Death),
+            AudioEvent::Play(Sound::Effect(effect)) => Some(effect),
+            AudioEvent::PowerPelletEnded | AudioEvent::GameOver |
+            AudioEvent::MenuOpened | AudioEvent::GameStarted |
+            AudioEvent::SetVolume { .. } | AudioEvent::Proximity { .. } |
+            AudioEvent::JukeboxOpened | AudioEvent::JukeboxClosed |
+            AudioEvent::Play(Sound::Music(_)) | AudioEvent::Stop | AudioEvent::ReloadAssets => None,
+        }
+    }
+}
+
+/// A sound effect
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Effect {
+    Pellet,
+    PowerPellet,
+    GhostEaten,
+    Fruit,
+    ExtraLife,
+    Death,
+}
+
+impl Effect {
+    pub const ALL: [Effect; 6] = [
+        Effect::Pellet, Effect::PowerPellet, Effect::GhostEaten, Effect::Fruit, Effect::ExtraLife, Effect::Death,
+    ];
+
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            Effect::Pellet => "Pellet",
+            Effect::PowerPellet => "Power Pellet",
+            Effect::GhostEaten => "Ghost Eaten",
+            Effect::Fruit => "Fruit",
+            Effect::ExtraLife => "Extra Life",
+            Effect::Death => "Death",
+        }
+    }
+
+    /// Logical name of the effect's sound file
+    fn asset_name(self) -> &'static str {
+        match self {
+            Effect::Pellet => "pellet",
+            Effect::PowerPellet => "power_pellet",
+            Effect::GhostEaten => "ghost_eaten",
+            Effect::Fruit => "fruit",
+            Effect::ExtraLife => "extra_life",
+            Effect::Death => "death",
+        }
+    }
+
+    /// The sound file replacing the synthesized effect, if there is one
+    pub fn sound_file(self) -> Option<PathBuf> {
+        find_asset(AUDIO_DIR, self.asset_name(), AUDIO_EXTENSIONS)
+    }
+
+    /// The synthesized effect (`waka_up` picks the half of the waka-waka for a pellet)
+    pub fn segments(self, waka_up: bool) -> &'static [Segment] {
+        match self {
+            Effect::Pellet if waka_up => WAKA_UP,
+            Effect::Pellet => WAKA_DOWN,
+            Effect::PowerPellet => POWER_JINGLE,
+            Effect::GhostEaten => GHOST_ZAP,
+            Effect::Fruit => FRUIT_CHIME,
+            Effect::ExtraLife => EXTRA_LIFE_TRILL,
+            Effect::Death => DEATH_ARPEGGIO,
+        }
+    }
+}
+
+/// Something the jukebox plays: a track or an effect
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Sound {
+    Music(MusicContext),
+    Effect(Effect),
+}
+
+/// Part of a sound effect: a square wave sweeping from one pitch to another
+#[derive(Clone, Copy, Debug)]
+pub struct Segment {
+    pub start_hz: f32,
+    pub end_hz: f32,
+    pub seconds: f32,
+}
+
+const fn seg(start_hz: f32, end_hz: f32, seconds: f32) -> Segment {
+    Segment { start_hz, end_hz, seconds }
+}
+
+/// First half of the waka-waka (mouth closing)
+const WAKA_DOWN: &[Segment] = &[seg(480.0, 260.0, 0.07)];
+/// Second half of the waka-waka (mouth opening)
+const WAKA_UP: &[Segment] = &[seg(260.0, 480.0, 0.07)];
+/// Rising power pellet jingle
+const POWER_JINGLE: &[Segment] = &[
+    seg(523.25, 523.25, 0.06), seg(659.25, 659.25, 0.06),
+    seg(783.99, 783.99, 0.06), seg(1046.50, 1046.50, 0.12),
+];
+/// Upward zap when a ghost is eaten
+const GHOST_ZAP: &[Segment] = &[seg(200.0, 1600.0, 0.25)];
+/// Two-note fruit chime
+const FRUIT_CHIME: &[Segment] = &[seg(1046.50, 1046.50, 0.08), seg(1318.51, 1318.51, 0.16)];
+/// Fast high trill rising to a held top note for an extra life
+const EXTRA_LIFE_TRILL: &[Segment] = &[
+    seg(1567.98, 1567.98, 0.05), seg(2093.00, 2093.00, 0.05), seg(1567.98, 1567.98, 0.05),
+    seg(2093.00, 2093.00, 0.05), seg(2637.02, 2637.02, 0.18),
+];
+/// Falling death arpeggio ending in a slide down
+const DEATH_ARPEGGIO: &[Segment] = &[
+    seg(783.99, 783.99, 0.09), seg(659.25, 659.25, 0.09), seg(523.25, 523.25, 0.09),
+    seg(392.00, 392.00, 0.09), seg(329.63, 329.63, 0.09), seg(261.63, 100.0, 0.35),
+];
+
+/// Volume of sound effects
+const EFFECT_VOLUME: f32 = 0.2;
+
+/// Effects playing at the same time; the oldest is dropped beyond this
+const MAX_VOICES: usize = 6;
+
+/// One sound effect being played
+enum Voice {
+    /// Synthesized effect
+    Synth {
+        segments: &'static [Segment],
+        /// Current segment and time into it
+        segment: usize,
+        time: f32,
+        /// Square wave phase (0..1)
+        phase: f32,
+    },
+    /// Effect loaded from a file
+    Sample {
+        samples: Arc<[f32]>,
+        position: usize,
+    },
+}
+
+impl Voice {
+    fn synth(segments: &'static [Segment]) -> Self {
+        Voice::Synth { segments, segment: 0, time: 0.0, phase: 0.0 }
+    }
+
+    /// Returns the next sample, or None once the effect has ended
+    fn next_sample(&mut self, sample_rate: f32) -> Option<f32> {
+        match self {
+            Voice::Synth { segments, segment, time, phase } => {
+                let mut current = *segments.get(*segment)?;
+                while *time >= current.seconds {
+                    *time -= current.seconds;
+                    *segment += 1;
+                    current = *segments.get(*segment)?;
+                }
+                let progress = *time / current.seconds;
+                let freq = current.start_hz + (current.end_hz - current.start_hz) * progress;
+                *phase = (*phase + freq / sample_rate).fract();
+                *time += 1.0 / sample_rate;
+                Some(if *phase < 0.5 { EFFECT_VOLUME } else { -EFFECT_VOLUME })
+            }
+            Voice::Sample { samples, position } => {
+                let sample = *samples.get(*position)?;
+                *position += 1;
+                Some(sample)
+            }
+        }
+    }
+}
+
+/// Tiles within which a ghost is heard on the proximity tone
+const PROXIMITY_RANGE: i32 = 12;
+
+/// Pitch of the proximity tone for a ghost at the edge of the range and for one touching Pac-Man
+const PROXIMITY_LOW_HZ: f32 = 180.0;
+const PROXIMITY_HIGH_HZ: f32 = 720.0;
+
+/// Volume of the proximity tone for a ghost touching Pac-Man (kept under the effects)
+const PROXIMITY_VOLUME: f32 = 0.08;
+
+/// Seconds the proximity tone takes to glide to a new closeness (so it never clicks)
+const PROXIMITY_GLIDE_SECONDS: f32 = 0.1;
+
+/// The proximity tone: a hum that follows the nearest ghost
+#[derive(Default)]
+struct ProximityTone {
+    /// Closeness asked for, 0..1
+    target: f32,
+    /// Closeness now, gliding towards `target`
+    level: f32,
+    frightened: bool,
+    /// Wave phase (0..1)
+    phase: f32,
+}
+
+impl ProximityTone {
+    fn next_sample(&mut self, sample_rate: f32) -> f32 {
+        self.level += (self.target - self.level) * (1.0 / (PROXIMITY_GLIDE_SECONDS * sample_rate)).min(1.0);
+        if self.level < 0.001 {
+            return 0.0;
+        }
+        let freq = PROXIMITY_LOW_HZ + (PROXIMITY_HIGH_HZ - PROXIMITY_LOW_HZ) * self.level;
+        self.phase = (self.phase + freq / sample_rate).fract();
+        // A soft triangle for danger, a thin pulse while the ghosts can be eaten
+        let wave = if self.frightened {
+            if self.phase < 0.2 { 0.7 } else { -0.7 }
+        } else {
+            1.0 - 4.0 * (self.phase - 0.5).abs()
+        };
+        wave * PROXIMITY_VOLUME * self.level
+    }
+}
+
+/// Which music plays: chosen by what the game is doing
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum MusicContext {
+    Menu,
+    Gameplay,
+    /// A power pellet is active
+    PowerPellet,
+    GameOver,
+}
+
+impl MusicContext {
+    pub const ALL: [MusicContext; 4] = [
+        MusicContext::Menu, MusicContext::Gameplay, MusicContext::PowerPellet, MusicContext::GameOver,
+    ];
+
+    /// Display name
+    pub fn name(self) -> &'static str {
+        match self {
+            MusicContext::Menu => "Menu",
+            MusicContext::Gameplay => "Gameplay",
+            MusicContext::PowerPellet => "Power Pellet",
+            MusicContext::GameOver => "Game Over",
+        }
+    }
+
+    /// The music file replacing the context's song, if there is one
+    pub fn music_file(self) -> Option<PathBuf> {
+        find_asset(AUDIO_DIR, self.asset_name(), AUDIO_EXTENSIONS)
+    }
+
+    /// The song file replacing the built-in song, if there is one
+    pub fn song_file(self) -> Option<PathBuf> {
+        find_asset(MUSIC_DIR, self.song_name(), &[SONG_EXTENSION])
+    }
+
+    /// Logical name of the context's music file
+    fn asset_name(self) -> &'static str {
+        match self {
+            MusicContext::Menu => "music_menu",
+            MusicContext::Gameplay => MUSIC_NAME,
+            MusicContext::PowerPellet => "music_power",
+            MusicContext::GameOver => "music_game_over",
+        }
+    }
+
+    /// Logical name of the context's song file (tracker notation, see `tracker.rs`)
+    fn song_name(self) -> &'static str {
+        match self {
+            MusicContext::Menu => "menu",
+            MusicContext::Gameplay => "gameplay",
+            MusicContext::PowerPellet => "power",
+            MusicContext::GameOver => "game_over",
+        }
+    }
+
+    /// Built-in song used when there is no music or song file
+    fn builtin_song(self) -> &'static str {
+        match self {
+            MusicContext::Menu => MENU_SONG,
+            MusicContext::Gameplay => GAMEPLAY_SONG,
+            MusicContext::PowerPellet => POWER_SONG,
+            MusicContext::GameOver => GAME_OVER_SONG,
+        }
+    }
+
+    /// Loads the context's song from `assets/music/`, or the built-in one
+    pub fn load_song(self) -> Song {
+        if let Some(path) = self.song_file() {
+            match Song::load(&path) {
+                Ok(song) => return song,
+                Err(e) => eprintln!("Could not load {}: {}", path.display(), e),
+            }
+        }
+        // Built-in songs are known to parse; an empty song would just be silent
+        Song::parse(self.builtin_song()).unwrap_or_default()
+    }
+}
 
-use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+/// Gameplay: Tetris-style melody over a Zelda-style progression
+const GAMEPLAY_SONG: &str = "\
+tempo 18
+# Tetris theme (Korobeiniki melody) - simplified, on a thin pulse
+channel 1 square:0.25 0.12 adsr 0.01 0.4 0.6 0.15
+C4:1 E4:1 G4:1 C5:1 G4:1 E4:1 C4:1 G4:1
+E4:1 C4:1 G4:1 E4:1 C4:1 E4:1 G4:1 C5:1
+# Zelda-style chord progression (I-V-vi-IV): C major, G major, A minor, F major
+channel 2 triangle 0.3 adsr 0.01 0.2 0.8 0.2
+C3:2 G2:2 A2:2 F2:2
+channel 3 square:0.5 0.05 adsr 0.05 0.5 0.5 0.3
+E4:2 B4:2 C4:2 A3:2
+channel 4 square:0.125 0.04 adsr 0.05 0.5 0.5 0.3
+G4:2 D5:2 E4:2 C4:2
+";
 
-/// Simple audio callback that generates chiptune music
+/// Menu: calm arpeggio over I-IV-V-I
+const MENU_SONG: &str = "\
+tempo 15
+channel 1 square:0.5 0.1 adsr 0.02 0.6 0.5 0.3
+G4:1 E4:1 C4:1 E4:1 A4:1 F4:1 D4:1 G4:1
+channel 2 triangle 0.3 adsr 0.05 0.3 0.8 0.4
+C3:2 F2:2 G2:2 C3:2
+channel 3 sine 0.12
+E4:2 A3:2 B3:2 E4:2
+channel 4 sine 0.08
+G4:2 C4:2 D4:2 G4:2
+";
+
+/// Power pellet: fast, tense trill over A minor and E major, with a ticking hi-hat
+const POWER_SONG: &str = "\
+tempo 360
+channel 1 square:0.125 0.12 adsr 0 0.05 0.7 0.02
+C5:1 D5:1 D#5:1 D5:1
+channel 2 triangle 0.3
+A2:6 E2:6
+channel 3 square:0.5 0.05 adsr 0 0.3 0.4 0.05
+C4:6 G#3:6
+channel 4 square:0.25 0.04 adsr 0 0.3 0.4 0.05
+E4:6 B3:6
+channel 5 noise 0.06 adsr 0 0.04 0 0
+C8:1 R:1
+";
+
+/// Game over: slow descending line over C minor and A-flat major
+const GAME_OVER_SONG: &str = "\
+tempo 30
+channel 1 square:0.5 0.1 adsr 0.01 0.8 0.3 0.4
+G4:1 F4:1 Eb4:1 D4:1 C4:1 C4:1
+channel 2 triangle 0.3 adsr 0.05 0.5 0.7 0.5
+C2:2 Ab2:2
+channel 3 sine 0.12
+Eb3:2 C4:2
+channel 4 sine 0.08
+G3:2 Eb4:2
+";
+
+/// Gain for a volume setting from 0 to 10
 /// 
-/// Combines Tetris-style melody with Zelda-style chord progressions
-struct MusicGenerator {
-    sample_rate: i32,
-    time: f32,
-    melody_time: f32,
-    bass_time: f32,
-}
-
-impl AudioCallback for MusicGenerator {
-    type Channel = f32;
-
-    fn callback(&mut self, out: &mut [f32]) {
-        // Tetris theme notes (Korobeiniki melody) - simplified
-        // C, E, G, C, G, E, C, G, E, C, G, E, C, E, G, C
-        let tetris_melody = [
-            261.63, 329.63, 392.00, 523.25, 392.00, 329.63, 261.63, 392.00,
-            329.63, 261.63, 392.00, 329.63, 261.63, 329.63, 392.00, 523.25,
-        ];
-        
-        // Zelda-style chord progression (I-V-vi-IV)
-        // C major, G major, A minor, F major
-        let zelda_chords = [
-            (261.63, 329.63, 392.00),  // C major
-            (392.00, 493.88, 587.33),  // G major
-            (220.00, 261.63, 329.63),  // A minor
-            (174.61, 220.00, 261.63),  // F major
-        ];
+/// Squared, so each step sounds about as big as the last.
+fn volume_gain(volume: u8) -> f32 {
+    let level = volume.min(10) as f32 / 10.0;
+    level * level
+}
+
+/// Seconds one track takes to fade into the next
+const CROSSFADE_SECONDS: f32 = 0.6;
+
+/// Background music: a music file looped from memory, a sequenced song, or none
+enum Music {
+    Sequenced(Sequencer),
+    Track { samples: Arc<[f32]>, position: usize },
+    Silent,
+}
+
+impl Music {
+    fn next_sample(&mut self) -> f32 {
+        match self {
+            Music::Sequenced(sequencer) => sequencer.next_sample(),
+            Music::Track { samples, position } => {
+                let sample = samples[*position];
+                *position = (*position + 1) % samples.len();
+                sample
+            }
+            Music::Silent => 0.0,
+        }
+    }
+}
+
+/// Mixes the background music with the sound effects requested over the event channel
+/// 
+/// Runs on the audio backend's thread. Music events switch between the tracks of each `MusicContext`, crossfading from the
+/// old track to the new one.
+pub struct Mixer {
+    sample_rate: f32,
+    /// Music the game asks for
+    context: MusicContext,
+    /// Music playing (None while the jukebox is silent)
+    playing: Option<MusicContext>,
+    /// The jukebox has the music, not the game
+    jukebox: bool,
+    music: Music,
+    /// Previous track while it fades out, and how far the fade has got (0..1)
+    fading_out: Option<(Music, f32)>,
+    /// Music files loaded for each context, replacing their songs
+    tracks: HashMap<MusicContext, Arc<[f32]>>,
+    /// Song of each context
+    songs: HashMap<MusicContext, Song>,
+    events: Receiver<AudioEvent>,
+    voices: Vec<Voice>,
+    /// Effects loaded from files, replacing the synthesized ones
+    samples: HashMap<Effect, Arc<[f32]>>,
+    /// Which half of the waka-waka the next pellet plays
+    waka_up: bool,
+    /// Music and effect volume set by `AudioEvent::SetVolume` (0..1)
+    music_gain: f32,
+    effects_gain: f32,
+    /// Hum following the nearest ghost, set by `AudioEvent::Proximity`
+    proximity: ProximityTone,
+    /// Copies of the mixed samples, for clips with sound
+    tap: Arc<AudioTap>,
+}
+
+impl Mixer {
+    /// Starts the music for a context from the beginning
+    fn start_music(&self, context: MusicContext) -> Music {
+        match self.tracks.get(&context) {
+            Some(samples) => Music::Track { samples: samples.clone(), position: 0 },
+            None => {
+                let song = self.songs.get(&context).cloned().unwrap_or_default();
+                Music::Sequenced(Sequencer::new(song, self.sample_rate as i32))
+            }
+        }
+    }
+
+    /// Crossfades to a context's music, or to silence (nothing happens if it is already playing)
+    fn play_music(&mut self, music: Option<MusicContext>) {
+        if music == self.playing {
+            return;
+        }
+        self.playing = music;
+        let next = match music {
+            Some(context) => self.start_music(context),
+            None => Music::Silent,
+        };
+        self.fading_out = Some((std::mem::replace(&mut self.music, next), 0.0));
+    }
+
+    /// Follows the game to another context, whose music plays unless the jukebox is open
+    fn switch_music(&mut self, context: MusicContext) {
+        self.context = context;
+        if !self.jukebox {
+            self.play_music(Some(context));
+        }
+    }
+
+    /// Updates the music for an event and starts its effect, if it has one
+    fn trigger(&mut self, event: AudioEvent) {
+        match event {
+            AudioEvent::MenuOpened => self.switch_music(MusicContext::Menu),
+            AudioEvent::GameStarted | AudioEvent::PowerPelletEnded => self.switch_music(MusicContext::Gameplay),
+            AudioEvent::PowerPelletEaten => self.switch_music(MusicContext::PowerPellet),
+            AudioEvent::GameOver => self.switch_music(MusicContext::GameOver),
+            AudioEvent::SetVolume { music, effects } => {
+                self.music_gain = volume_gain(music);
+                self.effects_gain = volume_gain(effects);
+                return;
+            }
+            AudioEvent::Proximity { closeness, frightened } => {
+                self.proximity.target = closeness as f32 / 255.0;
+                self.proximity.frightened = frightened;
+                return;
+            }
+            AudioEvent::JukeboxOpened => {
+                self.jukebox = true;
+                self.play_music(None);
+            }
+            AudioEvent::JukeboxClosed => {
+                self.jukebox = false;
+                self.play_music(Some(self.context));
+            }
+            AudioEvent::Play(Sound::Music(context)) if self.jukebox => self.play_music(Some(context)),
+            AudioEvent::Stop => {
+                self.play_music(None);
+                self.voices.clear();
+            }
+            AudioEvent::ReloadAssets => {
+                self.reload();
+                return;
+            }
+            _ => {}
+        }
+        // A death or the end of the game silences the proximity tone until told otherwise
+        if matches!(event, AudioEvent::Death | AudioEvent::GameOver | AudioEvent::MenuOpened | AudioEvent::JukeboxOpened) {
+            self.proximity.target = 0.0;
+        }
         
-        let melody_speed = 0.3;  // How fast the melody plays
-        let bass_speed = 0.15;   // How fast the bass/chords play
+        if let Some(effect) = event.effect() {
+            self.play_effect(effect);
+        }
+    }
+
+    /// Starts an effect, from its file if it has one
+    fn play_effect(&mut self, effect: Effect) {
+        if let Some(samples) = self.samples.get(&effect) {
+            let voice = Voice::Sample { samples: samples.clone(), position: 0 };
+            self.add_voice(voice);
+            return;
+        }
+        if effect == Effect::Pellet {
+            self.waka_up = !self.waka_up;
+        }
+        self.add_voice(Voice::synth(effect.segments(self.waka_up)));
+    }
+
+    /// Starts an effect, dropping the oldest one if too many are playing
+    fn add_voice(&mut self, voice: Voice) {
+        if self.voices.len() >= MAX_VOICES {
+            self.voices.remove(0);
+        }
+        self.voices.push(voice);
+    }
+
+    /// Returns the next music sample, blending in the fading track
+    fn next_music_sample(&mut self) -> f32 {
+        let sample = self.music.next_sample();
+        let Some((ref mut old, ref mut fade)) = self.fading_out else {
+            return sample;
+        };
+        *fade += 1.0 / (CROSSFADE_SECONDS * self.sample_rate);
+        if *fade >= 1.0 {
+            self.fading_out = None;
+            return sample;
+        }
+        old.next_sample() * (1.0 - *fade) + sample * *fade
+    }
+}
+
+impl Mixer {
+    /// Creates the mixer, loading music and effect files for `sample_rate`
+    fn new(sample_rate: i32, events: Receiver<AudioEvent>, tap: Arc<AudioTap>) -> Self {
+        tap.set_sample_rate(sample_rate as u32);
+        let (tracks, songs, samples) = load_assets(sample_rate);
+        let mut mixer = Mixer {
+            sample_rate: sample_rate as f32,
+            context: MusicContext::Menu,
+            playing: Some(MusicContext::Menu),
+            jukebox: false,
+            music: Music::Sequenced(Sequencer::new(Song::default(), sample_rate)),
+            fading_out: None,
+            tracks,
+            songs,
+            events,
+            voices: Vec::with_capacity(MAX_VOICES),
+            samples,
+            waka_up: false,
+            music_gain: 1.0,
+            effects_gain: 1.0,
+            proximity: ProximityTone::default(),
+            tap,
+        };
+        mixer.music = mixer.start_music(MusicContext::Menu);
+        mixer
+    }
+
+    /// Loads the music and effect files again, then starts the music playing
+    /// over (the sound may skip while a long file decodes)
+    fn reload(&mut self) {
+        (self.tracks, self.songs, self.samples) = load_assets(self.sample_rate as i32);
+        let playing = self.playing.take();
+        self.play_music(playing);
+    }
+
+    /// Fills a buffer of mono samples, first handling the events that arrived since the last call
+    pub fn fill(&mut self, out: &mut [f32]) {
+        while let Ok(event) = self.events.try_recv() {
+            self.trigger(event);
+        }
         
+        let sample_rate = self.sample_rate;
         for x in out.iter_mut() {
-            // Melody (Tetris theme)
-            let melody_index = ((self.melody_time * melody_speed) as usize) % tetris_melody.len();
-            let melody_freq = tetris_melody[melody_index];
-            let melody_phase = self.time * melody_freq * 2.0 * std::f32::consts::PI;
-            let melody = (melody_phase.sin() * 0.3).max(-1.0).min(1.0);
-            
-            // Bass/Chord (Zelda progression)
-            let chord_index = ((self.bass_time * bass_speed) as usize) % zelda_chords.len();
-            let (bass_freq, mid_freq, high_freq) = zelda_chords[chord_index];
-            
-            let bass_phase = self.time * bass_freq * 2.0 * std::f32::consts::PI;
-            let mid_phase = self.time * mid_freq * 2.0 * std::f32::consts::PI;
-            let high_phase = self.time * high_freq * 2.0 * std::f32::consts::PI;
-            
-            let bass = (bass_phase.sin() * 0.2).max(-1.0).min(1.0);
-            let mid = (mid_phase.sin() * 0.15).max(-1.0).min(1.0);
-            let high = (high_phase.sin() * 0.1).max(-1.0).min(1.0);
-            
-            // Combine all layers
-            *x = (melody + bass + mid + high).max(-1.0).min(1.0);
-            
-            self.time += 1.0 / (self.sample_rate as f32);
-            self.melody_time += 1.0 / (self.sample_rate as f32);
-            self.bass_time += 1.0 / (self.sample_rate as f32);
+            let music = self.next_music_sample() * self.music_gain;
+            let mut effects = 0.0;
+            self.voices.retain_mut(|voice| match voice.next_sample(sample_rate) {
+                Some(effect) => {
+                    effects += effect;
+                    true
+                }
+                None => false,
+            });
+            effects += self.proximity.next_sample(sample_rate);
+            let sample = music + effects * self.effects_gain;
+            *x = sample.max(-1.0).min(1.0);
         }
+        self.tap.push(out);
     }
 }
 
+/// Plays the mixer's output on some audio device
+/// 
+/// The backend decides the sample rate, builds the mixer with `make_mixer` and then
+/// keeps calling `Mixer::fill` from its audio thread. `SdlAudio` (in `sdl_audio.rs`)
+/// is the default and `CpalAudio` (in `cpal_audio.rs`) the SDL-free one; the game itself never touches the device, so it builds and runs
+/// (e.g. headless) without any backend.
+pub trait AudioBackend {
+    /// Opens the device and starts playing
+    fn start(&mut self, make_mixer: Box<dyn FnOnce(i32) -> Mixer + Send>) -> Result<(), String>;
+}
+
 /// Audio manager for the game
 pub struct AudioManager {
-    _device: AudioDevice<MusicGenerator>,
+    /// Keeps the device open
+    _backend: Box<dyn AudioBackend>,
+    /// Sending half of the event channel (cloned into each game)
+    events: Sender<AudioEvent>,
+    /// The mixer's output, for clips with sound
+    tap: Arc<AudioTap>,
 }
 
 impl AudioManager {
-    /// Creates a new audio manager and starts playing background music
-    pub fn new(sdl: &sdl2::Sdl) -> Result<Self, String> {
-        let audio_subsystem = sdl.audio()?;
-        
-        let desired_spec = AudioSpecDesired {
-            freq: Some(44100),
-            channels: Some(1),  // Mono
-            samples: None,     // Default sample size
-        };
-        
-        let device = audio_subsystem.open_playback(None, &desired_spec, |spec| {
-            MusicGenerator {
-                sample_rate: spec.freq,
-                time: 0.0,
-                melody_time: 0.0,
-                bass_time: 0.0,
-            }
-        })?;
-        
-        // Start playing
-        device.resume();
-        
+    /// Creates a new audio manager and starts playing background music on `backend`
+    /// 
+    /// Sound effects are played for events sent through `sender()`.
+    pub fn new(mut backend: Box<dyn AudioBackend>) -> Result<Self, String> {
+        let (events, receiver) = channel();
+        let tap = Arc::new(AudioTap::new());
+        let mixer_tap = tap.clone();
+        backend.start(Box::new(move |sample_rate| Mixer::new(sample_rate, receiver, mixer_tap)))?;
         Ok(AudioManager {
-            _device: device,
+            _backend: backend,
+            events,
+            tap,
         })
     }
+
+    /// Returns the tap on the mixed audio
+    pub fn tap(&self) -> Arc<AudioTap> {
+        self.tap.clone()
+    }
+    
+    /// Returns a sender for sound effect events
+    pub fn sender(&self) -> Sender<AudioEvent> {
+        self.events.clone()
+    }
 }
 
+/// Loads a sound from the asset directories, converted to mono f32 at `sample_rate`
+/// 
+/// # Returns
+/// Music files, songs and effect files for every context and effect, as the mixer keeps them
+type MixerAssets = (HashMap<MusicContext, Arc<[f32]>>, HashMap<MusicContext, Song>, HashMap<Effect, Arc<[f32]>>);
+
+/// Loads the music files, songs and effect files at `sample_rate`
+fn load_assets(sample_rate: i32) -> MixerAssets {
+    let tracks = MusicContext::ALL.iter()
+        .filter_map(|&context| {
+            load_sound(context.asset_name(), sample_rate)
+                .filter(|samples| !samples.is_empty())
+                .map(|samples| (context, Arc::from(samples)))
+        })
+        .collect();
+    let songs = MusicContext::ALL.iter()
+        .map(|&context| (context, context.load_song()))
+        .collect();
+    let samples = Effect::ALL.iter()
+        .filter_map(|&effect| {
+            load_sound(effect.asset_name(), sample_rate).map(|samples| (effect, Arc::from(samples)))
+        })
+        .collect();
+    (tracks, songs, samples)
+}
+
+/// The samples, or None if there is no file (or it could not be decoded, which is reported)
+fn load_sound(name: &str, sample_rate: i32) -> Option<Vec<f32>> {
+    let path = find_asset(AUDIO_DIR, name, AUDIO_EXTENSIONS)?;
+    match decode_sound(&path, sample_rate) {
+        Ok(samples) => Some(samples),
+        Err(e) => {
+            eprintln!("Could not load {}: {}", path.display(), e);
+            None
+        }
+    }
+}
+
+/// Decodes a sound file (WAV, or OGG Vorbis with the `ogg` feature) to mono f32 samples at `sample_rate`
+fn decode_sound(path: &Path, sample_rate: i32) -> Result<Vec<f32>, String> {
+    let bytes = std::fs::read(path).map_err(|e| e.to_string())?;
+    #[cfg(feature = "ogg")]
+    if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("ogg")) {
+        return decode_ogg(&bytes, sample_rate);
+    }
+    decode_wav(&bytes, sample_rate)
+}
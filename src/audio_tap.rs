@@ -0,0 +1,157 @@
+//! Tap on the mixed audio, so clips come with sound
+//!
+//! While a clip is being kept (F8) with **Clip Sound** on in the audio
+//! settings, the mixer copies every buffer it fills into an `AudioTap`. The
+//! tap is a lock-free ring of samples with one producer and one consumer: the
+//! audio thread only stores samples and moves the write index, so it never
+//! waits or allocates, and if the ring is full it drops samples rather than
+//! hold up the device. `AudioClip` is the consumer, on a writer thread of its
+//! own: it keeps the last `CLIP_SECONDS` of sound and writes them to a WAV
+//! file next to the clip's GIF when the clip is saved (F12).
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::time::Duration;
+use crate::constants::CLIP_SECONDS;
+use crate::wav::encode_wav;
+
+/// Samples the ring holds (a power of two, about 1.5 seconds at 44.1 kHz)
+const TAP_CAPACITY: usize = 1 << 16;
+
+/// How often the writer thread empties the ring
+const DRAIN_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Ring of mixed samples between the audio thread and a clip's writer thread
+pub struct AudioTap {
+    /// Sample bits (`f32::to_bits`), indexed by position modulo the capacity
+    samples: Box<[AtomicU32]>,
+    /// Samples ever written and read; only the audio thread moves `write`
+    /// and only the consumer moves `read`
+    write: AtomicUsize,
+    read: AtomicUsize,
+    /// Samples are only stored while a clip is being kept
+    enabled: AtomicBool,
+    /// Held by the consumer while it reads, so there is never a second one
+    reading: AtomicBool,
+    /// The mixer's sample rate
+    sample_rate: AtomicU32,
+}
+
+impl AudioTap {
+    pub fn new() -> Self {
+        Self {
+            samples: (0..TAP_CAPACITY).map(|_| AtomicU32::new(0)).collect(),
+            write: AtomicUsize::new(0),
+            read: AtomicUsize::new(0),
+            enabled: AtomicBool::new(false),
+            reading: AtomicBool::new(false),
+            sample_rate: AtomicU32::new(0),
+        }
+    }
+
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    pub fn sample_rate(&self) -> u32 {
+        self.sample_rate.load(Ordering::Relaxed)
+    }
+
+    /// Set by the mixer once the device's rate is known
+    pub fn set_sample_rate(&self, sample_rate: u32) {
+        self.sample_rate.store(sample_rate, Ordering::Relaxed);
+    }
+
+    /// Stores samples, as many as fit (audio thread only)
+    pub fn push(&self, samples: &[f32]) {
+        if !self.enabled.load(Ordering::Relaxed) {
+            return;
+        }
+        let write = self.write.load(Ordering::Relaxed);
+        let free = TAP_CAPACITY - write.wrapping_sub(self.read.load(Ordering::Acquire));
+        let count = samples.len().min(free);
+        for (i, sample) in samples[..count].iter().enumerate() {
+            self.samples[write.wrapping_add(i) & (TAP_CAPACITY - 1)].store(sample.to_bits(), Ordering::Relaxed);
+        }
+        self.write.store(write.wrapping_add(count), Ordering::Release);
+    }
+
+    /// Hands every stored sample to `take`, oldest first, and frees its place
+    ///
+    /// Does nothing while another consumer is reading.
+    pub fn drain(&self, mut take: impl FnMut(f32)) {
+        if self.reading.swap(true, Ordering::Acquire) {
+            return;
+        }
+        let read = self.read.load(Ordering::Relaxed);
+        let count = self.write.load(Ordering::Acquire).wrapping_sub(read);
+        for i in 0..count {
+            take(f32::from_bits(self.samples[read.wrapping_add(i) & (TAP_CAPACITY - 1)].load(Ordering::Relaxed)));
+        }
+        self.read.store(read.wrapping_add(count), Ordering::Release);
+        self.reading.store(false, Ordering::Release);
+    }
+}
+
+impl Default for AudioTap {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The last few seconds of sound, kept by a writer thread
+///
+/// The tap is on for as long as this lives.
+pub struct AudioClip {
+    tap: Arc<AudioTap>,
+    /// Where to write the sound kept so far
+    saves: Sender<PathBuf>,
+}
+
+impl AudioClip {
+    /// Turns the tap on and starts keeping its sound
+    pub fn start(tap: Arc<AudioTap>) -> Self {
+        // Sound from an earlier clip isn't this one's
+        tap.drain(|_| {});
+        tap.set_enabled(true);
+        let (saves, requests) = channel::<PathBuf>();
+        let thread_tap = tap.clone();
+        std::thread::spawn(move || {
+            let mut kept = VecDeque::new();
+            loop {
+                let request = match requests.recv_timeout(DRAIN_INTERVAL) {
+                    Ok(path) => Some(path),
+                    Err(RecvTimeoutError::Timeout) => None,
+                    Err(RecvTimeoutError::Disconnected) => break,
+                };
+                let sample_rate = thread_tap.sample_rate();
+                thread_tap.drain(|sample| kept.push_back(sample));
+                let limit = (sample_rate * CLIP_SECONDS) as usize;
+                if kept.len() > limit {
+                    kept.drain(..kept.len() - limit);
+                }
+                if let Some(path) = request {
+                    let wav = encode_wav(kept.make_contiguous(), sample_rate);
+                    if let Err(e) = std::fs::write(&path, wav) {
+                        eprintln!("Could not write {}: {}", path.display(), e);
+                    }
+                }
+            }
+        });
+        Self { tap, saves }
+    }
+
+    /// Writes the sound kept so far to a WAV file (on the writer thread)
+    pub fn save(&self, path: PathBuf) -> Result<(), String> {
+        self.saves.send(path).map_err(|_| "The clip sound writer stopped".to_string())
+    }
+}
+
+impl Drop for AudioClip {
+    fn drop(&mut self) {
+        self.tap.set_enabled(false);
+    }
+}
@@ -0,0 +1,159 @@
+//! Campaigns: several mazes played one after another as a single run
+//!
+//! A campaign is a text file in `campaigns/` under the asset directories (see
+//! `assets.rs`), offered on the maze select screen after the built-in mazes.
+//! Lives and score carry over from stage to stage, and clearing the last
+//! stage's maze wins the run. Each stage names its maze, a built-in one by
+//! number or a maze file (see `maze::load_maze_file`) next to the campaign,
+//! and may change how the stage plays:
+//!
+//! ```text
+//! name Grand Tour
+//! stage 1
+//! text The classic maze, to warm up.
+//! stage 2 level 4
+//! text The ghosts are faster from here on.
+//! stage tunnels.txt level 8 lives +1
+//! ```
+//!
+//! - `level <n>`: the stage plays at level n's speeds and timings (see
+//!   `level_table`); without it, a stage is one level past the one before
+//! - `lives +<n>`: extra lives when the stage starts
+//!
+//! `text` lines after a stage are shown on a card before the stage begins.
+//! Campaigns have no bonus stages.
+
+use std::path::Path;
+use std::sync::Arc;
+use crate::assets::list_assets;
+use crate::constants::{GRID_H, MAZE_1, MAZE_2};
+use crate::maze::load_maze_file;
+
+/// Asset subdirectory holding the campaign files
+const CAMPAIGN_DIR: &str = "campaigns";
+
+/// One maze of a campaign
+pub struct CampaignStage {
+    pub maze: &'static [&'static str; GRID_H as usize],
+    /// Level whose speeds and timings the stage plays at
+    pub level: u32,
+    /// Lives added when the stage starts
+    pub extra_lives: i32,
+    /// Shown on a card before the stage, if any
+    pub text: Option<String>,
+}
+
+/// A loaded campaign
+pub struct Campaign {
+    pub name: String,
+    /// Never empty
+    pub stages: Vec<CampaignStage>,
+}
+
+/// How far a run has got through its campaign
+#[derive(Clone)]
+pub struct CampaignRun {
+    pub campaign: Arc<Campaign>,
+    /// Index of the stage being played (the number of stages once the campaign is won)
+    pub stage: usize,
+}
+
+impl CampaignRun {
+    /// The stage being played, or None once the last one has been cleared
+    pub fn current(&self) -> Option<&CampaignStage> {
+        self.campaign.stages.get(self.stage)
+    }
+
+    /// Whether every stage has been cleared
+    pub fn finished(&self) -> bool {
+        self.stage >= self.campaign.stages.len()
+    }
+}
+
+/// Loads every campaign in the asset directories, sorted by file name
+///
+/// Campaigns that can't be read are reported and left out.
+pub fn load_campaigns() -> Vec<Arc<Campaign>> {
+    list_assets(CAMPAIGN_DIR, "txt")
+        .into_iter()
+        .filter_map(|path| match load_campaign(&path) {
+            Ok(campaign) => Some(Arc::new(campaign)),
+            Err(e) => {
+                eprintln!("Ignoring campaign {}: {}", path.display(), e);
+                None
+            }
+        })
+        .collect()
+}
+
+/// Loads a campaign file; maze files are looked up next to it
+pub fn load_campaign(path: &Path) -> Result<Campaign, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let fallback_name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    parse_campaign(&text, path.parent().unwrap_or(Path::new(".")), &fallback_name)
+}
+
+/// Parses a campaign (see the module documentation for the format)
+///
+/// # Arguments
+/// * `base_dir` - Directory maze files are relative to
+/// * `fallback_name` - Name used when the campaign has no `name` line
+fn parse_campaign(text: &str, base_dir: &Path, fallback_name: &str) -> Result<Campaign, String> {
+    let mut name = fallback_name.to_string();
+    let mut stages: Vec<CampaignStage> = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad_line = |reason: &str| format!("line {}: {}", line_index + 1, reason);
+        let (key, value) = line.split_once(' ').map(|(key, value)| (key, value.trim())).unwrap_or((line, ""));
+        match key {
+            "name" => name = value.to_string(),
+            "stage" => {
+                let previous_level = stages.last().map(|stage| stage.level).unwrap_or(0);
+                let stage = parse_stage(value, base_dir, previous_level + 1).map_err(|e| bad_line(&e))?;
+                stages.push(stage);
+            }
+            "text" => {
+                let stage = stages.last_mut().ok_or_else(|| bad_line("text before the first stage"))?;
+                stage.text = Some(match stage.text.take() {
+                    Some(text) => format!("{} {}", text, value),
+                    None => value.to_string(),
+                });
+            }
+            _ => return Err(bad_line(&format!("unknown key '{}'", key))),
+        }
+    }
+    if stages.is_empty() {
+        return Err("no stages".to_string());
+    }
+    Ok(Campaign { name, stages })
+}
+
+/// Parses the rest of a `stage` line: the maze, then any modifiers
+fn parse_stage(value: &str, base_dir: &Path, default_level: u32) -> Result<CampaignStage, String> {
+    let mut words = value.split_whitespace();
+    let maze = match words.next() {
+        Some("1") => &MAZE_1,
+        Some("2") => &MAZE_2,
+        Some(file) => load_maze_file(&base_dir.join(file))?,
+        None => return Err("stage without a maze".to_string()),
+    };
+    let mut stage = CampaignStage { maze, level: default_level, extra_lives: 0, text: None };
+    while let Some(modifier) = words.next() {
+        let number = words.next().ok_or_else(|| format!("'{}' without a value", modifier))?;
+        match modifier {
+            "level" => {
+                stage.level = number.parse().ok().filter(|&level| level >= 1)
+                    .ok_or_else(|| format!("bad level '{}'", number))?;
+            }
+            "lives" => {
+                stage.extra_lives = number.trim_start_matches('+').parse().ok().filter(|&lives| lives >= 0)
+                    .ok_or_else(|| format!("bad lives '{}'", number))?;
+            }
+            _ => return Err(format!("unknown modifier '{}'", modifier)),
+        }
+    }
+    Ok(stage)
+}
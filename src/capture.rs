@@ -0,0 +1,267 @@
+//! Gameplay clip capture
+//!
+//! While capture is on (F8), the last `CLIP_SECONDS` of gameplay are kept in
+//! memory as shrunk, palettized frames. Pressing F12 writes them to
+//! `saves/clips/` as a looping animated GIF so close calls can be shared
+//! without external recording software. With Clip Sound on, the last
+//! `CLIP_SECONDS` of the game's sound go next to it as a WAV file of the same
+//! name (see `audio_tap`).
+//!
+//! Frames use a fixed 3-3-2 RGB palette (256 colors), which reproduces the
+//! game's flat colors exactly or very closely, so no per-clip quantization is
+//! needed.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use sdl2::pixels::PixelFormatEnum;
+use crate::constants::{CLIP_FPS, CLIP_SECONDS, CLIP_MAX_WIDTH};
+use crate::audio_tap::{AudioClip, AudioTap};
+use crate::storage::save_path;
+
+/// Subdirectory of the save directory holding clips
+const CLIP_DIR: &str = "clips";
+
+/// Keeps the most recent frames for export
+pub struct ClipRecorder {
+    /// Palette indices of each frame, oldest first
+    frames: VecDeque<Vec<u8>>,
+    /// Size of the stored frames
+    width: u32,
+    height: u32,
+    /// When the last frame was stored
+    last_capture: Option<Instant>,
+    /// The sound of the clip, when it is kept
+    sound: Option<AudioClip>,
+}
+
+impl ClipRecorder {
+    /// Starts keeping frames
+    ///
+    /// # Arguments
+    /// * `sound` - The mixer's tap, to keep the sound too (None for silent clips)
+    pub fn new(sound: Option<Arc<AudioTap>>) -> Self {
+        Self {
+            frames: VecDeque::with_capacity((CLIP_FPS * CLIP_SECONDS) as usize),
+            width: 0,
+            height: 0,
+            last_capture: None,
+            sound: sound.map(AudioClip::start),
+        }
+    }
+
+    /// Stores the frame currently drawn on the canvas, at most `CLIP_FPS`
+    /// times a second
+    ///
+    /// Must be called before the frame is presented. A window size change
+    /// starts a new clip.
+    pub fn capture(&mut self, canvas: &sdl2::render::Canvas<sdl2::video::Window>) -> Result<(), String> {
+        let interval = Duration::from_secs(1) / CLIP_FPS;
+        if self.last_capture.is_some_and(|last| last.elapsed() < interval) {
+            return Ok(());
+        }
+        self.last_capture = Some(Instant::now());
+
+        let (window_w, window_h) = canvas.output_size()?;
+        if window_w == 0 || window_h == 0 {
+            return Ok(());
+        }
+        let width = window_w.min(CLIP_MAX_WIDTH);
+        let height = (window_h as u64 * width as u64 / window_w as u64).max(1) as u32;
+        if (width, height) != (self.width, self.height) {
+            self.frames.clear();
+            self.width = width;
+            self.height = height;
+        }
+
+        let pixels = canvas.read_pixels(None, PixelFormatEnum::RGB24)?;
+        let mut frame = Vec::with_capacity((width * height) as usize);
+        for y in 0..height {
+            let source_y = (y as u64 * window_h as u64 / height as u64) as usize;
+            for x in 0..width {
+                let source_x = (x as u64 * window_w as u64 / width as u64) as usize;
+                let i = (source_y * window_w as usize + source_x) * 3;
+                frame.push(palette_index(pixels[i], pixels[i + 1], pixels[i + 2]));
+            }
+        }
+
+        if self.frames.len() >= (CLIP_FPS * CLIP_SECONDS) as usize {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+        Ok(())
+    }
+
+    /// Writes the stored frames to a new GIF in the save directory, and the
+    /// sound (if kept) to a WAV file of the same name
+    ///
+    /// Encoding runs on background threads so the game does not stall; their
+    /// errors are printed.
+    ///
+    /// # Returns
+    /// The path the clip is being written to
+    pub fn save(&self) -> Result<PathBuf, String> {
+        if self.frames.is_empty() {
+            return Err("No frames captured yet".to_string());
+        }
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let path = save_path(CLIP_DIR, &format!("clip-{}.gif", stamp))
+            .map_err(|e| format!("Could not create clip: {}", e))?;
+
+        let frames: Vec<Vec<u8>> = self.frames.iter().cloned().collect();
+        let (width, height) = (self.width as u16, self.height as u16);
+        let thread_path = path.clone();
+        std::thread::spawn(move || {
+            let gif = encode_gif(&frames, width, height);
+            if let Err(e) = std::fs::write(&thread_path, gif) {
+                eprintln!("Could not write {}: {}", thread_path.display(), e);
+            }
+        });
+        if let Some(ref sound) = self.sound {
+            sound.save(path.with_extension("wav"))?;
+        }
+        Ok(path)
+    }
+}
+
+impl Default for ClipRecorder {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+/// Index of the nearest color in the 3-3-2 palette
+fn palette_index(r: u8, g: u8, b: u8) -> u8 {
+    (r & 0xE0) | ((g & 0xE0) >> 3) | (b >> 6)
+}
+
+/// Color of a 3-3-2 palette index, with each channel stretched to 0-255
+fn palette_color(index: u8) -> [u8; 3] {
+    let r = (index >> 5) as u32;
+    let g = ((index >> 2) & 0x07) as u32;
+    let b = (index & 0x03) as u32;
+    [(r * 255 / 7) as u8, (g * 255 / 7) as u8, (b * 255 / 3) as u8]
+}
+
+/// Builds a looping GIF89a from palettized frames played at `CLIP_FPS`
+fn encode_gif(frames: &[Vec<u8>], width: u16, height: u16) -> Vec<u8> {
+    let mut out = Vec::new();
+    let delay = (100 / CLIP_FPS) as u16;
+
+    // Header and logical screen with a 256-color global table
+    out.extend_from_slice(b"GIF89a");
+    out.extend_from_slice(&width.to_le_bytes());
+    out.extend_from_slice(&height.to_le_bytes());
+    out.extend_from_slice(&[0xF7, 0, 0]);
+    for index in 0..=255u8 {
+        out.extend_from_slice(&palette_color(index));
+    }
+
+    // Loop forever
+    out.extend_from_slice(&[0x21, 0xFF, 0x0B]);
+    out.extend_from_slice(b"NETSCAPE2.0");
+    out.extend_from_slice(&[0x03, 0x01, 0x00, 0x00, 0x00]);
+
+    for frame in frames {
+        // Graphic control extension (frame delay)
+        out.extend_from_slice(&[0x21, 0xF9, 0x04, 0x00]);
+        out.extend_from_slice(&delay.to_le_bytes());
+        out.extend_from_slice(&[0x00, 0x00]);
+
+        // Image descriptor covering the whole screen
+        out.push(0x2C);
+        out.extend_from_slice(&[0, 0, 0, 0]);
+        out.extend_from_slice(&width.to_le_bytes());
+        out.extend_from_slice(&height.to_le_bytes());
+        out.push(0x00);
+
+        out.push(8);
+        let data = lzw_compress(frame);
+        for block in data.chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0x00);
+    }
+
+    out.push(0x3B);
+    out
+}
+
+/// Packs variable-width codes least significant bit first
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, size: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// GIF-flavored LZW compression of 8-bit palette indices
+fn lzw_compress(indices: &[u8]) -> Vec<u8> {
+    const CLEAR: u16 = 256;
+    const END: u16 = 257;
+    const MAX_CODE: u16 = 4095;
+
+    let mut writer = BitWriter { bytes: Vec::new(), buffer: 0, bits: 0 };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = 9;
+    let mut last_code = END;
+    writer.write(CLEAR, code_size);
+
+    let mut current: Option<u16> = None;
+    for &index in indices {
+        let prefix = match current {
+            None => {
+                current = Some(index as u16);
+                continue;
+            }
+            Some(prefix) => prefix,
+        };
+        if let Some(&code) = table.get(&(prefix, index)) {
+            current = Some(code);
+            continue;
+        }
+
+        writer.write(prefix, code_size);
+        last_code += 1;
+        table.insert((prefix, index), last_code);
+        if last_code >= (1 << code_size) {
+            code_size += 1;
+        }
+        if last_code == MAX_CODE {
+            writer.write(CLEAR, code_size);
+            table.clear();
+            code_size = 9;
+            last_code = END;
+        }
+        current = Some(index as u16);
+    }
+
+    if let Some(prefix) = current {
+        writer.write(prefix, code_size);
+    }
+    writer.write(END, code_size);
+    writer.finish()
+}
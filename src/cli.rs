@@ -0,0 +1,220 @@
+//! Command-line options
+//!
+//! Lets testers, speedrunners and scripts start straight into a specific
+//! configuration instead of going through the menu:
+//!
+//! ```text
+//! paclike_2600_rs [--maze <path>] [--seed <n>] [--fullscreen] [--mute]
+//!                 [--replay <file>] [--load-state <file>] [--headless <ticks>]
+//!                 [--profile <file>] [--dev]
+//! paclike_2600_rs tournament [--pacman <styles>] [--ghosts <presets>]
+//!                 [--mazes <numbers>] [--seeds <n>] [--ticks <n>] [--csv]
+//! paclike_2600_rs bench [--ticks <n>] [--no-render]
+//! paclike_2600_rs golden [--update] [<replays or directories>]
+//! ```
+
+use std::path::PathBuf;
+use crate::bench::BenchOptions;
+use crate::golden::GoldenOptions;
+use crate::game_config::{GhostPreset, PacmanStyle};
+use crate::tournament::TournamentOptions;
+
+/// Usage text printed for `--help` and after a bad argument
+pub const USAGE: &str = "\
+Usage: paclike_2600_rs [options]
+       paclike_2600_rs tournament [tournament options]
+       paclike_2600_rs bench [bench options]
+       paclike_2600_rs golden [--update] [<replays or directories>]
+
+Options:
+  --maze <path>       Play a maze loaded from a text file (28x31 tiles)
+  --seed <n>          Seed for the random number generator (decimal or 0x hex)
+  --fullscreen        Start in desktop fullscreen
+  --mute              Start without music
+  --replay <file>     Play back an input recording (.tas) instead of the menu
+  --load-state <file> Start from a game state dumped with F4 (.json)
+  --headless <ticks>  Run the simulation without a window for the given number
+                      of ticks and print the final state
+  --profile <file>    Write tick and draw times of every frame to a CSV file
+  --dev               Reload assets and the --maze file as they are edited
+  --help              Show this text
+
+Tournament options (AI against AI, headless, results per pairing):
+  --pacman <styles>   AI Pac-Man styles, comma separated (default: all of
+                      greedy, cautious, speedrunner, troll)
+  --ghosts <presets>  Ghost presets, comma separated (default: all of
+                      classic, aggressive, random, coward)
+  --mazes <numbers>   Built-in mazes, comma separated (default: 1,2)
+  --seeds <n>         Games per pairing and maze, seeded 1 to n (default: 10)
+  --ticks <n>         Longest a game may run (default: 36000, ten minutes)
+  --csv               Print CSV instead of a table
+
+Bench options (a scripted AI game, timed):
+  --ticks <n>         Ticks to play (default: 10000)
+  --no-render         Only time the ticks, without drawing to a hidden window
+
+Golden replays (each .tas against the .golden file next to it; the
+recordings in tests/golden if none are given):
+  --update            Write the golden files from how the replays end now";
+
+/// Options given on the command line
+#[derive(Default)]
+pub struct CliOptions {
+    /// Maze file to play instead of the built-in mazes
+    pub maze: Option<PathBuf>,
+    /// RNG seed for new games
+    pub seed: Option<u16>,
+    pub fullscreen: bool,
+    pub mute: bool,
+    /// Recording to play back on startup
+    pub replay: Option<PathBuf>,
+    /// State dump to start from (see `state_dump`)
+    pub load_state: Option<PathBuf>,
+    /// Number of ticks to simulate without a window
+    pub headless: Option<u32>,
+    /// CSV file to write frame timings to
+    pub profile: Option<PathBuf>,
+    /// Dev mode: reload assets and the maze file as they change (see `hot_reload`)
+    pub dev: bool,
+    /// The `tournament` subcommand was given, with these options
+    pub tournament: Option<TournamentOptions>,
+    /// The `bench` subcommand was given, with these options
+    pub bench: Option<BenchOptions>,
+    /// The `golden` subcommand was given, with these options
+    pub golden: Option<GoldenOptions>,
+    /// `--help` was given
+    pub help: bool,
+}
+
+impl CliOptions {
+    /// Parses the arguments after the program name
+    ///
+    /// # Returns
+    /// The options, or a message naming the bad argument
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut options = CliOptions::default();
+        let mut args = args.into_iter().peekable();
+        if args.peek().map(String::as_str) == Some("tournament") {
+            args.next();
+            options.tournament = Some(parse_tournament(args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("bench") {
+            args.next();
+            options.bench = Some(parse_bench(args)?);
+            return Ok(options);
+        }
+        if args.peek().map(String::as_str) == Some("golden") {
+            args.next();
+            options.golden = Some(parse_golden(args));
+            return Ok(options);
+        }
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--maze" => options.maze = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--seed" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.seed = Some(parse_seed(&value).ok_or_else(|| format!("Bad seed: {}", value))?);
+                }
+                "--fullscreen" => options.fullscreen = true,
+                "--mute" => options.mute = true,
+                "--replay" => options.replay = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--load-state" => options.load_state = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--headless" => {
+                    let value = value_for(&arg, args.next())?;
+                    options.headless = Some(value.parse().map_err(|_| format!("Bad tick count: {}", value))?);
+                }
+                "--profile" => options.profile = Some(PathBuf::from(value_for(&arg, args.next())?)),
+                "--dev" => options.dev = true,
+                "--help" | "-h" => options.help = true,
+                _ => return Err(format!("Unknown argument: {}", arg)),
+            }
+        }
+        if options.replay.is_some() && options.load_state.is_some() {
+            return Err("--replay and --load-state can't be combined".to_string());
+        }
+        Ok(options)
+    }
+}
+
+/// Parses the arguments after `tournament`
+fn parse_tournament<I: Iterator<Item = String>>(mut args: I) -> Result<TournamentOptions, String> {
+    let mut options = TournamentOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pacman" => {
+                let value = value_for(&arg, args.next())?;
+                options.pacman_styles = parse_list(&value, PacmanStyle::from_name)
+                    .ok_or_else(|| format!("Bad Pac-Man styles: {}", value))?;
+            }
+            "--ghosts" => {
+                let value = value_for(&arg, args.next())?;
+                options.ghost_presets = parse_list(&value, GhostPreset::from_name)
+                    .ok_or_else(|| format!("Bad ghost presets: {}", value))?;
+            }
+            "--mazes" => {
+                let value = value_for(&arg, args.next())?;
+                options.mazes = parse_list(&value, |number| number.parse().ok())
+                    .ok_or_else(|| format!("Bad mazes: {}", value))?;
+            }
+            "--seeds" => {
+                let value = value_for(&arg, args.next())?;
+                options.seeds = value.parse().map_err(|_| format!("Bad seed count: {}", value))?;
+            }
+            "--ticks" => {
+                let value = value_for(&arg, args.next())?;
+                options.max_ticks = value.parse().map_err(|_| format!("Bad tick count: {}", value))?;
+            }
+            "--csv" => options.csv = true,
+            _ => return Err(format!("Unknown tournament argument: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Parses the arguments after `bench`
+fn parse_bench<I: Iterator<Item = String>>(mut args: I) -> Result<BenchOptions, String> {
+    let mut options = BenchOptions::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ticks" => {
+                let value = value_for(&arg, args.next())?;
+                options.ticks = value.parse().map_err(|_| format!("Bad tick count: {}", value))?;
+            }
+            "--no-render" => options.render = false,
+            _ => return Err(format!("Unknown bench argument: {}", arg)),
+        }
+    }
+    Ok(options)
+}
+
+/// Parses the arguments after `golden`
+fn parse_golden<I: Iterator<Item = String>>(args: I) -> GoldenOptions {
+    let mut options = GoldenOptions::default();
+    for arg in args {
+        match arg.as_str() {
+            "--update" => options.update = true,
+            _ => options.paths.push(PathBuf::from(arg)),
+        }
+    }
+    options
+}
+
+/// Parses a comma-separated list, None if any item is bad or there are none
+fn parse_list<T>(text: &str, parse: impl Fn(&str) -> Option<T>) -> Option<Vec<T>> {
+    let items: Option<Vec<T>> = text.split(',').map(|item| parse(item.trim())).collect();
+    items.filter(|items| !items.is_empty())
+}
+
+/// Returns the value following a flag, or an error if it is missing
+fn value_for(flag: &str, value: Option<String>) -> Result<String, String> {
+    value.ok_or_else(|| format!("{} needs a value", flag))
+}
+
+/// Parses a seed in decimal or `0x` hex
+fn parse_seed(text: &str) -> Option<u16> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u16::from_str_radix(hex, 16).ok(),
+        None => text.parse().ok(),
+    }
+}
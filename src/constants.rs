@@ -21,49 +21,115 @@ pub const VIEW_H: i32 = GRID_H * TILE;
 /// Height of the score area at the top of the screen in pixels
 pub const SCORE_AREA: i32 = 30;
 
-/// Target frames per second
+/// Default simulation tick rate, and the rate every duration in ticks is
+/// given at: the constants here and the level table count 60ths of a second,
+/// and games at another tick rate scale them (see `GameConfig::ticks`)
 pub const FPS: u32 = 60;
 
-/// Delta time (time per frame) in seconds
-pub const DT: f64 = 1.0 / FPS as f64;
+/// Simulation tick rates offered in the gameplay settings (see `GameConfig::tick_rate`)
+pub const TICK_RATES: [u32; 3] = [30, 60, 120];
 
 /// Scale factor for window size (makes window 4x larger than game view)
 pub const WINDOW_SCALE: i32 = 4;
 
-/// Row number where tunnels wrap around (0-indexed, row 14)
-pub const TUNNEL_ROW: i32 = 14;
+/// Window scale factors offered in the video settings (windowed sizes)
+pub const WINDOW_SCALES: [i32; 4] = [2, 3, 4, 5];
 
 // ============================================================================
 // Player Constants
 // ============================================================================
 
-/// Player's starting X position (center of maze)
+/// Player's starting X position on mazes without a 'P' tile (see `maze::spawn_points`)
 pub const PLAYER_START_X: i32 = 13;
 
 /// Player's starting Y position
 pub const PLAYER_START_Y: i32 = 23;
 
-/// Number of sub-frames before player moves (controls speed: higher = slower)
-/// Player moves every 5 sub-frames, making them 20% slower than original
-pub const PLAYER_MOVE_SUBFRAMES: i32 = 5;
+/// Second player's starting X position (co-op mode) on mazes without a second 'P' tile
+pub const PLAYER2_START_X: i32 = 14;
+
+/// Second player's starting Y position (co-op mode)
+pub const PLAYER2_START_Y: i32 = 23;
+
+/// Number of lives at the start of a run (shared by both players in co-op)
+pub const STARTING_LIVES: i32 = 3;
+
+/// Default cornering window in ticks: how early or late a turn at a junction
+/// may be input and still cut the corner (0 turns cornering off)
+pub const CORNERING_WINDOW: i32 = 2;
+
+/// Largest cornering window the gameplay settings offer
+pub const MAX_CORNERING_WINDOW: i32 = 4;
 
 // ============================================================================
 // Ghost Constants
 // ============================================================================
 
-/// Ghost's starting X position (center of maze)
+/// Middle ghost's starting X position on mazes without 'G' tiles (the
+/// others start to its left and right); eaten ghosts go back to their start
 pub const GHOST_START_X: i32 = 13;
 
 /// Ghost's starting Y position
 pub const GHOST_START_Y: i32 = 14;
 
-/// Number of sub-frames before ghost moves (controls speed: higher = slower)
-/// Ghosts move every 6 sub-frames, making them 20% slower than original
-pub const GHOST_MOVE_SUBFRAMES: i32 = 6;
-
 /// Number of frames between ghost AI decisions
 pub const GHOST_THINK_INTERVAL: i32 = 8;
 
+/// Tiles ahead of Pac-Man the pink ghost aims for
+pub const PINK_GHOST_LOOKAHEAD: i32 = 4;
+
+/// Tiles ahead of Pac-Man the cyan ghost mirrors the red ghost's position through
+pub const CYAN_GHOST_LOOKAHEAD: i32 = 2;
+
+/// Ticks an emote stays above the ghost (one second)
+pub const EMOTE_TICKS: i32 = FPS as i32;
+
+/// Tiles from Pac-Man within which a coward ghost breaks off for its corner
+pub const COWARD_GHOST_DISTANCE: i32 = 8;
+
+/// Tiles the coward ghosts retreat towards (red, pink, cyan): top right, top left, bottom right
+pub const COWARD_GHOST_CORNERS: [(i32, i32); 3] = [(GRID_W - 2, 1), (1, 1), (GRID_W - 2, GRID_H - 2)];
+
+/// Steps ahead of every dangerous ghost the AI Pac-Man must be to count a tile as safe
+pub const AI_PACMAN_SAFE_MARGIN: i32 = 2;
+
+/// Safe margin of the cautious AI Pac-Man
+pub const AI_PACMAN_CAUTIOUS_MARGIN: i32 = 4;
+
+/// Safe margin of the speedrunning AI Pac-Man, who cuts it fine to save time
+pub const AI_PACMAN_SPEEDRUN_MARGIN: i32 = 1;
+
+/// Steps from the nearest ghost beyond which the troll AI Pac-Man goes back to tease it
+pub const AI_PACMAN_TROLL_DISTANCE: i32 = 6;
+
+/// Tiles around a pellet whose pellets make it more attractive to the AI Pac-Man
+pub const AI_PACMAN_CLUSTER_RADIUS: i32 = 2;
+
+/// Tiles further the AI Pac-Man will walk for each other pellet around the one he goes for
+pub const AI_PACMAN_CLUSTER_WEIGHT: i32 = 1;
+
+/// Steps of power time the AI Pac-Man keeps in hand when hunting frightened ghosts
+pub const AI_PACMAN_FRIGHT_MARGIN: i32 = 4;
+
+/// Pellets each ghost waits for in the ghost house before it is let out, per level
+/// 
+/// Row 0 is level 1; the last row is used for every later level. The three
+/// ghosts leave in the order (and after the counts) of the arcade's Pinky,
+/// Inky and Clyde. Only the first ghost still inside counts pellets.
+pub const GHOST_DOT_LIMITS: [[u32; 3]; 3] = [
+    [0, 30, 60],
+    [0, 0, 50],
+    [0, 0, 0],
+];
+
+/// After a lost life one counter for all ghosts decides instead: each ghost
+/// leaves once this many pellets have been eaten since the death. Reaching the
+/// last count hands back to the ghosts' own counters.
+pub const GHOST_GLOBAL_DOT_LIMITS: [u32; 3] = [7, 17, 32];
+
+/// Ticks without a pellet eaten after which the next ghost leaves the house anyway (4 seconds)
+pub const GHOST_RELEASE_TIMEOUT: i32 = 240;
+
 // ============================================================================
 // Scoring Constants
 // ============================================================================
@@ -78,11 +144,119 @@ pub const SCORE_POWER_PELLET: i32 = 50;
 /// First ghost: 200, Second: 400, Third: 800, Fourth: 1600
 pub const SCORE_GHOST: [i32; 4] = [200, 400, 800, 1600];
 
-/// Duration of power pellet effect in frames (900 frames = ~15 seconds at 60 FPS)
-pub const POWER_PELLET_DURATION: i32 = 900;
+/// Scores at which an extra life is awarded, in increasing order
+pub const EXTRA_LIFE_SCORES: [i32; 3] = [10_000, 50_000, 100_000];
+
+/// Length in frames of one white-and-blue flash of the frightened ghosts; the
+/// number of flashes before they recover comes from the level table
+pub const FRIGHT_FLASH_FRAMES: i32 = 24;
+
+// ============================================================================
+// Bonus Fruit Constants
+// ============================================================================
+
+/// Pellets eaten on a level at which its bonus fruit appears (see `fruit`)
+pub const FRUIT_PELLETS: [i32; 2] = [70, 170];
+
+/// Shortest time a bonus fruit stays in the maze in ticks (9 seconds)
+pub const FRUIT_TICKS: i32 = 540;
+
+/// Most extra ticks a bonus fruit may randomly stay on top of `FRUIT_TICKS`
+pub const FRUIT_EXTRA_TICKS: i32 = 60;
+
+/// Bonus fruit tile on mazes without an 'F' tile, just below the ghost house
+pub const FRUIT_X: i32 = 13;
+pub const FRUIT_Y: i32 = 17;
+
+/// How many of the fruit eaten last the HUD shows
+pub const FRUIT_HISTORY: usize = 7;
+
+// ============================================================================
+// Double Points Constants
+// ============================================================================
+
+/// Pellets eaten on a level at which the double-points pickup appears, on
+/// player 1's starting tile (close enough together that the second can be
+/// taken while the first still doubles)
+pub const DOUBLE_POINTS_PELLETS: [i32; 2] = [100, 150];
+
+/// How long the double-points pickup stays in the maze in ticks (10 seconds)
+pub const DOUBLE_POINTS_PICKUP_TICKS: i32 = 600;
+
+/// How long points are doubled after the pickup in ticks (10 seconds); taking
+/// another while they are starts this time over rather than doubling again
+pub const DOUBLE_POINTS_TICKS: i32 = 600;
+
+/// What points are multiplied by while doubled
+pub const DOUBLE_POINTS_MULTIPLIER: i32 = 2;
+
+// ============================================================================
+// Bomb Constants
+// ============================================================================
+
+/// Pellets eaten on a level at which a bomb may appear on the bonus fruit's tile
+pub const BOMB_PELLETS: i32 = 130;
 
-/// Number of frames before power pellet expires when it starts flashing
-pub const POWER_PELLET_FLASH_START: i32 = 120;
+/// A bomb appears on one level in this many (on average)
+pub const BOMB_ODDS: i32 = 4;
+
+/// How long a bomb stays in the maze in ticks (10 seconds)
+pub const BOMB_PICKUP_TICKS: i32 = 600;
+
+/// How long Pac-Man must push against a wall to blow it up with a bomb in ticks (half a second)
+pub const BOMB_FUSE_TICKS: i32 = 30;
+
+/// How long a blown-up wall stays open in ticks (15 seconds); it heals once
+/// nobody stands on it
+pub const BOMB_HOLE_TICKS: i32 = 900;
+
+/// Most walls that can be open at once
+pub const MAX_OPEN_WALLS: usize = 4;
+
+/// New game plus: ticks between two pellet respawns (20 seconds)
+pub const NG_PLUS_RESPAWN_TICKS: i32 = 1200;
+
+/// New game plus: one eaten pellet in this many comes back at each respawn
+/// (at least one)
+pub const NG_PLUS_RESPAWN_SHARE: usize = 10;
+
+// ============================================================================
+// Bonus Stage Constants
+// ============================================================================
+
+/// A bonus stage is played after every level that is a multiple of this value
+pub const BONUS_STAGE_INTERVAL: u32 = 3;
+
+/// Duration of the bonus stage in frames (1200 frames = 20 seconds at 60 FPS)
+pub const BONUS_STAGE_DURATION: i32 = 1200;
+
+/// How long the bonus results card stays on screen in frames (3 seconds)
+pub const BONUS_RESULTS_DURATION: i32 = 180;
+
+// ============================================================================
+// Maze Color Themes
+// ============================================================================
+
+/// Wall and pellet colors (RGB) of a maze color theme
+pub struct MazePalette {
+    /// Display name
+    pub name: &'static str,
+    pub wall: (u8, u8, u8),
+    pub pellet: (u8, u8, u8),
+}
+
+/// Maze color theme for each level
+/// 
+/// Level 1 uses the first entry; after the last one the table starts over.
+/// A player can also keep one theme for every level (see `unlocks`).
+pub const MAZE_PALETTES: [MazePalette; 6] = [
+    MazePalette { name: "Blue", wall: (0, 100, 255), pellet: (255, 255, 255) },
+    MazePalette { name: "Green", wall: (0, 170, 70), pellet: (255, 255, 160) },
+    MazePalette { name: "Pink", wall: (255, 100, 180), pellet: (255, 255, 255) },
+    MazePalette { name: "Grey", wall: (150, 150, 150), pellet: (255, 200, 120) },
+    MazePalette { name: "Orange", wall: (230, 120, 0), pellet: (255, 255, 255) },
+    MazePalette { name: "Purple", wall: (140, 60, 220), pellet: (180, 255, 180) },
+];
 
 // ============================================================================
 // Animation Constants
@@ -104,8 +278,74 @@ pub const POWER_PELLET_FLASH_SPEED: u32 = 15;
 /// Maximum time step to prevent spiral of death (0.25 seconds)
 pub const MAX_TIME_STEP: f64 = 0.25;
 
-/// Sleep duration in milliseconds to reduce CPU usage when vsync is off
-pub const SLEEP_DURATION_MS: u64 = 1;
+/// Game speed while Tab is held in a practice run or playtest
+pub const FAST_FORWARD_SPEED: f64 = 4.0;
+
+/// Game speed while the key left of 1 (`) is held in a practice run or playtest
+pub const SLOW_MOTION_SPEED: f64 = 0.25;
+
+/// Frame rate caps offered in the video settings when vsync is off (0 = unlimited)
+pub const FPS_CAPS: [u32; 5] = [30, 60, 120, 144, 0];
+
+/// Default frame rate cap (index into FPS_CAPS)
+pub const DEFAULT_FPS_CAP_INDEX: usize = 1;
+
+/// Frames per second drawn in the menus, where only the attract demo moves
+/// (input wakes the main loop at once; see `App::idle_period`)
+pub const MENU_FPS: u32 = 30;
+
+/// Frames per second drawn while nothing moves (paused, game over, editor)
+pub const PAUSED_FPS: u32 = 10;
+
+/// Frames per second while the window is minimized and the game isn't running
+pub const MINIMIZED_FPS: u32 = 4;
+
+/// Render scales offered in the video settings, in percent of the window's
+/// resolution the game is drawn at (see `render::draw_scaled`)
+pub const RENDER_SCALES: [u32; 4] = [100, 75, 50, 25];
+
+/// How long before a frame deadline the limiter stops sleeping and spins instead
+/// (OS sleeps can overshoot by a millisecond or more)
+pub const FRAME_LIMITER_SPIN_MS: u64 = 2;
+
+/// Ticks the board stays frozen after a life is lost (1 second)
+pub const DEATH_FREEZE_TICKS: i32 = 60;
+
+/// Ticks the board stays frozen after a maze is cleared (2 seconds)
+pub const LEVEL_COMPLETE_TICKS: i32 = 120;
+
+/// Ticks a campaign stage's text card stays up unless skipped (5 seconds)
+pub const STAGE_CARD_TICKS: i32 = 300;
+
+/// Opacity of the black layer between the maze and a campaign stage card (out of 255)
+pub const STAGE_CARD_DIM_ALPHA: u8 = 200;
+
+/// The attract demo behind the main menu advances one game tick every this many
+/// ticks (it plays at a relaxed pace under the menu text)
+pub const DEMO_TICK_INTERVAL: u32 = 2;
+
+/// Opacity of the black layer between the attract demo and the menu text (out of 255)
+pub const DEMO_DIM_ALPHA: u8 = 190;
+
+// ============================================================================
+// Input Constants
+// ============================================================================
+
+/// Analog stick deflection (out of 32767) needed before it counts as a direction
+pub const STICK_DEADZONE: i16 = 12000;
+
+// ============================================================================
+// Clip Capture Constants
+// ============================================================================
+
+/// Frames per second stored in captured clips
+pub const CLIP_FPS: u32 = 25;
+
+/// Length of the clip kept in memory while capturing, in seconds
+pub const CLIP_SECONDS: u32 = 6;
+
+/// Clips are shrunk to at most this width in pixels (keeps files small)
+pub const CLIP_MAX_WIDTH: u32 = 336;
 
 // Maze 1: Original Atari 2600 layout
 pub static MAZE_1: [&str; GRID_H as usize] = [
@@ -0,0 +1,67 @@
+//! cpal audio backend (the `cpal-audio` feature)
+//!
+//! Plays the mixer through the system's default output device without SDL,
+//! at the device's own sample rate and channel count: the mono mix is copied
+//! to every channel. Used by builds without `sdl-audio`.
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, Stream};
+use crate::audio::{AudioBackend, Mixer};
+
+/// Plays audio through cpal
+#[derive(Default)]
+pub struct CpalAudio {
+    stream: Option<Stream>,
+}
+
+impl CpalAudio {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl AudioBackend for CpalAudio {
+    fn start(&mut self, make_mixer: Box<dyn FnOnce(i32) -> Mixer + Send>) -> Result<(), String> {
+        let device = cpal::default_host()
+            .default_output_device()
+            .ok_or("No audio output device")?;
+        let supported = device.default_output_config().map_err(|e| e.to_string())?;
+        let format = supported.sample_format();
+        let config: cpal::StreamConfig = supported.into();
+        let mixer = make_mixer(config.sample_rate.0 as i32);
+
+        let stream = match format {
+            SampleFormat::F32 => build_stream::<f32>(&device, &config, mixer),
+            SampleFormat::I16 => build_stream::<i16>(&device, &config, mixer),
+            SampleFormat::U16 => build_stream::<u16>(&device, &config, mixer),
+            other => return Err(format!("Unsupported audio sample format {}", other)),
+        }?;
+        stream.play().map_err(|e| e.to_string())?;
+        self.stream = Some(stream);
+        Ok(())
+    }
+}
+
+/// Opens an output stream of samples `T` that fills every channel from the mixer
+fn build_stream<T>(device: &cpal::Device, config: &cpal::StreamConfig, mut mixer: Mixer) -> Result<Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels.max(1) as usize;
+    // The mono mix, reused between callbacks
+    let mut mono: Vec<f32> = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |out: &mut [T], _: &cpal::OutputCallbackInfo| {
+                mono.resize(out.len() / channels, 0.0);
+                mixer.fill(&mut mono);
+                for (frame, &sample) in out.chunks_mut(channels).zip(&mono) {
+                    frame.fill(T::from_sample(sample));
+                }
+            },
+            |e| eprintln!("Audio stream error: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
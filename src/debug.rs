@@ -0,0 +1,206 @@
+//! Debug overlay (toggled with F3)
+//!
+//! Shows the numbers needed when tuning the AI and the renderer:
+//! - Frames per second, and rolling average, 95th percentile and worst tick
+//!   and draw times (see `profile`)
+//! - Tile position and heading of every Pac-Man and ghost
+//! - Each ghost's state and chase target, also marked over the maze along
+//!   with the tile of its next turn
+//! - The power pellet timer, frame counter and LFSR state
+//! - Heap allocations made while drawing the last frame, counted by
+//!   `CountingAllocator` (the game binary's global allocator); drawing a
+//!   game should make none once the maze is cached
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use crate::constants::TILE;
+use crate::font::{draw_lines, line_height, text_width, Align};
+use crate::game::{Game, GamePhase};
+use crate::profile::{FrameProfiler, SpanStats};
+use crate::render::{RenderCache, GHOST_COLORS};
+
+/// Scale of the overlay text
+const TEXT_SCALE: i32 = 1;
+
+/// Heap allocations made so far (see `CountingAllocator`)
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+
+/// The system allocator, counting allocations for the overlay
+///
+/// Installed with `#[global_allocator]`; without it `allocations` stays at 0.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc_zeroed(layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+/// Heap allocations made since the start, by every thread
+pub fn allocations() -> u64 {
+    ALLOCATIONS.load(Ordering::Relaxed)
+}
+
+/// Frame rate and frame timing measured by the main loop
+pub struct DebugStats {
+    /// Frames presented during the last full second
+    pub fps: u32,
+    /// Tick and draw timings
+    pub profile: FrameProfiler,
+    /// Heap allocations made while the last frame's game was drawn
+    pub draw_allocations: u64,
+    /// Frames presented so far in the current second
+    frames: u32,
+    /// Start of the current second
+    second_start: Instant,
+}
+
+impl DebugStats {
+    pub fn new() -> Self {
+        Self {
+            fps: 0,
+            profile: FrameProfiler::new(),
+            draw_allocations: 0,
+            frames: 0,
+            second_start: Instant::now(),
+        }
+    }
+
+    /// Counts a presented frame, updating `fps` once a second
+    pub fn frame_presented(&mut self) {
+        self.profile.end_frame();
+        self.frames += 1;
+        let elapsed = self.second_start.elapsed();
+        if elapsed >= Duration::from_secs(1) {
+            self.fps = (self.frames as f64 / elapsed.as_secs_f64()).round() as u32;
+            self.frames = 0;
+            self.second_start = Instant::now();
+        }
+    }
+}
+
+impl Default for DebugStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Letter for a heading: U, D, L, R, or - when standing still
+fn heading_name(dx: i32, dy: i32) -> char {
+    match (dx.signum(), dy.signum()) {
+        (0, -1) => 'U',
+        (0, 1) => 'D',
+        (-1, 0) => 'L',
+        (1, 0) => 'R',
+        _ => '-',
+    }
+}
+
+/// Average, 95th percentile and worst time of a span in milliseconds
+fn span_line(name: &str, stats: &SpanStats) -> String {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    format!(
+        "{} AVG {:.2} P95 {:.2} MAX {:.2}",
+        name, ms(stats.average()), ms(stats.percentile(95)), ms(stats.percentile(100))
+    )
+}
+
+/// Builds the overlay text, one entry per line
+fn overlay_lines(game: &Game, stats: &DebugStats) -> Vec<String> {
+    let mut lines = vec![
+        format!("FPS {}", stats.fps),
+        span_line("TICK", &stats.profile.tick),
+        span_line("DRAW", &stats.profile.draw),
+        format!("ALLOCS/FRAME {}", stats.draw_allocations),
+        format!("FRAME {}  LEVEL {}", game.frame, game.level),
+    ];
+
+    for (i, player) in game.players.iter().enumerate() {
+        lines.push(format!("P{} {},{} {}", i + 1, player.x, player.y, heading_name(player.dx, player.dy)));
+    }
+
+    for (i, ghost) in game.ghosts.iter().enumerate() {
+        let state = if game.player_ghost_index == Some(i) {
+            "PLAYER"
+        } else if ghost.in_house {
+            "HOUSE"
+        } else if ghost.vulnerable {
+            "FRIGHT"
+        } else {
+            "CHASE"
+        };
+        lines.push(format!(
+            "G{} {},{} {} {} T{},{}",
+            i + 1, ghost.x, ghost.y, heading_name(ghost.dx, ghost.dy), state, ghost.target.0, ghost.target.1
+        ));
+    }
+
+    lines.push(format!("POWER {}  EATEN {}", game.power_pellet_timer, game.ghost_eaten_count));
+    lines.push(format!("PELLETS {}  RNG {:04X}", game.pellets, game.rng.state()));
+    lines
+}
+
+/// Marks each AI ghost's target tile (outline, joined to the ghost by a line)
+/// and the tile of its next turn (filled square) in the ghost's color
+pub fn draw_ghost_markers(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, cache: &RenderCache, game: &Game) {
+    if game.phase != GamePhase::Playing {
+        return;
+    }
+    let tile_center = |x: i32, y: i32| cache.to_screen(x * TILE + TILE / 2, y * TILE + TILE / 2, 1, 1).top_left();
+    
+    for (i, ghost) in game.ghosts.iter().enumerate() {
+        if game.player_ghost_index == Some(i) {
+            continue;
+        }
+        canvas.set_draw_color(GHOST_COLORS[i % GHOST_COLORS.len()]);
+        
+        let (target_x, target_y) = ghost.target;
+        let _ = canvas.draw_rect(cache.to_screen(target_x * TILE, target_y * TILE, TILE, TILE));
+        let _ = canvas.draw_line(tile_center(ghost.x, ghost.y), tile_center(target_x, target_y));
+        
+        if let Some((turn_x, turn_y)) = ghost.next_turn() {
+            let _ = canvas.fill_rect(cache.to_screen(turn_x * TILE + 1, turn_y * TILE + 1, TILE - 2, TILE - 2));
+        }
+    }
+}
+
+/// Draws the ghost markers over the maze, then the overlay text in the
+/// top-left corner of the window on a dimmed backdrop
+pub fn draw_debug_overlay(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    game: &Game,
+    stats: &DebugStats,
+) {
+    draw_ghost_markers(canvas, cache, game);
+    
+    let lines = overlay_lines(game, stats);
+    let padding = 4;
+    let width = lines.iter().map(|line| text_width(line, TEXT_SCALE)).max().unwrap_or(0);
+    let height = lines.len() as i32 * line_height(TEXT_SCALE);
+
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
+    let _ = canvas.fill_rect(Rect::new(0, 0, (width + padding * 2) as u32, (height + padding * 2) as u32));
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+
+    draw_lines(canvas, &lines, padding, padding, TEXT_SCALE, Color::RGB(0, 255, 0), Align::Left);
+}
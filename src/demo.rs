@@ -0,0 +1,46 @@
+//! Attract demo: a game nobody plays, shown behind the main menu
+//!
+//! Pac-Man and the ghosts are all AI-controlled (`GameMode::Demo`), so the
+//! demo is the same simulation the headless mode runs, only slowed down to
+//! one game tick every `DEMO_TICK_INTERVAL` ticks. When the AI Pac-Man runs
+//! out of lives a new demo starts, seeded from where the last one ended so
+//! no two rounds play the same.
+//!
+//! The seed decides the whole round, the AI Pac-Man's `PacmanStyle`
+//! included, so successive demos show off different styles. Nothing here
+//! makes sound or counts towards progress: the application draws the game
+//! dimmed under the menu and ignores its events.
+
+use crate::constants::DEMO_TICK_INTERVAL;
+use crate::game::Game;
+use crate::game_config::{GameConfig, GameMode, PacmanStyle, PlayerRole};
+
+pub struct AttractDemo {
+    pub game: Game,
+    /// Ticks since the game last advanced (see `DEMO_TICK_INTERVAL`)
+    slow_ticks: u32,
+}
+
+impl AttractDemo {
+    /// Starts a demo on the current maze
+    pub fn new(seed: u16) -> Self {
+        let mut config = GameConfig::new(GameMode::Demo, PlayerRole::PacMan, None);
+        config.pacman_style = PacmanStyle::ALL[seed as usize % PacmanStyle::ALL.len()];
+        Self { game: Game::new_with_seed(config, seed), slow_ticks: 0 }
+    }
+
+    /// Advances the demo by fixed-timestep ticks, starting over after a game over
+    pub fn update(&mut self, ticks: u32) {
+        for _ in 0..ticks {
+            self.slow_ticks += 1;
+            if self.slow_ticks < DEMO_TICK_INTERVAL {
+                continue;
+            }
+            self.slow_ticks = 0;
+            if !self.game.alive {
+                *self = AttractDemo::new(self.game.rng.state());
+            }
+            self.game.tick(None, None);
+        }
+    }
+}
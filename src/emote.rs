@@ -0,0 +1,41 @@
+//! Quick emotes a human ghost player can show above their ghost
+//!
+//! Emotes are cosmetic: they never enter the simulation, recordings or saves.
+//! Online they travel with the input messages (see `NetSession::queue_emote`),
+//! so the other player sees them a few ticks later.
+
+/// An emote, shown for `EMOTE_TICKS` in a bubble above the ghost
+#[derive(Clone, Copy, PartialEq)]
+pub enum Emote {
+    Taunt,
+    GoodGame,
+    Oops,
+}
+
+impl Emote {
+    /// Every emote, in key order (see `settings::EMOTE_KEYS`)
+    pub const ALL: [Emote; 3] = [Emote::Taunt, Emote::GoodGame, Emote::Oops];
+
+    /// Text drawn in the bubble
+    pub fn label(self) -> &'static str {
+        match self {
+            Emote::Taunt => ":P",
+            Emote::GoodGame => "GG",
+            Emote::Oops => "OOPS",
+        }
+    }
+
+    /// Byte sent over the network (0 means no emote)
+    pub fn code(self) -> u8 {
+        match self {
+            Emote::Taunt => 1,
+            Emote::GoodGame => 2,
+            Emote::Oops => 3,
+        }
+    }
+
+    /// The emote sent as a byte, if any
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.into_iter().find(|emote| emote.code() == code)
+    }
+}
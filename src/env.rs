@@ -0,0 +1,247 @@
+//! Gym-style environment for reinforcement learning
+//!
+//! Wraps a single-player game so an agent can control Pac-Man with the real
+//! game rules, one step at a time:
+//!
+//! ```ignore
+//! let mut env = Env::new(EnvConfig::default());
+//! let mut observation = env.reset(0xACE1);
+//! loop {
+//!     let action = agent.act(&observation);
+//!     let (next, reward, done) = env.step(action);
+//!     agent.learn(&observation, action, reward, &next, done);
+//!     observation = next;
+//!     if done { break; }
+//! }
+//! ```
+//!
+//! Rewards come from the events of each tick, weighted by `RewardShaping`.
+//! Nothing here opens a window or an audio device.
+
+use crate::constants::{GRID_W, GRID_H};
+use crate::game::{Game, GameEvent};
+use crate::game_config::{GameConfig, GameMode, PlayerRole};
+use crate::maze::{is_wall, is_pellet, is_power_pellet, set_active_floor};
+
+/// Move chosen by the agent for one step
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    /// Keep going (no new input)
+    Stay,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Action {
+    /// Every action, in index order
+    pub const ALL: [Action; 5] = [Action::Stay, Action::Up, Action::Down, Action::Left, Action::Right];
+
+    /// Action for a discrete action index (0-4), as used by most RL libraries
+    pub fn from_index(index: usize) -> Option<Action> {
+        Action::ALL.get(index).copied()
+    }
+
+    /// Direction given to the game (None keeps going)
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            Action::Stay => None,
+            Action::Up => Some((0, -1)),
+            Action::Down => Some((0, 1)),
+            Action::Left => Some((-1, 0)),
+            Action::Right => Some((1, 0)),
+        }
+    }
+}
+
+/// Reward for each kind of event
+#[derive(Clone, Copy, Debug)]
+pub struct RewardShaping {
+    pub pellet: f32,
+    pub power_pellet: f32,
+    /// For each ghost eaten (regardless of its points)
+    pub ghost_eaten: f32,
+    /// For each bonus fruit eaten (regardless of its points)
+    pub fruit_eaten: f32,
+    /// For each life lost
+    pub death: f32,
+    pub level_cleared: f32,
+    /// Added every tick (negative values push the agent to hurry)
+    pub per_tick: f32,
+}
+
+impl Default for RewardShaping {
+    fn default() -> Self {
+        Self {
+            pellet: 1.0,
+            power_pellet: 1.0,
+            ghost_eaten: 20.0,
+            fruit_eaten: 10.0,
+            death: -100.0,
+            level_cleared: 0.0,
+            per_tick: 0.0,
+        }
+    }
+}
+
+/// Environment settings
+#[derive(Clone, Copy, Debug)]
+pub struct EnvConfig {
+    pub rewards: RewardShaping,
+    /// Game ticks per step, all with the same action
+    pub ticks_per_step: u32,
+    /// Steps before an episode is cut off (0 = no limit)
+    pub max_steps: u32,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        Self {
+            rewards: RewardShaping::default(),
+            ticks_per_step: 4,
+            max_steps: 0,
+        }
+    }
+}
+
+/// Contents of a maze tile in an observation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum Tile {
+    Empty = 0,
+    Wall = 1,
+    Pellet = 2,
+    PowerPellet = 3,
+}
+
+/// A ghost in an observation
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct GhostObservation {
+    pub x: i32,
+    pub y: i32,
+    /// Direction of travel
+    pub dx: i32,
+    pub dy: i32,
+    /// Can be eaten
+    pub frightened: bool,
+}
+
+/// What the agent sees after each step
+#[derive(Clone, PartialEq, Debug)]
+pub struct Observation {
+    /// Maze tiles, row by row (`GRID_W` x `GRID_H`), of the floor Pac-Man is on
+    pub tiles: Vec<Tile>,
+    /// Pac-Man's tile and direction of travel
+    pub player: (i32, i32),
+    pub player_direction: (i32, i32),
+    pub ghosts: Vec<GhostObservation>,
+    /// Ticks left on the power pellet effect
+    pub power_timer: i32,
+    pub lives: i32,
+    pub score: i32,
+}
+
+impl Observation {
+    fn from_game(game: &Game) -> Self {
+        let mut tiles = Vec::with_capacity((GRID_W * GRID_H) as usize);
+        let floor = game.view_floor();
+        set_active_floor(floor);
+        for y in 0..GRID_H {
+            for x in 0..GRID_W {
+                let eaten = game.eaten[Game::pellet_index(floor, x, y)];
+                tiles.push(if is_wall(x, y) {
+                    Tile::Wall
+                } else if is_power_pellet(x, y) && !eaten {
+                    Tile::PowerPellet
+                } else if is_pellet(x, y) && !eaten {
+                    Tile::Pellet
+                } else {
+                    Tile::Empty
+                });
+            }
+        }
+        let player = &game.players[0];
+        Self {
+            tiles,
+            player: (player.x, player.y),
+            player_direction: (player.dx, player.dy),
+            ghosts: game.ghosts.iter()
+                .map(|ghost| GhostObservation {
+                    x: ghost.x,
+                    y: ghost.y,
+                    dx: ghost.dx,
+                    dy: ghost.dy,
+                    frightened: ghost.vulnerable,
+                })
+                .collect(),
+            power_timer: game.power_pellet_timer,
+            lives: game.lives,
+            score: game.score(),
+        }
+    }
+}
+
+/// Single-player game driven step by step by an agent
+pub struct Env {
+    config: EnvConfig,
+    game: Game,
+    steps: u32,
+}
+
+impl Env {
+    /// Creates the environment (call `reset` before the first step)
+    pub fn new(config: EnvConfig) -> Self {
+        Self { config, game: Env::new_game(0), steps: 0 }
+    }
+
+    fn new_game(seed: u16) -> Game {
+        Game::new_with_seed(GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None), seed)
+    }
+
+    /// Starts a new episode; the same seed always plays out the same way for the same actions
+    pub fn reset(&mut self, seed: u16) -> Observation {
+        self.game = Env::new_game(seed);
+        self.steps = 0;
+        Observation::from_game(&self.game)
+    }
+
+    /// Applies an action for `ticks_per_step` ticks
+    ///
+    /// # Returns
+    /// The new observation, the reward earned during the step and whether the
+    /// episode is over (game over or `max_steps` reached)
+    pub fn step(&mut self, action: Action) -> (Observation, f32, bool) {
+        let rewards = self.config.rewards;
+        let mut reward = 0.0;
+        for _ in 0..self.config.ticks_per_step.max(1) {
+            if !self.game.alive {
+                break;
+            }
+            reward += rewards.per_tick;
+            for event in self.game.tick(action.direction(), None) {
+                reward += match event {
+                    GameEvent::PelletEaten { .. } => rewards.pellet,
+                    GameEvent::PowerPelletEaten { .. } => rewards.power_pellet,
+                    GameEvent::GhostEaten { .. } => rewards.ghost_eaten,
+                    GameEvent::FruitEaten { .. } => rewards.fruit_eaten,
+                    GameEvent::PlayerDied { .. } => rewards.death,
+                    GameEvent::LevelCleared { .. } => rewards.level_cleared,
+                    GameEvent::FruitSpawned | GameEvent::DoublePointsSpawned | GameEvent::DoublePointsTaken { .. } |
+                    GameEvent::BombSpawned | GameEvent::BombTaken { .. } | GameEvent::WallBlasted { .. } |
+                    GameEvent::WallHealed { .. } | GameEvent::PelletsRespawned { .. } |
+                    GameEvent::ScoreChanged { .. } | GameEvent::ExtraLife { .. } |
+                    GameEvent::PowerPelletEnded | GameEvent::GameOver => 0.0,
+                };
+            }
+        }
+        self.steps += 1;
+        let out_of_steps = self.config.max_steps > 0 && self.steps >= self.config.max_steps;
+        (Observation::from_game(&self.game), reward, !self.game.alive || out_of_steps)
+    }
+
+    /// The game being played (read-only, e.g. for rendering an episode)
+    pub fn game(&self) -> &Game {
+        &self.game
+    }
+}
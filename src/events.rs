@@ -0,0 +1,70 @@
+//! Event bus for gameplay events
+//!
+//! `Game::tick` only reports what happened (`GameEvent`s); it never calls
+//! audio, statistics, recorders or the network itself. Whoever runs the game
+//! publishes each tick's events on an `EventBus`, and every system that cares
+//! subscribes to it, so new features hook in here instead of growing `tick`.
+
+use std::sync::mpsc::Sender;
+use crate::audio::AudioEvent;
+use crate::game::{Game, GameEvent};
+
+/// Something that reacts to gameplay events
+pub trait EventSubscriber {
+    /// Called once per event, in the order they happened
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick that raised the event
+    /// * `event` - What happened
+    fn on_event(&mut self, game: &Game, event: &GameEvent);
+}
+
+/// Closures can subscribe directly
+impl<F: FnMut(&Game, &GameEvent)> EventSubscriber for F {
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        self(game, event)
+    }
+}
+
+/// Sound effects and music changes: forwards events to the audio mixer
+impl EventSubscriber for Sender<AudioEvent> {
+    fn on_event(&mut self, _game: &Game, event: &GameEvent) {
+        if let Some(sound) = AudioEvent::for_game_event(event) {
+            // The mixer may already be gone while shutting down
+            let _ = self.send(sound);
+        }
+    }
+}
+
+/// Hands every gameplay event to the subscribed systems
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: Vec<Box<dyn EventSubscriber + Send>>,
+}
+
+impl EventBus {
+    /// Creates a bus with no subscribers
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a subscriber; subscribers see each event in the order they subscribed
+    ///
+    /// Subscribers must be `Send` so a bus can live in a static (the libretro core).
+    pub fn subscribe(&mut self, subscriber: Box<dyn EventSubscriber + Send>) {
+        self.subscribers.push(subscriber);
+    }
+
+    /// Delivers the events of one tick to every subscriber
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick
+    /// * `events` - The events `Game::tick` returned
+    pub fn publish(&mut self, game: &Game, events: &[GameEvent]) {
+        for event in events {
+            for subscriber in &mut self.subscribers {
+                subscriber.on_event(game, event);
+            }
+        }
+    }
+}
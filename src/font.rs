@@ -0,0 +1,327 @@
+//! Bitmap fonts shared by the menus and the HUD
+//!
+//! This module provides:
+//! - A 5x7 text font covering all printable ASCII (lowercase letters are
+//!   drawn as capitals, arcade style)
+//! - A compact 4x6 digit font for scores and timers
+//! - Width measurement, left/center/right alignment and word wrapping
+
+#[cfg(feature = "sdl")]
+use sdl2::pixels::Color;
+#[cfg(feature = "sdl")]
+use sdl2::rect::Rect;
+
+/// Width of a text glyph in font pixels
+pub const GLYPH_W: i32 = 5;
+
+/// Height of a text glyph in font pixels
+pub const GLYPH_H: i32 = 7;
+
+/// Gap between text glyphs in font pixels
+const GLYPH_SPACING: i32 = 1;
+
+/// Gap between wrapped lines in font pixels
+const LINE_SPACING: i32 = 2;
+
+/// Text font: one glyph per printable ASCII character from ' ' (0x20) to '~' (0x7E)
+///
+/// Each glyph is 7 rows of 5 bits, most significant bit on the left.
+/// Lowercase letters reuse the capitals.
+const TEXT_GLYPHS: [[u8; 7]; 95] = [
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // ' '
+    [0x04, 0x04, 0x04, 0x04, 0x00, 0x00, 0x04], // !
+    [0x0A, 0x0A, 0x0A, 0x00, 0x00, 0x00, 0x00], // "
+    [0x0A, 0x0A, 0x1F, 0x0A, 0x1F, 0x0A, 0x0A], // #
+    [0x04, 0x0F, 0x14, 0x0E, 0x05, 0x1E, 0x04], // $
+    [0x18, 0x19, 0x02, 0x04, 0x08, 0x13, 0x03], // %
+    [0x0C, 0x12, 0x14, 0x08, 0x15, 0x12, 0x0D], // &
+    [0x0C, 0x04, 0x08, 0x00, 0x00, 0x00, 0x00], // '
+    [0x02, 0x04, 0x08, 0x08, 0x08, 0x04, 0x02], // (
+    [0x08, 0x04, 0x02, 0x02, 0x02, 0x04, 0x08], // )
+    [0x00, 0x04, 0x15, 0x0E, 0x15, 0x04, 0x00], // *
+    [0x00, 0x04, 0x04, 0x1F, 0x04, 0x04, 0x00], // +
+    [0x00, 0x00, 0x00, 0x00, 0x0C, 0x04, 0x08], // ,
+    [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00], // -
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x0C, 0x0C], // .
+    [0x00, 0x01, 0x02, 0x04, 0x08, 0x10, 0x00], // /
+    [0x0E, 0x11, 0x13, 0x15, 0x19, 0x11, 0x0E], // 0
+    [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E], // 1
+    [0x0E, 0x11, 0x01, 0x06, 0x08, 0x10, 0x1F], // 2
+    [0x1F, 0x02, 0x04, 0x02, 0x01, 0x11, 0x0E], // 3
+    [0x02, 0x06, 0x0A, 0x12, 0x1F, 0x02, 0x02], // 4
+    [0x1F, 0x10, 0x1E, 0x01, 0x01, 0x11, 0x0E], // 5
+    [0x06, 0x08, 0x10, 0x1E, 0x11, 0x11, 0x0E], // 6
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x08, 0x08], // 7
+    [0x0E, 0x11, 0x11, 0x0E, 0x11, 0x11, 0x0E], // 8
+    [0x0E, 0x11, 0x11, 0x0F, 0x01, 0x02, 0x0C], // 9
+    [0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x00], // :
+    [0x00, 0x0C, 0x0C, 0x00, 0x0C, 0x04, 0x08], // ;
+    [0x02, 0x04, 0x08, 0x10, 0x08, 0x04, 0x02], // <
+    [0x00, 0x00, 0x1F, 0x00, 0x1F, 0x00, 0x00], // =
+    [0x08, 0x04, 0x02, 0x01, 0x02, 0x04, 0x08], // >
+    [0x0E, 0x11, 0x01, 0x02, 0x04, 0x00, 0x04], // ?
+    [0x0E, 0x11, 0x01, 0x0D, 0x15, 0x15, 0x0E], // @
+    [0x0E, 0x11, 0x1F, 0x11, 0x11, 0x11, 0x11], // A
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // B
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // C
+    [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E], // D
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // E
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // F
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0E], // G
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // H
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x1F], // I
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // J
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // K
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // L
+    [0x11, 0x1B, 0x15, 0x11, 0x11, 0x11, 0x11], // M
+    [0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x11], // N
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // O
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // P
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // Q
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // R
+    [0x0E, 0x11, 0x10, 0x0E, 0x01, 0x11, 0x0E], // S
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // T
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // U
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // V
+    [0x11, 0x11, 0x11, 0x11, 0x15, 0x1B, 0x11], // W
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // X
+    [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04], // Y
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // Z
+    [0x0E, 0x08, 0x08, 0x08, 0x08, 0x08, 0x0E], // [
+    [0x00, 0x10, 0x08, 0x04, 0x02, 0x01, 0x00], // backslash
+    [0x0E, 0x02, 0x02, 0x02, 0x02, 0x02, 0x0E], // ]
+    [0x04, 0x0A, 0x11, 0x00, 0x00, 0x00, 0x00], // ^
+    [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1F], // _
+    [0x08, 0x04, 0x02, 0x00, 0x00, 0x00, 0x00], // `
+    [0x0E, 0x11, 0x1F, 0x11, 0x11, 0x11, 0x11], // a
+    [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E], // b
+    [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E], // c
+    [0x1E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x1E], // d
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F], // e
+    [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x10], // f
+    [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0E], // g
+    [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11], // h
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x1F], // i
+    [0x07, 0x02, 0x02, 0x02, 0x02, 0x12, 0x0C], // j
+    [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11], // k
+    [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F], // l
+    [0x11, 0x1B, 0x15, 0x11, 0x11, 0x11, 0x11], // m
+    [0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x11], // n
+    [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // o
+    [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10], // p
+    [0x0E, 0x11, 0x11, 0x11, 0x15, 0x12, 0x0D], // q
+    [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11], // r
+    [0x0E, 0x11, 0x10, 0x0E, 0x01, 0x11, 0x0E], // s
+    [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // t
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E], // u
+    [0x11, 0x11, 0x11, 0x11, 0x11, 0x0A, 0x04], // v
+    [0x11, 0x11, 0x11, 0x11, 0x15, 0x1B, 0x11], // w
+    [0x11, 0x11, 0x0A, 0x04, 0x0A, 0x11, 0x11], // x
+    [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04], // y
+    [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F], // z
+    [0x02, 0x04, 0x04, 0x08, 0x04, 0x04, 0x02], // {
+    [0x04, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04], // |
+    [0x08, 0x04, 0x04, 0x02, 0x04, 0x04, 0x08], // }
+    [0x00, 0x00, 0x08, 0x15, 0x02, 0x00, 0x00], // ~
+];
+
+/// Glyph drawn for characters outside printable ASCII (a '?')
+const UNKNOWN_GLYPH: usize = (b'?' - b' ') as usize;
+
+/// Compact bitmask: each digit is 4x6, stored as 6 u8s (one per row)
+const DIGITS: [[u8; 6]; 10] = [
+    [0b1111, 0b1001, 0b1001, 0b1001, 0b1001, 0b1111], // 0
+    [0b0010, 0b0110, 0b0010, 0b0010, 0b0010, 0b0111], // 1
+    [0b1111, 0b0001, 0b1111, 0b1000, 0b1000, 0b1111], // 2
+    [0b1111, 0b0001, 0b1111, 0b0001, 0b0001, 0b1111], // 3
+    [0b1001, 0b1001, 0b1111, 0b0001, 0b0001, 0b0001], // 4
+    [0b1111, 0b1000, 0b1111, 0b0001, 0b0001, 0b1111], // 5
+    [0b1111, 0b1000, 0b1111, 0b1001, 0b1001, 0b1111], // 6
+    [0b1111, 0b0001, 0b0001, 0b0001, 0b0001, 0b0001], // 7
+    [0b1111, 0b1001, 0b1111, 0b1001, 0b1001, 0b1111], // 8
+    [0b1111, 0b1001, 0b1111, 0b0001, 0b0001, 0b1111], // 9
+];
+
+/// Horizontal alignment of text relative to the x coordinate it is drawn at
+#[derive(Clone, Copy, PartialEq)]
+pub enum Align {
+    /// x is the left edge
+    Left,
+    /// x is the center
+    Center,
+    /// x is the right edge
+    Right,
+}
+
+/// Returns the glyph for a character (unknown characters show as '?')
+pub fn glyph(ch: char) -> &'static [u8; 7] {
+    let index = match ch {
+        ' '..='~' => ch as usize - ' ' as usize,
+        _ => UNKNOWN_GLYPH,
+    };
+    &TEXT_GLYPHS[index]
+}
+
+/// Returns the 4x6 glyph of a digit (0-9) from the digit font
+pub fn digit_glyph(digit: u32) -> Option<&'static [u8; 6]> {
+    DIGITS.get(digit as usize)
+}
+
+/// Width in pixels of a single line of text in the text font
+///
+/// # Arguments
+/// * `text` - Text to measure
+/// * `scale` - Size of one font pixel in screen pixels
+pub fn text_width(text: &str, scale: i32) -> i32 {
+    text.chars().count() as i32 * (GLYPH_W + GLYPH_SPACING) * scale
+}
+
+/// Distance in pixels between the tops of two wrapped lines
+pub fn line_height(scale: i32) -> i32 {
+    (GLYPH_H + LINE_SPACING) * scale
+}
+
+/// Draws a single line of text in the text font
+///
+/// # Arguments
+/// * `x` - Left edge, center or right edge depending on `align`
+/// * `y` - Top of the text
+/// * `scale` - Size of one font pixel in screen pixels
+#[cfg(feature = "sdl")]
+pub fn draw_text(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    text: &str,
+    x: i32,
+    y: i32,
+    scale: i32,
+    color: Color,
+    align: Align,
+) {
+    let mut x_pos = match align {
+        Align::Left => x,
+        Align::Center => x - text_width(text, scale) / 2,
+        Align::Right => x - text_width(text, scale),
+    };
+
+    canvas.set_draw_color(color);
+    for ch in text.chars() {
+        for (row, &bits) in glyph(ch).iter().enumerate() {
+            for col in 0..GLYPH_W {
+                if (bits >> (GLYPH_W - 1 - col)) & 1 != 0 {
+                    let _ = canvas.fill_rect(Rect::new(
+                        x_pos + col * scale,
+                        y + row as i32 * scale,
+                        scale as u32,
+                        scale as u32,
+                    ));
+                }
+            }
+        }
+        x_pos += (GLYPH_W + GLYPH_SPACING) * scale;
+    }
+}
+
+/// Splits text into lines no wider than `max_width` pixels, breaking between words
+///
+/// Explicit newlines are kept. A single word wider than the limit gets a line of its own.
+pub fn wrap_text(text: &str, max_width: i32, scale: i32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            let candidate_width = text_width(&line, scale)
+                + if line.is_empty() { 0 } else { text_width(" ", scale) }
+                + text_width(word, scale);
+            if !line.is_empty() && candidate_width > max_width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// Draws several lines of text below each other (e.g. from `wrap_text`)
+///
+/// # Returns
+/// The total height drawn in pixels
+#[cfg(feature = "sdl")]
+pub fn draw_lines(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    lines: &[String],
+    x: i32,
+    y: i32,
+    scale: i32,
+    color: Color,
+    align: Align,
+) -> i32 {
+    for (i, line) in lines.iter().enumerate() {
+        draw_text(canvas, line, x, y + i as i32 * line_height(scale), scale, color, align);
+    }
+    lines.len() as i32 * line_height(scale)
+}
+
+/// Width in pixels of a digit string drawn with `draw_digits`
+pub fn digits_width(text: &str, pixel_size: i32) -> i32 {
+    let char_w = 4;
+    let spacing = 1;
+    text.chars()
+        .map(|ch| match ch {
+            ':' | '.' => (1 + spacing) * pixel_size,
+            _ if ch.is_ascii_digit() => (char_w + spacing) * pixel_size,
+            _ => 0,
+        })
+        .sum()
+}
+
+/// Draws a string of digits with the 4x6 bitmap font, left-aligned at (x, y)
+///
+/// ':' and '.' are drawn as narrow separators (for times); other
+/// non-digit characters are skipped.
+#[cfg(feature = "sdl")]
+pub fn draw_digits(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    text: &str,
+    x: i32,
+    y: i32,
+    pixel_size: i32,
+    color: Color,
+) {
+    let char_w = 4;
+    let spacing = 1;
+    let mut x_pos = x;
+
+    canvas.set_draw_color(color);
+    for ch in text.chars() {
+        if let Some(digit) = ch.to_digit(10).and_then(|d| DIGITS.get(d as usize)) {
+            for (row, &bits) in digit.iter().enumerate() {
+                for col in 0..char_w {
+                    if (bits >> (char_w - 1 - col)) & 1 != 0 {
+                        let _ = canvas.fill_rect(Rect::new(
+                            x_pos + (col * pixel_size),
+                            y + (row as i32 * pixel_size),
+                            pixel_size as u32,
+                            pixel_size as u32,
+                        ));
+                    }
+                }
+            }
+            x_pos += (char_w + spacing) * pixel_size;
+        } else if ch == ':' || ch == '.' {
+            let rows: &[i32] = if ch == ':' { &[1, 4] } else { &[5] };
+            for row in rows {
+                let _ = canvas.fill_rect(Rect::new(
+                    x_pos,
+                    y + row * pixel_size,
+                    pixel_size as u32,
+                    pixel_size as u32,
+                ));
+            }
+            x_pos += (1 + spacing) * pixel_size;
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Bonus fruit: the arcade's ladder from cherry to key
+//!
+//! Twice a level, after `FRUIT_PELLETS` pellets have been eaten, the level's
+//! fruit appears below the ghost house for a little under ten seconds.
+//! Which fruit each level has is a column of the level table (see
+//! `level_table`); later fruit are worth more. The last few fruit eaten in a
+//! run are shown in the HUD.
+
+/// A bonus fruit
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Fruit {
+    Cherry,
+    Strawberry,
+    Orange,
+    Apple,
+    Melon,
+    Galaxian,
+    Bell,
+    Key,
+}
+
+/// A fruit's 6x6 icon in two colors: the body, then the details drawn over it
+///
+/// Rows are one byte each, the most significant of the 6 bits on the left
+/// (as the software renderer's sprites).
+pub struct FruitSprite {
+    pub body: [u8; 6],
+    pub body_color: (u8, u8, u8),
+    pub detail: [u8; 6],
+    pub detail_color: (u8, u8, u8),
+}
+
+/// The double-points pickup, drawn like a fruit: a magenta coin with a white 2
+pub const DOUBLE_POINTS_SPRITE: FruitSprite = FruitSprite {
+    body: [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b011110],
+    body_color: (220, 0, 220),
+    detail: [0, 0b001110, 0b000010, 0b001110, 0b001000, 0b001110],
+    detail_color: (255, 255, 255),
+};
+
+/// A bomb, drawn like a fruit: a grey ball with a lit fuse
+pub const BOMB_SPRITE: FruitSprite = FruitSprite {
+    body: [0, 0b011100, 0b111110, 0b111110, 0b111110, 0b011100],
+    body_color: (130, 130, 150),
+    detail: [0b000011, 0b000100, 0, 0, 0, 0],
+    detail_color: (255, 140, 0),
+};
+
+impl Fruit {
+    /// Every fruit, from the first level's on
+    pub const ALL: [Fruit; 8] = [
+        Fruit::Cherry, Fruit::Strawberry, Fruit::Orange, Fruit::Apple,
+        Fruit::Melon, Fruit::Galaxian, Fruit::Bell, Fruit::Key,
+    ];
+
+    /// Display name, also used (in lower case) in the level table, saves and dumps
+    pub fn name(self) -> &'static str {
+        match self {
+            Fruit::Cherry => "Cherry",
+            Fruit::Strawberry => "Strawberry",
+            Fruit::Orange => "Orange",
+            Fruit::Apple => "Apple",
+            Fruit::Melon => "Melon",
+            Fruit::Galaxian => "Galaxian",
+            Fruit::Bell => "Bell",
+            Fruit::Key => "Key",
+        }
+    }
+
+    /// Looks a fruit up by name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|fruit| fruit.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Points for eating it
+    pub fn points(self) -> i32 {
+        match self {
+            Fruit::Cherry => 100,
+            Fruit::Strawberry => 300,
+            Fruit::Orange => 500,
+            Fruit::Apple => 700,
+            Fruit::Melon => 1000,
+            Fruit::Galaxian => 2000,
+            Fruit::Bell => 3000,
+            Fruit::Key => 5000,
+        }
+    }
+
+    /// How it is drawn, in the maze and in the HUD
+    pub fn sprite(self) -> FruitSprite {
+        let (body, body_color, detail, detail_color) = match self {
+            // Two red cherries hanging from green stems
+            Fruit::Cherry => (
+                [0, 0, 0, 0b110011, 0b110011, 0],
+                (255, 0, 0),
+                [0b000110, 0b001010, 0b010010, 0, 0, 0],
+                (0, 200, 0),
+            ),
+            // Red, narrowing to the bottom, under green leaves
+            Fruit::Strawberry => (
+                [0, 0, 0b111111, 0b111111, 0b011110, 0b001100],
+                (255, 0, 60),
+                [0b001100, 0b011110, 0, 0, 0, 0],
+                (0, 200, 0),
+            ),
+            // Round and orange with a green leaf
+            Fruit::Orange => (
+                [0, 0b011110, 0b111111, 0b111111, 0b111111, 0b011110],
+                (255, 160, 0),
+                [0b000110, 0, 0, 0, 0, 0],
+                (0, 200, 0),
+            ),
+            // Round and red with a brown stem
+            Fruit::Apple => (
+                [0, 0b011110, 0b111111, 0b111111, 0b111111, 0b011110],
+                (220, 0, 0),
+                [0b001000, 0, 0b010000, 0, 0, 0],
+                (150, 90, 40),
+            ),
+            // Green with pale stripes
+            Fruit::Melon => (
+                [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b011110],
+                (0, 170, 0),
+                [0, 0b010010, 0b010010, 0b010010, 0b010010, 0],
+                (170, 255, 170),
+            ),
+            // The Galaxian flagship: yellow body, blue wings
+            Fruit::Galaxian => (
+                [0b001100, 0b011110, 0b001100, 0b001100, 0, 0],
+                (255, 255, 0),
+                [0, 0, 0b110011, 0b110011, 0b100001, 0],
+                (40, 80, 255),
+            ),
+            // Yellow bell with a pale blue clapper
+            Fruit::Bell => (
+                [0b001100, 0b011110, 0b011110, 0b011110, 0b111111, 0],
+                (255, 220, 0),
+                [0, 0, 0, 0, 0, 0b001100],
+                (140, 220, 255),
+            ),
+            // Pale blue bow and a white blade
+            Fruit::Key => (
+                [0b011110, 0b010010, 0b011110, 0, 0, 0],
+                (100, 200, 255),
+                [0, 0, 0, 0b001000, 0b001110, 0b001000],
+                (255, 255, 255),
+            ),
+        };
+        FruitSprite { body, body_color, detail, detail_color }
+    }
+}
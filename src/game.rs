@@ -3,54 +3,206 @@
 //! This module manages the overall game state, including the player, ghosts,
 //! pellets, scoring, and game loop updates.
 
+use std::sync::Arc;
 use crate::constants::{
     GRID_W, GRID_H, 
+    GHOST_DOT_LIMITS, GHOST_GLOBAL_DOT_LIMITS, GHOST_RELEASE_TIMEOUT, GHOST_THINK_INTERVAL,
     SCORE_PELLET, SCORE_POWER_PELLET, SCORE_GHOST,
-    POWER_PELLET_DURATION, POWER_PELLET_FLASH_START
+    FRIGHT_FLASH_FRAMES,
+    BONUS_STAGE_INTERVAL, BONUS_STAGE_DURATION, BONUS_RESULTS_DURATION,
+    FRUIT_PELLETS, FRUIT_TICKS, FRUIT_EXTRA_TICKS, FRUIT_HISTORY,
+    DOUBLE_POINTS_PELLETS, DOUBLE_POINTS_PICKUP_TICKS, DOUBLE_POINTS_TICKS, DOUBLE_POINTS_MULTIPLIER,
+    BOMB_PELLETS, BOMB_ODDS, BOMB_PICKUP_TICKS, BOMB_FUSE_TICKS, BOMB_HOLE_TICKS, MAX_OPEN_WALLS,
+    NG_PLUS_RESPAWN_TICKS, NG_PLUS_RESPAWN_SHARE,
+    STARTING_LIVES, FPS
+};
+use crate::maze::{
+    is_pellet, is_power_pellet, is_blastable, count_pellets, maze_hash, spawn_points,
+    set_current_maze, set_open_walls, set_active_floor, floor_count, floor_rows, is_stairs
 };
-use crate::maze::{is_pellet, is_power_pellet, count_pellets};
 use crate::player::Player;
-use crate::ghost::Ghost;
-use crate::rng::Lfsr;
-use crate::render::{RenderCache, draw_score, draw_game};
+use crate::pacman_ai::{PacmanView, style_ai};
+use crate::hooks::Tweaks;
+use crate::ghost::{Ghost, ChaseView, preset_ai};
+use crate::level_table::level_spec;
+use crate::fruit::Fruit;
+use crate::scoring::{Scoring, ScoreSource};
+use crate::rng::{RngStreams, clock_seed};
+use crate::time_trial::TimeTrial;
+use crate::campaign::{Campaign, CampaignRun};
 use crate::game_config::{GameConfig, GameMode, PlayerRole};
-use sdl2::keyboard::Scancode;
+use crate::renderer::Renderer;
+
+/// Something that happened during a tick, reported by `Game::tick`
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    /// A Pac-Man ate a pellet
+    PelletEaten { player: usize },
+    /// A Pac-Man ate a power pellet (ghosts are now frightened)
+    PowerPelletEaten { player: usize },
+    /// A frightened ghost was eaten for `points`
+    GhostEaten { ghost: usize, points: i32 },
+    /// A bonus fruit appeared in the maze
+    FruitSpawned,
+    /// A Pac-Man ate the bonus fruit for `points`
+    FruitEaten { player: usize, fruit: Fruit, points: i32 },
+    /// The double-points pickup appeared in the maze
+    DoublePointsSpawned,
+    /// A Pac-Man took the double-points pickup (points are doubled for a while)
+    DoublePointsTaken { player: usize },
+    /// A bomb appeared in the maze
+    BombSpawned,
+    /// A Pac-Man picked up the bomb
+    BombTaken { player: usize },
+    /// A Pac-Man blew up the wall tile at (`x`, `y`)
+    WallBlasted { player: usize, x: i32, y: i32 },
+    /// The wall tile at (`x`, `y`) closed again
+    WallHealed { x: i32, y: i32 },
+    /// A life was lost
+    PlayerDied { lives_left: i32 },
+    /// The maze of `level` was cleared
+    LevelCleared { level: u32 },
+    /// The power pellet effect wore off (or was cut short by a respawn)
+    PowerPelletEnded,
+    /// New game plus: `count` eaten pellets came back
+    PelletsRespawned { count: i32 },
+    /// `points` from `source` were added, making the score `score`
+    ScoreChanged { source: ScoreSource, points: i32, score: i32 },
+    /// The score passed one of the `EXTRA_LIFE_SCORES`
+    ExtraLife { lives: i32 },
+    /// The last life was lost
+    GameOver,
+}
+
+/// A wall tile blown up by a bomb, open until it heals
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct OpenWall {
+    pub x: i32,
+    pub y: i32,
+    /// Ticks until it heals (it stays at 0 while someone stands on it)
+    pub ticks: i32,
+}
+
+/// Which part of a run the game is currently in
+#[derive(Clone, Copy, PartialEq)]
+pub enum GamePhase {
+    /// Normal play: ghosts are active and clearing the maze advances the level
+    Playing,
+    /// Ghost-free bonus stage; `timer` counts down the remaining frames
+    BonusStage { timer: i32 },
+    /// Results card shown after a bonus stage; `timer` counts down until the next level
+    BonusResults { timer: i32 },
+}
+
+/// Progress of the player who is waiting for their turn in alternating mode
+/// 
+/// Everything that belongs to one player's run is kept here while the other
+/// player is at the controls, and swapped back in on the next death.
+#[derive(Clone)]
+pub struct TurnState {
+    pub scoring: Scoring,
+    pub lives: i32,
+    pub level: u32,
+    pub eaten: Vec<bool>,
+    pub pellets: i32,
+    pub fruits: Vec<Fruit>,
+}
 
 /// Main game state structure
 /// 
 /// Contains all the game's state: player, ghosts, pellets, score, etc.
+#[derive(Clone)]
 pub struct Game {
-    /// The player (Pac-Man)
-    pub player: Player,
+    /// The Pac-Men (one normally, two in co-op)
+    pub players: Vec<Player>,
     /// Array of 3 ghosts
     pub ghosts: [Ghost; 3],
     /// Tracks which pellets have been eaten (true = eaten, false = not eaten)
     pub eaten: Vec<bool>,
-    /// Random number generator for ghost AI
-    pub rng: Lfsr,
+    /// Random streams for the ghost AI, the AI Pac-Man, the pickups and the
+    /// new game plus pellet respawns (see `RngStreams`)
+    pub rng: RngStreams,
     /// Current frame number (increments each frame)
     pub frame: u32,
     /// Number of pellets remaining
     pub pellets: i32,
-    /// Current score
-    pub score: i32,
-    /// Whether the player is still alive
+    /// Current score and where its points came from (only the score itself is
+    /// in the state hash)
+    pub scoring: Scoring,
+    /// Whether the run is still going (false once every life has been lost or a campaign has been won)
     pub alive: bool,
-    /// Timer for power pellet effect (counts down from POWER_PELLET_DURATION)
+    /// Remaining lives, shared by all Pac-Men in co-op
+    pub lives: i32,
+    /// Timer for power pellet effect (counts down from the level's frightened time)
     pub power_pellet_timer: i32,
     /// Number of ghosts eaten in current power pellet cycle (for scoring multiplier)
     pub ghost_eaten_count: i32,
-    /// Rendering cache for performance optimization
-    pub render_cache: RenderCache,
+    /// Pellets counted towards letting each ghost out of the ghost house (see `GHOST_DOT_LIMITS`)
+    pub ghost_dot_counters: [u32; 3],
+    /// Pellets eaten since the last lost life, while that count decides when
+    /// ghosts leave the house (see `GHOST_GLOBAL_DOT_LIMITS`)
+    pub global_dot_counter: Option<u32>,
+    /// Ticks since a pellet was last eaten (see `GHOST_RELEASE_TIMEOUT`)
+    pub ticks_since_dot: i32,
     /// Game configuration (player modes and roles)
     pub config: GameConfig,
     /// Which ghost is controlled by player (if any)
     pub player_ghost_index: Option<usize>,
+    /// Current level (starts at 1, increments each time the maze is cleared)
+    pub level: u32,
+    /// Current phase of the run (normal play or bonus stage)
+    pub phase: GamePhase,
+    /// Ticks the bonus fruit has left in the maze (0 while there is none)
+    pub fruit_timer: i32,
+    /// The last `FRUIT_HISTORY` fruit eaten in the run, oldest first (for the HUD)
+    pub fruits: Vec<Fruit>,
+    /// Ticks the double-points pickup has left in the maze (0 while there is none)
+    pub double_points_pickup: i32,
+    /// Ticks points stay doubled (0 while they aren't, see `award`)
+    pub double_points_timer: i32,
+    /// Ticks the bomb has left in the maze (0 while there is none)
+    pub bomb_pickup: i32,
+    /// Bombs the Pac-Men carry
+    pub bombs: i32,
+    /// Ticks a Pac-Man carrying a bomb has pushed against a wall (see `update_bomb_fuse`)
+    pub bomb_fuse: i32,
+    /// Walls blown up by a bomb, in the order they were
+    pub open_walls: Vec<OpenWall>,
+    /// Points collected during the current bonus stage
+    pub bonus_haul: i32,
+    /// Pellets collected during the current bonus stage
+    pub bonus_pellets: i32,
+    /// Whose turn it is in alternating mode (0 = player 1, 1 = player 2)
+    pub turn: usize,
+    /// The waiting player's progress in alternating mode
+    pub waiting_turn: Option<TurnState>,
+    /// Clock, trace and personal best in time trial mode
+    pub time_trial: Option<TimeTrial>,
+    /// The campaign being played and how far it has got (None for a single maze)
+    pub campaign: Option<CampaignRun>,
+    /// Set while the game is paused (nothing moves)
+    pub paused: bool,
+    /// Speeds and frightened time as changed by gameplay hooks (see `hooks`)
+    pub tweaks: Tweaks,
+    /// Practice aid: the frightened timer doesn't run down (never set in
+    /// recorded or online games, so it is left out of the state hash)
+    pub power_frozen: bool,
+    /// Events raised during the current tick (handed out by `tick`)
+    events: Vec<GameEvent>,
 }
 
 impl Game {
-    /// Creates a new game with initial state
+    /// Creates a new game with initial state, seeded from the system clock so
+    /// every game plays differently
     pub fn new(config: GameConfig) -> Self {
+        Self::new_with_seed(config, clock_seed())
+    }
+
+    /// Creates a new game with initial state and every random stream derived from `seed`
+    ///
+    /// The same config, seed and inputs always play out the same way: tests,
+    /// replays and online matches seed their games with this.
+    pub fn new_with_seed(config: GameConfig, seed: u16) -> Self {
         let total_pellets = count_pellets();
         
         // Determine which ghost is player-controlled (if any)
@@ -63,74 +215,370 @@ impl Game {
             None
         };
         
+        // Alternating mode: player 2 starts with a fresh copy of the run
+        let waiting_turn = if config.mode == GameMode::Alternating {
+            Some(TurnState {
+                scoring: Scoring::default(),
+                lives: STARTING_LIVES,
+                level: 1,
+                eaten: vec![false; Game::pellet_slots()],
+                pellets: total_pellets,
+                fruits: Vec::new(),
+            })
+        } else {
+            None
+        };
+        
+        let time_trial = if config.mode == GameMode::TimeTrial {
+            Some(TimeTrial::new(maze_hash()))
+        } else {
+            None
+        };
+        
         Self {
-            player: Player::new(),
-            ghosts: [
-                Ghost::new_at(12, 14),  // Left ghost
-                Ghost::new_at(13, 14),  // Center ghost
-                Ghost::new_at(14, 14),  // Right ghost
-            ],
-            eaten: vec![false; (GRID_W * GRID_H) as usize],
-            rng: Lfsr::new(0xACE1),  // Seed for random number generator
+            players: Game::starting_players(&config),
+            ghosts: Game::starting_ghosts(),
+            eaten: vec![false; Game::pellet_slots()],
+            rng: RngStreams::new(seed),
             frame: 0,
             pellets: total_pellets,
-            score: 0,
+            scoring: Scoring::default(),
             alive: true,
+            lives: STARTING_LIVES,
             power_pellet_timer: 0,
             ghost_eaten_count: 0,
-            render_cache: RenderCache::new(),
+            ghost_dot_counters: [0; 3],
+            global_dot_counter: None,
+            ticks_since_dot: 0,
             config,
             player_ghost_index,
+            level: 1,
+            phase: GamePhase::Playing,
+            fruit_timer: 0,
+            fruits: Vec::new(),
+            double_points_pickup: 0,
+            double_points_timer: 0,
+            bomb_pickup: 0,
+            bombs: 0,
+            bomb_fuse: 0,
+            open_walls: Vec::new(),
+            bonus_haul: 0,
+            bonus_pellets: 0,
+            turn: 0,
+            waiting_turn,
+            time_trial,
+            campaign: None,
+            paused: false,
+            tweaks: Tweaks::default(),
+            power_frozen: false,
+            events: Vec::new(),
         }
     }
 
-    /// Converts grid coordinates (x, y) to an index in the eaten array
-    fn pellet_index(x: i32, y: i32) -> usize {
-        (y * GRID_W + x) as usize
+    /// Starts the run at a later level instead of level 1 (level select)
+    /// 
+    /// Everything that changes from level to level is worked out from `level`,
+    /// so the game plays exactly as if the earlier levels had been cleared,
+    /// apart from the score. Call before the first tick.
+    pub fn start_at_level(&mut self, level: u32) {
+        self.level = level.max(1);
+        if let Some(ref mut waiting) = self.waiting_turn {
+            waiting.level = self.level;
+        }
+    }
+
+    /// Plays a campaign instead of a single maze, starting on its first stage
+    /// 
+    /// Switches the current maze to the stage's. Call before the first tick.
+    pub fn start_campaign(&mut self, campaign: Arc<Campaign>) {
+        self.campaign = Some(CampaignRun { campaign, stage: 0 });
+        self.start_stage();
+    }
+
+    /// Sets up the current campaign stage: its maze, level and extra lives
+    fn start_stage(&mut self) {
+        let Some(stage) = self.campaign.as_ref().and_then(CampaignRun::current) else { return };
+        set_current_maze(stage.maze);
+        self.level = stage.level;
+        self.lives += stage.extra_lives;
+        self.reset_maze();
+    }
+
+    /// Resets the maze, pellets and entity positions for a fresh layout
+    /// 
+    /// Used when moving on to the next level and when entering the bonus stage.
+    /// Score and level are kept.
+    fn reset_maze(&mut self) {
+        // A campaign's next maze may have another number of floors
+        self.eaten.clear();
+        self.eaten.resize(Game::pellet_slots(), false);
+        self.pellets = count_pellets();
+        self.ghost_dot_counters = [0; 3];
+        self.global_dot_counter = None;
+        self.respawn();
+    }
+
+    /// Starts the level over on the current maze: pellets back and everyone at
+    /// their spawn point, with score, lives and level kept
+    /// 
+    /// Used when dev mode swaps the maze under a running game.
+    pub fn restart_level(&mut self) {
+        self.reset_maze();
+    }
+
+    /// Puts every Pac-Man and ghost back at its spawn point and ends any
+    /// power pellet effect, bonus fruit, double points and bombs (blown-up
+    /// walls close at once), leaving pellets and score untouched
+    fn respawn(&mut self) {
+        self.fruit_timer = 0;
+        self.double_points_pickup = 0;
+        self.double_points_timer = 0;
+        self.bomb_pickup = 0;
+        self.bombs = 0;
+        self.bomb_fuse = 0;
+        self.open_walls.clear();
+        self.sync_open_walls();
+        self.players = Game::starting_players(&self.config);
+        self.ghosts = Game::starting_ghosts();
+        if self.power_pellet_timer > 0 {
+            self.events.push(GameEvent::PowerPelletEnded);
+        }
+        self.power_pellet_timer = 0;
+        self.ghost_eaten_count = 0;
+    }
+
+    /// Called when the maze has been cleared during normal play
+    /// 
+    /// Advances the level and either starts the bonus stage (every
+    /// `BONUS_STAGE_INTERVAL` levels) or goes straight to the next maze. In a
+    /// campaign the next stage starts instead, and clearing the last one ends
+    /// the run.
+    fn on_level_cleared(&mut self) {
+        self.events.push(GameEvent::LevelCleared { level: self.level });
+        
+        // Time trial: clearing the maze ends the run
+        if let Some(ref mut trial) = self.time_trial {
+            trial.finish();
+            return;
+        }
+        
+        // Campaign: on to the next stage's maze, or won after the last one
+        // (new game plus starts over from the first)
+        if let Some(ref mut run) = self.campaign {
+            run.stage += 1;
+            if run.finished() && self.config.ng_plus {
+                run.stage = 0;
+                self.start_stage();
+            } else if run.finished() {
+                self.alive = false;
+            } else {
+                self.start_stage();
+            }
+            return;
+        }
+        
+        let cleared_level = self.level;
+        self.level += 1;
+        self.reset_maze();
+        
+        if cleared_level.is_multiple_of(BONUS_STAGE_INTERVAL) {
+            self.bonus_haul = 0;
+            self.bonus_pellets = 0;
+            self.phase = GamePhase::BonusStage { timer: self.config.ticks(BONUS_STAGE_DURATION) };
+        }
+    }
+
+    /// Counts down the bonus stage and switches to the results card when
+    /// time runs out or every pellet has been collected
+    fn update_bonus_stage(&mut self) {
+        if let GamePhase::BonusStage { timer } = self.phase {
+            let timer = timer - 1;
+            if timer <= 0 || self.pellets == 0 {
+                self.award(ScoreSource::BonusStage, self.bonus_haul);
+                self.phase = GamePhase::BonusResults { timer: self.config.ticks(BONUS_RESULTS_DURATION) };
+            } else {
+                self.phase = GamePhase::BonusStage { timer };
+            }
+        }
+    }
+
+    /// Returns true while the ghost-free bonus stage is running
+    pub fn in_bonus_stage(&self) -> bool {
+        matches!(self.phase, GamePhase::BonusStage { .. })
+    }
+
+    /// Creates the Pac-Men at their spawn points (a second one in co-op)
+    fn starting_players(config: &GameConfig) -> Vec<Player> {
+        let [(x, y), (x2, y2)] = spawn_points().players;
+        let mut players = vec![Player::new_at(x, y)];
+        if config.mode == GameMode::Coop {
+            players.push(Player::new_at(x2, y2));
+        }
+        players
+    }
+
+    /// Creates the ghosts at their starting positions in the ghost house,
+    /// waiting to be let out
+    fn starting_ghosts() -> [Ghost; 3] {
+        spawn_points().ghosts.map(|(x, y)| {
+            let mut ghost = Ghost::new_at(x, y);
+            ghost.in_house = true;
+            ghost
+        })
+    }
+
+    /// Lets ghosts out of the ghost house once enough pellets have been eaten
+    /// 
+    /// Normally each ghost has its own counter and only the first ghost still
+    /// inside counts, so they leave one after another. After a lost life a single
+    /// counter for all of them decides until the last ghost's count is reached.
+    /// If no pellet is eaten for `GHOST_RELEASE_TIMEOUT` ticks, the next ghost
+    /// leaves anyway. A player-controlled ghost never waits.
+    fn release_ghosts(&mut self) {
+        let limits = ghost_dot_limits(self.level);
+        self.ticks_since_dot += 1;
+        let mut timed_out = self.ticks_since_dot >= self.config.ticks(GHOST_RELEASE_TIMEOUT);
+        if timed_out {
+            self.ticks_since_dot = 0;
+        }
+        for (i, ghost) in self.ghosts.iter_mut().enumerate() {
+            if !ghost.in_house {
+                continue;
+            }
+            let counted_out = match self.global_dot_counter {
+                Some(count) => count >= GHOST_GLOBAL_DOT_LIMITS[i],
+                None => self.ghost_dot_counters[i] >= limits[i],
+            };
+            if self.player_ghost_index == Some(i) || counted_out {
+                ghost.in_house = false;
+            } else if timed_out {
+                // Nobody has eaten for a while: this ghost leaves anyway
+                ghost.in_house = false;
+                timed_out = false;
+            } else if self.global_dot_counter.is_none() {
+                // The ghosts behind this one wait for it to leave first
+                break;
+            }
+        }
+        let last_limit = GHOST_GLOBAL_DOT_LIMITS[GHOST_GLOBAL_DOT_LIMITS.len() - 1];
+        if self.global_dot_counter.map(|count| count >= last_limit).unwrap_or(false) {
+            self.global_dot_counter = None;
+        }
+    }
+
+    /// Counts an eaten pellet towards letting the next ghost out (see `release_ghosts`)
+    fn count_dot_for_release(&mut self) {
+        self.ticks_since_dot = 0;
+        if let Some(ref mut count) = self.global_dot_counter {
+            *count += 1;
+            return;
+        }
+        let player_ghost = self.player_ghost_index;
+        let next_out = (0..self.ghosts.len()).find(|&i| self.ghosts[i].in_house && player_ghost != Some(i));
+        if let Some(i) = next_out {
+            self.ghost_dot_counters[i] += 1;
+        }
+    }
+
+    /// Converts grid coordinates (x, y) on a floor to an index in the eaten array
+    pub(crate) fn pellet_index(floor: usize, x: i32, y: i32) -> usize {
+        floor * (GRID_W * GRID_H) as usize + (y * GRID_W + x) as usize
+    }
+
+    /// Length of the eaten array: one flag per tile of every floor of the current maze
+    fn pellet_slots() -> usize {
+        floor_count() * (GRID_W * GRID_H) as usize
+    }
+
+    /// The eaten flags of one floor, one per tile row by row (see `pellet_index`)
+    pub fn floor_eaten(&self, floor: usize) -> &[bool] {
+        Game::floor_slice(&self.eaten, floor)
+    }
+
+    /// One floor's part of an eaten array (empty if the maze has no such floor)
+    fn floor_slice(eaten: &[bool], floor: usize) -> &[bool] {
+        let size = (GRID_W * GRID_H) as usize;
+        eaten.get(floor * size..(floor + 1) * size).unwrap_or(&[])
     }
 
     /// Updates the game state for one frame
     /// 
-    /// This is called every frame and handles:
-    /// - Input processing
-    /// - Player movement
-    /// - Pellet collection
-    /// - Power pellet effects
-    /// - Ghost AI and movement
-    /// - Collision detection
+    /// Each tick runs in this fixed order:
+    /// 1. Frame counter (a finished time trial stops here, a results card only
+    ///    counts down)
+    /// 2. Input: player 1, then player 2, then the bomb fuse
+    /// 3. AI Pac-Man decision, then movement of every Pac-Man in index order
+    /// 4. Time trial trace
+    /// 5. Pellet, fruit and pickup collection, in player index order
+    /// 6. Bonus stage countdown (bonus stages stop here)
+    /// 7. Power pellet, fruit, double points and bomb timers, then healing
+    ///    walls and (new game plus) respawning pellets
+    /// 8. Ghost AI and movement, in ghost index order
+    /// 9. Collisions
+    /// 10. Level clear
+    /// 
+    /// On a two-floor maze each Pac-Man and ghost moves on its own floor (see
+    /// `maze::set_active_floor`) and takes the stairs by walking onto them.
+    /// Afterwards the maze is left on player 1's floor.
+    /// 
+    /// Random numbers come from `rng`, one stream per consumer (see
+    /// `RngStreams`), so no step can shift another step's numbers.
+    /// Game logic uses integers only (floats are for rendering and audio), so
+    /// the same inputs give a bit-identical game on every platform; compare
+    /// `state_hash` to find the tick where two runs drift apart.
+    /// 
+    /// Inputs are plain directions so the same tick can be driven by the
+    /// keyboard, a network peer or a recording.
     /// 
     /// # Arguments
-    /// * `keyboard` - Current keyboard state (for held keys as fallback)
+    /// * `player1_input` - Direction held by player 1 this tick, if any
     /// * `player2_input` - Optional input for player 2 (in multiplayer)
-    pub fn tick(&mut self, keyboard: &sdl2::keyboard::KeyboardState, player2_input: Option<(i32, i32)>) {
-        self.frame = self.frame.wrapping_add(1);
+    /// 
+    /// # Returns
+    /// What happened during the tick, in order, for audio, statistics and the like
+    /// (publish them on an `events::EventBus` rather than calling those systems from here)
+    pub fn tick(&mut self, player1_input: Option<(i32, i32)>, player2_input: Option<(i32, i32)>) -> Vec<GameEvent> {
+        self.step(player1_input, player2_input);
+        set_active_floor(self.view_floor());
+        std::mem::take(&mut self.events)
+    }
 
-        // Handle player 1 input (Pac-Man or Ghost)
-        let player1_is_pacman = self.config.player1_role == PlayerRole::PacMan;
-        let player1_is_ghost = self.config.player1_role == PlayerRole::Ghost;
+    /// Advances the game by one tick (see `tick`), collecting events in `self.events`
+    fn step(&mut self, player1_input: Option<(i32, i32)>, player2_input: Option<(i32, i32)>) {
+        // The maze is shared with any other game (see `set_open_walls`)
+        self.sync_open_walls();
+
+        // Finished time trial: the board stays frozen on the results card
+        if self.time_trial.as_ref().map(|trial| trial.finished).unwrap_or(false) {
+            return;
+        }
         
-        if player1_is_pacman {
-            // Player 1 controls Pac-Man
-            const DIRECTION_KEYS: [(Scancode, (i32, i32)); 4] = [
-                (Scancode::Up, (0, -1)),
-                (Scancode::Down, (0, 1)),
-                (Scancode::Left, (-1, 0)),
-                (Scancode::Right, (1, 0)),
-            ];
-            if let Some((_, (dx, dy))) = DIRECTION_KEYS.iter().find(|(sc, _)| keyboard.is_scancode_pressed(*sc)) {
-                self.process_input(*dx, *dy);
+        self.frame = self.frame.wrapping_add(1);
+
+        // Results card: wait for it to expire, then start the next level
+        if let GamePhase::BonusResults { timer } = self.phase {
+            if timer <= 1 {
+                self.reset_maze();
+                self.phase = GamePhase::Playing;
+            } else {
+                self.phase = GamePhase::BonusResults { timer: timer - 1 };
             }
-        } else if player1_is_ghost && self.player_ghost_index == Some(0) {
-            // Player 1 controls first ghost
-            const DIRECTION_KEYS: [(Scancode, (i32, i32)); 4] = [
-                (Scancode::Up, (0, -1)),
-                (Scancode::Down, (0, 1)),
-                (Scancode::Left, (-1, 0)),
-                (Scancode::Right, (1, 0)),
-            ];
-            if let Some((_, (dx, dy))) = DIRECTION_KEYS.iter().find(|(sc, _)| keyboard.is_scancode_pressed(*sc)) {
+            return;
+        }
+
+        // Handle player 1 input (Pac-Man or Ghost)
+        if let Some((dx, dy)) = player1_input {
+            let player1_is_pacman = self.config.player1_role == PlayerRole::PacMan;
+            let player1_is_ghost = self.config.player1_role == PlayerRole::Ghost;
+            
+            if player1_is_pacman {
+                // Player 1 controls Pac-Man
+                self.process_input(dx, dy);
+            } else if player1_is_ghost && self.player_ghost_index == Some(0) {
+                // Player 1 controls first ghost
                 if let Some(ghost) = self.ghosts.get_mut(0) {
-                    ghost.process_input(*dx, *dy);
+                    set_active_floor(ghost.floor);
+                    ghost.process_input(dx, dy);
                 }
             }
         }
@@ -140,90 +588,596 @@ impl Game {
             let player2_is_pacman = self.config.player2_role == Some(PlayerRole::PacMan);
             let player2_is_ghost = self.config.player2_role == Some(PlayerRole::Ghost);
             
-            if player2_is_pacman {
+            if self.config.mode == GameMode::Coop {
+                // Player 2 controls the second Pac-Man (in co-op)
+                self.process_input_for(1, dx, dy);
+            } else if self.config.mode == GameMode::Alternating {
+                // Player 2 steers the (only) Pac-Man during their turn
+                if self.turn == 1 {
+                    self.process_input_for(0, dx, dy);
+                }
+            } else if player2_is_pacman {
                 // Player 2 controls Pac-Man (in multiplayer)
                 self.process_input(dx, dy);
             } else if player2_is_ghost && self.player_ghost_index == Some(1) {
                 // Player 2 controls second ghost
                 if let Some(ghost) = self.ghosts.get_mut(1) {
+                    set_active_floor(ghost.floor);
                     ghost.process_input(dx, dy);
                 }
             }
         }
 
+        self.update_bomb_fuse();
+
+        // Where everyone stood before moving, to catch Pac-Man and a ghost swapping tiles
+        let players_before: Vec<(i32, i32)> = self.players.iter()
+            .map(|player| (player.x, player.y))
+            .collect();
+
         // Update Pac-Man (player-controlled or AI)
+        let spec = level_spec(self.level);
+        let pacman_subframes = self.config.ticks(Tweaks::subframes(spec.pacman_subframes, self.tweaks.pacman_speed));
         if self.config.pacman_is_ai() {
             // AI-controlled Pac-Man
-            let ghost_data: Vec<(i32, i32, bool)> = self.ghosts.iter()
-                .map(|ghost| (ghost.x, ghost.y, ghost.vulnerable))
-                .collect();
-            self.player.update_ai(&ghost_data, self.power_pellet_timer > 0, 
-                                 &self.eaten, &mut self.rng);
+            let pacman = &self.players[0];
+            set_active_floor(pacman.floor);
+            let view = PacmanView {
+                position: (pacman.x, pacman.y),
+                heading: (pacman.dx, pacman.dy),
+                ghosts: &self.ghosts,
+                eaten: Game::floor_slice(&self.eaten, pacman.floor),
+                power_ticks: self.power_pellet_timer,
+                ticks_per_tile: pacman_subframes,
+            };
+            self.players[0].update_ai(style_ai(self.config.pacman_style), &view, self.config.ice);
+        }
+        for (player, &before) in self.players.iter_mut().zip(&players_before) {
+            set_active_floor(player.floor);
+            player.update(pacman_subframes, self.config.ticks(self.config.cornering_window), self.config.ice);
+            player.floor = Game::floor_after_move(player.floor, before, (player.x, player.y));
+        }
+        
+        // Time trial: advance the clock and record the trace for racing later
+        if let Some(ref mut trial) = self.time_trial {
+            trial.record(self.players[0].x, self.players[0].y);
         }
-        self.player.update();
 
-        // Check if player is on a pellet
-        self.handle_pellet_collection();
+        // Check if any player is on a pellet
+        for player_index in 0..self.players.len() {
+            self.handle_pellet_collection(player_index);
+            self.handle_fruit_collection(player_index);
+            self.handle_double_points_pickup(player_index);
+            self.handle_bomb_pickup(player_index);
+        }
+
+        // Bonus stage is ghost-free: only the countdown matters
+        if self.in_bonus_stage() {
+            self.update_bonus_stage();
+            return;
+        }
 
-        // Update power pellet timer and effects
+        // Update power pellet timer and effects, and take away an uneaten fruit in time
         self.update_power_pellet_timer();
+        for timer in [&mut self.fruit_timer, &mut self.double_points_pickup, &mut self.double_points_timer, &mut self.bomb_pickup] {
+            if *timer > 0 {
+                *timer -= 1;
+            }
+        }
+        self.heal_walls();
+        if self.config.ng_plus && self.frame.is_multiple_of(self.config.ticks(NG_PLUS_RESPAWN_TICKS) as u32) {
+            self.respawn_pellets();
+        }
 
-        // Update all ghosts (AI and movement)
-        for (i, ghost) in self.ghosts.iter_mut().enumerate() {
+        // Update all ghosts (AI and movement); the ones still in the ghost house wait
+        self.release_ghosts();
+        let ghosts_before: Vec<(i32, i32)> = self.ghosts.iter()
+            .map(|ghost| (ghost.x, ghost.y))
+            .collect();
+        let red_ghost = ghosts_before.first().copied().unwrap_or((0, 0));
+        let think_interval = self.config.ticks(GHOST_THINK_INTERVAL);
+        for ((i, ghost), rng) in self.ghosts.iter_mut().enumerate().zip(self.rng.ghosts.iter_mut()) {
+            if ghost.in_house {
+                continue;
+            }
             // Skip AI update if this ghost is player-controlled
             let is_player_controlled = self.player_ghost_index == Some(i);
+            // The red ghost speeds up as the maze empties ("Cruise Elroy"), unless frightened
+            let move_subframes = if i == 0 && !is_player_controlled && !ghost.vulnerable {
+                spec.red_ghost_subframes(self.pellets)
+            } else {
+                spec.ghost_subframes
+            };
+            let move_subframes = self.config.ticks(Tweaks::subframes(move_subframes, self.tweaks.ghost_speed));
+            set_active_floor(ghost.floor);
             if !is_player_controlled {
-                ghost.update(&mut self.rng, self.player.x, self.player.y);
+                // Ghost AI chases (or flees from) the nearest Pac-Man, one on its own floor first
+                let nearest = self.players.iter()
+                    .min_by_key(|player| (player.floor != ghost.floor, (player.x - ghost.x).abs() + (player.y - ghost.y).abs()));
+                let (pacman, heading) = nearest
+                    .map(|player| ((player.x, player.y), (player.dx, player.dy)))
+                    .unwrap_or(((ghost.x, ghost.y), (0, 0)));
+                let view = ChaseView {
+                    ghost: (ghost.x, ghost.y),
+                    pacman,
+                    heading,
+                    red_ghost,
+                    arcade_quirks: self.config.arcade_quirks,
+                };
+                let target = preset_ai(self.config.ghost_preset, i).target(&view);
+                ghost.update(rng, pacman.0, pacman.1, target, move_subframes, think_interval);
             } else {
                 // Player-controlled ghost: just update movement
-                ghost.update_movement_only();
+                ghost.update_movement_only(move_subframes);
             }
+            ghost.floor = Game::floor_after_move(ghost.floor, ghosts_before[i], (ghost.x, ghost.y));
         }
 
         // Check for collisions between player and ghosts
-        self.check_collisions();
+        self.check_collisions(&players_before, &ghosts_before);
+
+        // Advance to the next level once the maze is cleared
+        if self.alive && self.pellets == 0 {
+            self.on_level_cleared();
+        }
     }
     
+    /// Hash of everything that affects how the game continues
+    /// 
+    /// Two games that were fed the same inputs have the same hash on every
+    /// tick on any platform, so replays and netplay compare it to catch a
+    /// desync at the tick it happens. Presentation-only state (pause, time
+    /// trial ghost) is left out.
+    /// 
+    /// # Returns
+    /// A 64-bit FNV-1a hash of the game state
+    pub fn state_hash(&self) -> u64 {
+        let mut hash = StateHash::new();
+        hash.add(self.frame as i64);
+        hash.add(self.score() as i64);
+        hash.add(self.lives as i64);
+        hash.add(self.level as i64);
+        hash.add(self.alive as i64);
+        hash.add(self.pellets as i64);
+        hash.add(self.power_pellet_timer as i64);
+        hash.add(self.ghost_eaten_count as i64);
+        for &count in &self.ghost_dot_counters {
+            hash.add(count as i64);
+        }
+        hash.add(self.global_dot_counter.map(|count| count as i64).unwrap_or(-1));
+        hash.add(self.ticks_since_dot as i64);
+        for stream in self.rng.all() {
+            hash.add(stream.state() as i64);
+        }
+        hash.add(self.turn as i64);
+        hash.add(self.campaign.as_ref().map(|run| run.stage as i64).unwrap_or(-1));
+        match self.phase {
+            GamePhase::Playing => hash.add(0),
+            GamePhase::BonusStage { timer } => {
+                hash.add(1);
+                hash.add(timer as i64);
+            }
+            GamePhase::BonusResults { timer } => {
+                hash.add(2);
+                hash.add(timer as i64);
+            }
+        }
+        hash.add(self.bonus_haul as i64);
+        hash.add(self.bonus_pellets as i64);
+        // Only once a fruit has appeared, so games before then hash as they always have
+        if self.fruit_timer > 0 || !self.fruits.is_empty() {
+            hash.add(self.fruit_timer as i64);
+            for &fruit in &self.fruits {
+                hash.add(fruit as i64);
+            }
+        }
+        // Likewise for double points
+        if self.double_points_pickup > 0 || self.double_points_timer > 0 {
+            hash.add(self.double_points_pickup as i64);
+            hash.add(self.double_points_timer as i64);
+        }
+        // And bombs
+        if self.bomb_pickup > 0 || self.bombs > 0 || self.bomb_fuse > 0 || !self.open_walls.is_empty() {
+            hash.add(self.bomb_pickup as i64);
+            hash.add(self.bombs as i64);
+            hash.add(self.bomb_fuse as i64);
+            for wall in &self.open_walls {
+                hash.add(wall.x as i64);
+                hash.add(wall.y as i64);
+                hash.add(wall.ticks as i64);
+            }
+        }
+        // Only away from the default, so games at the default tick rate hash as they always have
+        if self.config.tick_rate != FPS {
+            hash.add(self.config.tick_rate as i64);
+        }
+        // Likewise off the ice, and outside new game plus
+        if self.config.ice {
+            hash.add(1);
+        }
+        if self.config.ng_plus {
+            hash.add(2);
+        }
+        // Only once hooks change them, so games without hooks hash as they always have
+        if self.tweaks != Tweaks::default() {
+            hash.add(self.tweaks.ghost_speed as i64);
+            hash.add(self.tweaks.pacman_speed as i64);
+            hash.add(self.tweaks.frightened_ticks as i64);
+        }
+        // Only on two-floor mazes, so other mazes hash as they always have
+        if floor_count() > 1 {
+            for floor in self.players.iter().map(|player| player.floor).chain(self.ghosts.iter().map(|ghost| ghost.floor)) {
+                hash.add(floor as i64);
+            }
+        }
+        for &eaten in &self.eaten {
+            hash.add(eaten as i64);
+        }
+        for player in &self.players {
+            for value in [player.x, player.y, player.dx, player.dy, player.sub_frame_counter,
+                          player.queued_dx, player.queued_dy, player.queued_ticks] {
+                hash.add(value as i64);
+            }
+        }
+        for ghost in &self.ghosts {
+            for value in [ghost.x, ghost.y, ghost.dx, ghost.dy, ghost.sub_frame_counter,
+                          ghost.think_timer, ghost.vulnerable as i32, ghost.target.0, ghost.target.1,
+                          ghost.in_house as i32] {
+                hash.add(value as i64);
+            }
+        }
+        if let Some(ref waiting) = self.waiting_turn {
+            hash.add(waiting.scoring.score() as i64);
+            hash.add(waiting.lives as i64);
+            hash.add(waiting.level as i64);
+            hash.add(waiting.pellets as i64);
+            for &eaten in &waiting.eaten {
+                hash.add(eaten as i64);
+            }
+            for &fruit in &waiting.fruits {
+                hash.add(fruit as i64);
+            }
+        }
+        hash.finish()
+    }
+
     /// Processes input for Pac-Man (called from main loop)
+    /// 
+    /// In alternating mode player 1's keys only work during player 1's turn.
     pub fn process_input(&mut self, dx: i32, dy: i32) {
-        if !self.config.pacman_is_ai() {
-            self.player.process_input(dx, dy);
+        if self.config.mode == GameMode::Alternating && self.turn != 0 {
+            return;
         }
+        self.process_input_for(0, dx, dy);
     }
 
-    /// Handles pellet collection when player moves onto a pellet
-    fn handle_pellet_collection(&mut self) {
-        if is_pellet(self.player.x, self.player.y) {
-            let pellet_index = Game::pellet_index(self.player.x, self.player.y);
+    /// Processes input for a specific Pac-Man (player 2 steers the second one in co-op)
+    pub fn process_input_for(&mut self, player_index: usize, dx: i32, dy: i32) {
+        if !self.config.pacman_is_ai() && !self.paused {
+            if let Some(player) = self.players.get_mut(player_index) {
+                set_active_floor(player.floor);
+                player.process_input(dx, dy, self.config.ticks(self.config.cornering_window), self.config.ice);
+            }
+        }
+    }
+
+    /// Handles pellet collection when a player moves onto a pellet
+    /// 
+    /// Pellets are shared: whichever Pac-Man reaches a pellet first eats it.
+    fn handle_pellet_collection(&mut self, player_index: usize) {
+        let player = &self.players[player_index];
+        let (player_x, player_y, floor) = (player.x, player.y, player.floor);
+        set_active_floor(floor);
+        if is_pellet(player_x, player_y) {
+            let pellet_index = Game::pellet_index(floor, player_x, player_y);
             
             // Only collect if not already eaten
             if !self.eaten[pellet_index] {
                 self.eaten[pellet_index] = true;
                 self.pellets -= 1;
+                self.events.push(if is_power_pellet(player_x, player_y) {
+                    GameEvent::PowerPelletEaten { player: player_index }
+                } else {
+                    GameEvent::PelletEaten { player: player_index }
+                });
+                
+                // Bonus stage: every pellet goes into the haul, no power effects
+                if self.in_bonus_stage() {
+                    self.bonus_pellets += 1;
+                    self.bonus_haul += if is_power_pellet(player_x, player_y) {
+                        SCORE_POWER_PELLET
+                    } else {
+                        SCORE_PELLET
+                    };
+                    return;
+                }
+                self.count_dot_for_release();
+                let eaten_on_level = count_pellets() - self.pellets;
+                if FRUIT_PELLETS.contains(&eaten_on_level) {
+                    self.spawn_fruit();
+                }
+                if DOUBLE_POINTS_PELLETS.contains(&eaten_on_level) {
+                    self.double_points_pickup = self.config.ticks(DOUBLE_POINTS_PICKUP_TICKS);
+                    self.events.push(GameEvent::DoublePointsSpawned);
+                }
+                if eaten_on_level == BOMB_PELLETS {
+                    self.maybe_spawn_bomb();
+                }
                 
                 // Check if it's a power pellet (marked with *)
-                if is_power_pellet(self.player.x, self.player.y) {
-                    self.score += SCORE_POWER_PELLET;
-                    self.power_pellet_timer = POWER_PELLET_DURATION;
+                if is_power_pellet(player_x, player_y) {
+                    self.award(ScoreSource::PowerPellet, SCORE_POWER_PELLET);
+                    self.ghost_eaten_count = 0;
                     
-                    // Make all ghosts vulnerable
+                    // Make all ghosts vulnerable for the level's frightened time
+                    // (late levels have none: the pellet only scores)
+                    let frightened_ticks = self.config.ticks(self.tweaks.frightened(level_spec(self.level).frightened_ticks));
+                    if frightened_ticks == 0 {
+                        self.events.push(GameEvent::PowerPelletEnded);
+                        return;
+                    }
+                    self.power_pellet_timer = frightened_ticks;
                     for ghost in &mut self.ghosts {
                         ghost.vulnerable = true;
                     }
-                    
-                    // Reset ghost eaten counter for new power pellet cycle
-                    self.ghost_eaten_count = 0;
                 } else {
                     // Regular pellet
-                    self.score += SCORE_PELLET;
+                    self.award(ScoreSource::Pellet, SCORE_PELLET);
                 }
             }
         }
     }
 
+    /// Puts the level's bonus fruit in the maze for `FRUIT_TICKS` and up to
+    /// `FRUIT_EXTRA_TICKS` more, picked from the fruit stream
+    fn spawn_fruit(&mut self) {
+        let extra = self.rng.fruit.range(0, FRUIT_EXTRA_TICKS);
+        self.fruit_timer = self.config.ticks(FRUIT_TICKS + extra);
+        self.events.push(GameEvent::FruitSpawned);
+    }
+
+    /// Eats the bonus fruit if the player is on its tile
+    fn handle_fruit_collection(&mut self, player_index: usize) {
+        let player = &self.players[player_index];
+        if self.fruit_timer == 0 || player.floor != 0 || (player.x, player.y) != spawn_points().fruit {
+            return;
+        }
+        let fruit = self.level_fruit();
+        self.fruit_timer = 0;
+        let points = self.award(ScoreSource::Fruit, fruit.points());
+        self.fruits.push(fruit);
+        if self.fruits.len() > FRUIT_HISTORY {
+            self.fruits.remove(0);
+        }
+        self.events.push(GameEvent::FruitEaten { player: player_index, fruit, points });
+    }
+
+    /// Takes the double-points pickup if the player is on its tile (player 1's
+    /// starting tile)
+    ///
+    /// Points are then doubled for `DOUBLE_POINTS_TICKS`. Taking it while they
+    /// already are starts that time over: the multiplier never goes above
+    /// `DOUBLE_POINTS_MULTIPLIER`.
+    fn handle_double_points_pickup(&mut self, player_index: usize) {
+        let player = &self.players[player_index];
+        if self.double_points_pickup == 0 || player.floor != 0 || (player.x, player.y) != spawn_points().players[0] {
+            return;
+        }
+        self.double_points_pickup = 0;
+        self.double_points_timer = self.config.ticks(DOUBLE_POINTS_TICKS);
+        self.events.push(GameEvent::DoublePointsTaken { player: player_index });
+    }
+
+    /// Puts a bomb on the bonus fruit's tile for `BOMB_PICKUP_TICKS` on one
+    /// level in `BOMB_ODDS`, picked from the bomb stream
+    ///
+    /// There is no bomb if the fruit is still waiting there, nor on two-floor
+    /// mazes, where a hole would go through one floor only.
+    fn maybe_spawn_bomb(&mut self) {
+        if self.fruit_timer > 0 || floor_count() > 1 || self.rng.bomb.range(0, BOMB_ODDS - 1) != 0 {
+            return;
+        }
+        self.bomb_pickup = self.config.ticks(BOMB_PICKUP_TICKS);
+        self.events.push(GameEvent::BombSpawned);
+    }
+
+    /// Picks up the bomb if the player is on its tile (the bonus fruit's)
+    fn handle_bomb_pickup(&mut self, player_index: usize) {
+        let player = &self.players[player_index];
+        if self.bomb_pickup == 0 || player.floor != 0 || (player.x, player.y) != spawn_points().fruit {
+            return;
+        }
+        self.bomb_pickup = 0;
+        self.bombs += 1;
+        self.events.push(GameEvent::BombTaken { player: player_index });
+    }
+
+    /// Lights the fuse while a Pac-Man carrying a bomb stands still and pushes
+    /// against a wall, and blows the wall up after `BOMB_FUSE_TICKS`
+    ///
+    /// Standing still means he has already run into it, so turning into walls
+    /// on the move never spends a bomb. Walls on the border of the maze don't
+    /// blow up (see `is_blastable`).
+    fn update_bomb_fuse(&mut self) {
+        if self.bombs == 0 || self.open_walls.len() >= MAX_OPEN_WALLS {
+            self.bomb_fuse = 0;
+            return;
+        }
+        let pushing = self.players.iter().enumerate().find_map(|(index, player)| {
+            let target = (player.x + player.queued_dx, player.y + player.queued_dy);
+            let stopped = player.dx == 0 && player.dy == 0;
+            let pushes = player.queued_dx != 0 || player.queued_dy != 0;
+            (stopped && pushes && is_blastable(target.0, target.1)).then_some((index, target))
+        });
+        let Some((player_index, (x, y))) = pushing else {
+            self.bomb_fuse = 0;
+            return;
+        };
+        self.bomb_fuse += 1;
+        if self.bomb_fuse >= self.config.ticks(BOMB_FUSE_TICKS) {
+            self.bomb_fuse = 0;
+            self.bombs -= 1;
+            self.open_walls.push(OpenWall { x, y, ticks: self.config.ticks(BOMB_HOLE_TICKS) });
+            self.sync_open_walls();
+            self.events.push(GameEvent::WallBlasted { player: player_index, x, y });
+        }
+    }
+
+    /// Counts down the blown-up walls and closes the ones whose time is up,
+    /// unless a Pac-Man or a ghost is standing in the gap
+    fn heal_walls(&mut self) {
+        let before = self.open_walls.len();
+        let occupied: Vec<(i32, i32)> = self.players.iter().map(|player| (player.x, player.y))
+            .chain(self.ghosts.iter().map(|ghost| (ghost.x, ghost.y)))
+            .collect();
+        let events = &mut self.events;
+        self.open_walls.retain_mut(|wall| {
+            if wall.ticks > 0 {
+                wall.ticks -= 1;
+            }
+            let heals = wall.ticks == 0 && !occupied.contains(&(wall.x, wall.y));
+            if heals {
+                events.push(GameEvent::WallHealed { x: wall.x, y: wall.y });
+            }
+            !heals
+        });
+        if self.open_walls.len() != before {
+            self.sync_open_walls();
+        }
+    }
+
+    /// New game plus: brings back one eaten pellet in `NG_PLUS_RESPAWN_SHARE`
+    /// (at least one), picked from the respawn stream
+    ///
+    /// Only regular pellets come back, never under a Pac-Man, and not once the
+    /// maze is cleared.
+    fn respawn_pellets(&mut self) {
+        if self.pellets == 0 {
+            return;
+        }
+        let occupied: Vec<(usize, i32, i32)> = self.players.iter().map(|player| (player.floor, player.x, player.y)).collect();
+        let mut eaten: Vec<usize> = (0..floor_count())
+            .flat_map(|floor| {
+                let rows = floor_rows(floor);
+                (0..GRID_H).flat_map(move |y| (0..GRID_W).map(move |x| (floor, x, y)))
+                    .filter(move |&(_, x, y)| rows[y as usize].as_bytes().get(x as usize) == Some(&b'.'))
+            })
+            .filter(|tile| !occupied.contains(tile))
+            .map(|(floor, x, y)| Game::pellet_index(floor, x, y))
+            .filter(|&index| self.eaten[index])
+            .collect();
+        let count = (eaten.len() / NG_PLUS_RESPAWN_SHARE).max(1).min(eaten.len());
+        for _ in 0..count {
+            let pick = self.rng.respawn.range(0, eaten.len() as i32 - 1) as usize;
+            self.eaten[eaten.swap_remove(pick)] = false;
+        }
+        if count > 0 {
+            self.pellets += count as i32;
+            self.events.push(GameEvent::PelletsRespawned { count: count as i32 });
+        }
+    }
+
+    /// The floor an entity is on after moving from `before` to `after`: walking
+    /// onto stairs takes it to the other floor, standing on them doesn't
+    fn floor_after_move(floor: usize, before: (i32, i32), after: (i32, i32)) -> usize {
+        if before != after && is_stairs(after.0, after.1) {
+            1 - floor.min(1)
+        } else {
+            floor
+        }
+    }
+
+    /// The floor shown on screen: player 1's (the other floor and everyone on
+    /// it are hidden)
+    pub fn view_floor(&self) -> usize {
+        self.players.first().map(|player| player.floor).unwrap_or(0)
+    }
+
+    /// Checks if a tile is one of this game's blown-up walls
+    ///
+    /// Renderers use this rather than `maze::is_open_wall`, which holds the
+    /// walls of whichever game ticked last.
+    pub fn is_open_wall(&self, x: i32, y: i32) -> bool {
+        self.open_walls.iter().any(|wall| (wall.x, wall.y) == (x, y))
+    }
+
+    /// Opens this game's blown-up walls in the maze (see `set_open_walls`)
+    fn sync_open_walls(&self) {
+        set_open_walls(self.open_walls.iter().map(|wall| (wall.x, wall.y)));
+    }
+
+    /// Adds points to the score through `scoring`, multiplied while double points are on
+    ///
+    /// Every score change goes through here, so the multiplier applies to
+    /// pellets, ghosts, fruit and the bonus stage haul alike. Raises
+    /// `ScoreChanged`, and `ExtraLife` for each extra life the new score earns.
+    ///
+    /// # Returns
+    /// The points actually added
+    fn award(&mut self, source: ScoreSource, points: i32) -> i32 {
+        let multiplier = self.score_multiplier();
+        let award = self.scoring.award(source, points, multiplier);
+        self.events.push(GameEvent::ScoreChanged { source, points: award.points, score: self.score() });
+        for _ in 0..award.extra_lives {
+            self.lives += 1;
+            self.events.push(GameEvent::ExtraLife { lives: self.lives });
+        }
+        award.points
+    }
+
+    /// The active player's score
+    pub fn score(&self) -> i32 {
+        self.scoring.score()
+    }
+
+    /// What points are multiplied by right now (1 or `DOUBLE_POINTS_MULTIPLIER`)
+    pub fn score_multiplier(&self) -> i32 {
+        if self.double_points_timer > 0 {
+            DOUBLE_POINTS_MULTIPLIER
+        } else {
+            1
+        }
+    }
+
+    /// The bonus fruit of the current level (see `level_table`)
+    pub fn level_fruit(&self) -> Fruit {
+        level_spec(self.level).fruit
+    }
+
+    /// The frame counter as ticks at `FPS`, for animations that must run at the
+    /// same speed whatever the tick rate
+    pub fn animation_frame(&self) -> u32 {
+        self.config.base_ticks(self.frame)
+    }
+
+    /// Whether frightened ghosts are drawn white this frame
+    ///
+    /// The ghosts flash as many times as the level table says for the current
+    /// level, in the last `flashes * FRIGHT_FLASH_FRAMES` frames of the power
+    /// pellet, each flash white for the first half and blue for the second.
+    pub fn frightened_flash_white(&self) -> bool {
+        let flashes = level_spec(self.level).flashes;
+        let flash_ticks = self.config.ticks(FRIGHT_FLASH_FRAMES);
+        let timer = self.power_pellet_timer;
+        timer > 0
+            && timer <= flashes * flash_ticks
+            && (timer - 1) % flash_ticks >= flash_ticks / 2
+    }
+
+    /// Share of the frightened time left, from 1 right after a power pellet to 0
+    /// when the ghosts aren't frightened
+    pub fn power_remaining(&self) -> f32 {
+        let total = self.config.ticks(self.tweaks.frightened(level_spec(self.level).frightened_ticks));
+        if self.power_pellet_timer <= 0 || total <= 0 {
+            0.0
+        } else {
+            (self.power_pellet_timer as f32 / total as f32).min(1.0)
+        }
+    }
+
     /// Updates the power pellet timer and removes vulnerability when it expires
     fn update_power_pellet_timer(&mut self) {
-        if self.power_pellet_timer > 0 {
+        if self.power_pellet_timer > 0 && !self.power_frozen {
             self.power_pellet_timer -= 1;
             
             // When timer expires, make ghosts normal again
@@ -231,78 +1185,168 @@ impl Game {
                 for ghost in &mut self.ghosts {
                     ghost.vulnerable = false;
                 }
+                self.events.push(GameEvent::PowerPelletEnded);
             }
         }
     }
 
-    /// Checks for collisions between player and ghosts
+    /// Checks for collisions between every player and the ghosts
     /// 
-    /// If player collides with a vulnerable ghost: eat it and score points
-    /// If player collides with a normal ghost: player dies
-    fn check_collisions(&mut self) {
-        for ghost in &mut self.ghosts {
-            // Check if player and ghost are on the same tile
-            if self.player.x == ghost.x && self.player.y == ghost.y {
-                if ghost.vulnerable {
-                    // Eat the ghost! Score increases with each ghost eaten
-                    let multiplier_index = self.ghost_eaten_count.min(3) as usize;
-                    self.score += SCORE_GHOST[multiplier_index];
-                    self.ghost_eaten_count += 1;
-                    
-                    // Reset ghost to center
-                    ghost.reset_to_center();
-                } else {
-                    // Player hit a normal ghost - game over
-                    self.alive = false;
-                    break;
+    /// If a player collides with a vulnerable ghost: eat it and score points
+    /// If a player collides with a normal ghost: a life is lost
+    ///
+    /// Moves are whole tiles, so a Pac-Man and a ghost walking towards each other
+    /// can swap tiles in one tick without ever sharing one; that counts as a
+    /// collision too.
+    ///
+    /// # Arguments
+    /// * `players_before` - Each player's tile before this tick's moves
+    /// * `ghosts_before` - Each ghost's tile before this tick's moves
+    fn check_collisions(&mut self, players_before: &[(i32, i32)], ghosts_before: &[(i32, i32)]) {
+        let mut caught = false;
+        let mut eaten_ghosts = Vec::new();
+        for (player_index, player) in self.players.iter().enumerate() {
+            for (ghost_index, ghost) in self.ghosts.iter_mut().enumerate() {
+                // Only on the same floor
+                if player.floor != ghost.floor {
+                    continue;
+                }
+                // Same tile now, or passed through each other on the way
+                let same_tile = player.x == ghost.x && player.y == ghost.y;
+                let swapped = players_before.get(player_index) == Some(&(ghost.x, ghost.y))
+                    && ghosts_before.get(ghost_index) == Some(&(player.x, player.y));
+                if same_tile || swapped {
+                    if ghost.vulnerable {
+                        // Eat the ghost (scored below) and send it back to the ghost house
+                        eaten_ghosts.push(ghost_index);
+                        ghost.reset_to_spawn(spawn_points().ghosts[ghost_index]);
+                    } else {
+                        // Player hit a normal ghost
+                        caught = true;
+                    }
                 }
             }
         }
-    }
 
-    /// Draws the entire game frame
-    /// 
-    /// This function:
-    /// 1. Updates the render cache
-    /// 2. Draws the game (maze, player, ghosts, pellets)
-    /// 3. Draws the score
-    /// 4. Presents the frame to the screen
-    pub fn draw(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) -> Result<(), String> {
-        let (window_width, window_height) = canvas.window().size();
-        
-        // Update render cache (handles window resizing)
-        self.render_cache.update_cache(window_width as i32, window_height as i32);
+        // Score increases with each ghost eaten
+        for ghost_index in eaten_ghosts {
+            let multiplier_index = self.ghost_eaten_count.min(3) as usize;
+            let points = self.award(ScoreSource::Ghost, SCORE_GHOST[multiplier_index]);
+            self.ghost_eaten_count += 1;
+            self.events.push(GameEvent::GhostEaten { ghost: ghost_index, points });
+        }
         
-        // Prepare ghost data for rendering (position and vulnerability state)
-        let ghost_data: Vec<(i32, i32, bool)> = self.ghosts.iter()
-            .map(|ghost| (ghost.x, ghost.y, ghost.vulnerable))
-            .collect();
+        if caught {
+            self.lose_life();
+        }
+    }
 
-        // Draw game elements (this clears the canvas)
-        draw_game(
-            canvas,
-            &mut self.render_cache,
-            &self.eaten,
-            self.player.x,
-            self.player.y,
-            &ghost_data,
-            self.power_pellet_timer,
-            self.frame,
-            self.alive,
-        )?;
+    /// Takes a life from the (shared) pool and respawns everyone,
+    /// or ends the game when no lives remain
+    fn lose_life(&mut self) {
+        self.lives -= 1;
+        self.events.push(GameEvent::PlayerDied { lives_left: self.lives });
+        // The ghosts go back into the house and come out on the shared count
+        self.global_dot_counter = Some(0);
         
-        // Draw score after game (so it appears on top)
-        draw_score(
-            canvas, 
-            self.score, 
-            self.render_cache.ox, 
-            self.render_cache.oy, 
-            self.render_cache.sw
-        );
+        // Alternating mode: hand the controls to the other player if they still have lives
+        if self.waiting_turn.as_ref().map(|waiting| waiting.lives > 0).unwrap_or(false) {
+            self.swap_turns();
+            self.respawn();
+            return;
+        }
         
-        // Present the frame to the screen
-        canvas.present();
-        Ok(())
+        if self.lives > 0 {
+            self.respawn();
+        } else {
+            self.alive = false;
+            self.events.push(GameEvent::GameOver);
+        }
+    }
+
+    /// Swaps the active player's progress with the waiting player's (alternating mode)
+    fn swap_turns(&mut self) {
+        if let Some(waiting) = self.waiting_turn.take() {
+            let current = TurnState {
+                scoring: std::mem::replace(&mut self.scoring, waiting.scoring),
+                lives: self.lives,
+                level: self.level,
+                eaten: std::mem::replace(&mut self.eaten, waiting.eaten),
+                pellets: self.pellets,
+                fruits: std::mem::replace(&mut self.fruits, waiting.fruits),
+            };
+            self.lives = waiting.lives;
+            self.level = waiting.level;
+            self.pellets = waiting.pellets;
+            self.waiting_turn = Some(current);
+            self.turn = 1 - self.turn;
+        }
     }
+
+    /// Distance from the Pac-Men to the nearest ghost out of the ghost house,
+    /// and whether that ghost is frightened
+    ///
+    /// The distance is in tiles along the grid (walls and the tunnel aside).
+    /// None while no ghost is out, and in the ghost-free bonus stage.
+    pub fn nearest_ghost(&self) -> Option<(i32, bool)> {
+        if self.phase != GamePhase::Playing {
+            return None;
+        }
+        self.ghosts.iter()
+            .filter(|ghost| !ghost.in_house)
+            .flat_map(|ghost| {
+                self.players.iter().map(move |player| {
+                    ((ghost.x - player.x).abs() + (ghost.y - player.y).abs(), ghost.vulnerable)
+                })
+            })
+            .min_by_key(|&(distance, _)| distance)
+    }
+
+    /// Returns both players' scores in alternating mode, indexed by player number
+    pub fn turn_scores(&self) -> Option<[i32; 2]> {
+        self.waiting_turn.as_ref().map(|waiting| {
+            if self.turn == 0 {
+                [self.score(), waiting.scoring.score()]
+            } else {
+                [waiting.scoring.score(), self.score()]
+            }
+        })
+    }
+
+    /// Draws the entire game frame: maze, then entities, then HUD
+    /// 
+    /// The caller presents the frame (after adding its own layers or capturing
+    /// it for clips, if on).
+    pub fn draw(&self, renderer: &mut dyn Renderer) -> Result<(), String> {
+        renderer.draw_maze(self)?;
+        renderer.draw_entities(self)?;
+        renderer.draw_hud(self)
+    }
+}
+
+/// Pellets each ghost waits for in the ghost house on a level (see `GHOST_DOT_LIMITS`)
+pub fn ghost_dot_limits(level: u32) -> [u32; 3] {
+    let row = (level.max(1) - 1) as usize;
+    GHOST_DOT_LIMITS[row.min(GHOST_DOT_LIMITS.len() - 1)]
 }
 
+/// FNV-1a over a sequence of integers (see `Game::state_hash`)
+pub(crate) struct StateHash(u64);
+
+impl StateHash {
+    pub(crate) fn new() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+
+    /// Mixes in one value, byte by byte in little-endian order
+    pub(crate) fn add(&mut self, value: i64) {
+        for byte in value.to_le_bytes() {
+            self.0 ^= byte as u64;
+            self.0 = self.0.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u64 {
+        self.0
+    }
+}
@@ -1,10 +1,21 @@
 //! Game configuration for player modes and roles
 
-/// Game mode: Single player or Multiplayer
+use crate::constants::{CORNERING_WINDOW, FPS};
+
+/// Game mode: Single player, Multiplayer (Pac-Man vs ghost), Co-op (two Pac-Men),
+/// Alternating (two players take turns, swapping on each death),
+/// Time trial (clear the maze as fast as possible against your personal best),
+/// Race (one of two games side by side, each player clearing their own maze) or
+/// Demo (nobody plays: the attract demo behind the main menu)
 #[derive(Clone, Copy, PartialEq)]
 pub enum GameMode {
     SinglePlayer,
     Multiplayer,
+    Coop,
+    Alternating,
+    TimeTrial,
+    Race,
+    Demo,
 }
 
 /// Player role: Pac-Man or Ghost
@@ -14,9 +25,83 @@ pub enum PlayerRole {
     Ghost,
 }
 
+/// Sets of ghost AIs to play against (see `ghost::preset_ai`)
+#[derive(Clone, Copy, PartialEq)]
+pub enum GhostPreset {
+    Classic,
+    Aggressive,
+    Random,
+    Coward,
+}
+
+impl GhostPreset {
+    /// The preset that is hardest to play against, for new game plus
+    pub const HARDEST: GhostPreset = GhostPreset::Aggressive;
+
+    /// Every preset, in menu order
+    pub const ALL: [GhostPreset; 4] = [GhostPreset::Classic, GhostPreset::Aggressive, GhostPreset::Random, GhostPreset::Coward];
+
+    /// Display name, also used (in lower case) in config, save and recording files
+    pub fn name(self) -> &'static str {
+        match self {
+            GhostPreset::Classic => "Classic",
+            GhostPreset::Aggressive => "Aggressive",
+            GhostPreset::Random => "Random",
+            GhostPreset::Coward => "Coward",
+        }
+    }
+
+    /// Looks a preset up by name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|preset| preset.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The preset after this one in menu order, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&preset| preset == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+/// Ways the computer-controlled Pac-Man plays (see `pacman_ai::style_ai`)
+#[derive(Clone, Copy, PartialEq)]
+pub enum PacmanStyle {
+    Greedy,
+    Cautious,
+    Speedrunner,
+    Troll,
+}
+
+impl PacmanStyle {
+    /// Every style, in menu order
+    pub const ALL: [PacmanStyle; 4] = [PacmanStyle::Greedy, PacmanStyle::Cautious, PacmanStyle::Speedrunner, PacmanStyle::Troll];
+
+    /// Display name, also used (in lower case) in config, save and recording files
+    pub fn name(self) -> &'static str {
+        match self {
+            PacmanStyle::Greedy => "Greedy",
+            PacmanStyle::Cautious => "Cautious",
+            PacmanStyle::Speedrunner => "Speedrunner",
+            PacmanStyle::Troll => "Troll",
+        }
+    }
+
+    /// Looks a style up by name, ignoring case
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|style| style.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The style after this one in menu order, wrapping around
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&style| style == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
 /// Game configuration
 /// 
 /// Determines how the game should behave based on player choices
+#[derive(Clone, Copy)]
 pub struct GameConfig {
     /// Game mode (single or multiplayer)
     pub mode: GameMode,
@@ -24,16 +109,57 @@ pub struct GameConfig {
     pub player1_role: PlayerRole,
     /// Player 2 role (only used in multiplayer)
     pub player2_role: Option<PlayerRole>,
+    /// Ticks a turn may be input early or late and still cut the corner (see `player.rs`)
+    pub cornering_window: i32,
+    /// Ghosts target like the arcade game, bugs included, rather than as intended (see `ghost.rs`)
+    pub arcade_quirks: bool,
+    /// Which AIs the computer-controlled ghosts use
+    pub ghost_preset: GhostPreset,
+    /// How the computer-controlled Pac-Man plays
+    pub pacman_style: PacmanStyle,
+    /// Simulation ticks per second (one of `TICK_RATES`); durations given at
+    /// `FPS` are scaled to it, so the game plays at the same speed, finer or coarser
+    pub tick_rate: u32,
+    /// Ice maze: Pac-Man slides on until a wall or a junction and only turns
+    /// there (see `player.rs`)
+    pub ice: bool,
+    /// New game plus, played after winning a campaign: the campaign starts
+    /// over when won, and eaten pellets come back now and then (see `Game::tick`)
+    pub ng_plus: bool,
 }
 
 impl GameConfig {
-    /// Creates a new game configuration
+    /// Creates a new game configuration with the default cornering window,
+    /// arcade-accurate ghost targeting and the classic ghosts
     pub fn new(mode: GameMode, player1_role: PlayerRole, player2_role: Option<PlayerRole>) -> Self {
         Self {
             mode,
             player1_role,
             player2_role,
+            cornering_window: CORNERING_WINDOW,
+            arcade_quirks: true,
+            ghost_preset: GhostPreset::Classic,
+            pacman_style: PacmanStyle::Greedy,
+            tick_rate: FPS,
+            ice: false,
+            ng_plus: false,
+        }
+    }
+
+    /// A duration in ticks at `FPS` (as the constants and the level table
+    /// give them) in ticks at this game's tick rate, rounded and never
+    /// brought down to 0
+    pub fn ticks(&self, ticks: i32) -> i32 {
+        if ticks <= 0 || self.tick_rate == FPS {
+            return ticks;
         }
+        ((ticks as i64 * self.tick_rate as i64 + FPS as i64 / 2) / FPS as i64).max(1) as i32
+    }
+
+    /// Ticks at this game's tick rate as ticks at `FPS` (for showing times
+    /// and keeping statistics the same whatever the tick rate)
+    pub fn base_ticks(&self, ticks: u32) -> u32 {
+        (ticks as u64 * FPS as u64 / self.tick_rate.max(1) as u64) as u32
     }
     
     /// Returns true if Pac-Man should be AI-controlled
@@ -43,11 +169,17 @@ impl GameConfig {
                 // In single player, if player chose ghost, Pac-Man is AI
                 self.player1_role == PlayerRole::Ghost
             }
+            GameMode::TimeTrial | GameMode::Race => false,
+            GameMode::Demo => true,
             GameMode::Multiplayer => {
                 // In multiplayer, if no one is Pac-Man, it's AI (shouldn't happen, but handle it)
                 self.player1_role != PlayerRole::PacMan && 
                 self.player2_role != Some(PlayerRole::PacMan)
             }
+            GameMode::Coop | GameMode::Alternating => {
+                // In co-op and alternating play, Pac-Man is always human
+                false
+            }
         }
     }
     
@@ -58,11 +190,16 @@ impl GameConfig {
                 // In single player, if player chose Pac-Man, ghosts are AI
                 self.player1_role == PlayerRole::PacMan
             }
+            GameMode::TimeTrial | GameMode::Race | GameMode::Demo => true,
             GameMode::Multiplayer => {
                 // In multiplayer, if no one is a ghost, they're AI (shouldn't happen, but handle it)
                 self.player1_role != PlayerRole::Ghost && 
                 self.player2_role != Some(PlayerRole::Ghost)
             }
+            GameMode::Coop | GameMode::Alternating => {
+                // In co-op and alternating play, ghosts are always AI
+                true
+            }
         }
     }
 }
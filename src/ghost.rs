@@ -2,15 +2,26 @@
 //! 
 //! This module manages ghost behavior, including:
 //! - AI decision making (normal and vulnerable states)
+//! - Chase targets, after the arcade ghosts' personalities
 //! - Movement with sub-frame precision
 //! - Tunnel wrapping
+//!
+//! Where a ghost heads while it isn't frightened is up to its `GhostAi`,
+//! picked per ghost by the game's `GhostPreset` (see `preset_ai`). The
+//! classic ghosts each chase their own target tile: the red one Pac-Man
+//! himself, the pink one a few tiles ahead of him and the cyan one a tile
+//! mirrored through a point ahead of him from the red ghost. The arcade game
+//! got "ahead" wrong when Pac-Man faces up, shifting the point as far to the
+//! left; `arcade_quirks` reproduces that, otherwise the intended tile is used.
 
 use crate::constants::{
-    GRID_W, TUNNEL_ROW, 
+    GRID_W, GRID_H,
     GHOST_START_X, GHOST_START_Y,
-    GHOST_MOVE_SUBFRAMES, GHOST_THINK_INTERVAL
+    PINK_GHOST_LOOKAHEAD, CYAN_GHOST_LOOKAHEAD,
+    COWARD_GHOST_DISTANCE, COWARD_GHOST_CORNERS
 };
-use crate::maze::is_wall;
+use crate::game_config::GhostPreset;
+use crate::maze::{is_wall, wrap_tunnel};
 use crate::rng::Lfsr;
 
 /// All possible movement directions (up, down, left, right)
@@ -21,7 +32,133 @@ const MOVEMENT_DIRECTIONS: [(i32, i32); 4] = [
     (1, 0),   // Right
 ];
 
+/// Directions in the order the arcade ghosts prefer them when two are equally good
+const PREFERRED_DIRECTIONS: [(i32, i32); 4] = [
+    (0, -1),  // Up
+    (-1, 0),  // Left
+    (0, 1),   // Down
+    (1, 0),   // Right
+];
+
+/// Tile `tiles` ahead of Pac-Man
+///
+/// With `arcade_quirks`, facing up also shifts it `tiles` to the left, like the
+/// arcade game's overflow bug.
+fn tile_ahead(pacman: (i32, i32), heading: (i32, i32), tiles: i32, arcade_quirks: bool) -> (i32, i32) {
+    let (x, y) = (pacman.0 + heading.0 * tiles, pacman.1 + heading.1 * tiles);
+    if arcade_quirks && heading == (0, -1) {
+        (x - tiles, y)
+    } else {
+        (x, y)
+    }
+}
+
+/// What a ghost AI sees when choosing where to go
+pub struct ChaseView {
+    /// The ghost's tile
+    pub ghost: (i32, i32),
+    /// Tile of the Pac-Man being chased
+    pub pacman: (i32, i32),
+    /// That Pac-Man's direction
+    pub heading: (i32, i32),
+    /// Tile of the red ghost (the cyan ghost's target depends on it)
+    pub red_ghost: (i32, i32),
+    /// Reproduce the arcade game's targeting bug when Pac-Man faces up
+    pub arcade_quirks: bool,
+}
+
+/// How a ghost chooses where to go while it isn't frightened
+///
+/// Implementations keep no state of their own (the ghost and the game do),
+/// so games stay cheap to clone and replay exactly.
+pub trait GhostAi: Sync {
+    /// Tile to head for, or None to take a random turn at every decision
+    fn target(&self, view: &ChaseView) -> Option<(i32, i32)>;
+}
+
+/// Heads straight for Pac-Man (the classic red ghost)
+pub struct Shadow;
+
+/// Aims a few tiles ahead of Pac-Man to cut him off (the classic pink ghost)
+pub struct Ambusher;
+
+/// Aims for the red ghost's tile mirrored through a point ahead of Pac-Man,
+/// closing in from the other side (the classic cyan ghost)
+pub struct Flanker;
+
+/// Ignores Pac-Man and turns at random
+pub struct Wanderer;
+
+/// Chases Pac-Man from afar but runs for its corner once within
+/// `COWARD_GHOST_DISTANCE` tiles of him
+pub struct Coward {
+    pub corner: (i32, i32),
+}
+
+impl GhostAi for Shadow {
+    fn target(&self, view: &ChaseView) -> Option<(i32, i32)> {
+        Some(view.pacman)
+    }
+}
+
+impl GhostAi for Ambusher {
+    fn target(&self, view: &ChaseView) -> Option<(i32, i32)> {
+        Some(tile_ahead(view.pacman, view.heading, PINK_GHOST_LOOKAHEAD, view.arcade_quirks))
+    }
+}
+
+impl GhostAi for Flanker {
+    fn target(&self, view: &ChaseView) -> Option<(i32, i32)> {
+        let (pivot_x, pivot_y) = tile_ahead(view.pacman, view.heading, CYAN_GHOST_LOOKAHEAD, view.arcade_quirks);
+        Some((2 * pivot_x - view.red_ghost.0, 2 * pivot_y - view.red_ghost.1))
+    }
+}
+
+impl GhostAi for Wanderer {
+    fn target(&self, _view: &ChaseView) -> Option<(i32, i32)> {
+        None
+    }
+}
+
+impl GhostAi for Coward {
+    fn target(&self, view: &ChaseView) -> Option<(i32, i32)> {
+        let (to_x, to_y) = (view.pacman.0 - view.ghost.0, view.pacman.1 - view.ghost.1);
+        if to_x * to_x + to_y * to_y < COWARD_GHOST_DISTANCE * COWARD_GHOST_DISTANCE {
+            Some(self.corner)
+        } else {
+            Some(view.pacman)
+        }
+    }
+}
+
+/// Each coward ghost with its own corner
+static COWARDS: [Coward; 3] = [
+    Coward { corner: COWARD_GHOST_CORNERS[0] },
+    Coward { corner: COWARD_GHOST_CORNERS[1] },
+    Coward { corner: COWARD_GHOST_CORNERS[2] },
+];
+
+/// The AI a preset gives a ghost
+///
+/// - Classic: the arcade ghosts' personalities, one each
+/// - Aggressive: two ghosts on Pac-Man's heels and one cutting him off
+/// - Random: every ghost wanders
+/// - Coward: every ghost keeps its distance, each retreating to its own corner
+///
+/// # Arguments
+/// * `ghost_index` - Which ghost: 0 is red, 1 pink, 2 cyan
+pub fn preset_ai(preset: GhostPreset, ghost_index: usize) -> &'static dyn GhostAi {
+    match (preset, ghost_index) {
+        (GhostPreset::Classic, 0) | (GhostPreset::Aggressive, 0 | 2) => &Shadow,
+        (GhostPreset::Classic, 1) | (GhostPreset::Aggressive, _) => &Ambusher,
+        (GhostPreset::Classic, _) => &Flanker,
+        (GhostPreset::Random, _) => &Wanderer,
+        (GhostPreset::Coward, _) => &COWARDS[ghost_index % COWARDS.len()],
+    }
+}
+
 /// Represents a ghost in the game
+#[derive(Clone)]
 pub struct Ghost {
     /// Current X position on the grid
     pub x: i32,
@@ -33,12 +170,18 @@ pub struct Ghost {
     pub dy: i32,
     /// Sub-frame counter: increments each frame, resets when ghost moves
     pub sub_frame_counter: i32,
-    /// Timer that counts up to GHOST_THINK_INTERVAL before making AI decision
+    /// Timer that counts up to the think interval (see `update`) before making AI decision
     pub think_timer: i32,
     /// Whether the ghost is vulnerable (can be eaten by player)
     pub vulnerable: bool,
     /// Reusable buffer for AI pathfinding (avoids allocations)
-    pub options_buffer: Vec<(i32, i32, i32)>,  // (dx, dy, priority)
+    pub options_buffer: Vec<(i32, i32, i32)>,  // (dx, dy, priority when fleeing or distance to target)
+    /// Tile the AI is currently chasing (or fleeing from)
+    pub target: (i32, i32),
+    /// Waiting in the ghost house to be let out (see `Game::release_ghosts`); it does not move
+    pub in_house: bool,
+    /// Floor of a two-floor maze it is on (0 = ground floor, where the ghost house is)
+    pub floor: usize,
 }
 
 impl Ghost {
@@ -62,27 +205,34 @@ impl Ghost {
             think_timer: 0,
             vulnerable: false,
             options_buffer: Vec::with_capacity(4),  // Max 4 directions
+            target: (x, y),
+            in_house: false,
+            floor: 0,
         }
     }
 
     /// Makes an AI decision about which direction to move
     /// 
     /// When vulnerable: tries to flee from the player
-    /// When normal: randomly chooses a valid direction (avoids reversing unless stuck)
+    /// When normal: heads for its target tile, or turns at random when
+    /// wandering (avoids reversing unless stuck)
     /// 
     /// # Arguments
     /// * `player_x` - Player's X position
     /// * `player_y` - Player's Y position
+    /// * `wander` - The ghost's AI has no target (see `GhostAi::target`)
     /// * `rng` - Random number generator for decision making
-    pub fn think(&mut self, player_x: i32, player_y: i32, rng: &mut Lfsr) {
+    pub fn think(&mut self, player_x: i32, player_y: i32, wander: bool, rng: &mut Lfsr) {
         self.options_buffer.clear();
         
         if self.vulnerable {
             // FLEE MODE: Try to move away from the player
             self.think_flee_mode(player_x, player_y, rng);
+        } else if wander {
+            self.think_wander_mode(rng);
         } else {
-            // NORMAL MODE: Randomly choose a direction
-            self.think_normal_mode(rng);
+            // NORMAL MODE: Head for the target
+            self.think_normal_mode();
         }
     }
 
@@ -134,16 +284,41 @@ impl Ghost {
         self.dy = dy;
     }
 
-    /// AI logic for when ghost is normal (random movement)
-    fn think_normal_mode(&mut self, rng: &mut Lfsr) {
-        // Check all possible directions
+    /// AI logic for a wandering ghost: any open direction but back
+    fn think_wander_mode(&mut self, rng: &mut Lfsr) {
         for (dx, dy) in MOVEMENT_DIRECTIONS.iter() {
+            if !is_wall(self.x + dx, self.y + dy) && (*dx, *dy) != (-self.dx, -self.dy) {
+                self.options_buffer.push((*dx, *dy, 0));
+            }
+        }
+
+        // If no valid directions, reverse (only option when stuck)
+        if self.options_buffer.is_empty() {
+            self.dx = -self.dx;
+            self.dy = -self.dy;
+            return;
+        }
+
+        let random_index = rng.range(0, self.options_buffer.len() as i32 - 1) as usize;
+        let (dx, dy, _) = self.options_buffer[random_index];
+        self.dx = dx;
+        self.dy = dy;
+    }
+
+    /// AI logic for when ghost is normal (chasing its target)
+    /// 
+    /// Like the arcade ghosts, takes the open direction whose next tile is
+    /// closest in a straight line to the target, without looking further ahead.
+    fn think_normal_mode(&mut self) {
+        // Check all possible directions
+        for (dx, dy) in PREFERRED_DIRECTIONS.iter() {
             let new_x = self.x + dx;
             let new_y = self.y + dy;
             
             // Skip if it's a wall or would reverse direction (unless stuck)
             if !is_wall(new_x, new_y) && (*dx, *dy) != (-self.dx, -self.dy) {
-                self.options_buffer.push((*dx, *dy, 0));  // All have same priority
+                let (to_x, to_y) = (self.target.0 - new_x, self.target.1 - new_y);
+                self.options_buffer.push((*dx, *dy, to_x * to_x + to_y * to_y));
             }
         }
 
@@ -154,11 +329,15 @@ impl Ghost {
             return;
         }
         
-        // Choose a random valid direction
-        let random_index = rng.range(0, self.options_buffer.len() as i32 - 1) as usize;
-        let (dx, dy, _) = self.options_buffer[random_index];
-        self.dx = dx;
-        self.dy = dy;
+        // Closest to the target; the first such in preference order on a tie
+        let mut best = self.options_buffer[0];
+        for &option in &self.options_buffer[1..] {
+            if option.2 < best.2 {
+                best = option;
+            }
+        }
+        self.dx = best.0;
+        self.dy = best.1;
     }
 
     /// Updates the ghost each frame
@@ -171,32 +350,41 @@ impl Ghost {
     /// 
     /// # Arguments
     /// * `rng` - Random number generator
-    /// * `player_x` - Player's X position (for AI)
-    /// * `player_y` - Player's Y position (for AI)
-    pub fn update(&mut self, rng: &mut Lfsr, player_x: i32, player_y: i32) {
+    /// * `player_x` - Player's X position (fled from while vulnerable)
+    /// * `player_y` - Player's Y position (fled from while vulnerable)
+    /// * `target` - Tile chased otherwise, None to wander (see `GhostAi`)
+    /// * `move_subframes` - Sub-frames per tile (see `level_table`)
+    /// * `think_interval` - Ticks between AI decisions (`GHOST_THINK_INTERVAL` at the game's tick rate)
+    pub fn update(
+        &mut self,
+        rng: &mut Lfsr,
+        player_x: i32,
+        player_y: i32,
+        target: Option<(i32, i32)>,
+        move_subframes: i32,
+        think_interval: i32,
+    ) {
+        let wander = target.is_none();
+        self.target = match target {
+            _ if self.vulnerable => (player_x, player_y),
+            Some(target) => target,
+            None => (self.x, self.y),
+        };
+        
         // Update AI decision timer
         self.think_timer += 1;
-        if self.think_timer >= GHOST_THINK_INTERVAL {
-            self.think(player_x, player_y, rng);
+        if self.think_timer >= think_interval {
+            self.think(player_x, player_y, wander, rng);
             self.think_timer = 0;
         }
 
         // Move ghost when enough sub-frames have passed
         self.sub_frame_counter += 1;
-        if self.sub_frame_counter >= GHOST_MOVE_SUBFRAMES {
+        if self.sub_frame_counter >= move_subframes {
             self.sub_frame_counter = 0;
             
-            // Calculate new position
-            let mut new_x = self.x + self.dx;
-            let mut new_y = self.y + self.dy;
-            
-            // Handle tunnel wrapping
-            if new_y == TUNNEL_ROW && new_x < 0 {
-                new_x = GRID_W - 1;
-            }
-            if new_y == TUNNEL_ROW && new_x >= GRID_W {
-                new_x = 0;
-            }
+            // Calculate new position, wrapping around through a tunnel
+            let (new_x, new_y) = wrap_tunnel(self.x + self.dx, self.y + self.dy);
             
             // Move if there's no wall, otherwise stop and re-think
             if !is_wall(new_x, new_y) {
@@ -206,17 +394,59 @@ impl Ghost {
                 // Hit a wall, stop and make a new decision
                 self.dx = 0;
                 self.dy = 0;
-                self.think(player_x, player_y, rng);
+                self.think(player_x, player_y, wander, rng);
+            }
+        }
+    }
+
+    /// Direction the ghost's eyes look: where it is moving, or toward its
+    /// target while it is standing still
+    pub fn look_direction(&self) -> (i32, i32) {
+        if self.dx != 0 || self.dy != 0 {
+            return (self.dx, self.dy);
+        }
+        let (to_x, to_y) = (self.target.0 - self.x, self.target.1 - self.y);
+        if to_x.abs() >= to_y.abs() {
+            (to_x.signum(), 0)
+        } else {
+            (0, to_y.signum())
+        }
+    }
+
+    /// Tile where the ghost will next have to choose a direction: the first
+    /// tile ahead with a side opening or a wall in front
+    /// 
+    /// Returns None while standing still.
+    pub fn next_turn(&self) -> Option<(i32, i32)> {
+        if self.dx == 0 && self.dy == 0 {
+            return None;
+        }
+        let (mut x, mut y) = (self.x, self.y);
+        for _ in 0..GRID_W + GRID_H {
+            let (next_x, next_y) = wrap_tunnel(x + self.dx, y + self.dy);
+            if is_wall(next_x, next_y) {
+                return Some((x, y));
+            }
+            x = next_x;
+            y = next_y;
+            // A side opening means a decision here
+            let (side_x, side_y) = (self.dy, self.dx);
+            if !is_wall(x + side_x, y + side_y) || !is_wall(x - side_x, y - side_y) {
+                return Some((x, y));
             }
         }
+        None
     }
 
-    /// Resets the ghost to the center starting position
+    /// Sends the ghost back to its starting tile in the ghost house
     /// 
     /// Called when the ghost is eaten by the player
-    pub fn reset_to_center(&mut self) {
-        self.x = GHOST_START_X;
-        self.y = GHOST_START_Y;
+    /// 
+    /// # Arguments
+    /// * `spawn` - The ghost's spawn point (see `maze::spawn_points`)
+    pub fn reset_to_spawn(&mut self, spawn: (i32, i32)) {
+        (self.x, self.y) = spawn;
+        self.floor = 0;
         self.dx = 0;
         self.dy = -1;  // Start moving up
     }
@@ -239,23 +469,17 @@ impl Ghost {
     }
     
     /// Updates only movement for player-controlled ghost (no AI)
-    pub fn update_movement_only(&mut self) {
+    /// 
+    /// # Arguments
+    /// * `move_subframes` - Sub-frames per tile (see `level_table`)
+    pub fn update_movement_only(&mut self, move_subframes: i32) {
         // Move ghost when enough sub-frames have passed
         self.sub_frame_counter += 1;
-        if self.sub_frame_counter >= GHOST_MOVE_SUBFRAMES {
+        if self.sub_frame_counter >= move_subframes {
             self.sub_frame_counter = 0;
             
-            // Calculate new position
-            let mut new_x = self.x + self.dx;
-            let mut new_y = self.y + self.dy;
-            
-            // Handle tunnel wrapping
-            if new_y == TUNNEL_ROW && new_x < 0 {
-                new_x = GRID_W - 1;
-            }
-            if new_y == TUNNEL_ROW && new_x >= GRID_W {
-                new_x = 0;
-            }
+            // Calculate new position, wrapping around through a tunnel
+            let (new_x, new_y) = wrap_tunnel(self.x + self.dx, self.y + self.dy);
             
             // Move if there's no wall, otherwise stop
             if !is_wall(new_x, new_y) {
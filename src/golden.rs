@@ -0,0 +1,233 @@
+//! Golden replays: known-good runs that gameplay changes are checked against
+//!
+//! A golden replay is an input recording (see `tas`) with a `.golden` file
+//! next to it holding how the run ended when it was known to be right:
+//!
+//! ```text
+//! paclike-golden 1
+//! ticks 2400 score 1730 lives 0 level 1 pellets 171 alive false rng 9b1e hash 5d0c1e9a2b7f4431
+//! ```
+//!
+//! Checking one plays the recording's inputs headlessly from its header
+//! (maze, level, seed, ghost preset and the rest) and compares the end state
+//! with the golden one, the state hash above all. Only the inputs are used:
+//! the recording's own per-tick checksums are not, so a change that means to
+//! alter the game (sub-tile movement, a new ghost AI) is caught by the end
+//! state alone, and regenerating the golden file is all it takes to accept it.
+//!
+//! `paclike_2600_rs golden [--update] [paths]` checks (or with `--update`
+//! regenerates) every `.tas` file given, or in the directories given, or in
+//! `tests/golden`. Tests use the same functions:
+//!
+//! ```text
+//! #[test]
+//! fn first_level_still_plays_the_same() {
+//!     paclike_2600_rs::golden::assert_golden("tests/golden/first_level.tas");
+//! }
+//! ```
+//!
+//! Golden replays are played without gameplay hooks and on the built-in
+//! mazes; a recording made on a `--maze` file is refused.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::constants::{MAZE_1, MAZE_2};
+use crate::game::Game;
+use crate::maze::set_current_maze;
+use crate::tas::TasPlayback;
+
+/// First line of every golden file
+const GOLDEN_MAGIC: &str = "paclike-golden 1";
+
+/// Extension of golden files (next to the recording, same file name)
+const GOLDEN_EXTENSION: &str = "golden";
+
+/// Where golden replays are looked for when no path is given
+pub const DEFAULT_GOLDEN_DIR: &str = "tests/golden";
+
+/// What the `golden` command checks or regenerates
+#[derive(Default)]
+pub struct GoldenOptions {
+    /// Regenerate the golden files instead of checking them
+    pub update: bool,
+    /// Recordings and directories of recordings (`DEFAULT_GOLDEN_DIR` if none)
+    pub paths: Vec<PathBuf>,
+}
+
+/// How a run ended
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct EndState {
+    /// Ticks played (a run stops early once the game is over)
+    pub ticks: u32,
+    pub score: i32,
+    pub lives: i32,
+    pub level: u32,
+    /// Pellets left in the maze
+    pub pellets: i32,
+    pub alive: bool,
+    /// RNG state (see `RngStreams::state`)
+    pub rng: u16,
+    /// See `Game::state_hash`
+    pub hash: u64,
+}
+
+impl EndState {
+    /// The state of a game after some ticks
+    pub fn of(game: &Game, ticks: u32) -> Self {
+        Self {
+            ticks,
+            score: game.score(),
+            lives: game.lives,
+            level: game.level,
+            pellets: game.pellets,
+            alive: game.alive,
+            rng: game.rng.state(),
+            hash: game.state_hash(),
+        }
+    }
+
+    /// Reads the state line of a golden file
+    fn parse(line: &str) -> Result<Self, String> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let field = |key: &str| {
+            words.iter().position(|&word| word == key)
+                .and_then(|index| words.get(index + 1).copied())
+                .ok_or_else(|| format!("no {} in the state line", key))
+        };
+        let number = |key: &str| field(key).and_then(|value| value.parse::<i64>().map_err(|_| format!("bad {}: {}", key, value)));
+        let hex = |key: &str| field(key).and_then(|value| u64::from_str_radix(value, 16).map_err(|_| format!("bad {}: {}", key, value)));
+        Ok(Self {
+            ticks: number("ticks")? as u32,
+            score: number("score")? as i32,
+            lives: number("lives")? as i32,
+            level: number("level")? as u32,
+            pellets: number("pellets")? as i32,
+            alive: field("alive")? == "true",
+            rng: hex("rng")? as u16,
+            hash: hex("hash")?,
+        })
+    }
+
+    /// What differs from another end state, e.g. "score 1730 (golden 1690)"
+    fn differences(&self, golden: &EndState) -> Vec<String> {
+        let pairs = [
+            ("ticks", self.ticks.to_string(), golden.ticks.to_string()),
+            ("score", self.score.to_string(), golden.score.to_string()),
+            ("lives", self.lives.to_string(), golden.lives.to_string()),
+            ("level", self.level.to_string(), golden.level.to_string()),
+            ("pellets", self.pellets.to_string(), golden.pellets.to_string()),
+            ("alive", self.alive.to_string(), golden.alive.to_string()),
+            ("rng", format!("{:04x}", self.rng), format!("{:04x}", golden.rng)),
+            ("hash", format!("{:016x}", self.hash), format!("{:016x}", golden.hash)),
+        ];
+        pairs.into_iter()
+            .filter(|(_, now, then)| now != then)
+            .map(|(key, now, then)| format!("{} {} (golden {})", key, now, then))
+            .collect()
+    }
+}
+
+impl fmt::Display for EndState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "ticks {} score {} lives {} level {} pellets {} alive {} rng {:04x} hash {:016x}",
+            self.ticks, self.score, self.lives, self.level, self.pellets, self.alive, self.rng, self.hash
+        )
+    }
+}
+
+/// Plays a recording's inputs headlessly, from the game its header describes
+///
+/// # Returns
+/// How the run ended, or an error if the recording is for a maze file
+pub fn simulate(playback: &TasPlayback) -> Result<EndState, String> {
+    set_current_maze(if playback.header.maze_index == 1 { &MAZE_2 } else { &MAZE_1 });
+    playback.check_maze()?;
+    let mut game = Game::new_with_seed(playback.header.config(), playback.header.seed);
+    game.start_at_level(playback.header.level);
+    let mut ticks = 0;
+    for frame in playback.frames() {
+        if !game.alive {
+            break;
+        }
+        game.tick(frame.player1_input, frame.player2_input);
+        ticks += 1;
+    }
+    Ok(EndState::of(&game, ticks))
+}
+
+/// The golden file of a recording
+pub fn golden_path(replay: &Path) -> PathBuf {
+    replay.with_extension(GOLDEN_EXTENSION)
+}
+
+/// Reads a recording's golden end state
+pub fn load_golden(replay: &Path) -> Result<EndState, String> {
+    let path = golden_path(replay);
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {} (write it with golden --update)", path.display(), e))?;
+    let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#'));
+    if lines.next() != Some(GOLDEN_MAGIC) {
+        return Err(format!("{} is not a golden file", path.display()));
+    }
+    let state = lines.next().ok_or_else(|| format!("{} has no state line", path.display()))?;
+    EndState::parse(state).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Plays a recording and compares how it ends with its golden file
+///
+/// # Returns
+/// The end state if it matches, or an error naming every difference
+pub fn check_golden(replay: &Path) -> Result<EndState, String> {
+    let golden = load_golden(replay)?;
+    let state = simulate(&TasPlayback::load(replay)?)?;
+    let differences = state.differences(&golden);
+    if differences.is_empty() {
+        Ok(state)
+    } else {
+        Err(format!("{} ended differently: {}", replay.display(), differences.join(", ")))
+    }
+}
+
+/// Plays a recording and writes how it ends as its golden file, on purpose
+/// replacing what was there
+///
+/// # Returns
+/// The end state written
+pub fn update_golden(replay: &Path) -> Result<EndState, String> {
+    let state = simulate(&TasPlayback::load(replay)?)?;
+    let path = golden_path(replay);
+    fs::write(&path, format!("{}\n{}\n", GOLDEN_MAGIC, state))
+        .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+    Ok(state)
+}
+
+/// Checks a golden replay, panicking with the differences if it doesn't
+/// match (for `#[test]` functions)
+pub fn assert_golden(replay: impl AsRef<Path>) {
+    if let Err(e) = check_golden(replay.as_ref()) {
+        panic!("{}", e);
+    }
+}
+
+/// The recordings among some paths: `.tas` files as given, and the `.tas`
+/// files in directories, sorted by name
+pub fn find_replays(paths: &[PathBuf]) -> Result<Vec<PathBuf>, String> {
+    let mut replays = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            replays.push(path.clone());
+            continue;
+        }
+        let entries = fs::read_dir(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let mut found: Vec<PathBuf> = entries
+            .filter_map(|entry| Some(entry.ok()?.path()))
+            .filter(|path| path.extension().is_some_and(|extension| extension == "tas"))
+            .collect();
+        found.sort();
+        replays.extend(found);
+    }
+    Ok(replays)
+}
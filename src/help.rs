@@ -0,0 +1,133 @@
+//! How-to-play screen: controls, scoring and what the ghosts do
+//!
+//! Nothing here is written out by hand where the game has the real value:
+//! the keys come from the current bindings (so the page is right after
+//! rebinding), and points and durations come from `constants.rs`.
+
+use sdl2::keyboard::Scancode;
+use crate::constants::{
+    SCORE_PELLET, SCORE_POWER_PELLET, SCORE_GHOST,
+    BONUS_STAGE_INTERVAL, BONUS_STAGE_DURATION, FRUIT_PELLETS, FPS,
+    DOUBLE_POINTS_PELLETS, DOUBLE_POINTS_TICKS, DOUBLE_POINTS_MULTIPLIER,
+    BOMB_PELLETS, BOMB_HOLE_TICKS
+};
+use crate::fruit::Fruit;
+use crate::game::ghost_dot_limits;
+use crate::level_table::level_spec;
+use crate::font::{draw_lines, line_height, wrap_text, Align};
+use crate::settings::{Controls, EMOTE_KEYS};
+use crate::ui;
+
+/// Widest the wrapped descriptions get in pixels
+const TEXT_WIDTH: i32 = 440;
+
+/// One line of the help page
+enum HelpLine {
+    /// Section title
+    Heading(&'static str),
+    /// Name on the left, value on the right
+    Row(&'static str, String),
+    /// Description, wrapped to `TEXT_WIDTH`
+    Text(String),
+}
+
+/// Whole seconds in a number of ticks
+fn seconds(ticks: i32) -> i32 {
+    ticks / FPS as i32
+}
+
+/// Up / down / left / right keys as one line, e.g. "W S A D"
+fn direction_keys(keys: &[Scancode; 4]) -> String {
+    keys.iter().map(|key| key.name()).collect::<Vec<_>>().join(" ")
+}
+
+/// Builds the page from the current bindings and the game's constants
+fn help_lines(controls: &Controls) -> Vec<HelpLine> {
+    let ghost_points: Vec<String> = SCORE_GHOST.iter().map(|points| points.to_string()).collect();
+    vec![
+        HelpLine::Heading("Controls"),
+        HelpLine::Row("Player 1 (up down left right)", direction_keys(&controls.player1)),
+        HelpLine::Row("Player 2 (up down left right)", direction_keys(&controls.player2)),
+        HelpLine::Row("Pause", controls.pause.name().to_string()),
+        HelpLine::Row("Pause menu", Scancode::Escape.name().to_string()),
+        HelpLine::Row("Ghost emotes (taunt, gg, oops)", EMOTE_KEYS.map(|key| key.name()).join(" ")),
+        HelpLine::Heading("Scoring"),
+        HelpLine::Row("Pellet", SCORE_PELLET.to_string()),
+        HelpLine::Row("Power pellet", SCORE_POWER_PELLET.to_string()),
+        HelpLine::Row("Ghosts in a row", ghost_points.join(" / ")),
+        HelpLine::Row("Bonus fruit", format!("{} to {}", Fruit::ALL[0].points(), Fruit::ALL[Fruit::ALL.len() - 1].points())),
+        HelpLine::Text(format!(
+            "After {} and {} pellets the level's fruit appears below the ghost house for a few seconds. \
+             Later levels have better fruit: {}.",
+            FRUIT_PELLETS[0],
+            FRUIT_PELLETS[1],
+            Fruit::ALL.map(|fruit| fruit.name().to_ascii_lowercase()).join(", ")
+        )),
+        HelpLine::Text(format!(
+            "After {} and {} pellets a coin appears on Pac-Man's starting tile: take it for {} seconds of x{} points \
+             (another restarts the time, it never multiplies again).",
+            DOUBLE_POINTS_PELLETS[0],
+            DOUBLE_POINTS_PELLETS[1],
+            seconds(DOUBLE_POINTS_TICKS),
+            DOUBLE_POINTS_MULTIPLIER
+        )),
+        HelpLine::Text(format!(
+            "Now and then a bomb appears below the ghost house after {} pellets. Carrying it, run into a wall \
+             and keep pushing: the wall blows open for {} seconds.",
+            BOMB_PELLETS,
+            seconds(BOMB_HOLE_TICKS)
+        )),
+        HelpLine::Text(format!(
+            "After every {} levels comes a {} second bonus stage without ghosts: eat all you can.",
+            BONUS_STAGE_INTERVAL,
+            seconds(BONUS_STAGE_DURATION)
+        )),
+        HelpLine::Heading("Ghosts"),
+        HelpLine::Text(format!(
+            "Ghosts leave their house one by one, after {} pellets on the first level. \
+             With the classic ghosts the red one chases you, the pink one cuts you off from ahead and \
+             the cyan one flanks you with the red one. Touching one costs a life.",
+            ghost_dot_limits(1).map(|limit| limit.to_string()).join(", ")
+        )),
+        HelpLine::Text(format!(
+            "A power pellet turns them blue for {} seconds on the first level, less later on: they flee from \
+             the nearest Pac-Man and can be eaten. They flash white just before they recover.",
+            seconds(level_spec(1).frightened_ticks)
+        )),
+    ]
+}
+
+/// Draws the help page from `top` down, centered on `center_x`
+///
+/// # Returns
+/// The height drawn in pixels
+pub fn draw_help(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    controls: &Controls,
+    center_x: i32,
+    top: i32,
+) -> i32 {
+    let gap = 8;
+    let mut y = top;
+    for line in help_lines(controls) {
+        match line {
+            HelpLine::Heading(text) => {
+                if y > top {
+                    y += line_height(1);
+                }
+                ui::label(canvas, text, center_x, y, ui::HIGHLIGHT_COLOR, Align::Center);
+                y += line_height(1) * 3 / 2;
+            }
+            HelpLine::Row(name, value) => {
+                ui::label(canvas, name, center_x - gap, y, ui::HINT_COLOR, Align::Right);
+                ui::label(canvas, &value, center_x + gap, y, ui::TEXT_COLOR, Align::Left);
+                y += line_height(1);
+            }
+            HelpLine::Text(text) => {
+                let lines = wrap_text(&text, TEXT_WIDTH, 1);
+                y += draw_lines(canvas, &lines, center_x, y, 1, ui::TEXT_COLOR, Align::Center);
+            }
+        }
+    }
+    y - top
+}
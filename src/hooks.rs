@@ -0,0 +1,139 @@
+//! Gameplay hooks: custom rules without forking the crate
+//!
+//! A hook sees every gameplay event after the tick that raised it, like an
+//! `EventSubscriber`, and may also change the game's `Tweaks`: a small set
+//! of parameters `Game::tick` reads from the next tick on (ghost and Pac-Man
+//! speed, frightened time). Everything else about the game stays read-only,
+//! so a hook can't break the rules the rest of the code relies on.
+//!
+//! Hooks are Rust closures (or types implementing `GameHook`) registered on
+//! a `Hooks` list, which whoever runs the game calls after every tick; the
+//! application takes them through `App::add_hook`. For example, ghosts that
+//! speed up every fifty pellets and calm down again on a new level:
+//!
+//! ```ignore
+//! let mut hooks = Hooks::new();
+//! let mut pellets = 0;
+//! hooks.on_pellet(move |_game, tweaks| {
+//!     pellets += 1;
+//!     if pellets % 50 == 0 {
+//!         tweaks.ghost_speed += 1;
+//!     }
+//! });
+//! hooks.on_level_clear(|_game, tweaks| tweaks.ghost_speed = 0);
+//! ```
+//!
+//! Tweaks take part in `Game::state_hash` once they are changed, so a
+//! recording or an online match played with hooks only checks out against
+//! the same hooks. They are not kept in saves.
+
+use crate::game::{Game, GameEvent};
+
+/// The game parameters hooks may change
+#[derive(Clone, Copy, PartialEq, Default)]
+pub struct Tweaks {
+    /// Ticks taken off the time the ghosts need per tile (negative slows them down)
+    pub ghost_speed: i32,
+    /// Ticks taken off the time Pac-Man needs per tile (negative slows him down)
+    pub pacman_speed: i32,
+    /// Ticks added to the frightened time of power pellets eaten from now on
+    /// (it never drops to nothing on levels that have any)
+    pub frightened_ticks: i32,
+}
+
+impl Tweaks {
+    /// Ticks per tile after a speed tweak (at least one)
+    ///
+    /// # Arguments
+    /// * `subframes` - Ticks per tile the level gives
+    /// * `speedup` - `ghost_speed` or `pacman_speed`
+    pub fn subframes(subframes: i32, speedup: i32) -> i32 {
+        (subframes - speedup).max(1)
+    }
+
+    /// Frightened time after the tweak, for a level giving `ticks` (0 stays 0)
+    pub fn frightened(&self, ticks: i32) -> i32 {
+        if ticks == 0 { 0 } else { (ticks + self.frightened_ticks).max(1) }
+    }
+}
+
+/// Something that reacts to gameplay events and may tweak the game
+pub trait GameHook {
+    /// Called once per event, in the order they happened
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick that raised the event
+    /// * `event` - What happened
+    /// * `tweaks` - The game's tweaks, applied from the next tick
+    fn on_event(&mut self, game: &Game, event: &GameEvent, tweaks: &mut Tweaks);
+}
+
+/// Closures can hook in directly
+impl<F: FnMut(&Game, &GameEvent, &mut Tweaks)> GameHook for F {
+    fn on_event(&mut self, game: &Game, event: &GameEvent, tweaks: &mut Tweaks) {
+        self(game, event, tweaks)
+    }
+}
+
+/// The hooks of a game, run after every tick
+#[derive(Default)]
+pub struct Hooks {
+    hooks: Vec<Box<dyn GameHook + Send>>,
+}
+
+impl Hooks {
+    /// Creates an empty list
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a hook; hooks see each event in the order they were added
+    pub fn add(&mut self, hook: Box<dyn GameHook + Send>) {
+        self.hooks.push(hook);
+    }
+
+    /// Adds a hook called for every pellet eaten, power pellets included
+    pub fn on_pellet(&mut self, mut hook: impl FnMut(&Game, &mut Tweaks) + Send + 'static) {
+        self.add(Box::new(move |game: &Game, event: &GameEvent, tweaks: &mut Tweaks| {
+            if let GameEvent::PelletEaten { .. } | GameEvent::PowerPelletEaten { .. } = event {
+                hook(game, tweaks);
+            }
+        }));
+    }
+
+    /// Adds a hook called for every life lost, the last one included
+    pub fn on_death(&mut self, mut hook: impl FnMut(&Game, &mut Tweaks) + Send + 'static) {
+        self.add(Box::new(move |game: &Game, event: &GameEvent, tweaks: &mut Tweaks| {
+            if let GameEvent::PlayerDied { .. } = event {
+                hook(game, tweaks);
+            }
+        }));
+    }
+
+    /// Adds a hook called for every maze cleared
+    pub fn on_level_clear(&mut self, mut hook: impl FnMut(&Game, &mut Tweaks) + Send + 'static) {
+        self.add(Box::new(move |game: &Game, event: &GameEvent, tweaks: &mut Tweaks| {
+            if let GameEvent::LevelCleared { .. } = event {
+                hook(game, tweaks);
+            }
+        }));
+    }
+
+    /// Hands the events of one tick to every hook and keeps the tweaks they make
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick
+    /// * `events` - The events `Game::tick` returned
+    pub fn run(&mut self, game: &mut Game, events: &[GameEvent]) {
+        if self.hooks.is_empty() {
+            return;
+        }
+        let mut tweaks = game.tweaks;
+        for event in events {
+            for hook in &mut self.hooks {
+                hook.on_event(game, event, &mut tweaks);
+            }
+        }
+        game.tweaks = tweaks;
+    }
+}
@@ -0,0 +1,125 @@
+//! Dev mode: reloading assets as they are edited
+//!
+//! With `--dev` the game keeps an eye on the asset directories (see
+//! `assets.rs`) and on the maze file given with `--maze`, so designers can
+//! edit them with the game running. Twice a second it looks for files that
+//! were added, changed or removed, and the main loop reloads what they
+//! belong to:
+//!
+//! - `audio/` and `music/`: sound effects, music files and songs (the music
+//!   playing starts over with the new version)
+//! - `skins/`: the skins, worn again by both players
+//! - `campaigns/`: the campaigns and the maze files next to them, for the
+//!   next campaign started
+//! - the `--maze` file: the maze itself; a game on it restarts the level,
+//!   but only if the layout really changed
+//!
+//! Files that fail to load are reported and left out, as at startup; a maze
+//! file that fails keeps the maze as it was. The level table (`data/`) is
+//! read once per run and isn't watched.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+use crate::assets::search_paths;
+
+/// How often the files are looked at
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// What a changed file belongs to
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AssetKind {
+    /// Sound effects, music files and songs
+    Audio,
+    Skins,
+    /// Campaigns and their maze files
+    Campaigns,
+    /// The maze file given with `--maze`
+    Maze,
+}
+
+/// Asset subdirectories watched, and what their files are
+const WATCHED_DIRS: [(&str, AssetKind); 4] = [
+    ("audio", AssetKind::Audio),
+    ("music", AssetKind::Audio),
+    ("skins", AssetKind::Skins),
+    ("campaigns", AssetKind::Campaigns),
+];
+
+/// Watches the asset directories and the maze file for changes
+pub struct AssetWatcher {
+    /// The `--maze` file, if any
+    maze: Option<PathBuf>,
+    /// Every file watched, what it is and when it was last modified
+    files: Vec<(PathBuf, AssetKind, SystemTime)>,
+    last_poll: Instant,
+}
+
+impl AssetWatcher {
+    /// Starts watching, taking the files as they are now as the starting point
+    ///
+    /// # Arguments
+    /// * `maze` - Maze file given with `--maze`, if any
+    pub fn new(maze: Option<PathBuf>) -> Self {
+        let mut watcher = Self { maze, files: Vec::new(), last_poll: Instant::now() };
+        watcher.files = watcher.scan();
+        watcher
+    }
+
+    /// The maze file watched, if any
+    pub fn maze(&self) -> Option<&Path> {
+        self.maze.as_deref()
+    }
+
+    /// Looks for changes, at most every `POLL_INTERVAL`
+    ///
+    /// # Returns
+    /// What changed since the last look, each kind once
+    pub fn poll(&mut self) -> Vec<AssetKind> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let files = self.scan();
+        let added_or_changed = files.iter().filter(|file| !self.files.contains(file));
+        let removed = self.files.iter().filter(|(path, ..)| !files.iter().any(|(other, ..)| other == path));
+        let mut changed: Vec<AssetKind> = Vec::new();
+        for &(_, kind, _) in added_or_changed.chain(removed) {
+            if !changed.contains(&kind) {
+                changed.push(kind);
+            }
+        }
+        self.files = files;
+        changed
+    }
+
+    /// Every watched file as it is now
+    fn scan(&self) -> Vec<(PathBuf, AssetKind, SystemTime)> {
+        let mut files = Vec::new();
+        for dir in search_paths() {
+            for (subdir, kind) in WATCHED_DIRS {
+                let Ok(entries) = std::fs::read_dir(dir.join(subdir)) else { continue };
+                for path in entries.filter_map(|entry| entry.ok().map(|entry| entry.path())) {
+                    if let Some(modified) = modified(&path) {
+                        files.push((path, kind, modified));
+                    }
+                }
+            }
+        }
+        if let Some(ref maze) = self.maze {
+            if let Some(modified) = modified(maze) {
+                files.push((maze.clone(), AssetKind::Maze, modified));
+            }
+        }
+        files
+    }
+}
+
+/// When a file was last modified (None if it isn't a readable file)
+fn modified(path: &Path) -> Option<SystemTime> {
+    let metadata = std::fs::metadata(path).ok()?;
+    if !metadata.is_file() {
+        return None;
+    }
+    metadata.modified().ok()
+}
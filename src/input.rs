@@ -0,0 +1,183 @@
+//! Gamepad input
+//! 
+//! This module handles:
+//! - Opening game controllers as they are plugged in and dropping them when removed
+//! - Assigning each controller to player 1 or player 2
+//! - Reading a steering direction from the d-pad or the left analog stick
+//! - Rumbling on deaths, eaten ghosts and power pellets, for controllers that can
+
+use sdl2::controller::{Axis, Button, GameController};
+use sdl2::GameControllerSubsystem;
+use crate::constants::STICK_DEADZONE;
+use crate::game::GameEvent;
+
+/// Controller buttons that show the emotes, in `Emote::ALL` order, when a
+/// human plays a ghost
+pub const EMOTE_BUTTONS: [Button; 3] = [Button::X, Button::Y, Button::B];
+
+/// A rumble pulse at full strength
+struct Pulse {
+    /// Low frequency (heavy) motor, 0.0 to 1.0
+    low: f32,
+    /// High frequency (light) motor, 0.0 to 1.0
+    high: f32,
+    /// Length in milliseconds
+    ms: u32,
+}
+
+/// The pulse a gameplay event is felt as, if any
+fn pulse_for(event: &GameEvent) -> Option<Pulse> {
+    match event {
+        GameEvent::PlayerDied { .. } => Some(Pulse { low: 1.0, high: 0.5, ms: 400 }),
+        GameEvent::GhostEaten { .. } => Some(Pulse { low: 0.3, high: 0.8, ms: 150 }),
+        GameEvent::PowerPelletEaten { .. } => Some(Pulse { low: 0.5, high: 0.2, ms: 120 }),
+        _ => None,
+    }
+}
+
+/// An open controller and the player it steers
+struct Pad {
+    controller: GameController,
+    /// 0 = player 1, 1 = player 2
+    player: usize,
+}
+
+/// All connected game controllers
+/// 
+/// The first controller plugged in steers player 1, the second player 2.
+/// Pressing Back on a controller moves it to the other player, so player 2 can
+/// use a controller while player 1 stays on the keyboard. Controllers come and
+/// go at any time; the application pauses when a player's last one goes.
+pub struct Gamepads {
+    subsystem: GameControllerSubsystem,
+    pads: Vec<Pad>,
+}
+
+impl Gamepads {
+    /// Creates the controller list
+    /// 
+    /// Controllers already connected at startup arrive as device-added events,
+    /// so none are opened here.
+    pub fn new(sdl: &sdl2::Sdl) -> Result<Self, String> {
+        Ok(Self {
+            subsystem: sdl.game_controller()?,
+            pads: Vec::new(),
+        })
+    }
+
+    /// Opens a newly connected controller and assigns it to the first free player
+    /// 
+    /// # Arguments
+    /// * `joystick_index` - Device index from the `ControllerDeviceAdded` event
+    /// 
+    /// # Returns
+    /// The player the controller went to, or None if it couldn't be opened or was already open
+    pub fn add(&mut self, joystick_index: u32) -> Option<usize> {
+        let controller = match self.subsystem.open(joystick_index) {
+            Ok(controller) => controller,
+            Err(e) => {
+                eprintln!("Could not open controller {}: {}", joystick_index, e);
+                return None;
+            }
+        };
+        // The same device can be reported twice at startup
+        if self.pads.iter().any(|pad| pad.controller.instance_id() == controller.instance_id()) {
+            return None;
+        }
+        let player = if self.has_pad(0) { 1 } else { 0 };
+        self.pads.push(Pad { controller, player });
+        Some(player)
+    }
+
+    /// Drops a disconnected controller
+    /// 
+    /// # Arguments
+    /// * `instance_id` - Joystick id from the `ControllerDeviceRemoved` event
+    /// 
+    /// # Returns
+    /// The player it belonged to if they are left without a controller
+    pub fn remove(&mut self, instance_id: u32) -> Option<usize> {
+        let player = self.player_for(instance_id)?;
+        self.pads.retain(|pad| pad.controller.instance_id() != instance_id);
+        (!self.has_pad(player)).then_some(player)
+    }
+
+    /// Gives a controller to a player
+    pub fn assign(&mut self, instance_id: u32, player: usize) {
+        if let Some(pad) = self.pads.iter_mut().find(|pad| pad.controller.instance_id() == instance_id) {
+            pad.player = player;
+        }
+    }
+
+    /// Whether a player has a controller
+    pub fn has_pad(&self, player: usize) -> bool {
+        self.pads.iter().any(|pad| pad.player == player)
+    }
+
+    /// Moves a controller to the other player
+    pub fn swap_player(&mut self, instance_id: u32) {
+        if let Some(pad) = self.pads.iter_mut().find(|pad| pad.controller.instance_id() == instance_id) {
+            pad.player = 1 - pad.player;
+        }
+    }
+
+    /// Which player a controller belongs to (0 or 1), if it is connected
+    pub fn player_for(&self, instance_id: u32) -> Option<usize> {
+        self.pads.iter()
+            .find(|pad| pad.controller.instance_id() == instance_id)
+            .map(|pad| pad.player)
+    }
+
+    /// Rumbles controllers for the events of a tick (the strongest pulse wins)
+    /// 
+    /// Controllers without rumble motors are left alone.
+    /// 
+    /// # Arguments
+    /// * `player` - Whose controllers, or None for everyone's
+    /// * `events` - The events `Game::tick` returned
+    /// * `strength` - The rumble setting, 0.0 (off) to 1.0
+    pub fn rumble(&mut self, player: Option<usize>, events: &[GameEvent], strength: f32) {
+        if strength <= 0.0 {
+            return;
+        }
+        let Some(pulse) = events.iter().filter_map(pulse_for).max_by_key(|pulse| pulse.ms) else { return };
+        let motor = |level: f32| (level * strength * u16::MAX as f32) as u16;
+        for pad in self.pads.iter_mut().filter(|pad| player.map(|player| pad.player == player).unwrap_or(true)) {
+            let _ = pad.controller.set_rumble(motor(pulse.low), motor(pulse.high), pulse.ms);
+        }
+    }
+
+    /// Direction a player is steering with their controller(s), if any
+    /// 
+    /// The d-pad wins over the analog stick; the stick uses its dominant axis
+    /// once it leaves the dead zone.
+    pub fn direction(&self, player: usize) -> Option<(i32, i32)> {
+        self.pads.iter()
+            .filter(|pad| pad.player == player)
+            .find_map(|pad| pad_direction(&pad.controller))
+    }
+}
+
+/// Steering direction of one controller, if any
+fn pad_direction(controller: &GameController) -> Option<(i32, i32)> {
+    const DPAD: [(Button, (i32, i32)); 4] = [
+        (Button::DPadUp, (0, -1)),
+        (Button::DPadDown, (0, 1)),
+        (Button::DPadLeft, (-1, 0)),
+        (Button::DPadRight, (1, 0)),
+    ];
+    if let Some((_, direction)) = DPAD.iter().find(|(button, _)| controller.button(*button)) {
+        return Some(*direction);
+    }
+    
+    let x = controller.axis(Axis::LeftX) as i32;
+    let y = controller.axis(Axis::LeftY) as i32;
+    let deadzone = STICK_DEADZONE as i32;
+    if x.abs() < deadzone && y.abs() < deadzone {
+        None
+    } else if x.abs() > y.abs() {
+        Some((x.signum(), 0))
+    } else {
+        Some((0, y.signum()))
+    }
+}
@@ -0,0 +1,79 @@
+//! Jukebox: every music track and sound effect, to listen to on their own
+//!
+//! Opened from the audio settings. While it is open the game's music stops
+//! and any track or effect can be played by itself (`AudioEvent::Play`), with
+//! what it is made from shown next to it: the tempo of a track's song and each
+//! channel's waveform, volume and envelope in the notation of `tracker.rs`,
+//! ready to copy into a `.song` file, or the pitch sweeps of a synthesized
+//! effect, or the file that replaces either.
+
+use std::path::Path;
+use crate::audio::{Effect, MusicContext, Sound};
+
+/// A track or effect as the jukebox lists it
+pub struct JukeboxEntry {
+    pub sound: Sound,
+    /// Display name
+    pub name: String,
+    /// What it is made from, a line each
+    pub details: Vec<String>,
+}
+
+/// Every music track, then every sound effect, as they would load now
+pub fn entries() -> Vec<JukeboxEntry> {
+    let music = MusicContext::ALL.iter().map(|&context| JukeboxEntry {
+        sound: Sound::Music(context),
+        name: format!("{} Music", context.name()),
+        details: music_details(context),
+    });
+    let effects = Effect::ALL.iter().map(|&effect| JukeboxEntry {
+        sound: Sound::Effect(effect),
+        name: effect.name().to_string(),
+        details: effect_details(effect),
+    });
+    music.chain(effects).collect()
+}
+
+/// The music file, or the song with its tempo and a line per channel
+fn music_details(context: MusicContext) -> Vec<String> {
+    if let Some(path) = context.music_file() {
+        return vec![format!("Music file {}", file_name(&path))];
+    }
+    let source = match context.song_file() {
+        Some(path) => format!("Song file {}", file_name(&path)),
+        None => "Built-in song".to_string(),
+    };
+    let song = context.load_song();
+    let mut details = vec![format!("{}, tempo {}", source, song.tempo)];
+    details.extend(song.channels.iter().enumerate().map(|(i, channel)| {
+        format!("channel {} {}  ({} notes, {} beats)", i + 1, channel, channel.notes.len(), channel.total_beats())
+    }));
+    details
+}
+
+/// The sound file, or the synthesized square wave's sweeps
+fn effect_details(effect: Effect) -> Vec<String> {
+    if let Some(path) = effect.sound_file() {
+        return vec![format!("Sound file {}", file_name(&path))];
+    }
+    let mut segments = effect.segments(true).to_vec();
+    if effect == Effect::Pellet {
+        // The two halves of the waka-waka take turns
+        segments.extend_from_slice(effect.segments(false));
+    }
+    let mut details = vec!["Synthesized square wave".to_string()];
+    details.extend(segments.iter().map(|segment| {
+        let ms = (segment.seconds * 1000.0).round();
+        if segment.start_hz == segment.end_hz {
+            format!("{:.0} Hz for {} ms", segment.start_hz, ms)
+        } else {
+            format!("{:.0} Hz to {:.0} Hz over {} ms", segment.start_hz, segment.end_hz, ms)
+        }
+    }));
+    details
+}
+
+/// Name of a file, without its directory
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
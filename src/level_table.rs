@@ -0,0 +1,178 @@
+//! Per-level speeds and timings, after the arcade's level specification
+//!
+//! How fast Pac-Man and the ghosts move, how long a power pellet frightens the
+//! ghosts, how often they flash before recovering, when the red ghost speeds
+//! up ("Cruise Elroy") and which bonus fruit appears change from level to
+//! level. The table is a data file, `data/levels.txt` in the asset directories
+//! (see `assets.rs`), so balance can be tuned without recompiling; the copy
+//! built into the game (`assets/data/levels.txt` in the source tree) is used
+//! when there is none. It is read once, the first time a level's values are
+//! needed.
+//!
+//! Each line gives the values from a level on, up to the next line's level:
+//!
+//! ```text
+//! # level  pacman  ghost  frightened  flashes  elroy1  elroy1_ghost  elroy2  elroy2_ghost  fruit
+//! 1        5       6      360         5        20      5             10      5             cherry
+//! 2        5       5      300         5        30      5             15      5             strawberry
+//! ```
+//!
+//! The fruit is named as in `Fruit::name`, in any case.
+//!
+//! Replays and online games only stay in sync when every side plays with the
+//! same table; online, the host sends `table_hash` in the session setup and a
+//! client with a different table refuses to join.
+
+use std::sync::OnceLock;
+use crate::assets::find_asset;
+use crate::fruit::Fruit;
+use crate::game::StateHash;
+
+/// Asset subdirectory and name of the table file
+const TABLE_DIR: &str = "data";
+const TABLE_NAME: &str = "levels";
+
+/// The table shipped with the game
+const BUILT_IN_TABLE: &str = include_str!("../assets/data/levels.txt");
+
+/// Level 1 of the built-in table, used if no table can be read at all
+const FALLBACK_SPEC: LevelSpec = LevelSpec {
+    pacman_subframes: 5,
+    ghost_subframes: 6,
+    frightened_ticks: 360,
+    flashes: 5,
+    elroy1_pellets: 20,
+    elroy1_subframes: 5,
+    elroy2_pellets: 10,
+    elroy2_subframes: 5,
+    fruit: Fruit::Cherry,
+};
+
+/// Speeds and timings of one level
+#[derive(Clone, Copy, PartialEq)]
+pub struct LevelSpec {
+    /// Ticks Pac-Man takes per tile (lower is faster)
+    pub pacman_subframes: i32,
+    /// Ticks a ghost takes per tile
+    pub ghost_subframes: i32,
+    /// Ticks the ghosts stay frightened after a power pellet (0 = not at all)
+    pub frightened_ticks: i32,
+    /// Times the frightened ghosts flash white before they recover
+    pub flashes: i32,
+    /// Pellets left when the red ghost first speeds up
+    pub elroy1_pellets: i32,
+    /// Red ghost's ticks per tile from then on
+    pub elroy1_subframes: i32,
+    /// Pellets left when the red ghost speeds up again
+    pub elroy2_pellets: i32,
+    /// Red ghost's ticks per tile from then on
+    pub elroy2_subframes: i32,
+    /// The bonus fruit that appears on the level
+    pub fruit: Fruit,
+}
+
+impl LevelSpec {
+    /// Ticks per tile of the red ghost with `pellets` left in the maze
+    pub fn red_ghost_subframes(&self, pellets: i32) -> i32 {
+        if pellets <= self.elroy2_pellets {
+            self.elroy2_subframes
+        } else if pellets <= self.elroy1_pellets {
+            self.elroy1_subframes
+        } else {
+            self.ghost_subframes
+        }
+    }
+}
+
+/// Rows of the table: first level each applies to, in increasing order
+static TABLE: OnceLock<Vec<(u32, LevelSpec)>> = OnceLock::new();
+
+/// Returns the speeds and timings of a level
+pub fn level_spec(level: u32) -> LevelSpec {
+    let table = TABLE.get_or_init(load_table);
+    table.iter()
+        .rev()
+        .find(|(from, _)| *from <= level)
+        .or(table.first())
+        .map(|&(_, spec)| spec)
+        .unwrap_or(FALLBACK_SPEC)
+}
+
+/// A hash of the table's values (FNV-1a, comments and spacing aside), to
+/// check that two games play with the same table
+pub fn table_hash() -> u64 {
+    let mut hash = StateHash::new();
+    for &(from, spec) in TABLE.get_or_init(load_table) {
+        let fruit = Fruit::ALL.iter().position(|&fruit| fruit == spec.fruit).unwrap_or(0);
+        for value in [
+            from as i32, spec.pacman_subframes, spec.ghost_subframes, spec.frightened_ticks, spec.flashes,
+            spec.elroy1_pellets, spec.elroy1_subframes, spec.elroy2_pellets, spec.elroy2_subframes, fruit as i32,
+        ] {
+            hash.add(value as i64);
+        }
+    }
+    hash.finish()
+}
+
+/// Reads the table file from the asset directories, or the built-in table
+fn load_table() -> Vec<(u32, LevelSpec)> {
+    if let Some(path) = find_asset(TABLE_DIR, TABLE_NAME, &["txt"]) {
+        let table = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| parse_table(&text));
+        match table {
+            Ok(table) => return table,
+            Err(e) => eprintln!("Ignoring level table {}: {}", path.display(), e),
+        }
+    }
+    parse_table(BUILT_IN_TABLE).unwrap_or_default()
+}
+
+/// Parses a level table (see the module documentation for the format)
+fn parse_table(text: &str) -> Result<Vec<(u32, LevelSpec)>, String> {
+    let mut table: Vec<(u32, LevelSpec)> = Vec::new();
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad_line = |reason: &str| format!("line {}: {}", line_index + 1, reason);
+        let columns: Vec<&str> = line.split_whitespace().collect();
+        let [numbers @ .., fruit] = &columns[..] else {
+            return Err(bad_line("expected 10 columns"));
+        };
+        let values: Vec<i32> = numbers.iter()
+            .map(|value| value.parse())
+            .collect::<Result<_, _>>()
+            .map_err(|_| bad_line("expected whole numbers before the fruit"))?;
+        let [level, pacman, ghost, frightened, flashes, elroy1, elroy1_ghost, elroy2, elroy2_ghost] = values[..] else {
+            return Err(bad_line("expected 10 columns"));
+        };
+        let fruit = Fruit::from_name(fruit).ok_or_else(|| bad_line("unknown fruit"))?;
+        let previous_level = table.last().map(|&(from, _)| from as i32).unwrap_or(0);
+        if level <= previous_level || (table.is_empty() && level != 1) {
+            return Err(bad_line("levels must start at 1 and increase"));
+        }
+        if [pacman, ghost, elroy1_ghost, elroy2_ghost].iter().any(|&subframes| subframes < 1) {
+            return Err(bad_line("speeds must be at least 1 tick per tile"));
+        }
+        if frightened < 0 || flashes < 0 || elroy1 < 0 || elroy2 < 0 {
+            return Err(bad_line("times, flashes and pellet counts can't be negative"));
+        }
+        table.push((level as u32, LevelSpec {
+            pacman_subframes: pacman,
+            ghost_subframes: ghost,
+            frightened_ticks: frightened,
+            flashes,
+            elroy1_pellets: elroy1,
+            elroy1_subframes: elroy1_ghost,
+            elroy2_pellets: elroy2,
+            elroy2_subframes: elroy2_ghost,
+            fruit,
+        }));
+    }
+    if table.is_empty() {
+        return Err("no levels".to_string());
+    }
+    Ok(table)
+}
@@ -4,10 +4,87 @@ pub mod constants;
 pub mod maze;
 pub mod rng;
 pub mod player;
+pub mod pacman_ai;
 pub mod ghost;
+#[cfg(feature = "sdl")]
 pub mod render;
 pub mod game;
+pub mod level_table;
+pub mod fruit;
+pub mod events;
+pub mod hooks;
+pub mod emote;
+#[cfg(feature = "sdl")]
 pub mod menu;
+#[cfg(feature = "sdl")]
+pub mod pause_menu;
+#[cfg(feature = "sdl")]
+pub mod help;
 pub mod audio;
 pub mod game_config;
+pub mod net;
+pub mod storage;
+pub mod time_trial;
+pub mod campaign;
+pub mod demo;
+pub mod tournament;
+pub mod bench;
+pub mod progress;
+pub mod unlocks;
+pub mod skins;
+pub mod practice;
+#[cfg(feature = "sdl")]
+pub mod race;
+pub mod telemetry;
+pub mod rating;
+pub mod stats;
+pub mod scoring;
+pub mod savegame;
+pub mod json;
+pub mod state_dump;
+#[cfg(feature = "sdl")]
+pub mod input;
+pub mod touch;
+pub mod speech;
+pub mod one_button;
+pub mod tas;
+pub mod golden;
+pub mod cli;
+#[cfg(feature = "sdl")]
+pub mod video;
+#[cfg(feature = "sdl")]
+pub mod settings;
+#[cfg(feature = "sdl")]
+pub mod profile_file;
+pub mod font;
+#[cfg(feature = "sdl")]
+pub mod ui;
+#[cfg(feature = "sdl")]
+pub mod debug;
+#[cfg(feature = "sdl")]
+pub mod capture;
+#[cfg(feature = "sdl")]
+pub mod app;
+pub mod assets;
+pub mod hot_reload;
+pub mod tracker;
+pub mod jukebox;
+pub mod wav;
+#[cfg(feature = "ogg")]
+pub mod ogg;
+pub mod audio_tap;
+pub mod env;
+pub mod renderer;
+pub mod profile;
+pub mod soft_render;
+#[cfg(feature = "sdl-audio")]
+pub mod sdl_audio;
+#[cfg(feature = "cpal-audio")]
+pub mod cpal_audio;
 
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+#[cfg(feature = "libretro")]
+pub mod libretro;
+#[cfg(feature = "macroquad")]
+pub mod mq_front;
@@ -0,0 +1,419 @@
+//! libretro core
+//!
+//! Implements the libretro API so the game runs inside RetroArch (or any other
+//! libretro front end). Build it as a shared library without SDL:
+//!
+//! ```text
+//! cargo rustc --lib --release --no-default-features --features libretro --crate-type cdylib
+//! ```
+//!
+//! The front end calls `retro_run` 60 times a second; each call polls the
+//! joypad, runs one `Game::tick`, hands the `soft_render` frame to the video
+//! callback and one frame's worth of mixed audio to the audio callback. The
+//! core needs no content file: it is started with "Start Core".
+//!
+//! Controls (RetroPad, port 1): d-pad to steer, Start to pause, or to play
+//! again after a game over.
+
+use std::ffi::{c_char, c_uint, c_void};
+use std::sync::mpsc::Sender;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use crate::audio::{AudioBackend, AudioEvent, AudioManager, Mixer};
+use crate::constants::FPS;
+use crate::events::EventBus;
+use crate::game::Game;
+use crate::game_config::{GameConfig, GameMode, PlayerRole};
+use crate::soft_render::{Framebuffer, FRAME_W, FRAME_H};
+
+/// Version of the libretro API this core implements
+const RETRO_API_VERSION: c_uint = 1;
+
+const RETRO_ENVIRONMENT_SET_PIXEL_FORMAT: c_uint = 10;
+const RETRO_ENVIRONMENT_SET_SUPPORT_NO_GAME: c_uint = 18;
+const RETRO_PIXEL_FORMAT_XRGB8888: c_uint = 1;
+const RETRO_REGION_NTSC: c_uint = 0;
+
+const RETRO_DEVICE_JOYPAD: c_uint = 1;
+const RETRO_DEVICE_ID_JOYPAD_START: c_uint = 3;
+const RETRO_DEVICE_ID_JOYPAD_UP: c_uint = 4;
+const RETRO_DEVICE_ID_JOYPAD_DOWN: c_uint = 5;
+const RETRO_DEVICE_ID_JOYPAD_LEFT: c_uint = 6;
+const RETRO_DEVICE_ID_JOYPAD_RIGHT: c_uint = 7;
+
+/// Sample rate of the audio handed to the front end
+const AUDIO_SAMPLE_RATE: i32 = 44100;
+
+type EnvironmentFn = extern "C" fn(cmd: c_uint, data: *mut c_void) -> bool;
+type VideoRefreshFn = extern "C" fn(data: *const c_void, width: c_uint, height: c_uint, pitch: usize);
+type AudioSampleFn = extern "C" fn(left: i16, right: i16);
+type AudioSampleBatchFn = extern "C" fn(data: *const i16, frames: usize) -> usize;
+type InputPollFn = extern "C" fn();
+type InputStateFn = extern "C" fn(port: c_uint, device: c_uint, index: c_uint, id: c_uint) -> i16;
+
+/// `struct retro_system_info`
+#[repr(C)]
+pub struct RetroSystemInfo {
+    pub library_name: *const c_char,
+    pub library_version: *const c_char,
+    pub valid_extensions: *const c_char,
+    pub need_fullpath: bool,
+    pub block_extract: bool,
+}
+
+/// `struct retro_game_geometry`
+#[repr(C)]
+pub struct RetroGameGeometry {
+    pub base_width: c_uint,
+    pub base_height: c_uint,
+    pub max_width: c_uint,
+    pub max_height: c_uint,
+    pub aspect_ratio: f32,
+}
+
+/// `struct retro_system_timing`
+#[repr(C)]
+pub struct RetroSystemTiming {
+    pub fps: f64,
+    pub sample_rate: f64,
+}
+
+/// `struct retro_system_av_info`
+#[repr(C)]
+pub struct RetroSystemAvInfo {
+    pub geometry: RetroGameGeometry,
+    pub timing: RetroSystemTiming,
+}
+
+/// `struct retro_game_info`
+#[repr(C)]
+pub struct RetroGameInfo {
+    pub path: *const c_char,
+    pub data: *const c_void,
+    pub size: usize,
+    pub meta: *const c_char,
+}
+
+/// Callbacks registered by the front end
+#[derive(Clone, Copy)]
+struct Callbacks {
+    environment: Option<EnvironmentFn>,
+    video_refresh: Option<VideoRefreshFn>,
+    audio_sample_batch: Option<AudioSampleBatchFn>,
+    input_poll: Option<InputPollFn>,
+    input_state: Option<InputStateFn>,
+}
+
+/// The running game
+struct Core {
+    game: Game,
+    screen: Framebuffer,
+    /// Sends music changes to the mixer
+    audio_events: Option<Sender<AudioEvent>>,
+    /// Hands gameplay events to the mixer
+    events: EventBus,
+    /// One frame of mixed mono audio
+    samples: Vec<f32>,
+    /// The same frame as interleaved stereo for the front end
+    stereo: Vec<i16>,
+    /// Start was held on the previous frame (it acts on the press)
+    start_held: bool,
+}
+
+static CALLBACKS: Mutex<Callbacks> = Mutex::new(Callbacks {
+    environment: None,
+    video_refresh: None,
+    audio_sample_batch: None,
+    input_poll: None,
+    input_state: None,
+});
+static CORE: Mutex<Option<Core>> = Mutex::new(None);
+/// Mixer built by `RetroAudio::start`, pulled once per frame by `retro_run`
+static MIXER: Mutex<Option<Mixer>> = Mutex::new(None);
+
+/// Locks a mutex, ignoring poisoning (a panic never leaves the state half-updated in a way that matters here)
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Audio backend for libretro: `retro_run` pulls samples and hands them to the front end
+struct RetroAudio;
+
+impl AudioBackend for RetroAudio {
+    fn start(&mut self, make_mixer: Box<dyn FnOnce(i32) -> Mixer + Send>) -> Result<(), String> {
+        *lock(&MIXER) = Some(make_mixer(AUDIO_SAMPLE_RATE));
+        Ok(())
+    }
+}
+
+impl Core {
+    fn new() -> Self {
+        // The manager only owns the backend; the mixer lives in MIXER and the
+        // sender keeps the event channel open
+        let audio_events = AudioManager::new(Box::new(RetroAudio))
+            .map(|manager| manager.sender())
+            .map_err(|e| eprintln!("Audio unavailable: {}", e))
+            .ok();
+        let mut events = EventBus::new();
+        if let Some(ref sounds) = audio_events {
+            events.subscribe(Box::new(sounds.clone()));
+        }
+        let samples_per_frame = (AUDIO_SAMPLE_RATE as u32 / FPS) as usize;
+        let core = Self {
+            game: Game::new(GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None)),
+            screen: Framebuffer::new(),
+            audio_events,
+            events,
+            samples: vec![0.0; samples_per_frame],
+            stereo: Vec::with_capacity(samples_per_frame * 2),
+            start_held: false,
+        };
+        core.send_audio(AudioEvent::GameStarted);
+        core
+    }
+
+    /// Starts a new game, seeded from the last one so each game plays differently
+    fn restart(&mut self) {
+        let seed = self.game.rng.state();
+        self.game = Game::new_with_seed(GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None), seed);
+        self.send_audio(AudioEvent::GameStarted);
+    }
+
+    fn send_audio(&self, event: AudioEvent) {
+        if let Some(ref events) = self.audio_events {
+            let _ = events.send(event);
+        }
+    }
+
+    /// Runs one frame: input, tick, video and audio
+    fn run(&mut self, callbacks: Callbacks) {
+        if let Some(poll) = callbacks.input_poll {
+            poll();
+        }
+        let pressed = |id: c_uint| {
+            callbacks.input_state
+                .map(|state| state(0, RETRO_DEVICE_JOYPAD, 0, id) != 0)
+                .unwrap_or(false)
+        };
+
+        let start = pressed(RETRO_DEVICE_ID_JOYPAD_START);
+        if start && !self.start_held {
+            if self.game.alive {
+                self.game.paused = !self.game.paused;
+            } else {
+                self.restart();
+            }
+        }
+        self.start_held = start;
+
+        let direction = [
+            (RETRO_DEVICE_ID_JOYPAD_UP, (0, -1)),
+            (RETRO_DEVICE_ID_JOYPAD_DOWN, (0, 1)),
+            (RETRO_DEVICE_ID_JOYPAD_LEFT, (-1, 0)),
+            (RETRO_DEVICE_ID_JOYPAD_RIGHT, (1, 0)),
+        ]
+        .into_iter()
+        .find(|&(id, _)| pressed(id))
+        .map(|(_, direction)| direction);
+
+        if self.game.alive && !self.game.paused {
+            let events = self.game.tick(direction, None);
+            self.events.publish(&self.game, &events);
+        }
+
+        let _ = self.game.draw(&mut self.screen);
+        if let Some(video_refresh) = callbacks.video_refresh {
+            let pixels = self.screen.pixels();
+            video_refresh(pixels.as_ptr() as *const c_void, FRAME_W as c_uint, FRAME_H as c_uint, FRAME_W as usize * 4);
+        }
+
+        if let Some(audio_sample_batch) = callbacks.audio_sample_batch {
+            match lock(&MIXER).as_mut() {
+                Some(mixer) => mixer.fill(&mut self.samples),
+                None => self.samples.fill(0.0),
+            }
+            self.stereo.clear();
+            self.stereo.extend(self.samples.iter().flat_map(|&sample| {
+                let sample = (sample * i16::MAX as f32) as i16;
+                [sample, sample]
+            }));
+            let mut sent = 0;
+            while sent < self.samples.len() {
+                let accepted = audio_sample_batch(self.stereo[sent * 2..].as_ptr(), self.samples.len() - sent);
+                if accepted == 0 {
+                    break;
+                }
+                sent += accepted;
+            }
+        }
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_api_version() -> c_uint {
+    RETRO_API_VERSION
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_environment(callback: EnvironmentFn) {
+    lock(&CALLBACKS).environment = Some(callback);
+    let mut no_game = true;
+    callback(RETRO_ENVIRONMENT_SET_SUPPORT_NO_GAME, &mut no_game as *mut bool as *mut c_void);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_video_refresh(callback: VideoRefreshFn) {
+    lock(&CALLBACKS).video_refresh = Some(callback);
+}
+
+/// Single samples are not used (audio goes through the batch callback)
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample(_callback: AudioSampleFn) {}
+
+#[no_mangle]
+pub extern "C" fn retro_set_audio_sample_batch(callback: AudioSampleBatchFn) {
+    lock(&CALLBACKS).audio_sample_batch = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_poll(callback: InputPollFn) {
+    lock(&CALLBACKS).input_poll = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_input_state(callback: InputStateFn) {
+    lock(&CALLBACKS).input_state = Some(callback);
+}
+
+#[no_mangle]
+pub extern "C" fn retro_init() {}
+
+#[no_mangle]
+pub extern "C" fn retro_deinit() {
+    *lock(&CORE) = None;
+    *lock(&MIXER) = None;
+}
+
+/// Fills in the core's name and version
+///
+/// # Safety
+/// `info` must be null or point to a writable `retro_system_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_info(info: *mut RetroSystemInfo) {
+    // SAFETY: the front end passes a valid struct (checked for null)
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    *info = RetroSystemInfo {
+        library_name: c"Paclike 2600".as_ptr(),
+        library_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+        valid_extensions: c"".as_ptr(),
+        need_fullpath: false,
+        block_extract: false,
+    };
+}
+
+/// Fills in the frame size, frame rate and audio sample rate
+///
+/// # Safety
+/// `info` must be null or point to a writable `retro_system_av_info`.
+#[no_mangle]
+pub unsafe extern "C" fn retro_get_system_av_info(info: *mut RetroSystemAvInfo) {
+    // SAFETY: the front end passes a valid struct (checked for null)
+    let Some(info) = (unsafe { info.as_mut() }) else {
+        return;
+    };
+    *info = RetroSystemAvInfo {
+        geometry: RetroGameGeometry {
+            base_width: FRAME_W as c_uint,
+            base_height: FRAME_H as c_uint,
+            max_width: FRAME_W as c_uint,
+            max_height: FRAME_H as c_uint,
+            aspect_ratio: FRAME_W as f32 / FRAME_H as f32,
+        },
+        timing: RetroSystemTiming {
+            fps: FPS as f64,
+            sample_rate: AUDIO_SAMPLE_RATE as f64,
+        },
+    };
+}
+
+#[no_mangle]
+pub extern "C" fn retro_set_controller_port_device(_port: c_uint, _device: c_uint) {}
+
+#[no_mangle]
+pub extern "C" fn retro_reset() {
+    if let Some(core) = lock(&CORE).as_mut() {
+        core.restart();
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn retro_run() {
+    let callbacks = *lock(&CALLBACKS);
+    if let Some(core) = lock(&CORE).as_mut() {
+        core.run(callbacks);
+    }
+}
+
+/// Savestates are not supported
+#[no_mangle]
+pub extern "C" fn retro_serialize_size() -> usize {
+    0
+}
+
+#[no_mangle]
+pub extern "C" fn retro_serialize(_data: *mut c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unserialize(_data: *const c_void, _size: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_reset() {}
+
+#[no_mangle]
+pub extern "C" fn retro_cheat_set(_index: c_uint, _enabled: bool, _code: *const c_char) {}
+
+/// Starts the game (there is no content to load, `game` is ignored)
+#[no_mangle]
+pub extern "C" fn retro_load_game(_game: *const RetroGameInfo) -> bool {
+    let Some(environment) = lock(&CALLBACKS).environment else {
+        return false;
+    };
+    let mut format = RETRO_PIXEL_FORMAT_XRGB8888;
+    if !environment(RETRO_ENVIRONMENT_SET_PIXEL_FORMAT, &mut format as *mut c_uint as *mut c_void) {
+        eprintln!("Front end does not support XRGB8888");
+        return false;
+    }
+    *lock(&CORE) = Some(Core::new());
+    true
+}
+
+#[no_mangle]
+pub extern "C" fn retro_load_game_special(_game_type: c_uint, _info: *const RetroGameInfo, _num_info: usize) -> bool {
+    false
+}
+
+#[no_mangle]
+pub extern "C" fn retro_unload_game() {
+    *lock(&CORE) = None;
+    *lock(&MIXER) = None;
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_region() -> c_uint {
+    RETRO_REGION_NTSC
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_data(_id: c_uint) -> *mut c_void {
+    std::ptr::null_mut()
+}
+
+#[no_mangle]
+pub extern "C" fn retro_get_memory_size(_id: c_uint) -> usize {
+    0
+}
@@ -1,204 +1,149 @@
 //! Main entry point for the Pac-Man game
 //! 
 //! This module handles:
+//! - Command-line options, including a headless simulation mode, AI tournaments
+//!   and the benchmark
 //! - SDL2 initialization
-//! - Window creation
-//! - Event loop (input handling)
-//! - Game loop with fixed timestep
-//! - Menu and game state management
+//! - Handing control to `app::App`, which owns the window, the menu and game
+//!   states, input, recordings and online play
 
-use sdl2::event::Event;
-use sdl2::keyboard::Scancode;
-use std::time::{Duration, Instant};
+use paclike_2600_rs::app::{App, CustomMaze, select_maze, start_replay};
 use paclike_2600_rs::game::Game;
-use paclike_2600_rs::menu::{Menu, MenuAction};
-use paclike_2600_rs::audio::AudioManager;
+use paclike_2600_rs::tas::TasPlayback;
+use paclike_2600_rs::cli::{CliOptions, USAGE};
+use paclike_2600_rs::maze::load_maze_file;
 use paclike_2600_rs::game_config::{GameConfig, GameMode, PlayerRole};
-use paclike_2600_rs::constants::{
-    VIEW_W, VIEW_H, SCORE_AREA, WINDOW_SCALE, DT, 
-    MAZE_1, MAZE_2, CURRENT_MAZE,
-    MAX_TIME_STEP, SLEEP_DURATION_MS
-};
+use paclike_2600_rs::rng::DEFAULT_SEED;
+use paclike_2600_rs::state_dump::load_state_dump;
+use paclike_2600_rs::tournament::{format_csv, format_table, run_tournament};
+use paclike_2600_rs::debug::CountingAllocator;
+use paclike_2600_rs::bench::{BenchOptions, bench_draw, bench_ticks};
+use paclike_2600_rs::golden::{EndState, GoldenOptions, DEFAULT_GOLDEN_DIR, check_golden, find_replays, update_golden};
+use paclike_2600_rs::render::{RenderCache, SdlRenderer};
+use paclike_2600_rs::video::{VideoSettings, build_canvas};
 
-fn main() -> Result<(), String> {
-    // Init SDL
-    let sdl = sdl2::init()?;
-    let video = sdl.video()?;
-    
-    // Calculate window size to fit game content with minimal borders
-    let window_w = (VIEW_W * WINDOW_SCALE) as u32;
-    let window_h = ((VIEW_H + SCORE_AREA) * WINDOW_SCALE) as u32;
-    
-    let window = video
-        .window("Pacman - Atari 2600 Style (Rust)", window_w, window_h)
-        .position_centered()
-        .opengl()
-        .resizable()
-        .build()
-        .map_err(|e| e.to_string())?;
-    let mut canvas = window
-        .into_canvas()
-        .accelerated()
-        .present_vsync()
-        .build()
-        .map_err(|e| e.to_string())?;
+/// Counts heap allocations for the debug overlay
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
 
-    // Initialize audio
-    let _audio_manager = AudioManager::new(&sdl)?;
+/// Runs the simulation without a window and prints the final state
+/// 
+/// With `--replay` the recording's inputs drive the game; otherwise a
+/// single-player game (or the game of `--load-state`) runs with no input at all.
+/// 
+/// # Arguments
+/// * `ticks` - Maximum number of ticks to simulate (stops early when the game ends or the recording runs out)
+fn run_headless(options: &CliOptions, custom_maze: CustomMaze, ticks: u32) -> Result<(), String> {
+    let mut playback = match options.replay {
+        Some(ref path) => Some(TasPlayback::load(path)?),
+        None => None,
+    };
+    let mut game = match (&playback, &options.load_state) {
+        (Some(playback), _) => start_replay(playback, custom_maze)?,
+        (None, Some(path)) => load_state_dump(path)?,
+        (None, None) => {
+            select_maze(0, custom_maze);
+            let config = GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None);
+            Game::new_with_seed(config, options.seed.unwrap_or(DEFAULT_SEED))
+        }
+    };
     
-    // Initialize game state
-    let mut event_pump = sdl.event_pump()?;
-    let mut menu = Menu::new();
-    let mut game: Option<Game> = None;
-    let mut time_accumulator = 0.0f64;  // Accumulates time for fixed timestep
-    let mut previous_frame_time = Instant::now();
-    let delta_time = DT;
-    let mut in_menu = true;
-
-    'main_loop: loop {
-        // Process ALL events immediately - instantaneous input response
-        for event in event_pump.poll_iter() {
-            match event {
-                Event::Quit { .. } => break 'main_loop,
-                
-                // Escape key: exit game or return to menu
-                Event::KeyDown { scancode: Some(Scancode::Escape), .. } => {
-                    if in_menu {
-                        break 'main_loop;  // Exit game
-                    } else {
-                        in_menu = true;  // Return to menu
-                        game = None;
-                    }
-                }
-                
-                // Arrow keys: navigate menu or move player
-                Event::KeyDown { scancode: Some(Scancode::Up), .. } => {
-                    if in_menu {
-                        menu.process_input(0, -1);  // Move menu selection up
-                    } else if let Some(ref mut current_game) = game {
-                        current_game.process_input(0, -1);  // Move player up
-                    }
-                }
-                Event::KeyDown { scancode: Some(Scancode::Down), .. } => {
-                    if in_menu {
-                        menu.process_input(0, 1);  // Move menu selection down
-                    } else if let Some(ref mut current_game) = game {
-                        current_game.process_input(0, 1);  // Move player down
-                    }
-                }
-                Event::KeyDown { scancode: Some(Scancode::Left), .. } => {
-                    if !in_menu {
-                        if let Some(ref mut current_game) = game {
-                            current_game.process_input(-1, 0);  // Move player left
-                        }
-                    }
-                }
-                Event::KeyDown { scancode: Some(Scancode::Right), .. } => {
-                    if !in_menu {
-                        if let Some(ref mut current_game) = game {
-                            current_game.process_input(1, 0);  // Move player right
-                        }
-                    }
-                }
-                
-                // Backspace: go back in menu
-                Event::KeyDown { scancode: Some(Scancode::Backspace), .. } => {
-                    if in_menu {
-                        menu.back();
-                    }
-                }
-                
-                // Enter key: select menu option
-                Event::KeyDown { scancode: Some(Scancode::Return), .. } => {
-                    if in_menu {
-                        match menu.select() {
-                            MenuAction::SelectMaze(maze_index) => {
-                                // Switch to selected maze
-                                unsafe {
-                                    CURRENT_MAZE = match maze_index {
-                                        0 => &MAZE_1 as *const _,
-                                        1 => &MAZE_2 as *const _,
-                                        _ => &MAZE_1 as *const _,  // Default to maze 1
-                                    };
-                                }
-                                
-                                // Create game config from menu selections
-                                let config = GameConfig::new(
-                                    menu.game_mode.unwrap_or(GameMode::SinglePlayer),
-                                    menu.player1_role.unwrap_or(PlayerRole::PacMan),
-                                    menu.player2_role,
-                                );
-                                
-                                game = Some(Game::new(config));
-                                in_menu = false;
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                
-                // Window resize: update render cache
-                Event::Window { win_event, .. } => {
-                    if matches!(win_event, sdl2::event::WindowEvent::Resized(_, _) | 
-                                       sdl2::event::WindowEvent::SizeChanged(_, _)) {
-                        if let Some(ref mut current_game) = game {
-                            current_game.render_cache.window_size_changed = true;
-                        }
-                    }
-                }
-                _ => {}
-            }
+    let mut ticks_run = 0;
+    while ticks_run < ticks && game.alive {
+        if let Some(ref mut playback) = playback {
+            let Some(frame) = playback.next_frame() else { break };
+            game.tick(frame.player1_input, frame.player2_input);
+            playback.verify(game.rng.state(), game.state_hash());
+        } else {
+            game.tick(None, None);
         }
+        ticks_run += 1;
+    }
+    
+    println!("{}", EndState::of(&game, ticks_run));
+    if let Some(tick) = playback.as_ref().and_then(|playback| playback.desync_tick) {
+        return Err(format!("Replay desynced at tick {}", tick));
+    }
+    Ok(())
+}
 
-        if in_menu {
-            // Draw menu screen
-            menu.draw(&mut canvas)?;
-        } else {
-            // Fixed timestep game loop
-            // This ensures the game runs at a consistent speed regardless of frame rate
-            let current_time = Instant::now();
-            let frame_duration = current_time.duration_since(previous_frame_time);
-            previous_frame_time = current_time;
-            
-            // Add elapsed time to accumulator (clamped to prevent spiral of death)
-            time_accumulator += (frame_duration.as_secs_f64()).min(MAX_TIME_STEP);
+/// Runs the benchmark: the scripted ticks without a window, then drawn to a hidden one
+fn run_bench(options: &BenchOptions) -> Result<(), String> {
+    println!("{}", bench_ticks(options.ticks).report());
+    if !options.render {
+        return Ok(());
+    }
 
-            // Get keyboard state for held keys (fallback input)
-            let keyboard_state = event_pump.keyboard_state();
-            
-            // Check for player 2 input (WASD keys for multiplayer)
-            let player2_input = if game.as_ref().map(|g| g.config.mode == GameMode::Multiplayer).unwrap_or(false) {
-                let mut input = None;
-                if keyboard_state.is_scancode_pressed(Scancode::W) {
-                    input = Some((0, -1));
-                } else if keyboard_state.is_scancode_pressed(Scancode::S) {
-                    input = Some((0, 1));
-                } else if keyboard_state.is_scancode_pressed(Scancode::A) {
-                    input = Some((-1, 0));
-                } else if keyboard_state.is_scancode_pressed(Scancode::D) {
-                    input = Some((1, 0));
-                }
-                input
-            } else {
-                None
-            };
-            
-            if let Some(ref mut current_game) = game {
-                // Run game updates until we've caught up with real time
-                while time_accumulator >= delta_time {
-                    if current_game.alive {
-                        current_game.tick(&keyboard_state, player2_input);
-                    }
-                    time_accumulator -= delta_time;
-                }
+    // A window of the default size, never shown, without vsync so presenting doesn't wait
+    let sdl = sdl2::init()?;
+    let (w, h) = VideoSettings::new(false).window_size();
+    let window = sdl.video()?.window("Paclike bench", w, h).hidden().build().map_err(|e| e.to_string())?;
+    let mut canvas = build_canvas(window, false)?;
+    let mut cache = RenderCache::new();
+    let draw = bench_draw(&mut SdlRenderer::new(&mut canvas, &mut cache), options.ticks)?;
+    println!("{}", draw.report());
+    Ok(())
+}
 
-                // Draw the game
-                current_game.draw(&mut canvas)?;
+/// Checks the golden replays, or regenerates their golden files with `--update`
+fn run_golden(options: &GoldenOptions) -> Result<(), String> {
+    let paths = if options.paths.is_empty() { vec![DEFAULT_GOLDEN_DIR.into()] } else { options.paths.clone() };
+    let replays = find_replays(&paths)?;
+    if replays.is_empty() {
+        return Err("No golden replays found".to_string());
+    }
+    let mut failed = 0;
+    for replay in &replays {
+        let result = if options.update { update_golden(replay) } else { check_golden(replay) };
+        match result {
+            Ok(state) if options.update => println!("updated {}: {}", replay.display(), state),
+            Ok(_) => println!("ok {}", replay.display()),
+            Err(e) => {
+                println!("FAILED {}", e);
+                failed += 1;
             }
         }
-        
-        // Small sleep to reduce CPU usage when vsync is off
-        std::thread::sleep(Duration::from_millis(SLEEP_DURATION_MS));
+    }
+    if failed > 0 {
+        return Err(format!("{} of {} golden replays failed", failed, replays.len()));
     }
     Ok(())
 }
+
+fn main() -> Result<(), String> {
+    let options = match CliOptions::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            std::process::exit(2);
+        }
+    };
+    if options.help {
+        println!("{}", USAGE);
+        return Ok(());
+    }
+    if let Some(ref tournament) = options.tournament {
+        let pairings = run_tournament(tournament)?;
+        print!("{}", if tournament.csv { format_csv(&pairings) } else { format_table(&pairings) });
+        return Ok(());
+    }
+    if let Some(ref bench) = options.bench {
+        return run_bench(bench);
+    }
+    if let Some(ref golden) = options.golden {
+        return run_golden(golden);
+    }
+    let custom_maze = match options.maze {
+        Some(ref path) => Some(load_maze_file(path)?),
+        None => None,
+    };
+    if let Some(ticks) = options.headless {
+        return run_headless(&options, custom_maze, ticks);
+    }
+    
+    // Init SDL
+    let sdl = sdl2::init()?;
+    let mut app = App::new(&sdl, &options, custom_maze)?;
+    let mut event_pump = sdl.event_pump()?;
+    app.run(&mut event_pump)
+}
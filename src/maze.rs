@@ -5,22 +5,101 @@
 //! - Pellets (.)
 //! - Power pellets (*)
 //! - Teleporters (1)
+//! - Spawn points (P for Pac-Man, G for a ghost, F for the bonus fruit)
+//! - Stairs (S) between the floors of a two-floor maze
 //! - Empty spaces
+//!
+//! Walls blown up by a bomb are open until they heal (see `set_open_walls`);
+//! the layouts themselves never change.
+//!
+//! A maze may have a second floor stacked on the first, reached by stairs at
+//! the same tiles on both. Everyone is on one floor at a time: the tile
+//! functions look at the active floor (see `set_active_floor`), which the game
+//! switches to each Pac-Man's and ghost's floor before moving it.
 
-use crate::constants::{GRID_W, GRID_H};
+use crate::constants::{
+    GRID_W, GRID_H, CURRENT_MAZE,
+    PLAYER_START_X, PLAYER_START_Y, PLAYER2_START_X, PLAYER2_START_Y,
+    GHOST_START_X, GHOST_START_Y, FRUIT_X, FRUIT_Y, MAX_OPEN_WALLS
+};
+use std::sync::atomic::{AtomicI32, AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// A maze layout: one floor of GRID_H rows
+type Floor = &'static [&'static str; GRID_H as usize];
+
+/// Second floor of the current maze (None on a single-floor maze)
+static mut CURRENT_UPPER_FLOOR: Option<Floor> = None;
+
+/// Floor the tile functions look at (0 = ground floor, 1 = upper floor)
+static ACTIVE_FLOOR: AtomicUsize = AtomicUsize::new(0);
+
+/// Upper floors of the two-floor mazes loaded so far, by the address of their
+/// ground floor, so `set_current_maze` finds them from the ground floor alone
+static UPPER_FLOORS: Mutex<Vec<(usize, Floor)>> = Mutex::new(Vec::new());
+
+/// Line between the two floors of a maze file
+pub const FLOOR_SEPARATOR: &str = "===";
+
+/// Wall tiles of the current maze that are open (see `set_open_walls`), as
+/// `y * GRID_W + x`, or -1 for an unused slot
+static OPEN_WALLS: [AtomicI32; MAX_OPEN_WALLS] = [const { AtomicI32::new(-1) }; MAX_OPEN_WALLS];
 
 /// Gets a reference to the currently selected maze
 /// 
-/// Returns the maze as a slice of strings, where each string is a row.
-/// Each character represents a tile: '#' = wall, '.' = pellet, '*' = power pellet
+/// Returns the active floor of the maze as a slice of strings, where each
+/// string is a row. Each character represents a tile: '#' = wall, '.' = pellet,
+/// '*' = power pellet
 #[inline]
 pub fn get_maze() -> &'static [&'static str] {
-    unsafe { 
-        let arr_ref: &[&str; crate::constants::GRID_H as usize] = &*crate::constants::CURRENT_MAZE;
-        arr_ref as &[&str]
+    floor_rows(ACTIVE_FLOOR.load(Ordering::Relaxed))
+}
+
+/// Gets the rows of one floor of the current maze (the ground floor if it has no such floor)
+pub fn floor_rows(floor: usize) -> &'static [&'static str] {
+    unsafe {
+        match CURRENT_UPPER_FLOOR {
+            Some(upper) if floor == 1 => upper as &[&str],
+            _ => {
+                let arr_ref: &[&str; GRID_H as usize] = &*CURRENT_MAZE;
+                arr_ref as &[&str]
+            }
+        }
     }
 }
 
+/// Number of floors of the current maze (1 or 2)
+pub fn floor_count() -> usize {
+    match unsafe { CURRENT_UPPER_FLOOR } {
+        Some(_) => 2,
+        None => 1,
+    }
+}
+
+/// Makes the tile functions look at the given floor of the current maze
+pub fn set_active_floor(floor: usize) {
+    ACTIVE_FLOOR.store(floor.min(floor_count() - 1), Ordering::Relaxed);
+}
+
+/// Floor the tile functions look at (see `set_active_floor`)
+pub fn active_floor() -> usize {
+    ACTIVE_FLOOR.load(Ordering::Relaxed)
+}
+
+/// Checks if the given position holds stairs to the other floor ('S')
+#[inline]
+pub fn is_stairs(x: i32, y: i32) -> bool {
+    tile_on(get_maze(), x, y) == Some(b'S')
+}
+
+/// The tile at a position of a floor (None out of bounds)
+fn tile_on(rows: &[&str], x: i32, y: i32) -> Option<u8> {
+    if x < 0 || y < 0 {
+        return None;
+    }
+    rows.get(y as usize)?.as_bytes().get(x as usize).copied()
+}
+
 /// Checks if the given position contains a wall
 /// 
 /// # Arguments
@@ -28,9 +107,19 @@ pub fn get_maze() -> &'static [&'static str] {
 /// * `y` - Y coordinate (0 to GRID_H-1)
 /// 
 /// # Returns
-/// `true` if the position is out of bounds or contains a wall ('#'), `false` otherwise
+/// `true` if the position is out of bounds or contains a wall ('#') that
+/// isn't open (see `set_open_walls`), `false` otherwise
 #[inline]
 pub fn is_wall(x: i32, y: i32) -> bool {
+    is_layout_wall(x, y) && !is_open_wall(x, y)
+}
+
+/// Checks if the given position contains a wall in the maze layout, open or not
+/// 
+/// # Returns
+/// `true` if the position is out of bounds or contains a wall ('#'), `false` otherwise
+#[inline]
+pub fn is_layout_wall(x: i32, y: i32) -> bool {
     if x < 0 || x >= GRID_W || y < 0 || y >= GRID_H {
         return true;
     }
@@ -46,6 +135,62 @@ pub fn is_wall(x: i32, y: i32) -> bool {
     row.as_bytes()[x as usize] == b'#'
 }
 
+/// Opens the given wall tiles for `is_wall`, closing any opened before
+///
+/// The game that owns them sets them at the start of every tick, so several
+/// games on one maze (like a split-screen race) each see their own. Only the
+/// first `MAX_OPEN_WALLS` are taken.
+pub fn set_open_walls(tiles: impl IntoIterator<Item = (i32, i32)>) {
+    let mut tiles = tiles.into_iter();
+    for slot in &OPEN_WALLS {
+        let index = tiles.next().map(|(x, y)| y * GRID_W + x).unwrap_or(-1);
+        slot.store(index, Ordering::Relaxed);
+    }
+}
+
+/// Checks if the given position is a wall tile that is currently open (see `set_open_walls`)
+#[inline]
+pub fn is_open_wall(x: i32, y: i32) -> bool {
+    let index = y * GRID_W + x;
+    OPEN_WALLS.iter().any(|slot| slot.load(Ordering::Relaxed) == index)
+}
+
+/// Checks if the given position is a wall a bomb can blow up: any wall tile
+/// that isn't on the border of the maze
+pub fn is_blastable(x: i32, y: i32) -> bool {
+    x > 0 && x < GRID_W - 1 && y > 0 && y < GRID_H - 1 && is_wall(x, y)
+}
+
+/// Checks if a row is a tunnel: open on both the left and right border, so
+/// walking off one side comes back in on the other
+/// 
+/// A maze may have any number of tunnels.
+/// 
+/// # Arguments
+/// * `y` - Row (0 to GRID_H-1)
+pub fn is_tunnel_row(y: i32) -> bool {
+    !is_wall(0, y) && !is_wall(GRID_W - 1, y)
+}
+
+/// Wraps a position that walked off the left or right border of a tunnel row
+/// around to the other side
+/// 
+/// # Arguments
+/// * `x` - X coordinate, possibly -1 or GRID_W
+/// * `y` - Y coordinate
+/// 
+/// # Returns
+/// The position to move to: unchanged unless it left the maze through a tunnel
+pub fn wrap_tunnel(x: i32, y: i32) -> (i32, i32) {
+    if x < 0 && is_tunnel_row(y) {
+        (GRID_W - 1, y)
+    } else if x >= GRID_W && is_tunnel_row(y) {
+        (0, y)
+    } else {
+        (x, y)
+    }
+}
+
 /// Checks if the given position contains a pellet (regular or power)
 /// 
 /// # Arguments
@@ -216,19 +361,206 @@ pub fn find_other_teleporter(current_x: i32, current_y: i32) -> Option<(i32, i32
     None
 }
 
+/// Where Pac-Man and the ghosts start, and the bonus fruit appears, on the current maze
+#[derive(Clone, Copy, PartialEq)]
+pub struct SpawnPoints {
+    /// Player 1's and (in co-op) player 2's tiles
+    pub players: [(i32, i32); 2],
+    /// Each ghost's tile in the ghost house
+    pub ghosts: [(i32, i32); 3],
+    /// The bonus fruit's tile
+    pub fruit: (i32, i32),
+}
+
+/// Finds the spawn points of the current maze
+/// 
+/// 'P' tiles place the Pac-Men and 'G' tiles the ghosts, in reading order
+/// (left to right, top to bottom); both are otherwise empty. With one 'P'
+/// the second Pac-Man starts next to the first, and with fewer 'G' tiles than
+/// ghosts the last one is shared. The first 'F' tile is where the bonus fruit
+/// appears. A maze without them (like the built-in ones) uses the classic
+/// positions from `constants.rs`, and the fruit appears on player 1's tile if
+/// the classic fruit tile is a wall.
+pub fn spawn_points() -> SpawnPoints {
+    let mut player_tiles = Vec::new();
+    let mut ghost_tiles = Vec::new();
+    let mut fruit_tile = None;
+    let ground = floor_rows(0);
+    for (y, row) in ground.iter().enumerate() {
+        for (x, tile) in row.bytes().enumerate() {
+            match tile {
+                b'P' => player_tiles.push((x as i32, y as i32)),
+                b'G' => ghost_tiles.push((x as i32, y as i32)),
+                b'F' if fruit_tile.is_none() => fruit_tile = Some((x as i32, y as i32)),
+                _ => {}
+            }
+        }
+    }
+
+    // Bombs aside: spawn points never move
+    let is_wall = |x: i32, y: i32| tile_on(ground, x, y).is_none_or(|tile| tile == b'#');
+    let players = match player_tiles[..] {
+        [] => [(PLAYER_START_X, PLAYER_START_Y), (PLAYER2_START_X, PLAYER2_START_Y)],
+        [(x, y)] if !is_wall(x + 1, y) => [(x, y), (x + 1, y)],
+        [first] => [first, first],
+        [first, second, ..] => [first, second],
+    };
+    let ghosts = if ghost_tiles.is_empty() {
+        [(GHOST_START_X - 1, GHOST_START_Y), (GHOST_START_X, GHOST_START_Y), (GHOST_START_X + 1, GHOST_START_Y)]
+    } else {
+        std::array::from_fn(|i| ghost_tiles[i.min(ghost_tiles.len() - 1)])
+    };
+    let fruit = fruit_tile.unwrap_or(if is_wall(FRUIT_X, FRUIT_Y) { players[0] } else { (FRUIT_X, FRUIT_Y) });
+    SpawnPoints { players, ghosts, fruit }
+}
+
 /// Counts the total number of pellets (regular + power) in the current maze
 /// 
 /// # Returns
-/// The total number of pellets in the maze
+/// The total number of pellets in the maze, on every floor
 pub fn count_pellets() -> i32 {
     let mut count = 0;
-    for y in 0..GRID_H {
-        for x in 0..GRID_W {
-            if is_pellet(x, y) {
-                count += 1;
+    for floor in 0..floor_count() {
+        let rows = floor_rows(floor);
+        for y in 0..GRID_H {
+            for x in 0..GRID_W {
+                if matches!(tile_on(rows, x, y), Some(b'.' | b'*')) {
+                    count += 1;
+                }
             }
         }
     }
     count
 }
 
+/// Computes a hash of the current maze layout (FNV-1a over all tiles)
+/// 
+/// Used to key per-maze records so they stay valid for custom mazes and
+/// never mix between layouts.
+pub fn maze_hash() -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for row in (0..floor_count()).flat_map(floor_rows) {
+        for byte in row.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+    hash
+}
+
+/// Makes the given maze the current one
+pub fn set_current_maze(maze: &'static [&'static str; GRID_H as usize]) {
+    let upper = UPPER_FLOORS
+        .lock()
+        .ok()
+        .and_then(|floors| floors.iter().find(|(ground, _)| *ground == maze.as_ptr() as usize).map(|(_, upper)| *upper));
+    unsafe {
+        CURRENT_MAZE = maze as *const _;
+        CURRENT_UPPER_FLOOR = upper;
+    }
+    ACTIVE_FLOOR.store(0, Ordering::Relaxed);
+}
+
+/// Loads a maze from a text file in the same format as the built-in mazes
+/// 
+/// The file holds GRID_H rows of up to GRID_W tiles ('#' wall, '.' pellet,
+/// '*' power pellet, ' ' empty, digits for teleporter pairs, 'P', 'G' and
+/// 'F' for spawn points). Shorter rows are padded with empty tiles.
+/// 
+/// A two-floor maze follows the ground floor with a `===` line and GRID_H
+/// more rows for the upper floor. Stairs ('S') must sit on the same tiles on
+/// both floors, and spawn points belong on the ground floor.
+/// 
+/// The maze lives for the rest of the program, like the built-in ones.
+pub fn load_maze_file(path: &std::path::Path) -> Result<&'static [&'static str; GRID_H as usize], String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read maze {}: {}", path.display(), e))?;
+    parse_maze(&text, &path.display().to_string())
+}
+
+/// Parses a maze from text in the same format as `load_maze_file`
+/// 
+/// # Arguments
+/// * `text` - The maze rows, one per line
+/// * `name` - What to call the maze in error messages (e.g. its file name)
+pub fn parse_maze(text: &str, name: &str) -> Result<&'static [&'static str; GRID_H as usize], String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let (ground_lines, upper_lines) = match lines.iter().position(|line| line.trim() == FLOOR_SEPARATOR) {
+        Some(split) => (&lines[..split], Some(&lines[split + 1..])),
+        None => (&lines[..], None),
+    };
+
+    let ground = parse_floor(ground_lines, name, "")?;
+    let Some(upper_lines) = upper_lines else {
+        if ground.iter().any(|row| row.contains('S')) {
+            return Err(format!("Maze {} has stairs but no upper floor", name));
+        }
+        if !ground.iter().any(|row| row.contains('.') || row.contains('*')) {
+            return Err(format!("Maze {} has no pellets", name));
+        }
+        return Ok(ground);
+    };
+
+    let upper = parse_floor(upper_lines, name, "upper floor ")?;
+    if upper.iter().any(|row| row.contains(['P', 'G', 'F'])) {
+        return Err(format!("Maze {} has spawn points on its upper floor", name));
+    }
+    let stairs = |floor: &[&str]| -> Vec<(usize, usize)> {
+        floor.iter().enumerate()
+            .flat_map(|(y, row)| row.bytes().enumerate().filter(|&(_, tile)| tile == b'S').map(move |(x, _)| (x, y)))
+            .collect()
+    };
+    let ground_stairs = stairs(ground);
+    if ground_stairs.is_empty() {
+        return Err(format!("Maze {} has two floors but no stairs", name));
+    }
+    if ground_stairs != stairs(upper) {
+        return Err(format!("Maze {} has stairs that do not line up between its floors", name));
+    }
+    if !ground.iter().chain(upper.iter()).any(|row| row.contains('.') || row.contains('*')) {
+        return Err(format!("Maze {} has no pellets", name));
+    }
+    if let Ok(mut floors) = UPPER_FLOORS.lock() {
+        floors.push((ground.as_ptr() as usize, upper));
+    }
+    Ok(ground)
+}
+
+/// Parses the GRID_H rows of one floor of a maze (see `parse_maze`)
+/// 
+/// # Arguments
+/// * `lines` - The floor's rows, one per line
+/// * `name` - What to call the maze in error messages
+/// * `floor` - Which floor this is in error messages ("" or "upper floor ")
+fn parse_floor(lines: &[&str], name: &str, floor: &str) -> Result<Floor, String> {
+    // Trailing blank lines are allowed
+    let row_count = lines.iter().rposition(|row| !row.trim().is_empty()).map(|i| i + 1).unwrap_or(0);
+    if row_count != GRID_H as usize {
+        return Err(format!("Maze {} {}has {} rows, expected {}", name, floor, row_count, GRID_H));
+    }
+    
+    let mut maze: [&'static str; GRID_H as usize] = [""; GRID_H as usize];
+    for (y, row) in lines[..row_count].iter().enumerate() {
+        if row.chars().count() > GRID_W as usize {
+            return Err(format!("Maze {}row {} is wider than {} tiles", floor, y + 1, GRID_W));
+        }
+        if let Some(bad) = row.chars().find(|c| !matches!(c, '#' | '.' | '*' | ' ' | '0'..='9' | 'P' | 'G' | 'F' | 'S')) {
+            return Err(format!("Maze {}row {} has unknown tile '{}'", floor, y + 1, bad));
+        }
+        let padded = format!("{:<width$}", row, width = GRID_W as usize);
+        maze[y] = Box::leak(padded.into_boxed_str());
+    }
+    Ok(Box::leak(Box::new(maze)))
+}
+
+/// Writes the current maze back out in the format read by `parse_maze`
+pub fn maze_text() -> String {
+    let mut text = floor_rows(0).join("\n");
+    if floor_count() > 1 {
+        text.push('\n');
+        text.push_str(FLOOR_SEPARATOR);
+        text.push('\n');
+        text.push_str(&floor_rows(1).join("\n"));
+    }
+    text
+}
@@ -2,21 +2,170 @@
 
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
+use crate::audio::{MusicContext, Sound};
+use crate::constants::{MAX_CORNERING_WINDOW, FPS, TICK_RATES};
 use crate::game_config::{GameMode, PlayerRole};
+use crate::net::{DEFAULT_INPUT_DELAY, MAX_INPUT_DELAY};
+use sdl2::keyboard::Scancode;
+use crate::profile_file::ImportMode;
+use crate::rating::{Ratings, is_name_char, MAX_NAME_LEN};
+use crate::scoring::ScoreSource;
+use crate::stats::History;
+use crate::settings::{Settings, BINDING_NAMES, MAX_RUMBLE, MAX_VOLUME};
+use crate::font::{draw_text, draw_lines, wrap_text, Align};
+use crate::help::draw_help;
+use crate::jukebox::{self, JukeboxEntry};
+use crate::render::{draw_ghost, draw_pacman, GhostView, GHOST_COLORS, PLAYER_COLORS};
+use crate::skins::{find_skin, load_skins, Skin, SkinChoices};
+use crate::ui;
+use crate::unlocks::{self, Unlock, Unlocks, MAZE_NAMES};
 
 /// Menu state: which menu screen is currently displayed
 #[derive(Clone, Copy, PartialEq)]
 pub enum MenuState {
-    GameMode,      // Single player or Multiplayer
+    ContinueSave,  // An autosaved run exists: continue it or start something new
+    GameMode,      // Single player, multiplayer, co-op, alternating, time trial, race or online
     RoleSelection, // Pac-Man or Ghost
     MazeSelection, // Which maze to play
+    LevelSelection, // Which unlocked level to start at
+    OnlineSelection, // Host or join an online game
+    HostLobby,     // Hosting: shows port/join code, role and maze choice, start
+    JoinAddress,   // Joining: type the host's address or join code
+    ClientLobby,   // Joined: shows the host's choices, ready toggle
+    Connecting,    // Connection status or a readable connection error
+    Settings,      // Settings pages: video, audio, controls, gameplay
+    VideoSettings, // Fullscreen, monitor and window size
+    AudioSettings, // Music and sound effect volume
+    Jukebox,       // Every track and effect, played on their own
+    ControlSettings, // Key bindings
+    GameplaySettings, // Pause on focus loss, cornering window, ghost targeting, ghost AI preset, Pac-Man AI, tick rate, ice maze, telemetry
+    PlayerSettings, // Profile names of player 1 and player 2, with their ratings and statistics
+    Skins,         // The skin each player's profile wears, previewed as it is chosen
+    Unlocks,       // What player 1 has earned and what the rest takes; maze colors kept for every level
+    ImportConflict, // An imported profile's name is taken: merge, replace or cancel
+    Help,          // Controls, scoring and ghost behavior
+}
+
+impl MenuState {
+    /// Whether this is the settings page or one of its sub-pages
+    pub fn is_settings(self) -> bool {
+        matches!(
+            self,
+            MenuState::Settings | MenuState::VideoSettings | MenuState::AudioSettings | MenuState::Jukebox |
+            MenuState::ControlSettings | MenuState::GameplaySettings | MenuState::PlayerSettings |
+            MenuState::ImportConflict | MenuState::Skins | MenuState::Unlocks
+        )
+    }
 }
 
 pub enum MenuAction {
     None,
     SelectMaze(usize),
+    /// Start on a maze at a later level (maze index, level)
+    SelectLevel(usize, u32),
+    /// Play a campaign (index into `campaign_names`)
+    SelectCampaign(usize),
     SelectGameMode(GameMode),
     SelectRole(PlayerRole),
+    HostOnline,
+    JoinOnline,
+    LobbyChanged,
+    ToggleReady,
+    StartOnline,
+    VideoChanged,
+    /// An audio, control or gameplay setting changed (save and apply it)
+    SettingsChanged,
+    /// A profile name was edited (save the ratings file)
+    NamesChanged,
+    /// A player chose another skin (save the skins file and wear it)
+    SkinsChanged,
+    /// Continue the autosaved run
+    ContinueSave,
+    /// Write the statistics to CSV files (see `stats`)
+    ExportStats,
+    /// Write player 1's profile to a file (see `profile_file`)
+    ExportProfile,
+    /// Read a profile file waiting to be imported
+    ImportProfile,
+    /// Take in the profile waiting on the conflict page
+    ResolveImport(ImportMode),
+    /// Play a track or effect in the jukebox
+    PlaySound(Sound),
+    /// Silence the jukebox
+    StopSounds,
+}
+
+/// What the lobby screens show about the online session
+/// 
+/// Filled in by the main loop from the network state; the host edits
+/// `host_role`, `maze_index` and `input_delay` through the menu.
+pub struct LobbyInfo {
+    /// Port the host is listening on
+    pub port: u16,
+    /// Join code for the host's address
+    pub join_code: String,
+    /// Role played by the host (the client plays the other one)
+    pub host_role: PlayerRole,
+    /// Maze chosen by the host
+    pub maze_index: usize,
+    /// Ticks between reading an input and applying it, chosen by the host
+    /// (more hides a worse connection, at the cost of sluggish controls)
+    pub input_delay: u32,
+    /// Whether the other player is connected
+    pub peer_connected: bool,
+    /// Whether the client has said it is ready
+    pub client_ready: bool,
+    /// The other player's profile name, once it has arrived
+    pub peer_name: Option<String>,
+}
+
+/// Display name of a role
+fn role_name(role: PlayerRole) -> &'static str {
+    match role {
+        PlayerRole::PacMan => "Pac-Man",
+        PlayerRole::Ghost => "Ghost",
+    }
+}
+
+/// Input delay in ticks and in milliseconds, e.g. "3 ticks (50 ms)"
+fn input_delay_name(ticks: u32) -> String {
+    let ms = ticks * 1000 / FPS;
+    match ticks {
+        0 => "None".to_string(),
+        1 => format!("1 tick ({} ms)", ms),
+        _ => format!("{} ticks ({} ms)", ticks, ms),
+    }
+}
+
+/// The role opposite to the given one
+fn other_role(role: PlayerRole) -> PlayerRole {
+    match role {
+        PlayerRole::PacMan => PlayerRole::Ghost,
+        PlayerRole::Ghost => PlayerRole::PacMan,
+    }
+}
+
+/// The selectable entries of a page as it is drawn, in selection order
+#[derive(Default)]
+struct Selectable {
+    areas: Vec<Rect>,
+    labels: Vec<String>,
+}
+
+impl Selectable {
+    /// Draws entries with `ui::list` and notes them
+    fn list(
+        &mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, entries: &[&str], selected: usize, center_x: i32, y: i32
+    ) {
+        self.areas.extend(ui::list(canvas, entries, selected, center_x, y));
+        self.labels.extend(entries.iter().map(|entry| entry.to_string()));
+    }
+
+    /// Notes an entry drawn some other way
+    fn push(&mut self, area: Rect, label: String) {
+        self.areas.push(area);
+        self.labels.push(label);
+    }
 }
 
 pub struct Menu {
@@ -25,6 +174,57 @@ pub struct Menu {
     pub game_mode: Option<GameMode>,
     pub player1_role: Option<PlayerRole>,
     pub player2_role: Option<PlayerRole>,
+    /// Address or join code typed on the join screen
+    pub join_address: String,
+    /// Status line shown on the connecting screen
+    pub status: String,
+    /// Online session details shown in the lobby screens
+    pub lobby: LobbyInfo,
+    /// Whether the next games will be recorded (shows a REC marker)
+    pub recording: bool,
+    /// Whether the next games will be practice runs (shows a PRACTICE marker)
+    pub practice: bool,
+    /// Settings edited on the settings pages
+    pub settings: Settings,
+    /// Versus ratings, with the profile names edited on the players page
+    pub ratings: Ratings,
+    /// Runs played, added up per profile on the players page
+    pub history: History,
+    /// Where the last export went, how the last import went, or why either
+    /// failed, shown on the players page
+    pub notice: String,
+    /// Name of the imported profile the conflict page asks about
+    pub importing: String,
+    /// What the last run unlocked, shown on the first page until the next run
+    pub unlock_notice: String,
+    /// Every skin, read at startup and again as the skins page opens
+    pub skins: Vec<Skin>,
+    /// The skin each profile wears
+    pub skin_choices: SkinChoices,
+    /// Waiting for a key to bind to the selected action on the controls page
+    pub capturing: bool,
+    /// Number of monitors, filled in by the main loop
+    pub display_count: i32,
+    /// Highest level the player may start at, filled in by the main loop
+    pub unlocked_level: u32,
+    /// Level chosen on the level select screen
+    pub start_level: u32,
+    /// Maze chosen before the level select screen
+    pub maze_index: usize,
+    /// Campaigns offered after the mazes, filled in by the main loop
+    pub campaign_names: Vec<String>,
+    /// Screen area of each selectable entry as last drawn (for the mouse)
+    hit_areas: Vec<Rect>,
+    /// What each selectable entry says, as last drawn (for announcements)
+    labels: Vec<String>,
+    /// Sliders as last drawn; slider `i` is entry `i` of its page
+    sliders: Vec<ui::SliderArea>,
+    /// Tracks and effects listed by the jukebox, read as it opens
+    jukebox: Vec<JukeboxEntry>,
+    /// Track the jukebox is playing
+    jukebox_playing: Option<MusicContext>,
+    /// Frames drawn, to animate the skin preview
+    preview_frame: u32,
 }
 
 impl Menu {
@@ -35,14 +235,223 @@ impl Menu {
             game_mode: None,
             player1_role: None,
             player2_role: None,
+            join_address: String::new(),
+            status: String::new(),
+            lobby: LobbyInfo {
+                port: 0,
+                join_code: String::new(),
+                host_role: PlayerRole::PacMan,
+                maze_index: 0,
+                input_delay: DEFAULT_INPUT_DELAY,
+                peer_connected: false,
+                client_ready: false,
+                peer_name: None,
+            },
+            recording: false,
+            practice: false,
+            settings: Settings::default(),
+            ratings: Ratings::default(),
+            history: History::default(),
+            notice: String::new(),
+            importing: String::new(),
+            unlock_notice: String::new(),
+            skins: Vec::new(),
+            skin_choices: SkinChoices::default(),
+            capturing: false,
+            display_count: 1,
+            unlocked_level: 1,
+            start_level: 1,
+            maze_index: 0,
+            campaign_names: Vec::new(),
+            hit_areas: Vec::new(),
+            labels: Vec::new(),
+            sliders: Vec::new(),
+            jukebox: Vec::new(),
+            jukebox_playing: None,
+            preview_frame: 0,
         }
     }
 
-    pub fn process_input(&mut self, _dx: i32, dy: i32) -> MenuAction {
+    /// Adds typed text to the join address on the join screen, or to the
+    /// selected profile name on the players page
+    pub fn text_input(&mut self, text: &str) -> MenuAction {
+        if self.state == MenuState::JoinAddress {
+            for ch in text.chars() {
+                if (ch.is_ascii_alphanumeric() || ch == '.' || ch == ':' || ch == '-') && self.join_address.len() < 40 {
+                    self.join_address.push(ch);
+                }
+            }
+        }
+        let Some(name) = self.edited_name() else { return MenuAction::None };
+        let before = name.len();
+        for ch in text.chars() {
+            if is_name_char(ch) && name.len() < MAX_NAME_LEN {
+                name.push(ch);
+            }
+        }
+        if name.len() == before { MenuAction::None } else { MenuAction::NamesChanged }
+    }
+
+    /// Backspace: deletes a character of the profile name being edited, or goes back
+    pub fn backspace(&mut self) -> MenuAction {
+        if let Some(name) = self.edited_name() {
+            if name.pop().is_some() {
+                return MenuAction::NamesChanged;
+            }
+        }
+        self.back();
+        MenuAction::None
+    }
+
+    /// The profile name selected on the players page (typing edits it)
+    fn edited_name(&mut self) -> Option<&mut String> {
+        if self.state != MenuState::PlayerSettings {
+            return None;
+        }
+        self.ratings.names.get_mut(self.selected)
+    }
+
+    /// "Alice (1032) vs Bob (968)" for the online lobby, or just our side
+    /// while the other player's name hasn't arrived
+    fn versus_line(&self) -> String {
+        let ours = self.ratings.name(0);
+        let with_rating = |name: &str| format!("{} ({})", name, self.ratings.get(name).rating);
+        match self.lobby.peer_name {
+            Some(ref peer) => format!("{} vs {}", with_rating(ours), with_rating(peer)),
+            None => with_rating(ours),
+        }
+    }
+
+    /// What the selected entry says, as last drawn (the connecting screen's
+    /// status, which has no entries)
+    pub fn selected_label(&self) -> Option<&str> {
+        if self.state == MenuState::Connecting {
+            return Some(&self.status);
+        }
+        self.labels.get(self.selected).map(String::as_str)
+    }
+
+    /// What player 1's profile has unlocked
+    pub fn unlocks(&self) -> Unlocks {
+        Unlocks::of(&self.history, self.ratings.name(0))
+    }
+
+    /// Whether a player's profile may wear a skin (only built-in ones are earned)
+    pub fn skin_unlocked(&self, player: usize, skin: &Skin) -> bool {
+        skin.built_in_index().is_none_or(|index| {
+            Unlocks::of(&self.history, self.ratings.name(player)).has(Unlock::Skin(index))
+        })
+    }
+
+    /// The skin a player's profile wears (the classic one if it isn't theirs to wear)
+    pub fn skin_of(&self, player: usize) -> Option<&Skin> {
+        let chosen = find_skin(&self.skins, self.skin_choices.get(self.ratings.name(player)))?;
+        if self.skin_unlocked(player, chosen) { Some(chosen) } else { self.skins.first() }
+    }
+
+    /// Picks the next (or previous) tick rate of `TICK_RATES`, wrapping around
+    fn cycle_tick_rate(&mut self, step: i32) -> MenuAction {
+        let gameplay = &mut self.settings.gameplay;
+        let index = TICK_RATES.iter().position(|&rate| rate == gameplay.tick_rate).unwrap_or(0);
+        gameplay.tick_rate = TICK_RATES[(index as i32 + step).rem_euclid(TICK_RATES.len() as i32) as usize];
+        MenuAction::SettingsChanged
+    }
+
+    /// Puts the next (or previous) skin a player may wear on their profile
+    fn cycle_skin(&mut self, player: usize, step: i32) -> MenuAction {
+        let count = self.skins.len() as i32;
+        let current = self.skin_of(player).and_then(|worn| self.skins.iter().position(|skin| skin.name == worn.name));
+        let Some(current) = current else { return MenuAction::None };
+        let next = (1..count)
+            .map(|offset| (current as i32 + step * offset).rem_euclid(count) as usize)
+            .find(|&index| self.skin_unlocked(player, &self.skins[index]));
+        let Some(next) = next else { return MenuAction::None };
+        let name = self.skins[next].name.clone();
+        self.skin_choices.set(self.ratings.name(player), &name);
+        MenuAction::SkinsChanged
+    }
+
+    /// Switches to the connecting screen with the given status message
+    pub fn show_status(&mut self, status: &str) {
+        self.state = MenuState::Connecting;
+        self.status = status.to_string();
+        self.selected = 0;
+    }
+
+    pub fn process_input(&mut self, dx: i32, dy: i32) -> MenuAction {
+        if self.capturing {
+            return MenuAction::None;
+        }
+        if dx != 0 && self.state == MenuState::AudioSettings && self.selected < 2 {
+            let audio = &mut self.settings.audio;
+            let volume = if self.selected == 0 { &mut audio.music_volume } else { &mut audio.effects_volume };
+            let changed = (*volume as i32 + dx).clamp(0, MAX_VOLUME as i32) as u8;
+            if changed == *volume {
+                return MenuAction::None;
+            }
+            *volume = changed;
+            return MenuAction::SettingsChanged;
+        }
+        if dx != 0 && self.state == MenuState::ControlSettings && self.selected == BINDING_NAMES.len() {
+            let controls = &mut self.settings.controls;
+            let changed = (controls.rumble as i32 + dx).clamp(0, MAX_RUMBLE as i32) as u8;
+            if changed == controls.rumble {
+                return MenuAction::None;
+            }
+            controls.rumble = changed;
+            return MenuAction::SettingsChanged;
+        }
+        if dx != 0 && self.state == MenuState::GameplaySettings && self.selected == 1 {
+            let gameplay = &mut self.settings.gameplay;
+            let changed = (gameplay.cornering_window + dx).clamp(0, MAX_CORNERING_WINDOW);
+            if changed == gameplay.cornering_window {
+                return MenuAction::None;
+            }
+            gameplay.cornering_window = changed;
+            return MenuAction::SettingsChanged;
+        }
+        if dx != 0 && self.state == MenuState::GameplaySettings && self.selected == 5 {
+            return self.cycle_tick_rate(dx.signum());
+        }
+        if dx != 0 && self.state == MenuState::HostLobby && self.selected == 2 {
+            let changed = (self.lobby.input_delay as i32 + dx).clamp(0, MAX_INPUT_DELAY as i32) as u32;
+            if changed == self.lobby.input_delay {
+                return MenuAction::None;
+            }
+            self.lobby.input_delay = changed;
+            return MenuAction::LobbyChanged;
+        }
+        if dx != 0 && self.state == MenuState::Skins && self.selected < 2 {
+            return self.cycle_skin(self.selected, dx.signum());
+        }
+        if dx != 0 && self.state == MenuState::LevelSelection {
+            // Wraps around, so the highest level is one press away
+            let levels = self.unlocked_level.max(1) as i32;
+            self.start_level = ((self.start_level as i32 - 1 + dx).rem_euclid(levels) + 1) as u32;
+            return MenuAction::None;
+        }
+        
         let max_selection = match self.state {
-            MenuState::GameMode => 1,        // Single Player, Multiplayer
+            MenuState::ContinueSave => 1,     // Continue, New Game
+            MenuState::GameMode => 8,        // Single Player, Multiplayer, Co-op, Alternating, Time Trial, Race, Online, Settings, How to Play
             MenuState::RoleSelection => 1,    // Pac-Man, Ghost
-            MenuState::MazeSelection => 1,    // Maze 1, Maze 2
+            MenuState::MazeSelection => self.maze_options().len() - 1, // Maze 1, Maze 2, campaigns
+            MenuState::LevelSelection => 0,   // Level (changed with left and right)
+            MenuState::OnlineSelection => 1,  // Host, Join
+            MenuState::HostLobby => 3,        // Role, Maze, Input delay, Start
+            MenuState::ClientLobby => 0,      // Ready
+            MenuState::JoinAddress | MenuState::Connecting => 0,
+            MenuState::Settings => 7,         // Video, Audio, Controls, Gameplay, Players, Skins, Unlocks, Back
+            MenuState::VideoSettings => 7,    // Fullscreen, Display, Window size, VSync, Frame cap, Render scale, Photosensitive, Back
+            MenuState::AudioSettings => 6,    // Music, Effects, Ghost Proximity, Announcements, Clip Sound, Jukebox, Back
+            MenuState::Jukebox => self.jukebox.len() + 1, // One per track and effect, Stop, Back
+            MenuState::ControlSettings => BINDING_NAMES.len() + 3, // One per binding, Rumble, Touch Controls, One Button, Back
+            MenuState::GameplaySettings => 8, // Pause on focus loss, Cornering, Targeting, Ghosts, Pac-Man AI, Tick Rate, Ice Maze, Telemetry, Back
+            MenuState::PlayerSettings => 5,   // Player 1, Player 2, Export Stats, Export Profile, Import Profile, Back
+            MenuState::Skins => 2,            // Player 1, Player 2, Back
+            MenuState::Unlocks => unlocks::listed().len(), // One per unlock, Back
+            MenuState::ImportConflict => 2,   // Merge, Replace, Cancel
+            MenuState::Help => 0,             // Back
         };
         
         if dy < 0 && self.selected > 0 {
@@ -55,14 +464,53 @@ impl Menu {
 
     pub fn select(&mut self) -> MenuAction {
         match self.state {
+            MenuState::ContinueSave if self.selected == 0 => MenuAction::ContinueSave,
+            MenuState::ContinueSave => {
+                self.state = MenuState::GameMode;
+                self.selected = 0;
+                MenuAction::None
+            }
+            MenuState::GameMode if self.selected == 6 => {
+                // Online: host plays Pac-Man, client plays a ghost
+                self.game_mode = Some(GameMode::Multiplayer);
+                self.player1_role = Some(PlayerRole::PacMan);
+                self.player2_role = Some(PlayerRole::Ghost);
+                self.state = MenuState::OnlineSelection;
+                self.selected = 0;
+                MenuAction::SelectGameMode(GameMode::Multiplayer)
+            }
+            MenuState::GameMode if self.selected == 7 => {
+                self.state = MenuState::Settings;
+                self.selected = 0;
+                MenuAction::None
+            }
+            MenuState::GameMode if self.selected == 8 => {
+                self.state = MenuState::Help;
+                self.selected = 0;
+                MenuAction::None
+            }
             MenuState::GameMode => {
-                let mode = if self.selected == 0 {
-                    GameMode::SinglePlayer
-                } else {
-                    GameMode::Multiplayer
+                let mode = match self.selected {
+                    0 => GameMode::SinglePlayer,
+                    1 => GameMode::Multiplayer,
+                    2 => GameMode::Coop,
+                    3 => GameMode::Alternating,
+                    4 => GameMode::TimeTrial,
+                    _ => GameMode::Race,
                 };
                 self.game_mode = Some(mode);
-                self.state = MenuState::RoleSelection;
+                if mode == GameMode::Coop || mode == GameMode::Alternating {
+                    // Co-op and alternating: both players are Pac-Men, skip role selection
+                    self.player1_role = Some(PlayerRole::PacMan);
+                    self.player2_role = Some(PlayerRole::PacMan);
+                    self.state = MenuState::MazeSelection;
+                } else if mode == GameMode::TimeTrial || mode == GameMode::Race {
+                    // Time trial and race: always Pac-Man, straight to maze selection
+                    self.player1_role = Some(PlayerRole::PacMan);
+                    self.state = MenuState::MazeSelection;
+                } else {
+                    self.state = MenuState::RoleSelection;
+                }
                 self.selected = 0;
                 MenuAction::SelectGameMode(mode)
             }
@@ -93,17 +541,380 @@ impl Menu {
                     MenuAction::SelectRole(role)
                 }
             }
+            MenuState::MazeSelection if self.selected >= MAZE_NAMES.len() => {
+                // Campaigns set their own levels
+                MenuAction::SelectCampaign(self.selected - MAZE_NAMES.len())
+            }
+            MenuState::MazeSelection if !self.unlocks().has(Unlock::Maze(self.selected)) => {
+                // What it takes is shown under the list
+                MenuAction::None
+            }
             MenuState::MazeSelection => {
-                MenuAction::SelectMaze(self.selected)
+                // Once later levels are unlocked, offer to start at one (not in a time trial, which is a single clear)
+                if self.unlocked_level > 1 && self.game_mode != Some(GameMode::TimeTrial) {
+                    self.maze_index = self.selected;
+                    self.start_level = self.start_level.clamp(1, self.unlocked_level);
+                    self.state = MenuState::LevelSelection;
+                    self.selected = 0;
+                    MenuAction::None
+                } else {
+                    MenuAction::SelectMaze(self.selected)
+                }
+            }
+            MenuState::LevelSelection => MenuAction::SelectLevel(self.maze_index, self.start_level),
+            MenuState::OnlineSelection => {
+                if self.selected == 0 {
+                    // Host: the main loop starts listening and fills in the lobby
+                    self.lobby.host_role = PlayerRole::PacMan;
+                    self.lobby.maze_index = 0;
+                    self.lobby.input_delay = DEFAULT_INPUT_DELAY;
+                    self.lobby.peer_connected = false;
+                    self.lobby.peer_name = None;
+                    self.lobby.client_ready = false;
+                    self.state = MenuState::HostLobby;
+                    self.selected = 0;
+                    MenuAction::HostOnline
+                } else {
+                    self.state = MenuState::JoinAddress;
+                    self.selected = 0;
+                    MenuAction::None
+                }
+            }
+            MenuState::HostLobby => match self.selected {
+                0 => {
+                    self.lobby.host_role = other_role(self.lobby.host_role);
+                    MenuAction::LobbyChanged
+                }
+                1 => {
+                    // The host only offers mazes they have unlocked (the first always is)
+                    let unlocks = self.unlocks();
+                    self.lobby.maze_index = (1..=MAZE_NAMES.len())
+                        .map(|step| (self.lobby.maze_index + step) % MAZE_NAMES.len())
+                        .find(|&index| unlocks.has(Unlock::Maze(index)))
+                        .unwrap_or(0);
+                    MenuAction::LobbyChanged
+                }
+                2 => {
+                    self.lobby.input_delay = (self.lobby.input_delay + 1) % (MAX_INPUT_DELAY + 1);
+                    MenuAction::LobbyChanged
+                }
+                _ if self.lobby.peer_connected && self.lobby.client_ready => MenuAction::StartOnline,
+                _ => MenuAction::None,
+            },
+            MenuState::JoinAddress => MenuAction::JoinOnline,
+            MenuState::ClientLobby => MenuAction::ToggleReady,
+            MenuState::Connecting => MenuAction::None,
+            MenuState::Settings => {
+                self.state = match self.selected {
+                    0 => MenuState::VideoSettings,
+                    1 => MenuState::AudioSettings,
+                    2 => MenuState::ControlSettings,
+                    3 => MenuState::GameplaySettings,
+                    4 => MenuState::PlayerSettings,
+                    5 => {
+                        // Skin files added since startup show up
+                        self.skins = load_skins();
+                        MenuState::Skins
+                    }
+                    6 => MenuState::Unlocks,
+                    _ => {
+                        self.back();
+                        return MenuAction::None;
+                    }
+                };
+                self.selected = 0;
+                MenuAction::None
+            }
+            MenuState::VideoSettings => match self.selected {
+                0 => {
+                    self.settings.video.fullscreen = !self.settings.video.fullscreen;
+                    MenuAction::VideoChanged
+                }
+                1 => {
+                    self.settings.video.next_display(self.display_count);
+                    MenuAction::VideoChanged
+                }
+                2 => {
+                    self.settings.video.next_scale();
+                    MenuAction::VideoChanged
+                }
+                3 => {
+                    self.settings.video.vsync = !self.settings.video.vsync;
+                    MenuAction::VideoChanged
+                }
+                4 => {
+                    self.settings.video.next_fps_cap();
+                    MenuAction::VideoChanged
+                }
+                5 => {
+                    self.settings.video.next_render_scale();
+                    MenuAction::VideoChanged
+                }
+                6 => {
+                    self.settings.video.photosensitive = !self.settings.video.photosensitive;
+                    MenuAction::VideoChanged
+                }
+                _ => {
+                    self.back();
+                    MenuAction::None
+                }
+            },
+            MenuState::AudioSettings => match self.selected {
+                // Enter steps the volume up, wrapping to silent
+                0 => {
+                    self.settings.audio.music_volume = (self.settings.audio.music_volume + 1) % (MAX_VOLUME + 1);
+                    MenuAction::SettingsChanged
+                }
+                1 => {
+                    self.settings.audio.effects_volume = (self.settings.audio.effects_volume + 1) % (MAX_VOLUME + 1);
+                    MenuAction::SettingsChanged
+                }
+                2 => {
+                    self.settings.audio.proximity_tone = !self.settings.audio.proximity_tone;
+                    MenuAction::SettingsChanged
+                }
+                3 => {
+                    self.settings.audio.announcements = !self.settings.audio.announcements;
+                    MenuAction::SettingsChanged
+                }
+                4 => {
+                    self.settings.audio.clip_sound = !self.settings.audio.clip_sound;
+                    MenuAction::SettingsChanged
+                }
+                5 => {
+                    // The main loop hands the music to the jukebox (it sees the state change)
+                    self.jukebox = jukebox::entries();
+                    self.jukebox_playing = None;
+                    self.state = MenuState::Jukebox;
+                    self.selected = 0;
+                    MenuAction::None
+                }
+                _ => {
+                    self.back();
+                    MenuAction::None
+                }
+            },
+            MenuState::Jukebox => match self.jukebox.get(self.selected).map(|entry| entry.sound) {
+                // Enter on the track playing stops it
+                Some(Sound::Music(context)) if self.jukebox_playing == Some(context) => {
+                    self.jukebox_playing = None;
+                    MenuAction::StopSounds
+                }
+                Some(sound) => {
+                    if let Sound::Music(context) = sound {
+                        self.jukebox_playing = Some(context);
+                    }
+                    MenuAction::PlaySound(sound)
+                }
+                None if self.selected == self.jukebox.len() => {
+                    self.jukebox_playing = None;
+                    MenuAction::StopSounds
+                }
+                None => {
+                    self.back();
+                    MenuAction::None
+                }
+            },
+            MenuState::ControlSettings if self.selected < BINDING_NAMES.len() => {
+                // The next key pressed is bound (see `bind_key`)
+                self.capturing = true;
+                MenuAction::None
+            }
+            MenuState::ControlSettings if self.selected == BINDING_NAMES.len() => {
+                self.settings.controls.rumble = (self.settings.controls.rumble + 1) % (MAX_RUMBLE + 1);
+                MenuAction::SettingsChanged
+            }
+            MenuState::ControlSettings if self.selected == BINDING_NAMES.len() + 1 => {
+                self.settings.controls.touch = !self.settings.controls.touch;
+                MenuAction::SettingsChanged
+            }
+            MenuState::ControlSettings if self.selected == BINDING_NAMES.len() + 2 => {
+                self.settings.controls.one_button = !self.settings.controls.one_button;
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 0 => {
+                self.settings.gameplay.pause_on_focus_loss = !self.settings.gameplay.pause_on_focus_loss;
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 1 => {
+                let gameplay = &mut self.settings.gameplay;
+                gameplay.cornering_window = (gameplay.cornering_window + 1) % (MAX_CORNERING_WINDOW + 1);
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 2 => {
+                self.settings.gameplay.arcade_targeting = !self.settings.gameplay.arcade_targeting;
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 3 => {
+                self.settings.gameplay.ghost_preset = self.settings.gameplay.ghost_preset.next();
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 4 => {
+                self.settings.gameplay.pacman_style = self.settings.gameplay.pacman_style.next();
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 5 => self.cycle_tick_rate(1),
+            MenuState::GameplaySettings if self.selected == 6 => {
+                self.settings.gameplay.ice_maze = !self.settings.gameplay.ice_maze;
+                MenuAction::SettingsChanged
+            }
+            MenuState::GameplaySettings if self.selected == 7 => {
+                self.settings.gameplay.telemetry = !self.settings.gameplay.telemetry;
+                MenuAction::SettingsChanged
+            }
+            MenuState::PlayerSettings if self.selected < 2 => {
+                // Names are typed in place; Enter moves on to the next entry
+                self.selected += 1;
+                MenuAction::None
+            }
+            MenuState::Skins if self.selected < 2 => self.cycle_skin(self.selected, 1),
+            MenuState::Skins => {
+                self.back();
+                MenuAction::None
+            }
+            MenuState::Unlocks => match unlocks::listed().get(self.selected) {
+                Some(&Unlock::Palette(palette)) if self.unlocks().has(Unlock::Palette(palette)) => {
+                    // Enter on the colors kept goes back to colors by level
+                    let video = &mut self.settings.video;
+                    video.maze_colors = if video.maze_colors == Some(palette) { None } else { Some(palette) };
+                    MenuAction::SettingsChanged
+                }
+                Some(_) => MenuAction::None,
+                None => {
+                    self.back();
+                    MenuAction::None
+                }
+            },
+            MenuState::PlayerSettings if self.selected == 2 => MenuAction::ExportStats,
+            MenuState::PlayerSettings if self.selected == 3 => MenuAction::ExportProfile,
+            MenuState::PlayerSettings if self.selected == 4 => MenuAction::ImportProfile,
+            MenuState::ImportConflict => match self.selected {
+                0 => MenuAction::ResolveImport(ImportMode::Merge),
+                1 => MenuAction::ResolveImport(ImportMode::Replace),
+                _ => {
+                    self.back();
+                    MenuAction::None
+                }
+            },
+            MenuState::ControlSettings | MenuState::GameplaySettings | MenuState::PlayerSettings | MenuState::Help => {
+                self.back();
+                MenuAction::None
             }
         }
     }
+
+    /// Entries of the maze select screen: the mazes, then the campaigns
+    ///
+    /// Campaigns are offered in the modes that play a single run with shared
+    /// lives (not in a time trial, which is one maze, or in alternating turns).
+    fn maze_options(&self) -> Vec<String> {
+        let mut options: Vec<String> = MAZE_NAMES.iter().map(|name| name.to_string()).collect();
+        if matches!(self.game_mode, Some(GameMode::SinglePlayer) | Some(GameMode::Multiplayer) | Some(GameMode::Coop)) {
+            options.extend(self.campaign_names.iter().map(|name| format!("Campaign: {}", name)));
+        }
+        options
+    }
+
+    /// Returns to the first menu screen, clearing all selections
+    /// 
+    /// The typed address, recording, practice, the settings, the ratings, the
+    /// run history (and with it the unlocks), the skins, the level select
+    /// choice and the campaigns are kept.
+    pub fn reset(&mut self) {
+        let join_address = std::mem::take(&mut self.join_address);
+        let (recording, practice, settings, display_count) = (self.recording, self.practice, self.settings, self.display_count);
+        let (unlocked_level, start_level) = (self.unlocked_level, self.start_level);
+        let campaign_names = std::mem::take(&mut self.campaign_names);
+        let ratings = std::mem::take(&mut self.ratings);
+        let history = std::mem::take(&mut self.history);
+        let unlock_notice = std::mem::take(&mut self.unlock_notice);
+        let skins = std::mem::take(&mut self.skins);
+        let skin_choices = std::mem::take(&mut self.skin_choices);
+        *self = Menu::new();
+        self.skins = skins;
+        self.skin_choices = skin_choices;
+        self.ratings = ratings;
+        self.history = history;
+        self.unlock_notice = unlock_notice;
+        self.campaign_names = campaign_names;
+        self.unlocked_level = unlocked_level;
+        self.start_level = start_level;
+        self.join_address = join_address;
+        self.recording = recording;
+        self.practice = practice;
+        self.settings = settings;
+        self.display_count = display_count;
+    }
+    
+    /// Selects the entry under the mouse pointer, if any
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        if self.capturing {
+            return;
+        }
+        if let Some(entry) = ui::hit_test(&self.hit_areas, x, y) {
+            self.selected = entry;
+        }
+    }
+
+    /// Chooses the entry under the mouse pointer like Enter would
+    ///
+    /// Clicking a slider's bar sets it to the value under the pointer instead.
+    pub fn mouse_clicked(&mut self, x: i32, y: i32) -> MenuAction {
+        if self.capturing {
+            return MenuAction::None;
+        }
+        let Some(entry) = ui::hit_test(&self.hit_areas, x, y) else { return MenuAction::None };
+        self.selected = entry;
+        if let Some(slider) = self.sliders.get(entry).filter(|slider| slider.bar.contains_point((x, y))) {
+            let volume = (slider.value_at(x) * MAX_VOLUME as f32).round() as u8;
+            if entry == 0 {
+                self.settings.audio.music_volume = volume;
+            } else {
+                self.settings.audio.effects_volume = volume;
+            }
+            return MenuAction::SettingsChanged;
+        }
+        let state = self.state;
+        let action = self.select();
+        if self.state != state {
+            // The areas belong to the page that was left
+            self.hit_areas.clear();
+            self.sliders.clear();
+        }
+        action
+    }
+
+    /// Mouse wheel over the menu: changes the selected slider or level like Left / Right
+    pub fn mouse_wheel(&mut self, amount: i32) -> MenuAction {
+        self.process_input(amount.signum(), 0)
+    }
+
+    /// Binds a key to the action selected on the controls page
+    /// 
+    /// Only does something after Enter was pressed on a binding (`capturing`).
+    pub fn bind_key(&mut self, scancode: Scancode) -> MenuAction {
+        if !self.capturing {
+            return MenuAction::None;
+        }
+        self.capturing = false;
+        self.settings.controls.bind(self.selected, scancode);
+        MenuAction::SettingsChanged
+    }
     
     pub fn back(&mut self) {
+        if self.capturing {
+            // Cancel binding a key
+            self.capturing = false;
+            return;
+        }
         match self.state {
             MenuState::GameMode => {
                 // Can't go back from game mode
             }
+            MenuState::ContinueSave => {
+                self.state = MenuState::GameMode;
+                self.selected = 0;
+            }
             MenuState::RoleSelection => {
                 if self.player1_role.is_some() && self.game_mode == Some(GameMode::Multiplayer) {
                     // Go back to player 1 role selection
@@ -117,9 +928,63 @@ impl Menu {
                     self.selected = 0;
                 }
             }
+            MenuState::JoinAddress if !self.join_address.is_empty() => {
+                // Backspace deletes typed characters before leaving the screen
+                self.join_address.pop();
+            }
+            MenuState::HostLobby | MenuState::JoinAddress | MenuState::ClientLobby => {
+                // Leaving a lobby drops the connection (the main loop sees the state change)
+                self.state = MenuState::OnlineSelection;
+                self.selected = 0;
+            }
+            MenuState::OnlineSelection | MenuState::Connecting => {
+                // Leave online play entirely
+                self.reset();
+            }
+            MenuState::Settings => {
+                self.state = MenuState::GameMode;
+                self.selected = 7;
+            }
+            MenuState::Help => {
+                self.state = MenuState::GameMode;
+                self.selected = 8;
+            }
+            MenuState::VideoSettings | MenuState::AudioSettings | MenuState::ControlSettings |
+            MenuState::GameplaySettings | MenuState::PlayerSettings | MenuState::Skins | MenuState::Unlocks => {
+                self.selected = match self.state {
+                    MenuState::VideoSettings => 0,
+                    MenuState::AudioSettings => 1,
+                    MenuState::ControlSettings => 2,
+                    MenuState::GameplaySettings => 3,
+                    MenuState::PlayerSettings => 4,
+                    MenuState::Skins => 5,
+                    _ => 6,
+                };
+                self.state = MenuState::Settings;
+                self.notice.clear();
+            }
+            MenuState::ImportConflict => {
+                self.state = MenuState::PlayerSettings;
+                self.selected = 4;
+            }
+            MenuState::Jukebox => {
+                self.state = MenuState::AudioSettings;
+                self.selected = 5;
+            }
+            MenuState::LevelSelection => {
+                self.state = MenuState::MazeSelection;
+                self.selected = self.maze_index;
+            }
             MenuState::MazeSelection => {
                 // Go back to role selection
-                if self.game_mode == Some(GameMode::Multiplayer) && self.player2_role.is_some() {
+                if matches!(self.game_mode, Some(GameMode::Coop | GameMode::Alternating | GameMode::TimeTrial | GameMode::Race)) {
+                    // Co-op, alternating, time trial and race have no role selection, go back to game mode
+                    self.state = MenuState::GameMode;
+                    self.game_mode = None;
+                    self.player1_role = None;
+                    self.player2_role = None;
+                    self.selected = 0;
+                } else if self.game_mode == Some(GameMode::Multiplayer) && self.player2_role.is_some() {
                     self.player2_role = None;
                     self.state = MenuState::RoleSelection;
                     self.selected = 0;
@@ -132,27 +997,42 @@ impl Menu {
         }
     }
 
-    pub fn draw(&self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) -> Result<(), String> {
-        canvas.set_draw_color(Color::RGB(0, 0, 0));
-        canvas.clear();
-
+    /// Draws the current page over whatever is on the canvas (the caller clears
+    /// it or draws the attract demo first, then presents)
+    pub fn draw(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>) -> Result<(), String> {
         let (ww, wh) = canvas.window().size();
         let center_x = ww as i32 / 2;
         let start_y = wh as i32 / 2 - 60;
+        // Each selectable entry, in selection order, for the mouse and announcements
+        let mut selectable = Selectable::default();
+        let mut sliders = Vec::new();
+
+        self.preview_frame = self.preview_frame.wrapping_add(1);
 
         // Title
-        self.draw_text_simple(canvas, "PAC-MAN", center_x, start_y - 40, 3, Color::RGB(255, 255, 0))?;
+        ui::title(canvas, "PAC-MAN", center_x, start_y - 40);
+        
+        // Input recording armed
+        if self.recording {
+            draw_text(canvas, "REC", 20, 20, 2, Color::RGB(255, 0, 0), Align::Left);
+        }
+
+        // Practice mode armed
+        if self.practice {
+            draw_text(canvas, "PRACTICE", ww as i32 - 20, 20, 2, Color::RGB(255, 255, 0), Align::Right);
+        }
         
         match self.state {
+            MenuState::ContinueSave => {
+                ui::heading(canvas, "Saved Game Found", center_x, start_y - 60);
+                selectable.list(canvas, &["Continue", "New Game"], self.selected, center_x, start_y);
+            }
             MenuState::GameMode => {
-                let options = ["Single Player", "Multiplayer"];
-                for (i, option) in options.iter().enumerate() {
-                    let color = if i == self.selected {
-                        Color::RGB(255, 255, 0)
-                    } else {
-                        Color::RGB(255, 255, 255)
-                    };
-                    self.draw_text_simple(canvas, option, center_x, start_y + (i as i32 * 40), 2, color)?;
+                // The longest list: a little higher, with the hints below moved down
+                let options = ["Single Player", "Multiplayer", "Co-op", "Alternating", "Time Trial", "Race", "Online", "Settings", "How to Play"];
+                selectable.list(canvas, &options, self.selected, center_x, start_y - 10);
+                if !self.unlock_notice.is_empty() {
+                    ui::label(canvas, &self.unlock_notice, center_x, start_y - 50, Color::RGB(0, 255, 0), Align::Center);
                 }
             }
             MenuState::RoleSelection => {
@@ -162,99 +1042,361 @@ impl Menu {
                 } else {
                     "Choose Role".to_string()
                 };
-                self.draw_text_simple(canvas, &title, center_x, start_y - 60, 2, Color::RGB(255, 255, 255))?;
+                ui::heading(canvas, &title, center_x, start_y - 60);
+                selectable.list(canvas, &["Pac-Man", "Ghost"], self.selected, center_x, start_y);
+            }
+            MenuState::MazeSelection => {
+                let options = self.maze_options();
+                let options: Vec<&str> = options.iter().map(String::as_str).collect();
+                // Campaigns are never locked
+                let unlocks = self.unlocks();
+                let locked: Vec<bool> = (0..options.len()).map(|index| !unlocks.has(Unlock::Maze(index))).collect();
+                let areas = ui::list_locked(canvas, &options, &locked, self.selected, center_x, start_y, ui::LIST_SPACING);
+                for (area, option) in areas.into_iter().zip(options) {
+                    selectable.push(area, option.to_string());
+                }
+                if let Some(missing) = unlocks.missing(Unlock::Maze(self.selected)) {
+                    ui::hint(canvas, &format!("Locked: {}", missing), center_x, start_y + 290);
+                }
+            }
+            MenuState::LevelSelection => {
+                ui::heading(canvas, "Start at Level", center_x, start_y - 60);
+                let level = format!("< {} >", self.start_level);
+                selectable.list(canvas, &[level.as_str()], self.selected, center_x, start_y);
+                ui::hint(canvas, &format!("Levels 1-{} unlocked", self.unlocked_level), center_x, start_y + ui::LIST_SPACING);
+            }
+            MenuState::OnlineSelection => {
+                ui::heading(canvas, "Online", center_x, start_y - 60);
+                selectable.list(canvas, &["Host Game", "Join Game"], self.selected, center_x, start_y);
+            }
+            MenuState::HostLobby => {
+                let info = format!("Port {}   Code {}", self.lobby.port, self.lobby.join_code);
+                ui::label(canvas, &info, center_x, start_y - 60, ui::TEXT_COLOR, Align::Center);
                 
-                let options = ["Pac-Man", "Ghost"];
-                for (i, option) in options.iter().enumerate() {
-                    let color = if i == self.selected {
-                        Color::RGB(255, 255, 0)
-                    } else {
-                        Color::RGB(255, 255, 255)
+                let role = format!("You Play: {}", role_name(self.lobby.host_role));
+                let maze = MAZE_NAMES[self.lobby.maze_index % MAZE_NAMES.len()];
+                let delay = format!("Input Delay: < {} >", input_delay_name(self.lobby.input_delay));
+                selectable.list(canvas, &[role.as_str(), maze, delay.as_str(), "Start Game"], self.selected, center_x, start_y);
+                
+                let (status, color) = if !self.lobby.peer_connected {
+                    ("Waiting for a player to join", ui::HINT_COLOR)
+                } else if !self.lobby.client_ready {
+                    ("Player joined - waiting for them to be ready", ui::TEXT_COLOR)
+                } else {
+                    ("Player ready - press Enter on Start Game", Color::RGB(0, 255, 0))
+                };
+                ui::label(canvas, status, center_x, start_y + 170, color, Align::Center);
+                ui::hint(canvas, &self.versus_line(), center_x, start_y + 200);
+            }
+            MenuState::JoinAddress => {
+                ui::heading(canvas, "Host Address or Join Code", center_x, start_y - 60);
+                ui::text_field(canvas, &self.join_address, center_x, start_y);
+            }
+            MenuState::ClientLobby => {
+                let role = format!("You Play: {}", role_name(other_role(self.lobby.host_role)));
+                ui::heading(canvas, &role, center_x, start_y - 60);
+                let maze = MAZE_NAMES[self.lobby.maze_index % MAZE_NAMES.len()];
+                ui::label(canvas, maze, center_x, start_y - 30, ui::TEXT_COLOR, Align::Center);
+                let delay = format!("Input Delay: {}", input_delay_name(self.lobby.input_delay));
+                ui::hint(canvas, &delay, center_x, start_y + ui::LIST_SPACING);
+                
+                let ready = if self.lobby.client_ready { "Ready" } else { "Not Ready" };
+                selectable.list(canvas, &[ready], self.selected, center_x, start_y);
+                
+                let status = if self.lobby.client_ready {
+                    "Waiting for the host to start"
+                } else {
+                    "Press Enter when ready"
+                };
+                ui::hint(canvas, status, center_x, start_y + 130);
+                ui::hint(canvas, &self.versus_line(), center_x, start_y + 160);
+            }
+            MenuState::Connecting => {
+                // Error messages can be long: wrap them to the window
+                let lines = wrap_text(&self.status, ww as i32 - 40, 1);
+                draw_lines(canvas, &lines, center_x, start_y, 1, ui::TEXT_COLOR, Align::Center);
+            }
+            MenuState::Settings => {
+                ui::heading(canvas, "Settings", center_x, start_y - 60);
+                let entries = ["Video", "Audio", "Controls", "Gameplay", "Players", "Skins", "Unlocks", "Back"];
+                selectable.list(canvas, &entries, self.selected, center_x, start_y);
+            }
+            MenuState::VideoSettings => {
+                let video = &self.settings.video;
+                let (w, h) = video.window_size();
+                let fullscreen = format!("Fullscreen: {}", if video.fullscreen { "On" } else { "Off" });
+                let display = format!("Display: {}", video.display_index + 1);
+                let size = format!("Window: {}x{}", w, h);
+                let vsync = format!("VSync: {}", if video.vsync { "On" } else { "Off" });
+                let fps_cap = match video.fps_cap() {
+                    0 => "Frame Cap: None".to_string(),
+                    cap => format!("Frame Cap: {}", cap),
+                };
+                let render_scale = format!("Render Scale: {}%", video.render_scale());
+                let photosensitive = format!("Photosensitive Mode: {}", if video.photosensitive { "On" } else { "Off" });
+                let options = [fullscreen.as_str(), &display, &size, &vsync, &fps_cap, &render_scale, &photosensitive, "Back"];
+                selectable.list(canvas, &options, self.selected, center_x, start_y);
+                ui::hint(canvas, "F11: Toggle fullscreen", center_x, start_y + 330);
+            }
+            MenuState::AudioSettings => {
+                ui::heading(canvas, "Audio", center_x, start_y - 60);
+                let audio = &self.settings.audio;
+                let max = MAX_VOLUME as f32;
+                let music_y = start_y;
+                let effects_y = start_y + ui::LIST_SPACING;
+                sliders.push(ui::slider(canvas, "Music", audio.music_volume as f32 / max, center_x, music_y, self.selected == 0));
+                sliders.push(ui::slider(canvas, "Effects", audio.effects_volume as f32 / max, center_x, effects_y, self.selected == 1));
+                selectable.push(sliders[0].row, format!("Music {} of {}", audio.music_volume, MAX_VOLUME));
+                selectable.push(sliders[1].row, format!("Effects {} of {}", audio.effects_volume, MAX_VOLUME));
+                let proximity = format!("Ghost Proximity Tone: {}", if audio.proximity_tone { "On" } else { "Off" });
+                let announcements = format!("Announcements: {}", if audio.announcements { "On" } else { "Off" });
+                let clip_sound = format!("Clip Sound: {}", if audio.clip_sound { "On" } else { "Off" });
+                let entries = [proximity.as_str(), announcements.as_str(), clip_sound.as_str(), "Jukebox", "Back"];
+                selectable.list(canvas, &entries, self.selected.wrapping_sub(2), center_x, start_y + 2 * ui::LIST_SPACING);
+                ui::hint(canvas, "Left / Right: Change volume", center_x, start_y + 290);
+            }
+            MenuState::Jukebox => {
+                ui::heading(canvas, "Jukebox", center_x, start_y - 60);
+                // Tighter spacing than a list so every track and effect fits
+                let spacing = 20;
+                let mut entries: Vec<String> = self.jukebox.iter()
+                    .map(|entry| match entry.sound {
+                        Sound::Music(context) if self.jukebox_playing == Some(context) => format!("{} (Playing)", entry.name),
+                        _ => entry.name.clone(),
+                    })
+                    .collect();
+                entries.extend(["Stop".to_string(), "Back".to_string()]);
+                let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+                let areas = ui::list_spaced(canvas, &entries, self.selected, center_x, start_y, spacing);
+                for (area, entry) in areas.into_iter().zip(entries) {
+                    selectable.push(area, entry.to_string());
+                }
+                if let Some(entry) = self.jukebox.get(self.selected) {
+                    let details_y = start_y + (self.jukebox.len() + 2) as i32 * spacing;
+                    draw_lines(canvas, &entry.details, center_x, details_y, 1, ui::HINT_COLOR, Align::Center);
+                }
+            }
+            MenuState::ControlSettings => {
+                // Tighter spacing than a list so all bindings fit
+                let spacing = 25;
+                for (i, name) in BINDING_NAMES.iter().enumerate() {
+                    let key = self.settings.controls.binding(i).map(|scancode| scancode.name()).unwrap_or("");
+                    let selected = self.selected == i;
+                    let y = start_y + i as i32 * spacing;
+                    let area = ui::key_field(canvas, name, key, selected && self.capturing, center_x, y, selected);
+                    selectable.push(area, format!("{}: {}", name, key));
+                }
+                let rumble_y = start_y + BINDING_NAMES.len() as i32 * spacing;
+                let rumble = match self.settings.controls.rumble {
+                    0 => "Off".to_string(),
+                    step => format!("{}%", step as u32 * 100 / MAX_RUMBLE as u32),
+                };
+                let rumble_selected = self.selected == BINDING_NAMES.len();
+                let area = ui::key_field(canvas, "Rumble", &rumble, false, center_x, rumble_y, rumble_selected);
+                selectable.push(area, format!("Rumble: {}", rumble));
+                let touch_y = rumble_y + spacing;
+                let touch = if self.settings.controls.touch { "On" } else { "Off" };
+                let touch_selected = self.selected == BINDING_NAMES.len() + 1;
+                let area = ui::key_field(canvas, "Touch Controls", touch, false, center_x, touch_y, touch_selected);
+                selectable.push(area, format!("Touch Controls: {}", touch));
+                let one_button_y = touch_y + spacing;
+                let one_button = if self.settings.controls.one_button { "On" } else { "Off" };
+                let one_button_selected = self.selected == BINDING_NAMES.len() + 2;
+                let area = ui::key_field(canvas, "One Button", one_button, false, center_x, one_button_y, one_button_selected);
+                selectable.push(area, format!("One Button: {}", one_button));
+                let back_y = one_button_y + spacing;
+                selectable.list(canvas, &["Back"], self.selected.wrapping_sub(BINDING_NAMES.len() + 3), center_x, back_y);
+            }
+            MenuState::GameplaySettings => {
+                ui::heading(canvas, "Gameplay", center_x, start_y - 60);
+                let focus = format!("Pause on Focus Loss: {}", if self.settings.gameplay.pause_on_focus_loss { "On" } else { "Off" });
+                let cornering = match self.settings.gameplay.cornering_window {
+                    0 => "Cornering: Off".to_string(),
+                    1 => "Cornering: 1 tick".to_string(),
+                    ticks => format!("Cornering: {} ticks", ticks),
+                };
+                let targeting = format!("Targeting: {}", if self.settings.gameplay.arcade_targeting { "Arcade Accurate" } else { "Fixed" });
+                let ghosts = format!("Ghosts: {}", self.settings.gameplay.ghost_preset.name());
+                let pacman = format!("Pac-Man AI: {}", self.settings.gameplay.pacman_style.name());
+                let tick_rate = format!("Tick Rate: {} Hz", self.settings.gameplay.tick_rate);
+                let ice = format!("Ice Maze: {}", if self.settings.gameplay.ice_maze { "On" } else { "Off" });
+                let telemetry = format!("Telemetry: {}", if self.settings.gameplay.telemetry { "On" } else { "Off" });
+                let entries = [focus.as_str(), &cornering, &targeting, &ghosts, &pacman, &tick_rate, &ice, &telemetry, "Back"];
+                selectable.list(canvas, &entries, self.selected, center_x, start_y);
+                let hint = match self.selected {
+                    5 => "Ticks per second: the game plays at the same speed",
+                    6 => "Pac-Man slides on and only turns at junctions",
+                    _ => "Left / Right: Change cornering or tick rate",
+                };
+                ui::hint(canvas, hint, center_x, start_y + 330);
+            }
+            MenuState::PlayerSettings => {
+                ui::heading(canvas, "Players", center_x, start_y - 60);
+                for player in 0..2 {
+                    let selected = self.selected == player;
+                    // A caret on the name being typed
+                    let name = if selected { format!("{}_", self.ratings.names[player]) } else { self.ratings.names[player].clone() };
+                    let label = format!("Player {}", player + 1);
+                    let y = start_y + player as i32 * ui::LIST_SPACING;
+                    let area = ui::key_field(canvas, &label, &name, false, center_x, y, selected);
+                    selectable.push(area, format!("{}: {}", label, self.ratings.names[player]));
+                }
+                let entries = ["Export Stats", "Export Profile", "Import Profile", "Back"];
+                selectable.list(canvas, &entries, self.selected.wrapping_sub(2), center_x, start_y + 2 * ui::LIST_SPACING);
+                for player in 0..2 {
+                    let name = self.ratings.name(player);
+                    let rating = self.ratings.get(name);
+                    let lifetime = self.history.lifetime(name);
+                    let mut record = format!(
+                        "{}: rating {}, {} won, {} lost, {} runs, best {}",
+                        name, rating.rating, rating.wins, rating.losses, lifetime.runs, lifetime.best_score
+                    );
+                    if lifetime.ng_plus_best_score > 0 {
+                        record.push_str(&format!(", NG+ best {}", lifetime.ng_plus_best_score));
+                    }
+                    let y = start_y + 230 + player as i32 * 28;
+                    ui::hint(canvas, &record, center_x, y);
+                    // Where the points came from, once there are any
+                    if lifetime.points.iter().any(|&points| points > 0) {
+                        let sources: Vec<String> = ScoreSource::ALL.iter()
+                            .map(|&source| format!("{} {}", source.label(), lifetime.points[source.index()]))
+                            .collect();
+                        ui::hint(canvas, &sources.join("   "), center_x, y + 14);
+                    }
+                }
+                ui::hint(canvas, "Type a name   Player 1's is used online", center_x, start_y + 294);
+                if !self.notice.is_empty() {
+                    ui::hint(canvas, &self.notice, center_x, start_y + 312);
+                }
+            }
+            MenuState::Skins => {
+                ui::heading(canvas, "Skins", center_x, start_y - 60);
+                let worn: Vec<String> = (0..2)
+                    .map(|player| {
+                        let skin = self.skin_of(player).map(|skin| skin.name.as_str()).unwrap_or("Classic");
+                        format!("{}: < {} >", self.ratings.name(player), skin)
+                    })
+                    .collect();
+                selectable.list(canvas, &[worn[0].as_str(), worn[1].as_str(), "Back"], self.selected, center_x, start_y);
+
+                // The selected player's skin, live: Pac-Man chomping and the four ghosts
+                let player = self.selected.min(1);
+                if let Some(skin) = self.skin_of(player) {
+                    draw_skin_preview(canvas, skin, player, self.preview_frame, center_x, start_y + 3 * ui::LIST_SPACING)?;
+                    let source = match skin.file {
+                        Some(ref path) => format!("From {}", path.display()),
+                        None => "Built in".to_string(),
                     };
-                    self.draw_text_simple(canvas, option, center_x, start_y + (i as i32 * 40), 2, color)?;
+                    ui::hint(canvas, &source, center_x, start_y + 3 * ui::LIST_SPACING + 60);
                 }
+                let locked = self.skins.iter().filter(|skin| !self.skin_unlocked(player, skin)).count();
+                if locked > 0 {
+                    let more = format!("{} more to earn, see Unlocks", locked);
+                    ui::hint(canvas, &more, center_x, start_y + 3 * ui::LIST_SPACING + 80);
+                }
+                ui::hint(canvas, "Left / Right: Change skin", center_x, start_y + 290);
             }
-            MenuState::MazeSelection => {
-                let options = ["Maze 1: Classic", "Maze 2: Simple"];
-                for (i, option) in options.iter().enumerate() {
-                    let color = if i == self.selected {
-                        Color::RGB(255, 255, 0)
-                    } else {
-                        Color::RGB(255, 255, 255)
+            MenuState::Unlocks => {
+                ui::heading(canvas, "Unlocks", center_x, start_y - 60);
+                // Tighter spacing than a list so everything fits
+                let spacing = 25;
+                let unlocks = self.unlocks();
+                let listed = unlocks::listed();
+                let mut entries: Vec<String> = listed.iter()
+                    .map(|&unlock| match unlock {
+                        Unlock::Palette(palette) if self.settings.video.maze_colors == Some(palette) => format!("{} (On)", unlock.name()),
+                        _ => unlock.name(),
+                    })
+                    .collect();
+                entries.push("Back".to_string());
+                let entries: Vec<&str> = entries.iter().map(String::as_str).collect();
+                let locked: Vec<bool> = listed.iter().map(|&unlock| !unlocks.has(unlock)).collect();
+                let areas = ui::list_locked(canvas, &entries, &locked, self.selected, center_x, start_y, spacing);
+                for (area, entry) in areas.into_iter().zip(entries) {
+                    selectable.push(area, entry.to_string());
+                }
+
+                let hints_y = start_y + (listed.len() as i32 + 1) * spacing + 15;
+                if let Some(&unlock) = listed.get(self.selected) {
+                    let hint = match (unlocks.missing(unlock), unlock) {
+                        (Some(missing), _) => format!("Locked: {}", missing),
+                        (None, Unlock::Palette(palette)) if self.settings.video.maze_colors == Some(palette) => {
+                            "Enter: Change the colors by level again".to_string()
+                        }
+                        (None, Unlock::Palette(_)) => "Enter: Keep these colors on every level".to_string(),
+                        (None, Unlock::Maze(_)) => "Unlocked: pick it on the maze select screen".to_string(),
+                        (None, Unlock::Skin(_)) => "Unlocked: wear it from the Skins page".to_string(),
                     };
-                    self.draw_text_simple(canvas, option, center_x, start_y + (i as i32 * 40), 2, color)?;
+                    ui::hint(canvas, &hint, center_x, hints_y);
                 }
+                let earned = format!("Earned by {}'s runs", self.ratings.name(0));
+                ui::hint(canvas, &earned, center_x, hints_y + 20);
+            }
+            MenuState::ImportConflict => {
+                ui::heading(canvas, "Import Profile", center_x, start_y - 60);
+                selectable.list(canvas, &["Merge", "Replace", "Cancel"], self.selected, center_x, start_y);
+                let taken = format!("A profile named {} is already here", self.importing);
+                ui::hint(canvas, &taken, center_x, start_y + 150);
+                ui::hint(canvas, "Merge keeps the best of both", center_x, start_y + 170);
+                ui::hint(canvas, "Replace takes everything from the file", center_x, start_y + 190);
+            }
+            MenuState::Help => {
+                ui::heading(canvas, "How to Play", center_x, start_y - 60);
+                let height = draw_help(canvas, &self.settings.controls, center_x, start_y);
+                let back_y = start_y + height + ui::LIST_SPACING / 2;
+                selectable.list(canvas, &["Back"], self.selected, center_x, back_y);
             }
         }
 
-        self.draw_text_simple(canvas, "Arrow Keys: Select", center_x, start_y + 100, 1, Color::RGB(150, 150, 150))?;
         if self.state != MenuState::GameMode {
-            self.draw_text_simple(canvas, "Backspace: Back", center_x, start_y + 120, 1, Color::RGB(150, 150, 150))?;
+            ui::hint(canvas, "Arrow Keys or Mouse: Select", center_x, start_y + 310);
+            ui::hint(canvas, "Backspace: Back", center_x, start_y + 330);
+        } else {
+            ui::hint(canvas, "Arrow Keys or Mouse: Select", center_x, start_y + 332);
         }
-        self.draw_text_simple(canvas, "Enter: Continue", center_x, start_y + 140, 1, Color::RGB(150, 150, 150))?;
+        ui::hint(canvas, "Enter: Continue", center_x, start_y + 350);
 
-        canvas.present();
+        self.hit_areas = selectable.areas;
+        self.labels = selectable.labels;
+        self.sliders = sliders;
         Ok(())
     }
+}
 
-    /// Simple text rendering using a minimal bitmap font
-    /// This is much simpler than the previous implementation - just renders ASCII characters
-    fn draw_text_simple(&self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, 
-                       text: &str, center_x: i32, y: i32, scale: i32, color: Color) -> Result<(), String> {
-        // Use a simple 5x7 bitmap font stored as a string pattern
-        // Each character is 5 bits wide, stored as 7 rows
-        let font_data: std::collections::HashMap<char, [u8; 7]> = [
-            ('A', [0x0E, 0x11, 0x1F, 0x11, 0x11, 0x11, 0x11]),
-            ('B', [0x1E, 0x11, 0x11, 0x1E, 0x11, 0x11, 0x1E]),
-            ('C', [0x0E, 0x11, 0x10, 0x10, 0x10, 0x11, 0x0E]),
-            ('E', [0x1F, 0x10, 0x10, 0x1E, 0x10, 0x10, 0x1F]),
-            ('G', [0x0E, 0x11, 0x10, 0x17, 0x11, 0x11, 0x0E]),
-            ('H', [0x11, 0x11, 0x11, 0x1F, 0x11, 0x11, 0x11]),
-            ('I', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x1F]),
-            ('K', [0x11, 0x12, 0x14, 0x18, 0x14, 0x12, 0x11]),
-            ('L', [0x10, 0x10, 0x10, 0x10, 0x10, 0x10, 0x1F]),
-            ('M', [0x11, 0x1B, 0x15, 0x11, 0x11, 0x11, 0x11]),
-            ('N', [0x11, 0x19, 0x15, 0x13, 0x11, 0x11, 0x11]),
-            ('O', [0x0E, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
-            ('P', [0x1E, 0x11, 0x11, 0x1E, 0x10, 0x10, 0x10]),
-            ('R', [0x1E, 0x11, 0x11, 0x1E, 0x14, 0x12, 0x11]),
-            ('S', [0x0E, 0x11, 0x10, 0x0E, 0x01, 0x11, 0x0E]),
-            ('T', [0x1F, 0x04, 0x04, 0x04, 0x04, 0x04, 0x04]),
-            ('U', [0x11, 0x11, 0x11, 0x11, 0x11, 0x11, 0x0E]),
-            ('W', [0x11, 0x11, 0x11, 0x11, 0x15, 0x1B, 0x11]),
-            ('Y', [0x11, 0x11, 0x0A, 0x04, 0x04, 0x04, 0x04]),
-            ('Z', [0x1F, 0x01, 0x02, 0x04, 0x08, 0x10, 0x1F]),
-            ('1', [0x04, 0x0C, 0x04, 0x04, 0x04, 0x04, 0x0E]),
-            ('2', [0x0E, 0x11, 0x01, 0x06, 0x08, 0x10, 0x1F]),
-            ('-', [0x00, 0x00, 0x00, 0x1F, 0x00, 0x00, 0x00]),
-            (' ', [0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00]),
-            (':', [0x00, 0x04, 0x00, 0x00, 0x00, 0x04, 0x00]),
-        ].iter().cloned().collect();
-
-        let char_width = 5;
-        let spacing = 1;
-        let text_width = text.chars().count() as i32 * (char_width + spacing) * scale;
-        let mut x_pos = center_x - text_width / 2;
-
-        canvas.set_draw_color(color);
-        for ch in text.chars() {
-            let ch_upper = ch.to_uppercase().next().unwrap_or(' ');
-            if let Some(&glyph) = font_data.get(&ch_upper).or_else(|| font_data.get(&' ')) {
-                for (row_idx, &row_data) in glyph.iter().enumerate() {
-                    for col_idx in 0..char_width {
-                        if (row_data >> (4 - col_idx)) & 1 != 0 {
-                            let _ = canvas.fill_rect(Rect::new(
-                                x_pos + (col_idx as i32 * scale),
-                                y + (row_idx as i32 * scale),
-                                scale as u32,
-                                scale as u32,
-                            ));
-                        }
-                    }
-                }
-            }
-            x_pos += (char_width + spacing) * scale;
-        }
-        Ok(())
+/// Draws a skin's Pac-Man and ghosts side by side, animated
+///
+/// # Arguments
+/// * `player` - Player slot whose Pac-Man color is shown
+/// * `frame` - Frames drawn so far, for the mouth, the skirts and the eyes
+/// * `center_x`, `y` - Top center of the row
+fn draw_skin_preview(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    skin: &Skin,
+    player: usize,
+    frame: u32,
+    center_x: i32,
+    y: i32,
+) -> Result<(), String> {
+    // Sprite pixels drawn as squares, with a sprite's width between sprites
+    let pixel = 6;
+    let step = 12 * pixel;
+    let left = center_x - (5 * step - 6 * pixel) / 2;
+    let at = |origin_x: i32| move |x: i32, py: i32, w: i32, h: i32| {
+        Rect::new(origin_x + x * pixel, y + py * pixel, (w * pixel) as u32, (h * pixel) as u32)
+    };
+
+    let pacman_color = skin.pacman_color(player)
+        .map(|(r, g, b)| Color::RGB(r, g, b))
+        .unwrap_or(PLAYER_COLORS[player % PLAYER_COLORS.len()]);
+    draw_pacman(canvas, &at(left), 0, 0, pacman_color, frame, skin)?;
+
+    // The eyes look around, a quarter turn every half second
+    let looks = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let look = looks[(frame / (FPS / 2)) as usize % looks.len()];
+    for (ghost, &usual) in GHOST_COLORS.iter().enumerate() {
+        let color = skin.ghost_color(ghost).map(|(r, g, b)| Color::RGB(r, g, b)).unwrap_or(usual);
+        let view = GhostView { x: 0, y: 0, vulnerable: false, look };
+        draw_ghost(canvas, &at(left + (ghost as i32 + 1) * step), &view, color, frame, skin)?;
     }
+    Ok(())
 }
@@ -0,0 +1,175 @@
+//! macroquad front end
+//!
+//! The game in a macroquad window, for systems where linking SDL2 is painful
+//! (Windows CI runners, minimal Linux installs). macroquad opens the window
+//! with the platform's own OpenGL (or Metal) and needs no native libraries at
+//! build time. Built with:
+//!
+//! ```text
+//! cargo run --release --no-default-features --features macroquad --bin paclike_2600_mq
+//! ```
+//!
+//! Frames are drawn by `MqRenderer`, which implements `Renderer` with the same
+//! primitives as the browser and libretro builds (`soft_render`) and lets the
+//! GPU scale the frame up to the window. The game runs a fixed `FPS` ticks a
+//! second whatever the display's refresh rate. Sound needs the
+//! `cpal-audio` feature as well; without it the game runs silently.
+//!
+//! Controls: arrow keys or WASD to steer, P or Escape to pause, Enter to play
+//! again after a game over. Single-player only: no menu, saves or online play.
+
+use macroquad::prelude::*;
+use crate::audio::{AudioEvent, AudioManager};
+use crate::constants::{FPS, WINDOW_SCALE};
+use crate::events::EventBus;
+use crate::game::Game;
+use crate::game_config::{GameConfig, GameMode, PlayerRole};
+use crate::renderer::Renderer;
+use crate::rng::clock_seed;
+use crate::soft_render::{Framebuffer, FRAME_W, FRAME_H};
+
+/// Most ticks run in one display frame, so a stall doesn't fast-forward the game
+const MAX_TICKS_PER_FRAME: u32 = 4;
+
+/// Window settings for `macroquad::main`
+pub fn window_conf() -> Conf {
+    Conf {
+        window_title: "Pac-Man 2600".to_string(),
+        window_width: FRAME_W * WINDOW_SCALE,
+        window_height: FRAME_H * WINDOW_SCALE,
+        ..Default::default()
+    }
+}
+
+/// Draws game frames into a macroquad window
+///
+/// The frame is drawn into a `soft_render::Framebuffer`, then `present`
+/// uploads it to a texture and draws it as large as the window allows,
+/// keeping the aspect ratio and sharp pixels.
+pub struct MqRenderer {
+    screen: Framebuffer,
+    /// The frame as RGBA bytes, uploaded to `texture`
+    frame: Vec<u8>,
+    texture: Texture2D,
+}
+
+impl MqRenderer {
+    /// Creates the renderer (needs the window, so only inside `macroquad::main`)
+    pub fn new() -> Self {
+        let screen = Framebuffer::new();
+        let mut frame = Vec::with_capacity((FRAME_W * FRAME_H * 4) as usize);
+        screen.write_rgba(&mut frame);
+        let texture = Texture2D::from_rgba8(FRAME_W as u16, FRAME_H as u16, &frame);
+        texture.set_filter(FilterMode::Nearest);
+        Self { screen, frame, texture }
+    }
+}
+
+impl Default for MqRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Renderer for MqRenderer {
+    fn draw_maze(&mut self, game: &Game) -> Result<(), String> {
+        self.screen.draw_maze(game)
+    }
+
+    fn draw_entities(&mut self, game: &Game) -> Result<(), String> {
+        self.screen.draw_entities(game)
+    }
+
+    fn draw_hud(&mut self, game: &Game) -> Result<(), String> {
+        self.screen.draw_hud(game)
+    }
+
+    fn present(&mut self) -> Result<(), String> {
+        self.screen.write_rgba(&mut self.frame);
+        self.texture.update_from_bytes(FRAME_W as u32, FRAME_H as u32, &self.frame);
+        // Whole multiples of the frame when the window is big enough, letterboxed
+        let fit = (screen_width() / FRAME_W as f32).min(screen_height() / FRAME_H as f32);
+        let scale = if fit >= 1.0 { fit.floor() } else { fit };
+        let (w, h) = (FRAME_W as f32 * scale, FRAME_H as f32 * scale);
+        clear_background(BLACK);
+        draw_texture_ex(&self.texture, ((screen_width() - w) / 2.0).floor(), ((screen_height() - h) / 2.0).floor(), WHITE, DrawTextureParams {
+            dest_size: Some(vec2(w, h)),
+            ..Default::default()
+        });
+        Ok(())
+    }
+}
+
+/// Direction held on the keyboard, if any
+fn held_direction() -> Option<(i32, i32)> {
+    if is_key_down(KeyCode::Up) || is_key_down(KeyCode::W) {
+        Some((0, -1))
+    } else if is_key_down(KeyCode::Down) || is_key_down(KeyCode::S) {
+        Some((0, 1))
+    } else if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
+        Some((-1, 0))
+    } else if is_key_down(KeyCode::Right) || is_key_down(KeyCode::D) {
+        Some((1, 0))
+    } else {
+        None
+    }
+}
+
+/// Opens the sound output (cpal)
+#[cfg(feature = "cpal-audio")]
+fn open_audio() -> Option<AudioManager> {
+    AudioManager::new(Box::new(crate::cpal_audio::CpalAudio::new()))
+        .map_err(|e| eprintln!("Audio unavailable: {}", e))
+        .ok()
+}
+
+/// Built without an audio backend: the game runs silently
+#[cfg(not(feature = "cpal-audio"))]
+fn open_audio() -> Option<AudioManager> {
+    None
+}
+
+/// A new single-player game, with its events going to the audio
+fn new_game(seed: u16, audio: Option<&AudioManager>) -> (Game, EventBus) {
+    let game = Game::new_with_seed(GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None), seed);
+    let mut events = EventBus::new();
+    if let Some(audio) = audio {
+        let sounds = audio.sender();
+        let _ = sounds.send(AudioEvent::GameStarted);
+        events.subscribe(Box::new(sounds));
+    }
+    (game, events)
+}
+
+/// Runs the game until the window is closed
+pub async fn run() {
+    let audio = open_audio();
+    let (mut game, mut events) = new_game(clock_seed(), audio.as_ref());
+    let mut renderer = MqRenderer::new();
+    let step = 1.0 / FPS as f32;
+    let mut lag = 0.0;
+    loop {
+        if is_key_pressed(KeyCode::P) || is_key_pressed(KeyCode::Escape) {
+            game.paused = !game.paused;
+        }
+        if !game.alive && is_key_pressed(KeyCode::Enter) {
+            // Seeded from the last game so each one plays differently
+            (game, events) = new_game(game.rng.state(), audio.as_ref());
+            lag = 0.0;
+        }
+
+        lag = (lag + get_frame_time()).min(step * MAX_TICKS_PER_FRAME as f32);
+        while lag >= step {
+            lag -= step;
+            if game.alive && !game.paused {
+                let ticked = game.tick(held_direction(), None);
+                events.publish(&game, &ticked);
+            }
+        }
+
+        if let Err(e) = game.draw(&mut renderer).and_then(|()| renderer.present()) {
+            eprintln!("Draw failed: {}", e);
+        }
+        next_frame().await;
+    }
+}
@@ -0,0 +1,8 @@
+//! Entry point of the macroquad build (no SDL2, see `mq_front`)
+
+use paclike_2600_rs::mq_front::{run, window_conf};
+
+#[macroquad::main(window_conf)]
+async fn main() {
+    run().await;
+}
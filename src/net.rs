@@ -0,0 +1,769 @@
+//! Online multiplayer using deterministic lockstep over TCP
+//!
+//! Both peers run the full simulation. Every tick each side sends its own
+//! input and waits for the other side's input for the same tick, so both
+//! games receive identical inputs in identical order. Together with a shared
+//! `Lfsr` seed (sent by the host in the session setup) the two simulations
+//! stay in sync without ever sending game state. Each input message carries
+//! the sender's `Game::state_hash`, so a desync is caught at the tick where
+//! the games first differ instead of showing up later as odd behaviour.
+//!
+//! Both peers notice a desync at the same tick. They then stop ticking and
+//! swap their game states (in the save format, see `savegame::write_game`)
+//! so each side can dump both for diffing. The host may resync: both games are
+//! replaced with the host's state and the lockstep starts over from there.
+//!
+//! A connection goes through two stages:
+//! 1. `NetLobby` - non-blocking; the peers swap profile names (for the
+//!    ratings, see `rating.rs`), the host picks roles and maze, the client
+//!    marks itself ready, and the host starts the match
+//! 2. `NetSession` - blocking lockstep input exchange during the match
+//!
+//! Input messages also carry timestamps, echoed back by the peer, from which
+//! each side measures the round-trip time and its jitter (see
+//! `NetSession::ping_ms`) for the in-game network overlay.
+//!
+//! Roles map onto the existing Pac-Man-versus-ghost split: one peer plays
+//! Pac-Man and the other plays a ghost, as negotiated in the lobby.
+
+use std::collections::VecDeque;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener, TcpStream, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+use crate::emote::Emote;
+use crate::game_config::PlayerRole;
+use crate::level_table::table_hash;
+use crate::rating::clean_name;
+
+/// Default TCP port used for hosting and joining
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// Number of ticks between reading an input and applying it
+///
+/// Inputs are scheduled this many ticks ahead so the peer's input has time
+/// to arrive before it is needed, hiding network latency.
+pub const DEFAULT_INPUT_DELAY: u32 = 3;
+
+/// Largest input delay the host can choose in the lobby
+pub const MAX_INPUT_DELAY: u32 = 10;
+
+/// How long to wait for the peer before giving up on the connection
+const PEER_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Magic bytes at the start of the handshake (bumped when the protocol changes)
+const HANDSHAKE_MAGIC: &[u8; 4] = b"PAC9";
+
+/// Message tags
+const MSG_SETUP: u8 = b'S';
+const MSG_READY: u8 = b'R';
+const MSG_GO: u8 = b'G';
+const MSG_NAME: u8 = b'N';
+const MSG_INPUT: u8 = b'I';
+const MSG_SNAPSHOT: u8 = b'D';
+const MSG_RESYNC: u8 = b'Y';
+
+/// Size of a setup message: tag, seed (u16), maze, input delay, host role,
+/// level table hash (u64)
+const SETUP_MSG_LEN: usize = 14;
+
+/// Size of a name message before the name: tag, name length
+const NAME_HEADER_LEN: usize = 2;
+
+/// Size of a ready message: tag, ready flag
+const READY_MSG_LEN: usize = 2;
+
+/// Size of one input message: tag, tick (u32), has input, dx, dy, emote (see `Emote::code`),
+/// sender's state hash (u64) when it sent the input, sender's clock (u32 ms),
+/// the last clock reading received from the peer (u32 ms) and how long the
+/// sender held it (u16 ms, `NO_ECHO` before the first one)
+const INPUT_MSG_LEN: usize = 27;
+
+/// Echo hold time meaning "no clock reading received yet"
+const NO_ECHO: u16 = u16::MAX;
+
+/// Weight of a new sample in the smoothed round-trip time (1/8, as in TCP)
+const RTT_SMOOTHING: f32 = 0.125;
+
+/// Weight of a new sample in the smoothed jitter (1/16, as in RTP)
+const JITTER_SMOOTHING: f32 = 0.0625;
+
+/// Size of the start of a snapshot message: tag, length of the state text (u32)
+const SNAPSHOT_HEADER_LEN: usize = 5;
+
+/// Largest game state accepted from the peer, in bytes
+const MAX_SNAPSHOT_LEN: usize = 1 << 20;
+
+/// Alphabet for join codes (Crockford base 32: no I, L, O or U to avoid misreading)
+const JOIN_CODE_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Which side of the connection this client is
+#[derive(Clone, Copy, PartialEq)]
+pub enum NetRole {
+    /// Accepted the connection, chooses seed, roles and maze
+    Host,
+    /// Connected to the host
+    Client,
+}
+
+/// Settings the host sends to the client so both simulations start identical
+#[derive(Clone, Copy)]
+pub struct SessionSetup {
+    /// Seed for the game's random number generator
+    pub seed: u16,
+    /// Index of the maze to play
+    pub maze_index: usize,
+    /// Input delay in ticks used by both peers
+    pub input_delay: u32,
+    /// Role played by the host (the client plays the other one)
+    pub host_role: PlayerRole,
+    /// The host's `level_table::table_hash` (a client with another table can't keep in sync)
+    pub level_table: u64,
+}
+
+impl SessionSetup {
+    fn encode(&self) -> [u8; SETUP_MSG_LEN] {
+        let seed = self.seed.to_le_bytes();
+        let host_role = if self.host_role == PlayerRole::PacMan { 0 } else { 1 };
+        let mut message = [0u8; SETUP_MSG_LEN];
+        message[..6].copy_from_slice(&[MSG_SETUP, seed[0], seed[1], self.maze_index as u8, self.input_delay as u8, host_role]);
+        message[6..].copy_from_slice(&self.level_table.to_le_bytes());
+        message
+    }
+
+    fn decode(message: &[u8]) -> Self {
+        Self {
+            seed: u16::from_le_bytes([message[1], message[2]]),
+            maze_index: message[3] as usize,
+            input_delay: message[4] as u32,
+            host_role: if message[5] == 0 { PlayerRole::PacMan } else { PlayerRole::Ghost },
+            level_table: u64::from_le_bytes([
+                message[6], message[7], message[8], message[9], message[10], message[11], message[12], message[13],
+            ]),
+        }
+    }
+}
+
+/// A host waiting for a client to connect
+pub struct NetHost {
+    listener: TcpListener,
+    /// Setup that will be sent to the client when it connects
+    pub setup: SessionSetup,
+}
+
+impl NetHost {
+    /// Starts listening for a client on the given port (all interfaces)
+    pub fn bind(port: u16, setup: SessionSetup) -> Result<Self, String> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|e| format!("Could not host on port {}: {}", port, describe_io_error(&e)))?;
+        listener.set_nonblocking(true).map_err(|e| e.to_string())?;
+        Ok(Self { listener, setup })
+    }
+
+    /// Port the host is listening on
+    pub fn port(&self) -> u16 {
+        self.listener.local_addr().map(|addr| addr.port()).unwrap_or(0)
+    }
+
+    /// Checks for an incoming client without blocking
+    ///
+    /// Returns `Ok(None)` while nobody has connected yet. Once a client
+    /// connects, the handshake is sent and the lobby connection is returned.
+    pub fn poll_accept(&self) -> Result<Option<NetLobby>, String> {
+        match self.listener.accept() {
+            Ok((stream, _)) => {
+                let mut stream = prepare_stream(stream)?;
+                let mut handshake = Vec::with_capacity(HANDSHAKE_MAGIC.len() + SETUP_MSG_LEN);
+                handshake.extend_from_slice(HANDSHAKE_MAGIC);
+                handshake.extend_from_slice(&self.setup.encode());
+                stream.write_all(&handshake).map_err(|e| connection_lost(&e))?;
+                Ok(Some(NetLobby::new(stream, NetRole::Host, self.setup)))
+            }
+            Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(describe_io_error(&e)),
+        }
+    }
+}
+
+/// A connected pair of players in the lobby, before the match starts
+pub struct NetLobby {
+    stream: TcpStream,
+    /// Which side of the connection we are
+    pub role: NetRole,
+    /// Current settings (chosen by the host)
+    pub setup: SessionSetup,
+    /// Whether the client has marked itself ready
+    pub client_ready: bool,
+    /// Whether the host has started the match
+    pub started: bool,
+    /// The other player's profile name, once it has arrived
+    pub peer_name: Option<String>,
+    /// Bytes received but not yet parsed into a whole message
+    buffer: Vec<u8>,
+}
+
+impl NetLobby {
+    fn new(stream: TcpStream, role: NetRole, setup: SessionSetup) -> Self {
+        Self {
+            stream,
+            role,
+            setup,
+            client_ready: false,
+            started: false,
+            peer_name: None,
+            buffer: Vec::new(),
+        }
+    }
+
+    /// Connects to a host and reads the session settings from the handshake
+    ///
+    /// # Arguments
+    /// * `address` - Host address ("192.168.1.10", "192.168.1.10:7777") or a join code
+    pub fn join(address: &str) -> Result<Self, String> {
+        let socket_addr = resolve_address(address)?;
+        let stream = TcpStream::connect_timeout(&socket_addr, PEER_TIMEOUT)
+            .map_err(|e| format!("Could not connect to {}: {}", socket_addr, describe_io_error(&e)))?;
+        let mut stream = prepare_stream(stream)?;
+
+        let mut handshake = [0u8; 4 + SETUP_MSG_LEN];
+        stream.read_exact(&mut handshake).map_err(|e| format!("Handshake failed: {}", describe_io_error(&e)))?;
+        if &handshake[0..4] != HANDSHAKE_MAGIC || handshake[4] != MSG_SETUP {
+            return Err("Handshake failed: not a Pac-Man host or a different game version".to_string());
+        }
+        let setup = SessionSetup::decode(&handshake[4..]);
+        if setup.level_table != table_hash() {
+            return Err("Handshake failed: the host plays with a different level table (data/levels.txt)".to_string());
+        }
+        Ok(Self::new(stream, NetRole::Client, setup))
+    }
+
+    /// Role this peer will play with the current setup
+    pub fn local_role(&self) -> PlayerRole {
+        local_role(self.role, self.setup.host_role)
+    }
+
+    /// Sends changed settings to the client (host only)
+    pub fn update_setup(&mut self, setup: SessionSetup) -> Result<(), String> {
+        self.setup = setup;
+        // Changing the rules un-readies the client so it can review them
+        self.client_ready = false;
+        self.stream.write_all(&setup.encode()).map_err(|e| connection_lost(&e))
+    }
+
+    /// Sends our profile name to the other player (both sides, once connected)
+    pub fn send_name(&mut self, name: &str) -> Result<(), String> {
+        let name = clean_name(name);
+        let mut message = vec![MSG_NAME, name.len() as u8];
+        message.extend_from_slice(name.as_bytes());
+        self.stream.write_all(&message).map_err(|e| connection_lost(&e))
+    }
+
+    /// Tells the host whether we are ready to start (client only)
+    pub fn set_ready(&mut self, ready: bool) -> Result<(), String> {
+        self.client_ready = ready;
+        self.stream.write_all(&[MSG_READY, ready as u8]).map_err(|e| connection_lost(&e))
+    }
+
+    /// Starts the match (host only, once the client is ready)
+    pub fn start(&mut self) -> Result<(), String> {
+        self.stream.write_all(&[MSG_GO]).map_err(|e| connection_lost(&e))?;
+        self.started = true;
+        Ok(())
+    }
+
+    /// Processes any lobby messages that have arrived, without blocking
+    pub fn poll(&mut self) -> Result<(), String> {
+        self.stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let mut chunk = [0u8; 64];
+        let read_result = loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => break Err("The other player left the lobby".to_string()),
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(connection_lost(&e)),
+            }
+        };
+        self.stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        read_result?;
+
+        // Parse whole messages; stop at the first incomplete one (or the start of the match)
+        let mut setup_changed = false;
+        while !self.buffer.is_empty() && !self.started {
+            let message_len = match self.buffer[0] {
+                MSG_SETUP => SETUP_MSG_LEN,
+                MSG_READY => READY_MSG_LEN,
+                MSG_GO => 1,
+                MSG_NAME if self.buffer.len() >= NAME_HEADER_LEN => NAME_HEADER_LEN + self.buffer[1] as usize,
+                MSG_NAME => break,
+                _ => return Err("Protocol error: unexpected message in lobby".to_string()),
+            };
+            if self.buffer.len() < message_len {
+                break;
+            }
+            let message: Vec<u8> = self.buffer.drain(..message_len).collect();
+            match message[0] {
+                MSG_SETUP => {
+                    self.setup = SessionSetup::decode(&message);
+                    setup_changed = true;
+                }
+                MSG_READY => self.client_ready = message[1] != 0,
+                MSG_NAME => {
+                    let name = clean_name(&String::from_utf8_lossy(&message[NAME_HEADER_LEN..]));
+                    self.peer_name = Some(name).filter(|name| !name.is_empty());
+                }
+                _ => self.started = true,
+            }
+        }
+        
+        // New rules from the host: un-ready and tell the host so both sides agree
+        if setup_changed && self.role == NetRole::Client && self.client_ready {
+            self.set_ready(false)?;
+        }
+        Ok(())
+    }
+
+    /// Turns the lobby into a lockstep session once the match has started
+    pub fn into_session(self) -> NetSession {
+        NetSession::new(self.stream, self.role, self.setup, self.buffer)
+    }
+}
+
+/// Outcome of exchanging one tick's inputs (see `NetSession::advance`)
+pub enum Exchange {
+    /// `(pacman_input, ghost_input)` to feed into `Game::tick` as player 1 and player 2
+    Inputs(Option<(i32, i32)>, Option<(i32, i32)>),
+    /// The peer's state hash differs from ours after the given tick: stop
+    /// ticking and swap states (see `NetSession::exchange_snapshots`)
+    Desync(u32),
+}
+
+/// An established lockstep connection to the other player
+pub struct NetSession {
+    stream: TcpStream,
+    /// Which side of the connection we are
+    pub role: NetRole,
+    /// Settings agreed in the lobby
+    pub setup: SessionSetup,
+    /// Tick the next call to `advance` will produce inputs for
+    tick: u32,
+    /// Our own inputs waiting to be applied (front = current tick)
+    local_queue: VecDeque<Option<(i32, i32)>>,
+    /// Our state hashes not yet checked against the peer's (front = oldest)
+    local_hashes: VecDeque<u64>,
+    /// Bytes received but not yet read (left over from the lobby, or a resync tag)
+    pending: Vec<u8>,
+    /// Start of our clock, sent with every input for the peer to echo
+    clock: Instant,
+    /// Last clock reading received from the peer and when it arrived
+    peer_clock: Option<(u32, Instant)>,
+    /// Smoothed round-trip time in milliseconds (None until the first echo)
+    rtt_ms: Option<f32>,
+    /// Last round-trip sample, to measure jitter against
+    last_rtt_ms: f32,
+    /// Smoothed variation between consecutive round trips in milliseconds
+    jitter_ms: f32,
+    /// Emote to send with our next input
+    queued_emote: Option<Emote>,
+    /// Emote received from the peer and not yet shown
+    peer_emote: Option<Emote>,
+}
+
+impl NetSession {
+    fn new(stream: TcpStream, role: NetRole, setup: SessionSetup, pending: Vec<u8>) -> Self {
+        // The first `input_delay` ticks have no input from either side
+        let local_queue = (0..setup.input_delay).map(|_| None).collect();
+        Self {
+            stream,
+            role,
+            setup,
+            tick: 0,
+            local_queue,
+            local_hashes: VecDeque::new(),
+            pending,
+            clock: Instant::now(),
+            peer_clock: None,
+            rtt_ms: None,
+            last_rtt_ms: 0.0,
+            jitter_ms: 0.0,
+            queued_emote: None,
+            peer_emote: None,
+        }
+    }
+
+    /// Sends an emote along with our next input
+    pub fn queue_emote(&mut self, emote: Emote) {
+        self.queued_emote = Some(emote);
+    }
+
+    /// The emote the peer sent, if one has arrived since the last call
+    pub fn take_peer_emote(&mut self) -> Option<Emote> {
+        self.peer_emote.take()
+    }
+
+    /// Smoothed round-trip time to the peer in milliseconds (None until measured)
+    pub fn ping_ms(&self) -> Option<u32> {
+        self.rtt_ms.map(|rtt| rtt.round() as u32)
+    }
+
+    /// Smoothed variation of the round-trip time in milliseconds
+    pub fn jitter_ms(&self) -> u32 {
+        self.jitter_ms.round() as u32
+    }
+
+    /// Milliseconds on our clock, wrapping after about 49 days
+    fn clock_ms(&self) -> u32 {
+        self.clock.elapsed().as_millis() as u32
+    }
+
+    /// Adds one round-trip sample to the smoothed ping and jitter
+    fn add_rtt_sample(&mut self, sample: f32) {
+        match self.rtt_ms {
+            Some(rtt) => {
+                self.rtt_ms = Some(rtt + (sample - rtt) * RTT_SMOOTHING);
+                self.jitter_ms += ((sample - self.last_rtt_ms).abs() - self.jitter_ms) * JITTER_SMOOTHING;
+            }
+            None => self.rtt_ms = Some(sample),
+        }
+        self.last_rtt_ms = sample;
+    }
+
+    /// Role this peer plays in the match
+    pub fn local_role(&self) -> PlayerRole {
+        local_role(self.role, self.setup.host_role)
+    }
+
+    /// Exchanges inputs for one tick
+    ///
+    /// Sends our input (scheduled `input_delay` ticks ahead) and blocks until
+    /// the peer's input for the current tick arrives. The peer's input comes
+    /// with its state hash from `input_delay` ticks ago, which is checked
+    /// against ours from the same tick.
+    ///
+    /// # Arguments
+    /// * `local_input` - Direction held by the local player
+    /// * `state_hash` - `Game::state_hash` of the local game before this tick
+    ///
+    /// # Returns
+    /// The inputs for the tick or the desync, or an error when the connection fails
+    pub fn advance(&mut self, local_input: Option<(i32, i32)>, state_hash: u64) -> Result<Exchange, String> {
+        self.read_available()?;
+        let send_tick = self.tick + self.setup.input_delay;
+        self.send_input(send_tick, local_input, state_hash)?;
+        self.local_queue.push_back(local_input);
+        self.local_hashes.push_back(state_hash);
+
+        let remote_input = if self.tick < self.setup.input_delay {
+            None
+        } else {
+            let (input, remote_hash) = self.receive_input(self.tick)?;
+            let checked_tick = self.tick - self.setup.input_delay;
+            if self.local_hashes.pop_front() != Some(remote_hash) {
+                return Ok(Exchange::Desync(checked_tick));
+            }
+            input
+        };
+        let local_input = self.local_queue.pop_front().unwrap_or(None);
+        self.tick += 1;
+
+        Ok(if self.local_role() == PlayerRole::PacMan {
+            Exchange::Inputs(local_input, remote_input)
+        } else {
+            Exchange::Inputs(remote_input, local_input)
+        })
+    }
+
+    /// Sends our game state after a desync and receives the peer's
+    ///
+    /// Both peers call this at the same tick. Inputs the peer had already
+    /// sent for later ticks are skipped.
+    ///
+    /// # Arguments
+    /// * `local_state` - Our game in the save format
+    ///
+    /// # Returns
+    /// The peer's game in the save format
+    pub fn exchange_snapshots(&mut self, local_state: &str) -> Result<String, String> {
+        let mut message = Vec::with_capacity(SNAPSHOT_HEADER_LEN + local_state.len());
+        message.push(MSG_SNAPSHOT);
+        message.extend_from_slice(&(local_state.len() as u32).to_le_bytes());
+        message.extend_from_slice(local_state.as_bytes());
+        self.stream.write_all(&message).map_err(|e| connection_lost(&e))?;
+
+        let mut header = [0u8; SNAPSHOT_HEADER_LEN];
+        loop {
+            self.read_message(&mut header[..1])?;
+            match header[0] {
+                MSG_INPUT => {
+                    let mut rest = [0u8; INPUT_MSG_LEN - 1];
+                    self.read_message(&mut rest)?;
+                }
+                MSG_SNAPSHOT => break,
+                _ => return Err("Protocol error: unexpected message".to_string()),
+            }
+        }
+        self.read_message(&mut header[1..])?;
+        let len = u32::from_le_bytes([header[1], header[2], header[3], header[4]]) as usize;
+        if len > MAX_SNAPSHOT_LEN {
+            return Err("Protocol error: game state too large".to_string());
+        }
+        let mut state = vec![0u8; len];
+        self.read_message(&mut state)?;
+        String::from_utf8(state).map_err(|_| "Protocol error: game state is not text".to_string())
+    }
+
+    /// Tells the client to load the host's state and starts the lockstep over (host only)
+    pub fn send_resync(&mut self) -> Result<(), String> {
+        self.stream.write_all(&[MSG_RESYNC]).map_err(|e| connection_lost(&e))?;
+        self.restart();
+        Ok(())
+    }
+
+    /// Checks, without blocking, whether the host has resynced (client only)
+    ///
+    /// # Returns
+    /// True once the client should load the host's state; the lockstep has
+    /// then started over
+    pub fn poll_resync(&mut self) -> Result<bool, String> {
+        if self.pending.is_empty() {
+            self.stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+            let mut byte = [0u8; 1];
+            let read_result = match self.stream.read(&mut byte) {
+                Ok(0) => Err("The other player left".to_string()),
+                Ok(_) => Ok(Some(byte[0])),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => Ok(None),
+                Err(e) => Err(connection_lost(&e)),
+            };
+            self.stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+            match read_result? {
+                Some(tag) => self.pending.push(tag),
+                None => return Ok(false),
+            }
+        }
+        if self.pending.remove(0) != MSG_RESYNC {
+            return Err("Protocol error: unexpected message".to_string());
+        }
+        self.restart();
+        Ok(true)
+    }
+
+    /// Starts the lockstep over from tick 0, as at the start of the match
+    fn restart(&mut self) {
+        self.tick = 0;
+        self.local_queue = (0..self.setup.input_delay).map(|_| None).collect();
+        self.local_hashes.clear();
+    }
+
+    /// Fills `buffer` with the next bytes from the peer, starting with any
+    /// left over from the lobby
+    fn read_message(&mut self, buffer: &mut [u8]) -> Result<(), String> {
+        let from_pending = self.pending.len().min(buffer.len());
+        buffer[..from_pending].copy_from_slice(&self.pending[..from_pending]);
+        self.pending.drain(..from_pending);
+        self.stream.read_exact(&mut buffer[from_pending..]).map_err(|e| connection_lost(&e))
+    }
+
+    fn send_input(&mut self, tick: u32, input: Option<(i32, i32)>, state_hash: u64) -> Result<(), String> {
+        let mut message = [0u8; INPUT_MSG_LEN];
+        message[0] = MSG_INPUT;
+        message[1..5].copy_from_slice(&tick.to_le_bytes());
+        if let Some((dx, dy)) = input {
+            message[5] = 1;
+            message[6] = dx as i8 as u8;
+            message[7] = dy as i8 as u8;
+        }
+        message[8] = self.queued_emote.take().map(Emote::code).unwrap_or(0);
+        message[9..17].copy_from_slice(&state_hash.to_le_bytes());
+        message[17..21].copy_from_slice(&self.clock_ms().to_le_bytes());
+        let (echo, hold) = match self.peer_clock {
+            Some((peer_ms, received)) => (peer_ms, received.elapsed().as_millis().min(NO_ECHO as u128 - 1) as u16),
+            None => (0, NO_ECHO),
+        };
+        message[21..25].copy_from_slice(&echo.to_le_bytes());
+        message[25..27].copy_from_slice(&hold.to_le_bytes());
+        self.stream.write_all(&message).map_err(|e| connection_lost(&e))
+    }
+
+    /// Reads the peer's input for `expected_tick` and the state hash sent with it
+    fn receive_input(&mut self, expected_tick: u32) -> Result<(Option<(i32, i32)>, u64), String> {
+        let mut message = [0u8; INPUT_MSG_LEN];
+        self.read_message(&mut message)?;
+
+        if message[0] != MSG_INPUT {
+            return Err("Protocol error: unexpected message".to_string());
+        }
+        let tick = u32::from_le_bytes([message[1], message[2], message[3], message[4]]);
+        if tick != expected_tick {
+            return Err(format!("Protocol error: expected input for tick {}, got {}", expected_tick, tick));
+        }
+        let input = if message[5] != 0 {
+            Some((message[6] as i8 as i32, message[7] as i8 as i32))
+        } else {
+            None
+        };
+        if let Some(emote) = Emote::from_code(message[8]) {
+            self.peer_emote = Some(emote);
+        }
+        let mut hash = [0u8; 8];
+        hash.copy_from_slice(&message[9..17]);
+
+        self.note_arrival(&message);
+        Ok((input, u64::from_le_bytes(hash)))
+    }
+
+    /// Moves whatever the peer has sent so far into `pending` without blocking,
+    /// noting when each input message arrived
+    ///
+    /// Inputs are sent `input_delay` ticks before they are read, so timing
+    /// them when they are read would add that delay to the ping.
+    fn read_available(&mut self) -> Result<(), String> {
+        self.stream.set_nonblocking(true).map_err(|e| e.to_string())?;
+        let mut chunk = [0u8; 256];
+        let read_result = loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => break Err("The other player left".to_string()),
+                Ok(n) => self.pending.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break Ok(()),
+                Err(e) => break Err(connection_lost(&e)),
+            }
+        };
+        self.stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+        read_result?;
+
+        let mut message = [0u8; INPUT_MSG_LEN];
+        let mut offset = 0;
+        while self.pending.len() >= offset + INPUT_MSG_LEN && self.pending[offset] == MSG_INPUT {
+            message.copy_from_slice(&self.pending[offset..offset + INPUT_MSG_LEN]);
+            self.note_arrival(&message);
+            offset += INPUT_MSG_LEN;
+        }
+        Ok(())
+    }
+
+    /// Takes the clock readings from an input message that has just arrived
+    /// (the first time it is seen) and measures the round trip from them
+    fn note_arrival(&mut self, message: &[u8; INPUT_MSG_LEN]) {
+        let peer_ms = u32::from_le_bytes([message[17], message[18], message[19], message[20]]);
+        if let Some((last_ms, _)) = self.peer_clock {
+            if (peer_ms.wrapping_sub(last_ms) as i32) <= 0 {
+                return;
+            }
+        }
+        self.peer_clock = Some((peer_ms, Instant::now()));
+
+        // Round trip: our clock now, less the reading the peer echoed and how long it held it
+        let echo = u32::from_le_bytes([message[21], message[22], message[23], message[24]]);
+        let hold = u16::from_le_bytes([message[25], message[26]]);
+        if hold != NO_ECHO {
+            let rtt = self.clock_ms().wrapping_sub(echo).saturating_sub(hold as u32);
+            self.add_rtt_sample(rtt as f32);
+        }
+    }
+}
+
+/// Role played by this peer given its side of the connection and the host's role
+fn local_role(role: NetRole, host_role: PlayerRole) -> PlayerRole {
+    match (role, host_role) {
+        (NetRole::Host, host_role) => host_role,
+        (NetRole::Client, PlayerRole::PacMan) => PlayerRole::Ghost,
+        (NetRole::Client, PlayerRole::Ghost) => PlayerRole::PacMan,
+    }
+}
+
+/// Configures a freshly connected stream for low-latency blocking lockstep
+fn prepare_stream(stream: TcpStream) -> Result<TcpStream, String> {
+    stream.set_nonblocking(false).map_err(|e| e.to_string())?;
+    stream.set_nodelay(true).map_err(|e| e.to_string())?;
+    stream.set_read_timeout(Some(PEER_TIMEOUT)).map_err(|e| e.to_string())?;
+    stream.set_write_timeout(Some(PEER_TIMEOUT)).map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+/// Turns what the player typed (address, address:port or join code) into a socket address
+fn resolve_address(text: &str) -> Result<SocketAddr, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("Enter an address or join code".to_string());
+    }
+    if let Some(address) = decode_join_code(text) {
+        return Ok(SocketAddr::V4(address));
+    }
+    let with_port = if text.contains(':') {
+        text.to_string()
+    } else {
+        format!("{}:{}", text, DEFAULT_PORT)
+    };
+    with_port.to_socket_addrs()
+        .map_err(|_| format!("Unknown address or join code: {}", text))?
+        .next()
+        .ok_or_else(|| format!("Unknown address or join code: {}", text))
+}
+
+/// Best guess at this machine's LAN address, for showing to the host
+///
+/// Connecting a UDP socket sends nothing; it only asks the OS which local
+/// interface would be used to reach the outside world.
+pub fn local_ipv4() -> Ipv4Addr {
+    UdpSocket::bind("0.0.0.0:0")
+        .and_then(|socket| {
+            socket.connect("8.8.8.8:80")?;
+            socket.local_addr()
+        })
+        .ok()
+        .and_then(|addr| match addr {
+            SocketAddr::V4(v4) => Some(*v4.ip()),
+            SocketAddr::V6(_) => None,
+        })
+        .unwrap_or(Ipv4Addr::LOCALHOST)
+}
+
+/// Encodes an IPv4 address and port as a 10-character join code ("XXXXX-XXXXX")
+pub fn encode_join_code(address: SocketAddrV4) -> String {
+    let value = ((u32::from(*address.ip()) as u64) << 16) | address.port() as u64;
+    let mut code = String::with_capacity(11);
+    for i in (0..10).rev() {
+        let digit = ((value >> (i * 5)) & 0x1F) as usize;
+        code.push(JOIN_CODE_ALPHABET[digit] as char);
+        if i == 5 {
+            code.push('-');
+        }
+    }
+    code
+}
+
+/// Decodes a join code produced by `encode_join_code` (case and dashes are ignored)
+pub fn decode_join_code(code: &str) -> Option<SocketAddrV4> {
+    let digits: Vec<u8> = code.bytes()
+        .filter(|byte| *byte != b'-')
+        .map(|byte| byte.to_ascii_uppercase())
+        .collect();
+    if digits.len() != 10 {
+        return None;
+    }
+    let mut value: u64 = 0;
+    for digit in digits {
+        let index = JOIN_CODE_ALPHABET.iter().position(|c| *c == digit)?;
+        value = (value << 5) | index as u64;
+    }
+    let ip = Ipv4Addr::from((value >> 16) as u32);
+    let port = (value & 0xFFFF) as u16;
+    Some(SocketAddrV4::new(ip, port))
+}
+
+/// Readable explanation for common connection failures
+fn describe_io_error(error: &std::io::Error) -> String {
+    match error.kind() {
+        ErrorKind::ConnectionRefused => "nobody is hosting a game there".to_string(),
+        ErrorKind::TimedOut | ErrorKind::WouldBlock => "the other player is not responding".to_string(),
+        ErrorKind::AddrInUse => "the port is already in use by another program".to_string(),
+        ErrorKind::AddrNotAvailable => "that address is not available on this machine".to_string(),
+        ErrorKind::PermissionDenied => "permission denied (check your firewall)".to_string(),
+        ErrorKind::ConnectionReset | ErrorKind::ConnectionAborted | ErrorKind::UnexpectedEof => {
+            "the other player disconnected".to_string()
+        }
+        _ => error.to_string(),
+    }
+}
+
+/// Error message for a connection that dropped mid-lobby or mid-match
+fn connection_lost(error: &std::io::Error) -> String {
+    format!("Connection lost: {}", describe_io_error(error))
+}
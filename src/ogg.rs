@@ -0,0 +1,31 @@
+//! OGG Vorbis decoder
+//!
+//! Reads OGG Vorbis files (any number of channels) into mono f32 samples at
+//! the mixer's sample rate, the same buffer `wav::decode_wav` fills, so the
+//! mixer plays either kind of file alike. Decoding is done by the `lewton`
+//! crate, built with the `ogg` feature.
+
+use std::io::Cursor;
+use lewton::inside_ogg::OggStreamReader;
+use crate::wav::resample;
+
+/// Decodes an OGG Vorbis file to mono samples in -1..1 at `sample_rate`
+///
+/// Channels are averaged and the result is resampled linearly.
+pub fn decode_ogg(bytes: &[u8], sample_rate: i32) -> Result<Vec<f32>, String> {
+    let mut reader = OggStreamReader::new(Cursor::new(bytes)).map_err(|e| format!("not an OGG Vorbis file: {}", e))?;
+    let channels = reader.ident_hdr.audio_channels as usize;
+    let source_rate = reader.ident_hdr.audio_sample_rate as i32;
+    if channels == 0 || source_rate <= 0 {
+        return Err("bad channel count or sample rate".to_string());
+    }
+
+    let mut mono = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl().map_err(|e| e.to_string())? {
+        mono.extend(packet.chunks_exact(channels).map(|frame| {
+            let sum: f32 = frame.iter().map(|&sample| sample as f32 / 32768.0).sum();
+            sum / channels as f32
+        }));
+    }
+    Ok(resample(&mono, source_rate, sample_rate))
+}
@@ -0,0 +1,94 @@
+//! One-button control scheme, for switch-access hardware
+//!
+//! With **One Button** on in the controls settings, player 1's Pac-Man moves
+//! by himself: he goes on straight and takes corners and dead ends on his own.
+//! The only control is one action (Space, Enter or a controller's A button)
+//! that cycles the way he will take at the next junction, shown by an arrow
+//! on that junction. Choosing to go back turns him around at once. Every new
+//! junction starts on straight on, where there is a straight on.
+//!
+//! The choice is turned into a held direction on every tick (`steer`), like
+//! a player holding a key, so the game itself, recordings and online play
+//! don't know the difference.
+
+use crate::game::Game;
+use crate::game_config::PlayerRole;
+use crate::player::Player;
+
+/// The next junction and the way chosen there
+pub struct OneButton {
+    /// The player wants one-button control
+    pub enabled: bool,
+    /// Tile of the next junction
+    junction: Option<(i32, i32)>,
+    /// Ways open at the junction (see `Player::next_junction`)
+    ways: Vec<(i32, i32)>,
+    /// Index into `ways` of the chosen one
+    choice: usize,
+    /// Junction Pac-Man stands on and has already taken the chosen way out of
+    left: Option<(i32, i32)>,
+}
+
+impl OneButton {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, junction: None, ways: Vec::new(), choice: 0, left: None }
+    }
+
+    /// The action: the next way at the junction
+    pub fn cycle(&mut self) {
+        if !self.ways.is_empty() {
+            self.choice = (self.choice + 1) % self.ways.len();
+        }
+    }
+
+    /// The next junction and the way chosen there, if known
+    pub fn target(&self) -> Option<((i32, i32), (i32, i32))> {
+        Some((self.junction?, *self.ways.get(self.choice)?))
+    }
+
+    /// The direction player 1 holds this tick
+    ///
+    /// # Returns
+    /// None when the scheme is off or player 1 doesn't play Pac-Man (their
+    /// usual input is used)
+    pub fn steer(&mut self, game: &Game) -> Option<(i32, i32)> {
+        if !self.enabled || game.config.player1_role != PlayerRole::PacMan {
+            self.junction = None;
+            return None;
+        }
+        let player = game.players.first()?;
+        self.look_ahead(player);
+        let (junction, way) = self.target()?;
+        // Until the junction he holds his heading, so that a side way on the
+        // tiles before it (or a post-turn back onto the last one) isn't taken;
+        // going back, when chosen rather than a dead end, starts at once
+        let heading = (player.dx, player.dy);
+        let chose_back = self.choice > 0 && way == (-heading.0, -heading.1);
+        if junction == (player.x, player.y) || chose_back {
+            Some(way)
+        } else {
+            Some(heading)
+        }
+    }
+
+    /// Finds the next junction, keeping the choice while it stays the same one
+    fn look_ahead(&mut self, player: &Player) {
+        let here = (player.x, player.y);
+        if self.left != Some(here) {
+            self.left = None;
+        }
+        // Headed out of the junction the chosen way: it is behind him
+        if let Some((junction, way)) = self.target() {
+            if junction == here && (player.dx, player.dy) == way {
+                self.left = Some(here);
+            }
+        }
+
+        let (junction, ways) = player.next_junction(self.left.is_some());
+        if self.junction != Some(junction) || self.ways != ways {
+            self.junction = Some(junction);
+            self.ways = ways;
+            self.choice = 0;
+        }
+    }
+}
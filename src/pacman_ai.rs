@@ -0,0 +1,368 @@
+//! AI Pac-Man, who plays when a human takes a ghost (and in the attract demo)
+//!
+//! How he plays is up to a `PacmanAi`, picked by the game's `PacmanStyle`
+//! (see `style_ai`). All the styles build on one route planner, which plans
+//! from scratch every time he reaches a tile:
+//!
+//! 1. A danger map holds, for each tile, how many steps a dangerous ghost
+//!    needs to get there. It comes from a breadth-first search out of every
+//!    ghost that may not reverse on its first step, since ghosts don't.
+//!    Frightened ghosts are harmless, unless they will have recovered by the
+//!    time he could reach them.
+//! 2. A second search from Pac-Man only walks the tiles he reaches a safe
+//!    margin of steps before any dangerous ghost.
+//! 3. During power time he may hunt the nearest frightened ghost he can catch
+//!    in time. Otherwise he heads for the best pellet among the safe tiles,
+//!    trading distance against how many other pellets lie around it, so he
+//!    clears clusters instead of chasing strays.
+//! 4. With no pellet safe to reach he waits on the safe tile nearest the
+//!    pellets for the ghosts to move on, or, cornered, runs for the
+//!    neighbouring tile farthest from them.
+
+use std::collections::VecDeque;
+use crate::constants::{
+    GRID_W, GRID_H,
+    AI_PACMAN_SAFE_MARGIN, AI_PACMAN_CAUTIOUS_MARGIN, AI_PACMAN_SPEEDRUN_MARGIN, AI_PACMAN_TROLL_DISTANCE,
+    AI_PACMAN_CLUSTER_RADIUS, AI_PACMAN_CLUSTER_WEIGHT, AI_PACMAN_FRIGHT_MARGIN
+};
+use crate::game_config::PacmanStyle;
+use crate::ghost::Ghost;
+use crate::maze::{is_wall, is_pellet, is_teleporter, find_other_teleporter, wrap_tunnel};
+
+/// All possible movement directions (up, down, left, right)
+const DIRECTIONS: [(i32, i32); 4] = [(0, -1), (0, 1), (-1, 0), (1, 0)];
+
+/// What the AI Pac-Man knows about the game when he plans his next step
+pub struct PacmanView<'a> {
+    /// Pac-Man's tile
+    pub position: (i32, i32),
+    /// Direction Pac-Man is moving in ((0, 0) when standing still)
+    pub heading: (i32, i32),
+    /// All the ghosts, human-controlled ones included
+    pub ghosts: &'a [Ghost],
+    /// Which pellets have been eaten (indexed by `y * GRID_W + x`)
+    pub eaten: &'a [bool],
+    /// Ticks of power time left (0 when the ghosts aren't frightened)
+    pub power_ticks: i32,
+    /// Ticks Pac-Man takes to move one tile at this level
+    pub ticks_per_tile: i32,
+}
+
+/// A tile reached by a search: steps from the start and the first step taken towards it
+#[derive(Clone, Copy)]
+struct Reached {
+    distance: i32,
+    first_step: (i32, i32),
+}
+
+/// A way for the computer to play Pac-Man
+pub trait PacmanAi: Sync {
+    /// The direction for Pac-Man's next step, picked once he is on a tile
+    ///
+    /// # Returns
+    /// None when no move is worth making (he keeps his current heading)
+    fn plan(&self, view: &PacmanView) -> Option<(i32, i32)>;
+}
+
+/// The route planner described above, tuned by its fields
+pub struct Planner {
+    /// Steps ahead of every dangerous ghost he must be to count a tile as safe
+    pub safe_margin: i32,
+    /// Tiles further he will walk for each other pellet around the one he goes for
+    pub cluster_weight: i32,
+    /// Whether he goes after frightened ghosts
+    pub hunts: bool,
+}
+
+/// Plays like the planner it wraps, but goes back towards the nearest ghost
+/// whenever it is more than `AI_PACMAN_TROLL_DISTANCE` steps away, keeping
+/// just out of its reach
+pub struct Troll {
+    pub planner: Planner,
+}
+
+impl PacmanAi for Planner {
+    fn plan(&self, view: &PacmanView) -> Option<(i32, i32)> {
+        let danger = danger_map(view);
+        let safe = self.safe_tiles(view, &danger);
+        self.hunts.then(|| hunt(view, &safe)).flatten()
+            .or_else(|| best_pellet(view, &safe, self.cluster_weight))
+            .or_else(|| approach(view, &safe))
+            .or_else(|| flee(view, &danger))
+    }
+}
+
+impl Planner {
+    /// How Pac-Man gets to every tile he reaches safely ahead of the ghosts
+    fn safe_tiles(&self, view: &PacmanView, danger: &[i32]) -> Vec<Option<Reached>> {
+        search(view.position, view.heading, false, |tile, distance| distance + self.safe_margin <= danger[tile])
+    }
+}
+
+impl PacmanAi for Troll {
+    fn plan(&self, view: &PacmanView) -> Option<(i32, i32)> {
+        // Ghosts out in the maze to tease (not frightened or waiting in the house)
+        let teased = reach_map(view, |ghost| !ghost.vulnerable && !ghost.in_house);
+        let ghost_distance = tile_index(view.position.0, view.position.1).map(|tile| teased[tile]).unwrap_or(0);
+        if ghost_distance <= AI_PACMAN_TROLL_DISTANCE || ghost_distance == i32::MAX {
+            return self.planner.plan(view);
+        }
+        // Too far: the safe tile nearest one of them
+        self.planner.safe_tiles(view, &danger_map(view)).iter().enumerate()
+            .filter_map(|(tile, reached)| reached.map(|reached| (teased[tile], reached)))
+            .filter(|&(_, reached)| reached.distance > 0)
+            .min_by_key(|&(ghost_distance, reached)| (ghost_distance, reached.distance))
+            .map(|(_, reached)| reached.first_step)
+            .or_else(|| self.planner.plan(view))
+    }
+}
+
+/// Goes for the biggest pellet clusters and every frightened ghost he can catch
+static GREEDY: Planner = Planner {
+    safe_margin: AI_PACMAN_SAFE_MARGIN,
+    cluster_weight: AI_PACMAN_CLUSTER_WEIGHT,
+    hunts: true,
+};
+
+/// Keeps well away from the ghosts and never hunts them
+static CAUTIOUS: Planner = Planner {
+    safe_margin: AI_PACMAN_CAUTIOUS_MARGIN,
+    cluster_weight: AI_PACMAN_CLUSTER_WEIGHT,
+    hunts: false,
+};
+
+/// Eats the nearest pellet every time and cuts it fine, to clear mazes fast
+static SPEEDRUNNER: Planner = Planner {
+    safe_margin: AI_PACMAN_SPEEDRUN_MARGIN,
+    cluster_weight: 0,
+    hunts: false,
+};
+
+/// Hangs around the ghosts, then eats them when he can
+static TROLL: Troll = Troll {
+    planner: Planner {
+        safe_margin: AI_PACMAN_CAUTIOUS_MARGIN,
+        cluster_weight: AI_PACMAN_CLUSTER_WEIGHT,
+        hunts: true,
+    },
+};
+
+/// The AI a style gives Pac-Man
+pub fn style_ai(style: PacmanStyle) -> &'static dyn PacmanAi {
+    match style {
+        PacmanStyle::Greedy => &GREEDY,
+        PacmanStyle::Cautious => &CAUTIOUS,
+        PacmanStyle::Speedrunner => &SPEEDRUNNER,
+        PacmanStyle::Troll => &TROLL,
+    }
+}
+
+/// The first step towards the nearest frightened ghost Pac-Man can catch before it recovers
+fn hunt(view: &PacmanView, safe: &[Option<Reached>]) -> Option<(i32, i32)> {
+    view.ghosts.iter()
+        .filter(|ghost| ghost.vulnerable && !ghost.in_house)
+        .filter_map(|ghost| safe[tile_index(ghost.x, ghost.y)?])
+        .filter(|reached| reached.distance > 0 && can_catch(view, reached.distance))
+        .min_by_key(|reached| reached.distance)
+        .map(|reached| reached.first_step)
+}
+
+/// The first step towards the safe pellet best worth going for (closest, with the most pellets around it)
+///
+/// # Arguments
+/// * `cluster_weight` - Tiles further worth walking for each other pellet around a pellet
+fn best_pellet(view: &PacmanView, safe: &[Option<Reached>], cluster_weight: i32) -> Option<(i32, i32)> {
+    let mut best: Option<(i32, Reached)> = None;
+    for y in 0..GRID_H {
+        for x in 0..GRID_W {
+            if !has_pellet(view, x, y) {
+                continue;
+            }
+            let Some(reached) = tile_index(x, y).and_then(|tile| safe[tile]) else { continue };
+            if reached.distance == 0 {
+                continue;
+            }
+            let cost = reached.distance - cluster_weight * pellets_around(view, x, y);
+            if best.map(|(best_cost, _)| cost < best_cost).unwrap_or(true) {
+                best = Some((cost, reached));
+            }
+        }
+    }
+    best.map(|(_, reached)| reached.first_step)
+}
+
+/// The first step towards the safe tile nearest the pellets he can't safely
+/// reach yet, to wait there for the ghosts to move on
+fn approach(view: &PacmanView, safe: &[Option<Reached>]) -> Option<(i32, i32)> {
+    let to_pellet = pellet_distances(view);
+    safe.iter().enumerate()
+        .filter_map(|(tile, reached)| reached.map(|reached| (to_pellet[tile], reached)))
+        .filter(|&(pellet_distance, reached)| reached.distance > 0 && pellet_distance < i32::MAX)
+        .min_by_key(|&(pellet_distance, reached)| (pellet_distance, reached.distance))
+        .map(|(_, reached)| reached.first_step)
+}
+
+/// The first step onto the neighbouring tile the ghosts need the longest to
+/// reach, when no tile is safe
+fn flee(view: &PacmanView, danger: &[i32]) -> Option<(i32, i32)> {
+    DIRECTIONS.iter()
+        .filter_map(|&direction| {
+            let (x, y) = step(view.position, direction)?;
+            Some((danger[tile_index(x, y)?], direction))
+        })
+        .max_by_key(|&(ghost_distance, _)| ghost_distance)
+        .map(|(_, direction)| direction)
+}
+
+/// Steps from each tile to the nearest pellet left (`i32::MAX` where there is none to walk to)
+fn pellet_distances(view: &PacmanView) -> Vec<i32> {
+    let mut distances = vec![i32::MAX; (GRID_W * GRID_H) as usize];
+    let mut queue = VecDeque::new();
+    for y in 0..GRID_H {
+        for x in 0..GRID_W {
+            if let Some(tile) = tile_index(x, y).filter(|_| has_pellet(view, x, y)) {
+                distances[tile] = 0;
+                queue.push_back((x, y));
+            }
+        }
+    }
+    while let Some(position) = queue.pop_front() {
+        let Some(distance) = tile_index(position.0, position.1).map(|tile| distances[tile]) else { continue };
+        for &direction in &DIRECTIONS {
+            let Some(next) = step(position, direction) else { continue };
+            let Some(tile) = tile_index(next.0, next.1) else { continue };
+            if distances[tile] == i32::MAX {
+                distances[tile] = distance + 1;
+                queue.push_back(next);
+            }
+        }
+    }
+    distances
+}
+
+/// Steps the nearest dangerous ghost needs to reach each tile (`i32::MAX` where none can)
+///
+/// A frightened ghost only counts from when it will have recovered.
+fn danger_map(view: &PacmanView) -> Vec<i32> {
+    let fright_steps = view.power_ticks / view.ticks_per_tile.max(1) - AI_PACMAN_FRIGHT_MARGIN;
+    ghost_steps(view, |ghost| {
+        let frightened = ghost.vulnerable && !ghost.in_house;
+        Some(if frightened { fright_steps.max(0) } else { 0 })
+    })
+}
+
+/// Steps the nearest of some ghosts needs to reach each tile (`i32::MAX` where none can)
+fn reach_map(view: &PacmanView, counts: impl Fn(&Ghost) -> bool) -> Vec<i32> {
+    ghost_steps(view, |ghost| counts(ghost).then_some(0))
+}
+
+/// Steps the nearest ghost needs to reach each tile, each ghost searched
+/// from its tile and heading (`i32::MAX` where none can)
+///
+/// # Arguments
+/// * `earliest` - Fewest steps a ghost can count for, or None to leave it out
+fn ghost_steps(view: &PacmanView, earliest: impl Fn(&Ghost) -> Option<i32>) -> Vec<i32> {
+    let mut steps = vec![i32::MAX; (GRID_W * GRID_H) as usize];
+    for ghost in view.ghosts {
+        let Some(earliest) = earliest(ghost) else { continue };
+        let reach = search((ghost.x, ghost.y), (ghost.dx, ghost.dy), true, |_, _| true);
+        for (tile, reached) in reach.iter().enumerate() {
+            if let Some(reached) = reached {
+                steps[tile] = steps[tile].min(reached.distance.max(earliest));
+            }
+        }
+    }
+    steps
+}
+
+/// Breadth-first search over the maze, through tunnels and teleporters
+///
+/// Directions are tried in the order of `heading` first, then up, down,
+/// left, right, so that of several equally short routes the one straight
+/// ahead wins.
+///
+/// # Arguments
+/// * `start` - Tile to search from
+/// * `heading` - Direction of travel at the start
+/// * `no_reverse` - Whether the first step may not go back against `heading`
+/// * `enter` - Whether a tile (by index) may be entered at a number of steps
+///
+/// # Returns
+/// For every tile (by index), how it was reached, or None if it wasn't
+fn search(
+    start: (i32, i32),
+    heading: (i32, i32),
+    no_reverse: bool,
+    enter: impl Fn(usize, i32) -> bool,
+) -> Vec<Option<Reached>> {
+    let mut reached = vec![None; (GRID_W * GRID_H) as usize];
+    let Some(start_tile) = tile_index(start.0, start.1) else { return reached };
+    reached[start_tile] = Some(Reached { distance: 0, first_step: (0, 0) });
+
+    let directions: Vec<(i32, i32)> = std::iter::once(heading)
+        .filter(|&direction| direction != (0, 0))
+        .chain(DIRECTIONS.iter().copied().filter(|&direction| direction != heading))
+        .collect();
+    let mut queue = VecDeque::from([(start, 0, (0, 0))]);
+    while let Some((position, distance, first_step)) = queue.pop_front() {
+        for &direction in &directions {
+            if distance == 0 && no_reverse && direction == (-heading.0, -heading.1) && heading != (0, 0) {
+                continue;
+            }
+            let Some(next) = step(position, direction) else { continue };
+            let Some(tile) = tile_index(next.0, next.1) else { continue };
+            if reached[tile].is_some() || !enter(tile, distance + 1) {
+                continue;
+            }
+            let first_step = if distance == 0 { direction } else { first_step };
+            reached[tile] = Some(Reached { distance: distance + 1, first_step });
+            queue.push_back((next, distance + 1, first_step));
+        }
+    }
+    reached
+}
+
+/// The tile one step away in a direction, as Pac-Man would move there
+/// (None into a wall)
+fn step(position: (i32, i32), direction: (i32, i32)) -> Option<(i32, i32)> {
+    let (x, y) = wrap_tunnel(position.0 + direction.0, position.1 + direction.1);
+    if is_wall(x, y) {
+        return None;
+    }
+    if is_teleporter(x, y) {
+        return Some(find_other_teleporter(x, y).unwrap_or((x, y)));
+    }
+    Some((x, y))
+}
+
+/// Whether Pac-Man can reach a frightened ghost this many steps away before it recovers
+fn can_catch(view: &PacmanView, distance: i32) -> bool {
+    (distance + AI_PACMAN_FRIGHT_MARGIN) * view.ticks_per_tile < view.power_ticks
+}
+
+/// Whether a tile still holds a pellet (regular or power)
+fn has_pellet(view: &PacmanView, x: i32, y: i32) -> bool {
+    let eaten = tile_index(x, y).and_then(|tile| view.eaten.get(tile)).copied().unwrap_or(true);
+    !eaten && is_pellet(x, y)
+}
+
+/// Pellets left within `AI_PACMAN_CLUSTER_RADIUS` tiles of a tile (counting
+/// steps across and down, through walls), not counting the tile itself
+fn pellets_around(view: &PacmanView, x: i32, y: i32) -> i32 {
+    let radius = AI_PACMAN_CLUSTER_RADIUS;
+    let mut count = 0;
+    for offset_y in -radius..=radius {
+        for offset_x in -radius..=radius {
+            let near = offset_x.abs() + offset_y.abs();
+            if near > 0 && near <= radius && has_pellet(view, x + offset_x, y + offset_y) {
+                count += 1;
+            }
+        }
+    }
+    count
+}
+
+/// Index of a tile in per-tile arrays (None off the grid)
+fn tile_index(x: i32, y: i32) -> Option<usize> {
+    ((0..GRID_W).contains(&x) && (0..GRID_H).contains(&y)).then(|| (y * GRID_W + x) as usize)
+}
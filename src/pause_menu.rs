@@ -0,0 +1,167 @@
+//! Menus shown over the board while a game is paused: the pause menu itself
+//! and the confirmation asked before a run is thrown away
+
+use sdl2::rect::Rect;
+use crate::ui;
+
+/// What was chosen in the pause menu
+pub enum PauseAction {
+    None,
+    Resume,
+    RestartLevel,
+    Settings,
+    QuitToMenu,
+}
+
+/// Entries of the pause menu, in order
+const ITEMS: [&str; 4] = ["Resume", "Restart Level", "Settings", "Quit to Menu"];
+
+pub struct PauseMenu {
+    pub selected: usize,
+    /// Screen area of each entry as last drawn (for the mouse)
+    hit_areas: Vec<Rect>,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self { selected: 0, hit_areas: Vec::new() }
+    }
+
+    /// Moves the selection up (`dy` < 0) or down (`dy` > 0)
+    pub fn process_input(&mut self, dy: i32) {
+        if dy < 0 && self.selected > 0 {
+            self.selected -= 1;
+        } else if dy > 0 && self.selected < ITEMS.len() - 1 {
+            self.selected += 1;
+        }
+    }
+
+    /// Selects the entry under the mouse pointer, if any
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        if let Some(entry) = ui::hit_test(&self.hit_areas, x, y) {
+            self.selected = entry;
+        }
+    }
+
+    /// Text of the selected entry (for announcements)
+    pub fn selected_label(&self) -> &'static str {
+        ITEMS[self.selected.min(ITEMS.len() - 1)]
+    }
+
+    /// Chooses the entry under the mouse pointer like Enter would
+    pub fn mouse_clicked(&mut self, x: i32, y: i32) -> PauseAction {
+        match ui::hit_test(&self.hit_areas, x, y) {
+            Some(entry) => {
+                self.selected = entry;
+                self.select()
+            }
+            None => PauseAction::None,
+        }
+    }
+
+    pub fn select(&self) -> PauseAction {
+        match self.selected {
+            0 => PauseAction::Resume,
+            1 => PauseAction::RestartLevel,
+            2 => PauseAction::Settings,
+            3 => PauseAction::QuitToMenu,
+            _ => PauseAction::None,
+        }
+    }
+
+    /// Draws the entries as a list starting at `start_y` (on top of the pause overlay)
+    pub fn draw(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, center_x: i32, start_y: i32) {
+        self.hit_areas = ui::list(canvas, &ITEMS, self.selected, center_x, start_y);
+    }
+
+    /// Height of the menu as drawn
+    pub fn height() -> i32 {
+        ITEMS.len() as i32 * ui::LIST_SPACING
+    }
+}
+
+impl Default for PauseMenu {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// What was chosen when asked to confirm quitting
+pub enum QuitChoice {
+    None,
+    SaveAndQuit,
+    Quit,
+    Cancel,
+}
+
+/// Asks before a run in progress is left, offering to save it first
+pub struct QuitConfirm {
+    pub selected: usize,
+    /// Whether "Save and Quit" is offered (see `savegame::can_save`)
+    can_save: bool,
+    /// Screen area of each choice as last drawn (for the mouse)
+    hit_areas: Vec<Rect>,
+}
+
+impl QuitConfirm {
+    pub fn new(can_save: bool) -> Self {
+        Self { selected: 0, can_save, hit_areas: Vec::new() }
+    }
+
+    fn items(&self) -> &'static [&'static str] {
+        if self.can_save {
+            &["Save and Quit", "Quit Without Saving", "Cancel"]
+        } else {
+            &["Quit", "Cancel"]
+        }
+    }
+
+    /// Moves the selection up (`dy` < 0) or down (`dy` > 0)
+    pub fn process_input(&mut self, dy: i32) {
+        if dy < 0 && self.selected > 0 {
+            self.selected -= 1;
+        } else if dy > 0 && self.selected < self.items().len() - 1 {
+            self.selected += 1;
+        }
+    }
+
+    /// Selects the choice under the mouse pointer, if any
+    pub fn mouse_moved(&mut self, x: i32, y: i32) {
+        if let Some(entry) = ui::hit_test(&self.hit_areas, x, y) {
+            self.selected = entry;
+        }
+    }
+
+    /// Text of the selected choice (for announcements)
+    pub fn selected_label(&self) -> &'static str {
+        let items = self.items();
+        items[self.selected.min(items.len() - 1)]
+    }
+
+    /// Chooses the choice under the mouse pointer like Enter would
+    pub fn mouse_clicked(&mut self, x: i32, y: i32) -> QuitChoice {
+        match ui::hit_test(&self.hit_areas, x, y) {
+            Some(entry) => {
+                self.selected = entry;
+                self.select()
+            }
+            None => QuitChoice::None,
+        }
+    }
+
+    pub fn select(&self) -> QuitChoice {
+        // Without saving, the list starts at "Quit"
+        match self.selected + if self.can_save { 0 } else { 1 } {
+            0 => QuitChoice::SaveAndQuit,
+            1 => QuitChoice::Quit,
+            2 => QuitChoice::Cancel,
+            _ => QuitChoice::None,
+        }
+    }
+
+    /// Draws the question and the choices starting at `start_y`
+    pub fn draw(&mut self, canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, center_x: i32, start_y: i32) {
+        ui::heading(canvas, "Quit this run?", center_x, start_y);
+        self.hit_areas = ui::list(canvas, self.items(), self.selected, center_x, start_y + ui::LIST_SPACING);
+    }
+}
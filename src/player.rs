@@ -2,11 +2,27 @@
 //! 
 //! This module manages the player's position, movement, and input processing.
 //! It handles grid-based movement with sub-frame precision for smooth animation.
+//!
+//! Turns at a junction may be input a little early or late and still cut the
+//! corner, as in the arcade game ("cornering"). An early turn ("pre-turn")
+//! is taken the moment Pac-Man reaches the junction, and the ticks it was early
+//! count towards the next tile, a small speed advantage. A late one
+//! ("post-turn"), input just after Pac-Man left the junction, takes him back
+//! onto it heading the new way, keeping the ticks already spent moving. How
+//! many ticks count as "a little" is the game's cornering window
+//! (`GameConfig::cornering_window`); 0 turns cornering off.
+//!
+//! On an ice maze (`GameConfig::ice`) Pac-Man slides: once moving he keeps
+//! going until he hits a wall or reaches a junction, and only turns (or turns
+//! back) there. A turn input on the way is held until the next junction, and
+//! there is no cornering.
 
-use crate::constants::{GRID_W, TUNNEL_ROW, PLAYER_START_X, PLAYER_START_Y, PLAYER_MOVE_SUBFRAMES};
-use crate::maze::{is_wall, is_teleporter, find_other_teleporter};
+use crate::constants::{GRID_W, GRID_H, PLAYER_START_X, PLAYER_START_Y};
+use crate::maze::{is_wall, is_teleporter, find_other_teleporter, wrap_tunnel};
+use crate::pacman_ai::{PacmanAi, PacmanView};
 
 /// Represents the player (Pac-Man) in the game
+#[derive(Clone)]
 pub struct Player {
     /// Current X position on the grid
     pub x: i32,
@@ -22,19 +38,34 @@ pub struct Player {
     pub queued_dx: i32,
     /// Queued Y direction (for perpendicular turns that can't happen immediately)
     pub queued_dy: i32,
+    /// Ticks since the queued direction was input (for pre-turns)
+    pub queued_ticks: i32,
+    /// Floor of a two-floor maze he is on (0 = ground floor)
+    pub floor: usize,
 }
 
 impl Player {
     /// Creates a new player at the starting position
     pub fn new() -> Self {
+        Self::new_at(PLAYER_START_X, PLAYER_START_Y)
+    }
+
+    /// Creates a new player at the specified position
+    /// 
+    /// # Arguments
+    /// * `x` - Starting X position
+    /// * `y` - Starting Y position
+    pub fn new_at(x: i32, y: i32) -> Self {
         Self {
-            x: PLAYER_START_X,
-            y: PLAYER_START_Y,
+            x,
+            y,
             dx: 0,
             dy: 0,
             sub_frame_counter: 0,
             queued_dx: 0,
             queued_dy: 0,
+            queued_ticks: 0,
+            floor: 0,
         }
     }
 
@@ -47,15 +78,40 @@ impl Player {
     /// - Reverse turns: 180-degree turns (always allowed)
     /// 
     /// If a turn can't happen immediately, it's queued for when the player aligns to the grid.
+    /// A perpendicular turn input just after passing a junction is a post-turn
+    /// (see the module documentation).
+    /// 
+    /// On ice, only a standing Pac-Man turns at once; any other turn is queued
+    /// for the next junction (see `update`).
     /// 
     /// # Arguments
     /// * `dx` - Desired X direction (-1 = left, 0 = none, 1 = right)
     /// * `dy` - Desired Y direction (-1 = up, 0 = none, 1 = down)
-    pub fn process_input(&mut self, dx: i32, dy: i32) {
+    /// * `cornering_window` - Ticks a turn may be late or early and still cut the corner
+    /// * `ice` - The maze is icy
+    pub fn process_input(&mut self, dx: i32, dy: i32, cornering_window: i32, ice: bool) {
         // Always update queued direction (for perpendicular turns)
         if dx != self.dx || dy != self.dy {
+            if dx != self.queued_dx || dy != self.queued_dy {
+                self.queued_ticks = 0;
+            }
             self.queued_dx = dx;
             self.queued_dy = dy;
+        } else if ice {
+            // Holding the way he slides calls off a turn waiting for a junction
+            self.queued_dx = 0;
+            self.queued_dy = 0;
+        }
+
+        if ice {
+            let standing = self.dx == 0 && self.dy == 0;
+            if standing && !is_wall(self.x + dx, self.y + dy) {
+                self.dx = dx;
+                self.dy = dy;
+                self.queued_dx = 0;
+                self.queued_dy = 0;
+            }
+            return;
         }
 
         // Check if we can change direction immediately
@@ -77,9 +133,39 @@ impl Player {
                 self.queued_dx = 0;
                 self.queued_dy = 0;
             }
+        } else if is_perpendicular_turn && self.can_post_turn(dx, dy, cornering_window) {
+            // Just missed the junction: back onto it, already heading the new way
+            self.x -= self.dx;
+            self.y -= self.dy;
+            self.dx = dx;
+            self.dy = dy;
+            self.queued_dx = 0;
+            self.queued_dy = 0;
         }
     }
 
+    /// Whether a turn missed at the junction just left can still be taken from it
+    ///
+    /// Pac-Man must have stepped off the junction less than `cornering_window`
+    /// ticks ago, in a straight line (not through the tunnel or a teleporter).
+    fn can_post_turn(&self, dx: i32, dy: i32, cornering_window: i32) -> bool {
+        let (junction_x, junction_y) = (self.x - self.dx, self.y - self.dy);
+        self.sub_frame_counter < cornering_window
+            && !is_teleporter(self.x, self.y)
+            && !is_wall(junction_x, junction_y)
+            && !is_wall(junction_x + dx, junction_y + dy)
+    }
+
+    /// Whether he may head (`dx`, `dy`) from his tile on ice: standing still,
+    /// going on the way he slides, or on a junction (a tile he can turn off at)
+    fn can_turn_on_ice(&self, dx: i32, dy: i32) -> bool {
+        if (self.dx == 0 && self.dy == 0) || (dx, dy) == (self.dx, self.dy) {
+            return true;
+        }
+        let (side_x, side_y) = (self.dy, self.dx);
+        !is_wall(self.x + side_x, self.y + side_y) || !is_wall(self.x - side_x, self.y - side_y)
+    }
+
     /// Updates the player's position each frame
     /// 
     /// This function:
@@ -89,39 +175,36 @@ impl Player {
     /// 4. Handles tunnel wrapping
     /// 5. Handles teleportation (if on a '1' tile)
     /// 6. Stops movement if hitting a wall
-    pub fn update(&mut self) {
+    /// 7. Takes a turn queued just before reaching a junction (a pre-turn)
+    /// 
+    /// On ice a queued turn is only taken on a junction, is kept until then,
+    /// and there are no pre-turns.
+    /// 
+    /// # Arguments
+    /// * `move_subframes` - Sub-frames per tile on the current level (see `level_table`)
+    /// * `cornering_window` - Ticks a turn may be early and still cut the corner
+    /// * `ice` - The maze is icy
+    pub fn update(&mut self, move_subframes: i32, cornering_window: i32, ice: bool) {
         // Increment sub-frame counter
         self.sub_frame_counter += 1;
         
         // Only move when enough sub-frames have passed (controls movement speed)
-        if self.sub_frame_counter >= PLAYER_MOVE_SUBFRAMES {
+        if self.sub_frame_counter >= move_subframes {
             self.sub_frame_counter = 0;
             
-            // Check queued direction when aligned (for perpendicular turns that were queued)
-            if self.queued_dx != 0 || self.queued_dy != 0 {
-                if !is_wall(self.x + self.queued_dx, self.y + self.queued_dy) {
-                    self.dx = self.queued_dx;
-                    self.dy = self.queued_dy;
-                    self.queued_dx = 0;
-                    self.queued_dy = 0;
-                } else {
-                    // Can't turn to queued direction, clear queue
-                    self.queued_dx = 0;
-                    self.queued_dy = 0;
-                }
+            // Check queued direction when aligned (for perpendicular turns that were queued);
+            // one that can't be taken here is kept until after the move, as a possible pre-turn
+            if (self.queued_dx != 0 || self.queued_dy != 0)
+                && !is_wall(self.x + self.queued_dx, self.y + self.queued_dy)
+                && (!ice || self.can_turn_on_ice(self.queued_dx, self.queued_dy)) {
+                self.dx = self.queued_dx;
+                self.dy = self.queued_dy;
+                self.queued_dx = 0;
+                self.queued_dy = 0;
             }
             
-            // Calculate new position
-            let mut new_x = self.x + self.dx;
-            let mut new_y = self.y + self.dy;
-            
-            // Handle tunnel wrapping: if on tunnel row and going off-screen, wrap to other side
-            if new_y == TUNNEL_ROW && new_x < 0 {
-                new_x = GRID_W - 1;
-            }
-            if new_y == TUNNEL_ROW && new_x >= GRID_W {
-                new_x = 0;
-            }
+            // Calculate new position, wrapping around through a tunnel
+            let (new_x, new_y) = wrap_tunnel(self.x + self.dx, self.y + self.dy);
             
             // Move if there's no wall, otherwise stop
             if !is_wall(new_x, new_y) {
@@ -135,98 +218,91 @@ impl Player {
                         self.y = teleport_y;
                     }
                 }
+
+                // Pre-turn: a turn input just before reaching this junction is taken
+                // now, and the ticks it was early count towards the next tile
+                let is_perpendicular = self.queued_dx * self.dx + self.queued_dy * self.dy == 0;
+                if cornering_window > 0
+                    && !ice
+                    && (self.queued_dx != 0 || self.queued_dy != 0)
+                    && is_perpendicular
+                    && self.queued_ticks <= cornering_window
+                    && !is_wall(self.x + self.queued_dx, self.y + self.queued_dy) {
+                    self.dx = self.queued_dx;
+                    self.dy = self.queued_dy;
+                    self.sub_frame_counter = self.queued_ticks;
+                }
             } else {
-                // Hit a wall, stop and clear queue
+                // Hit a wall, stop
                 self.dx = 0;
                 self.dy = 0;
+            }
+            // Whatever was queued has been taken or missed (on ice it waits for a junction)
+            if !ice {
                 self.queued_dx = 0;
                 self.queued_dy = 0;
             }
         }
+
+        if self.queued_dx != 0 || self.queued_dy != 0 {
+            self.queued_ticks += 1;
+        }
     }
     
     /// AI update for Pac-Man when controlled by computer
     /// 
-    /// Simple AI that:
-    /// 1. Tries to collect nearby pellets
-    /// 2. Avoids ghosts when not powered up
-    /// 3. Chases ghosts when powered up
+    /// Once he is aligned to a tile, turns the way the AI picks (see `pacman_ai`)
+    /// 
+    /// On ice he only takes the AI's turns where a player could (see `can_turn_on_ice`).
     /// 
     /// # Arguments
-    /// * `ghosts` - Array of ghosts to avoid/chase
-    /// * `power_pellet_active` - Whether power pellet is active
-    /// * `eaten` - Which pellets have been eaten
-    /// * `rng` - Random number generator for decision making
-    pub fn update_ai(&mut self, ghosts: &[(i32, i32, bool)], power_pellet_active: bool, 
-                     eaten: &[bool], rng: &mut crate::rng::Lfsr) {
-        use crate::maze::{is_pellet, is_wall};
-        use crate::constants::GRID_W;
-        
+    /// * `ai` - The AI playing him, from the game's `PacmanStyle`
+    /// * `view` - What the AI knows about the game (his own position and heading included)
+    /// * `ice` - The maze is icy
+    pub fn update_ai(&mut self, ai: &dyn PacmanAi, view: &PacmanView, ice: bool) {
         // Only make AI decisions when aligned to grid
         if self.sub_frame_counter != 0 {
             return;
         }
-        
-        // Check all possible directions
-        const DIRECTIONS: [(i32, i32); 4] = [
-            (0, -1),  // Up
-            (0, 1),   // Down
-            (-1, 0),  // Left
-            (1, 0),   // Right
-        ];
-        
-        let mut best_direction = (self.dx, self.dy);
-        let mut best_score = i32::MIN;
-        
-        for (dx, dy) in DIRECTIONS.iter() {
-            let new_x = self.x + dx;
-            let new_y = self.y + dy;
-            
-            // Skip if wall or reverse direction (unless stuck)
-            if is_wall(new_x, new_y) {
-                continue;
-            }
-            if *dx == -self.dx && *dy == -self.dy && (self.dx != 0 || self.dy != 0) {
-                continue;  // Don't reverse unless stuck
-            }
-            
-            let mut score = 0;
-            
-            // Score based on pellets nearby
-            let pellet_index = (new_y * GRID_W + new_x) as usize;
-            if pellet_index < eaten.len() && !eaten[pellet_index] && is_pellet(new_x, new_y) {
-                score += 100;  // High priority for uneaten pellets
-            }
-            
-            // Score based on ghost proximity
-            for (ghost_x, ghost_y, ghost_vulnerable) in ghosts {
-                let distance = (new_x - ghost_x).abs() + (new_y - ghost_y).abs();
-                
-                if power_pellet_active && *ghost_vulnerable {
-                    // Chase vulnerable ghosts
-                    score += (10 - distance).max(0);
-                } else if !power_pellet_active {
-                    // Avoid normal ghosts
-                    score -= (10 - distance).max(0) * 5;
-                }
-            }
-            
-            // Prefer continuing in current direction (slight bias)
-            if *dx == self.dx && *dy == self.dy {
-                score += 5;
-            }
-            
-            if score > best_score {
-                best_score = score;
-                best_direction = (*dx, *dy);
+        if let Some((dx, dy)) = ai.plan(view) {
+            if !ice || self.can_turn_on_ice(dx, dy) {
+                self.dx = dx;
+                self.dy = dy;
             }
         }
-        
-        // Apply best direction
-        if best_direction.0 != 0 || best_direction.1 != 0 {
-            self.dx = best_direction.0;
-            self.dy = best_direction.1;
+    }
+
+    /// Where he next has a choice of way, and the ways open there
+    ///
+    /// Looking ahead along his heading, that is the first tile where he can't
+    /// go on straight or can turn off, and the ways are listed straight on
+    /// first, then left, right and back. Standing still, it is his own tile
+    /// with every way open from it. Used by the one-button scheme (see `one_button`).
+    ///
+    /// # Arguments
+    /// * `skip_current` - Start looking from the tile ahead (the way out of his own tile is settled)
+    pub fn next_junction(&self, skip_current: bool) -> ((i32, i32), Vec<(i32, i32)>) {
+        let open = |x: i32, y: i32, (dx, dy): (i32, i32)| {
+            let (next_x, next_y) = wrap_tunnel(x + dx, y + dy);
+            !is_wall(next_x, next_y)
+        };
+        let (dx, dy) = (self.dx, self.dy);
+        if dx == 0 && dy == 0 {
+            let ways = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+            return ((self.x, self.y), ways.into_iter().filter(|&way| open(self.x, self.y, way)).collect());
+        }
+
+        let (mut x, mut y) = if skip_current { wrap_tunnel(self.x + dx, self.y + dy) } else { (self.x, self.y) };
+        let ways = [(dx, dy), (dy, -dx), (-dy, dx), (-dx, -dy)];
+        // A corridor that loops without a turn ends the search after a lap
+        for _ in 0..GRID_W * GRID_H {
+            let [straight, left, right, _] = ways.map(|way| open(x, y, way));
+            if !straight || left || right {
+                break;
+            }
+            (x, y) = wrap_tunnel(x + dx, y + dy);
         }
+        ((x, y), ways.into_iter().filter(|&way| open(x, y, way)).collect())
     }
 }
 
@@ -0,0 +1,52 @@
+//! Practice mode: instant savestates and drill aids
+//!
+//! Armed with F7 in the menu, like recording with F9. In a practice run F5
+//! keeps a copy of the whole `Game` in memory and F8 puts it back, so a
+//! tricky spot can be tried again and again without replaying the run up to
+//! it. F6 marks each ghost's target tile and F7 freezes the frightened timer.
+//!
+//! Practice runs are not real runs: they unlock no levels, can't be saved,
+//! aren't recorded and aren't rated.
+
+use crate::game::Game;
+
+/// State of a practice run kept outside the game itself
+#[derive(Default)]
+pub struct Practice {
+    /// The game as it was when F5 was last pressed
+    savestate: Option<Game>,
+    /// Whether the ghosts' target tiles are marked over the maze
+    pub show_targets: bool,
+}
+
+impl Practice {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keeps a copy of the game, replacing the previous savestate
+    pub fn save(&mut self, game: &Game) {
+        self.savestate = Some(game.clone());
+    }
+
+    /// Whether F5 has been pressed in this run
+    pub fn has_savestate(&self) -> bool {
+        self.savestate.is_some()
+    }
+
+    /// A copy of the saved game, to continue from
+    ///
+    /// The frightened timer stays frozen or running as it is now rather than
+    /// as it was when the state was saved.
+    ///
+    /// # Arguments
+    /// * `current` - The game being played
+    ///
+    /// # Returns
+    /// None if nothing has been saved yet
+    pub fn restore(&self, current: &Game) -> Option<Game> {
+        let mut game = self.savestate.clone()?;
+        game.power_frozen = current.power_frozen;
+        Some(game)
+    }
+}
@@ -0,0 +1,168 @@
+//! Frame-time profiling
+//!
+//! The main loop times two spans every frame: `Span::Tick` (all game ticks run
+//! that frame) and `Span::Draw` (building the frame, not counting the wait for
+//! vsync in `present`). The last `PROFILE_WINDOW` frames of each span are kept
+//! for rolling averages and percentiles, shown in the debug overlay (F3), and
+//! every frame can also be appended to a CSV file (`--profile <file>`) to
+//! compare runs before and after a change:
+//!
+//! ```text
+//! frame,ticks,tick_us,draw_us
+//! 0,1,41,212
+//! 1,1,39,198
+//! ```
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// Number of frames the rolling statistics cover (2 seconds at 60 FPS)
+pub const PROFILE_WINDOW: usize = 120;
+
+/// A timed part of the frame
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Span {
+    /// Every game tick run during the frame
+    Tick,
+    /// Drawing the frame (up to, not including, `present`)
+    Draw,
+}
+
+/// The last `PROFILE_WINDOW` durations of one span
+pub struct SpanStats {
+    samples: Vec<Duration>,
+    /// Where the next sample goes once the window is full
+    next: usize,
+}
+
+impl SpanStats {
+    fn new() -> Self {
+        Self { samples: Vec::with_capacity(PROFILE_WINDOW), next: 0 }
+    }
+
+    fn push(&mut self, duration: Duration) {
+        if self.samples.len() < PROFILE_WINDOW {
+            self.samples.push(duration);
+        } else {
+            self.samples[self.next] = duration;
+            self.next = (self.next + 1) % PROFILE_WINDOW;
+        }
+    }
+
+    /// Mean duration over the window
+    pub fn average(&self) -> Duration {
+        if self.samples.is_empty() {
+            return Duration::ZERO;
+        }
+        self.samples.iter().sum::<Duration>() / self.samples.len() as u32
+    }
+
+    /// Duration that `percent` percent of the frames in the window stayed under
+    ///
+    /// # Arguments
+    /// * `percent` - 0 to 100 (50 = median, 100 = worst frame)
+    pub fn percentile(&self, percent: u32) -> Duration {
+        let mut sorted = self.samples.clone();
+        sorted.sort_unstable();
+        let rank = (sorted.len() * percent.min(100) as usize).div_ceil(100);
+        sorted.get(rank.saturating_sub(1)).copied().unwrap_or(Duration::ZERO)
+    }
+}
+
+/// Rolling span statistics plus the optional CSV dump
+pub struct FrameProfiler {
+    pub tick: SpanStats,
+    pub draw: SpanStats,
+    /// Ticks run during the current frame
+    ticks: u32,
+    /// Time spent ticking during the current frame
+    frame_tick: Duration,
+    /// Time spent drawing during the current frame
+    frame_draw: Duration,
+    /// Frames finished so far
+    frame: u64,
+    csv: Option<BufWriter<File>>,
+}
+
+impl FrameProfiler {
+    pub fn new() -> Self {
+        Self {
+            tick: SpanStats::new(),
+            draw: SpanStats::new(),
+            ticks: 0,
+            frame_tick: Duration::ZERO,
+            frame_draw: Duration::ZERO,
+            frame: 0,
+            csv: None,
+        }
+    }
+
+    /// Starts appending one line per frame to a CSV file (replacing the file)
+    pub fn open_csv(&mut self, path: &Path) -> Result<(), String> {
+        let file = File::create(path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        let mut csv = BufWriter::new(file);
+        writeln!(csv, "frame,ticks,tick_us,draw_us")
+            .map_err(|e| format!("Could not write {}: {}", path.display(), e))?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    /// Records how long a span took this frame
+    ///
+    /// # Arguments
+    /// * `span` - Which part of the frame
+    /// * `duration` - Time it took
+    /// * `ticks` - For `Span::Tick`, how many ticks ran (ignored otherwise)
+    pub fn record(&mut self, span: Span, duration: Duration, ticks: u32) {
+        match span {
+            Span::Tick => {
+                self.tick.push(duration);
+                self.frame_tick = duration;
+                self.ticks = ticks;
+            }
+            Span::Draw => {
+                self.draw.push(duration);
+                self.frame_draw = duration;
+            }
+        }
+    }
+
+    /// Finishes the frame, writing its CSV line if a dump is open
+    pub fn end_frame(&mut self) {
+        if let Some(ref mut csv) = self.csv {
+            let line = writeln!(
+                csv,
+                "{},{},{},{}",
+                self.frame,
+                self.ticks,
+                self.frame_tick.as_micros(),
+                self.frame_draw.as_micros()
+            );
+            if let Err(e) = line {
+                eprintln!("Profile dump stopped: {}", e);
+                self.csv = None;
+            }
+        }
+        self.ticks = 0;
+        self.frame_tick = Duration::ZERO;
+        self.frame_draw = Duration::ZERO;
+        self.frame += 1;
+    }
+}
+
+impl Default for FrameProfiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for FrameProfiler {
+    fn drop(&mut self) {
+        if let Some(ref mut csv) = self.csv {
+            let _ = csv.flush();
+        }
+    }
+}
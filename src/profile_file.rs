@@ -0,0 +1,299 @@
+//! Portable profile files, to take a profile to another machine
+//!
+//! **Export Profile** on the Players page writes player 1's profile to
+//! `exports/<name>.profile` in the save directory: the settings (key
+//! bindings included), the levels unlocked, the time trial personal bests,
+//! the versus rating, the skin worn and the run history. **Import Profile** reads the first
+//! `.profile` file found in `imports/`, makes it player 1's profile and
+//! renames the file to `.imported` so the next import takes the next one.
+//!
+//! If this machine already knows a profile by that name, the player chooses
+//! between merging (keep the local settings, add the imported runs, keep the
+//! better of each personal best, the higher unlocked level and the rating
+//! with more games behind it, and the local skin if one was chosen here) and replacing (the file's data wins).
+//!
+//! The file is text, like the other saves:
+//!
+//! ```text
+//! paclike_profile 1
+//! name Alice
+//! highest_level 7
+//! rating 1032 5 3
+//! skin Blocky
+//! setting fullscreen off
+//! setting p1_up Up
+//! run single 35ab8bf4459695cf 3 12340 11020 412 9 2 Alice
+//! points 2370 200 8400 1000 370
+//! best 35ab8bf4459695cf 1834 13,23 12,23 11,23
+//! ```
+//!
+//! `setting` lines are the config file's (see `settings`), `run` and `points`
+//! lines the run history's (see `stats`), and a `best` line holds a maze's hash, the
+//! clear time in ticks and Pac-Man's tile on every tick. Files from a newer
+//! version of the game are refused rather than half read.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::progress::Progress;
+use crate::rating::{Rating, Ratings, clean_name};
+use crate::settings::Settings;
+use crate::skins::{SkinChoices, BUILT_IN_SKINS};
+use crate::stats::{History, RunStats, EXPORT_DIR};
+use crate::storage::{save_dir, save_path};
+use crate::time_trial::{BestRun, load_best, save_best, saved_bests};
+
+/// Version written to (and the newest read from) profile files
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Subdirectory of the save directory profiles are imported from
+const IMPORT_DIR: &str = "imports";
+
+/// Extension of profile files
+const PROFILE_EXTENSION: &str = "profile";
+
+/// What to do with a profile that already exists on this machine
+#[derive(Clone, Copy, PartialEq)]
+pub enum ImportMode {
+    /// Keep the best of both
+    Merge,
+    /// The imported profile wins
+    Replace,
+}
+
+/// Everything a profile takes along
+pub struct ProfileFile {
+    pub name: String,
+    pub settings: Settings,
+    /// Highest level unlocked for level select
+    pub highest_level: u32,
+    pub rating: Rating,
+    /// Name of the skin worn (see `skins`)
+    pub skin: String,
+    /// The profile's runs, oldest first
+    pub runs: Vec<RunStats>,
+    /// Time trial personal bests by maze hash
+    pub bests: Vec<(u64, BestRun)>,
+}
+
+impl ProfileFile {
+    /// Gathers a profile from this machine's saves
+    ///
+    /// # Arguments
+    /// * `name` - Profile name
+    /// * `settings` - The current settings
+    /// * `progress` - Levels unlocked
+    /// * `ratings` - Versus ratings
+    /// * `history` - Run history
+    /// * `skins` - Skins worn
+    pub fn gather(
+        name: &str, settings: &Settings, progress: &Progress, ratings: &Ratings, history: &History, skins: &SkinChoices
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            settings: *settings,
+            highest_level: progress.highest_level,
+            rating: ratings.get(name),
+            skin: skins.get(name).to_string(),
+            runs: history.runs_of(name).cloned().collect(),
+            bests: saved_bests().into_iter().filter_map(|maze| Some((maze, load_best(maze)?))).collect(),
+        }
+    }
+
+    /// The profile in the file format
+    pub fn to_text(&self) -> String {
+        let mut text = format!("paclike_profile {}\nname {}\n", PROFILE_VERSION, self.name);
+        text.push_str(&format!("highest_level {}\n", self.highest_level));
+        text.push_str(&format!("rating {} {} {}\n", self.rating.rating, self.rating.wins, self.rating.losses));
+        text.push_str(&format!("skin {}\n", self.skin));
+        for line in self.settings.to_text().lines() {
+            text.push_str(&format!("setting {}\n", line));
+        }
+        for run in &self.runs {
+            text.push_str(&run.to_line());
+            text.push('\n');
+        }
+        for (maze, best) in &self.bests {
+            let trace: Vec<String> = best.trace.iter().map(|(x, y)| format!("{},{}", x, y)).collect();
+            text.push_str(&format!("best {:016x} {} {}\n", maze, best.frames, trace.join(" ")));
+        }
+        text
+    }
+
+    /// Reads a profile in the file format
+    ///
+    /// # Returns
+    /// The profile, or an error if the text isn't a profile or is from a newer version
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let version = lines
+            .next()
+            .and_then(|line| line.strip_prefix("paclike_profile "))
+            .and_then(|version| version.trim().parse::<u32>().ok())
+            .ok_or("Not a profile file")?;
+        if version > PROFILE_VERSION {
+            return Err(format!(
+                "The profile is from a newer version of the game (profile version {}, this one reads up to {})",
+                version, PROFILE_VERSION
+            ));
+        }
+
+        let mut name = String::new();
+        let mut highest_level = 1;
+        let mut rating = Rating::default();
+        let mut skin = BUILT_IN_SKINS[0].to_string();
+        let mut settings_text = String::new();
+        let mut runs = Vec::new();
+        let mut bests = Vec::new();
+        for line in lines {
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => name = clean_name(value),
+                "highest_level" => highest_level = value.trim().parse::<u32>().unwrap_or(1).max(1),
+                "rating" => {
+                    let numbers: Vec<i64> = value.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+                    if let [points, wins, losses] = numbers[..] {
+                        rating = Rating { rating: points as i32, wins: wins.max(0) as u32, losses: losses.max(0) as u32 };
+                    }
+                }
+                "skin" if !value.trim().is_empty() => skin = value.trim().to_string(),
+                "setting" => {
+                    settings_text.push_str(value);
+                    settings_text.push('\n');
+                }
+                "run" => runs.extend(RunStats::parse(line)),
+                "points" => {
+                    if let Some(run) = runs.last_mut() {
+                        run.parse_points(value);
+                    }
+                }
+                "best" => bests.extend(parse_best(value)),
+                _ => {}
+            }
+        }
+        if name.is_empty() {
+            return Err("The profile has no name".to_string());
+        }
+        // Runs are the profile's own, whatever name they were saved under
+        for run in &mut runs {
+            run.player = name.clone();
+        }
+        Ok(Self { name, settings: Settings::from_text(&settings_text), highest_level, rating, skin, runs, bests })
+    }
+
+    /// Writes the profile to the export directory, replacing an earlier export of it
+    ///
+    /// # Returns
+    /// The path of the file
+    pub fn export(&self) -> Result<PathBuf, String> {
+        let path = save_path(EXPORT_DIR, &format!("{}.{}", self.name, PROFILE_EXTENSION))
+            .map_err(|e| format!("Could not export the profile: {}", e))?;
+        fs::write(&path, self.to_text())
+            .map_err(|e| format!("Could not export the profile to {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+
+    /// Loads a profile file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        Self::parse(&text).map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Whether this machine already knows a profile by this name (importing
+    /// then asks whether to merge or replace)
+    pub fn conflicts(&self, ratings: &Ratings, history: &History) -> bool {
+        ratings.rated().any(|(name, _)| name == self.name)
+            || history.runs_of(&self.name).next().is_some()
+    }
+
+    /// Takes the profile into this machine's saves and makes it player 1's
+    ///
+    /// # Arguments
+    /// * `mode` - How to treat what is already saved (a profile new to this
+    ///   machine comes out the same either way)
+    pub fn apply(
+        self,
+        mode: ImportMode,
+        settings: &mut Settings,
+        progress: &mut Progress,
+        ratings: &mut Ratings,
+        history: &mut History,
+        skins: &mut SkinChoices,
+    ) -> Result<(), String> {
+        if mode == ImportMode::Replace {
+            *settings = self.settings;
+            settings.save()?;
+        }
+
+        let highest_level = match mode {
+            ImportMode::Merge => progress.highest_level.max(self.highest_level),
+            ImportMode::Replace => self.highest_level,
+        };
+        if highest_level != progress.highest_level {
+            progress.highest_level = highest_level;
+            progress.save()?;
+        }
+
+        for (maze, best) in &self.bests {
+            let faster = load_best(*maze).map(|local| best.frames < local.frames).unwrap_or(true);
+            if mode == ImportMode::Replace || faster {
+                save_best(*maze, best).map_err(|e| format!("Could not save time trial record: {}", e))?;
+            }
+        }
+
+        let local = ratings.get(&self.name);
+        let games = |rating: Rating| rating.wins + rating.losses;
+        let rated_here = ratings.rated().any(|(name, _)| name == self.name);
+        let keep_local = mode == ImportMode::Merge && games(local) >= games(self.rating);
+        if !keep_local && (rated_here || games(self.rating) > 0) {
+            ratings.set(&self.name, self.rating);
+        }
+        ratings.names[0] = self.name.clone();
+        ratings.save()?;
+
+        if mode == ImportMode::Replace || skins.chosen(&self.name).is_none() {
+            skins.set(&self.name, &self.skin);
+            skins.save()?;
+        }
+
+        if mode == ImportMode::Replace {
+            history.runs.retain(|run| run.player != self.name);
+        }
+        for run in self.runs {
+            if !history.runs.contains(&run) {
+                history.runs.push(run);
+            }
+        }
+        history.save()
+    }
+}
+
+/// The first profile file waiting in the import directory, if any
+pub fn find_import() -> Option<PathBuf> {
+    let entries = fs::read_dir(save_dir().join(IMPORT_DIR)).ok()?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| path.extension().map(|extension| extension == PROFILE_EXTENSION).unwrap_or(false))
+        .collect();
+    paths.sort();
+    paths.into_iter().next()
+}
+
+/// Marks an imported file as done, so the next import takes the next one
+pub fn mark_imported(path: &Path) -> Result<(), String> {
+    fs::rename(path, path.with_extension("imported"))
+        .map_err(|e| format!("Could not rename {}: {}", path.display(), e))
+}
+
+/// Reads the value of a `best` line: maze hash, clear ticks, then `x,y` per tick
+fn parse_best(value: &str) -> Option<(u64, BestRun)> {
+    let mut fields = value.split_whitespace();
+    let maze = u64::from_str_radix(fields.next()?, 16).ok()?;
+    let frames = fields.next()?.parse().ok()?;
+    let trace = fields
+        .map(|field| {
+            let (x, y) = field.split_once(',')?;
+            Some((x.parse().ok()?, y.parse().ok()?))
+        })
+        .collect::<Option<Vec<(i32, i32)>>>()?;
+    Some((maze, BestRun { frames, trace }))
+}
@@ -0,0 +1,56 @@
+//! Progress kept between sessions: the levels unlocked for level select
+//!
+//! Reaching a level in a local game unlocks it, and the level select screen
+//! then offers every level up to the highest one reached. Saved as
+//! `progress.txt` in the save directory:
+//!
+//! ```text
+//! highest_level 7
+//! ```
+
+use std::fs;
+use crate::storage::save_dir;
+
+/// Name of the progress file in the save directory
+const PROGRESS_FILE: &str = "progress.txt";
+
+pub struct Progress {
+    /// Highest level reached in any local game (at least 1)
+    pub highest_level: u32,
+}
+
+impl Progress {
+    /// Loads the saved progress (only level 1 is unlocked without a file)
+    pub fn load() -> Self {
+        let highest_level = fs::read_to_string(save_dir().join(PROGRESS_FILE))
+            .ok()
+            .and_then(|text| {
+                text.lines()
+                    .find_map(|line| line.trim().strip_prefix("highest_level "))
+                    .and_then(|value| value.trim().parse().ok())
+            })
+            .unwrap_or(1);
+        Self { highest_level: highest_level.max(1) }
+    }
+
+    /// Unlocks a level that was just reached
+    ///
+    /// # Returns
+    /// True if it was not unlocked before (and the progress should be saved)
+    pub fn unlock(&mut self, level: u32) -> bool {
+        if level <= self.highest_level {
+            return false;
+        }
+        self.highest_level = level;
+        true
+    }
+
+    /// Writes the progress file
+    pub fn save(&self) -> Result<(), String> {
+        let dir = save_dir();
+        let path = dir.join(PROGRESS_FILE);
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, format!("highest_level {}\n", self.highest_level)))
+            .map_err(|e| format!("Could not save progress to {}: {}", path.display(), e))
+    }
+}
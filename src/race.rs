@@ -0,0 +1,69 @@
+//! Split-screen race: two local players, two mazes, one window
+//!
+//! Chosen as **Race** in the main menu. Each player gets a game of their own
+//! (`GameMode::Race`, played like single-player Pac-Man against AI ghosts),
+//! both started from the same state and seed, and the window is split in two
+//! viewports: player 1's game on the left, steered with player 1's keys and
+//! first controller, player 2's on the right with player 2's. The first to
+//! clear their maze wins; losing the last life loses the race.
+//!
+//! Deaths and cleared mazes don't freeze the boards as in other modes, since
+//! the other player is still racing. Player 1's game is the application's
+//! usual game, so pausing, quitting and the overlays work as in any run;
+//! `Race` holds everything player 2 adds.
+
+use sdl2::rect::Rect;
+use crate::game::{Game, GameEvent};
+use crate::render::RenderCache;
+
+/// Gap between the two viewports in pixels
+const DIVIDER_W: u32 = 4;
+
+/// Player 2's side of a race, and the result
+pub struct Race {
+    /// Player 2's game
+    pub rival: Game,
+    /// Player 2's game as it was at the start (for Restart Level)
+    pub rival_start: Game,
+    /// Layout and maze layer of player 2's viewport
+    pub rival_cache: RenderCache,
+    /// Set once the race is decided: the winning player (0 or 1)
+    pub winner: Option<usize>,
+}
+
+impl Race {
+    /// Starts a race; player 2's game begins as a copy of player 1's
+    pub fn new(game: &Game) -> Self {
+        Self { rival: game.clone(), rival_start: game.clone(), rival_cache: RenderCache::new(), winner: None }
+    }
+
+    /// Puts player 2's game back to the start and forgets the result
+    pub fn restart(&mut self) {
+        self.rival = self.rival_start.clone();
+        self.winner = None;
+    }
+
+    /// Looks through one tick's events of a player's game for the end of the race
+    ///
+    /// # Arguments
+    /// * `player` - Whose game raised them (0 or 1)
+    /// * `events` - The events `Game::tick` returned
+    pub fn check(&mut self, player: usize, events: &[GameEvent]) {
+        if self.winner.is_some() {
+            return;
+        }
+        for event in events {
+            match event {
+                GameEvent::LevelCleared { .. } => self.winner = Some(player),
+                GameEvent::GameOver => self.winner = Some(1 - player),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Left and right halves of the window, with a gap between them
+pub fn viewports(ww: u32, wh: u32) -> [Rect; 2] {
+    let half = ww.saturating_sub(DIVIDER_W) / 2;
+    [Rect::new(0, 0, half, wh), Rect::new((ww - half) as i32, 0, half, wh)]
+}
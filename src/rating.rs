@@ -0,0 +1,215 @@
+//! Elo-style ratings for Pac-Man-versus-ghost games
+//!
+//! Players are told apart by their profile name. Player 1 and player 2 each
+//! have one on this machine (set on the Players settings page), and online
+//! the peers swap names in the lobby. Every finished versus game, local or
+//! online, moves rating points from the loser to the winner: few when the
+//! favourite wins, more for an upset. Pac-Man wins by clearing at least one
+//! maze before the last life is lost; otherwise the ghost wins.
+//!
+//! Ratings are saved as `ratings.txt` in the save directory. Online, each
+//! side updates its own file for both players. The file looks like this:
+//!
+//! ```text
+//! p1 Alice
+//! p2 Bob
+//! player 1032 5 3 Alice
+//! player 968 3 5 Bob
+//! ```
+//!
+//! `player` lines hold the rating, wins and losses, then the name.
+
+use std::fs;
+use crate::game_config::PlayerRole;
+use crate::storage::save_dir;
+
+/// Name of the ratings file in the save directory
+const RATINGS_FILE: &str = "ratings.txt";
+
+/// Rating of a player who has not played a versus game yet
+pub const START_RATING: i32 = 1000;
+
+/// Most rating points a single game can move
+const K_FACTOR: f32 = 32.0;
+
+/// Longest profile name in characters
+pub const MAX_NAME_LEN: usize = 12;
+
+/// Profile names of player 1 and player 2 until they choose their own
+const DEFAULT_NAMES: [&str; 2] = ["Player 1", "Player 2"];
+
+/// One player's rating and record
+#[derive(Clone, Copy)]
+pub struct Rating {
+    pub rating: i32,
+    pub wins: u32,
+    pub losses: u32,
+}
+
+impl Default for Rating {
+    fn default() -> Self {
+        Self { rating: START_RATING, wins: 0, losses: 0 }
+    }
+}
+
+/// The two players of a versus game and, once it is over, how it went
+pub struct VersusMatch {
+    /// Profile name of the player steering Pac-Man
+    pub pacman: String,
+    /// Profile name of the player steering the ghost
+    pub ghost: String,
+    /// Mazes Pac-Man has cleared so far
+    pub mazes_cleared: u32,
+    /// Set when the game ends: the winning role and the rating points it moved
+    pub result: Option<(PlayerRole, i32)>,
+}
+
+impl VersusMatch {
+    pub fn new(pacman: &str, ghost: &str) -> Self {
+        Self { pacman: pacman.to_string(), ghost: ghost.to_string(), mazes_cleared: 0, result: None }
+    }
+
+    /// Whether the game counts towards the ratings (someone playing both sides doesn't)
+    pub fn is_rated(&self) -> bool {
+        self.pacman != self.ghost
+    }
+
+    /// The role that won a finished game
+    pub fn winner(&self) -> PlayerRole {
+        if self.mazes_cleared > 0 { PlayerRole::PacMan } else { PlayerRole::Ghost }
+    }
+}
+
+/// Every rated player's rating, and the profile names of the local players
+pub struct Ratings {
+    /// Profile names of player 1 and player 2 (player 1 is also the name used online)
+    pub names: [String; 2],
+    players: Vec<(String, Rating)>,
+}
+
+impl Default for Ratings {
+    fn default() -> Self {
+        Self { names: DEFAULT_NAMES.map(String::from), players: Vec::new() }
+    }
+}
+
+impl Ratings {
+    /// Loads the ratings file (nobody is rated and the default names are used without one)
+    pub fn load() -> Self {
+        let mut ratings = Ratings::default();
+        let Ok(text) = fs::read_to_string(save_dir().join(RATINGS_FILE)) else {
+            return ratings;
+        };
+        for line in text.lines() {
+            let (key, value) = line.trim().split_once(' ').unwrap_or((line.trim(), ""));
+            match key {
+                "p1" | "p2" => {
+                    let name = clean_name(value);
+                    if !name.is_empty() {
+                        ratings.names[(key == "p2") as usize] = name;
+                    }
+                }
+                "player" => {
+                    let mut fields = value.splitn(4, ' ');
+                    let mut number = || fields.next().and_then(|field| field.parse::<i64>().ok());
+                    let (Some(rating), Some(wins), Some(losses)) = (number(), number(), number()) else { continue };
+                    let name = clean_name(fields.next().unwrap_or(""));
+                    if !name.is_empty() {
+                        let rating = Rating { rating: rating as i32, wins: wins.max(0) as u32, losses: losses.max(0) as u32 };
+                        ratings.players.push((name, rating));
+                    }
+                }
+                _ => {}
+            }
+        }
+        ratings
+    }
+
+    /// Writes the ratings file
+    pub fn save(&self) -> Result<(), String> {
+        let mut text = format!("p1 {}\np2 {}\n", self.name(0), self.name(1));
+        for (name, rating) in &self.players {
+            text.push_str(&format!("player {} {} {} {}\n", rating.rating, rating.wins, rating.losses, name));
+        }
+        let dir = save_dir();
+        let path = dir.join(RATINGS_FILE);
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|e| format!("Could not save ratings to {}: {}", path.display(), e))
+    }
+
+    /// Profile name of player 1 (0) or player 2 (1), the default one while it is blank
+    pub fn name(&self, player: usize) -> &str {
+        match self.names.get(player).map(|name| name.trim()) {
+            Some(name) if !name.is_empty() => name,
+            _ => DEFAULT_NAMES[player.min(1)],
+        }
+    }
+
+    /// A player's rating and record (the starting rating for someone new)
+    pub fn get(&self, name: &str) -> Rating {
+        self.players.iter().find(|(player, _)| player == name).map(|(_, rating)| *rating).unwrap_or_default()
+    }
+
+    /// Everyone who has played a rated game, with their rating and record
+    pub fn rated(&self) -> impl Iterator<Item = (&str, Rating)> {
+        self.players.iter().map(|(name, rating)| (name.as_str(), *rating))
+    }
+
+    /// Sets a player's rating and record, e.g. from an imported profile
+    pub fn set(&mut self, name: &str, rating: Rating) {
+        *self.get_mut(name) = rating;
+    }
+
+    fn get_mut(&mut self, name: &str) -> &mut Rating {
+        let index = match self.players.iter().position(|(player, _)| player == name) {
+            Some(index) => index,
+            None => {
+                self.players.push((name.to_string(), Rating::default()));
+                self.players.len() - 1
+            }
+        };
+        &mut self.players[index].1
+    }
+
+    /// Moves rating points from the loser of a game to its winner
+    ///
+    /// # Returns
+    /// The number of points moved
+    pub fn record_game(&mut self, winner: &str, loser: &str) -> i32 {
+        let points = points_won(self.get(winner).rating, self.get(loser).rating);
+        let winner = self.get_mut(winner);
+        winner.rating += points;
+        winner.wins += 1;
+        let loser = self.get_mut(loser);
+        loser.rating -= points;
+        loser.losses += 1;
+        points
+    }
+}
+
+/// Rating points the winner of a game takes from the loser (at least one)
+///
+/// # Arguments
+/// * `winner` - Winner's rating before the game
+/// * `loser` - Loser's rating before the game
+pub fn points_won(winner: i32, loser: i32) -> i32 {
+    let expected = 1.0 / (1.0 + 10f32.powf((loser - winner) as f32 / 400.0));
+    ((K_FACTOR * (1.0 - expected)).round() as i32).max(1)
+}
+
+/// Keeps the characters a profile name may use (letters, digits, spaces,
+/// '-' and '_') and cuts it to `MAX_NAME_LEN`
+pub fn clean_name(name: &str) -> String {
+    name.chars()
+        .filter(|&ch| is_name_char(ch))
+        .take(MAX_NAME_LEN)
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
+
+/// Whether a character may be part of a profile name
+pub fn is_name_char(ch: char) -> bool {
+    ch.is_ascii_alphanumeric() || ch == ' ' || ch == '-' || ch == '_'
+}
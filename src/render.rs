@@ -5,14 +5,45 @@
 //! - Game elements (maze, player, ghosts, pellets)
 //! - Sprite rendering (Pac-Man and ghosts)
 
+use std::fmt::{self, Write};
 use sdl2::pixels::Color;
 use sdl2::rect::Rect;
+use sdl2::render::Texture;
 use crate::constants::{
-    GRID_W, GRID_H, TILE, VIEW_W, VIEW_H, SCORE_AREA,
+    GRID_W, GRID_H, TILE, VIEW_W, VIEW_H, SCORE_AREA, FPS,
     PACMAN_MOUTH_ANIMATION_SPEED, GHOST_WAVE_ANIMATION_SPEED,
-    POWER_PELLET_FLASH_SPEED, POWER_PELLET_FLASH_START
+    POWER_PELLET_FLASH_SPEED, MAZE_PALETTES, MazePalette
 };
-use crate::maze::get_maze;
+use crate::fruit::{Fruit, FruitSprite, BOMB_SPRITE, DOUBLE_POINTS_SPRITE};
+use crate::game::{Game, GamePhase, OpenWall};
+use crate::maze::{floor_count, get_maze, set_active_floor, spawn_points};
+use crate::renderer::Renderer;
+use crate::emote::Emote;
+use crate::font::{digits_width, draw_digits, text_width, Align, GLYPH_H};
+use crate::ui;
+use crate::touch::{TouchButton, TouchLayout};
+use crate::skins::{Rgb, Skin};
+use std::rc::Rc;
+
+/// Pac-Man color for each player (yellow for player 1, orange for player 2)
+pub const PLAYER_COLORS: [Color; 2] = [Color::RGB(255, 255, 0), Color::RGB(255, 140, 0)];
+
+/// What `draw_game` needs to know to draw one ghost
+pub struct GhostView {
+    pub x: i32,
+    pub y: i32,
+    pub vulnerable: bool,
+    /// Direction the eyes look (see `Ghost::look_direction`)
+    pub look: (i32, i32),
+}
+
+/// Body color of each ghost, in ghost order (red, pink, cyan, orange)
+pub const GHOST_COLORS: [Color; 4] = [
+    Color::RGB(255, 0, 0),
+    Color::RGB(255, 184, 255),
+    Color::RGB(0, 255, 255),
+    Color::RGB(255, 184, 82),
+];
 
 pub struct RenderCache {
     pub scale: f32,
@@ -21,6 +52,55 @@ pub struct RenderCache {
     pub game_start_y: i32,
     pub sw: i32,
     pub window_size_changed: bool,
+    /// Part of the window the game is laid out in (None for all of it; one
+    /// side each in a split-screen race, each side with its own cache)
+    area: Option<Rect>,
+    /// Walls and pellets pre-rendered at the current scale (see `draw_maze_layer`)
+    maze_texture: Option<Texture>,
+    /// Which maze the texture shows (address of the maze rows)
+    maze_texture_id: usize,
+    /// Size of the texture in pixels
+    maze_texture_size: (u32, u32),
+    /// Palette the texture was drawn with (index into MAZE_PALETTES)
+    maze_texture_palette: usize,
+    /// Eaten pellets as of the last frame (see `sync_pellets`)
+    last_eaten: Vec<bool>,
+    /// Pellet tiles eaten since the last frame, to paint over in the texture
+    dirty_pellets: Vec<usize>,
+    /// Set when pellets were restored (new level, turn swap): the texture is rebuilt
+    pellets_reset: bool,
+    /// Power pellet tiles of the current maze (they flash, so they aren't in the texture)
+    power_pellet_tiles: Vec<(i32, i32)>,
+    /// Stair tiles of the current maze (a two-floor maze's, drawn on top like the power pellets)
+    stair_tiles: Vec<(i32, i32)>,
+    /// Wall rectangles of the current maze in maze layer pixels, built once
+    /// per maze and scale (see `sync_walls`)
+    wall_rects: Vec<Rect>,
+    /// Maze (address of its rows) and scale `wall_rects` was built for
+    wall_rects_key: (usize, u32),
+    /// Blown-up walls as of the last frame (see `sync_walls`)
+    open_walls: Vec<(i32, i32)>,
+    /// Wall tiles blown up or healed since the last frame, to patch in the texture
+    dirty_walls: Vec<(i32, i32)>,
+    /// Rectangles of the pellets left, refilled in place whenever the maze is drawn
+    pellet_rects: Vec<Rect>,
+    /// Set when render targets are unsupported, so the maze is drawn tile by tile
+    maze_texture_failed: bool,
+    /// Photosensitive mode: power pellets, frightened ghosts and the
+    /// frightened time bar keep one color
+    pub photosensitive: bool,
+    /// Percent of the window's resolution the game is drawn at (see `draw_scaled`)
+    pub render_scale: u32,
+    /// Maze colors kept for every level (index into MAZE_PALETTES; None changes them by level)
+    pub maze_colors: Option<usize>,
+    /// Skins of player 1 and player 2 (the ghosts wear player 1's)
+    pub skins: [Rc<Skin>; 2],
+    /// The game drawn at the render scale, before it is stretched over the window
+    frame_texture: Option<Texture>,
+    /// Size of the frame texture in pixels
+    frame_texture_size: (u32, u32),
+    /// Set when the frame texture can't be made, so the game is drawn at full resolution
+    frame_texture_failed: bool,
 }
 
 impl RenderCache {
@@ -32,24 +112,264 @@ impl RenderCache {
             game_start_y: 0,
             sw: 0,
             window_size_changed: true,
+            area: None,
+            maze_texture: None,
+            maze_texture_id: 0,
+            maze_texture_size: (0, 0),
+            maze_texture_palette: 0,
+            last_eaten: Vec::new(),
+            dirty_pellets: Vec::with_capacity(16),
+            pellets_reset: true,
+            power_pellet_tiles: Vec::with_capacity(8),
+            stair_tiles: Vec::new(),
+            wall_rects: Vec::new(),
+            wall_rects_key: (0, 0),
+            open_walls: Vec::new(),
+            dirty_walls: Vec::new(),
+            pellet_rects: Vec::new(),
+            maze_texture_failed: false,
+            photosensitive: false,
+            render_scale: 100,
+            maze_colors: None,
+            skins: [Rc::new(Skin::default()), Rc::new(Skin::default())],
+            frame_texture: None,
+            frame_texture_size: (0, 0),
+            frame_texture_failed: false,
         }
     }
 
+    /// Forces the layout to be recomputed on the next draw (window resized or video mode changed)
+    pub fn invalidate(&mut self) {
+        self.window_size_changed = true;
+    }
+
+    /// Lays the game out in part of the window from now on (None for all of it)
+    pub fn set_area(&mut self, area: Option<Rect>) {
+        if self.area != area {
+            self.area = area;
+            self.invalidate();
+        }
+    }
+
+    /// The part of the window the game is drawn in
+    pub fn area(&self, ww: u32, wh: u32) -> Rect {
+        self.area.unwrap_or_else(|| Rect::new(0, 0, ww, wh))
+    }
+
+    /// Compares the eaten pellets with the last frame's
+    /// 
+    /// Newly eaten pellets are painted over in the maze layer; if any came back
+    /// (new level, turn swap, new game) the layer is rebuilt.
+    fn sync_pellets(&mut self, eaten: &[bool]) {
+        if self.last_eaten.len() != eaten.len() {
+            self.pellets_reset = true;
+        } else {
+            for (index, (&now, &before)) in eaten.iter().zip(&self.last_eaten).enumerate() {
+                if now && !before {
+                    self.dirty_pellets.push(index);
+                } else if before && !now {
+                    self.pellets_reset = true;
+                }
+            }
+        }
+        self.last_eaten.clear();
+        self.last_eaten.extend_from_slice(eaten);
+    }
+
+    /// Forgets cached textures after the renderer was recreated
+    /// 
+    /// The old renderer already freed them, so they must not be destroyed again.
+    pub fn drop_textures(&mut self) {
+        // With `unsafe_textures`, dropping a texture handle does not free it
+        self.maze_texture = None;
+        self.maze_texture_failed = false;
+        self.frame_texture = None;
+        self.frame_texture_failed = false;
+        self.invalidate();
+    }
+
     pub fn update_cache(&mut self, ww: i32, wh: i32) {
         if self.window_size_changed {
+            let area = self.area(ww as u32, wh as u32);
+            let (aw, ah) = (area.width() as i32, area.height() as i32);
             let total_view_h = VIEW_H + SCORE_AREA;
-            let sx = ww as f32 / VIEW_W as f32;
-            let sy = wh as f32 / total_view_h as f32;
+            let sx = aw as f32 / VIEW_W as f32;
+            let sy = ah as f32 / total_view_h as f32;
             self.scale = sx.min(sy);
             self.sw = (VIEW_W as f32 * self.scale).floor() as i32;
             let total_sh = (total_view_h as f32 * self.scale).floor() as i32;
-            self.ox = (ww - self.sw) / 2;
-            self.oy = (wh - total_sh) / 2;
+            self.ox = area.x() + (aw - self.sw) / 2;
+            self.oy = area.y() + (ah - total_sh) / 2;
             let score_area_scaled = (SCORE_AREA as f32 * self.scale).floor() as i32;
             self.game_start_y = self.oy + score_area_scaled;
             self.window_size_changed = false;
         }
     }
+
+    /// Converts game pixel coordinates (inside the maze) to a screen rectangle
+    pub fn to_screen(&self, x: i32, y: i32, w: i32, h: i32) -> Rect {
+        Placement { x: self.ox, y: self.game_start_y, scale: self.scale }.rect(x, y, w, h)
+    }
+
+    /// Size in pixels of the maze layer (the maze on screen, without the score area)
+    fn layer_size(&self) -> (u32, u32) {
+        (self.sw.max(1) as u32, ((VIEW_H as f32 * self.scale).floor() as u32).max(1))
+    }
+
+    /// Rebuilds the wall rectangles and finds the power pellets if the maze or
+    /// scale changed, or walls were blown up or healed since the last frame
+    ///
+    /// Blown-up and healed walls are also noted to patch in the maze layer.
+    fn sync_walls(&mut self, open_walls: &[OpenWall]) {
+        let key = (get_maze().as_ptr() as usize, self.scale.to_bits());
+        let maze_changed = self.wall_rects_key != key || self.wall_rects.is_empty();
+        let open_changed = open_walls.len() != self.open_walls.len()
+            || open_walls.iter().zip(&self.open_walls).any(|(wall, &before)| (wall.x, wall.y) != before);
+        if open_changed {
+            // Tiles in one list but not the other changed
+            for wall in open_walls {
+                if !self.open_walls.contains(&(wall.x, wall.y)) {
+                    self.dirty_walls.push((wall.x, wall.y));
+                }
+            }
+            for &(x, y) in &self.open_walls {
+                if !open_walls.iter().any(|wall| (wall.x, wall.y) == (x, y)) {
+                    self.dirty_walls.push((x, y));
+                }
+            }
+            self.open_walls.clear();
+            self.open_walls.extend(open_walls.iter().map(|wall| (wall.x, wall.y)));
+        }
+        if maze_changed || open_changed {
+            let layer = Placement { x: 0, y: 0, scale: self.scale };
+            collect_walls(&|x, y, w, h| layer.rect(x, y, w, h), &self.open_walls, &mut self.wall_rects);
+        }
+        if maze_changed {
+            find_tiles(b'*', &mut self.power_pellet_tiles);
+            find_tiles(b'S', &mut self.stair_tiles);
+            self.wall_rects_key = key;
+        }
+    }
+}
+
+/// Where maze pixels land on a render target: its origin and the scale
+#[derive(Clone, Copy)]
+struct Placement {
+    x: i32,
+    y: i32,
+    scale: f32,
+}
+
+impl Placement {
+    /// Converts game pixel coordinates (inside the maze) to a rectangle on the target
+    fn rect(&self, x: i32, y: i32, w: i32, h: i32) -> Rect {
+        let rx = self.x + ((x as f32) * self.scale) as i32;
+        let ry = self.y + ((y as f32) * self.scale) as i32;
+        let rw = (w as f32 * self.scale).ceil() as i32;
+        let rh = (h as f32 * self.scale).ceil() as i32;
+        Rect::new(rx, ry, rw as u32, rh as u32)
+    }
+}
+
+/// A HUD number formatted on the stack, so the HUD is drawn without allocating
+struct HudText {
+    bytes: [u8; 16],
+    len: usize,
+}
+
+impl HudText {
+    fn new(args: fmt::Arguments) -> Self {
+        let mut text = Self { bytes: [0; 16], len: 0 };
+        // Longer text is cut off; HUD numbers are far shorter
+        let _ = text.write_fmt(args);
+        text
+    }
+
+    fn as_str(&self) -> &str {
+        std::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl Write for HudText {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        if end > self.bytes.len() {
+            return Err(fmt::Error);
+        }
+        self.bytes[self.len..end].copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
+
+impl Drop for RenderCache {
+    fn drop(&mut self) {
+        // The canvas outlives the game, so the renderer still owns this texture
+        if let Some(texture) = self.maze_texture.take() {
+            unsafe { texture.destroy() };
+        }
+        if let Some(texture) = self.frame_texture.take() {
+            unsafe { texture.destroy() };
+        }
+    }
+}
+
+/// Draws the game at `cache.render_scale` percent of the window's resolution
+///
+/// `draw` draws in window coordinates as usual. Below 100% that lands in an
+/// intermediate texture the render scale's fraction of the window's size,
+/// which is then stretched over the window with nearest-neighbor filtering,
+/// so fewer pixels are filled each frame and the pixels stay square. At 100%,
+/// or where render targets are unsupported, `draw` draws straight to the window.
+pub fn draw_scaled<F>(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &mut RenderCache,
+    draw: F,
+) -> Result<(), String>
+where
+    F: FnOnce(&mut sdl2::render::Canvas<sdl2::video::Window>, &mut RenderCache) -> Result<(), String>,
+{
+    let (ww, wh) = canvas.window().size();
+    let percent = cache.render_scale.clamp(1, 100);
+    let size = ((ww * percent / 100).max(1), (wh * percent / 100).max(1));
+    if percent == 100 || cache.frame_texture_failed {
+        return draw(canvas, cache);
+    }
+
+    let mut texture = match cache.frame_texture.take() {
+        Some(texture) if cache.frame_texture_size == size => texture,
+        old => {
+            if let Some(old) = old {
+                unsafe { old.destroy() };
+            }
+            // Textures made from now on are stretched without smoothing
+            sdl2::hint::set("SDL_RENDER_SCALE_QUALITY", "nearest");
+            match canvas.texture_creator().create_texture_target(None, size.0, size.1) {
+                Ok(texture) => texture,
+                Err(e) => {
+                    eprintln!("Render scale unavailable, drawing at full resolution: {}", e);
+                    cache.frame_texture_failed = true;
+                    return draw(canvas, cache);
+                }
+            }
+        }
+    };
+
+    let mut result = Ok(());
+    let drawn = canvas.with_texture_canvas(&mut texture, |frame_canvas| {
+        frame_canvas.set_draw_color(Color::RGB(0, 0, 0));
+        frame_canvas.clear();
+        let _ = frame_canvas.set_scale(size.0 as f32 / ww as f32, size.1 as f32 / wh as f32);
+        result = draw(frame_canvas, cache);
+    });
+    cache.frame_texture = Some(texture);
+    cache.frame_texture_size = size;
+    drawn.map_err(|e| e.to_string())?;
+    result?;
+    if let Some(ref texture) = cache.frame_texture {
+        canvas.copy(texture, None, None)?;
+    }
+    Ok(())
 }
 
 pub fn draw_score(
@@ -59,246 +379,887 @@ pub fn draw_score(
     oy: i32,
     sw: i32,
 ) {
-    // Compact bitmask: each digit is 4x6, stored as 6 u8s (one per row)
-    const DIGITS: [[u8; 6]; 10] = [
-        [0b1111, 0b1001, 0b1001, 0b1001, 0b1001, 0b1111], // 0
-        [0b0010, 0b0110, 0b0010, 0b0010, 0b0010, 0b0111], // 1
-        [0b1111, 0b0001, 0b1111, 0b1000, 0b1000, 0b1111], // 2
-        [0b1111, 0b0001, 0b1111, 0b0001, 0b0001, 0b1111], // 3
-        [0b1001, 0b1001, 0b1111, 0b0001, 0b0001, 0b0001], // 4
-        [0b1111, 0b1000, 0b1111, 0b0001, 0b0001, 0b1111], // 5
-        [0b1111, 0b1000, 0b1111, 0b1001, 0b1001, 0b1111], // 6
-        [0b1111, 0b0001, 0b0001, 0b0001, 0b0001, 0b0001], // 7
-        [0b1111, 0b1001, 0b1111, 0b1001, 0b1001, 0b1111], // 8
-        [0b1111, 0b1001, 0b1111, 0b0001, 0b0001, 0b1111], // 9
-    ];
-    
-    let score_str = format!("{:06}", score);
-    let char_w = 4;
-    let spacing = 1;
+    let score_text = HudText::new(format_args!("{:06}", score));
+    let score_str = score_text.as_str();
     let pixel_size = 2;
-    let score_width = (score_str.len() as i32 * (char_w + spacing) * pixel_size) as i32;
-    let mut x_pos = ox + (sw - score_width) / 2;
-    let y_pos = oy + 5;
-    
-    canvas.set_draw_color(Color::RGB(255, 255, 255));
-    for ch in score_str.chars() {
-        if let Some(digit) = ch.to_digit(10).and_then(|d| DIGITS.get(d as usize)) {
-            for (row, &bits) in digit.iter().enumerate() {
-                for col in 0..char_w {
-                    if (bits >> (char_w - 1 - col)) & 1 != 0 {
-                        let _ = canvas.fill_rect(Rect::new(
-                            x_pos + (col as i32 * pixel_size),
-                            y_pos + (row as i32 * pixel_size),
-                            pixel_size as u32,
-                            pixel_size as u32,
-                        ));
-                    }
-                }
-            }
-            x_pos += (char_w + spacing) * pixel_size;
+    let score_width = digits_width(score_str, pixel_size);
+    draw_digits(canvas, score_str, ox + (sw - score_width) / 2, oy + 5, pixel_size, Color::RGB(255, 255, 255));
+}
+
+/// Draws both players' scores for alternating mode
+/// 
+/// Player 1's total sits on the left half of the score area and player 2's on
+/// the right. The player whose turn it is gets their Pac-Man color and an
+/// underline; the waiting player's total is dimmed.
+pub fn draw_turn_scores(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    scores: [i32; 2],
+    active: usize,
+) {
+    let pixel_size = 2;
+    for (i, score) in scores.iter().enumerate() {
+        let text = HudText::new(format_args!("{:06}", score));
+        let width = digits_width(text.as_str(), pixel_size);
+        let half_x = cache.ox + (cache.sw / 2) * i as i32;
+        let x = half_x + (cache.sw / 2 - width) / 2;
+        let color = if i == active { PLAYER_COLORS[i] } else { Color::RGB(100, 100, 100) };
+        draw_digits(canvas, text.as_str(), x, cache.oy + 5, pixel_size, color);
+        if i == active {
+            canvas.set_draw_color(color);
+            let _ = canvas.fill_rect(Rect::new(x, cache.oy + 5 + 7 * pixel_size, width as u32, 2));
         }
     }
 }
 
-pub fn draw_game(
+/// Formats a tick count as "M:SS.CC" (minutes, seconds, hundredths)
+fn format_time(frames: u32) -> HudText {
+    let hundredths = frames as u64 * 100 / FPS as u64;
+    HudText::new(format_args!("{}:{:02}.{:02}", hundredths / 6000, (hundredths / 100) % 60, hundredths % 100))
+}
+
+/// Draws the running time trial clock in the top-right of the score area
+pub fn draw_trial_timer(
     canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
-    cache: &mut RenderCache,
-    eaten: &[bool],
-    player_x: i32,
-    player_y: i32,
-    ghosts: &[(i32, i32, bool)],
-    power_pellet_timer: i32,
+    cache: &RenderCache,
+    frames: u32,
+) {
+    let text = format_time(frames);
+    let pixel_size = 2;
+    let x = cache.ox + cache.sw - digits_width(text.as_str(), pixel_size) - 4;
+    draw_digits(canvas, text.as_str(), x, cache.oy + 5, pixel_size, Color::RGB(255, 255, 0));
+}
+
+/// Draws an online match's ping, jitter and input delay at the right of the HUD
+/// 
+/// # Arguments
+/// * `ping_ms` - Round-trip time (None until the first one has been measured)
+/// * `jitter_ms` - Variation of the round-trip time
+/// * `input_delay` - Ticks between reading an input and applying it
+pub fn draw_net_quality(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    ping_ms: Option<u32>,
+    jitter_ms: u32,
+    input_delay: u32,
+) {
+    let ping = ping_ms.map(|ping| ping.to_string()).unwrap_or_else(|| "-".to_string());
+    let text = format!("Ping {} ms  Jitter {} ms  Delay {}", ping, jitter_ms, input_delay);
+    ui::label(canvas, &text, cache.ox + cache.sw - 4, cache.oy + 5, ui::HINT_COLOR, Align::Right);
+}
+
+/// Draws the practice keys in the top right corner, with the frightened timer's state
+pub fn draw_practice_status(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    has_savestate: bool,
+    power_frozen: bool,
+) {
+    let load = if has_savestate { "  F8 Load" } else { "" };
+    let frozen = if power_frozen { "  Power frozen" } else { "" };
+    let text = format!("Practice  F5 Save{}{}", load, frozen);
+    ui::label(canvas, &text, cache.ox + cache.sw - 4, cache.oy + 5, ui::HINT_COLOR, Align::Right);
+}
+
+/// Draws the fast-forward or slow-motion speed in the top right corner, under
+/// the practice keys
+///
+/// # Arguments
+/// * `speed` - How fast the game runs against real time
+pub fn draw_game_speed(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    speed: f64,
+) {
+    let text = if speed > 1.0 { format!(">> {}x", speed) } else { format!("> {}x", speed) };
+    ui::label(canvas, &text, cache.ox + cache.sw - 4, cache.oy + 5 + GLYPH_H + 4, ui::HINT_COLOR, Align::Right);
+}
+
+/// Draws an emote in a speech bubble just above a ghost's tile
+pub fn draw_emote(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    x: i32,
+    y: i32,
+    emote: Emote,
+) {
+    let text = emote.label();
+    let padding = 3;
+    let (w, h) = (text_width(text, 1) + 2 * padding, GLYPH_H + 2 * padding);
+    let tile = cache.to_screen(x * TILE, y * TILE, TILE, TILE);
+    let center_x = tile.x() + tile.width() as i32 / 2;
+    let bubble = Rect::new(center_x - w / 2, tile.y() - h - 2, w as u32, h as u32);
+    ui::panel(canvas, bubble, ui::TEXT_COLOR);
+    ui::label(canvas, text, center_x, bubble.y() + padding, ui::TEXT_COLOR, Align::Center);
+}
+
+/// Draws the one-button scheme's arrow on a junction, pointing the way chosen
+///
+/// # Arguments
+/// * `x`, `y` - The junction's tile
+/// * `(dx, dy)` - The way Pac-Man will take there
+pub fn draw_exit_marker(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    x: i32,
+    y: i32,
+    (dx, dy): (i32, i32),
+) {
+    // A shaft from the middle of the tile to its edge, and a head across its tip
+    let (cx, cy) = (x * TILE + TILE / 2, y * TILE + TILE / 2);
+    let shaft = if dx != 0 {
+        cache.to_screen(cx.min(cx + dx * TILE / 2), cy - 1, TILE / 2 + 1, 2)
+    } else {
+        cache.to_screen(cx - 1, cy.min(cy + dy * TILE / 2), 2, TILE / 2 + 1)
+    };
+    let (tip_x, tip_y) = (cx + dx * (TILE / 2), cy + dy * (TILE / 2));
+    let head = if dx != 0 {
+        cache.to_screen(tip_x - 1, tip_y - 2, 2, 4)
+    } else {
+        cache.to_screen(tip_x - 2, tip_y - 1, 4, 2)
+    };
+    canvas.set_draw_color(Color::RGBA(255, 255, 255, 200));
+    let _ = canvas.fill_rect(shaft);
+    let _ = canvas.fill_rect(head);
+}
+
+/// Draws the personal-best Pac-Man as a translucent racer
+pub fn draw_replay_ghost(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    x: i32,
+    y: i32,
     frame: u32,
-    alive: bool,
 ) -> Result<(), String> {
-    // Clear
-    canvas.set_draw_color(Color::RGB(0, 0, 0));
-    canvas.clear();
+    let to_screen = |x: i32, y: i32, w: i32, h: i32| cache.to_screen(x, y, w, h);
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    let result = draw_pacman(canvas, &to_screen, x * TILE, y * TILE, Color::RGBA(255, 255, 255, 90), frame, &cache.skins[0]);
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+    result
+}
 
-    // Update cache if needed
-    let (ww, wh) = canvas.window().size();
-    cache.update_cache(ww as i32, wh as i32);
+/// Draws the time trial results card: this run's time and the personal best
+/// 
+/// A new record is shown in green.
+pub fn draw_trial_results(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    frames: u32,
+    best_frames: Option<u32>,
+    new_record: bool,
+) {
+    let pixel_size = (cache.scale as i32).max(1);
+    let card_w = cache.sw * 2 / 3;
+    let card_h = 30 * pixel_size;
+    let card_x = cache.ox + (cache.sw - card_w) / 2;
+    let card_y = cache.game_start_y + (VIEW_H as f32 * cache.scale) as i32 / 2 - card_h / 2;
     
-    let s = cache.scale;
-    let ox = cache.ox;
-    let game_start_y = cache.game_start_y;
-
-    // Helper to convert game coordinates to screen coordinates
-    let to_screen = |x: i32, y: i32, w: i32, h: i32| -> Rect {
-        let rx = ox + ((x as f32) * s) as i32;
-        let ry = game_start_y + ((y as f32) * s) as i32;
-        let rw = (w as f32 * s).ceil() as i32;
-        let rh = (h as f32 * s).ceil() as i32;
-        Rect::new(rx, ry, rw as u32, rh as u32)
-    };
+    ui::panel(canvas, Rect::new(card_x, card_y, card_w as u32, card_h as u32), ui::HIGHLIGHT_COLOR);
+    
+    // This run's time, large
+    let time_text = format_time(frames);
+    let time_size = 2 * pixel_size;
+    let time_color = if new_record { Color::RGB(0, 255, 0) } else { Color::RGB(255, 255, 255) };
+    let time_x = card_x + (card_w - digits_width(time_text.as_str(), time_size)) / 2;
+    draw_digits(canvas, time_text.as_str(), time_x, card_y + 5 * pixel_size, time_size, time_color);
+    
+    // Personal best, smaller, below
+    if let Some(best_frames) = best_frames {
+        let best_text = format_time(best_frames);
+        let best_x = card_x + (card_w - digits_width(best_text.as_str(), pixel_size)) / 2;
+        draw_digits(canvas, best_text.as_str(), best_x, card_y + 20 * pixel_size, pixel_size, Color::RGB(255, 255, 0));
+    }
+}
+
+/// Draws the seconds left in the bonus stage in the top-right of the score area
+pub fn draw_bonus_timer(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    frames_left: i32,
+) {
+    let seconds = (frames_left + FPS as i32 - 1) / FPS as i32;
+    let text = HudText::new(format_args!("{:02}", seconds));
+    let pixel_size = 2;
+    let x = cache.ox + cache.sw - digits_width(text.as_str(), pixel_size) - 4;
+    let color = if seconds <= 5 { Color::RGB(255, 80, 80) } else { Color::RGB(255, 255, 0) };
+    draw_digits(canvas, text.as_str(), x, cache.oy + 5, pixel_size, color);
+}
+
+/// Draws the bonus stage results card over the maze
+/// 
+/// Shows a pellet icon with the number of pellets collected, and the
+/// points added to the run score below it.
+pub fn draw_bonus_results(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    pellets: i32,
+    haul: i32,
+) {
+    let pixel_size = (cache.scale as i32).max(1);
+    let card_w = cache.sw * 2 / 3;
+    let card_h = 30 * pixel_size;
+    let card_x = cache.ox + (cache.sw - card_w) / 2;
+    let card_y = cache.game_start_y + (VIEW_H as f32 * cache.scale) as i32 / 2 - card_h / 2;
+    
+    ui::panel(canvas, Rect::new(card_x, card_y, card_w as u32, card_h as u32), ui::HIGHLIGHT_COLOR);
+    
+    // Pellet icon followed by the pellet count
+    let count_text = HudText::new(format_args!("{}", pellets));
+    let icon_size = 2 * pixel_size;
+    let row_w = icon_size + 3 * pixel_size + digits_width(count_text.as_str(), pixel_size);
+    let row_x = card_x + (card_w - row_w) / 2;
+    let row_y = card_y + 5 * pixel_size;
+    canvas.set_draw_color(Color::RGB(255, 255, 255));
+    let _ = canvas.fill_rect(Rect::new(row_x, row_y + 2 * pixel_size, icon_size as u32, icon_size as u32));
+    draw_digits(canvas, count_text.as_str(), row_x + icon_size + 3 * pixel_size, row_y, pixel_size, Color::RGB(255, 255, 255));
+    
+    // Points added to the run score
+    let haul_text = HudText::new(format_args!("{}", haul));
+    let haul_size = 2 * pixel_size;
+    let haul_x = card_x + (card_w - digits_width(haul_text.as_str(), haul_size)) / 2;
+    draw_digits(canvas, haul_text.as_str(), haul_x, row_y + 10 * pixel_size, haul_size, Color::RGB(255, 255, 0));
+}
+
+/// Top of the pause symbol: a third of the way down the maze, leaving room for the pause menu below
+fn pause_symbol_top(cache: &RenderCache) -> i32 {
+    let maze_h = (VIEW_H as f32 * cache.scale) as i32;
+    cache.game_start_y + maze_h / 3 - (12.0 * cache.scale) as i32
+}
 
-    // Batch rendering - collect all rectangles first
-    let mut wall_rects = Vec::with_capacity(200);
-    let mut pellet_rects = Vec::with_capacity(300);
-    let mut power_pellet_rects_white = Vec::with_capacity(4);
-    let mut power_pellet_rects_cyan = Vec::with_capacity(4);
-
-    // Collect all rectangles
-    let maze = get_maze();
-    for y in 0..GRID_H {
-        let y_idx = y as usize;
-        if y_idx >= maze.len() {
-            continue;
-        }
-        let row = maze[y_idx];
-        if row.len() < GRID_W as usize {
-            continue;
-        }
-        let bytes = row.as_bytes();
-        for x in 0..GRID_W {
-            let x_idx = x as usize;
-            if x_idx >= bytes.len() {
-                continue;
+/// Where the pause menu starts, below the pause symbol and heading
+pub fn pause_menu_top(cache: &RenderCache) -> i32 {
+    pause_symbol_top(cache) + (24.0 * cache.scale) as i32 + 10 * GLYPH_H
+}
+
+/// Dims the maze and draws a pause symbol (two bars) with a heading under it
+/// 
+/// The pause menu itself is drawn by the application at `pause_menu_top`.
+pub fn draw_pause_overlay(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+) {
+    let maze_h = (VIEW_H as f32 * cache.scale) as i32;
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 160));
+    let _ = canvas.fill_rect(Rect::new(cache.ox, cache.game_start_y, cache.sw as u32, maze_h as u32));
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+    
+    let bar_w = (6.0 * cache.scale) as i32;
+    let bar_h = (24.0 * cache.scale) as i32;
+    let center_x = cache.ox + cache.sw / 2;
+    let top = pause_symbol_top(cache);
+    canvas.set_draw_color(Color::RGB(255, 255, 0));
+    let _ = canvas.fill_rect(Rect::new(center_x - bar_w * 3 / 2, top, bar_w as u32, bar_h as u32));
+    let _ = canvas.fill_rect(Rect::new(center_x + bar_w / 2, top, bar_w as u32, bar_h as u32));
+    
+    ui::heading(canvas, "PAUSED", center_x, top + bar_h + 4 * GLYPH_H);
+}
+
+/// Darkens the whole window with translucent black (under the menu over the attract demo)
+/// 
+/// # Arguments
+/// * `alpha` - Opacity of the black layer (0 = no change, 255 = black)
+pub fn dim_screen(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, alpha: u8) {
+    let (ww, wh) = canvas.window().size();
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, alpha));
+    let _ = canvas.fill_rect(Rect::new(0, 0, ww, wh));
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+}
+
+/// Draws the on-screen D-pad and pause button, translucent over the game
+///
+/// # Arguments
+/// * `layout` - Where the controls sit in the window
+/// * `held` - The D-pad arm being held, drawn brighter
+pub fn draw_touch_controls(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    layout: &TouchLayout,
+    held: Option<TouchButton>,
+) {
+    let to_rect = |(x, y, w, h): (i32, i32, i32, i32)| Rect::new(x, y, w as u32, h as u32);
+    canvas.set_blend_mode(sdl2::render::BlendMode::Blend);
+    let (cx, cy) = layout.center;
+    canvas.set_draw_color(Color::RGBA(255, 255, 255, 50));
+    let _ = canvas.fill_rect(to_rect((cx - layout.cell / 2, cy - layout.cell / 2, layout.cell, layout.cell)));
+    for arm in TouchButton::ARMS {
+        let alpha = if held == Some(arm) { 140 } else { 70 };
+        canvas.set_draw_color(Color::RGBA(255, 255, 255, alpha));
+        let _ = canvas.fill_rect(to_rect(layout.rect(arm)));
+    }
+
+    // The pause button: two bars on a square
+    let (x, y, size, _) = layout.rect(TouchButton::Pause);
+    canvas.set_draw_color(Color::RGBA(255, 255, 255, 70));
+    let _ = canvas.fill_rect(to_rect((x, y, size, size)));
+    let bar_w = (size / 6).max(1);
+    canvas.set_draw_color(Color::RGBA(0, 0, 0, 140));
+    let _ = canvas.fill_rect(to_rect((x + size / 2 - bar_w * 3 / 2, y + size / 4, bar_w, size / 2)));
+    let _ = canvas.fill_rect(to_rect((x + size / 2 + bar_w / 2, y + size / 4, bar_w, size / 2)));
+    canvas.set_blend_mode(sdl2::render::BlendMode::None);
+}
+
+/// Draws the remaining lives as small Pac-Man icons in the left of the score area
+pub fn draw_lives(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    lives: i32,
+) {
+    let icon_size = 8;
+    canvas.set_draw_color(PLAYER_COLORS[0]);
+    for i in 0..lives.max(0) {
+        let x = cache.ox + 4 + i * (icon_size + 3);
+        // Circle-ish icon with a wedge-shaped mouth facing right
+        let _ = canvas.fill_rect(Rect::new(x + 1, cache.oy + 5, (icon_size - 2) as u32, icon_size as u32));
+        let _ = canvas.fill_rect(Rect::new(x, cache.oy + 6, icon_size as u32, (icon_size - 2) as u32));
+    }
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    for i in 0..lives.max(0) {
+        let x = cache.ox + 4 + i * (icon_size + 3);
+        let _ = canvas.fill_rect(Rect::new(x + icon_size / 2, cache.oy + 5 + icon_size / 2 - 1, (icon_size / 2) as u32, 2));
+    }
+}
+
+/// Draws the score multiplier and the seconds it has left as a badge (e.g.
+/// "x2 7") just right of the score
+///
+/// # Arguments
+/// * `score_right` - Right edge of the score's digits
+/// * `ticks_left` - Ticks the multiplier has left, at `FPS`
+pub fn draw_multiplier_badge(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    score_right: i32,
+    multiplier: i32,
+    ticks_left: i32,
+) {
+    let seconds = (ticks_left + FPS as i32 - 1) / FPS as i32;
+    let text = HudText::new(format_args!("x{} {}", multiplier, seconds));
+    let padding = 2;
+    let badge = Rect::new(
+        score_right + 6,
+        cache.oy + 5,
+        (text_width(text.as_str(), 1) + 2 * padding) as u32,
+        (GLYPH_H + 2 * padding) as u32,
+    );
+    let (r, g, b) = DOUBLE_POINTS_SPRITE.body_color;
+    canvas.set_draw_color(Color::RGB(r, g, b));
+    let _ = canvas.fill_rect(badge);
+    ui::label(canvas, text.as_str(), badge.x() + padding, badge.y() + padding, Color::RGB(255, 255, 255), Align::Left);
+}
+
+/// Draws the last fruit eaten in the run under the lives, oldest on the left,
+/// then the bombs carried
+pub fn draw_fruit_history(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    fruits: &[Fruit],
+    bombs: i32,
+) {
+    let to_screen = |x: i32, y: i32, w: i32, h: i32| Rect::new(x, y, w as u32, h as u32);
+    let sprites = fruits.iter().map(|fruit| fruit.sprite()).chain((0..bombs.max(0)).map(|_| BOMB_SPRITE));
+    for (i, sprite) in sprites.enumerate() {
+        draw_fruit(canvas, &to_screen, cache.ox + 5 + i as i32 * 11, cache.oy + 16, &sprite);
+    }
+}
+
+/// Color of the frightened ghosts this frame: blue, flashing white when the
+/// power pellet is about to run out (steady blue in photosensitive mode)
+pub fn frightened_color(game: &Game, photosensitive: bool) -> Color {
+    if game.frightened_flash_white() && !photosensitive {
+        Color::RGB(255, 255, 255)
+    } else {
+        Color::RGB(0, 100, 255)
+    }
+}
+
+/// Draws the frightened time left as a bar under the score, shrinking to the left
+///
+/// The bar takes the frightened ghosts' color, so it flashes with them near
+/// the end (and is all there is to go by in photosensitive mode).
+///
+/// # Arguments
+/// * `remaining` - Share of the frightened time left, 0 to 1 (see `Game::power_remaining`)
+/// * `color` - The frightened ghosts' color this frame (see `frightened_color`)
+pub fn draw_power_bar(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    remaining: f32,
+    color: Color,
+) {
+    let full_w = cache.sw - 8;
+    let y = cache.game_start_y - 5;
+    canvas.set_draw_color(Color::RGB(40, 40, 40));
+    let _ = canvas.fill_rect(Rect::new(cache.ox + 4, y, full_w.max(1) as u32, 3));
+    let w = (full_w as f32 * remaining.clamp(0.0, 1.0)) as u32;
+    if w > 0 {
+        canvas.set_draw_color(color);
+        let _ = canvas.fill_rect(Rect::new(cache.ox + 4, y, w, 3));
+    }
+}
+
+/// Draws game frames on an SDL2 canvas
+/// 
+/// Borrows the canvas and the layout cache for one frame; both outlive it.
+/// Frames go to the window, or to a texture (see `draw_game_to_texture`).
+pub struct SdlRenderer<'a> {
+    pub canvas: &'a mut sdl2::render::Canvas<sdl2::video::Window>,
+    pub cache: &'a mut RenderCache,
+    /// Size of the texture being drawn into (None when drawing to the window)
+    target_size: Option<(u32, u32)>,
+}
+
+impl<'a> SdlRenderer<'a> {
+    pub fn new(canvas: &'a mut sdl2::render::Canvas<sdl2::video::Window>, cache: &'a mut RenderCache) -> Self {
+        Self { canvas, cache, target_size: None }
+    }
+
+    /// Size in pixels of what the frame is drawn on: the window or the target texture
+    pub fn surface_size(&self) -> (u32, u32) {
+        self.target_size.unwrap_or_else(|| self.canvas.window().size())
+    }
+}
+
+/// Draws a game frame into a texture instead of the window
+///
+/// The texture must have been made with `create_texture_target`. The game is
+/// laid out to fill it and drawn by the same code as in the window, so
+/// anything that shows a game in part of the screen (picture-in-picture
+/// replays, maze previews, one side of a split screen) can draw it into a
+/// texture and place that wherever it likes. Give every texture its own
+/// `RenderCache`: the cache holds the layout and the maze layer for one surface.
+pub fn draw_game_to_texture(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &mut RenderCache,
+    texture: &mut Texture,
+    game: &Game,
+) -> Result<(), String> {
+    let query = texture.query();
+    let size = (query.width, query.height);
+    cache.set_area(Some(Rect::new(0, 0, size.0, size.1)));
+    let mut result = Ok(());
+    canvas.with_texture_canvas(texture, |target| {
+        target.set_draw_color(Color::RGB(0, 0, 0));
+        target.clear();
+        result = game.draw(&mut SdlRenderer { canvas: target, cache, target_size: Some(size) });
+    }).map_err(|e| e.to_string())?;
+    result
+}
+
+impl Renderer for SdlRenderer<'_> {
+    /// Clears the game's part of the window, then blits the cached maze layer and the flashing power pellets
+    fn draw_maze(&mut self, game: &Game) -> Result<(), String> {
+        let (ww, wh) = self.surface_size();
+        let canvas = &mut *self.canvas;
+        let cache = &mut *self.cache;
+        canvas.set_draw_color(Color::RGB(0, 0, 0));
+        match cache.area {
+            Some(area) => {
+                let _ = canvas.fill_rect(area);
             }
-            let c = bytes[x_idx];
-            match c {
-                b'#' => {
-                    wall_rects.push(to_screen(x * TILE, y * TILE, TILE, TILE));
-                }
-                b'.' => {
-                    let idx = (y * GRID_W + x) as usize;
-                    if !eaten[idx] {
-                        pellet_rects.push(to_screen(
-                            x * TILE + TILE / 2 - 1,
-                            y * TILE + TILE / 2 - 1,
-                            2,
-                            2,
-                        ));
-                    }
-                }
-                b'*' => {
-                    let idx = (y * GRID_W + x) as usize;
-                    if !eaten[idx] {
-                        let flash = (frame / POWER_PELLET_FLASH_SPEED) % 2 == 0;
-                        let rect = to_screen(
-                            x * TILE + TILE / 2 - 2,
-                            y * TILE + TILE / 2 - 2,
-                            4,
-                            4,
-                        );
-                        if flash {
-                            power_pellet_rects_cyan.push(rect);
-                        } else {
-                            power_pellet_rects_white.push(rect);
-                        }
-                    }
+            None => canvas.clear(),
+        }
+
+        // Only the floor player 1 is on is shown
+        let floor = game.view_floor();
+        set_active_floor(floor);
+        let eaten = game.floor_eaten(floor);
+
+        // Update cache if needed (handles window resizing)
+        cache.update_cache(ww as i32, wh as i32);
+        cache.sync_pellets(eaten);
+
+        // Walls and pellets come from the cached maze layer; if render targets
+        // aren't available, they are drawn tile by tile instead
+        let palette = cache.maze_colors.filter(|&palette| palette < MAZE_PALETTES.len()).unwrap_or_else(|| level_palette(game.level));
+        cache.sync_walls(&game.open_walls);
+        if cache.maze_texture_failed || draw_maze_layer(canvas, cache, eaten, palette).is_err() {
+            let layer = Placement { x: 0, y: 0, scale: cache.scale };
+            collect_pellets(&|x, y, w, h| layer.rect(x, y, w, h), eaten, &mut cache.pellet_rects);
+            let (w, h) = cache.layer_size();
+            canvas.set_viewport(Rect::new(cache.ox, cache.game_start_y, w, h));
+            draw_maze_tiles(canvas, &cache.wall_rects, &cache.pellet_rects, palette);
+            canvas.set_viewport(None);
+            cache.dirty_pellets.clear();
+            cache.dirty_walls.clear();
+            cache.pellets_reset = false;
+        }
+
+        // Power pellets flash, so they are drawn on top every frame
+        let to_screen = |x: i32, y: i32, w: i32, h: i32| cache.to_screen(x, y, w, h);
+        let flash_frame = if cache.photosensitive { None } else { Some(game.animation_frame()) };
+        draw_power_pellets(canvas, &to_screen, &cache.power_pellet_tiles, eaten, flash_frame);
+        draw_stairs(canvas, &to_screen, &cache.stair_tiles, palette);
+
+        // Blown-up walls keep their outline until they heal
+        let (wall_r, wall_g, wall_b) = MAZE_PALETTES[palette].wall;
+        canvas.set_draw_color(Color::RGB(wall_r, wall_g, wall_b));
+        for wall in &game.open_walls {
+            let _ = canvas.draw_rect(to_screen(wall.x * TILE, wall.y * TILE, TILE, TILE));
+        }
+        Ok(())
+    }
+
+    /// Draws the Pac-Men, the ghosts, the time trial racer and the dimming after death
+    fn draw_entities(&mut self, game: &Game) -> Result<(), String> {
+        let (ww, wh) = self.surface_size();
+        let canvas = &mut *self.canvas;
+        let cache = &*self.cache;
+        let to_screen = |x: i32, y: i32, w: i32, h: i32| cache.to_screen(x, y, w, h);
+
+        // Personal-best racer, under the real Pac-Man
+        if let Some(ref trial) = game.time_trial {
+            if !trial.finished {
+                if let Some((ghost_x, ghost_y)) = trial.ghost_position() {
+                    draw_replay_ghost(canvas, cache, ghost_x, ghost_y, game.animation_frame())?;
                 }
-                _ => {}
             }
         }
+
+        // Everything on the floor that isn't shown is hidden with it; the
+        // pickups are all on the ground floor
+        let floor = game.view_floor();
+
+        // Bonus fruit, under whoever walks over it
+        if game.fruit_timer > 0 && floor == 0 {
+            let (fruit_x, fruit_y) = spawn_points().fruit;
+            draw_fruit(canvas, &to_screen, fruit_x * TILE, fruit_y * TILE, &game.level_fruit().sprite());
+        }
+        if game.double_points_pickup > 0 && floor == 0 {
+            let (pickup_x, pickup_y) = spawn_points().players[0];
+            draw_fruit(canvas, &to_screen, pickup_x * TILE, pickup_y * TILE, &DOUBLE_POINTS_SPRITE);
+        }
+        if game.bomb_pickup > 0 && floor == 0 {
+            let (bomb_x, bomb_y) = spawn_points().fruit;
+            draw_fruit(canvas, &to_screen, bomb_x * TILE, bomb_y * TILE, &BOMB_SPRITE);
+        }
+
+        // Players (Pac-Man sprite with animated mouth, one color per player, each in their skin)
+        for (i, player) in game.players.iter().enumerate().filter(|(_, player)| player.floor == floor) {
+            let slot = (i + game.turn) % PLAYER_COLORS.len();
+            let skin = &cache.skins[slot];
+            let player_color = skin.pacman_color(slot).map(rgb).unwrap_or(PLAYER_COLORS[slot]);
+            draw_pacman(canvas, &to_screen, player.x * TILE, player.y * TILE, player_color, game.animation_frame(), skin)?;
+        }
+
+        // Ghosts (sprite with eyes and body, in player 1's skin); the bonus
+        // stage and its results card are ghost-free
+        if game.phase == GamePhase::Playing {
+            let skin = &cache.skins[0];
+            for (i, ghost) in game.ghosts.iter().enumerate().filter(|(_, ghost)| ghost.floor == floor) {
+                let ghost_color = if ghost.vulnerable {
+                    frightened_color(game, cache.photosensitive)
+                } else {
+                    skin.ghost_color(i).map(rgb).unwrap_or(GHOST_COLORS[i % GHOST_COLORS.len()])
+                };
+                let view = GhostView {
+                    x: ghost.x,
+                    y: ghost.y,
+                    vulnerable: ghost.vulnerable,
+                    look: ghost.look_direction(),
+                };
+                draw_ghost(canvas, &to_screen, &view, ghost_color, game.animation_frame(), skin)?;
+            }
+        }
+
+        // Dead overlay
+        if !game.alive {
+            canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
+            let _ = canvas.fill_rect(cache.area(ww, wh));
+        }
+        Ok(())
     }
 
-    // Draw in batches
-    if !wall_rects.is_empty() {
-        canvas.set_draw_color(Color::RGB(0, 100, 255));
-        for rect in &wall_rects {
-            let _ = canvas.fill_rect(*rect);
+    /// Draws the score and lives, then the time trial, bonus stage and pause overlays
+    fn draw_hud(&mut self, game: &Game) -> Result<(), String> {
+        let canvas = &mut *self.canvas;
+        let cache = &*self.cache;
+
+        // Alternating mode shows both totals with the active player highlighted
+        if let Some(scores) = game.turn_scores() {
+            draw_turn_scores(canvas, cache, scores, game.turn);
+        } else {
+            draw_score(canvas, game.score(), cache.ox, cache.oy, cache.sw);
         }
+        draw_lives(canvas, cache, game.lives);
+        draw_fruit_history(canvas, cache, &game.fruits, game.bombs);
+        if floor_count() > 1 {
+            draw_floor_label(canvas, cache, game.view_floor());
+        }
+        if game.double_points_timer > 0 {
+            // Next to the score being played for (the active player's in alternating mode)
+            let score_w = digits_width(HudText::new(format_args!("{:06}", game.score())).as_str(), 2);
+            let score_right = match game.turn_scores() {
+                Some(_) => cache.ox + (cache.sw / 2) * game.turn as i32 + (cache.sw / 2 + score_w) / 2,
+                None => cache.ox + (cache.sw + score_w) / 2,
+            };
+            let ticks_left = game.config.base_ticks(game.double_points_timer as u32) as i32;
+            draw_multiplier_badge(canvas, cache, score_right, game.score_multiplier(), ticks_left);
+        }
+        if game.power_pellet_timer > 0 {
+            draw_power_bar(canvas, cache, game.power_remaining(), frightened_color(game, cache.photosensitive));
+        }
+
+        // Time trial clock and results
+        if let Some(ref trial) = game.time_trial {
+            if !trial.finished {
+                draw_trial_timer(canvas, cache, trial.frames);
+            } else {
+                let best_frames = trial.best.as_ref().map(|best| best.frames);
+                draw_trial_results(canvas, cache, trial.frames, best_frames, trial.new_record);
+            }
+        }
+
+        // Bonus stage countdown and results card
+        match game.phase {
+            GamePhase::BonusStage { timer } => draw_bonus_timer(canvas, cache, game.config.base_ticks(timer.max(0) as u32) as i32),
+            GamePhase::BonusResults { .. } => draw_bonus_results(canvas, cache, game.bonus_pellets, game.bonus_haul),
+            GamePhase::Playing => {}
+        }
+
+        if game.paused {
+            draw_pause_overlay(canvas, cache);
+        }
+        Ok(())
     }
-    
-    if !pellet_rects.is_empty() {
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for rect in &pellet_rects {
-            let _ = canvas.fill_rect(*rect);
+
+    /// Shows the frame in the window (a texture target needs no presenting)
+    fn present(&mut self) -> Result<(), String> {
+        if self.target_size.is_none() {
+            self.canvas.present();
         }
+        Ok(())
     }
-    
-    if !power_pellet_rects_white.is_empty() {
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        for rect in &power_pellet_rects_white {
-            let _ = canvas.fill_rect(*rect);
+}
+
+/// A skin's color as an SDL color
+fn rgb((r, g, b): Rgb) -> Color {
+    Color::RGB(r, g, b)
+}
+
+/// Index into MAZE_PALETTES for a level (1-based), cycling through the table
+fn level_palette(level: u32) -> usize {
+    (level.max(1) as usize - 1) % MAZE_PALETTES.len()
+}
+
+/// Draws the walls and the regular pellets left, given as maze layer rectangles
+/// 
+/// Used to fill the cached maze layer, and directly when it is unavailable.
+/// Power pellets are left out because they flash (see `draw_power_pellets`).
+fn draw_maze_tiles(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    walls: &[Rect],
+    pellets: &[Rect],
+    palette: usize,
+) {
+    let MazePalette { wall: (wall_r, wall_g, wall_b), pellet: (pellet_r, pellet_g, pellet_b), .. } = MAZE_PALETTES[palette];
+    canvas.set_draw_color(Color::RGB(wall_r, wall_g, wall_b));
+    let _ = canvas.fill_rects(walls);
+    canvas.set_draw_color(Color::RGB(pellet_r, pellet_g, pellet_b));
+    let _ = canvas.fill_rects(pellets);
+}
+
+/// Replaces `rects` with a rectangle per wall tile of the current maze, but
+/// for the `open` ones (blown up)
+fn collect_walls(to_rect: &dyn Fn(i32, i32, i32, i32) -> Rect, open: &[(i32, i32)], rects: &mut Vec<Rect>) {
+    rects.clear();
+    for (y, row) in get_maze().iter().enumerate().take(GRID_H as usize) {
+        for (x, &c) in row.as_bytes().iter().enumerate().take(GRID_W as usize) {
+            let (x, y) = (x as i32, y as i32);
+            if c == b'#' && !open.contains(&(x, y)) {
+                rects.push(to_rect(x * TILE, y * TILE, TILE, TILE));
+            }
         }
     }
-    
-    if !power_pellet_rects_cyan.is_empty() {
-        canvas.set_draw_color(Color::RGB(0, 255, 255));
-        for rect in &power_pellet_rects_cyan {
-            let _ = canvas.fill_rect(*rect);
+}
+
+/// Replaces `rects` with a rectangle per regular pellet not eaten yet
+fn collect_pellets(to_rect: &dyn Fn(i32, i32, i32, i32) -> Rect, eaten: &[bool], rects: &mut Vec<Rect>) {
+    rects.clear();
+    for (y, row) in get_maze().iter().enumerate().take(GRID_H as usize) {
+        for (x, &c) in row.as_bytes().iter().enumerate().take(GRID_W as usize) {
+            let (x, y) = (x as i32, y as i32);
+            if c == b'.' && !eaten[(y * GRID_W + x) as usize] {
+                rects.push(pellet_rect(to_rect, x, y));
+            }
         }
     }
+}
 
-    // Player (Pac-Man sprite with animated mouth)
-    draw_pacman(canvas, &to_screen, player_x * TILE, player_y * TILE, frame)?;
+/// Screen rectangle of the regular pellet on a tile
+fn pellet_rect(to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect, x: i32, y: i32) -> Rect {
+    to_screen(x * TILE + TILE / 2 - 1, y * TILE + TILE / 2 - 1, 2, 2)
+}
 
-    // Ghosts (sprite with eyes and body)
-    let ghost_colors = [Color::RGB(255, 0, 0), Color::RGB(255, 184, 255), Color::RGB(0, 255, 255)]; // Red, Pink, Cyan
-    for (i, (ghost_x, ghost_y, ghost_vulnerable)) in ghosts.iter().enumerate() {
-        let ghost_color = if *ghost_vulnerable {
-            if power_pellet_timer < POWER_PELLET_FLASH_START && (frame / GHOST_WAVE_ANIMATION_SPEED) % 2 == 0 {
-                Color::RGB(255, 255, 255) // White (flashing when about to expire)
-            } else {
-                Color::RGB(0, 100, 255) // Blue (vulnerable)
+/// Finds the tiles of one kind (like '*' for power pellets) on the active floor of the current maze
+fn find_tiles(tile: u8, tiles: &mut Vec<(i32, i32)>) {
+    tiles.clear();
+    for (y, row) in get_maze().iter().enumerate().take(GRID_H as usize) {
+        for (x, &c) in row.as_bytes().iter().enumerate().take(GRID_W as usize) {
+            if c == tile {
+                tiles.push((x as i32, y as i32));
             }
-        } else {
-            ghost_colors[i.min(2)]
-        };
-        draw_ghost(canvas, &to_screen, *ghost_x * TILE, *ghost_y * TILE, ghost_color, frame)?;
+        }
     }
+}
 
-    // Dead overlay
-    if !alive {
-        canvas.set_draw_color(Color::RGBA(0, 0, 0, 180));
-        let _ = canvas.fill_rect(Rect::new(0, 0, ww, wh));
+/// Draws the power pellets that haven't been eaten, alternating white and cyan
+///
+/// # Arguments
+/// * `frame` - Game frame the flashing follows (None: steady white, for photosensitive mode)
+fn draw_power_pellets(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect,
+    tiles: &[(i32, i32)],
+    eaten: &[bool],
+    frame: Option<u32>,
+) {
+    let flash = frame.map(|frame| (frame / POWER_PELLET_FLASH_SPEED) % 2 == 0).unwrap_or(false);
+    canvas.set_draw_color(if flash { Color::RGB(0, 255, 255) } else { Color::RGB(255, 255, 255) });
+    for &(x, y) in tiles {
+        if !eaten[(y * GRID_W + x) as usize] {
+            let _ = canvas.fill_rect(to_screen(x * TILE + TILE / 2 - 2, y * TILE + TILE / 2 - 2, 4, 4));
+        }
     }
+}
+
+/// Draws stairs as three steps climbing to the right, in the pellet color
+fn draw_stairs(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect,
+    tiles: &[(i32, i32)],
+    palette: usize,
+) {
+    let (r, g, b) = MAZE_PALETTES[palette].pellet;
+    canvas.set_draw_color(Color::RGB(r, g, b));
+    for &(x, y) in tiles {
+        for step in 0..3 {
+            let _ = canvas.fill_rect(to_screen(x * TILE + 1 + step * 2, y * TILE + TILE - 3 - step * 2, TILE - 2 - step * 2, 2));
+        }
+    }
+}
+
+/// Draws which floor of a two-floor maze is shown, at the right of the HUD under the top line
+pub fn draw_floor_label(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &RenderCache,
+    floor: usize,
+) {
+    let text = if floor == 0 { "Ground floor" } else { "Upper floor" };
+    ui::label(canvas, text, cache.ox + cache.sw - 4, cache.oy + 16, ui::HINT_COLOR, Align::Right);
+}
 
+/// Blits the cached maze layer (walls and pellets), rebuilding or patching it first if needed
+/// 
+/// The layer is rendered once per maze and window size into a texture the
+/// size of the maze on screen. Afterwards only the pellets eaten since the
+/// last frame (see `RenderCache::sync_pellets`) are painted over, and walls
+/// blown up or healed (see `RenderCache::sync_walls`) cleared or filled
+/// back in; if pellets come back it is rebuilt.
+/// 
+/// # Returns
+/// An error if render targets aren't supported (the caller then draws tiles directly)
+fn draw_maze_layer(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    cache: &mut RenderCache,
+    eaten: &[bool],
+    palette: usize,
+) -> Result<(), String> {
+    if cache.maze_texture_failed {
+        return Err("Maze layer unavailable".to_string());
+    }
+    
+    let maze_id = get_maze().as_ptr() as usize;
+    let size = cache.layer_size();
+    let layer = Placement { x: 0, y: 0, scale: cache.scale };
+    let to_texture = |x: i32, y: i32, w: i32, h: i32| layer.rect(x, y, w, h);
+    // Switching render targets resets the scale `draw_scaled` draws with
+    let (scale_x, scale_y) = canvas.scale();
+    
+    let stale = cache.maze_texture.is_none()
+        || cache.maze_texture_id != maze_id
+        || cache.maze_texture_size != size
+        || cache.maze_texture_palette != palette
+        || cache.pellets_reset;
+    
+    if stale {
+        if let Some(old) = cache.maze_texture.take() {
+            unsafe { old.destroy() };
+        }
+        let texture = canvas.texture_creator()
+            .create_texture_target(None, size.0, size.1)
+            .map_err(|e| e.to_string());
+        let mut texture = match texture {
+            Ok(texture) => texture,
+            Err(e) => {
+                eprintln!("Maze layer texture unavailable, drawing tiles directly: {}", e);
+                cache.maze_texture_failed = true;
+                return Err(e);
+            }
+        };
+        collect_pellets(&to_texture, eaten, &mut cache.pellet_rects);
+        let (walls, pellets) = (&cache.wall_rects, &cache.pellet_rects);
+        canvas.with_texture_canvas(&mut texture, |texture_canvas| {
+            texture_canvas.set_draw_color(Color::RGB(0, 0, 0));
+            texture_canvas.clear();
+            draw_maze_tiles(texture_canvas, walls, pellets, palette);
+        }).map_err(|e| e.to_string())?;
+        
+        cache.maze_texture = Some(texture);
+        cache.maze_texture_id = maze_id;
+        cache.maze_texture_size = size;
+        cache.maze_texture_palette = palette;
+    } else if let Some(ref mut texture) = cache.maze_texture {
+        // Paint over just the pellets eaten since the last frame
+        if !cache.dirty_pellets.is_empty() {
+            let dirty_pellets = &cache.dirty_pellets;
+            canvas.with_texture_canvas(texture, |texture_canvas| {
+                texture_canvas.set_draw_color(Color::RGB(0, 0, 0));
+                for &index in dirty_pellets {
+                    let (x, y) = (index as i32 % GRID_W, index as i32 / GRID_W);
+                    let _ = texture_canvas.fill_rect(pellet_rect(&to_texture, x, y));
+                }
+            }).map_err(|e| e.to_string())?;
+        }
+        // Clear blown-up walls and fill healed ones back in
+        if !cache.dirty_walls.is_empty() {
+            let (dirty_walls, open_walls) = (&cache.dirty_walls, &cache.open_walls);
+            let (wall_r, wall_g, wall_b) = MAZE_PALETTES[palette].wall;
+            canvas.with_texture_canvas(texture, |texture_canvas| {
+                for tile in dirty_walls {
+                    let open = open_walls.contains(tile);
+                    texture_canvas.set_draw_color(if open { Color::RGB(0, 0, 0) } else { Color::RGB(wall_r, wall_g, wall_b) });
+                    let _ = texture_canvas.fill_rect(to_texture(tile.0 * TILE, tile.1 * TILE, TILE, TILE));
+                }
+            }).map_err(|e| e.to_string())?;
+        }
+    }
+    cache.dirty_pellets.clear();
+    cache.dirty_walls.clear();
+    cache.pellets_reset = false;
+    canvas.set_scale(scale_x, scale_y)?;
+    
+    if let Some(ref texture) = cache.maze_texture {
+        canvas.copy(texture, None, Rect::new(cache.ox, cache.game_start_y, size.0, size.1))?;
+    }
     Ok(())
 }
 
-// Draw Pac-Man sprite (6x6 pixels with animated mouth)
-fn draw_pacman(
+/// Draws Pac-Man's sprite in a skin (6x6 pixels with animated mouth)
+pub fn draw_pacman(
     canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
     to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect,
     x: i32,
     y: i32,
+    color: Color,
     frame: u32,
+    skin: &Skin,
 ) -> Result<(), String> {
-    // Pac-Man sprite: 6x6 pixels
     // Mouth animation: 0=closed, 1=half, 2=open, 3=half (cycle)
     let mouth_frame = (frame / PACMAN_MOUTH_ANIMATION_SPEED) % 4;
+    let sprite = skin.pacman_frame(mouth_frame);
     
-    // Boolean array for Pac-Man (6x6) - true = yellow pixel
-    let sprite: [[bool; 6]; 6] = match mouth_frame {
-        0 => [
-            [false, true, true, true, true, false], // Closed mouth (circle)
-            [true, true, true, true, true, true],
-            [true, true, true, true, true, true],
-            [true, true, true, true, true, true],
-            [true, true, true, true, true, true],
-            [false, true, true, true, true, false],
-        ],
-        1 => [
-            [false, true, true, true, true, false], // Half open
-            [true, true, true, true, true, true],
-            [true, true, false, false, true, true],
-            [true, true, false, false, true, true],
-            [true, true, true, true, true, true],
-            [false, true, true, true, true, false],
-        ],
-        2 => [
-            [false, true, true, true, true, false], // Open
-            [true, true, true, true, true, true],
-            [true, false, false, false, false, true],
-            [false, false, false, false, false, false],
-            [true, true, true, true, true, true],
-            [false, true, true, true, true, false],
-        ],
-        _ => [
-            [false, true, true, true, true, false], // Half open
-            [true, true, true, true, true, true],
-            [true, true, false, false, true, true],
-            [true, true, false, false, true, true],
-            [true, true, true, true, true, true],
-            [false, true, true, true, true, false],
-        ],
-    };
-    
-    canvas.set_draw_color(Color::RGB(255, 255, 0));
+    canvas.set_draw_color(color);
     for (row, row_bits) in sprite.iter().enumerate() {
         for col in 0..6 {
             if row_bits[col] {
@@ -315,59 +1276,76 @@ fn draw_pacman(
     Ok(())
 }
 
-// Draw ghost sprite (6x6 pixels with eyes and wavy bottom)
-fn draw_ghost(
+/// Draws a bonus fruit's (or the double-points pickup's) 6x6 icon (see `Fruit::sprite`)
+pub fn draw_fruit(
     canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
     to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect,
     x: i32,
     y: i32,
+    sprite: &FruitSprite,
+) {
+    for (rows, (r, g, b)) in [(sprite.body, sprite.body_color), (sprite.detail, sprite.detail_color)] {
+        canvas.set_draw_color(Color::RGB(r, g, b));
+        for (row, &bits) in rows.iter().enumerate() {
+            for col in 0..6 {
+                if (bits >> (5 - col)) & 1 != 0 {
+                    let _ = canvas.fill_rect(to_screen(x + col, y + row as i32, 1, 1));
+                }
+            }
+        }
+    }
+}
+
+/// Draws a ghost's sprite in a skin (6x6 pixels with wavy bottom and eyes
+/// looking where it is heading)
+pub fn draw_ghost(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    to_screen: &dyn Fn(i32, i32, i32, i32) -> Rect,
+    ghost: &GhostView,
     color: Color,
     frame: u32,
+    skin: &Skin,
 ) -> Result<(), String> {
-    // Ghost sprite: 6x6 pixels
-    // Wavy bottom animation
+    let (x, y) = (ghost.x * TILE, ghost.y * TILE);
+
+    // The skirt's two frames take turns every GHOST_WAVE_ANIMATION_SPEED frames.
+    // The silhouette is the same in every state so a vulnerable ghost still
+    // reads as a ghost.
     let wave_frame = (frame / GHOST_WAVE_ANIMATION_SPEED) % 2;
-    
-    // Boolean array for ghost (6x6) - true = ghost body pixel
-    // Top: rounded head, middle: body, bottom: wavy
-    let sprite: [[bool; 6]; 6] = [
-        [false, true, true, true, true, false], // Row 0: rounded top
-        [true, true, true, true, true, true], // Row 1: top of body
-        [true, false, true, true, false, true], // Row 2: eyes area (will draw eyes separately)
-        [true, true, true, true, true, true], // Row 3: body
-        if wave_frame == 0 {
-            [true, true, false, false, true, true] // Row 4: wavy bottom (pattern 1)
-        } else {
-            [false, false, true, true, false, false] // Row 4: wavy bottom (pattern 2)
-        },
-        if wave_frame == 0 {
-            [true, true, false, false, true, true] // Row 5: wavy bottom (pattern 1)
-        } else {
-            [false, false, true, true, false, false] // Row 5: wavy bottom (pattern 2)
-        },
-    ];
+    let sprite = &skin.ghost[wave_frame as usize];
     
     canvas.set_draw_color(color);
     for (row, row_bits) in sprite.iter().enumerate() {
-        for col in 0..6 {
-            if row_bits[col] {
-                let pixel_size = 1;
-                let _ = canvas.fill_rect(to_screen(
-                    x + col as i32 * pixel_size,
-                    y + row as i32 * pixel_size,
-                    pixel_size,
-                    pixel_size,
-                ));
+        for (col, &filled) in row_bits.iter().enumerate() {
+            if filled {
+                let _ = canvas.fill_rect(to_screen(x + col as i32, y + row as i32, 1, 1));
             }
         }
     }
     
-    // Draw white eyes (skip if vulnerable)
-    if color != Color::RGB(0, 100, 255) && color != Color::RGB(255, 255, 255) {
-        canvas.set_draw_color(Color::RGB(255, 255, 255));
-        let eye_positions = [(1, 2), (4, 2)]; // Two eyes at row 2
-        for (ex, ey) in eye_positions.iter() {
-            let _ = canvas.fill_rect(to_screen(x + *ex, y + *ey, 1, 1));
+    if ghost.vulnerable {
+        // Frightened face: two dots and a zig-zag mouth, pale on blue / red on white
+        let face_color = if color == Color::RGB(255, 255, 255) {
+            Color::RGB(255, 0, 0)
+        } else {
+            Color::RGB(255, 184, 174)
+        };
+        canvas.set_draw_color(face_color);
+        for (fx, fy) in [(1, 2), (4, 2), (1, 4), (2, 3), (3, 4), (4, 3)] {
+            let _ = canvas.fill_rect(to_screen(x + fx, y + fy, 1, 1));
+        }
+    } else {
+        // 2x2 eye whites shifted toward the look direction, pupils on that side
+        let (look_x, look_y) = (ghost.look.0.signum(), ghost.look.1.signum());
+        let pupil_x = if look_x > 0 { 1 } else { 0 };
+        let pupil_y = if look_y < 0 { 0 } else { 1 };
+        for eye_x in [1, 3] {
+            let white_x = x + eye_x + look_x;
+            let white_y = y + 1 + look_y.max(0);
+            canvas.set_draw_color(Color::RGB(255, 255, 255));
+            let _ = canvas.fill_rect(to_screen(white_x, white_y, 2, 2));
+            canvas.set_draw_color(Color::RGB(33, 33, 255));
+            let _ = canvas.fill_rect(to_screen(white_x + pupil_x, white_y + pupil_y, 1, 1));
         }
     }
     
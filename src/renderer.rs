@@ -0,0 +1,28 @@
+//! Renderer abstraction
+//!
+//! `Game::draw` describes a frame through this trait, so the game logic never
+//! touches a drawing API. Implementations:
+//! - `render::SdlRenderer`: the SDL2 canvas of the desktop game
+//! - `soft_render::Framebuffer`: a plain pixel buffer, used by the browser and
+//!   libretro builds
+//!
+//! Each frame is drawn in order: maze, entities, HUD. Presenting is left to
+//! the caller so it can add its own layers (e.g. the debug overlay) or read
+//! the frame back (clips) before it is shown.
+
+use crate::game::Game;
+
+/// Something that can draw a game frame
+pub trait Renderer {
+    /// Clears the frame and draws the walls and the pellets not eaten yet
+    fn draw_maze(&mut self, game: &Game) -> Result<(), String>;
+
+    /// Draws the Pac-Men, the ghosts and anything else that moves
+    fn draw_entities(&mut self, game: &Game) -> Result<(), String>;
+
+    /// Draws the score, lives, timers and overlays (result cards, pause)
+    fn draw_hud(&mut self, game: &Game) -> Result<(), String>;
+
+    /// Shows the finished frame
+    fn present(&mut self) -> Result<(), String>;
+}
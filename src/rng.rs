@@ -1,6 +1,10 @@
 // Random number generator (LFSR - Linear Feedback Shift Register)
 // Tiny deterministic RNG - same sequence each run unless you change seed
 
+/// Seed used where none is given: recordings from before seeds were saved,
+/// and in place of zero (the LFSR never leaves zero)
+pub const DEFAULT_SEED: u16 = 0xACE1;
+
 #[derive(Clone, Copy)]
 pub struct Lfsr {
     s: u16,
@@ -8,7 +12,7 @@ pub struct Lfsr {
 
 impl Lfsr {
     pub fn new(seed: u16) -> Self {
-        Self { s: if seed == 0 { 0xACE1 } else { seed } }
+        Self { s: if seed == 0 { DEFAULT_SEED } else { seed } }
     }
     
     pub fn next(&mut self) -> u16 {
@@ -20,9 +24,105 @@ impl Lfsr {
         self.s
     }
     
+    pub fn state(&self) -> u16 {
+        self.s
+    }
+    
     pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
         let span = (hi - lo + 1) as u16;
         lo + (self.next() % span) as i32
     }
 }
 
+
+// Independent random streams, one per consumer, all derived from one seed.
+// Each consumer draws only from its own stream, so adding a new consumer (or
+// changing how often one draws) never shifts the numbers another one sees and
+// recordings keep playing back the same way across versions.
+//
+// Stream ids are part of the recording format: never renumber them, give a new
+// consumer the next free id instead.
+
+/// Stream id of the first ghost (ghost `i` uses `STREAM_GHOST + i`)
+const STREAM_GHOST: u16 = 0;
+/// Stream id of the AI-controlled Pac-Man
+const STREAM_PACMAN_AI: u16 = 16;
+/// Stream id of the bonus fruit spawner
+const STREAM_FRUIT: u16 = 17;
+/// Stream id of the bomb pickup spawner
+const STREAM_BOMB: u16 = 18;
+/// Stream id of the pellets respawned in new game plus
+const STREAM_RESPAWN: u16 = 19;
+
+/// The game's random streams, derived from one master seed
+#[derive(Clone, Copy)]
+pub struct RngStreams {
+    seed: u16,
+    /// One stream per ghost, by ghost index
+    pub ghosts: [Lfsr; 3],
+    /// Decisions of the AI-controlled Pac-Man
+    pub pacman_ai: Lfsr,
+    /// Where and when bonus fruit appears
+    pub fruit: Lfsr,
+    /// Whether a level gets a bomb pickup
+    pub bomb: Lfsr,
+    /// Which eaten pellets come back in new game plus
+    pub respawn: Lfsr,
+}
+
+impl RngStreams {
+    pub fn new(seed: u16) -> Self {
+        let seed = Lfsr::new(seed).state();
+        Self {
+            seed,
+            ghosts: [
+                derive(seed, STREAM_GHOST),
+                derive(seed, STREAM_GHOST + 1),
+                derive(seed, STREAM_GHOST + 2),
+            ],
+            pacman_ai: derive(seed, STREAM_PACMAN_AI),
+            fruit: derive(seed, STREAM_FRUIT),
+            bomb: derive(seed, STREAM_BOMB),
+            respawn: derive(seed, STREAM_RESPAWN),
+        }
+    }
+
+    /// The master seed the streams were derived from
+    pub fn seed(&self) -> u16 {
+        self.seed
+    }
+
+    /// Every stream, in stream id order
+    pub fn all(&self) -> impl Iterator<Item = &Lfsr> {
+        self.ghosts.iter().chain([&self.pacman_ai, &self.fruit, &self.bomb, &self.respawn])
+    }
+
+    /// Fingerprint of all stream states (changes whenever any stream is drawn from)
+    pub fn state(&self) -> u16 {
+        self.all().fold(0, |acc, stream| acc.rotate_left(5) ^ stream.state())
+    }
+}
+
+/// A seed from the system clock, different for every game
+///
+/// The browser has no system clock to read, so there it is `DEFAULT_SEED`
+/// (the page passes its own seed to `paclike_start`).
+pub fn clock_seed() -> u16 {
+    if cfg!(target_arch = "wasm32") {
+        return DEFAULT_SEED;
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos() as u16)
+        .unwrap_or(DEFAULT_SEED)
+}
+
+// Seeds stream `stream` from the master seed; the mixing (a 32-bit integer
+// hash) gives neighbouring ids unrelated seeds
+fn derive(seed: u16, stream: u16) -> Lfsr {
+    let mut z = ((seed as u32) << 16 | stream as u32).wrapping_mul(0x9E37_79B1);
+    z ^= z >> 15;
+    z = z.wrapping_mul(0x85EB_CA77);
+    z ^= z >> 13;
+    Lfsr::new((z ^ (z >> 16)) as u16)
+}
@@ -0,0 +1,456 @@
+//! Autosave: a run written to disk when the player quits in the middle of it
+//!
+//! Quitting a local game (from the pause menu or by closing the window) offers
+//! to save it, and the main menu then offers to continue it. The save holds
+//! everything `Game::tick` depends on, plus the maze itself, so a run on a
+//! `--maze` maze can be continued without the maze file. It is plain text:
+//!
+//! ```text
+//! paclike-save 4
+//! mode single
+//! player1 pacman
+//! player2 -
+//! cornering 2
+//! targeting arcade
+//! ghosts classic
+//! pacman greedy
+//! rate 60
+//! ice off
+//! ng_plus off
+//! seed ace1
+//! rng 5a3c 91e2 0b7d 44f0 c3a1 7e02 19d6
+//! frame 5120
+//! score 1230
+//! points 700 100 400 0 30
+//! lives 2
+//! level 3
+//! player 13 23 -1 0 2 0 0 0
+//! ghost 12 11 0 -1 1 4 0 13 23 0
+//! dots 30 12 0 - 45
+//! fruit 0 cherry strawberry
+//! double 0 312
+//! bomb 0 1 0
+//! open_wall 6 13 412
+//! eaten 0000110111...
+//! ---
+//! ############################
+//! #............##............#
+//! ...
+//! ```
+//!
+//! `points` splits the score by source, in the order of `ScoreSource::ALL`.
+//! In alternating mode the waiting player's score, lives, level, pellets and
+//! eaten pellets follow on a `waiting` line, with `waiting_points` and
+//! `waiting_fruit` lines after it.
+//!
+//! On a two-floor maze a `floors` line gives the floor of every Pac-Man and
+//! then every ghost (`floors 1 0 0 1`), `eaten` runs on through the upper
+//! floor, and the maze is written with its `===` line and upper floor.
+//!
+//! The same format carries online games: after a desync both peers swap their
+//! games in it, dump them under `saves/desync/` and may resync from the host's.
+//!
+//! Time trials are never saved, as their clock is the whole point, and neither
+//! are campaigns, whose stages come from files that may have changed since.
+
+use std::fs;
+use std::path::PathBuf;
+use crate::constants::{GRID_W, GRID_H, TICK_RATES};
+use crate::game::{Game, GamePhase, OpenWall, TurnState};
+use crate::fruit::Fruit;
+use crate::game_config::{GameConfig, GameMode, GhostPreset, PacmanStyle};
+use crate::maze::{floor_count, maze_text, parse_maze, set_current_maze};
+use crate::player::Player;
+use crate::rng::Lfsr;
+use crate::scoring::{Scoring, ScoreSource};
+use crate::storage::{save_dir, save_path};
+use crate::tas::{mode_name, parse_mode, role_name, parse_role, targeting_name, parse_targeting};
+
+/// Save subdirectory for online desync dumps
+const DESYNC_DIR: &str = "desync";
+
+/// First line of every save (bumped when the format changes)
+const SAVE_MAGIC: &str = "paclike-save 4";
+
+/// Name of the autosave in the save directory
+const AUTOSAVE_FILE: &str = "autosave.sav";
+
+/// Returns the path of the autosave (it may not exist)
+fn autosave_path() -> PathBuf {
+    save_dir().join(AUTOSAVE_FILE)
+}
+
+/// Whether a game can be saved (a run in progress that is not a time trial,
+/// a campaign or half of a race)
+pub fn can_save(game: &Game) -> bool {
+    game.alive && game.time_trial.is_none() && game.campaign.is_none() && game.config.mode != GameMode::Race
+}
+
+/// Whether there is an autosave to continue
+pub fn autosave_exists() -> bool {
+    autosave_path().is_file()
+}
+
+/// Writes the game to the autosave, replacing any previous one
+pub fn save_autosave(game: &Game) -> Result<PathBuf, String> {
+    let dir = save_dir();
+    let path = autosave_path();
+    fs::create_dir_all(&dir)
+        .and_then(|_| fs::write(&path, write_game(game)))
+        .map_err(|e| format!("Could not save the game to {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Loads the autosave, making its maze the current one
+pub fn load_autosave() -> Result<Game, String> {
+    let path = autosave_path();
+    let text = fs::read_to_string(&path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    read_game(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Deletes the autosave (once it has been continued or replaced by a new run)
+pub fn delete_autosave() {
+    let _ = fs::remove_file(autosave_path());
+}
+
+/// Writes both peers' games after an online desync, for diffing
+///
+/// # Arguments
+/// * `tick` - Tick after which the games first differed
+/// * `host_state`, `client_state` - The games in the save format (see `write_game`)
+///
+/// # Returns
+/// Path of the host's file; the client's is next to it
+pub fn save_desync_dump(tick: u32, host_state: &str, client_state: &str) -> Result<PathBuf, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    let write = |side: &str, state: &str| {
+        save_path(DESYNC_DIR, &format!("{}-tick{}-{}.sav", stamp, tick, side))
+            .and_then(|path| fs::write(&path, state).map(|_| path))
+            .map_err(|e| format!("Could not save the desync dump: {}", e))
+    };
+    let host_path = write("host", host_state)?;
+    write("client", client_state)?;
+    Ok(host_path)
+}
+
+/// Writes a game in the save format
+pub fn write_game(game: &Game) -> String {
+    let mut lines = vec![
+        SAVE_MAGIC.to_string(),
+        format!("mode {}", mode_name(game.config.mode)),
+        format!("player1 {}", role_name(Some(game.config.player1_role))),
+        format!("player2 {}", role_name(game.config.player2_role)),
+        format!("cornering {}", game.config.cornering_window),
+        format!("targeting {}", targeting_name(game.config.arcade_quirks)),
+        format!("ghosts {}", game.config.ghost_preset.name().to_ascii_lowercase()),
+        format!("pacman {}", game.config.pacman_style.name().to_ascii_lowercase()),
+        format!("rate {}", game.config.tick_rate),
+        format!("ice {}", if game.config.ice { "on" } else { "off" }),
+        format!("ng_plus {}", if game.config.ng_plus { "on" } else { "off" }),
+        format!("seed {:04x}", game.rng.seed()),
+    ];
+    let streams: Vec<String> = game.rng.all().map(|stream| format!("{:04x}", stream.state())).collect();
+    lines.push(format!("rng {}", streams.join(" ")));
+    lines.push(format!("frame {}", game.frame));
+    lines.push(format!("score {}", game.score()));
+    lines.push(format!("points{}", breakdown_text(&game.scoring)));
+    lines.push(format!("lives {}", game.lives));
+    lines.push(format!("level {}", game.level));
+    lines.push(format!("pellets {}", game.pellets));
+    lines.push(format!("power {} {}", game.power_pellet_timer, game.ghost_eaten_count));
+    lines.push(match game.phase {
+        GamePhase::Playing => "phase playing".to_string(),
+        GamePhase::BonusStage { timer } => format!("phase bonus {}", timer),
+        GamePhase::BonusResults { timer } => format!("phase results {}", timer),
+    });
+    lines.push(format!("bonus {} {}", game.bonus_haul, game.bonus_pellets));
+    lines.push(format!("turn {}", game.turn));
+    for player in &game.players {
+        lines.push(format!(
+            "player {} {} {} {} {} {} {} {}",
+            player.x, player.y, player.dx, player.dy, player.sub_frame_counter,
+            player.queued_dx, player.queued_dy, player.queued_ticks
+        ));
+    }
+    for ghost in &game.ghosts {
+        lines.push(format!(
+            "ghost {} {} {} {} {} {} {} {} {} {}",
+            ghost.x, ghost.y, ghost.dx, ghost.dy, ghost.sub_frame_counter, ghost.think_timer,
+            ghost.vulnerable as i32, ghost.target.0, ghost.target.1, ghost.in_house as i32
+        ));
+    }
+    let [pinky, inky, clyde] = game.ghost_dot_counters;
+    let global = game.global_dot_counter.map(|count| count.to_string()).unwrap_or_else(|| "-".to_string());
+    lines.push(format!("dots {} {} {} {} {}", pinky, inky, clyde, global, game.ticks_since_dot));
+    lines.push(format!("fruit {}{}", game.fruit_timer, fruit_names(&game.fruits)));
+    lines.push(format!("double {} {}", game.double_points_pickup, game.double_points_timer));
+    lines.push(format!("bomb {} {} {}", game.bomb_pickup, game.bombs, game.bomb_fuse));
+    for wall in &game.open_walls {
+        lines.push(format!("open_wall {} {} {}", wall.x, wall.y, wall.ticks));
+    }
+    if floor_count() > 1 {
+        let floors: Vec<String> = game.players.iter().map(|player| player.floor)
+            .chain(game.ghosts.iter().map(|ghost| ghost.floor))
+            .map(|floor| floor.to_string())
+            .collect();
+        lines.push(format!("floors {}", floors.join(" ")));
+    }
+    lines.push(format!("eaten {}", bits(&game.eaten)));
+    if let Some(ref waiting) = game.waiting_turn {
+        lines.push(format!(
+            "waiting {} {} {} {} {}",
+            waiting.scoring.score(), waiting.lives, waiting.level, waiting.pellets, bits(&waiting.eaten)
+        ));
+        lines.push(format!("waiting_points{}", breakdown_text(&waiting.scoring)));
+        lines.push(format!("waiting_fruit{}", fruit_names(&waiting.fruits)));
+    }
+    lines.push("---".to_string());
+    lines.push(maze_text());
+    lines.join("\n") + "\n"
+}
+
+/// Reads a game written by `write_game`, making its maze the current one
+pub fn read_game(text: &str) -> Result<Game, String> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some(SAVE_MAGIC) {
+        return Err("not a save from this version of the game".to_string());
+    }
+    let header: Vec<&str> = lines.by_ref().take_while(|line| line.trim() != "---").collect();
+    let maze_text: Vec<&str> = lines.collect();
+    set_current_maze(parse_maze(&maze_text.join("\n"), "in the save")?);
+
+    let value_of = |key: &str| {
+        header.iter().find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+    };
+    let mode = value_of("mode").and_then(parse_mode).ok_or("no valid mode")?;
+    let player1_role = value_of("player1").and_then(parse_role).ok_or("no valid player1 role")?;
+    let player2_role = value_of("player2").and_then(parse_role);
+    let mut config = GameConfig::new(mode, player1_role, player2_role);
+    config.cornering_window = value_of("cornering").and_then(|value| value.parse().ok()).ok_or("no valid cornering window")?;
+    config.arcade_quirks = value_of("targeting").and_then(parse_targeting).ok_or("no valid targeting")?;
+    // Saves from before ghost presets played the classic ghosts
+    if let Some(name) = value_of("ghosts") {
+        config.ghost_preset = GhostPreset::from_name(name).ok_or("no valid ghosts")?;
+    }
+    // Saves from before AI Pac-Man styles played the greedy one
+    if let Some(name) = value_of("pacman") {
+        config.pacman_style = PacmanStyle::from_name(name).ok_or("no valid pacman style")?;
+    }
+    // Saves from before tick rates played at the default one
+    if let Some(rate) = value_of("rate") {
+        config.tick_rate = rate.parse().ok().filter(|rate| TICK_RATES.contains(rate)).ok_or("no valid tick rate")?;
+    }
+    // Saves from before ice mazes were off the ice
+    if let Some(ice) = value_of("ice") {
+        config.ice = match ice {
+            "on" => true,
+            "off" => false,
+            _ => return Err("no valid ice".to_string()),
+        };
+    }
+    // Saves from before new game plus were regular runs
+    if let Some(ng_plus) = value_of("ng_plus") {
+        config.ng_plus = match ng_plus {
+            "on" => true,
+            "off" => false,
+            _ => return Err("no valid ng_plus".to_string()),
+        };
+    }
+    let seed = value_of("seed").and_then(|value| u16::from_str_radix(value, 16).ok()).ok_or("no valid seed")?;
+    let mut game = Game::new_with_seed(config, seed);
+    if !can_save(&game) {
+        return Err("time trials cannot be continued".to_string());
+    }
+
+    let streams: Vec<u16> = value_of("rng").ok_or("no rng line")?
+        .split_whitespace()
+        .map(|value| u16::from_str_radix(value, 16))
+        .collect::<Result<_, _>>()
+        .map_err(|_| "bad rng line")?;
+    let [g0, g1, g2, pacman_ai, fruit, ref newer @ ..] = streams[..] else { return Err("bad rng line".to_string()) };
+    game.rng.ghosts = [Lfsr::new(g0), Lfsr::new(g1), Lfsr::new(g2)];
+    game.rng.pacman_ai = Lfsr::new(pacman_ai);
+    game.rng.fruit = Lfsr::new(fruit);
+    // Saves from before the bomb or pellet respawn stream keep the ones derived from the seed
+    match *newer {
+        [] => {}
+        [bomb] => game.rng.bomb = Lfsr::new(bomb),
+        [bomb, respawn] => {
+            game.rng.bomb = Lfsr::new(bomb);
+            game.rng.respawn = Lfsr::new(respawn);
+        }
+        _ => return Err("bad rng line".to_string()),
+    }
+
+    let mut players = Vec::new();
+    let mut ghost_count = 0;
+    let mut floors = Vec::new();
+    let mut score = 0;
+    let mut breakdown = [0; ScoreSource::ALL.len()];
+    for line in &header {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+        let bad_line = || format!("bad line: {}", line);
+        match key {
+            "frame" => game.frame = value.parse().map_err(|_| bad_line())?,
+            "score" => score = value.parse().map_err(|_| bad_line())?,
+            "lives" => game.lives = value.parse().map_err(|_| bad_line())?,
+            "level" => game.level = value.parse().map_err(|_| bad_line())?,
+            "pellets" => game.pellets = value.parse().map_err(|_| bad_line())?,
+            "turn" => game.turn = value.parse().map_err(|_| bad_line())?,
+            "power" => {
+                let [timer, eaten] = numbers::<2>(value).ok_or_else(bad_line)?;
+                game.power_pellet_timer = timer;
+                game.ghost_eaten_count = eaten;
+            }
+            "bonus" => {
+                let [haul, pellets] = numbers::<2>(value).ok_or_else(bad_line)?;
+                game.bonus_haul = haul;
+                game.bonus_pellets = pellets;
+            }
+            "phase" => {
+                let (name, timer) = value.split_once(' ').unwrap_or((value, "0"));
+                let timer = timer.parse().map_err(|_| bad_line())?;
+                game.phase = match name {
+                    "playing" => GamePhase::Playing,
+                    "bonus" => GamePhase::BonusStage { timer },
+                    "results" => GamePhase::BonusResults { timer },
+                    _ => return Err(bad_line()),
+                };
+            }
+            "player" => {
+                let [x, y, dx, dy, sub_frame_counter, queued_dx, queued_dy, queued_ticks] =
+                    numbers::<8>(value).ok_or_else(bad_line)?;
+                players.push(Player { x, y, dx, dy, sub_frame_counter, queued_dx, queued_dy, queued_ticks, floor: 0 });
+            }
+            "ghost" => {
+                let [x, y, dx, dy, sub_frame_counter, think_timer, vulnerable, target_x, target_y, in_house] =
+                    numbers::<10>(value).ok_or_else(bad_line)?;
+                let ghost = game.ghosts.get_mut(ghost_count).ok_or_else(bad_line)?;
+                (ghost.x, ghost.y, ghost.dx, ghost.dy) = (x, y, dx, dy);
+                ghost.sub_frame_counter = sub_frame_counter;
+                ghost.think_timer = think_timer;
+                ghost.vulnerable = vulnerable != 0;
+                ghost.target = (target_x, target_y);
+                ghost.in_house = in_house != 0;
+                ghost_count += 1;
+            }
+            "dots" => {
+                let [pinky, inky, clyde, global, ticks_since_dot] = value.split_whitespace().collect::<Vec<_>>()[..] else {
+                    return Err(bad_line());
+                };
+                let counters = numbers::<3>(&[pinky, inky, clyde].join(" ")).ok_or_else(bad_line)?;
+                game.ghost_dot_counters = counters.map(|count| count.max(0) as u32);
+                game.global_dot_counter = match global {
+                    "-" => None,
+                    count => Some(count.parse().map_err(|_| bad_line())?),
+                };
+                game.ticks_since_dot = ticks_since_dot.parse().map_err(|_| bad_line())?;
+            }
+            "eaten" => game.eaten = parse_bits(value).ok_or_else(bad_line)?,
+            // Only on two-floor mazes
+            "floors" => {
+                floors = value.split_whitespace()
+                    .map(|floor| floor.parse::<usize>().ok().filter(|&floor| floor < floor_count()))
+                    .collect::<Option<_>>()
+                    .ok_or_else(bad_line)?;
+            }
+            // Saves from before the score breakdown have no points or waiting_points line
+            "points" => breakdown = numbers(value).ok_or_else(bad_line)?,
+            // Saves from before bonus fruit, double points and bombs have no
+            // fruit, double, bomb or open_wall lines
+            "double" => {
+                let [pickup, timer] = numbers::<2>(value).ok_or_else(bad_line)?;
+                game.double_points_pickup = pickup;
+                game.double_points_timer = timer;
+            }
+            "bomb" => {
+                let [pickup, held, fuse] = numbers::<3>(value).ok_or_else(bad_line)?;
+                game.bomb_pickup = pickup;
+                game.bombs = held;
+                game.bomb_fuse = fuse;
+            }
+            "open_wall" => {
+                let [x, y, ticks] = numbers::<3>(value).ok_or_else(bad_line)?;
+                game.open_walls.push(OpenWall { x, y, ticks });
+            }
+            "fruit" => {
+                let mut parts = value.split_whitespace();
+                game.fruit_timer = parts.next().and_then(|timer| timer.parse().ok()).ok_or_else(bad_line)?;
+                game.fruits = parts.map(Fruit::from_name).collect::<Option<_>>().ok_or_else(bad_line)?;
+            }
+            "waiting" => {
+                let mut parts = value.rsplitn(2, ' ');
+                let eaten = parts.next().and_then(parse_bits).ok_or_else(bad_line)?;
+                let [score, lives, level, pellets] = parts.next().and_then(numbers::<4>).ok_or_else(bad_line)?;
+                let scoring = Scoring::restore(score, [0; ScoreSource::ALL.len()]);
+                game.waiting_turn = Some(TurnState { scoring, lives, level: level as u32, eaten, pellets, fruits: Vec::new() });
+            }
+            "waiting_points" => {
+                let breakdown = numbers(value).ok_or_else(bad_line)?;
+                if let Some(ref mut waiting) = game.waiting_turn {
+                    waiting.scoring = Scoring::restore(waiting.scoring.score(), breakdown);
+                }
+            }
+            "waiting_fruit" => {
+                let fruits = value.split_whitespace().map(Fruit::from_name).collect::<Option<_>>().ok_or_else(bad_line)?;
+                if let Some(ref mut waiting) = game.waiting_turn {
+                    waiting.fruits = fruits;
+                }
+            }
+            _ => {}
+        }
+    }
+    if players.len() != game.players.len() || ghost_count != game.ghosts.len() {
+        return Err("wrong number of players or ghosts".to_string());
+    }
+    game.players = players;
+    game.scoring = Scoring::restore(score, breakdown);
+    if !floors.is_empty() {
+        if floors.len() != game.players.len() + game.ghosts.len() {
+            return Err("wrong number of floors".to_string());
+        }
+        let (player_floors, ghost_floors) = floors.split_at(game.players.len());
+        game.players.iter_mut().zip(player_floors).for_each(|(player, &floor)| player.floor = floor);
+        game.ghosts.iter_mut().zip(ghost_floors).for_each(|(ghost, &floor)| ghost.floor = floor);
+    }
+    Ok(game)
+}
+
+/// A score's breakdown as numbers, each after a space
+fn breakdown_text(scoring: &Scoring) -> String {
+    scoring.breakdown().iter().map(|points| format!(" {}", points)).collect()
+}
+
+/// Parses exactly `N` whitespace-separated integers
+fn numbers<const N: usize>(value: &str) -> Option<[i32; N]> {
+    let parsed: Vec<i32> = value.split_whitespace().map(str::parse).collect::<Result<_, _>>().ok()?;
+    parsed.try_into().ok()
+}
+
+/// Fruit as their lower-case names, each after a space
+fn fruit_names(fruits: &[Fruit]) -> String {
+    fruits.iter().map(|fruit| format!(" {}", fruit.name().to_ascii_lowercase())).collect()
+}
+
+/// Writes a pellet bitmap as a string of 0s and 1s
+fn bits(eaten: &[bool]) -> String {
+    eaten.iter().map(|&eaten| if eaten { '1' } else { '0' }).collect()
+}
+
+/// Reads a pellet bitmap written by `bits` (one flag per tile of every floor)
+fn parse_bits(value: &str) -> Option<Vec<bool>> {
+    if value.len() != floor_count() * (GRID_W * GRID_H) as usize {
+        return None;
+    }
+    value.chars()
+        .map(|c| match c {
+            '0' => Some(false),
+            '1' => Some(true),
+            _ => None,
+        })
+        .collect()
+}
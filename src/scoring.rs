@@ -0,0 +1,119 @@
+//! Scoring: where points come from and what they earn
+//!
+//! A `Scoring` holds one player's score and its per-source breakdown for the
+//! statistics screen. Every point in a run is awarded through
+//! `Scoring::award`, which applies the current multiplier and works out the
+//! extra lives earned by crossing one of the `EXTRA_LIFE_SCORES`. The
+//! thresholds are checked against the score before and after the award, so
+//! nothing about them needs to be remembered between awards. In alternating
+//! mode each player has a `Scoring` of their own, swapped in with their turn.
+
+use crate::constants::EXTRA_LIFE_SCORES;
+
+/// What a batch of points was awarded for
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ScoreSource {
+    Pellet,
+    PowerPellet,
+    Ghost,
+    Fruit,
+    BonusStage,
+}
+
+impl ScoreSource {
+    /// Every source, in the order of `Scoring::breakdown`
+    pub const ALL: [ScoreSource; 5] = [
+        ScoreSource::Pellet, ScoreSource::PowerPellet, ScoreSource::Ghost,
+        ScoreSource::Fruit, ScoreSource::BonusStage,
+    ];
+
+    /// Short name, used in saves, dumps and statistics exports
+    pub fn name(self) -> &'static str {
+        match self {
+            ScoreSource::Pellet => "pellets",
+            ScoreSource::PowerPellet => "power_pellets",
+            ScoreSource::Ghost => "ghosts",
+            ScoreSource::Fruit => "fruit",
+            ScoreSource::BonusStage => "bonus",
+        }
+    }
+
+    /// Name shown on the statistics screen
+    pub fn label(self) -> &'static str {
+        match self {
+            ScoreSource::Pellet => "Pellets",
+            ScoreSource::PowerPellet => "Power",
+            ScoreSource::Ghost => "Ghosts",
+            ScoreSource::Fruit => "Fruit",
+            ScoreSource::BonusStage => "Bonus",
+        }
+    }
+
+    /// Position of this source in `Scoring::breakdown`
+    pub fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// The outcome of one award
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Award {
+    /// Points actually added (after the multiplier)
+    pub points: i32,
+    /// Extra lives earned by the new score
+    pub extra_lives: i32,
+}
+
+/// A player's score and where it came from
+///
+/// The score only changes through `award` (or comes back whole from a save
+/// with `restore`), so it never drifts from the breakdown.
+#[derive(Clone, Default, PartialEq, Debug)]
+pub struct Scoring {
+    score: i32,
+    /// Points awarded so far, per source (indexed by `ScoreSource::index`)
+    breakdown: [i32; ScoreSource::ALL.len()],
+}
+
+impl Scoring {
+    /// A score read back from a save or a state dump
+    ///
+    /// Saves from before the breakdown was kept have none: their points are
+    /// in the score but in no source.
+    pub fn restore(score: i32, breakdown: [i32; ScoreSource::ALL.len()]) -> Self {
+        Self { score, breakdown }
+    }
+
+    pub fn score(&self) -> i32 {
+        self.score
+    }
+
+    /// Points awarded so far, per source (indexed by `ScoreSource::index`)
+    pub fn breakdown(&self) -> &[i32; ScoreSource::ALL.len()] {
+        &self.breakdown
+    }
+
+    /// Adds `points` from `source`, times `multiplier`, to the score
+    ///
+    /// # Arguments
+    /// * `source` - What the points are for
+    /// * `points` - The base points
+    /// * `multiplier` - What the points are multiplied by (1 normally)
+    ///
+    /// # Returns
+    /// The points added and the extra lives they earned
+    pub fn award(&mut self, source: ScoreSource, points: i32, multiplier: i32) -> Award {
+        let points = points * multiplier;
+        let before = self.score;
+        self.score += points;
+        self.breakdown[source.index()] += points;
+        Award { points, extra_lives: extra_lives_between(before, self.score) }
+    }
+}
+
+/// Number of `EXTRA_LIFE_SCORES` passed going from `before` to `after`
+pub fn extra_lives_between(before: i32, after: i32) -> i32 {
+    EXTRA_LIFE_SCORES.iter()
+        .filter(|&&threshold| before < threshold && after >= threshold)
+        .count() as i32
+}
@@ -0,0 +1,47 @@
+//! SDL2 audio backend (the default, `sdl-audio` feature)
+//!
+//! Opens a mono f32 playback device and fills it from the mixer in SDL's
+//! audio callback.
+
+use sdl2::audio::{AudioCallback, AudioDevice, AudioSpecDesired};
+use crate::audio::{AudioBackend, Mixer};
+
+/// SDL callback wrapper around the mixer
+struct Callback(Mixer);
+
+impl AudioCallback for Callback {
+    type Channel = f32;
+
+    fn callback(&mut self, out: &mut [f32]) {
+        self.0.fill(out);
+    }
+}
+
+/// Plays audio through SDL2
+pub struct SdlAudio {
+    subsystem: sdl2::AudioSubsystem,
+    device: Option<AudioDevice<Callback>>,
+}
+
+impl SdlAudio {
+    pub fn new(sdl: &sdl2::Sdl) -> Result<Self, String> {
+        Ok(Self { subsystem: sdl.audio()?, device: None })
+    }
+}
+
+impl AudioBackend for SdlAudio {
+    fn start(&mut self, make_mixer: Box<dyn FnOnce(i32) -> Mixer + Send>) -> Result<(), String> {
+        let desired_spec = AudioSpecDesired {
+            freq: Some(44100),
+            channels: Some(1),  // Mono
+            samples: None,     // Default sample size
+        };
+        
+        let device = self.subsystem.open_playback(None, &desired_spec, |spec| Callback(make_mixer(spec.freq)))?;
+        
+        // Start playing
+        device.resume();
+        self.device = Some(device);
+        Ok(())
+    }
+}
@@ -0,0 +1,374 @@
+//! Player settings and the config file that keeps them
+//!
+//! Everything changed on the settings pages of the menu (video, audio,
+//! controls, gameplay) lives in `Settings` and is written to
+//! `settings.cfg` in the save directory after every change, so it is back the
+//! next time the game starts. The file is plain `key value` lines and can be
+//! edited by hand; unknown keys and bad values are ignored (the default stays):
+//!
+//! ```text
+//! fullscreen off
+//! window_scale 4
+//! vsync on
+//! render_scale 100
+//! photosensitive_mode off
+//! maze_colors level
+//! music_volume 8
+//! effects_volume 10
+//! proximity_tone off
+//! announcements off
+//! clip_sound off
+//! p1_up Up
+//! p2_left A
+//! pause P
+//! rumble 0
+//! touch_controls on
+//! one_button off
+//! pause_on_focus_loss on
+//! cornering_window 2
+//! arcade_targeting on
+//! ghost_preset classic
+//! pacman_style greedy
+//! tick_rate 60
+//! ice_maze off
+//! telemetry off
+//! ```
+
+use std::fs;
+use std::io::Write;
+use sdl2::keyboard::Scancode;
+use crate::constants::{
+    WINDOW_SCALES, FPS_CAPS, RENDER_SCALES, CORNERING_WINDOW, MAX_CORNERING_WINDOW, MAZE_PALETTES, FPS, TICK_RATES
+};
+use crate::game_config::{GhostPreset, PacmanStyle};
+use crate::storage::save_dir;
+use crate::video::VideoSettings;
+
+/// Name of the config file in the save directory
+const SETTINGS_FILE: &str = "settings.cfg";
+
+/// Highest volume step (volumes go from 0 to this)
+pub const MAX_VOLUME: u8 = 10;
+
+/// Highest controller rumble step (rumble goes from 0, off, to this)
+pub const MAX_RUMBLE: u8 = 4;
+
+/// Music and sound effect volumes
+#[derive(Clone, Copy)]
+pub struct AudioSettings {
+    /// Music volume, 0 to `MAX_VOLUME`
+    pub music_volume: u8,
+    /// Sound effect volume, 0 to `MAX_VOLUME`
+    pub effects_volume: u8,
+    /// Hum a tone that rises as the nearest ghost closes in (see `audio`)
+    pub proximity_tone: bool,
+    /// Speak game events and menu entries (see `speech`)
+    pub announcements: bool,
+    /// Save the sound with clips, as a WAV file next to the GIF (see `audio_tap`)
+    pub clip_sound: bool,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: MAX_VOLUME,
+            effects_volume: MAX_VOLUME,
+            proximity_tone: false,
+            announcements: false,
+            clip_sound: false,
+        }
+    }
+}
+
+/// Names of the rebindable actions, in binding index order (see `Controls::binding`)
+pub const BINDING_NAMES: [&str; 9] = [
+    "P1 Up", "P1 Down", "P1 Left", "P1 Right",
+    "P2 Up", "P2 Down", "P2 Left", "P2 Right",
+    "Pause",
+];
+
+/// Config file keys of the bindings, in binding index order
+const BINDING_KEYS: [&str; 9] = [
+    "p1_up", "p1_down", "p1_left", "p1_right",
+    "p2_up", "p2_down", "p2_left", "p2_right",
+    "pause",
+];
+
+/// Keys that show the emotes, in `Emote::ALL` order, when a human plays a
+/// ghost (not rebindable)
+pub const EMOTE_KEYS: [Scancode; 3] = [Scancode::Num1, Scancode::Num2, Scancode::Num3];
+
+/// Keyboard bindings for steering and pausing
+#[derive(Clone, Copy)]
+pub struct Controls {
+    /// Player 1's up, down, left and right keys
+    pub player1: [Scancode; 4],
+    /// Player 2's up, down, left and right keys
+    pub player2: [Scancode; 4],
+    /// Pauses and resumes the game
+    pub pause: Scancode,
+    /// Strength of the controller rumble, 0 to `MAX_RUMBLE`; off unless turned
+    /// up, since not every player can take it
+    pub rumble: u8,
+    /// Show the on-screen D-pad and pause button once the screen is touched (see `touch`)
+    pub touch: bool,
+    /// Player 1 steers with a single action while Pac-Man moves by himself (see `one_button`)
+    pub one_button: bool,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        Self {
+            player1: [Scancode::Up, Scancode::Down, Scancode::Left, Scancode::Right],
+            player2: [Scancode::W, Scancode::S, Scancode::A, Scancode::D],
+            pause: Scancode::P,
+            rumble: 0,
+            touch: true,
+            one_button: false,
+        }
+    }
+}
+
+impl Controls {
+    /// Rumble strength from 0.0 (off) to 1.0
+    pub fn rumble_strength(&self) -> f32 {
+        self.rumble.min(MAX_RUMBLE) as f32 / MAX_RUMBLE as f32
+    }
+
+    /// Key bound to an action (index into `BINDING_NAMES`)
+    pub fn binding(&self, index: usize) -> Option<Scancode> {
+        match index {
+            0..=3 => Some(self.player1[index]),
+            4..=7 => Some(self.player2[index - 4]),
+            8 => Some(self.pause),
+            _ => None,
+        }
+    }
+
+    fn binding_mut(&mut self, index: usize) -> Option<&mut Scancode> {
+        match index {
+            0..=3 => Some(&mut self.player1[index]),
+            4..=7 => Some(&mut self.player2[index - 4]),
+            8 => Some(&mut self.pause),
+            _ => None,
+        }
+    }
+
+    /// Binds a key to an action
+    ///
+    /// If another action already used the key, it gets this action's old key,
+    /// so no key ever does two things.
+    pub fn bind(&mut self, index: usize, scancode: Scancode) {
+        let Some(old) = self.binding(index) else { return };
+        let taken_by = (0..BINDING_NAMES.len()).find(|&other| other != index && self.binding(other) == Some(scancode));
+        if let Some(slot) = taken_by.and_then(|other| self.binding_mut(other)) {
+            *slot = old;
+        }
+        if let Some(slot) = self.binding_mut(index) {
+            *slot = scancode;
+        }
+    }
+}
+
+/// Options that change how the game behaves around play
+#[derive(Clone, Copy)]
+pub struct GameplaySettings {
+    /// Pause a local game when the window loses focus
+    pub pause_on_focus_loss: bool,
+    /// Cornering window of local games in ticks (see `GameConfig::cornering_window`)
+    pub cornering_window: i32,
+    /// Ghosts of local games target like the arcade game, bugs included (see `GameConfig::arcade_quirks`)
+    pub arcade_targeting: bool,
+    /// AIs of the computer-controlled ghosts in local games (see `GameConfig::ghost_preset`)
+    pub ghost_preset: GhostPreset,
+    /// How the computer-controlled Pac-Man of local games plays (see `GameConfig::pacman_style`)
+    pub pacman_style: PacmanStyle,
+    /// Simulation ticks per second of local games, one of `TICK_RATES` (see `GameConfig::tick_rate`)
+    pub tick_rate: u32,
+    /// Local games are played on ice (see `GameConfig::ice`)
+    pub ice_maze: bool,
+    /// Append a summary of each game to the telemetry file (see `telemetry`); off unless turned on
+    pub telemetry: bool,
+}
+
+impl Default for GameplaySettings {
+    fn default() -> Self {
+        Self {
+            pause_on_focus_loss: true,
+            cornering_window: CORNERING_WINDOW,
+            arcade_targeting: true,
+            ghost_preset: GhostPreset::Classic,
+            pacman_style: PacmanStyle::Greedy,
+            tick_rate: FPS,
+            ice_maze: false,
+            telemetry: false,
+        }
+    }
+}
+
+/// Everything on the settings pages
+#[derive(Clone, Copy)]
+pub struct Settings {
+    pub video: VideoSettings,
+    pub audio: AudioSettings,
+    pub controls: Controls,
+    pub gameplay: GameplaySettings,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            video: VideoSettings::new(false),
+            audio: AudioSettings::default(),
+            controls: Controls::default(),
+            gameplay: GameplaySettings::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the config file, falling back to the defaults for anything missing
+    pub fn load() -> Self {
+        fs::read_to_string(save_dir().join(SETTINGS_FILE))
+            .map(|text| Settings::from_text(&text))
+            .unwrap_or_default()
+    }
+
+    /// Reads settings in the config file format, falling back to the defaults for anything missing
+    pub fn from_text(text: &str) -> Self {
+        let mut settings = Settings::default();
+        for line in text.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            settings.apply_line(key, value.trim());
+        }
+        settings
+    }
+
+    /// Sets one value from the config file (ignoring unknown keys and bad values)
+    fn apply_line(&mut self, key: &str, value: &str) {
+        let on = match value {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        };
+        let number = value.parse::<u32>().ok();
+        match key {
+            "fullscreen" => self.video.fullscreen = on.unwrap_or(self.video.fullscreen),
+            "display" => self.video.display_index = number.map(|n| n as i32).unwrap_or(self.video.display_index),
+            "window_scale" => {
+                if let Some(index) = WINDOW_SCALES.iter().position(|&scale| Some(scale as u32) == number) {
+                    self.video.scale_index = index;
+                }
+            }
+            "vsync" => self.video.vsync = on.unwrap_or(self.video.vsync),
+            "photosensitive_mode" => self.video.photosensitive = on.unwrap_or(self.video.photosensitive),
+            "fps_cap" => {
+                if let Some(index) = FPS_CAPS.iter().position(|&cap| Some(cap) == number) {
+                    self.video.fps_cap_index = index;
+                }
+            }
+            "render_scale" => {
+                if let Some(index) = RENDER_SCALES.iter().position(|&scale| Some(scale) == number) {
+                    self.video.render_scale_index = index;
+                }
+            }
+            "maze_colors" if value == "level" => self.video.maze_colors = None,
+            "maze_colors" => {
+                if let Some(index) = MAZE_PALETTES.iter().position(|palette| palette.name.eq_ignore_ascii_case(value)) {
+                    self.video.maze_colors = Some(index);
+                }
+            }
+            "music_volume" => {
+                self.audio.music_volume = number.map(|n| n.min(MAX_VOLUME as u32) as u8).unwrap_or(self.audio.music_volume);
+            }
+            "effects_volume" => {
+                self.audio.effects_volume = number.map(|n| n.min(MAX_VOLUME as u32) as u8).unwrap_or(self.audio.effects_volume);
+            }
+            "proximity_tone" => self.audio.proximity_tone = on.unwrap_or(self.audio.proximity_tone),
+            "announcements" => self.audio.announcements = on.unwrap_or(self.audio.announcements),
+            "clip_sound" => self.audio.clip_sound = on.unwrap_or(self.audio.clip_sound),
+            "pause_on_focus_loss" => {
+                self.gameplay.pause_on_focus_loss = on.unwrap_or(self.gameplay.pause_on_focus_loss);
+            }
+            "cornering_window" => {
+                self.gameplay.cornering_window = number
+                    .map(|n| n.min(MAX_CORNERING_WINDOW as u32) as i32)
+                    .unwrap_or(self.gameplay.cornering_window);
+            }
+            "arcade_targeting" => self.gameplay.arcade_targeting = on.unwrap_or(self.gameplay.arcade_targeting),
+            "ghost_preset" => {
+                self.gameplay.ghost_preset = GhostPreset::from_name(value).unwrap_or(self.gameplay.ghost_preset);
+            }
+            "pacman_style" => {
+                self.gameplay.pacman_style = PacmanStyle::from_name(value).unwrap_or(self.gameplay.pacman_style);
+            }
+            "tick_rate" => {
+                self.gameplay.tick_rate = number.filter(|rate| TICK_RATES.contains(rate)).unwrap_or(self.gameplay.tick_rate);
+            }
+            "ice_maze" => self.gameplay.ice_maze = on.unwrap_or(self.gameplay.ice_maze),
+            "telemetry" => self.gameplay.telemetry = on.unwrap_or(self.gameplay.telemetry),
+            "touch_controls" => self.controls.touch = on.unwrap_or(self.controls.touch),
+            "one_button" => self.controls.one_button = on.unwrap_or(self.controls.one_button),
+            "rumble" => {
+                self.controls.rumble = number.map(|n| n.min(MAX_RUMBLE as u32) as u8).unwrap_or(self.controls.rumble);
+            }
+            _ => {
+                let binding = BINDING_KEYS.iter().position(|&name| name == key);
+                if let (Some(index), Some(scancode)) = (binding, Scancode::from_name(value)) {
+                    self.controls.bind(index, scancode);
+                }
+            }
+        }
+    }
+
+    /// Writes the config file
+    pub fn save(&self) -> Result<(), String> {
+        let dir = save_dir();
+        let path = dir.join(SETTINGS_FILE);
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::File::create(&path))
+            .and_then(|mut file| file.write_all(self.to_text().as_bytes()))
+            .map_err(|e| format!("Could not save settings to {}: {}", path.display(), e))
+    }
+
+    /// The settings in the config file format, one `key value` line each
+    pub fn to_text(&self) -> String {
+        let on_off = |on: bool| if on { "on" } else { "off" };
+        let mut text = String::new();
+        text.push_str(&format!("fullscreen {}\n", on_off(self.video.fullscreen)));
+        text.push_str(&format!("display {}\n", self.video.display_index));
+        text.push_str(&format!("window_scale {}\n", WINDOW_SCALES[self.video.scale_index.min(WINDOW_SCALES.len() - 1)]));
+        text.push_str(&format!("vsync {}\n", on_off(self.video.vsync)));
+        text.push_str(&format!("fps_cap {}\n", self.video.fps_cap()));
+        text.push_str(&format!("render_scale {}\n", self.video.render_scale()));
+        text.push_str(&format!("photosensitive_mode {}\n", on_off(self.video.photosensitive)));
+        let maze_colors = self.video.maze_colors.and_then(|index| MAZE_PALETTES.get(index));
+        text.push_str(&format!("maze_colors {}\n", maze_colors.map(|palette| palette.name.to_ascii_lowercase()).unwrap_or("level".to_string())));
+        text.push_str(&format!("music_volume {}\n", self.audio.music_volume));
+        text.push_str(&format!("effects_volume {}\n", self.audio.effects_volume));
+        text.push_str(&format!("proximity_tone {}\n", on_off(self.audio.proximity_tone)));
+        text.push_str(&format!("announcements {}\n", on_off(self.audio.announcements)));
+        text.push_str(&format!("clip_sound {}\n", on_off(self.audio.clip_sound)));
+        for (index, key) in BINDING_KEYS.iter().enumerate() {
+            if let Some(scancode) = self.controls.binding(index) {
+                text.push_str(&format!("{} {}\n", key, scancode.name()));
+            }
+        }
+        text.push_str(&format!("rumble {}\n", self.controls.rumble));
+        text.push_str(&format!("touch_controls {}\n", on_off(self.controls.touch)));
+        text.push_str(&format!("one_button {}\n", on_off(self.controls.one_button)));
+        text.push_str(&format!("pause_on_focus_loss {}\n", on_off(self.gameplay.pause_on_focus_loss)));
+        text.push_str(&format!("cornering_window {}\n", self.gameplay.cornering_window));
+        text.push_str(&format!("arcade_targeting {}\n", on_off(self.gameplay.arcade_targeting)));
+        text.push_str(&format!("ghost_preset {}\n", self.gameplay.ghost_preset.name().to_ascii_lowercase()));
+        text.push_str(&format!("pacman_style {}\n", self.gameplay.pacman_style.name().to_ascii_lowercase()));
+        text.push_str(&format!("tick_rate {}\n", self.gameplay.tick_rate));
+        text.push_str(&format!("ice_maze {}\n", on_off(self.gameplay.ice_maze)));
+        text.push_str(&format!("telemetry {}\n", on_off(self.gameplay.telemetry)));
+        text
+    }
+}
@@ -0,0 +1,315 @@
+//! Skins: how Pac-Man and the ghosts look
+//!
+//! A skin is a set of 6x6 sprites (Pac-Man with the mouth closed, half open
+//! and open, the ghost with either skirt) and the colors they are drawn in.
+//! A few are built in, shape or color variants of the classic look, and more
+//! are read from `.skin` files in `skins/` under the asset directories (see
+//! `assets.rs`), named after the file:
+//!
+//! ```text
+//! # Robot: a square Pac-Man and boxy ghosts
+//! pacman_colors 200 200 220 120 200 255
+//! ghost_colors 255 60 60 255 160 220 60 220 220 255 170 60
+//! pacman_open
+//! XXXXXX
+//! X.XX.X
+//! XXXX..
+//! XXX...
+//! XXXX..
+//! XXXXXX
+//! ```
+//!
+//! - `pacman_colors`: red, green and blue for player 1, then player 2
+//! - `ghost_colors`: the same for each ghost, in ghost order
+//! - `pacman_closed`, `pacman_half`, `pacman_open`, `ghost`, `ghost_alt`: a
+//!   sprite, six rows of six pixels below the key (`X` drawn, `.` not)
+//!
+//! Whatever a file leaves out is the classic one. Frightened ghosts are blue
+//! and white whatever the skin, and their eyes are drawn on top of it.
+//!
+//! Each profile wears its own skin, chosen on the **Skins** settings page
+//! and saved as `skins.txt` in the save directory (`skin <skin> <profile>`
+//! lines). The ghosts wear player 1's. Some built-in skins are earned first
+//! (see `unlocks`); skins from files never are.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::assets::list_assets;
+use crate::storage::save_dir;
+
+/// Asset subdirectory holding the skin files
+const SKIN_DIR: &str = "skins";
+
+/// Name of the file in the save directory holding each profile's skin
+const SKINS_FILE: &str = "skins.txt";
+
+/// A 6x6 sprite, row by row (true = drawn)
+pub type Sprite = [[bool; 6]; 6];
+
+/// A color as red, green and blue
+pub type Rgb = (u8, u8, u8);
+
+/// Names of the built-in skins; `Unlock::Skin` indexes into this
+pub const BUILT_IN_SKINS: [&str; 3] = ["Classic", "Blocky", "Pastel"];
+
+/// Writes a sprite as rows of `X` and `.`, for the tables below
+macro_rules! sprite {
+    ($($row:literal)*) => {
+        [$(row($row)),*]
+    };
+}
+
+/// One sprite row from `X` and `.`
+const fn row(pixels: &str) -> [bool; 6] {
+    let bytes = pixels.as_bytes();
+    let mut bits = [false; 6];
+    let mut col = 0;
+    while col < 6 {
+        bits[col] = bytes[col] == b'X';
+        col += 1;
+    }
+    bits
+}
+
+const CLASSIC_PACMAN: [Sprite; 3] = [
+    sprite!(".XXXX." "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" ".XXXX."),
+    sprite!(".XXXX." "XXXXXX" "XX..XX" "XX..XX" "XXXXXX" ".XXXX."),
+    sprite!(".XXXX." "XXXXXX" "X....X" "......" "XXXXXX" ".XXXX."),
+];
+
+const CLASSIC_GHOST: [Sprite; 2] = [
+    sprite!(".XXXX." "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "X.XX.X"),
+    sprite!(".XXXX." "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XX..XX"),
+];
+
+const BLOCKY_PACMAN: [Sprite; 3] = [
+    sprite!("XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX"),
+    sprite!("XXXXXX" "XXXXXX" "XXX..." "XXX..." "XXXXXX" "XXXXXX"),
+    sprite!("XXXXXX" "XXXX.." "XX...." "XX...." "XXXX.." "XXXXXX"),
+];
+
+const BLOCKY_GHOST: [Sprite; 2] = [
+    sprite!("XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "X.XX.X"),
+    sprite!("XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XXXXXX" "XX..XX"),
+];
+
+/// How Pac-Man and the ghosts look
+#[derive(Clone)]
+pub struct Skin {
+    /// Display name, also what the save file records
+    pub name: String,
+    /// Pac-Man with the mouth closed, half open and open
+    pub pacman: [Sprite; 3],
+    /// The ghost with either skirt (they take turns as it moves)
+    pub ghost: [Sprite; 2],
+    /// Pac-Man's color for player 1, player 2 (empty for the usual ones)
+    pub pacman_colors: Vec<Rgb>,
+    /// Each ghost's color, in ghost order (empty for the usual ones)
+    pub ghost_colors: Vec<Rgb>,
+    /// The file it was read from (None if built in)
+    pub file: Option<PathBuf>,
+}
+
+impl Default for Skin {
+    fn default() -> Self {
+        Self {
+            name: BUILT_IN_SKINS[0].to_string(),
+            pacman: CLASSIC_PACMAN,
+            ghost: CLASSIC_GHOST,
+            pacman_colors: Vec::new(),
+            ghost_colors: Vec::new(),
+            file: None,
+        }
+    }
+}
+
+impl Skin {
+    /// A built-in skin (index into `BUILT_IN_SKINS`)
+    pub fn built_in(index: usize) -> Option<Self> {
+        let classic = Skin::default();
+        let skin = match index {
+            0 => classic,
+            1 => Skin { pacman: BLOCKY_PACMAN, ghost: BLOCKY_GHOST, ..classic },
+            2 => Skin {
+                pacman_colors: vec![(255, 214, 232), (255, 206, 160)],
+                ghost_colors: vec![(255, 150, 150), (240, 200, 255), (170, 240, 230), (255, 224, 170)],
+                ..classic
+            },
+            _ => return None,
+        };
+        Some(Skin { name: BUILT_IN_SKINS[index].to_string(), ..skin })
+    }
+
+    /// Where it is built in (index into `BUILT_IN_SKINS`; None for a skin file)
+    pub fn built_in_index(&self) -> Option<usize> {
+        if self.file.is_some() {
+            return None;
+        }
+        BUILT_IN_SKINS.iter().position(|&name| name == self.name)
+    }
+
+    /// Pac-Man's sprite for a mouth frame (0 closed, 1 half open, 2 open, 3 half open)
+    pub fn pacman_frame(&self, mouth_frame: u32) -> &Sprite {
+        match mouth_frame {
+            0 => &self.pacman[0],
+            2 => &self.pacman[2],
+            _ => &self.pacman[1],
+        }
+    }
+
+    /// Pac-Man's color for a player slot, if the skin sets one
+    pub fn pacman_color(&self, player: usize) -> Option<Rgb> {
+        self.pacman_colors.get(player).or(self.pacman_colors.last()).copied()
+    }
+
+    /// A ghost's color, if the skin sets one (cycling through the colors given)
+    pub fn ghost_color(&self, ghost: usize) -> Option<Rgb> {
+        match self.ghost_colors.len() {
+            0 => None,
+            count => Some(self.ghost_colors[ghost % count]),
+        }
+    }
+}
+
+/// Every skin: the built-in ones, then the skin files sorted by name
+///
+/// Files that can't be read, or take the name of a skin listed before them,
+/// are reported and left out.
+pub fn load_skins() -> Vec<Skin> {
+    let mut skins: Vec<Skin> = (0..BUILT_IN_SKINS.len()).filter_map(Skin::built_in).collect();
+    for path in list_assets(SKIN_DIR, "skin") {
+        match load_skin(&path) {
+            Ok(skin) if skins.iter().any(|other| other.name == skin.name) => {
+                eprintln!("Ignoring skin {}: there already is a skin named {}", path.display(), skin.name);
+            }
+            Ok(skin) => skins.push(skin),
+            Err(e) => eprintln!("Ignoring skin {}: {}", path.display(), e),
+        }
+    }
+    skins
+}
+
+/// Loads a skin file; the skin is named after the file
+pub fn load_skin(path: &Path) -> Result<Skin, String> {
+    let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        return Err("skin file names can't have spaces".to_string());
+    }
+    let text = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let skin = parse_skin(&text)?;
+    Ok(Skin { name, file: Some(path.to_path_buf()), ..skin })
+}
+
+/// Parses a skin (see the module documentation for the format)
+fn parse_skin(text: &str) -> Result<Skin, String> {
+    let mut skin = Skin::default();
+    let mut ghost_alt = None;
+    let mut lines = text.lines().enumerate();
+    while let Some((line_index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let bad_line = |reason: &str| format!("line {}: {}", line_index + 1, reason);
+        let (key, value) = line.split_once(' ').map(|(key, value)| (key, value.trim())).unwrap_or((line, ""));
+        let sprite = match key {
+            "pacman_colors" => {
+                skin.pacman_colors = parse_colors(value).map_err(|e| bad_line(&e))?;
+                continue;
+            }
+            "ghost_colors" => {
+                skin.ghost_colors = parse_colors(value).map_err(|e| bad_line(&e))?;
+                continue;
+            }
+            "pacman_closed" => &mut skin.pacman[0],
+            "pacman_half" => &mut skin.pacman[1],
+            "pacman_open" => &mut skin.pacman[2],
+            "ghost" => &mut skin.ghost[0],
+            "ghost_alt" => ghost_alt.insert(CLASSIC_GHOST[1]),
+            _ => return Err(bad_line(&format!("unknown key '{}'", key))),
+        };
+        for row in sprite.iter_mut() {
+            let (row_index, pixels) = lines.next().ok_or_else(|| bad_line("the sprite needs six rows"))?;
+            let pixels: Vec<char> = pixels.trim().chars().collect();
+            if pixels.len() != 6 || pixels.iter().any(|&pixel| pixel != 'X' && pixel != '.') {
+                return Err(format!("line {}: a sprite row is six of 'X' and '.'", row_index + 1));
+            }
+            for (bit, &pixel) in row.iter_mut().zip(&pixels) {
+                *bit = pixel == 'X';
+            }
+        }
+    }
+    // A ghost without a second skirt keeps the one it has
+    skin.ghost[1] = ghost_alt.unwrap_or(if skin.ghost[0] == CLASSIC_GHOST[0] { CLASSIC_GHOST[1] } else { skin.ghost[0] });
+    Ok(skin)
+}
+
+/// Reads colors as red, green and blue numbers in a row
+fn parse_colors(value: &str) -> Result<Vec<Rgb>, String> {
+    let numbers = value
+        .split_whitespace()
+        .map(|number| number.parse::<u8>().map_err(|_| format!("'{}' is not a color component (0-255)", number)))
+        .collect::<Result<Vec<u8>, String>>()?;
+    if numbers.is_empty() || numbers.len() % 3 != 0 {
+        return Err("colors are given as red, green and blue".to_string());
+    }
+    Ok(numbers.chunks(3).map(|rgb| (rgb[0], rgb[1], rgb[2])).collect())
+}
+
+/// The skin each profile wears
+#[derive(Default)]
+pub struct SkinChoices {
+    /// Profile name and skin name
+    chosen: Vec<(String, String)>,
+}
+
+impl SkinChoices {
+    /// Loads the skins file (everyone wears the classic skin without one)
+    pub fn load() -> Self {
+        let mut choices = SkinChoices::default();
+        let Ok(text) = fs::read_to_string(save_dir().join(SKINS_FILE)) else {
+            return choices;
+        };
+        for line in text.lines() {
+            let Some(value) = line.trim().strip_prefix("skin ") else { continue };
+            if let Some((skin, profile)) = value.split_once(' ') {
+                choices.set(profile.trim(), skin);
+            }
+        }
+        choices
+    }
+
+    /// Writes the skins file
+    pub fn save(&self) -> Result<(), String> {
+        let text: String = self.chosen.iter().map(|(profile, skin)| format!("skin {} {}\n", skin, profile)).collect();
+        let dir = save_dir();
+        let path = dir.join(SKINS_FILE);
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|e| format!("Could not save skins to {}: {}", path.display(), e))
+    }
+
+    /// Name of the skin a profile wears
+    pub fn get(&self, profile: &str) -> &str {
+        self.chosen(profile).unwrap_or(BUILT_IN_SKINS[0])
+    }
+
+    /// Name of the skin a profile has chosen on this machine, if it has
+    pub fn chosen(&self, profile: &str) -> Option<&str> {
+        self.chosen.iter().find(|(name, _)| name == profile).map(|(_, skin)| skin.as_str())
+    }
+
+    /// Sets the skin a profile wears
+    pub fn set(&mut self, profile: &str, skin: &str) {
+        match self.chosen.iter_mut().find(|(name, _)| name == profile) {
+            Some(chosen) => chosen.1 = skin.to_string(),
+            None => self.chosen.push((profile.to_string(), skin.to_string())),
+        }
+    }
+}
+
+/// Finds a skin by name, falling back to the classic one (e.g. for a skin
+/// file that has since been removed)
+pub fn find_skin<'a>(skins: &'a [Skin], name: &str) -> Option<&'a Skin> {
+    skins.iter().find(|skin| skin.name == name).or(skins.first())
+}
@@ -0,0 +1,295 @@
+//! Software renderer
+//!
+//! A `Renderer` that draws into a plain pixel buffer without SDL, for front ends
+//! that bring their own display: the browser page (`web.rs`) and the libretro
+//! core (`libretro.rs`). The maze is drawn at one pixel per maze pixel (`TILE`
+//! per tile), below a one-line HUD with the score, bombs, the fruit eaten and lives.
+//! Of a two-floor maze only player 1's floor is drawn.
+
+use crate::constants::{
+    GRID_W, GRID_H, TILE, VIEW_W, VIEW_H, MAZE_PALETTES,
+    POWER_PELLET_FLASH_SPEED, PACMAN_MOUTH_ANIMATION_SPEED, FPS
+};
+use crate::font::{digit_glyph, glyph, text_width, GLYPH_W, GLYPH_H};
+use crate::fruit::{FruitSprite, BOMB_SPRITE, DOUBLE_POINTS_SPRITE};
+use crate::game::{Game, GamePhase};
+use crate::maze::{floor_count, is_layout_wall, is_pellet, is_power_pellet, is_stairs, set_active_floor, spawn_points};
+use crate::renderer::Renderer;
+use crate::touch::{TouchButton, TouchLayout};
+
+/// Height of the HUD strip above the maze
+pub const HUD_H: i32 = 8;
+
+/// Size of a frame in pixels
+pub const FRAME_W: i32 = VIEW_W;
+pub const FRAME_H: i32 = VIEW_H + HUD_H;
+
+/// Pac-Man color for each player (same as the SDL renderer)
+const PLAYER_COLORS: [(u8, u8, u8); 2] = [(255, 255, 0), (255, 140, 0)];
+
+/// Body color of each ghost, in ghost order (same as the SDL renderer)
+const GHOST_COLORS: [(u8, u8, u8); 4] = [(255, 0, 0), (255, 184, 255), (0, 255, 255), (255, 184, 82)];
+
+/// 6x6 silhouettes, one row per byte, most significant of the 6 bits on the left
+const PACMAN_SPRITE: [u8; 6] = [0b011110, 0b111111, 0b111000, 0b111000, 0b111111, 0b011110];
+const PACMAN_CLOSED_SPRITE: [u8; 6] = [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b011110];
+const GHOST_SPRITE: [u8; 6] = [0b011110, 0b111111, 0b111111, 0b111111, 0b111111, 0b101101];
+
+/// Width of a digit in the digit font
+const DIGIT_W: i32 = 4;
+
+/// A frame of `FRAME_W` x `FRAME_H` pixels
+pub struct Framebuffer {
+    /// Pixels row by row as 0x00RRGGBB (libretro's XRGB8888)
+    pixels: Vec<u32>,
+    /// Photosensitive mode: nothing flashes, the frightened time bar at the
+    /// bottom of the HUD included (as `RenderCache::photosensitive`)
+    pub photosensitive: bool,
+}
+
+impl Default for Framebuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Framebuffer {
+    pub fn new() -> Self {
+        Self { pixels: vec![0; (FRAME_W * FRAME_H) as usize], photosensitive: false }
+    }
+
+    /// Pixels row by row as 0x00RRGGBB
+    pub fn pixels(&self) -> &[u32] {
+        &self.pixels
+    }
+
+    /// Copies the frame into `out` as RGBA bytes (the layout of an HTML canvas)
+    pub fn write_rgba(&self, out: &mut Vec<u8>) {
+        out.clear();
+        out.extend(self.pixels.iter().flat_map(|&pixel| {
+            [(pixel >> 16) as u8, (pixel >> 8) as u8, pixel as u8, 255]
+        }));
+    }
+
+    /// Draws the on-screen D-pad and pause button, translucent over the frame
+    ///
+    /// # Arguments
+    /// * `layout` - The controls laid out for `FRAME_W` x `FRAME_H`
+    /// * `held` - The D-pad arm being held, drawn brighter
+    pub fn draw_touch_controls(&mut self, layout: &TouchLayout, held: Option<TouchButton>) {
+        let (cx, cy) = layout.center;
+        let cell = layout.cell;
+        self.blend_rect((cx - cell / 2, cy - cell / 2, cell, cell), (255, 255, 255), 50);
+        for arm in TouchButton::ARMS {
+            let alpha = if held == Some(arm) { 140 } else { 70 };
+            self.blend_rect(layout.rect(arm), (255, 255, 255), alpha);
+        }
+        let (x, y, size, _) = layout.rect(TouchButton::Pause);
+        self.blend_rect((x, y, size, size), (255, 255, 255), 70);
+        let bar_w = (size / 6).max(1);
+        self.blend_rect((x + size / 2 - bar_w * 3 / 2, y + size / 4, bar_w, size / 2), (0, 0, 0), 140);
+        self.blend_rect((x + size / 2 + bar_w / 2, y + size / 4, bar_w, size / 2), (0, 0, 0), 140);
+    }
+
+    /// Score on the left, one Pac-Man per life on the right and the fruit
+    /// eaten left of them, the latest nearest
+    fn draw_status(&mut self, game: &Game) {
+        let mut x = 1;
+        for digit in game.score().max(0).to_string().chars().filter_map(|ch| ch.to_digit(10)) {
+            if let Some(rows) = digit_glyph(digit) {
+                self.draw_bits(x, 1, rows, DIGIT_W, (255, 255, 255));
+            }
+            x += DIGIT_W + 1;
+        }
+        if game.double_points_timer > 0 {
+            // Multiplier badge: "x2" and the seconds left, in the pickup's magenta
+            let color = DOUBLE_POINTS_SPRITE.body_color;
+            let ticks_left = game.config.base_ticks(game.double_points_timer as u32) as i32;
+            let seconds = (ticks_left + FPS as i32 - 1) / FPS as i32;
+            x += 2;
+            self.draw_bits(x, 1, glyph('x'), GLYPH_W, color);
+            x += GLYPH_W + 1;
+            let badge = format!("{} {}", game.score_multiplier(), seconds);
+            for digit in badge.chars() {
+                if let Some(rows) = digit.to_digit(10).and_then(digit_glyph) {
+                    self.draw_bits(x, 1, rows, DIGIT_W, color);
+                }
+                x += DIGIT_W + 1;
+            }
+        }
+        if floor_count() > 1 {
+            // Two-floor maze: the floor shown, 1 or 2, in gray
+            if let Some(rows) = digit_glyph(game.view_floor() as u32 + 1) {
+                self.draw_bits(x + 2, 1, rows, DIGIT_W, (150, 150, 150));
+            }
+        }
+        for life in 0..game.lives.max(0) {
+            let x = FRAME_W - (life + 1) * (TILE + 1);
+            self.draw_bits(x, 1, &PACMAN_SPRITE, 6, PLAYER_COLORS[0]);
+        }
+        let fruits_right = FRAME_W - game.lives.max(0) * (TILE + 1) - 2;
+        for (i, &fruit) in game.fruits.iter().rev().enumerate() {
+            self.draw_fruit(fruits_right - (i as i32 + 1) * (TILE + 1), 1, &fruit.sprite());
+        }
+        // Bombs carried, left of the fruit
+        let bombs_right = fruits_right - game.fruits.len() as i32 * (TILE + 1) - 2;
+        for i in 0..game.bombs.max(0) {
+            self.draw_fruit(bombs_right - (i + 1) * (TILE + 1), 1, &BOMB_SPRITE);
+        }
+    }
+
+    /// Frightened ghosts' color this frame: blue, flashing white near the end
+    fn frightened_color(&self, game: &Game) -> (u8, u8, u8) {
+        if game.frightened_flash_white() && !self.photosensitive {
+            (255, 255, 255)
+        } else {
+            (0, 100, 255)
+        }
+    }
+
+    /// A bonus fruit's (or the double-points pickup's) two-color icon (see `Fruit::sprite`)
+    fn draw_fruit(&mut self, x: i32, y: i32, sprite: &FruitSprite) {
+        self.draw_bits(x, y, &sprite.body, 6, sprite.body_color);
+        self.draw_bits(x, y, &sprite.detail, 6, sprite.detail_color);
+    }
+
+    /// Centered text on a black strip across the middle of the maze
+    fn draw_banner(&mut self, text: &str) {
+        let y = HUD_H + VIEW_H / 2 - GLYPH_H / 2;
+        self.fill_rect(0, y - 2, FRAME_W, GLYPH_H + 4, (0, 0, 0));
+        let mut x = FRAME_W / 2 - text_width(text, 1) / 2;
+        for ch in text.chars() {
+            self.draw_bits(x, y, glyph(ch), GLYPH_W, (255, 255, 0));
+            x += text_width(" ", 1);
+        }
+    }
+
+    /// Fills a rectangle (clipped to the frame)
+    fn fill_rect(&mut self, x: i32, y: i32, w: i32, h: i32, color: (u8, u8, u8)) {
+        let pixel = (color.0 as u32) << 16 | (color.1 as u32) << 8 | color.2 as u32;
+        let (left, right) = (x.max(0), (x + w).min(FRAME_W));
+        if left >= right {
+            return;
+        }
+        for py in y.max(0)..(y + h).min(FRAME_H) {
+            let row = (py * FRAME_W) as usize;
+            self.pixels[row + left as usize..row + right as usize].fill(pixel);
+        }
+    }
+
+    /// Mixes a color into a rectangle (clipped to the frame), `alpha` out of 255
+    fn blend_rect(&mut self, (x, y, w, h): (i32, i32, i32, i32), color: (u8, u8, u8), alpha: u32) {
+        let mix = |under: u32, over: u8| (under * (255 - alpha) + over as u32 * alpha) / 255;
+        for py in y.max(0)..(y + h).min(FRAME_H) {
+            for px in x.max(0)..(x + w).min(FRAME_W) {
+                let pixel = &mut self.pixels[(py * FRAME_W + px) as usize];
+                let (r, g, b) = ((*pixel >> 16) & 0xFF, (*pixel >> 8) & 0xFF, *pixel & 0xFF);
+                *pixel = mix(r, color.0) << 16 | mix(g, color.1) << 8 | mix(b, color.2);
+            }
+        }
+    }
+
+    /// Draws a 1-bit bitmap, one row per byte with the leftmost of `width` bits first
+    fn draw_bits(&mut self, x: i32, y: i32, rows: &[u8], width: i32, color: (u8, u8, u8)) {
+        for (row, &bits) in rows.iter().enumerate() {
+            for col in 0..width {
+                if (bits >> (width - 1 - col)) & 1 != 0 {
+                    self.fill_rect(x + col, y + row as i32, 1, 1, color);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for Framebuffer {
+    fn draw_maze(&mut self, game: &Game) -> Result<(), String> {
+        self.pixels.fill(0);
+        let palette = &MAZE_PALETTES[(game.level.max(1) as usize - 1) % MAZE_PALETTES.len()];
+        let power_flash = !self.photosensitive && (game.animation_frame() / POWER_PELLET_FLASH_SPEED).is_multiple_of(2);
+        let floor = game.view_floor();
+        set_active_floor(floor);
+        let eaten_on_floor = game.floor_eaten(floor);
+        for y in 0..GRID_H {
+            for x in 0..GRID_W {
+                let eaten = eaten_on_floor.get((y * GRID_W + x) as usize).copied().unwrap_or(true);
+                let (px, py) = (x * TILE, HUD_H + y * TILE);
+                if game.is_open_wall(x, y) {
+                    // Blown up: just the outline, until it heals
+                    for (ex, ey, ew, eh) in [(0, 0, TILE, 1), (0, TILE - 1, TILE, 1), (0, 0, 1, TILE), (TILE - 1, 0, 1, TILE)] {
+                        self.fill_rect(px + ex, py + ey, ew, eh, palette.wall);
+                    }
+                } else if is_layout_wall(x, y) {
+                    self.fill_rect(px, py, TILE, TILE, palette.wall);
+                } else if is_power_pellet(x, y) && !eaten {
+                    let color = if power_flash { (0, 255, 255) } else { (255, 255, 255) };
+                    self.fill_rect(px + TILE / 2 - 2, py + TILE / 2 - 2, 4, 4, color);
+                } else if is_pellet(x, y) && !eaten {
+                    self.fill_rect(px + TILE / 2 - 1, py + TILE / 2 - 1, 2, 2, palette.pellet);
+                } else if is_stairs(x, y) {
+                    for step in 0..3 {
+                        self.fill_rect(px + 1 + step * 2, py + TILE - 3 - step * 2, TILE - 2 - step * 2, 2, palette.pellet);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_entities(&mut self, game: &Game) -> Result<(), String> {
+        // Only what is on the floor shown; the pickups are on the ground floor
+        let floor = game.view_floor();
+        if game.fruit_timer > 0 && floor == 0 {
+            let (x, y) = spawn_points().fruit;
+            self.draw_fruit(x * TILE, HUD_H + y * TILE, &game.level_fruit().sprite());
+        }
+        if game.double_points_pickup > 0 && floor == 0 {
+            let (x, y) = spawn_points().players[0];
+            self.draw_fruit(x * TILE, HUD_H + y * TILE, &DOUBLE_POINTS_SPRITE);
+        }
+        if game.bomb_pickup > 0 && floor == 0 {
+            let (x, y) = spawn_points().fruit;
+            self.draw_fruit(x * TILE, HUD_H + y * TILE, &BOMB_SPRITE);
+        }
+
+        let mouth_open = (game.animation_frame() / PACMAN_MOUTH_ANIMATION_SPEED).is_multiple_of(2);
+        for (i, player) in game.players.iter().enumerate().filter(|(_, player)| player.floor == floor) {
+            let sprite = if mouth_open { &PACMAN_SPRITE } else { &PACMAN_CLOSED_SPRITE };
+            let color = PLAYER_COLORS[(i + game.turn) % PLAYER_COLORS.len()];
+            self.draw_bits(player.x * TILE, HUD_H + player.y * TILE, sprite, 6, color);
+        }
+
+        // The bonus stage and its results card are ghost-free
+        if game.phase == GamePhase::Playing {
+            for (i, ghost) in game.ghosts.iter().enumerate().filter(|(_, ghost)| ghost.floor == floor) {
+                let color = if ghost.vulnerable {
+                    self.frightened_color(game)
+                } else {
+                    GHOST_COLORS[i % GHOST_COLORS.len()]
+                };
+                self.draw_bits(ghost.x * TILE, HUD_H + ghost.y * TILE, &GHOST_SPRITE, 6, color);
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_hud(&mut self, game: &Game) -> Result<(), String> {
+        self.draw_status(game);
+        // The frightened time left, in the frightened ghosts' color
+        if game.power_pellet_timer > 0 {
+            let w = (FRAME_W as f32 * game.power_remaining()) as i32;
+            let color = self.frightened_color(game);
+            self.fill_rect(0, HUD_H - 1, w, 1, color);
+        }
+        if !game.alive {
+            self.draw_banner("GAME OVER");
+        } else if game.paused {
+            self.draw_banner("PAUSED");
+        }
+        Ok(())
+    }
+
+    /// Nothing to do: the owner reads `pixels()` once the frame is drawn
+    fn present(&mut self) -> Result<(), String> {
+        Ok(())
+    }
+}
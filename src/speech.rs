@@ -0,0 +1,198 @@
+//! Spoken announcements, for players who can't (or would rather not) read the screen
+//!
+//! With "Announcements" on in the audio settings, key moments of a game are
+//! spoken ("Power pellet, ghosts vulnerable", "Level three", "Game over,
+//! twelve thousand points") and so is every menu entry as it is selected, in
+//! every mode. The game announces through a `Speaker`, which subscribes to
+//! the event bus like the audio does and hands the lines to a
+//! `SpeechBackend` on its own thread. `SystemSpeech` uses the operating
+//! system's voice: System.Speech on Windows, `say` on macOS and
+//! speech-dispatcher (`spd-say`, the service screen readers such as Orca
+//! speak through) elsewhere.
+
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{channel, Sender};
+use std::sync::Arc;
+use crate::events::EventSubscriber;
+use crate::game::{Game, GameEvent};
+
+/// Something that can speak a line of text
+pub trait SpeechBackend: Send {
+    /// Starts speaking, cutting short whatever was being said
+    fn speak(&mut self, text: &str) -> Result<(), String>;
+}
+
+/// The operating system's own text-to-speech
+#[derive(Default)]
+pub struct SystemSpeech {
+    /// The line being spoken
+    current: Option<Child>,
+}
+
+impl SpeechBackend for SystemSpeech {
+    fn speak(&mut self, text: &str) -> Result<(), String> {
+        if let Some(mut child) = self.current.take() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        let child = speech_command(text)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| format!("Could not start the system voice: {}", e))?;
+        self.current = Some(child);
+        Ok(())
+    }
+}
+
+/// The command speaking `text` on this system
+fn speech_command(text: &str) -> Command {
+    if cfg!(target_os = "windows") {
+        // The text goes through the environment, so nothing in it is read as script
+        let mut command = Command::new("powershell");
+        command.args([
+            "-NoProfile", "-NonInteractive", "-Command",
+            "Add-Type -AssemblyName System.Speech; (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak($env:PACLIKE_SPEECH)",
+        ]);
+        command.env("PACLIKE_SPEECH", text);
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("say");
+        command.arg(text);
+        command
+    } else {
+        let mut command = Command::new("spd-say");
+        command.arg("--").arg(text);
+        command
+    }
+}
+
+/// Says announcements, when they are turned on
+///
+/// Clones share the switch and the voice, so the one subscribed to the event
+/// bus and the one the menus use go on and off together.
+#[derive(Clone)]
+pub struct Speaker {
+    enabled: Arc<AtomicBool>,
+    lines: Sender<String>,
+}
+
+impl Speaker {
+    /// Starts a thread speaking through `backend`, so a slow voice never holds up a frame
+    ///
+    /// # Arguments
+    /// * `enabled` - Whether announcements start on
+    pub fn start(mut backend: Box<dyn SpeechBackend>, enabled: bool) -> Self {
+        let (lines, receiver) = channel::<String>();
+        std::thread::spawn(move || {
+            let mut reported = false;
+            while let Ok(mut text) = receiver.recv() {
+                // Of a burst of lines, only the latest is worth saying
+                while let Ok(newer) = receiver.try_recv() {
+                    text = newer;
+                }
+                if let Err(e) = backend.speak(&text) {
+                    if !reported {
+                        eprintln!("{}", e);
+                        reported = true;
+                    }
+                }
+            }
+        });
+        Self { enabled: Arc::new(AtomicBool::new(enabled)), lines }
+    }
+
+    /// Turns announcements on or off
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// Says a line, if announcements are on
+    pub fn say(&self, text: &str) {
+        if self.enabled.load(Ordering::Relaxed) {
+            // The voice thread only stops with the game
+            let _ = self.lines.send(text.to_string());
+        }
+    }
+}
+
+/// Game events, announced
+impl EventSubscriber for Speaker {
+    fn on_event(&mut self, game: &Game, event: &GameEvent) {
+        if let Some(text) = announcement(game, event) {
+            self.say(&text);
+        }
+    }
+}
+
+/// What is said for a gameplay event, if anything
+pub fn announcement(game: &Game, event: &GameEvent) -> Option<String> {
+    match *event {
+        GameEvent::PowerPelletEaten { .. } => Some("Power pellet, ghosts vulnerable".to_string()),
+        GameEvent::PowerPelletEnded => Some("Ghosts back to normal".to_string()),
+        GameEvent::GhostEaten { points, .. } => Some(format!("Ghost, {} points", number_words(points as i64))),
+        GameEvent::FruitSpawned => Some(game.level_fruit().name().to_string()),
+        GameEvent::FruitEaten { fruit, points, .. } => Some(format!("{}, {} points", fruit.name(), number_words(points as i64))),
+        GameEvent::DoublePointsSpawned => Some("Double points pickup".to_string()),
+        GameEvent::DoublePointsTaken { .. } => Some("Double points".to_string()),
+        GameEvent::BombSpawned => Some("Bomb".to_string()),
+        GameEvent::BombTaken { .. } => Some("Bomb taken, push against a wall".to_string()),
+        GameEvent::WallBlasted { .. } => Some("Wall down".to_string()),
+        GameEvent::PelletsRespawned { count } => Some(format!("{} pellets back", number_words(count as i64))),
+        GameEvent::ExtraLife { lives } => Some(format!("Extra life, {} lives", number_words(lives as i64))),
+        // The last life is announced as the game over
+        GameEvent::PlayerDied { lives_left } if lives_left > 0 => {
+            Some(format!("Life lost, {} left", number_words(lives_left as i64)))
+        }
+        GameEvent::LevelCleared { level } => Some(format!("Level {}", number_words(level as i64 + 1))),
+        GameEvent::GameOver => Some(format!("Game over, {} points", number_words(game.score() as i64))),
+        GameEvent::PelletEaten { .. } | GameEvent::PlayerDied { .. } | GameEvent::WallHealed { .. } |
+        GameEvent::ScoreChanged { .. } => None,
+    }
+}
+
+/// A number in English words, e.g. 12400 as "twelve thousand four hundred"
+pub fn number_words(number: i64) -> String {
+    const ONES: [&str; 20] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+        "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+    ];
+    const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+    const SCALES: [(i64, &str); 3] = [(1_000_000_000, "billion"), (1_000_000, "million"), (1_000, "thousand")];
+
+    /// 1 to 999
+    fn below_thousand(number: i64) -> String {
+        let mut words = Vec::new();
+        if number >= 100 {
+            words.push(format!("{} hundred", ONES[(number / 100) as usize]));
+        }
+        match number % 100 {
+            0 => {}
+            rest if rest < 20 => words.push(ONES[rest as usize].to_string()),
+            rest if rest % 10 == 0 => words.push(TENS[(rest / 10) as usize].to_string()),
+            rest => words.push(format!("{}-{}", TENS[(rest / 10) as usize], ONES[(rest % 10) as usize])),
+        }
+        words.join(" ")
+    }
+
+    if number < 0 {
+        return format!("minus {}", number_words(-number));
+    }
+    if number == 0 {
+        return ONES[0].to_string();
+    }
+    let mut words = Vec::new();
+    let mut rest = number;
+    for (scale, name) in SCALES {
+        if rest >= scale {
+            words.push(format!("{} {}", number_words(rest / scale), name));
+            rest %= scale;
+        }
+    }
+    if rest > 0 {
+        words.push(below_thousand(rest));
+    }
+    words.join(" ")
+}
@@ -0,0 +1,514 @@
+//! Game state dumps: the whole game as JSON, for bug reports
+//!
+//! **F4** during a game writes `saves/states/state-<time>.json`, and
+//! `--load-state <file>` starts the game straight from such a dump, so a
+//! reported bug can be played from the exact tick it was seen at. Everything
+//! `Game::tick` depends on is in it:
+//!
+//! ```text
+//! {
+//!   "format": "paclike-state 1",
+//!   "config": { "mode": "single", "player1": "pacman", "player2": "-", ... },
+//!   "maze": { "built_in": 1, "hash": "35ab8bf4459695cf", "rows": ["####...", ...] },
+//!   "rng": { "seed": "ace1", "ghosts": ["5a3c", "91e2", "0b7d"], "pacman_ai": "44f0", "fruit": "c3a1", "bomb": "7e02", "respawn": "19d6" },
+//!   "frame": 5120,
+//!   "timers": { "power_pellet": 0, "ghosts_eaten": 0, "ticks_since_dot": 12, ... },
+//!   "players": [ { "x": 13, "y": 23, "dx": -1, "dy": 0, ... } ],
+//!   "ghosts": [ ... ],
+//!   "eaten": ["0000000000000000000000000000", ...],
+//!   "state_hash": "5d0c1e9a2b7f4431",
+//!   ...
+//! }
+//! ```
+//!
+//! `eaten` has one string per maze row, `1` for every eaten pellet. The maze
+//! rows are always written, so dumps of `--maze` games load without the maze
+//! file; `built_in` only says which built-in maze (1 or 2) it is, if any. A
+//! two-floor maze adds `upper_rows`, and its `eaten` rows run on through the
+//! upper floor; every Pac-Man and ghost has the `floor` it is on.
+//! Hex numbers are strings, as JSON has no hex.
+//!
+//! A loaded time trial starts its clock again, and a campaign game is played
+//! as a single maze (the campaign's name and stage are in the dump, to read).
+//! Gameplay hooks are the ones of the game loading the dump.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::constants::{GRID_W, GRID_H, MAZE_1, MAZE_2, TICK_RATES};
+use crate::fruit::Fruit;
+use crate::game::{Game, GamePhase, OpenWall, TurnState};
+use crate::game_config::{GameConfig, GhostPreset, PacmanStyle};
+use crate::hooks::Tweaks;
+use crate::json::Json;
+use crate::maze::{floor_count, floor_rows, maze_hash, parse_maze, set_current_maze, FLOOR_SEPARATOR};
+use crate::player::Player;
+use crate::rng::Lfsr;
+use crate::scoring::{Scoring, ScoreSource};
+use crate::storage::save_path;
+use crate::tas::{mode_name, parse_mode, role_name, parse_role, targeting_name, parse_targeting};
+
+/// `format` of every dump (bumped when the layout changes)
+const STATE_FORMAT: &str = "paclike-state 1";
+
+/// Save subdirectory for dumps
+const STATE_DIR: &str = "states";
+
+/// Writes the game to a new dump in the save directory
+///
+/// # Returns
+/// Path of the dump
+pub fn save_state_dump(game: &Game) -> Result<PathBuf, String> {
+    let stamp = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0);
+    save_path(STATE_DIR, &format!("state-{}.json", stamp))
+        .and_then(|path| fs::write(&path, state_to_json(game).pretty()).map(|_| path))
+        .map_err(|e| format!("Could not dump the game state: {}", e))
+}
+
+/// Loads a dump, making its maze the current one
+pub fn load_state_dump(path: &Path) -> Result<Game, String> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+    Json::parse(&text)
+        .and_then(|json| state_from_json(&json))
+        .map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Index (from 1) of the built-in maze that is current, if it is one
+fn built_in_maze() -> Option<usize> {
+    if floor_count() > 1 {
+        return None;
+    }
+    let maze = floor_rows(0);
+    [&MAZE_1, &MAZE_2].iter().position(|built_in| built_in[..] == *maze).map(|index| index + 1)
+}
+
+fn hex16(value: u16) -> Json {
+    Json::string(format!("{:04x}", value))
+}
+
+/// A pellet bitmap as one string of 0s and 1s per maze row
+fn bitmap(eaten: &[bool]) -> Json {
+    Json::Array(
+        eaten.chunks(GRID_W as usize)
+            .map(|row| Json::String(row.iter().map(|&eaten| if eaten { '1' } else { '0' }).collect()))
+            .collect()
+    )
+}
+
+/// The whole game as JSON
+pub fn state_to_json(game: &Game) -> Json {
+    let config = &game.config;
+    let (phase, phase_timer) = match game.phase {
+        GamePhase::Playing => ("playing", 0),
+        GamePhase::BonusStage { timer } => ("bonus", timer),
+        GamePhase::BonusResults { timer } => ("results", timer),
+    };
+    let players = game.players.iter()
+        .map(|player| Json::object([
+            ("x", Json::number(player.x)),
+            ("y", Json::number(player.y)),
+            ("dx", Json::number(player.dx)),
+            ("dy", Json::number(player.dy)),
+            ("sub_frame_counter", Json::number(player.sub_frame_counter)),
+            ("queued_dx", Json::number(player.queued_dx)),
+            ("queued_dy", Json::number(player.queued_dy)),
+            ("queued_ticks", Json::number(player.queued_ticks)),
+            ("floor", Json::number(player.floor as i64)),
+        ]))
+        .collect();
+    let ghosts = game.ghosts.iter()
+        .map(|ghost| Json::object([
+            ("x", Json::number(ghost.x)),
+            ("y", Json::number(ghost.y)),
+            ("dx", Json::number(ghost.dx)),
+            ("dy", Json::number(ghost.dy)),
+            ("sub_frame_counter", Json::number(ghost.sub_frame_counter)),
+            ("think_timer", Json::number(ghost.think_timer)),
+            ("vulnerable", Json::Bool(ghost.vulnerable)),
+            ("target", Json::Array(vec![Json::number(ghost.target.0), Json::number(ghost.target.1)])),
+            ("in_house", Json::Bool(ghost.in_house)),
+            ("floor", Json::number(ghost.floor as i64)),
+        ]))
+        .collect();
+    let waiting_turn = match game.waiting_turn {
+        Some(ref waiting) => Json::object([
+            ("score", Json::number(waiting.scoring.score())),
+            ("points", breakdown(&waiting.scoring)),
+            ("lives", Json::number(waiting.lives)),
+            ("level", Json::number(waiting.level)),
+            ("pellets", Json::number(waiting.pellets)),
+            ("eaten", bitmap(&waiting.eaten)),
+            ("fruits", fruit_names(&waiting.fruits)),
+        ]),
+        None => Json::Null,
+    };
+    let time_trial = match game.time_trial {
+        Some(ref trial) => Json::object([("frames", Json::number(trial.frames)), ("finished", Json::Bool(trial.finished))]),
+        None => Json::Null,
+    };
+    let campaign = match game.campaign {
+        Some(ref run) => Json::object([("name", Json::string(&run.campaign.name)), ("stage", Json::number(run.stage as i64))]),
+        None => Json::Null,
+    };
+    Json::object([
+        ("format", Json::string(STATE_FORMAT)),
+        ("config", Json::object([
+            ("mode", Json::string(mode_name(config.mode))),
+            ("player1", Json::string(role_name(Some(config.player1_role)))),
+            ("player2", Json::string(role_name(config.player2_role))),
+            ("cornering", Json::number(config.cornering_window)),
+            ("targeting", Json::string(targeting_name(config.arcade_quirks))),
+            ("ghosts", Json::string(config.ghost_preset.name().to_ascii_lowercase())),
+            ("pacman", Json::string(config.pacman_style.name().to_ascii_lowercase())),
+            ("tick_rate", Json::number(config.tick_rate)),
+            ("ice", Json::Bool(config.ice)),
+            ("ng_plus", Json::Bool(config.ng_plus)),
+        ])),
+        ("maze", Json::object([
+            ("built_in", built_in_maze().map(|index| Json::number(index as i64)).unwrap_or(Json::Null)),
+            ("hash", Json::string(format!("{:016x}", maze_hash()))),
+            ("rows", Json::Array(floor_rows(0).iter().map(|&row| Json::string(row)).collect())),
+            ("upper_rows", if floor_count() > 1 {
+                Json::Array(floor_rows(1).iter().map(|&row| Json::string(row)).collect())
+            } else {
+                Json::Null
+            }),
+        ])),
+        ("rng", Json::object([
+            ("seed", hex16(game.rng.seed())),
+            ("ghosts", Json::Array(game.rng.ghosts.iter().map(|stream| hex16(stream.state())).collect())),
+            ("pacman_ai", hex16(game.rng.pacman_ai.state())),
+            ("fruit", hex16(game.rng.fruit.state())),
+            ("bomb", hex16(game.rng.bomb.state())),
+            ("respawn", hex16(game.rng.respawn.state())),
+        ])),
+        ("frame", Json::number(game.frame)),
+        ("alive", Json::Bool(game.alive)),
+        ("paused", Json::Bool(game.paused)),
+        ("score", Json::number(game.score())),
+        ("points", breakdown(&game.scoring)),
+        ("lives", Json::number(game.lives)),
+        ("level", Json::number(game.level)),
+        ("pellets", Json::number(game.pellets)),
+        ("phase", Json::string(phase)),
+        ("turn", Json::number(game.turn as i64)),
+        ("timers", Json::object([
+            ("power_pellet", Json::number(game.power_pellet_timer)),
+            ("power_frozen", Json::Bool(game.power_frozen)),
+            ("ghosts_eaten", Json::number(game.ghost_eaten_count)),
+            ("ghost_dot_counters", Json::Array(game.ghost_dot_counters.iter().map(|&count| Json::number(count)).collect())),
+            ("global_dot_counter", game.global_dot_counter.map(Json::number).unwrap_or(Json::Null)),
+            ("ticks_since_dot", Json::number(game.ticks_since_dot)),
+            ("phase", Json::number(phase_timer)),
+        ])),
+        ("bonus", Json::object([("haul", Json::number(game.bonus_haul)), ("pellets", Json::number(game.bonus_pellets))])),
+        ("fruit", Json::object([("timer", Json::number(game.fruit_timer)), ("eaten", fruit_names(&game.fruits))])),
+        ("double_points", Json::object([
+            ("pickup", Json::number(game.double_points_pickup)),
+            ("timer", Json::number(game.double_points_timer)),
+        ])),
+        ("bombs", Json::object([
+            ("pickup", Json::number(game.bomb_pickup)),
+            ("held", Json::number(game.bombs)),
+            ("fuse", Json::number(game.bomb_fuse)),
+            ("open_walls", Json::Array(game.open_walls.iter().map(|wall| Json::object([
+                ("x", Json::number(wall.x)),
+                ("y", Json::number(wall.y)),
+                ("ticks", Json::number(wall.ticks)),
+            ])).collect())),
+        ])),
+        ("tweaks", Json::object([
+            ("ghost_speed", Json::number(game.tweaks.ghost_speed)),
+            ("pacman_speed", Json::number(game.tweaks.pacman_speed)),
+            ("frightened_ticks", Json::number(game.tweaks.frightened_ticks)),
+        ])),
+        ("players", Json::Array(players)),
+        ("ghosts", Json::Array(ghosts)),
+        ("eaten", bitmap(&game.eaten)),
+        ("waiting_turn", waiting_turn),
+        ("time_trial", time_trial),
+        ("campaign", campaign),
+        ("state_hash", Json::string(format!("{:016x}", game.state_hash()))),
+    ])
+}
+
+/// Fruit as an array of their lower-case names
+fn fruit_names(fruits: &[Fruit]) -> Json {
+    Json::Array(fruits.iter().map(|fruit| Json::string(fruit.name().to_ascii_lowercase())).collect())
+}
+
+/// Reads fruit written by `fruit_names`
+fn parse_fruits(json: &Json, key: &str) -> Result<Vec<Fruit>, String> {
+    array(json, key)?.iter()
+        .map(|name| name.as_str().and_then(Fruit::from_name))
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("bad {}", key))
+}
+
+/// A value of an object, or an error naming the missing key
+/// A score's breakdown as an object keyed by `ScoreSource::name`
+fn breakdown(scoring: &Scoring) -> Json {
+    Json::object(ScoreSource::ALL.map(|source| (source.name(), Json::number(scoring.breakdown()[source.index()]))))
+}
+
+/// Reads `score` and its `points` breakdown
+fn parse_scoring(json: &Json) -> Result<Scoring, String> {
+    let mut breakdown = [0; ScoreSource::ALL.len()];
+    // Dumps from before the score breakdown have no points
+    if let Some(points) = json.get("points") {
+        for source in ScoreSource::ALL {
+            breakdown[source.index()] = int(points, source.name())?;
+        }
+    }
+    Ok(Scoring::restore(int(json, "score")?, breakdown))
+}
+
+fn field<'a>(json: &'a Json, key: &str) -> Result<&'a Json, String> {
+    json.get(key).ok_or_else(|| format!("no {}", key))
+}
+
+fn int(json: &Json, key: &str) -> Result<i32, String> {
+    field(json, key)?.as_i64().and_then(|value| i32::try_from(value).ok()).ok_or_else(|| format!("bad {}", key))
+}
+
+fn flag(json: &Json, key: &str) -> Result<bool, String> {
+    field(json, key)?.as_bool().ok_or_else(|| format!("bad {}", key))
+}
+
+/// The floor a Pac-Man or ghost is on (dumps from before two-floor mazes have none)
+fn floor(json: &Json) -> Result<usize, String> {
+    match json.get("floor") {
+        Some(_) => Ok(int(json, "floor")?.clamp(0, floor_count() as i32 - 1) as usize),
+        None => Ok(0),
+    }
+}
+
+fn text<'a>(json: &'a Json, key: &str) -> Result<&'a str, String> {
+    field(json, key)?.as_str().ok_or_else(|| format!("bad {}", key))
+}
+
+fn array<'a>(json: &'a Json, key: &str) -> Result<&'a [Json], String> {
+    field(json, key)?.as_array().ok_or_else(|| format!("bad {}", key))
+}
+
+/// Reads a hex string written by `hex16`
+fn stream(json: &Json, what: &str) -> Result<Lfsr, String> {
+    json.as_str()
+        .and_then(|value| u16::from_str_radix(value, 16).ok())
+        .map(Lfsr::new)
+        .ok_or_else(|| format!("bad {}", what))
+}
+
+/// Reads a pellet bitmap written by `bitmap`
+fn parse_bitmap(json: &Json, key: &str) -> Result<Vec<bool>, String> {
+    let rows = array(json, key)?;
+    let eaten: Vec<bool> = rows.iter()
+        .map(|row| row.as_str().filter(|row| row.len() == GRID_W as usize))
+        .collect::<Option<Vec<&str>>>()
+        .ok_or_else(|| format!("bad {}", key))?
+        .concat()
+        .chars()
+        .map(|c| match c {
+            '0' => Some(false),
+            '1' => Some(true),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or_else(|| format!("bad {}", key))?;
+    if eaten.len() != floor_count() * (GRID_W * GRID_H) as usize {
+        return Err(format!("bad {}", key));
+    }
+    Ok(eaten)
+}
+
+/// Makes the dump's maze the current one, checking it against its hash
+fn load_maze(maze: &Json) -> Result<(), String> {
+    let built_in = match field(maze, "built_in")? {
+        Json::Null => None,
+        index => match index.as_i64() {
+            Some(1) => Some(&MAZE_1),
+            Some(2) => Some(&MAZE_2),
+            _ => return Err("bad maze built_in".to_string()),
+        },
+    };
+    match built_in {
+        Some(maze) => set_current_maze(maze),
+        None => {
+            let mut rows: Vec<&str> = array(maze, "rows")?.iter()
+                .map(Json::as_str)
+                .collect::<Option<_>>()
+                .ok_or("bad maze rows")?;
+            // Dumps from before two-floor mazes have no upper floor
+            if maze.get("upper_rows").is_some_and(|upper| *upper != Json::Null) {
+                rows.push(FLOOR_SEPARATOR);
+                for row in array(maze, "upper_rows")? {
+                    rows.push(row.as_str().ok_or("bad maze upper_rows")?);
+                }
+            }
+            set_current_maze(parse_maze(&rows.join("\n"), "in the state dump")?);
+        }
+    }
+    let hash = text(maze, "hash")?;
+    if format!("{:016x}", maze_hash()) != hash {
+        return Err(format!("the maze is not the one dumped (hash {})", hash));
+    }
+    Ok(())
+}
+
+/// Reads a game written by `state_to_json`, making its maze the current one
+pub fn state_from_json(json: &Json) -> Result<Game, String> {
+    if json.get("format").and_then(Json::as_str) != Some(STATE_FORMAT) {
+        return Err("not a state dump from this version of the game".to_string());
+    }
+    load_maze(field(json, "maze")?)?;
+
+    let config_json = field(json, "config")?;
+    let mode = parse_mode(text(config_json, "mode")?).ok_or("bad mode")?;
+    let player1_role = parse_role(text(config_json, "player1")?).ok_or("bad player1 role")?;
+    let player2_role = parse_role(text(config_json, "player2")?);
+    let mut config = GameConfig::new(mode, player1_role, player2_role);
+    config.cornering_window = int(config_json, "cornering")?;
+    config.arcade_quirks = parse_targeting(text(config_json, "targeting")?).ok_or("bad targeting")?;
+    config.ghost_preset = GhostPreset::from_name(text(config_json, "ghosts")?).ok_or("bad ghosts")?;
+    config.pacman_style = PacmanStyle::from_name(text(config_json, "pacman")?).ok_or("bad pacman style")?;
+    config.tick_rate = Some(int(config_json, "tick_rate")? as u32)
+        .filter(|rate| TICK_RATES.contains(rate))
+        .ok_or("bad tick_rate")?;
+    // Dumps from before ice mazes were off the ice
+    if config_json.get("ice").is_some() {
+        config.ice = flag(config_json, "ice")?;
+    }
+    // Dumps from before new game plus weren't in it
+    if config_json.get("ng_plus").is_some() {
+        config.ng_plus = flag(config_json, "ng_plus")?;
+    }
+
+    let rng = field(json, "rng")?;
+    let seed = stream(field(rng, "seed")?, "seed")?.state();
+    let mut game = Game::new_with_seed(config, seed);
+    let [g0, g1, g2] = array(rng, "ghosts")? else { return Err("bad rng ghosts".to_string()) };
+    game.rng.ghosts = [stream(g0, "rng ghosts")?, stream(g1, "rng ghosts")?, stream(g2, "rng ghosts")?];
+    game.rng.pacman_ai = stream(field(rng, "pacman_ai")?, "rng pacman_ai")?;
+    game.rng.fruit = stream(field(rng, "fruit")?, "rng fruit")?;
+    // Dumps from before the bomb stream keep the one derived from the seed
+    if let Some(bomb) = rng.get("bomb") {
+        game.rng.bomb = stream(bomb, "rng bomb")?;
+    }
+    // Dumps from before the pellet respawn stream keep the one derived from the seed
+    if let Some(respawn) = rng.get("respawn") {
+        game.rng.respawn = stream(respawn, "rng respawn")?;
+    }
+
+    game.frame = int(json, "frame")? as u32;
+    game.alive = flag(json, "alive")?;
+    game.paused = flag(json, "paused")?;
+    game.scoring = parse_scoring(json)?;
+    game.lives = int(json, "lives")?;
+    game.level = int(json, "level")? as u32;
+    game.pellets = int(json, "pellets")?;
+    game.turn = int(json, "turn")? as usize;
+
+    let timers = field(json, "timers")?;
+    let phase_timer = int(timers, "phase")?;
+    game.phase = match text(json, "phase")? {
+        "playing" => GamePhase::Playing,
+        "bonus" => GamePhase::BonusStage { timer: phase_timer },
+        "results" => GamePhase::BonusResults { timer: phase_timer },
+        _ => return Err("bad phase".to_string()),
+    };
+    game.power_pellet_timer = int(timers, "power_pellet")?;
+    game.power_frozen = flag(timers, "power_frozen")?;
+    game.ghost_eaten_count = int(timers, "ghosts_eaten")?;
+    let counters: Vec<u32> = array(timers, "ghost_dot_counters")?.iter()
+        .map(|count| count.as_i64().and_then(|count| u32::try_from(count).ok()))
+        .collect::<Option<_>>()
+        .ok_or("bad ghost_dot_counters")?;
+    game.ghost_dot_counters = counters.try_into().map_err(|_| "bad ghost_dot_counters")?;
+    game.global_dot_counter = match field(timers, "global_dot_counter")? {
+        Json::Null => None,
+        _ => Some(int(timers, "global_dot_counter")? as u32),
+    };
+    game.ticks_since_dot = int(timers, "ticks_since_dot")?;
+
+    let bonus = field(json, "bonus")?;
+    game.bonus_haul = int(bonus, "haul")?;
+    game.bonus_pellets = int(bonus, "pellets")?;
+    // Dumps from before bonus fruit, double points and bombs have none of them
+    if let Some(fruit) = json.get("fruit") {
+        game.fruit_timer = int(fruit, "timer")?;
+        game.fruits = parse_fruits(fruit, "eaten")?;
+    }
+    if let Some(double_points) = json.get("double_points") {
+        game.double_points_pickup = int(double_points, "pickup")?;
+        game.double_points_timer = int(double_points, "timer")?;
+    }
+    if let Some(bombs) = json.get("bombs") {
+        game.bomb_pickup = int(bombs, "pickup")?;
+        game.bombs = int(bombs, "held")?;
+        game.bomb_fuse = int(bombs, "fuse")?;
+        game.open_walls = array(bombs, "open_walls")?.iter()
+            .map(|wall| Ok(OpenWall { x: int(wall, "x")?, y: int(wall, "y")?, ticks: int(wall, "ticks")? }))
+            .collect::<Result<_, String>>()?;
+    }
+    let tweaks = field(json, "tweaks")?;
+    game.tweaks = Tweaks {
+        ghost_speed: int(tweaks, "ghost_speed")?,
+        pacman_speed: int(tweaks, "pacman_speed")?,
+        frightened_ticks: int(tweaks, "frightened_ticks")?,
+    };
+
+    let players = array(json, "players")?;
+    if players.len() != game.players.len() {
+        return Err("wrong number of players".to_string());
+    }
+    game.players = players.iter()
+        .map(|player| Ok(Player {
+            x: int(player, "x")?,
+            y: int(player, "y")?,
+            dx: int(player, "dx")?,
+            dy: int(player, "dy")?,
+            sub_frame_counter: int(player, "sub_frame_counter")?,
+            queued_dx: int(player, "queued_dx")?,
+            queued_dy: int(player, "queued_dy")?,
+            queued_ticks: int(player, "queued_ticks")?,
+            floor: floor(player)?,
+        }))
+        .collect::<Result<_, String>>()?;
+    let ghosts = array(json, "ghosts")?;
+    if ghosts.len() != game.ghosts.len() {
+        return Err("wrong number of ghosts".to_string());
+    }
+    for (ghost, dumped) in game.ghosts.iter_mut().zip(ghosts) {
+        (ghost.x, ghost.y, ghost.dx, ghost.dy) = (int(dumped, "x")?, int(dumped, "y")?, int(dumped, "dx")?, int(dumped, "dy")?);
+        ghost.sub_frame_counter = int(dumped, "sub_frame_counter")?;
+        ghost.think_timer = int(dumped, "think_timer")?;
+        ghost.vulnerable = flag(dumped, "vulnerable")?;
+        let [x, y] = array(dumped, "target")? else { return Err("bad ghost target".to_string()) };
+        ghost.target = x.as_i64().zip(y.as_i64()).map(|(x, y)| (x as i32, y as i32)).ok_or("bad ghost target")?;
+        ghost.in_house = flag(dumped, "in_house")?;
+        ghost.floor = floor(dumped)?;
+    }
+    game.eaten = parse_bitmap(json, "eaten")?;
+    game.waiting_turn = match field(json, "waiting_turn")? {
+        Json::Null => None,
+        waiting => Some(TurnState {
+            scoring: parse_scoring(waiting)?,
+            lives: int(waiting, "lives")?,
+            level: int(waiting, "level")? as u32,
+            pellets: int(waiting, "pellets")?,
+            eaten: parse_bitmap(waiting, "eaten")?,
+            fruits: if waiting.get("fruits").is_some() { parse_fruits(waiting, "fruits")? } else { Vec::new() },
+        }),
+    };
+
+    // Campaigns come from files that may have changed, so the game is played
+    // as a single maze and hashes differently (the campaign stage is hashed)
+    let hash = text(json, "state_hash")?;
+    if field(json, "campaign")?.is_null() && format!("{:016x}", game.state_hash()) != hash {
+        eprintln!("State dump: the loaded game hashes differently from the dumped one (was {})", hash);
+    }
+    Ok(game)
+}
@@ -0,0 +1,335 @@
+//! Run history and the statistics export
+//!
+//! Every local or online game played to its end or left (replays, practice
+//! runs and playtests excepted) is added to `runs.txt` in the save
+//! directory under player 1's profile name, one line per run:
+//!
+//! ```text
+//! run single 35ab8bf4459695cf 3 12340 11020 412 9 2 Alice
+//! ```
+//!
+//! The fields are the game mode (as in recordings, with a `+` after it for
+//! new game plus, like `single+`), the maze's hash, the level reached, the
+//! score, the ticks played, the pellets and ghosts eaten and the lives lost,
+//! then the name. A `points` line may follow a run with where its score
+//! came from, in the order of `ScoreSource::ALL` (pellets, power pellets,
+//! ghosts, fruit and bonus stages):
+//!
+//! ```text
+//! points 2370 200 8400 1000 370
+//! ```
+//!
+//! Runs recorded before the breakdown existed have none. New game plus scores are a category of their own: they
+//! never count towards a profile's best or total score, only towards its new
+//! game plus ones. The Players page adds them up per
+//! profile and its Export entry writes the history to two CSV files in
+//! `exports/`: `stats_lifetime.csv` with one row per profile (versus
+//! record included) and `stats_runs.csv` with one row per run. Profile names
+//! can't hold commas, so nothing needs quoting.
+
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use crate::constants::FPS;
+use crate::game::{Game, GameEvent};
+use crate::game_config::GameMode;
+use crate::maze::maze_hash;
+use crate::scoring::ScoreSource;
+use crate::rating::{Ratings, clean_name};
+use crate::storage::{save_dir, save_path};
+use crate::tas::{mode_name, parse_mode};
+
+/// Name of the run history in the save directory
+const RUNS_FILE: &str = "runs.txt";
+
+/// Subdirectory of the save directory exports (CSV files, profiles) are written to
+pub(crate) const EXPORT_DIR: &str = "exports";
+
+/// How one run went
+#[derive(Clone, PartialEq)]
+pub struct RunStats {
+    /// Profile name of player 1
+    pub player: String,
+    pub mode: GameMode,
+    /// A new game plus run (see `GameConfig::ng_plus`)
+    pub ng_plus: bool,
+    /// `maze_hash` of the maze the run started on
+    pub maze: u64,
+    /// Level reached
+    pub level: u32,
+    pub score: i32,
+    /// Ticks played, counted at `FPS` whatever the game's tick rate
+    pub ticks: u32,
+    /// Ticks played at the game's own tick rate
+    game_ticks: u32,
+    /// Pellets eaten, power pellets included
+    pub pellets: u32,
+    /// Frightened ghosts eaten
+    pub ghosts: u32,
+    /// Lives lost
+    pub deaths: u32,
+    /// Points scored in the run, per source (indexed by `ScoreSource::index`)
+    pub points: [i32; ScoreSource::ALL.len()],
+}
+
+impl RunStats {
+    /// Starts counting a new run
+    ///
+    /// # Arguments
+    /// * `game` - The game just started
+    /// * `player` - Profile name of player 1
+    pub fn new(game: &Game, player: &str) -> Self {
+        Self {
+            player: player.to_string(),
+            mode: game.config.mode,
+            ng_plus: game.config.ng_plus,
+            maze: maze_hash(),
+            level: game.level,
+            score: game.score(),
+            ticks: 0,
+            game_ticks: 0,
+            pellets: 0,
+            ghosts: 0,
+            deaths: 0,
+            points: [0; ScoreSource::ALL.len()],
+        }
+    }
+
+    /// Takes in one tick
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick
+    /// * `events` - The events `Game::tick` returned
+    pub fn record(&mut self, game: &Game, events: &[GameEvent]) {
+        self.game_ticks += 1;
+        self.ticks = game.config.base_ticks(self.game_ticks);
+        self.level = game.level;
+        self.score = game.score();
+        for event in events {
+            match event {
+                GameEvent::PelletEaten { .. } | GameEvent::PowerPelletEaten { .. } => self.pellets += 1,
+                GameEvent::GhostEaten { .. } => self.ghosts += 1,
+                GameEvent::PlayerDied { .. } => self.deaths += 1,
+                GameEvent::ScoreChanged { source, points, .. } => self.points[source.index()] += points,
+                _ => {}
+            }
+        }
+    }
+
+    /// The game mode as written to the history file (`single+` for new game plus)
+    pub fn mode_label(&self) -> String {
+        format!("{}{}", mode_name(self.mode), if self.ng_plus { "+" } else { "" })
+    }
+
+    /// Time played in seconds
+    pub fn seconds(&self) -> f32 {
+        self.ticks as f32 / FPS as f32
+    }
+
+    /// Reads a `run` line of the history file
+    pub(crate) fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.trim().strip_prefix("run ")?.splitn(9, ' ');
+        let mode = fields.next()?;
+        let (mode, ng_plus) = match mode.strip_suffix('+') {
+            Some(mode) => (mode, true),
+            None => (mode, false),
+        };
+        let mode = parse_mode(mode)?;
+        let maze = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let mut number = || fields.next().and_then(|field| field.parse::<u32>().ok());
+        let (level, score, ticks, pellets, ghosts, deaths) = (number()?, number()?, number()?, number()?, number()?, number()?);
+        let player = clean_name(fields.next().unwrap_or(""));
+        if player.is_empty() {
+            return None;
+        }
+        let points = [0; ScoreSource::ALL.len()];
+        Some(Self { player, mode, ng_plus, maze, level, score: score as i32, ticks, game_ticks: ticks, pellets, ghosts, deaths, points })
+    }
+
+    /// Reads the fields of the `points` line that follows the run's line
+    /// (an unreadable one leaves the breakdown empty)
+    pub(crate) fn parse_points(&mut self, fields: &str) {
+        let points: Vec<i32> = fields.split_whitespace().filter_map(|field| field.parse().ok()).collect();
+        if points.len() == self.points.len() {
+            self.points.copy_from_slice(&points);
+        }
+    }
+
+    /// The run as lines of the history file (without the last newline):
+    /// the `run` line, then the `points` line if anything was scored
+    pub(crate) fn to_line(&self) -> String {
+        let mut text = format!(
+            "run {} {:016x} {} {} {} {} {} {} {}",
+            self.mode_label(), self.maze, self.level, self.score.max(0),
+            self.ticks, self.pellets, self.ghosts, self.deaths, self.player
+        );
+        if self.points.iter().any(|&points| points != 0) {
+            let points: Vec<String> = self.points.iter().map(|points| points.to_string()).collect();
+            text.push_str(&format!("\npoints {}", points.join(" ")));
+        }
+        text
+    }
+}
+
+/// One profile's runs added up
+#[derive(Default)]
+pub struct Lifetime {
+    pub runs: u32,
+    pub ticks: u64,
+    /// Best and total score of the runs that weren't new game plus
+    pub best_score: i32,
+    pub total_score: i64,
+    /// Best and total score of the new game plus runs
+    pub ng_plus_best_score: i32,
+    pub ng_plus_total_score: i64,
+    pub highest_level: u32,
+    pub pellets: u64,
+    pub ghosts: u64,
+    pub deaths: u64,
+    /// Points scored, per source (indexed by `ScoreSource::index`)
+    pub points: [i64; ScoreSource::ALL.len()],
+}
+
+/// Every run played on this machine, oldest first
+#[derive(Default)]
+pub struct History {
+    pub runs: Vec<RunStats>,
+}
+
+impl History {
+    /// Loads the run history (empty without one; unreadable lines are skipped)
+    pub fn load() -> Self {
+        let runs = fs::read_to_string(save_dir().join(RUNS_FILE))
+            .map(|text| parse_runs(&text))
+            .unwrap_or_default();
+        Self { runs }
+    }
+
+    /// Adds a finished run and appends it to the history file
+    pub fn add(&mut self, run: RunStats) -> Result<(), String> {
+        let dir = save_dir();
+        let path = dir.join(RUNS_FILE);
+        let saved = fs::create_dir_all(&dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| writeln!(file, "{}", run.to_line()))
+            .map_err(|e| format!("Could not save the run to {}: {}", path.display(), e));
+        self.runs.push(run);
+        saved
+    }
+
+    /// Rewrites the history file with every run
+    pub fn save(&self) -> Result<(), String> {
+        let text: String = self.runs.iter().map(|run| run.to_line() + "\n").collect();
+        let dir = save_dir();
+        let path = dir.join(RUNS_FILE);
+        fs::create_dir_all(&dir)
+            .and_then(|_| fs::write(&path, text))
+            .map_err(|e| format!("Could not save the run history to {}: {}", path.display(), e))
+    }
+
+    /// A profile's runs, oldest first
+    pub fn runs_of<'a>(&'a self, player: &'a str) -> impl Iterator<Item = &'a RunStats> {
+        self.runs.iter().filter(move |run| run.player == player)
+    }
+
+    /// A profile's runs added up
+    pub fn lifetime(&self, player: &str) -> Lifetime {
+        let mut lifetime = Lifetime::default();
+        for run in self.runs_of(player) {
+            lifetime.runs += 1;
+            lifetime.ticks += run.ticks as u64;
+            if run.ng_plus {
+                lifetime.ng_plus_best_score = lifetime.ng_plus_best_score.max(run.score);
+                lifetime.ng_plus_total_score += run.score as i64;
+            } else {
+                lifetime.best_score = lifetime.best_score.max(run.score);
+                lifetime.total_score += run.score as i64;
+            }
+            lifetime.highest_level = lifetime.highest_level.max(run.level);
+            lifetime.pellets += run.pellets as u64;
+            lifetime.ghosts += run.ghosts as u64;
+            lifetime.deaths += run.deaths as u64;
+            for (total, &points) in lifetime.points.iter_mut().zip(&run.points) {
+                *total += points as i64;
+            }
+        }
+        lifetime
+    }
+
+    /// One row per profile that has played or been rated, with a header line
+    pub fn lifetime_csv(&self, ratings: &Ratings) -> String {
+        let mut players: Vec<&str> = self.runs.iter().map(|run| run.player.as_str()).collect();
+        players.extend(ratings.rated().map(|(name, _)| name));
+        players.sort_unstable();
+        players.dedup();
+
+        let mut text = format!(
+            "player,runs,play_seconds,best_score,total_score,ng_plus_best_score,ng_plus_total_score,highest_level,pellets,ghosts_eaten,lives_lost,rating,versus_wins,versus_losses,{}\n",
+            points_header()
+        );
+        for player in players {
+            let lifetime = self.lifetime(player);
+            let rating = ratings.get(player);
+            let points: Vec<String> = lifetime.points.iter().map(|points| points.to_string()).collect();
+            text.push_str(&format!(
+                "{},{},{:.1},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+                player, lifetime.runs, lifetime.ticks as f32 / FPS as f32, lifetime.best_score, lifetime.total_score,
+                lifetime.ng_plus_best_score, lifetime.ng_plus_total_score, lifetime.highest_level, lifetime.pellets, lifetime.ghosts, lifetime.deaths,
+                rating.rating, rating.wins, rating.losses, points.join(",")
+            ));
+        }
+        text
+    }
+
+    /// One row per run, oldest first, with a header line
+    pub fn runs_csv(&self) -> String {
+        let mut text = format!("run,player,mode,maze,level,score,seconds,pellets,ghosts_eaten,lives_lost,{}\n", points_header());
+        for (index, run) in self.runs.iter().enumerate() {
+            let points: Vec<String> = run.points.iter().map(|points| points.to_string()).collect();
+            text.push_str(&format!(
+                "{},{},{},{:016x},{},{},{:.1},{},{},{},{}\n",
+                index + 1, run.player, run.mode_label(), run.maze, run.level, run.score,
+                run.seconds(), run.pellets, run.ghosts, run.deaths, points.join(",")
+            ));
+        }
+        text
+    }
+
+    /// Writes both CSV files to the export directory, replacing earlier exports
+    ///
+    /// # Returns
+    /// The directory the files were written to
+    pub fn export(&self, ratings: &Ratings) -> Result<PathBuf, String> {
+        let write = |name: &str, text: String| {
+            save_path(EXPORT_DIR, name)
+                .and_then(|path| fs::write(path, text))
+                .map_err(|e| format!("Could not export {}: {}", name, e))
+        };
+        write("stats_lifetime.csv", self.lifetime_csv(ratings))?;
+        write("stats_runs.csv", self.runs_csv())?;
+        Ok(save_dir().join(EXPORT_DIR))
+    }
+}
+
+/// Reads the runs in the history file format, each with the `points` line
+/// that follows it (other lines are skipped)
+pub(crate) fn parse_runs(text: &str) -> Vec<RunStats> {
+    let mut runs: Vec<RunStats> = Vec::new();
+    for line in text.lines() {
+        match line.trim().strip_prefix("points ") {
+            Some(fields) => {
+                if let Some(run) = runs.last_mut() {
+                    run.parse_points(fields);
+                }
+            }
+            None => runs.extend(RunStats::parse(line)),
+        }
+    }
+    runs
+}
+
+/// The CSV columns of the score breakdown, e.g. `points_pellets,points_ghosts`
+fn points_header() -> String {
+    let columns: Vec<String> = ScoreSource::ALL.iter().map(|source| format!("points_{}", source.name())).collect();
+    columns.join(",")
+}
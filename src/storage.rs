@@ -0,0 +1,29 @@
+//! Locations of files the game writes (records, settings, replays)
+//! 
+//! Everything lives under one save directory so it is easy to back up or
+//! wipe. It defaults to `saves/` next to where the game is started and can be
+//! moved with the `PACLIKE_SAVE_DIR` environment variable.
+
+use std::path::PathBuf;
+
+/// Environment variable that overrides the save directory
+const SAVE_DIR_ENV: &str = "PACLIKE_SAVE_DIR";
+
+/// Returns the save directory (not created yet)
+pub fn save_dir() -> PathBuf {
+    std::env::var_os(SAVE_DIR_ENV)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from("saves"))
+}
+
+/// Returns the path of a file inside a subdirectory of the save directory,
+/// creating the subdirectory if needed
+/// 
+/// # Arguments
+/// * `subdir` - Subdirectory name (e.g. "time_trials")
+/// * `file_name` - File name inside the subdirectory
+pub fn save_path(subdir: &str, file_name: &str) -> std::io::Result<PathBuf> {
+    let dir = save_dir().join(subdir);
+    std::fs::create_dir_all(&dir)?;
+    Ok(dir.join(file_name))
+}
@@ -0,0 +1,505 @@
+//! TAS-style input recordings
+//! 
+//! A recording is a plain text file: a header describing how the game was set
+//! up, then one line per simulation tick with the inputs the tick accepted, the
+//! RNG state after it and a rolling checksum. Feeding the lines back into
+//! `Game::tick` reproduces the run exactly, and the last two columns pinpoint
+//! the first tick where a replay drifts from the original (useful when
+//! debugging movement and cornering).
+//! 
+//! ```text
+//! paclike-tas 4
+//! mode single
+//! player1 pacman
+//! player2 -
+//! cornering 2
+//! targeting arcade
+//! ghosts classic
+//! maze 0
+//! mazehash 35ab8bf4459695cf
+//! level 1
+//! seed ace1
+//! ---
+//! 0 R . 3c6a c525690a25b38c77
+//! 1 R . 3c6a 25eb5e0bcc87c15b
+//! ```
+//! 
+//! `mazehash` is `maze::maze_hash` of the maze the run was recorded on: a
+//! recording is refused on any other maze (a changed `--maze` file, say)
+//! instead of drifting from the first tick. The checksum chains the header,
+//! then every tick's inputs, RNG state and `Game::state_hash`, so a replay
+//! that diverges, or a recording whose header or inputs were changed
+//! afterwards, is reported at the first tick that no longer matches.
+//! 
+//! Inputs are `U`, `D`, `L`, `R` or `.` for none. Lines can be edited by hand
+//! to build tool-assisted runs; the tick column is informational, and the
+//! checksum column may be dropped to check the RNG only. Version 3
+//! recordings have no maze hash and a plain state hash in place of the
+//! checksum; recordings older than that were made with a single shared RNG
+//! and cannot be played back. A recording without a `cornering` line was made
+//! before cornering and plays without it. `targeting` is `arcade` (ghosts keep
+//! the arcade game's targeting bugs) or `fixed`. `ghosts` names the ghost AI
+//! preset (see `GhostPreset`); recordings without it used the classic ghosts.
+//! `pacman` names the AI Pac-Man's style (see `PacmanStyle`); recordings
+//! without it used the greedy one. `rate` is the tick rate (see
+//! `GameConfig::tick_rate`), written only when it isn't the default `FPS`;
+//! every tick line is then one tick at that rate. An `ice` line marks a game
+//! on an ice maze (see `GameConfig::ice`).
+
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use crate::game::{Game, StateHash};
+use crate::constants::{FPS, TICK_RATES};
+use crate::game_config::{GameConfig, GameMode, GhostPreset, PacmanStyle, PlayerRole};
+use crate::maze::maze_hash;
+use crate::rng::DEFAULT_SEED;
+use crate::storage::save_path;
+
+/// First line of every recording
+const TAS_MAGIC: &str = "paclike-tas 4";
+
+/// First line of recordings without maze hash and rolling checksum, which still play back
+const TAS_MAGIC_V3: &str = "paclike-tas 3";
+
+/// Start of the first line of every recording, whatever its version
+const TAS_MAGIC_PREFIX: &str = "paclike-tas ";
+
+/// Save subdirectory for recordings
+const TAS_DIR: &str = "tas";
+
+/// How the recorded game was set up
+#[derive(Clone, Copy)]
+pub struct TasHeader {
+    pub mode: GameMode,
+    pub player1_role: PlayerRole,
+    pub player2_role: Option<PlayerRole>,
+    /// Cornering window the game was played with (see `GameConfig::cornering_window`)
+    pub cornering_window: i32,
+    /// Whether the ghosts targeted with the arcade game's quirks (see `GameConfig::arcade_quirks`)
+    pub arcade_quirks: bool,
+    /// AIs of the computer-controlled ghosts (see `GameConfig::ghost_preset`; classic when not recorded)
+    pub ghost_preset: GhostPreset,
+    /// How the computer-controlled Pac-Man played (see `GameConfig::pacman_style`; greedy when not recorded)
+    pub pacman_style: PacmanStyle,
+    /// Index of the built-in maze
+    pub maze_index: usize,
+    /// `maze::maze_hash` of the maze the run started on (None in version 3 recordings)
+    pub maze_hash: Option<u64>,
+    /// Level the run started at (see `Game::start_at_level`)
+    pub level: u32,
+    /// Master seed of the RNG streams
+    pub seed: u16,
+    /// Ticks per second (see `GameConfig::tick_rate`; `FPS` when not recorded)
+    pub tick_rate: u32,
+    /// Whether the maze was icy (see `GameConfig::ice`; off when not recorded)
+    pub ice: bool,
+}
+
+impl TasHeader {
+    /// Game configuration to replay the recording with
+    pub fn config(&self) -> GameConfig {
+        let mut config = GameConfig::new(self.mode, self.player1_role, self.player2_role);
+        config.cornering_window = self.cornering_window;
+        config.arcade_quirks = self.arcade_quirks;
+        config.ghost_preset = self.ghost_preset;
+        config.pacman_style = self.pacman_style;
+        config.tick_rate = self.tick_rate;
+        config.ice = self.ice;
+        config
+    }
+}
+
+/// One recorded tick
+#[derive(Clone, Copy)]
+pub struct TasFrame {
+    pub player1_input: Option<(i32, i32)>,
+    pub player2_input: Option<(i32, i32)>,
+    /// RNG state after the tick
+    pub rng_state: u16,
+    /// Rolling checksum after the tick (see `rolling_checksum`); the plain
+    /// `Game::state_hash` in version 3 recordings; None in hand-trimmed ones
+    pub checksum: Option<u64>,
+}
+
+/// Writes a recording while a game is played
+pub struct TasRecorder {
+    file: BufWriter<fs::File>,
+    path: PathBuf,
+    tick: u32,
+    /// Rolling checksum up to the last recorded tick
+    checksum: u64,
+}
+
+impl TasRecorder {
+    /// Creates a new recording file in the save directory and writes its header
+    pub fn create(header: &TasHeader) -> Result<Self, String> {
+        let stamp = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let path = save_path(TAS_DIR, &format!("run-{}.tas", stamp))
+            .map_err(|e| format!("Could not create recording: {}", e))?;
+        let file = fs::File::create(&path)
+            .map_err(|e| format!("Could not create {}: {}", path.display(), e))?;
+        let lines = header_lines(header);
+        let mut recorder = Self { file: BufWriter::new(file), path, tick: 0, checksum: header_checksum(&lines) };
+        lines.iter()
+            .try_for_each(|line| writeln!(recorder.file, "{}", line))
+            .map_err(|e| format!("Could not write recording: {}", e))?;
+        Ok(recorder)
+    }
+
+    /// Appends one tick
+    /// 
+    /// # Arguments
+    /// * `player1_input`, `player2_input` - The inputs the tick was given
+    /// * `game` - The game after the tick
+    pub fn record(&mut self, player1_input: Option<(i32, i32)>, player2_input: Option<(i32, i32)>, game: &Game) -> Result<(), String> {
+        let rng_state = game.rng.state();
+        self.checksum = rolling_checksum(self.checksum, player1_input, player2_input, rng_state, game.state_hash());
+        writeln!(
+            self.file,
+            "{} {} {} {:04x} {:016x}",
+            self.tick,
+            input_code(player1_input),
+            input_code(player2_input),
+            rng_state,
+            self.checksum
+        ).map_err(|e| format!("Could not write recording: {}", e))?;
+        self.tick += 1;
+        Ok(())
+    }
+
+    /// Where the recording is being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TasRecorder {
+    fn drop(&mut self) {
+        let _ = self.file.flush();
+    }
+}
+
+/// A loaded recording being fed back into the simulation
+pub struct TasPlayback {
+    pub header: TasHeader,
+    frames: Vec<TasFrame>,
+    position: usize,
+    /// Whether the recording has rolling checksums (version 4) or plain state hashes
+    rolling: bool,
+    /// Rolling checksum of the replay up to the last played tick
+    checksum: u64,
+    /// First tick whose RNG state or checksum did not match the recording
+    pub desync_tick: Option<usize>,
+}
+
+impl TasPlayback {
+    /// Loads a recording
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Could not read {}: {}", path.display(), e))?;
+        let mut lines = text.lines();
+        let magic = lines.next().map(str::trim);
+        let rolling = match magic {
+            Some(TAS_MAGIC) => true,
+            Some(TAS_MAGIC_V3) => false,
+            Some(magic) if magic.starts_with(TAS_MAGIC_PREFIX) => {
+                return Err(format!("{} was recorded by an unsupported version and cannot be played back", path.display()));
+            }
+            _ => return Err(format!("{} is not a recording", path.display())),
+        };
+        
+        // Header: "key value" lines up to the separator
+        let mut header_text: Vec<&str> = magic.into_iter().collect();
+        let mut mode = None;
+        let mut player1_role = None;
+        let mut player2_role = None;
+        let mut cornering_window = 0;
+        let mut arcade_quirks = true;
+        let mut ghost_preset = GhostPreset::Classic;
+        let mut pacman_style = PacmanStyle::Greedy;
+        let mut maze_index = 0;
+        let mut maze_hash = None;
+        let mut level = 1;
+        let mut seed = DEFAULT_SEED;
+        let mut tick_rate = FPS;
+        let mut ice = false;
+        for line in lines.by_ref() {
+            let line = line.trim();
+            header_text.push(line);
+            if line == "---" {
+                break;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "mode" => mode = parse_mode(value),
+                "player1" => player1_role = parse_role(value),
+                "player2" => player2_role = parse_role(value),
+                "cornering" => cornering_window = value.parse().map_err(|_| format!("Bad cornering line: {}", line))?,
+                "targeting" => arcade_quirks = parse_targeting(value).ok_or_else(|| format!("Bad targeting line: {}", line))?,
+                "ghosts" => ghost_preset = GhostPreset::from_name(value).ok_or_else(|| format!("Bad ghosts line: {}", line))?,
+                "pacman" => pacman_style = PacmanStyle::from_name(value).ok_or_else(|| format!("Bad pacman line: {}", line))?,
+                "maze" => maze_index = value.parse().map_err(|_| format!("Bad maze line: {}", line))?,
+                "mazehash" => {
+                    maze_hash = Some(u64::from_str_radix(value, 16).map_err(|_| format!("Bad mazehash line: {}", line))?);
+                }
+                "level" => level = value.parse().map_err(|_| format!("Bad level line: {}", line))?,
+                "seed" => seed = u16::from_str_radix(value, 16).map_err(|_| format!("Bad seed line: {}", line))?,
+                "rate" => {
+                    tick_rate = value.parse().ok()
+                        .filter(|rate| TICK_RATES.contains(rate))
+                        .ok_or_else(|| format!("Bad rate line: {}", line))?;
+                }
+                "ice" => ice = true,
+                _ => {}
+            }
+        }
+        let header = TasHeader {
+            mode: mode.ok_or("Recording has no valid mode")?,
+            player1_role: player1_role.ok_or("Recording has no valid player1 role")?,
+            player2_role,
+            cornering_window,
+            arcade_quirks,
+            ghost_preset,
+            pacman_style,
+            maze_index,
+            maze_hash,
+            level,
+            seed,
+            tick_rate,
+            ice,
+        };
+        
+        // Ticks: "<tick> <p1> <p2> <rng> [<checksum>]"
+        let mut frames = Vec::new();
+        for (line_number, line) in lines.enumerate() {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            if parts.is_empty() || parts[0].starts_with('#') {
+                continue;
+            }
+            let frame = (|| {
+                Some(TasFrame {
+                    player1_input: parse_input(parts.get(1)?)?,
+                    player2_input: parse_input(parts.get(2)?)?,
+                    rng_state: u16::from_str_radix(parts.get(3)?, 16).ok()?,
+                    checksum: match parts.get(4) {
+                        Some(checksum) => Some(u64::from_str_radix(checksum, 16).ok()?),
+                        None => None,
+                    },
+                })
+            })();
+            frames.push(frame.ok_or_else(|| format!("Bad tick line {}: {}", line_number + 1, line))?);
+        }
+        
+        let checksum = header_checksum(&header_text);
+        Ok(Self { header, frames, position: 0, rolling, checksum, desync_tick: None })
+    }
+
+    /// Checks that the current maze is the one the run was recorded on
+    pub fn check_maze(&self) -> Result<(), String> {
+        match self.header.maze_hash {
+            Some(recorded) if recorded != maze_hash() => Err(format!(
+                "Recording was made on a different maze (hash {:016x}, current maze {:016x})",
+                recorded,
+                maze_hash()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Loads the most recent recording in the save directory
+    pub fn load_latest() -> Result<Self, String> {
+        let dir = crate::storage::save_dir().join(TAS_DIR);
+        let latest = fs::read_dir(&dir)
+            .map_err(|_| "No recordings yet".to_string())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map(|ext| ext == "tas").unwrap_or(false))
+            .max_by_key(|path| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .ok_or("No recordings yet")?;
+        Self::load(&latest)
+    }
+
+    /// Every recorded tick, in order
+    pub fn frames(&self) -> &[TasFrame] {
+        &self.frames
+    }
+
+    /// The next tick to play, or None when the recording is over
+    pub fn next_frame(&self) -> Option<TasFrame> {
+        self.frames.get(self.position).copied()
+    }
+
+    /// Checks the RNG state and checksum after the tick against the recording and moves on
+    /// 
+    /// # Arguments
+    /// * `rng_state` - The game's RNG state after the tick
+    /// * `state_hash` - `Game::state_hash` after the tick
+    pub fn verify(&mut self, rng_state: u16, state_hash: u64) {
+        if let Some(frame) = self.frames.get(self.position) {
+            let checksum = if self.rolling {
+                self.checksum = rolling_checksum(self.checksum, frame.player1_input, frame.player2_input, rng_state, state_hash);
+                self.checksum
+            } else {
+                state_hash
+            };
+            if self.desync_tick.is_none() {
+                if frame.rng_state != rng_state {
+                    eprintln!(
+                        "Replay desync at tick {}: RNG {:04x}, recorded {:04x}",
+                        self.position, rng_state, frame.rng_state
+                    );
+                    self.desync_tick = Some(self.position);
+                } else if frame.checksum.is_some_and(|recorded| recorded != checksum) {
+                    eprintln!(
+                        "Replay desync at tick {}: checksum {:016x}, recorded {:016x}",
+                        self.position, checksum, frame.checksum.unwrap_or(0)
+                    );
+                    self.desync_tick = Some(self.position);
+                }
+            }
+        }
+        self.position += 1;
+    }
+
+    /// True once every recorded tick has been played
+    pub fn finished(&self) -> bool {
+        self.position >= self.frames.len()
+    }
+}
+
+/// The header as written to a recording, from the version line to the separator
+fn header_lines(header: &TasHeader) -> Vec<String> {
+    let mut lines = vec![
+        TAS_MAGIC.to_string(),
+        format!("mode {}", mode_name(header.mode)),
+        format!("player1 {}", role_name(Some(header.player1_role))),
+        format!("player2 {}", role_name(header.player2_role)),
+        format!("cornering {}", header.cornering_window),
+        format!("targeting {}", targeting_name(header.arcade_quirks)),
+        format!("ghosts {}", header.ghost_preset.name().to_ascii_lowercase()),
+        format!("pacman {}", header.pacman_style.name().to_ascii_lowercase()),
+        format!("maze {}", header.maze_index),
+    ];
+    if let Some(hash) = header.maze_hash {
+        lines.push(format!("mazehash {:016x}", hash));
+    }
+    lines.push(format!("level {}", header.level));
+    lines.push(format!("seed {:04x}", header.seed));
+    if header.tick_rate != FPS {
+        lines.push(format!("rate {}", header.tick_rate));
+    }
+    if header.ice {
+        lines.push("ice".to_string());
+    }
+    lines.push("---".to_string());
+    lines
+}
+
+/// Start of the rolling checksum: a hash of the header lines
+fn header_checksum<S: AsRef<str>>(lines: &[S]) -> u64 {
+    let mut hash = StateHash::new();
+    for line in lines {
+        for byte in line.as_ref().bytes() {
+            hash.add(byte as i64);
+        }
+        hash.add(-1);
+    }
+    hash.finish()
+}
+
+/// Chains one tick onto the rolling checksum: its inputs, and the RNG state
+/// and `Game::state_hash` after it
+fn rolling_checksum(
+    previous: u64,
+    player1_input: Option<(i32, i32)>,
+    player2_input: Option<(i32, i32)>,
+    rng_state: u16,
+    state_hash: u64,
+) -> u64 {
+    let mut hash = StateHash::new();
+    hash.add(previous as i64);
+    hash.add(input_code(player1_input) as i64);
+    hash.add(input_code(player2_input) as i64);
+    hash.add(rng_state as i64);
+    hash.add(state_hash as i64);
+    hash.finish()
+}
+
+pub(crate) fn mode_name(mode: GameMode) -> &'static str {
+    match mode {
+        GameMode::SinglePlayer => "single",
+        GameMode::Multiplayer => "multi",
+        GameMode::Coop => "coop",
+        GameMode::Alternating => "alternating",
+        GameMode::TimeTrial => "timetrial",
+        GameMode::Race => "race",
+        GameMode::Demo => "demo",
+    }
+}
+
+pub(crate) fn parse_mode(name: &str) -> Option<GameMode> {
+    match name {
+        "single" => Some(GameMode::SinglePlayer),
+        "multi" => Some(GameMode::Multiplayer),
+        "coop" => Some(GameMode::Coop),
+        "alternating" => Some(GameMode::Alternating),
+        "timetrial" => Some(GameMode::TimeTrial),
+        "race" => Some(GameMode::Race),
+        "demo" => Some(GameMode::Demo),
+        _ => None,
+    }
+}
+
+pub(crate) fn role_name(role: Option<PlayerRole>) -> &'static str {
+    match role {
+        Some(PlayerRole::PacMan) => "pacman",
+        Some(PlayerRole::Ghost) => "ghost",
+        None => "-",
+    }
+}
+
+pub(crate) fn parse_role(name: &str) -> Option<PlayerRole> {
+    match name {
+        "pacman" => Some(PlayerRole::PacMan),
+        "ghost" => Some(PlayerRole::Ghost),
+        _ => None,
+    }
+}
+
+pub(crate) fn targeting_name(arcade_quirks: bool) -> &'static str {
+    if arcade_quirks { "arcade" } else { "fixed" }
+}
+
+pub(crate) fn parse_targeting(name: &str) -> Option<bool> {
+    match name {
+        "arcade" => Some(true),
+        "fixed" => Some(false),
+        _ => None,
+    }
+}
+
+/// Single-letter code for an input direction
+fn input_code(input: Option<(i32, i32)>) -> char {
+    match input {
+        Some((0, -1)) => 'U',
+        Some((0, 1)) => 'D',
+        Some((-1, 0)) => 'L',
+        Some((1, 0)) => 'R',
+        _ => '.',
+    }
+}
+
+/// Parses an input code; the outer Option is None for an unknown code
+fn parse_input(code: &str) -> Option<Option<(i32, i32)>> {
+    match code {
+        "U" => Some(Some((0, -1))),
+        "D" => Some(Some((0, 1))),
+        "L" => Some(Some((-1, 0))),
+        "R" => Some(Some((1, 0))),
+        "." => Some(None),
+        _ => None,
+    }
+}
@@ -0,0 +1,132 @@
+//! Opt-in session telemetry for maze authors
+//!
+//! With "Telemetry" turned on in the gameplay settings (it is off unless the
+//! player turns it on), each game played appends one JSON line to
+//! `telemetry.jsonl` in the save directory when it ends. Nothing leaves the
+//! machine, and the line carries nothing about who played: no names, no
+//! addresses, no dates. Only how the session went:
+//!
+//! ```text
+//! {"mode":"single","maze":"9c3e0b5f2a71d4e8","seconds":184.3,"inputs_per_minute":92.1,
+//!  "deaths_per_level":{"1":1,"2":2},"region_size":4,"region_seconds":[[0.0,3.2,...],...]}
+//! ```
+//!
+//! (one line in the file). `maze` is the maze's hash, as in recordings, so
+//! sessions on a custom maze can be told apart. `region_seconds` splits the
+//! maze into squares of `region_size` tiles, top row first, and says how long
+//! Pac-Man spent in each: where players linger and where they die is where
+//! a maze is hard.
+
+use std::collections::BTreeMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use crate::constants::{GRID_W, GRID_H};
+use crate::game::{Game, GameEvent};
+use crate::maze::maze_hash;
+use crate::storage::save_dir;
+use crate::tas::mode_name;
+
+/// Name of the telemetry file in the save directory
+const TELEMETRY_FILE: &str = "telemetry.jsonl";
+
+/// Side of a maze region in tiles
+const REGION_SIZE: i32 = 4;
+
+/// Regions across and down the maze (the last ones may be cut short)
+const REGIONS_W: usize = ((GRID_W + REGION_SIZE - 1) / REGION_SIZE) as usize;
+const REGIONS_H: usize = ((GRID_H + REGION_SIZE - 1) / REGION_SIZE) as usize;
+
+/// What has been gathered about the game being played
+pub struct SessionTelemetry {
+    /// Game mode, as in recordings
+    mode: &'static str,
+    /// `maze_hash` of the maze the game started on
+    maze: u64,
+    /// Ticks played
+    ticks: u32,
+    /// Ticks per second of the game (see `GameConfig::tick_rate`)
+    tick_rate: u32,
+    /// New directions pressed, by any local player
+    inputs: u32,
+    /// The last direction of each local player, to count presses rather than held ticks
+    last_input: [Option<(i32, i32)>; 2],
+    /// Lives lost on each level
+    deaths: BTreeMap<u32, u32>,
+    /// Ticks Pac-Man spent in each region, row by row
+    region_ticks: [[u32; REGIONS_W]; REGIONS_H],
+}
+
+impl SessionTelemetry {
+    /// Starts gathering for a new game
+    pub fn new(game: &Game) -> Self {
+        Self {
+            mode: mode_name(game.config.mode),
+            maze: maze_hash(),
+            ticks: 0,
+            tick_rate: game.config.tick_rate,
+            inputs: 0,
+            last_input: [None; 2],
+            deaths: BTreeMap::new(),
+            region_ticks: [[0; REGIONS_W]; REGIONS_H],
+        }
+    }
+
+    /// Takes in one tick
+    ///
+    /// # Arguments
+    /// * `game` - The game after the tick
+    /// * `inputs` - The directions the local players held during the tick
+    /// * `events` - The events `Game::tick` returned
+    pub fn record(&mut self, game: &Game, inputs: [Option<(i32, i32)>; 2], events: &[GameEvent]) {
+        self.ticks += 1;
+        for (last, input) in self.last_input.iter_mut().zip(inputs) {
+            if input.is_some() && input != *last {
+                self.inputs += 1;
+            }
+            *last = input;
+        }
+        for event in events {
+            if let GameEvent::PlayerDied { .. } = event {
+                *self.deaths.entry(game.level).or_insert(0) += 1;
+            }
+        }
+        for player in &game.players {
+            let row = (player.y.clamp(0, GRID_H - 1) / REGION_SIZE) as usize;
+            let column = (player.x.clamp(0, GRID_W - 1) / REGION_SIZE) as usize;
+            self.region_ticks[row][column] += 1;
+        }
+    }
+
+    /// The session as one line of JSON (without the newline)
+    pub fn to_json(&self) -> String {
+        let seconds = |ticks: u32| ticks as f32 / self.tick_rate as f32;
+        let minutes = seconds(self.ticks) / 60.0;
+        let inputs_per_minute = if minutes > 0.0 { self.inputs as f32 / minutes } else { 0.0 };
+        let deaths: Vec<String> = self.deaths.iter().map(|(level, deaths)| format!("\"{}\":{}", level, deaths)).collect();
+        let regions: Vec<String> = self.region_ticks.iter()
+            .map(|row| {
+                let cells: Vec<String> = row.iter().map(|&ticks| format!("{:.1}", seconds(ticks))).collect();
+                format!("[{}]", cells.join(","))
+            })
+            .collect();
+        format!(
+            "{{\"mode\":\"{}\",\"maze\":\"{:016x}\",\"seconds\":{:.1},\"inputs_per_minute\":{:.1},\"deaths_per_level\":{{{}}},\"region_size\":{},\"region_seconds\":[{}]}}",
+            self.mode, self.maze, seconds(self.ticks), inputs_per_minute, deaths.join(","), REGION_SIZE, regions.join(",")
+        )
+    }
+
+    /// Appends the session to the telemetry file
+    ///
+    /// A game left before its first tick isn't worth a line and is dropped.
+    pub fn save(&self) -> Result<(), String> {
+        if self.ticks == 0 {
+            return Ok(());
+        }
+        let dir = save_dir();
+        let path = dir.join(TELEMETRY_FILE);
+        std::fs::create_dir_all(&dir)
+            .and_then(|_| OpenOptions::new().create(true).append(true).open(&path))
+            .and_then(|mut file| writeln!(file, "{}", self.to_json()))
+            .map_err(|e| format!("Could not save telemetry to {}: {}", path.display(), e))
+    }
+}
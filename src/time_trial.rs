@@ -0,0 +1,138 @@
+//! Time trial mode
+//!
+//! The player clears the maze as fast as possible. The fastest clear for each
+//! maze is saved together with Pac-Man's position on every tick, so later runs
+//! can show a translucent "ghost" Pac-Man replaying the personal best in real
+//! time to race against tile for tile.
+
+use std::fs;
+use std::io::Write;
+use crate::storage::save_path;
+
+/// Personal best for one maze: clear time and position trace
+#[derive(Clone)]
+pub struct BestRun {
+    /// Number of ticks the clear took
+    pub frames: u32,
+    /// Pac-Man's tile position on every tick of the run
+    pub trace: Vec<(i32, i32)>,
+}
+
+/// State of a running time trial
+#[derive(Clone)]
+pub struct TimeTrial {
+    /// Hash of the maze being raced (records are kept per maze)
+    pub maze_hash: u64,
+    /// Ticks elapsed in the current run
+    pub frames: u32,
+    /// Pac-Man's position on every tick of the current run
+    pub trace: Vec<(i32, i32)>,
+    /// Personal best loaded at the start of the run
+    pub best: Option<BestRun>,
+    /// Set once the maze has been cleared
+    pub finished: bool,
+    /// Whether the finished run beat the previous personal best
+    pub new_record: bool,
+}
+
+impl TimeTrial {
+    /// Starts a time trial on the maze with the given hash, loading its personal best
+    pub fn new(maze_hash: u64) -> Self {
+        Self {
+            maze_hash,
+            frames: 0,
+            trace: Vec::with_capacity(60 * 120),
+            best: load_best(maze_hash),
+            finished: false,
+            new_record: false,
+        }
+    }
+
+    /// Records one tick of the run with Pac-Man's position
+    pub fn record(&mut self, x: i32, y: i32) {
+        if !self.finished {
+            self.frames += 1;
+            self.trace.push((x, y));
+        }
+    }
+
+    /// Where the personal-best Pac-Man is at the current point of the run
+    ///
+    /// Once the best run has finished, its ghost stays on its final tile.
+    pub fn ghost_position(&self) -> Option<(i32, i32)> {
+        let best = self.best.as_ref()?;
+        let index = (self.frames as usize).min(best.trace.len().saturating_sub(1));
+        best.trace.get(index).copied()
+    }
+
+    /// Ends the run after the maze was cleared, saving it if it is a new personal best
+    pub fn finish(&mut self) {
+        if self.finished {
+            return;
+        }
+        self.finished = true;
+        let is_record = self.best.as_ref().map(|best| self.frames < best.frames).unwrap_or(true);
+        if is_record {
+            let run = BestRun {
+                frames: self.frames,
+                trace: std::mem::take(&mut self.trace),
+            };
+            if let Err(e) = save_best(self.maze_hash, &run) {
+                eprintln!("Could not save time trial record: {}", e);
+            }
+            self.best = Some(run);
+            self.new_record = true;
+        }
+    }
+}
+
+/// File name of the record for a maze
+fn record_file_name(maze_hash: u64) -> String {
+    format!("{:016x}.txt", maze_hash)
+}
+
+/// Hashes of the mazes that have a personal best saved
+#[cfg(feature = "sdl")]
+pub(crate) fn saved_bests() -> Vec<u64> {
+    let Ok(entries) = save_path("time_trials", "").and_then(fs::read_dir) else {
+        return Vec::new();
+    };
+    let mut hashes: Vec<u64> = entries
+        .filter_map(|entry| {
+            let name = entry.ok()?.file_name().into_string().ok()?;
+            u64::from_str_radix(name.strip_suffix(".txt")?, 16).ok()
+        })
+        .collect();
+    hashes.sort_unstable();
+    hashes
+}
+
+/// Loads the personal best for a maze, if one has been saved
+///
+/// Format: a `frames <n>` header line followed by one `x y` line per tick.
+pub(crate) fn load_best(maze_hash: u64) -> Option<BestRun> {
+    let path = save_path("time_trials", &record_file_name(maze_hash)).ok()?;
+    let text = fs::read_to_string(path).ok()?;
+    let mut lines = text.lines();
+    let frames = lines.next()?.strip_prefix("frames ")?.trim().parse().ok()?;
+    let trace = lines
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let x = parts.next()?.parse().ok()?;
+            let y = parts.next()?.parse().ok()?;
+            Some((x, y))
+        })
+        .collect();
+    Some(BestRun { frames, trace })
+}
+
+/// Saves a personal best for a maze, replacing any previous one
+pub(crate) fn save_best(maze_hash: u64, run: &BestRun) -> std::io::Result<()> {
+    let path = save_path("time_trials", &record_file_name(maze_hash))?;
+    let mut file = std::io::BufWriter::new(fs::File::create(path)?);
+    writeln!(file, "frames {}", run.frames)?;
+    for (x, y) in &run.trace {
+        writeln!(file, "{} {}", x, y)?;
+    }
+    file.flush()
+}
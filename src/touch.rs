@@ -0,0 +1,167 @@
+//! On-screen touch controls: a D-pad and a pause button
+//!
+//! For touch screens (and the browser build on phones), a translucent D-pad
+//! is drawn in the bottom left corner and a pause button in the bottom right.
+//! They are laid out for whatever surface the front end draws on
+//! (`TouchLayout`), and `TouchControls` turns touches on that surface into a
+//! held direction and pause presses, like a controller.
+//!
+//! The controls show up with the first touch and hide again as soon as a
+//! key or a controller is used, so they never cover the maze for players who
+//! don't need them. Front ends can turn them off entirely (`enabled`).
+
+/// A part of the touch controls
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum TouchButton {
+    Up,
+    Down,
+    Left,
+    Right,
+    Pause,
+}
+
+impl TouchButton {
+    /// The four D-pad arms
+    pub const ARMS: [TouchButton; 4] = [TouchButton::Up, TouchButton::Down, TouchButton::Left, TouchButton::Right];
+
+    /// Steering direction of a D-pad arm (None for the pause button)
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            TouchButton::Up => Some((0, -1)),
+            TouchButton::Down => Some((0, 1)),
+            TouchButton::Left => Some((-1, 0)),
+            TouchButton::Right => Some((1, 0)),
+            TouchButton::Pause => None,
+        }
+    }
+}
+
+/// Where the touch controls sit on a surface of a given size, in its pixels
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct TouchLayout {
+    /// Center of the D-pad
+    pub center: (i32, i32),
+    /// Side of one D-pad cell (the pad is three cells across) and of the pause button
+    pub cell: i32,
+    /// Top left corner of the pause button
+    pub pause: (i32, i32),
+}
+
+impl TouchLayout {
+    /// Lays the controls out on a `w` x `h` surface, scaled to its shorter side
+    pub fn new(w: i32, h: i32) -> Self {
+        let cell = (w.min(h) / 12).max(4);
+        let margin = cell / 2;
+        Self {
+            center: (margin + cell * 3 / 2, h - margin - cell * 3 / 2),
+            cell,
+            pause: (w - margin - cell, h - margin - cell),
+        }
+    }
+
+    /// Rectangle (x, y, w, h) a button is drawn in
+    pub fn rect(&self, button: TouchButton) -> (i32, i32, i32, i32) {
+        let (cx, cy) = self.center;
+        let cell = self.cell;
+        match button.direction() {
+            Some((dx, dy)) => (cx - cell / 2 + dx * cell, cy - cell / 2 + dy * cell, cell, cell),
+            None => (self.pause.0, self.pause.1, cell, cell),
+        }
+    }
+
+    /// The button under a point, if any
+    ///
+    /// The D-pad answers anywhere in its square and a little around it, by
+    /// the direction from its center, so a thumb sliding off an arm still
+    /// steers; its very center is dead.
+    pub fn resolve(&self, x: i32, y: i32) -> Option<TouchButton> {
+        let (px, py, size, _) = self.rect(TouchButton::Pause);
+        let slack = self.cell / 4;
+        if x >= px - slack && x < px + size + slack && y >= py - slack && y < py + size + slack {
+            return Some(TouchButton::Pause);
+        }
+
+        let (dx, dy) = (x - self.center.0, y - self.center.1);
+        let reach = self.cell * 2;
+        if dx.abs() > reach || dy.abs() > reach || dx.abs().max(dy.abs()) < self.cell / 4 {
+            None
+        } else if dx.abs() > dy.abs() {
+            Some(if dx < 0 { TouchButton::Left } else { TouchButton::Right })
+        } else {
+            Some(if dy < 0 { TouchButton::Up } else { TouchButton::Down })
+        }
+    }
+}
+
+/// The touches on the controls and whether they are shown
+pub struct TouchControls {
+    /// The player wants touch controls at all
+    pub enabled: bool,
+    /// Shown since the last touch (hidden again by keys and controllers)
+    visible: bool,
+    /// Fingers down and the D-pad arm each one holds, oldest first
+    fingers: Vec<(i64, Option<TouchButton>)>,
+}
+
+impl TouchControls {
+    pub fn new(enabled: bool) -> Self {
+        Self { enabled, visible: false, fingers: Vec::new() }
+    }
+
+    /// Whether the controls should be drawn
+    pub fn visible(&self) -> bool {
+        self.enabled && self.visible
+    }
+
+    /// A finger touched the surface
+    ///
+    /// # Arguments
+    /// * `finger` - Id of the finger, to follow it until it lifts
+    /// * `x`, `y` - Where, in the layout's pixels
+    /// * `layout` - The controls on the surface touched
+    ///
+    /// # Returns
+    /// The button touched, if any (the caller acts on `Pause`)
+    pub fn press(&mut self, finger: i64, x: i32, y: i32, layout: &TouchLayout) -> Option<TouchButton> {
+        if !self.enabled {
+            return None;
+        }
+        self.visible = true;
+        let button = layout.resolve(x, y);
+        self.fingers.retain(|&(id, _)| id != finger);
+        self.fingers.push((finger, button.filter(|&button| button != TouchButton::Pause)));
+        button
+    }
+
+    /// A finger moved: it steers with whichever arm it is on now
+    pub fn slide(&mut self, finger: i64, x: i32, y: i32, layout: &TouchLayout) {
+        let button = layout.resolve(x, y).filter(|&button| button != TouchButton::Pause);
+        if let Some(held) = self.fingers.iter_mut().find(|(id, _)| *id == finger) {
+            held.1 = button;
+        }
+    }
+
+    /// A finger lifted
+    pub fn release(&mut self, finger: i64) {
+        self.fingers.retain(|&(id, _)| id != finger);
+    }
+
+    /// A key or controller was used: the controls get out of the way
+    pub fn physical_input(&mut self) {
+        self.visible = false;
+        self.fingers.clear();
+    }
+
+    /// The D-pad arm held by the latest finger on the D-pad, if any
+    pub fn held(&self) -> Option<TouchButton> {
+        self.fingers.iter().rev().find_map(|&(_, button)| button)
+    }
+
+    /// Direction steered on the D-pad, if any
+    pub fn direction(&self) -> Option<(i32, i32)> {
+        if !self.enabled {
+            return None;
+        }
+        self.held().and_then(TouchButton::direction)
+    }
+}
@@ -0,0 +1,159 @@
+//! AI-versus-AI tournaments for tuning the AIs
+//!
+//! `paclike_2600_rs tournament` plays headless games between every chosen
+//! AI Pac-Man style and ghost preset, on every chosen maze with every seed,
+//! and prints one line per pairing:
+//!
+//! ```text
+//! Pac-Man      Ghosts      Games  Pac-Man wins  Avg score  Avg survival
+//! Greedy       Classic        20         85.0%      30839       163.9 s
+//! ```
+//!
+//! Pac-Man wins a game by clearing at least one maze before his last life is
+//! lost, as in rated versus games. A game still going after `max_ticks` ends
+//! there; its survival time is the whole game. With `--csv` the same results
+//! come out as CSV for a spreadsheet.
+
+use crate::constants::{FPS, GRID_H, MAZE_1, MAZE_2};
+use crate::game::{Game, GameEvent};
+use crate::game_config::{GameConfig, GameMode, GhostPreset, PacmanStyle, PlayerRole};
+use crate::maze::set_current_maze;
+
+/// Built-in mazes a tournament can be played on, numbered from 1 as in the menu
+static MAZES: [&[&str; GRID_H as usize]; 2] = [&MAZE_1, &MAZE_2];
+
+/// Which games a tournament plays
+pub struct TournamentOptions {
+    /// AI Pac-Man styles to play with
+    pub pacman_styles: Vec<PacmanStyle>,
+    /// Ghost presets to play against
+    pub ghost_presets: Vec<GhostPreset>,
+    /// Built-in mazes to play on (from 1)
+    pub mazes: Vec<usize>,
+    /// Games per pairing and maze, seeded 1, 2, 3...
+    pub seeds: u16,
+    /// Longest a game may run, in ticks
+    pub max_ticks: u32,
+    /// Print CSV instead of a table
+    pub csv: bool,
+}
+
+impl Default for TournamentOptions {
+    fn default() -> Self {
+        Self {
+            pacman_styles: PacmanStyle::ALL.to_vec(),
+            ghost_presets: GhostPreset::ALL.to_vec(),
+            mazes: (1..=MAZES.len()).collect(),
+            seeds: 10,
+            max_ticks: 10 * 60 * FPS,
+            csv: false,
+        }
+    }
+}
+
+/// Results of one Pac-Man style against one ghost preset, over all its games
+pub struct Pairing {
+    pub pacman_style: PacmanStyle,
+    pub ghost_preset: GhostPreset,
+    pub games: u32,
+    pub pacman_wins: u32,
+    pub total_score: i64,
+    /// Ticks played, summed over the games
+    pub total_ticks: u64,
+}
+
+impl Pairing {
+    /// Share of the games Pac-Man won, in percent
+    pub fn win_rate(&self) -> f32 {
+        100.0 * self.pacman_wins as f32 / self.games.max(1) as f32
+    }
+
+    pub fn average_score(&self) -> i64 {
+        self.total_score / self.games.max(1) as i64
+    }
+
+    /// Average time Pac-Man lasted, in seconds
+    pub fn average_survival(&self) -> f32 {
+        self.total_ticks as f32 / self.games.max(1) as f32 / FPS as f32
+    }
+}
+
+/// Plays every game of a tournament
+///
+/// # Returns
+/// One result per pairing, Pac-Man styles outermost, or an error naming a bad maze
+pub fn run_tournament(options: &TournamentOptions) -> Result<Vec<Pairing>, String> {
+    if let Some(&maze) = options.mazes.iter().find(|&&maze| maze == 0 || maze > MAZES.len()) {
+        return Err(format!("There is no maze {} (mazes go from 1 to {})", maze, MAZES.len()));
+    }
+    let mut pairings = Vec::new();
+    for &pacman_style in &options.pacman_styles {
+        for &ghost_preset in &options.ghost_presets {
+            let mut pairing = Pairing { pacman_style, ghost_preset, games: 0, pacman_wins: 0, total_score: 0, total_ticks: 0 };
+            for &maze in &options.mazes {
+                set_current_maze(MAZES[maze - 1]);
+                for seed in 1..=options.seeds {
+                    let (won, score, ticks) = play_game(pacman_style, ghost_preset, seed, options.max_ticks);
+                    pairing.games += 1;
+                    pairing.pacman_wins += won as u32;
+                    pairing.total_score += score as i64;
+                    pairing.total_ticks += ticks as u64;
+                }
+            }
+            pairings.push(pairing);
+        }
+    }
+    Ok(pairings)
+}
+
+/// Plays one game on the current maze
+///
+/// # Returns
+/// Whether Pac-Man won, his score and the ticks the game lasted
+fn play_game(pacman_style: PacmanStyle, ghost_preset: GhostPreset, seed: u16, max_ticks: u32) -> (bool, i32, u32) {
+    let mut config = GameConfig::new(GameMode::Demo, PlayerRole::PacMan, None);
+    config.pacman_style = pacman_style;
+    config.ghost_preset = ghost_preset;
+    let mut game = Game::new_with_seed(config, seed);
+
+    let mut mazes_cleared = 0;
+    let mut ticks = 0;
+    while ticks < max_ticks && game.alive {
+        for event in game.tick(None, None) {
+            if let GameEvent::LevelCleared { .. } = event {
+                mazes_cleared += 1;
+            }
+        }
+        ticks += 1;
+    }
+    (mazes_cleared > 0, game.score(), ticks)
+}
+
+/// The results as a table with a header line
+pub fn format_table(pairings: &[Pairing]) -> String {
+    let mut text = format!(
+        "{:<12} {:<11} {:>5} {:>13} {:>10} {:>13}\n",
+        "Pac-Man", "Ghosts", "Games", "Pac-Man wins", "Avg score", "Avg survival"
+    );
+    for pairing in pairings {
+        text.push_str(&format!(
+            "{:<12} {:<11} {:>5} {:>12.1}% {:>10} {:>11.1} s\n",
+            pairing.pacman_style.name(), pairing.ghost_preset.name(), pairing.games,
+            pairing.win_rate(), pairing.average_score(), pairing.average_survival()
+        ));
+    }
+    text
+}
+
+/// The results as CSV with a header line
+pub fn format_csv(pairings: &[Pairing]) -> String {
+    let mut text = "pacman,ghosts,games,pacman_win_rate,avg_score,avg_survival_seconds\n".to_string();
+    for pairing in pairings {
+        text.push_str(&format!(
+            "{},{},{},{:.1},{},{:.1}\n",
+            pairing.pacman_style.name().to_ascii_lowercase(), pairing.ghost_preset.name().to_ascii_lowercase(),
+            pairing.games, pairing.win_rate(), pairing.average_score(), pairing.average_survival()
+        ));
+    }
+    text
+}
@@ -0,0 +1,333 @@
+//! Tiny chiptune tracker
+//!
+//! Songs are plain text so the soundtrack can be changed without touching the
+//! code. Each channel is a list of notes that loops on its own:
+//!
+//! ```text
+//! # Comment
+//! tempo 120                 # beats per minute
+//! channel 1 square:0.25 0.3 # channel number, waveform, volume (0-1)
+//! C4:1 E4:1 G4:0.5 R:0.5    # note + octave : duration in beats (R = rest)
+//! C#5:2 Bb4:2               # sharps and flats
+//! channel 2 triangle 0.4 adsr 0.01 0.1 0.7 0.05
+//! C3:4 G3:4
+//! ```
+//!
+//! Notes after a `channel` line belong to that channel until the next one.
+//!
+//! Waveforms, in the spirit of the 2600 and NES sound chips:
+//! - `square` (50% duty) or `square:<duty>`, e.g. `square:0.125`, `square:0.25`
+//! - `triangle`
+//! - `noise`, where the note sets how fast the noise changes
+//! - `sine` for soft tones
+//!
+//! The optional `adsr <attack> <decay> <sustain> <release>` shapes every note:
+//! attack, decay and release in seconds, sustain as a level (0-1). The release
+//! happens at the end of the note's own duration.
+
+use std::fmt;
+use std::path::Path;
+
+/// Shape of a channel's sound wave
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Waveform {
+    Sine,
+    /// Square wave, high for `duty` (0-1) of each cycle
+    Square { duty: f32 },
+    Triangle,
+    /// Random levels, a new one every cycle
+    Noise,
+}
+
+impl Waveform {
+    fn parse(name: &str) -> Option<Self> {
+        match name.split_once(':') {
+            Some(("square", duty)) => {
+                let duty: f32 = duty.parse().ok().filter(|duty: &f32| *duty > 0.0 && *duty < 1.0)?;
+                Some(Waveform::Square { duty })
+            }
+            Some(_) => None,
+            None => match name {
+                "sine" => Some(Waveform::Sine),
+                "square" => Some(Waveform::Square { duty: 0.5 }),
+                "triangle" => Some(Waveform::Triangle),
+                "noise" => Some(Waveform::Noise),
+                _ => None,
+            },
+        }
+    }
+
+    /// The waveform as written in a song, e.g. `square:0.25`
+    pub fn notation(self) -> String {
+        match self {
+            Waveform::Sine => "sine".to_string(),
+            Waveform::Square { duty: 0.5 } => "square".to_string(),
+            Waveform::Square { duty } => format!("square:{}", duty),
+            Waveform::Triangle => "triangle".to_string(),
+            Waveform::Noise => "noise".to_string(),
+        }
+    }
+
+    /// Value of the wave at `phase` (in cycles)
+    fn sample(self, phase: f64) -> f32 {
+        let cycle_phase = phase.fract() as f32;
+        match self {
+            Waveform::Sine => (phase * 2.0 * std::f64::consts::PI).sin() as f32,
+            Waveform::Square { duty } => if cycle_phase < duty { 1.0 } else { -1.0 },
+            Waveform::Triangle => 1.0 - 4.0 * (cycle_phase - 0.5).abs(),
+            Waveform::Noise => {
+                // Hash of the cycle number: the same phase always gives the same level
+                let mut x = (phase as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15);
+                x ^= x >> 29;
+                (x & 0xFFFF) as f32 / 32767.5 - 1.0
+            }
+        }
+    }
+}
+
+/// Volume shape applied to every note
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Envelope {
+    /// Seconds to rise from silence to full volume
+    pub attack: f32,
+    /// Seconds to fall from full volume to the sustain level
+    pub decay: f32,
+    /// Level held until the release (0-1)
+    pub sustain: f32,
+    /// Seconds to fade out at the end of the note
+    pub release: f32,
+}
+
+impl Envelope {
+    /// Full volume for the whole note
+    pub const FLAT: Envelope = Envelope { attack: 0.0, decay: 0.0, sustain: 1.0, release: 0.0 };
+
+    /// Volume (0-1) at `time` seconds into a note lasting `length` seconds
+    fn level(&self, time: f32, length: f32) -> f32 {
+        let level = if time < self.attack {
+            time / self.attack
+        } else if time < self.attack + self.decay {
+            1.0 - (1.0 - self.sustain) * (time - self.attack) / self.decay
+        } else {
+            self.sustain
+        };
+        let left = length - time;
+        if left < self.release {
+            level * (left / self.release).max(0.0)
+        } else {
+            level
+        }
+    }
+}
+
+/// One note (or rest) of a channel
+#[derive(Clone, Copy, Debug)]
+pub struct Note {
+    /// Pitch in Hz, None for a rest
+    pub freq: Option<f32>,
+    /// Length in beats
+    pub beats: f32,
+}
+
+/// One voice of a song
+#[derive(Clone, Debug)]
+pub struct Channel {
+    pub waveform: Waveform,
+    pub volume: f32,
+    pub envelope: Envelope,
+    pub notes: Vec<Note>,
+}
+
+impl Channel {
+    /// Length of the channel's loop in beats
+    pub fn total_beats(&self) -> f32 {
+        self.notes.iter().map(|note| note.beats).sum()
+    }
+}
+
+/// The channel's settings as written on its `channel` line, without the number
+/// (e.g. `square:0.25 0.12 adsr 0.01 0.4 0.6 0.15`)
+impl fmt::Display for Channel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.waveform.notation(), self.volume)?;
+        if self.envelope != Envelope::FLAT {
+            let envelope = self.envelope;
+            write!(f, " adsr {} {} {} {}", envelope.attack, envelope.decay, envelope.sustain, envelope.release)?;
+        }
+        Ok(())
+    }
+}
+
+/// A song: a tempo and channels that play together
+#[derive(Clone, Debug, Default)]
+pub struct Song {
+    /// Beats per minute
+    pub tempo: f32,
+    pub channels: Vec<Channel>,
+}
+
+impl Song {
+    /// Parses a song from its text notation
+    ///
+    /// # Returns
+    /// The song, or a message naming the first bad line
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut song = Song { tempo: 120.0, channels: Vec::new() };
+        for (line_index, line) in text.lines().enumerate() {
+            // Comments start with a `#` word (a `#` inside a note is a sharp)
+            let words: Vec<&str> = line.split_whitespace()
+                .take_while(|word| !word.starts_with('#'))
+                .collect();
+            let error = |message: &str| format!("Line {}: {}", line_index + 1, message);
+            match words.first().copied() {
+                None => {}
+                Some("tempo") => {
+                    song.tempo = words.get(1)
+                        .and_then(|value| value.parse().ok())
+                        .filter(|tempo: &f32| *tempo > 0.0)
+                        .ok_or_else(|| error("expected `tempo <beats per minute>`"))?;
+                }
+                Some("channel") => {
+                    let (Some(_), Some(waveform), Some(volume)) = (words.get(1), words.get(2), words.get(3)) else {
+                        return Err(error("expected `channel <number> <waveform> <volume>`"));
+                    };
+                    let waveform = Waveform::parse(waveform)
+                        .ok_or_else(|| error(&format!("unknown waveform `{}`", waveform)))?;
+                    let volume = volume.parse::<f32>()
+                        .map_err(|_| error(&format!("bad volume `{}`", volume)))?;
+                    let envelope = match words.get(4).copied() {
+                        None => Envelope::FLAT,
+                        Some("adsr") => parse_envelope(&words[5..])
+                            .ok_or_else(|| error("expected `adsr <attack> <decay> <sustain> <release>`"))?,
+                        Some(word) => return Err(error(&format!("unexpected `{}`", word))),
+                    };
+                    song.channels.push(Channel {
+                        waveform,
+                        volume: volume.clamp(0.0, 1.0),
+                        envelope,
+                        notes: Vec::new(),
+                    });
+                }
+                Some(_) => {
+                    let Some(channel) = song.channels.last_mut() else {
+                        return Err(error("notes before the first `channel` line"));
+                    };
+                    for word in words {
+                        let note = parse_note(word).ok_or_else(|| error(&format!("bad note `{}`", word)))?;
+                        channel.notes.push(note);
+                    }
+                }
+            }
+        }
+        Ok(song)
+    }
+
+    /// Reads and parses a song file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        Song::parse(&text)
+    }
+}
+
+/// Parses the four numbers after `adsr`
+fn parse_envelope(words: &[&str]) -> Option<Envelope> {
+    let [attack, decay, sustain, release] = words else {
+        return None;
+    };
+    let number = |word: &str| word.parse::<f32>().ok().filter(|value| *value >= 0.0);
+    Some(Envelope {
+        attack: number(attack)?,
+        decay: number(decay)?,
+        sustain: number(sustain)?.min(1.0),
+        release: number(release)?,
+    })
+}
+
+/// Parses `<note><octave>:<beats>`, e.g. `C#4:1` or `R:0.5`
+fn parse_note(word: &str) -> Option<Note> {
+    let (pitch, beats) = word.split_once(':')?;
+    let beats: f32 = beats.parse().ok().filter(|beats: &f32| *beats > 0.0)?;
+    if pitch == "R" {
+        return Some(Note { freq: None, beats });
+    }
+
+    let mut chars = pitch.chars();
+    let semitone = match chars.next()? {
+        'C' => 0, 'D' => 2, 'E' => 4, 'F' => 5, 'G' => 7, 'A' => 9, 'B' => 11,
+        _ => return None,
+    };
+    let rest = chars.as_str();
+    let (semitone, octave) = if let Some(octave) = rest.strip_prefix('#') {
+        (semitone + 1, octave)
+    } else if let Some(octave) = rest.strip_prefix('b') {
+        (semitone - 1, octave)
+    } else {
+        (semitone, rest)
+    };
+    let octave: i32 = octave.parse().ok()?;
+
+    // MIDI note number, A4 (69) = 440 Hz
+    let midi = (octave + 1) * 12 + semitone;
+    let freq = 440.0 * 2f32.powf((midi - 69) as f32 / 12.0);
+    Some(Note { freq: Some(freq), beats })
+}
+
+/// Playback position of one channel
+#[derive(Clone, Copy, Default)]
+struct Cursor {
+    /// Current note
+    index: usize,
+    /// Beat (within the channel's loop) the current note started on
+    start: f32,
+}
+
+/// Plays a song sample by sample
+pub struct Sequencer {
+    song: Song,
+    /// Loop length of each channel, in beats
+    lengths: Vec<f32>,
+    cursors: Vec<Cursor>,
+    sample_rate: f64,
+    /// Seconds since the song started
+    time: f64,
+}
+
+impl Sequencer {
+    pub fn new(song: Song, sample_rate: i32) -> Self {
+        let lengths = song.channels.iter().map(Channel::total_beats).collect();
+        let cursors = vec![Cursor::default(); song.channels.len()];
+        Self { song, lengths, cursors, sample_rate: sample_rate as f64, time: 0.0 }
+    }
+
+    /// Returns the next sample (all channels mixed)
+    pub fn next_sample(&mut self) -> f32 {
+        let beat_now = self.time * self.song.tempo as f64 / 60.0;
+        let mut sample = 0.0;
+        for (i, channel) in self.song.channels.iter().enumerate() {
+            let length = self.lengths[i];
+            if length <= 0.0 {
+                continue;
+            }
+            let beat = (beat_now % length as f64) as f32;
+            let cursor = &mut self.cursors[i];
+
+            // Back at the start of the loop
+            if beat < cursor.start {
+                *cursor = Cursor::default();
+            }
+            while cursor.index + 1 < channel.notes.len() && beat >= cursor.start + channel.notes[cursor.index].beats {
+                cursor.start += channel.notes[cursor.index].beats;
+                cursor.index += 1;
+            }
+
+            let note = channel.notes[cursor.index];
+            if let Some(freq) = note.freq {
+                let seconds_per_beat = 60.0 / self.song.tempo;
+                let level = channel.envelope.level((beat - cursor.start) * seconds_per_beat, note.beats * seconds_per_beat);
+                sample += channel.waveform.sample(self.time * freq as f64) * channel.volume * level;
+            }
+        }
+        self.time += 1.0 / self.sample_rate;
+        sample
+    }
+}
@@ -0,0 +1,214 @@
+//! Small UI widgets shared by the menus, settings pages, pause screen and HUD
+//!
+//! All widgets are drawn with the text font from `font.rs` in a common set of
+//! colors, so every screen looks the same:
+//! - Titles, headings, labels and hints
+//! - Selectable lists (the highlighted entry is the selection)
+//! - Value sliders and key-capture fields for settings
+//! - Text input fields
+//! - Bordered panels for result cards and overlays
+//!
+//! Selectable widgets return the screen area they cover, so menus can hit-test
+//! the mouse against what was actually drawn.
+
+use sdl2::pixels::Color;
+use sdl2::rect::Rect;
+use crate::font::{draw_text, text_width, Align, GLYPH_H};
+
+/// Normal text color
+pub const TEXT_COLOR: Color = Color::RGB(255, 255, 255);
+
+/// Selected entries, titles and text being edited
+pub const HIGHLIGHT_COLOR: Color = Color::RGB(255, 255, 0);
+
+/// Hints and secondary information
+pub const HINT_COLOR: Color = Color::RGB(150, 150, 150);
+
+/// Locked entries, greyed out
+const LOCKED_COLOR: Color = Color::RGB(90, 90, 90);
+
+/// A locked entry while selected
+const LOCKED_HIGHLIGHT_COLOR: Color = Color::RGB(130, 130, 50);
+
+/// Vertical distance between list entries in pixels
+pub const LIST_SPACING: i32 = 40;
+
+/// Scale of list entries and headings
+const LIST_SCALE: i32 = 2;
+
+/// Extra space around an entry's text that still counts as pointing at it
+const HIT_PADDING: i32 = 6;
+
+/// Area covered by text drawn at list scale, padded for easier pointing
+fn hit_rect(left: i32, y: i32, width: i32) -> Rect {
+    let height = GLYPH_H * LIST_SCALE + 2 * HIT_PADDING;
+    Rect::new(left - HIT_PADDING, y - HIT_PADDING, (width + 2 * HIT_PADDING) as u32, height as u32)
+}
+
+/// Index of the first area containing the point, if any
+pub fn hit_test(areas: &[Rect], x: i32, y: i32) -> Option<usize> {
+    areas.iter().position(|area| area.contains_point((x, y)))
+}
+
+/// Large yellow title (e.g. the game name)
+pub fn title(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, text: &str, center_x: i32, y: i32) {
+    draw_text(canvas, text, center_x, y, 3, HIGHLIGHT_COLOR, Align::Center);
+}
+
+/// Centered heading above a screen's content
+pub fn heading(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, text: &str, center_x: i32, y: i32) {
+    draw_text(canvas, text, center_x, y, LIST_SCALE, TEXT_COLOR, Align::Center);
+}
+
+/// Small line of text in any color
+pub fn label(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    text: &str,
+    x: i32,
+    y: i32,
+    color: Color,
+    align: Align,
+) {
+    draw_text(canvas, text, x, y, 1, color, align);
+}
+
+/// Small grey centered hint (controls, status)
+pub fn hint(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, text: &str, center_x: i32, y: i32) {
+    draw_text(canvas, text, center_x, y, 1, HINT_COLOR, Align::Center);
+}
+
+/// Vertical list of entries, one per `LIST_SPACING`, with the selected one highlighted
+///
+/// # Returns
+/// The area of each entry, in order
+pub fn list(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    items: &[&str],
+    selected: usize,
+    center_x: i32,
+    start_y: i32,
+) -> Vec<Rect> {
+    list_spaced(canvas, items, selected, center_x, start_y, LIST_SPACING)
+}
+
+/// A `list` with its entries `spacing` pixels apart, for pages with many entries
+pub fn list_spaced(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    items: &[&str],
+    selected: usize,
+    center_x: i32,
+    start_y: i32,
+    spacing: i32,
+) -> Vec<Rect> {
+    list_locked(canvas, items, &[], selected, center_x, start_y, spacing)
+}
+
+/// A `list_spaced` with the entries marked in `locked` greyed out
+pub fn list_locked(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    items: &[&str],
+    locked: &[bool],
+    selected: usize,
+    center_x: i32,
+    start_y: i32,
+    spacing: i32,
+) -> Vec<Rect> {
+    let mut areas = Vec::with_capacity(items.len());
+    for (i, item) in items.iter().enumerate() {
+        let color = match (i == selected, locked.get(i).copied().unwrap_or(false)) {
+            (true, false) => HIGHLIGHT_COLOR,
+            (false, false) => TEXT_COLOR,
+            (true, true) => LOCKED_HIGHLIGHT_COLOR,
+            (false, true) => LOCKED_COLOR,
+        };
+        let y = start_y + i as i32 * spacing;
+        draw_text(canvas, item, center_x, y, LIST_SCALE, color, Align::Center);
+        let width = text_width(item, LIST_SCALE);
+        areas.push(hit_rect(center_x - width / 2, y, width));
+    }
+    areas
+}
+
+/// Screen areas of a drawn slider
+pub struct SliderArea {
+    /// The whole row, label included
+    pub row: Rect,
+    /// Just the bar
+    pub bar: Rect,
+}
+
+impl SliderArea {
+    /// Slider value (0.0 to 1.0) for a point at `x` on the bar
+    pub fn value_at(&self, x: i32) -> f32 {
+        ((x - self.bar.x()) as f32 / self.bar.width().max(1) as f32).clamp(0.0, 1.0)
+    }
+}
+
+/// Labelled slider showing a value between 0.0 and 1.0 as a filled bar
+///
+/// The label sits left of `center_x` and the bar right of it.
+pub fn slider(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    label_text: &str,
+    value: f32,
+    center_x: i32,
+    y: i32,
+    selected: bool,
+) -> SliderArea {
+    let color = if selected { HIGHLIGHT_COLOR } else { TEXT_COLOR };
+    let gap = 8;
+    draw_text(canvas, label_text, center_x - gap, y, LIST_SCALE, color, Align::Right);
+
+    let bar_w = 120;
+    let bar_h = GLYPH_H * LIST_SCALE;
+    let bar = Rect::new(center_x + gap, y, bar_w as u32, bar_h as u32);
+    canvas.set_draw_color(color);
+    let _ = canvas.draw_rect(bar);
+    let fill_w = ((bar_w - 4) as f32 * value.clamp(0.0, 1.0)) as u32;
+    if fill_w > 0 {
+        let _ = canvas.fill_rect(Rect::new(bar.x() + 2, bar.y() + 2, fill_w, (bar_h - 4) as u32));
+    }
+    let label_w = text_width(label_text, LIST_SCALE);
+    SliderArea {
+        row: hit_rect(center_x - gap - label_w, y, label_w + 2 * gap + bar_w),
+        bar,
+    }
+}
+
+/// Labelled key binding; while `capturing`, it asks for the new key instead
+///
+/// # Returns
+/// The area of the whole row
+pub fn key_field(
+    canvas: &mut sdl2::render::Canvas<sdl2::video::Window>,
+    label_text: &str,
+    key_name: &str,
+    capturing: bool,
+    center_x: i32,
+    y: i32,
+    selected: bool,
+) -> Rect {
+    let color = if selected { HIGHLIGHT_COLOR } else { TEXT_COLOR };
+    let gap = 8;
+    draw_text(canvas, label_text, center_x - gap, y, LIST_SCALE, color, Align::Right);
+    let value = if capturing { "Press a key".to_string() } else { format!("[{}]", key_name) };
+    let value_color = if capturing { Color::RGB(0, 255, 0) } else { color };
+    draw_text(canvas, &value, center_x + gap, y, LIST_SCALE, value_color, Align::Left);
+    let label_w = text_width(label_text, LIST_SCALE);
+    hit_rect(center_x - gap - label_w, y, label_w + 2 * gap + text_width(&value, LIST_SCALE))
+}
+
+/// Single-line text input with a caret after the text
+pub fn text_field(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, text: &str, center_x: i32, y: i32) {
+    let shown = format!("{}_", text);
+    draw_text(canvas, &shown, center_x, y, LIST_SCALE, HIGHLIGHT_COLOR, Align::Center);
+}
+
+/// Black panel with a colored border (result cards, dialogs)
+pub fn panel(canvas: &mut sdl2::render::Canvas<sdl2::video::Window>, rect: Rect, border: Color) {
+    canvas.set_draw_color(Color::RGB(0, 0, 0));
+    let _ = canvas.fill_rect(rect);
+    canvas.set_draw_color(border);
+    let _ = canvas.draw_rect(rect);
+}
+
@@ -0,0 +1,154 @@
+//! Unlockables: mazes, maze colors and skins earned by playing
+//!
+//! Some of the game is earned rather than there from the start. Each
+//! unlockable has a requirement, either an achievement (reach a level, eat so
+//! many ghosts, play so many runs, score so much in one run) or a milestone of
+//! score summed over every run, and it unlocks for a profile once that
+//! profile's run history (see `stats`) meets it.
+//!
+//! Nothing else is saved: the history is the record, so unlocks follow a
+//! profile through exports and imports and never get out of step with it.
+//! The menus show what is still locked greyed out, with what it takes; the
+//! **Unlocks** settings page lists everything and keeps a chosen set of maze
+//! colors for every level; earned skins are worn from the **Skins** page.
+
+use crate::constants::MAZE_PALETTES;
+use crate::skins::BUILT_IN_SKINS;
+use crate::stats::{History, Lifetime};
+
+/// Something that can be locked
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Unlock {
+    /// A built-in maze (index in the maze selection)
+    Maze(usize),
+    /// Maze colors kept for every level (index into `MAZE_PALETTES`)
+    Palette(usize),
+    /// A built-in skin (index into `BUILT_IN_SKINS`)
+    Skin(usize),
+}
+
+/// What a profile has to have done for an unlock
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Requirement {
+    /// Points scored over all runs
+    TotalScore(i64),
+    /// Points scored in one run
+    BestScore(i32),
+    /// Level reached in a run
+    Level(u32),
+    /// Frightened ghosts eaten over all runs
+    Ghosts(u64),
+    /// Runs played
+    Runs(u32),
+}
+
+impl Requirement {
+    /// Whether a profile's runs meet it
+    pub fn met(self, lifetime: &Lifetime) -> bool {
+        let (done, needed) = self.progress(lifetime);
+        done >= needed
+    }
+
+    /// How far a profile has got, and how far it has to get
+    fn progress(self, lifetime: &Lifetime) -> (i64, i64) {
+        match self {
+            Requirement::TotalScore(points) => (lifetime.total_score, points),
+            Requirement::BestScore(points) => (lifetime.best_score as i64, points as i64),
+            Requirement::Level(level) => (lifetime.highest_level as i64, level as i64),
+            Requirement::Ghosts(ghosts) => (lifetime.ghosts as i64, ghosts as i64),
+            Requirement::Runs(runs) => (lifetime.runs as i64, runs as i64),
+        }
+    }
+
+    /// What it takes, with how far a profile has got, e.g. "Reach level 3 (2 so far)"
+    pub fn describe(self, lifetime: &Lifetime) -> String {
+        let goal = match self {
+            Requirement::TotalScore(points) => format!("Score {} points over all runs", points),
+            Requirement::BestScore(points) => format!("Score {} points in one run", points),
+            Requirement::Level(level) => format!("Reach level {}", level),
+            Requirement::Ghosts(ghosts) => format!("Eat {} ghosts", ghosts),
+            Requirement::Runs(runs) => format!("Play {} runs", runs),
+        };
+        format!("{} ({} so far)", goal, self.progress(lifetime).0)
+    }
+}
+
+/// An unlock and what it takes
+pub struct Unlockable {
+    pub unlock: Unlock,
+    pub requirement: Requirement,
+}
+
+/// Everything locked at first; whatever isn't here is always there
+pub const UNLOCKABLES: [Unlockable; 8] = [
+    Unlockable { unlock: Unlock::Maze(1), requirement: Requirement::TotalScore(10_000) },
+    Unlockable { unlock: Unlock::Palette(1), requirement: Requirement::Level(2) },
+    Unlockable { unlock: Unlock::Palette(2), requirement: Requirement::Level(3) },
+    Unlockable { unlock: Unlock::Palette(3), requirement: Requirement::Runs(10) },
+    Unlockable { unlock: Unlock::Palette(4), requirement: Requirement::Ghosts(50) },
+    Unlockable { unlock: Unlock::Palette(5), requirement: Requirement::BestScore(20_000) },
+    Unlockable { unlock: Unlock::Skin(1), requirement: Requirement::Level(4) },
+    Unlockable { unlock: Unlock::Skin(2), requirement: Requirement::TotalScore(25_000) },
+];
+
+/// Names of the built-in mazes, indexed like the maze selection
+pub const MAZE_NAMES: [&str; 2] = ["Maze 1: Classic", "Maze 2: Simple"];
+
+impl Unlock {
+    /// Display name
+    pub fn name(self) -> String {
+        match self {
+            Unlock::Maze(index) => MAZE_NAMES.get(index).unwrap_or(&"Maze").to_string(),
+            Unlock::Palette(index) => {
+                format!("{} Maze Colors", MAZE_PALETTES.get(index).map(|palette| palette.name).unwrap_or("Other"))
+            }
+            Unlock::Skin(index) => format!("{} Skin", BUILT_IN_SKINS.get(index).unwrap_or(&"Other")),
+        }
+    }
+
+    /// What it takes to unlock (None if it is never locked)
+    pub fn requirement(self) -> Option<Requirement> {
+        UNLOCKABLES.iter().find(|unlockable| unlockable.unlock == self).map(|unlockable| unlockable.requirement)
+    }
+}
+
+/// What one profile has unlocked
+pub struct Unlocks {
+    lifetime: Lifetime,
+}
+
+impl Unlocks {
+    /// The unlocks of a profile, from its runs
+    pub fn of(history: &History, player: &str) -> Self {
+        Self { lifetime: history.lifetime(player) }
+    }
+
+    /// Whether something is unlocked
+    pub fn has(&self, unlock: Unlock) -> bool {
+        unlock.requirement().is_none_or(|requirement| requirement.met(&self.lifetime))
+    }
+
+    /// What is still missing for something, e.g. "Reach level 3 (2 so far)"
+    ///
+    /// # Returns
+    /// None if it is unlocked
+    pub fn missing(&self, unlock: Unlock) -> Option<String> {
+        unlock.requirement()
+            .filter(|requirement| !requirement.met(&self.lifetime))
+            .map(|requirement| requirement.describe(&self.lifetime))
+    }
+
+    /// Everything that can be locked and is unlocked, in table order
+    pub fn unlocked(&self) -> Vec<Unlock> {
+        UNLOCKABLES.iter().map(|unlockable| unlockable.unlock).filter(|&unlock| self.has(unlock)).collect()
+    }
+}
+
+/// What the Unlocks page lists: the mazes that are earned, every set of maze
+/// colors, then the skins that are earned
+pub fn listed() -> Vec<Unlock> {
+    let earned = || UNLOCKABLES.iter().map(|unlockable| unlockable.unlock);
+    let mazes = earned().filter(|unlock| matches!(unlock, Unlock::Maze(_)));
+    let skins = earned().filter(|unlock| matches!(unlock, Unlock::Skin(_)));
+    mazes.chain((0..MAZE_PALETTES.len()).map(Unlock::Palette)).chain(skins).collect()
+}
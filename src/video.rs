@@ -0,0 +1,172 @@
+//! Video settings: fullscreen, monitor, window size, vsync, frame rate cap and render scale
+//! 
+//! The settings are edited on the video settings screen (and fullscreen with
+//! F11) and applied to the game window with `VideoSettings::apply`. Vsync is a
+//! property of the renderer, so changing it means rebuilding the canvas
+//! (`build_canvas`). Without vsync, `FrameLimiter` paces the main loop.
+//! Below 100%, the render scale draws the game at a fraction of the window's
+//! resolution and blows it up with square pixels, for machines too weak to
+//! fill the whole window every frame.
+
+use std::time::{Duration, Instant};
+use sdl2::render::Canvas;
+use sdl2::video::{FullscreenType, Window, WindowPos};
+use crate::constants::{
+    VIEW_W, VIEW_H, SCORE_AREA, WINDOW_SCALE, WINDOW_SCALES,
+    FPS_CAPS, DEFAULT_FPS_CAP_INDEX, FRAME_LIMITER_SPIN_MS, RENDER_SCALES
+};
+
+/// Current video settings
+#[derive(Clone, Copy)]
+pub struct VideoSettings {
+    /// Desktop fullscreen (borderless, at the monitor's own resolution)
+    pub fullscreen: bool,
+    /// Monitor the window is placed on
+    pub display_index: i32,
+    /// Index into `WINDOW_SCALES` for the windowed size
+    pub scale_index: usize,
+    /// Wait for the display's refresh when presenting
+    pub vsync: bool,
+    /// Index into `FPS_CAPS` (only used when vsync is off)
+    pub fps_cap_index: usize,
+    /// Index into `RENDER_SCALES`
+    pub render_scale_index: usize,
+    /// Photosensitive mode: nothing flashes (see `RenderCache::photosensitive`)
+    pub photosensitive: bool,
+    /// Maze colors chosen for every level (index into `MAZE_PALETTES`; None
+    /// changes them by level). Only used once unlocked (see `unlocks`)
+    pub maze_colors: Option<usize>,
+}
+
+impl VideoSettings {
+    /// Settings matching the window created at startup
+    pub fn new(fullscreen: bool) -> Self {
+        Self {
+            fullscreen,
+            display_index: 0,
+            scale_index: WINDOW_SCALES.iter().position(|&scale| scale == WINDOW_SCALE).unwrap_or(0),
+            vsync: true,
+            fps_cap_index: DEFAULT_FPS_CAP_INDEX,
+            render_scale_index: 0,
+            photosensitive: false,
+            maze_colors: None,
+        }
+    }
+
+    /// Frame rate cap in frames per second (0 = unlimited)
+    pub fn fps_cap(&self) -> u32 {
+        FPS_CAPS[self.fps_cap_index.min(FPS_CAPS.len() - 1)]
+    }
+
+    /// Switches to the next frame rate cap (wrapping around)
+    pub fn next_fps_cap(&mut self) {
+        self.fps_cap_index = (self.fps_cap_index + 1) % FPS_CAPS.len();
+    }
+
+    /// Render scale in percent of the window's resolution
+    pub fn render_scale(&self) -> u32 {
+        RENDER_SCALES[self.render_scale_index.min(RENDER_SCALES.len() - 1)]
+    }
+
+    /// Switches to the next render scale (wrapping around)
+    pub fn next_render_scale(&mut self) {
+        self.render_scale_index = (self.render_scale_index + 1) % RENDER_SCALES.len();
+    }
+
+    /// Windowed size in pixels for the selected scale
+    pub fn window_size(&self) -> (u32, u32) {
+        let scale = WINDOW_SCALES[self.scale_index.min(WINDOW_SCALES.len() - 1)];
+        ((VIEW_W * scale) as u32, ((VIEW_H + SCORE_AREA) * scale) as u32)
+    }
+
+    /// Switches to the next windowed size (wrapping around)
+    pub fn next_scale(&mut self) {
+        self.scale_index = (self.scale_index + 1) % WINDOW_SCALES.len();
+    }
+
+    /// Switches to the next monitor (wrapping around)
+    pub fn next_display(&mut self, display_count: i32) {
+        self.display_index = (self.display_index + 1) % display_count.max(1);
+    }
+
+    /// Applies the settings to the window
+    /// 
+    /// The window is first moved (centered) onto the chosen monitor, since
+    /// desktop fullscreen always fills the monitor the window is on.
+    pub fn apply(&self, window: &mut Window) -> Result<(), String> {
+        let bounds = window.subsystem().display_bounds(self.display_index)?;
+        let (w, h) = self.window_size();
+        
+        // Leave fullscreen before resizing or moving
+        window.set_fullscreen(FullscreenType::Off)?;
+        window.set_size(w, h).map_err(|e| e.to_string())?;
+        window.set_position(
+            WindowPos::Positioned(bounds.x() + (bounds.width() as i32 - w as i32) / 2),
+            WindowPos::Positioned(bounds.y() + (bounds.height() as i32 - h as i32) / 2),
+        );
+        
+        if self.fullscreen {
+            window.set_fullscreen(FullscreenType::Desktop)?;
+        }
+        Ok(())
+    }
+}
+
+/// Creates the renderer for a window, with or without vsync
+pub fn build_canvas(window: Window, vsync: bool) -> Result<Canvas<Window>, String> {
+    let mut builder = window.into_canvas().accelerated();
+    if vsync {
+        builder = builder.present_vsync();
+    }
+    builder.build().map_err(|e| e.to_string())
+}
+
+/// Keeps the main loop at a target frame rate when vsync is off
+/// 
+/// Sleeps until shortly before each frame's deadline, then spins for the rest,
+/// since OS sleeps are too coarse to hit e.g. 144 Hz on their own. Deadlines
+/// advance by a fixed period so small overshoots don't accumulate into drift.
+pub struct FrameLimiter {
+    next_frame: Instant,
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self { next_frame: Instant::now() }
+    }
+
+    /// Waits until the next frame is due
+    /// 
+    /// # Arguments
+    /// * `fps_cap` - Target frames per second (0 = don't wait)
+    pub fn wait(&mut self, fps_cap: u32) {
+        let now = Instant::now();
+        if fps_cap == 0 {
+            self.next_frame = now;
+            return;
+        }
+        let period = Duration::from_secs_f64(1.0 / fps_cap as f64);
+        self.next_frame += period;
+        
+        // Fell behind (slow frame, window dragged...): start pacing again from now
+        if self.next_frame < now {
+            self.next_frame = now;
+            return;
+        }
+        
+        let spin = Duration::from_millis(FRAME_LIMITER_SPIN_MS);
+        let remaining = self.next_frame - now;
+        if remaining > spin {
+            std::thread::sleep(remaining - spin);
+        }
+        while Instant::now() < self.next_frame {
+            std::thread::yield_now();
+        }
+    }
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
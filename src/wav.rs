@@ -0,0 +1,136 @@
+//! Minimal WAV decoder and encoder
+//!
+//! Reads uncompressed WAV files (8, 16, 24 or 32-bit integer PCM and 32-bit
+//! float, any number of channels) into mono f32 samples at the mixer's sample
+//! rate, without going through SDL, and writes the mixer's samples back out as
+//! 16-bit mono PCM (the sound of clips).
+
+/// Sample encoding of a WAV file
+#[derive(Clone, Copy, PartialEq)]
+enum Encoding {
+    Int,
+    Float,
+}
+
+/// Decodes a WAV file to mono samples in -1..1 at `sample_rate`
+///
+/// Channels are averaged and the result is resampled linearly.
+pub fn decode_wav(bytes: &[u8], sample_rate: i32) -> Result<Vec<f32>, String> {
+    if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+        return Err("not a WAV file".to_string());
+    }
+
+    let mut format = None;
+    let mut data = None;
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+        let body = &bytes[pos + 8..(pos + 8 + size).min(bytes.len())];
+        match id {
+            b"fmt " => format = Some(parse_format(body)?),
+            b"data" => data = Some(body),
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos += 8 + size + (size & 1);
+    }
+
+    let (encoding, channels, source_rate, bits) = format.ok_or("missing fmt chunk")?;
+    let data = data.ok_or("missing data chunk")?;
+    let bytes_per_sample = (bits / 8) as usize;
+    let frame_size = bytes_per_sample * channels as usize;
+
+    let mono: Vec<f32> = data.chunks_exact(frame_size)
+        .map(|frame| {
+            let sum: f32 = frame.chunks_exact(bytes_per_sample)
+                .map(|sample| decode_sample(sample, encoding))
+                .sum();
+            sum / channels as f32
+        })
+        .collect();
+    Ok(resample(&mono, source_rate, sample_rate))
+}
+
+/// Reads the fmt chunk: encoding, channel count, sample rate and bits per sample
+fn parse_format(body: &[u8]) -> Result<(Encoding, u16, i32, u16), String> {
+    if body.len() < 16 {
+        return Err("fmt chunk too short".to_string());
+    }
+    let mut tag = u16::from_le_bytes([body[0], body[1]]);
+    let channels = u16::from_le_bytes([body[2], body[3]]);
+    let rate = u32::from_le_bytes([body[4], body[5], body[6], body[7]]) as i32;
+    let bits = u16::from_le_bytes([body[14], body[15]]);
+
+    // WAVE_FORMAT_EXTENSIBLE keeps the real format at the start of its sub-format GUID
+    if tag == 0xFFFE && body.len() >= 26 {
+        tag = u16::from_le_bytes([body[24], body[25]]);
+    }
+
+    let encoding = match (tag, bits) {
+        (1, 8 | 16 | 24 | 32) => Encoding::Int,
+        (3, 32) => Encoding::Float,
+        _ => return Err(format!("unsupported WAV format (tag {}, {} bits)", tag, bits)),
+    };
+    if channels == 0 || rate <= 0 {
+        return Err("bad channel count or sample rate".to_string());
+    }
+    Ok((encoding, channels, rate, bits))
+}
+
+/// Converts one little-endian sample to -1..1
+fn decode_sample(sample: &[u8], encoding: Encoding) -> f32 {
+    match (encoding, sample.len()) {
+        (Encoding::Float, _) => f32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]),
+        // 8-bit WAV is unsigned
+        (Encoding::Int, 1) => (sample[0] as f32 - 128.0) / 128.0,
+        (Encoding::Int, 2) => i16::from_le_bytes([sample[0], sample[1]]) as f32 / 32768.0,
+        (Encoding::Int, 3) => i32::from_le_bytes([0, sample[0], sample[1], sample[2]]) as f32 / 2147483648.0,
+        (Encoding::Int, _) => i32::from_le_bytes([sample[0], sample[1], sample[2], sample[3]]) as f32 / 2147483648.0,
+    }
+}
+
+/// Encodes mono samples in -1..1 as a 16-bit PCM WAV file
+pub fn encode_wav(samples: &[f32], sample_rate: u32) -> Vec<u8> {
+    let data_size = (samples.len() * 2) as u32;
+    let mut bytes = Vec::with_capacity(44 + data_size as usize);
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_size).to_le_bytes());
+    bytes.extend_from_slice(b"WAVE");
+
+    // PCM, one channel, 2 bytes per frame
+    bytes.extend_from_slice(b"fmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&sample_rate.to_le_bytes());
+    bytes.extend_from_slice(&(sample_rate * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_size.to_le_bytes());
+    for &sample in samples {
+        let value = (sample.clamp(-1.0, 1.0) * 32767.0).round() as i16;
+        bytes.extend_from_slice(&value.to_le_bytes());
+    }
+    bytes
+}
+
+/// Linear resampling from one rate to another
+pub(crate) fn resample(samples: &[f32], from_rate: i32, to_rate: i32) -> Vec<f32> {
+    if from_rate == to_rate || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let step = from_rate as f64 / to_rate as f64;
+    let count = (samples.len() as f64 / step) as usize;
+    (0..count)
+        .map(|i| {
+            let position = i as f64 * step;
+            let index = position as usize;
+            let next = samples[(index + 1).min(samples.len() - 1)];
+            let fraction = (position - index as f64) as f32;
+            samples[index] + (next - samples[index]) * fraction
+        })
+        .collect()
+}
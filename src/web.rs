@@ -0,0 +1,295 @@
+//! Browser build (wasm32-unknown-unknown)
+//!
+//! The game core has no SDL dependency, so it compiles to WebAssembly with
+//! `--no-default-features`. This module is the thin bridge to the page in
+//! `web/`: plain exported functions, no bindings generator.
+//!
+//! - Rendering: `paclike_render` draws the frame with `soft_render` into an
+//!   RGBA buffer in wasm memory, which the page copies into a `<canvas>` with
+//!   `putImageData`.
+//! - Input: the page maps keys to a direction with `paclike_set_input`, and
+//!   passes touches on to the on-screen D-pad and pause button (`touch`) with
+//!   `paclike_touch`.
+//! - Audio: `paclike_audio_start` builds the usual `Mixer` and the page pulls
+//!   samples with `paclike_audio_fill` from a Web Audio callback.
+//! - Timing: the page calls `paclike_tick` `paclike_ticks_per_second()` times
+//!   a second, like the fixed timestep of the desktop loop.
+
+use std::cell::RefCell;
+use std::sync::mpsc::Sender;
+use crate::audio::{AudioBackend, AudioEvent, AudioManager, Mixer};
+use crate::constants::FPS;
+use crate::env::Action;
+use crate::events::EventBus;
+use crate::game::{Game, GameEvent};
+use crate::game_config::{GameConfig, GameMode, PlayerRole};
+use crate::soft_render::{Framebuffer, FRAME_W, FRAME_H};
+use crate::touch::{TouchButton, TouchControls, TouchLayout};
+
+/// Game running in the page
+struct WebGame {
+    game: Game,
+    /// Direction held by the player
+    input: Option<(i32, i32)>,
+    /// The on-screen D-pad and pause button
+    touch: TouchControls,
+    screen: Framebuffer,
+    /// The screen as RGBA bytes, handed to the page
+    frame: Vec<u8>,
+    /// Hands gameplay events to the audio
+    events: EventBus,
+}
+
+/// Audio started by the page
+struct WebAudioState {
+    /// Keeps the event channel open
+    _manager: AudioManager,
+    events: Sender<AudioEvent>,
+    /// Samples handed to the page by the last `paclike_audio_fill`
+    buffer: Vec<f32>,
+}
+
+thread_local! {
+    static GAME: RefCell<Option<WebGame>> = const { RefCell::new(None) };
+    /// Whether the page wants touch controls (kept across games)
+    static TOUCH_ENABLED: RefCell<bool> = const { RefCell::new(true) };
+    /// Whether the page asked for photosensitive mode (kept across games)
+    static PHOTOSENSITIVE: RefCell<bool> = const { RefCell::new(false) };
+    static AUDIO: RefCell<Option<WebAudioState>> = const { RefCell::new(None) };
+    /// Mixer built by `WebAudio::start`, filled on the page's audio callback
+    static MIXER: RefCell<Option<Mixer>> = const { RefCell::new(None) };
+}
+
+/// Audio backend for the browser: the page's Web Audio callback pulls samples
+struct WebAudio {
+    sample_rate: i32,
+}
+
+impl AudioBackend for WebAudio {
+    fn start(&mut self, make_mixer: Box<dyn FnOnce(i32) -> Mixer + Send>) -> Result<(), String> {
+        let mixer = make_mixer(self.sample_rate);
+        MIXER.with(|slot| *slot.borrow_mut() = Some(mixer));
+        Ok(())
+    }
+}
+
+/// Starts a new single-player game
+#[no_mangle]
+pub extern "C" fn paclike_start(seed: u32) {
+    // The LFSR never leaves zero, so zero falls back to the default seed
+    let game = Game::new_with_seed(GameConfig::new(GameMode::SinglePlayer, PlayerRole::PacMan, None), seed as u16);
+    // Audio may start after the game, so events go through `send_audio`
+    let mut events = EventBus::new();
+    events.subscribe(Box::new(|_: &Game, event: &GameEvent| {
+        if let Some(audio_event) = AudioEvent::for_game_event(event) {
+            send_audio(audio_event);
+        }
+    }));
+    let mut screen = Framebuffer::new();
+    screen.photosensitive = PHOTOSENSITIVE.with(|on| *on.borrow());
+    GAME.with(|slot| {
+        *slot.borrow_mut() = Some(WebGame {
+            game,
+            input: None,
+            touch: TouchControls::new(TOUCH_ENABLED.with(|enabled| *enabled.borrow())),
+            screen,
+            frame: Vec::with_capacity((FRAME_W * FRAME_H * 4) as usize),
+            events,
+        })
+    });
+    send_audio(AudioEvent::GameStarted);
+}
+
+/// Sets the held direction: 0 none, 1 up, 2 down, 3 left, 4 right (as `env::Action`)
+#[no_mangle]
+pub extern "C" fn paclike_set_input(direction: u32) {
+    let input = Action::from_index(direction as usize).and_then(Action::direction);
+    GAME.with(|slot| {
+        if let Some(web) = slot.borrow_mut().as_mut() {
+            web.input = input;
+            // A keyboard is in use: the on-screen controls get out of the way
+            web.touch.physical_input();
+        }
+    });
+}
+
+/// A touch on the frame, in frame pixels
+///
+/// # Arguments
+/// * `finger` - Id of the touch, to follow it until it ends
+/// * `phase` - 0 started, 1 moved, 2 ended
+#[no_mangle]
+pub extern "C" fn paclike_touch(finger: u32, x: i32, y: i32, phase: u32) {
+    let layout = TouchLayout::new(FRAME_W, FRAME_H);
+    GAME.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        let Some(web) = slot.as_mut() else {
+            return;
+        };
+        match phase {
+            0 => {
+                if web.touch.press(finger as i64, x, y, &layout) == Some(TouchButton::Pause) {
+                    web.game.paused = !web.game.paused;
+                }
+            }
+            1 => web.touch.slide(finger as i64, x, y, &layout),
+            _ => web.touch.release(finger as i64),
+        }
+    });
+}
+
+/// Turns the on-screen controls on (1) or off (0)
+#[no_mangle]
+pub extern "C" fn paclike_set_touch_controls(enabled: u32) {
+    TOUCH_ENABLED.with(|slot| *slot.borrow_mut() = enabled != 0);
+    GAME.with(|slot| {
+        if let Some(web) = slot.borrow_mut().as_mut() {
+            web.touch.enabled = enabled != 0;
+        }
+    });
+}
+
+/// Turns photosensitive mode on (1) or off (0): nothing flashes (see `soft_render`)
+#[no_mangle]
+pub extern "C" fn paclike_set_photosensitive(on: u32) {
+    PHOTOSENSITIVE.with(|slot| *slot.borrow_mut() = on != 0);
+    GAME.with(|slot| {
+        if let Some(web) = slot.borrow_mut().as_mut() {
+            web.screen.photosensitive = on != 0;
+        }
+    });
+}
+
+/// Pauses or resumes the game
+#[no_mangle]
+pub extern "C" fn paclike_toggle_pause() {
+    GAME.with(|slot| {
+        if let Some(web) = slot.borrow_mut().as_mut() {
+            web.game.paused = !web.game.paused;
+        }
+    });
+}
+
+/// Runs one game tick
+///
+/// # Returns
+/// 1 once the game is over, 0 otherwise
+#[no_mangle]
+pub extern "C" fn paclike_tick() -> u32 {
+    GAME.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        let Some(web) = slot.as_mut() else {
+            return 0;
+        };
+        if web.game.paused {
+            return 0;
+        }
+        let input = web.input.or_else(|| web.touch.direction());
+        let events = web.game.tick(input, None);
+        web.events.publish(&web.game, &events);
+        !web.game.alive as u32
+    })
+}
+
+/// Draws the current frame
+///
+/// # Returns
+/// Pointer to `paclike_frame_width() * paclike_frame_height()` RGBA pixels,
+/// valid until the next call (null before `paclike_start`)
+#[no_mangle]
+pub extern "C" fn paclike_render() -> *const u8 {
+    GAME.with(|slot| {
+        let mut slot = slot.borrow_mut();
+        let Some(web) = slot.as_mut() else {
+            return std::ptr::null();
+        };
+        let _ = web.game.draw(&mut web.screen);
+        if web.touch.visible() {
+            web.screen.draw_touch_controls(&TouchLayout::new(FRAME_W, FRAME_H), web.touch.held());
+        }
+        web.screen.write_rgba(&mut web.frame);
+        web.frame.as_ptr()
+    })
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_frame_width() -> u32 {
+    FRAME_W as u32
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_frame_height() -> u32 {
+    FRAME_H as u32
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_ticks_per_second() -> u32 {
+    FPS
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_score() -> i32 {
+    GAME.with(|slot| slot.borrow().as_ref().map(|web| web.game.score()).unwrap_or(0))
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_lives() -> i32 {
+    GAME.with(|slot| slot.borrow().as_ref().map(|web| web.game.lives).unwrap_or(0))
+}
+
+#[no_mangle]
+pub extern "C" fn paclike_level() -> u32 {
+    GAME.with(|slot| slot.borrow().as_ref().map(|web| web.game.level).unwrap_or(0))
+}
+
+/// Starts the music and sound effects (browsers only allow this after a user gesture)
+///
+/// # Returns
+/// 1 on success, 0 if audio was already started or could not start
+#[no_mangle]
+pub extern "C" fn paclike_audio_start(sample_rate: u32) -> u32 {
+    if AUDIO.with(|slot| slot.borrow().is_some()) {
+        return 0;
+    }
+    let Ok(manager) = AudioManager::new(Box::new(WebAudio { sample_rate: sample_rate as i32 })) else {
+        return 0;
+    };
+    let events = manager.sender();
+    AUDIO.with(|slot| *slot.borrow_mut() = Some(WebAudioState { _manager: manager, events, buffer: Vec::new() }));
+    send_audio(if GAME.with(|slot| slot.borrow().is_some()) {
+        AudioEvent::GameStarted
+    } else {
+        AudioEvent::MenuOpened
+    });
+    1
+}
+
+/// Mixes the next `count` mono samples
+///
+/// # Returns
+/// Pointer to the samples, valid until the next call (null before `paclike_audio_start`)
+#[no_mangle]
+pub extern "C" fn paclike_audio_fill(count: u32) -> *const f32 {
+    AUDIO.with(|audio| {
+        let mut audio = audio.borrow_mut();
+        let Some(state) = audio.as_mut() else {
+            return std::ptr::null();
+        };
+        state.buffer.resize(count as usize, 0.0);
+        MIXER.with(|mixer| {
+            if let Some(mixer) = mixer.borrow_mut().as_mut() {
+                mixer.fill(&mut state.buffer);
+            }
+        });
+        state.buffer.as_ptr()
+    })
+}
+
+/// Sends an event to the mixer, if audio is running
+fn send_audio(event: AudioEvent) {
+    AUDIO.with(|slot| {
+        if let Some(state) = slot.borrow().as_ref() {
+            let _ = state.events.send(event);
+        }
+    });
+}